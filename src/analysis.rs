@@ -0,0 +1,376 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use crate::ne::resource_table::type_name;
+use crate::ne::NeExecutable;
+use crate::util::json::json_string;
+
+/// Names which packers leave in the loader segment of self-loading modules
+const PACKER_SIGNATURES: &[&str] = &["Shrinker", "PKLITE", "WinLite", "WWPack"];
+
+///
+/// What the survey keeps of one parsed module
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileRecord {
+    pub path: String,
+    pub module_name: Option<String>,
+    pub linker_version: (u8, u8),
+    pub dialect: String,
+    /// `MODULE.target` of every imported procedure, once per file
+    pub imports: BTreeSet<String>,
+    /// Names of resource types present in the module
+    pub resource_types: BTreeSet<String>,
+    /// Packer by signature, `self-loading` for unknown loader, `None` for plain module
+    pub packer: Option<String>,
+}
+
+impl FileRecord {
+    pub fn new(path: &str, module: &NeExecutable) -> Self {
+        let header = &module.ne_header;
+        Self {
+            path: path.to_string(),
+            module_name: module.module_name(),
+            linker_version: (header.major_linker_version, header.minor_linker_version),
+            dialect: module.dialect().to_string(),
+            imports: module.imports().iter().map(ToString::to_string).collect(),
+            resource_types: module
+                .resource_table
+                .resource_types
+                .iter()
+                .map(|t| type_name(&t.id()))
+                .collect(),
+            packer: detect_packer(module),
+        }
+    }
+}
+
+///
+/// Self-loading modules are the ones packers produce:
+/// their loader in segment 1 unpacks the others.
+/// Loader is named by the first signature found in it.
+///
+pub fn detect_packer(module: &NeExecutable) -> Option<String> {
+    if !module.is_self_loading() {
+        return None;
+    }
+    let loader = module
+        .segment_entries
+        .first()
+        .and_then(|s| s.data.as_deref())
+        .unwrap_or_default();
+    let found = PACKER_SIGNATURES.iter().find(|signature| {
+        loader
+            .windows(signature.len())
+            .any(|w| w.eq_ignore_ascii_case(signature.as_bytes()))
+    });
+    Some(found.map_or("self-loading", |s| *s).to_string())
+}
+
+///
+/// Value and number of files having it, most frequent first;
+/// values of the same frequency are sorted by name
+///
+pub type Frequencies = Vec<(String, usize)>;
+
+fn frequencies<'a>(values: impl Iterator<Item = &'a str>) -> Frequencies {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+    let mut counts: Frequencies = counts
+        .into_iter()
+        .map(|(value, count)| (value.to_string(), count))
+        .collect();
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    counts
+}
+
+///
+/// Statistics over many modules, for surveys of Win16 archives:
+/// records of surveyed modules and files which failed to parse
+///
+#[derive(Debug, Clone, Default)]
+pub struct Corpus {
+    pub files: Vec<FileRecord>,
+    /// Path and error of every file which isn't a readable NE module
+    pub failures: Vec<(String, String)>,
+}
+
+impl Corpus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, path: &str, module: &NeExecutable) {
+        self.files.push(FileRecord::new(path, module));
+    }
+
+    pub fn add_failure(&mut self, path: &str, error: &io::Error) {
+        self.failures.push((path.to_string(), error.to_string()));
+    }
+
+    ///
+    /// Records `result` of parsing file at `path`
+    ///
+    pub fn add_result(&mut self, path: &str, result: &io::Result<NeExecutable>) {
+        match result {
+            Ok(module) => self.add(path, module),
+            Err(e) => self.add_failure(path, e),
+        }
+    }
+
+    pub fn linker_versions(&self) -> Frequencies {
+        let versions: Vec<String> = self
+            .files
+            .iter()
+            .map(|f| format!("{}.{}", f.linker_version.0, f.linker_version.1))
+            .collect();
+        frequencies(versions.iter().map(String::as_str))
+    }
+
+    pub fn dialects(&self) -> Frequencies {
+        frequencies(self.files.iter().map(|f| f.dialect.as_str()))
+    }
+
+    ///
+    /// `count` procedures imported by most files
+    ///
+    pub fn top_imports(&self, count: usize) -> Frequencies {
+        let mut imports = frequencies(
+            self.files
+                .iter()
+                .flat_map(|f| f.imports.iter().map(String::as_str)),
+        );
+        imports.truncate(count);
+        imports
+    }
+
+    pub fn resource_types(&self) -> Frequencies {
+        frequencies(
+            self.files
+                .iter()
+                .flat_map(|f| f.resource_types.iter().map(String::as_str)),
+        )
+    }
+
+    ///
+    /// Packers of self-loading modules; plain modules count as `none`
+    ///
+    pub fn packers(&self) -> Frequencies {
+        frequencies(
+            self.files
+                .iter()
+                .map(|f| f.packer.as_deref().unwrap_or("none")),
+        )
+    }
+
+    ///
+    /// One row per module: path, name, linker, dialect, packer,
+    /// number of imports and `;` separated resource types
+    ///
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("path,module,linker,dialect,packer,imports,resource_types\n");
+        for file in &self.files {
+            let row = [
+                file.path.clone(),
+                file.module_name.clone().unwrap_or_default(),
+                format!("{}.{}", file.linker_version.0, file.linker_version.1),
+                file.dialect.clone(),
+                file.packer.clone().unwrap_or_default(),
+                file.imports.len().to_string(),
+                file.resource_types
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(";"),
+            ];
+            let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    ///
+    /// Aggregates (`top_imports` of 100 procedures), records and failures
+    /// as one JSON object
+    ///
+    pub fn to_json(&self) -> String {
+        let frequencies_json = |counts: &Frequencies| {
+            let items: Vec<String> = counts
+                .iter()
+                .map(|(value, count)| format!("{}:{}", json_string(value), count))
+                .collect();
+            format!("{{{}}}", items.join(","))
+        };
+        let strings_json = |values: &BTreeSet<String>| {
+            let items: Vec<String> = values.iter().map(|v| json_string(v)).collect();
+            format!("[{}]", items.join(","))
+        };
+        let files: Vec<String> = self
+            .files
+            .iter()
+            .map(|f| {
+                format!(
+                    "{{\"path\":{},\"module\":{},\"linker\":\"{}.{}\",\"dialect\":{},\
+                     \"packer\":{},\"imports\":{},\"resource_types\":{}}}",
+                    json_string(&f.path),
+                    f.module_name
+                        .as_deref()
+                        .map_or("null".to_string(), json_string),
+                    f.linker_version.0,
+                    f.linker_version.1,
+                    json_string(&f.dialect),
+                    f.packer.as_deref().map_or("null".to_string(), json_string),
+                    strings_json(&f.imports),
+                    strings_json(&f.resource_types),
+                )
+            })
+            .collect();
+        let failures: Vec<String> = self
+            .failures
+            .iter()
+            .map(|(path, error)| {
+                format!(
+                    "{{\"path\":{},\"error\":{}}}",
+                    json_string(path),
+                    json_string(error)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"files_parsed\":{},\"files_failed\":{},\"linker_versions\":{},\
+             \"dialects\":{},\"top_imports\":{},\"resource_types\":{},\"packers\":{},\
+             \"files\":[{}],\"failures\":[{}]}}\n",
+            self.files.len(),
+            self.failures.len(),
+            frequencies_json(&self.linker_versions()),
+            frequencies_json(&self.dialects()),
+            frequencies_json(&self.top_imports(100)),
+            frequencies_json(&self.resource_types()),
+            frequencies_json(&self.packers()),
+            files.join(","),
+            failures.join(","),
+        )
+    }
+}
+
+impl fmt::Display for Corpus {
+    ///
+    /// `{}` gives numbers of files, `{:#}` also every distribution
+    /// (20 most imported procedures)
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} module(s), {} failed to parse",
+            self.files.len(),
+            self.failures.len()
+        )?;
+        if f.alternate() {
+            let sections = [
+                ("Linker versions", self.linker_versions()),
+                ("Dialects", self.dialects()),
+                ("Most imported procedures", self.top_imports(20)),
+                ("Resource types", self.resource_types()),
+                ("Packers", self.packers()),
+            ];
+            for (title, counts) in sections {
+                write!(f, "\n{}:", title)?;
+                for (value, count) in counts {
+                    write!(f, "\n\t{:>6}  {}", count, value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// Machine readable formats of `Corpus`
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown survey format `{}`", s)),
+        }
+    }
+}
+
+impl Corpus {
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Csv => self.to_csv(),
+            Format::Json => self.to_json(),
+        }
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(path: &str, linker: (u8, u8), imports: &[&str], packer: Option<&str>) -> FileRecord {
+        FileRecord {
+            path: path.to_string(),
+            module_name: None,
+            linker_version: linker,
+            dialect: "Windows 3.1".to_string(),
+            imports: imports.iter().map(|s| s.to_string()).collect(),
+            resource_types: ["ICON".to_string()].into_iter().collect(),
+            packer: packer.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn aggregates_files() {
+        let corpus = Corpus {
+            files: vec![
+                record("a.exe", (5, 10), &["KERNEL.@1", "USER.@5"], None),
+                record("b.exe", (5, 10), &["USER.@5"], Some("Shrinker")),
+                record("c,d.exe", (4, 0), &["USER.@5"], None),
+            ],
+            failures: vec![("e.exe".to_string(), "bad \"magic\"".to_string())],
+        };
+        assert_eq!(
+            corpus.linker_versions(),
+            [("5.10".to_string(), 2), ("4.0".to_string(), 1)]
+        );
+        assert_eq!(corpus.top_imports(1), [("USER.@5".to_string(), 3)]);
+        assert_eq!(
+            frequencies(["b", "a", "c", "a", "b"].into_iter()),
+            [
+                ("a".to_string(), 2),
+                ("b".to_string(), 2),
+                ("c".to_string(), 1)
+            ]
+        );
+        assert_eq!(
+            corpus.packers(),
+            [("none".to_string(), 2), ("Shrinker".to_string(), 1)]
+        );
+        assert!(corpus.to_csv().contains("\n\"c,d.exe\",,4.0,"));
+        assert!(corpus.to_json().contains("\"error\":\"bad \\\"magic\\\"\""));
+    }
+}
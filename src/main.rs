@@ -1,50 +1,1634 @@
-use std::fs::File;
-use std::io::{self, BufReader, Cursor, Read};
-use std::path::PathBuf;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Cursor};
+use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
+pub mod analysis;
 pub mod mz;
 pub mod ne;
+pub mod prelude;
 pub mod util;
 pub mod x86;
 
+#[cfg(feature = "alloc-metrics")]
+#[global_allocator]
+static ALLOCATOR: util::alloc_count::CountingAllocator = util::alloc_count::CountingAllocator;
+
+use analysis::Corpus;
+use ne::addr::Seg16;
+use ne::annotations::ScriptKind;
+use ne::editor::NeEditor;
+use ne::font::{charset_name, NeFont, RasterFont};
+use ne::footprint::InstallFootprint;
+use ne::findings::{Finding, FindingFilter, Severity};
+use ne::fuzz::parse_any_bytes;
+use ne::loaded::{parse_offset, LoadedImageMap, SegmentBase, FILE_SEGMENT_RECORD};
+use ne::open::Win16Ne;
+use ne::options::ParseOptions;
+use ne::owned::NeOwned;
+use ne::packing::detect_packing;
+use ne::repack::{RepackOptions, Repacked};
+use ne::report::Format;
+use ne::resolver::ImportResolver;
+use ne::resource_hash::{HashAlgorithm, ResourceHash};
+use ne::resource_search::{find_resources, ResourceQuery};
+use ne::resource_table::{type_id, type_name, ResourceId};
+use ne::sink::{DirSink, ExtractSink, ZipSink};
+use ne::slack::Slack;
+use ne::source::SourceName;
+use ne::strings::StringsOptions;
+use ne::symbols::{MapFile, OrdinalDatabase, SymbolProviders};
+use ne::template::TemplateKind;
+use ne::version_info::FileVersion;
+use ne::win32_res::{ResExportOptions, LANG_EN_US};
 use ne::NeExecutable;
+use util::chunked::ChunkedReader;
+use util::codepage::Codepage;
 
 #[derive(Debug, Clone, Parser)]
+#[clap(args_conflicts_with_subcommands = true)]
 pub struct Opts {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     #[clap(short, long)]
     disassemble: bool,
 
     #[clap(long)]
     data: bool,
 
+    /// `.MAP` file of the module naming its code (may be repeated)
+    #[clap(long)]
+    map: Vec<PathBuf>,
+
     #[clap(name = "FILE", value_parser)]
     files: Vec<PathBuf>,
 }
 
-fn main() -> io::Result<()> {
-    env_logger::init();
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// List fonts of the module (`.FON` library) and optionally extract them
+    Fonts {
+        /// Directory to write `.fnt` files into
+        #[clap(short, long)]
+        extract: Option<PathBuf>,
 
-    let opts = Opts::parse();
+        /// Directory to write character grid pictures of raster fonts into
+        #[clap(short, long)]
+        atlas: Option<PathBuf>,
+
+        /// Write zip archives at EXTRACT and ATLAS instead of directories
+        #[clap(short, long)]
+        zip: bool,
+
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Print readable report of every structure of the module
+    Report {
+        /// Document format instead of plain text (markdown, html)
+        #[clap(short, long)]
+        format: Option<Format>,
+
+        /// Name of the module read from standard input as FILE `-`,
+        /// like `DISK1.ZIP!SETUP.EXE` of a member piped by `unzip -p`
+        #[clap(long)]
+        name: Option<String>,
+
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Print every field of the module in fixed, versioned form
+    /// for snapshot tests and comparing parses of crate versions
+    Canonical {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Generate a script annotating the module in Ghidra or IDA
+    Script {
+        /// Target tool: ghidra (Python) or idc
+        #[clap(short, long, default_value = "ghidra")]
+        kind: ScriptKind,
+
+        /// `.MAP` file of the module naming its code (may be repeated)
+        #[clap(long)]
+        map: Vec<PathBuf>,
+
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// Generate Kaitai Struct or 010 Editor template of the file layout
+    Template {
+        /// Template language: kaitai (`.ksy`) or bt (010 Editor)
+        #[clap(short, long, default_value = "kaitai")]
+        kind: TemplateKind,
+
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// Generate Wine `.spec` file from exports of 16-bit DLL
+    Spec {
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// Generate `.def` and NASM sources of a Win32 DLL with the same exports
+    Shim {
+        /// Path of the sources without extension (`.def` and `.asm` are added)
+        #[clap(short, long)]
+        output: PathBuf,
+
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// List entry points of the module with their names and flags
+    Exports {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Group imports by Windows API area (drawing, files, comm ports, sound, DDE)
+    Apis {
+        /// `.MAP` file naming procedures of imported modules (may be repeated)
+        #[clap(long)]
+        map: Vec<PathBuf>,
+
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Count relocations of every segment by kind and by imported module
+    Relocations {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Reorder segments and change their attributes keeping references consistent
+    Edit {
+        /// New order of segments by their old numbers: `3,1,2`
+        #[clap(long, value_delimiter = ',')]
+        order: Vec<usize>,
+
+        /// Segments to load at start
+        #[clap(long, value_delimiter = ',')]
+        preload: Vec<usize>,
+
+        /// Segments to load on first call
+        #[clap(long, value_delimiter = ',')]
+        load_on_call: Vec<usize>,
+
+        /// Segments to make moveable
+        #[clap(long, value_delimiter = ',')]
+        moveable: Vec<usize>,
+
+        /// Segments to make fixed
+        #[clap(long, value_delimiter = ',')]
+        fixed: Vec<usize>,
+
+        /// File to write the edited module into
+        #[clap(short, long)]
+        output: PathBuf,
+
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// Edit the version resource: file and product versions, `StringFileInfo` strings
+    SetVersion {
+        /// New file version of fixed info and `FileVersion` string: `3.10.0.103`
+        #[clap(long)]
+        file_version: Option<FileVersion>,
+
+        /// Increment part of file version (0 major, 1 minor, 2 release, 3 build),
+        /// zeroing the parts after it
+        #[clap(long, value_parser = clap::value_parser!(u8).range(0..4))]
+        bump: Option<u8>,
+
+        /// New product version of fixed info and `ProductVersion` string
+        #[clap(long)]
+        product_version: Option<FileVersion>,
+
+        /// Strings to set: `CompanyName=Acme`
+        #[clap(short, long)]
+        string: Vec<String>,
+
+        /// Strings to remove
+        #[clap(long)]
+        remove: Vec<String>,
+
+        /// File to write the edited module into
+        #[clap(short, long)]
+        output: PathBuf,
+
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// Rebuild the module with the smallest resource (and segment) alignment.
+    /// FILE may also be a directory written by `unpack`.
+    Repack {
+        /// Also shrink sectors of segment data
+        #[clap(short, long)]
+        segments: bool,
+
+        /// File to write the rebuilt module into
+        #[clap(short, long)]
+        output: PathBuf,
+
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// Write segments, resources, names and tables of the module into
+    /// a directory tree with `manifest.json`, to rebuild it by `repack`
+    Unpack {
+        /// Directory to write the tree into
+        #[clap(short, long)]
+        output: PathBuf,
+
+        /// Write a zip archive at OUTPUT instead of a directory
+        #[clap(short, long)]
+        zip: bool,
+
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// Convert resources of the module into a Win32 `.res` file
+    Res {
+        /// Code page of resource strings (1251, 1252, 437, 866), guessed if not set
+        #[clap(short, long)]
+        codepage: Option<Codepage>,
+
+        /// Language ID of the resources (decimal, 1033 is English US)
+        #[clap(short, long, default_value_t = LANG_EN_US)]
+        language: u16,
+
+        /// File to write the resources into
+        #[clap(short, long)]
+        output: PathBuf,
+
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// Add resources of a Win32 `.res` file to the module (or replace them)
+    ImportRes {
+        /// Code page of resource strings (1251, 1252, 437, 866), guessed if not set
+        #[clap(short, long)]
+        codepage: Option<Codepage>,
+
+        /// File to write the edited module into
+        #[clap(short, long)]
+        output: PathBuf,
+
+        #[clap(name = "RES", value_parser)]
+        res: PathBuf,
+
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// Write data of every resource into files, unpacking SZDD and KWAJ
+    /// packed ones when built with feature `decompress`
+    Extract {
+        /// Directory to write `resources/<type>/<id>.bin` files into
+        #[clap(short, long)]
+        output: PathBuf,
+
+        /// Write a zip archive at OUTPUT instead of a directory
+        #[clap(short, long)]
+        zip: bool,
+
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// List resources of the module
+    Resources {
+        /// Show strings, menu trees, dialog controls and image dimensions
+        #[clap(short, long)]
+        preview: bool,
+
+        /// Code page of resource strings (1251, 1252, 437, 866), guessed if not set
+        #[clap(short, long)]
+        codepage: Option<Codepage>,
+
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Guess code page of resource strings, with the evidence
+    Codepage {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Print content hashes of resources, or find resources by hash
+    Hashes {
+        /// Hash function (sha256, fnv1a64, crc32)
+        #[clap(short, long, default_value = "sha256")]
+        algorithm: HashAlgorithm,
+
+        /// Print only resources with this hash (`algorithm:hex`)
+        #[clap(short, long)]
+        find: Option<ResourceHash>,
+
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Find modules of directories whose resources match all the conditions given
+    FindResource {
+        /// Resource type: predefined name (`STRING`), `#number` or custom name
+        #[clap(short = 't', long = "type")]
+        type_name: Option<String>,
+        /// Resource ID pattern; `*` and `?` are wildcards
+        #[clap(short, long)]
+        id: Option<String>,
+        /// Minimum size of resource data in bytes
+        #[clap(short, long)]
+        min_size: Option<u64>,
+        /// Hash of resource content (`algorithm:hex`)
+        #[clap(long)]
+        hash: Option<ResourceHash>,
+        /// Text resource data contains (case-insensitive)
+        #[clap(short, long)]
+        contains: Option<String>,
+
+        #[clap(name = "DIR", value_parser)]
+        paths: Vec<PathBuf>,
+    },
+    /// Find referenced modules recursively and check that they export what is imported
+    Depends {
+        /// Directory to search modules in (in order given, before directory of FILE)
+        #[clap(short, long = "path")]
+        paths: Vec<PathBuf>,
+
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// Map a directory of install disks to its modules: expand compressed files,
+    /// find DLL roots, leaf apps and modules missing from the disks
+    Footprint {
+        #[clap(name = "DIR", value_parser)]
+        dirs: Vec<PathBuf>,
+    },
+    /// Print module and modules it depends on as CycloneDX-like JSON
+    Sbom {
+        /// Directory to search modules in (in order given, before directory of FILE)
+        #[clap(short, long = "path")]
+        paths: Vec<PathBuf>,
+
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// List structures inside and outside of the gangload (fast-load) area
+    Gangload {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Show file ranges of headers and tables
+    Tables {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Show file ranges of segment data, relocations and padding after them
+    Layout {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Check that every structure given by headers lies inside the file
+    Validate {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Run every parser over files as fuzzing harnesses do,
+    /// to reproduce inputs found by fuzzers
+    Fuzz {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Report parse warnings and suspicious structures of the module
+    /// with their codes and severities
+    Lint {
+        /// Code of findings not to report, like `NE-L005` (may be repeated)
+        #[clap(long)]
+        allow: Vec<String>,
+
+        /// Report only findings of this severity or above (info, warn, error)
+        #[clap(long)]
+        min_severity: Option<Severity>,
+
+        /// Exit with status 2 if a reported finding has this severity or above
+        #[clap(long)]
+        deny: Option<Severity>,
+
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Print severity and explanation of finding codes, or of every code
+    Explain {
+        #[clap(name = "CODE")]
+        codes: Vec<String>,
+    },
+    /// Estimate minimal Windows version from imports, resources and flags
+    MinVersion {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Guess compiler and linker which built the module
+    Toolchain {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Classify driver by its exports and check the standard driver exports
+    Driver {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// List function starts of a code segment reachable from the entry points
+    Functions {
+        /// Segment number (from 1); segment of the entry point by default
+        #[clap(short, long)]
+        segment: Option<u16>,
+
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Write assembler-style listing of code segments: offsets, bytes, instructions, relocations
+    Listing {
+        /// Segment number (from 1); every segment by default
+        #[clap(short, long)]
+        segment: Option<u16>,
+
+        /// `.MAP` file of the module naming its code (may be repeated)
+        #[clap(long)]
+        map: Vec<PathBuf>,
+
+        /// File to write the listing into instead of standard output
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+    },
+    /// Estimate stack depth by the call graph and check stack and heap sizes against it
+    Stack {
+        /// `.MAP` file of the module naming its code (may be repeated)
+        #[clap(long)]
+        map: Vec<PathBuf>,
+
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Read modules measuring parsing stages, reads and allocations
+    Metrics {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Find INITTASK, WAITEVENT and INITAPP calls of startup code and the WinMain they lead to
+    Startup {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Show Visual Basic program or custom control (`.VBX`) structures
+    Vb {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Find printable strings in segments and overlay
+    Strings {
+        /// Minimal length of string
+        #[clap(short = 'n', long, default_value_t = 4)]
+        min_length: usize,
+
+        /// Code page for characters above 0x7F (1251, 1252, 437, 866)
+        #[clap(short, long)]
+        codepage: Option<Codepage>,
+
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Gather statistics of many modules: linkers, imports, resources, packers
+    Survey {
+        /// Per-file records and statistics instead of summary (csv, json)
+        #[clap(short, long)]
+        format: Option<analysis::Format>,
+
+        /// File to write the records into instead of standard output
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Tell which structure and field own bytes at file offsets,
+    /// or print the whole coverage map of the file without offsets
+    Identify {
+        #[clap(name = "FILE", value_parser)]
+        file: PathBuf,
+
+        /// Decimal or 0x-prefixed hexadecimal file offset
+        #[clap(name = "OFFSET", value_parser = parse_offset)]
+        offsets: Vec<u64>,
+    },
+    /// Report bytes between structures: padding, slack between tables, hidden strings
+    Slack {
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Reconstruct module from a memory dump with segments where the loader put them
+    Loaded {
+        /// Offset of the NE header (module database) in the dump
+        #[clap(long, value_parser = parse_offset)]
+        header: u64,
+
+        /// Offset of a loaded segment in the dump as NUMBER=OFFSET (may be repeated)
+        #[clap(short, long)]
+        segment: Vec<SegmentBase>,
+
+        /// Segment table of the dump has 8-byte records, as on file
+        #[clap(long)]
+        file_records: bool,
+
+        #[clap(short, long)]
+        disassemble: bool,
+
+        #[clap(name = "DUMP", value_parser)]
+        dump: PathBuf,
+    },
+    /// Compare structures of two modules (e.g. original and patched one)
+    Diff {
+        #[clap(name = "OLD", value_parser)]
+        old: PathBuf,
+
+        #[clap(name = "NEW", value_parser)]
+        new: PathBuf,
+    },
+    /// Expand files packed by `COMPRESS.EXE` (`*.EX_`, `*.DL_`) as `EXPAND.EXE -r` does,
+    /// restoring their names
+    #[cfg(feature = "decompress")]
+    Expand {
+        /// Directory to write expanded files into, instead of the one of every file
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+    /// Decode bitmaps, icons and cursors of the module into PNG files
+    #[cfg(feature = "image")]
+    Images {
+        /// Directory to write `.png` files into
+        #[clap(short, long)]
+        extract: PathBuf,
+
+        /// Write a zip archive at EXTRACT instead of a directory
+        #[clap(short, long)]
+        zip: bool,
+
+        #[clap(name = "FILE", value_parser)]
+        files: Vec<PathBuf>,
+    },
+}
+
+fn read_executable(file: &Path) -> io::Result<NeExecutable> {
+    Win16Ne::open(file)
+}
+
+///
+/// Reads only header and resources, for commands which look at nothing else
+///
+fn read_resources(file: &Path) -> io::Result<NeExecutable> {
+    Win16Ne::options().resources_only(true).open(file)
+}
 
-    if opts.files.is_empty() {
+fn check_files(files: &[PathBuf]) {
+    if files.is_empty() {
         eprintln!("Error: no files specified");
         std::process::exit(1);
     }
+}
+
+///
+/// Built-in symbol providers of the module after the given `.MAP` files
+///
+fn symbol_providers(ne: &NeExecutable, maps: &[PathBuf]) -> io::Result<SymbolProviders> {
+    let mut providers = SymbolProviders::for_module(ne)?;
+    for map in maps.iter().rev() {
+        providers.push_front(MapFile::parse(&fs::read_to_string(map)?));
+    }
+    Ok(providers)
+}
+
+fn apis(files: &[PathBuf], maps: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        let parsed = read_executable(file)?;
+        let symbols = symbol_providers(&parsed, maps)?;
+        println!("{}: {:#}", file.display(), parsed.api_usage(&symbols));
+    }
+    Ok(())
+}
+
+fn loaded(dump: &Path, map: &LoadedImageMap, disassemble: bool) -> io::Result<()> {
+    let parsed = NeExecutable::parse_loaded_image(&fs::read(dump)?, map)?;
+    let symbols = symbol_providers(&parsed, &[])?;
+    parsed.describe(false, disassemble, &symbols);
+    Ok(())
+}
+
+fn describe(
+    files: &[PathBuf],
+    show_data: bool,
+    disassemble: bool,
+    maps: &[PathBuf],
+) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        let parsed = read_executable(file)?;
+        let symbols = symbol_providers(&parsed, maps)?;
+        parsed.describe(show_data, disassemble, &symbols);
+    }
+    Ok(())
+}
+
+///
+/// Files of extracting commands: a directory or a zip archive at `path`
+///
+enum OutputSink {
+    Dir(DirSink),
+    Zip(ZipSink<io::BufWriter<File>>),
+}
+
+impl OutputSink {
+    fn create(path: &Path, zip: bool) -> io::Result<Self> {
+        Ok(if zip {
+            OutputSink::Zip(ZipSink::new(io::BufWriter::new(File::create(path)?)))
+        } else {
+            fs::create_dir_all(path)?;
+            OutputSink::Dir(DirSink::new(path))
+        })
+    }
+
+    fn sink(&mut self) -> &mut dyn ExtractSink {
+        match self {
+            OutputSink::Dir(sink) => sink,
+            OutputSink::Zip(sink) => sink,
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            OutputSink::Dir(_) => Ok(()),
+            OutputSink::Zip(sink) => sink.finish().map(drop),
+        }
+    }
+}
+
+fn fonts(
+    files: &[PathBuf],
+    extract: Option<&Path>,
+    atlas: Option<&Path>,
+    zip: bool,
+) -> io::Result<()> {
+    check_files(files);
+    let mut extract = extract.map(|path| OutputSink::create(path, zip)).transpose()?;
+    let mut atlas = atlas.map(|path| OutputSink::create(path, zip)).transpose()?;
+    for file in files {
+        let parsed = read_resources(file)?;
+        println!("{}:", file.display());
+        let fonts = match &mut extract {
+            Some(extract) => parsed.extract_fonts_to(extract.sink())?,
+            None => parsed.extract_fonts()?,
+        };
+        for font in fonts {
+            println!(
+                "\t{} \"{}\" {}pt {}x{} charset {} ({})",
+                font.file_name(),
+                font.face_name,
+                font.point_size(),
+                font.header.pix_width,
+                font.header.pix_height,
+                font.charset(),
+                charset_name(font.charset())
+            );
+            if let Some(atlas) = &mut atlas {
+                write_atlas(atlas.sink(), &font)?;
+            }
+        }
+    }
+    for sink in [extract, atlas].into_iter().flatten() {
+        sink.finish()?;
+    }
+    Ok(())
+}
+
+fn exports(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        let parsed = read_executable(file)?;
+        let thunks = parsed.import_thunks(&OrdinalDatabase);
+        println!("{}:", file.display());
+        for export in parsed.exports() {
+            let mut flags = Vec::new();
+            if export.is_exported() {
+                flags.push("EXPORTED");
+            }
+            if export.is_shared_data() {
+                flags.push("SHARED_DATA");
+            }
+            println!(
+                "\t@{:<4} {:04X}:{:04X} {:9} {:20} {} param words: {}{}",
+                export.ordinal,
+                export.segment,
+                export.offset,
+                if export.is_movable { ".MOVEABLE" } else { ".FIXED" },
+                flags.join(" | "),
+                export.name.as_deref().unwrap_or("<no name>"),
+                export.parameter_words(),
+                if export.is_resident_name { " (resident)" } else { "" }
+            );
+            if let Some(demangled) = export.demangled() {
+                println!("\t\t{}", demangled);
+            }
+            let thunk = thunks
+                .iter()
+                .find(|(s, o, _)| s.0 == export.segment as u16 && *o == export.offset);
+            if let Some((_, _, import)) = thunk {
+                println!("\t\t-> {}", import);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn report(files: &[PathBuf], format: Option<Format>, name: Option<&str>) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        if file.as_os_str() == "-" {
+            let named = Win16Ne::options().read_named(
+                &mut io::stdin().lock(),
+                SourceName::parse(name.unwrap_or("<stdin>")),
+            )?;
+            match format {
+                Some(format) => print!("{}", named.render_report(format)),
+                None => {
+                    println!("{}:", named.source_name);
+                    println!("{:#}", named.module);
+                }
+            }
+            continue;
+        }
+        let parsed = read_executable(file)?;
+        match format {
+            Some(format) => print!("{}", parsed.render_report(format)),
+            None => {
+                println!("{}:", file.display());
+                println!("{:#}", parsed);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn canonical(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        print!("{}", read_executable(file)?.canonical_dump());
+    }
+    Ok(())
+}
+
+fn script(file: &Path, kind: ScriptKind, maps: &[PathBuf]) -> io::Result<()> {
+    let parsed = read_executable(file)?;
+    let symbols = symbol_providers(&parsed, maps)?;
+    print!("{}", parsed.annotation_script(kind, &symbols));
+    Ok(())
+}
+
+fn template(file: &Path, kind: TemplateKind) -> io::Result<()> {
+    let parsed = read_executable(file)?;
+    print!("{}", parsed.layout_template(kind));
+    Ok(())
+}
+
+fn spec(file: &Path) -> io::Result<()> {
+    let parsed = read_executable(file)?;
+    print!("{}", parsed.wine_spec());
+    Ok(())
+}
+
+fn shim(file: &Path, output: &Path) -> io::Result<()> {
+    let shim = read_executable(file)?.win32_shim();
+    for (extension, text) in [("def", &shim.def), ("asm", &shim.asm)] {
+        let path = output.with_extension(extension);
+        fs::write(&path, text)?;
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+fn relocations(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        let ne = read_executable(file)?;
+        println!("{}:", file.display());
+        for (i, stats) in ne.segment_relocation_stats().iter().enumerate() {
+            println!("\t#{:<3} {}", i + 1, stats);
+        }
+        println!("Total: {:#}", ne.relocation_stats());
+    }
+    Ok(())
+}
+
+///
+/// Attributes are changed before reordering, so segments are given by old numbers
+///
+fn edit(command: &Command) -> io::Result<()> {
+    let Command::Edit {
+        order,
+        preload,
+        load_on_call,
+        moveable,
+        fixed,
+        output,
+        file,
+    } = command
+    else {
+        unreachable!()
+    };
+    let mut editor = NeEditor::from_owned(NeOwned::new(fs::read(file)?)?);
+    for &segment in preload {
+        editor.set_preload(segment, true)?;
+    }
+    for &segment in load_on_call {
+        editor.set_preload(segment, false)?;
+    }
+    for &segment in moveable {
+        editor.set_moveable(segment, true)?;
+    }
+    for &segment in fixed {
+        editor.set_moveable(segment, false)?;
+    }
+    if !order.is_empty() {
+        editor.reorder_segments(order)?;
+    }
+    let saved = editor.save()?;
+    fs::write(output, &saved.data)?;
+    println!("{}: {}", output.display(), saved);
+    Ok(())
+}
+
+fn set_version(command: &Command) -> io::Result<()> {
+    let Command::SetVersion {
+        file_version,
+        bump,
+        product_version,
+        string,
+        remove,
+        output,
+        file,
+    } = command
+    else {
+        unreachable!()
+    };
+    let mut editor = NeEditor::from_owned(NeOwned::new(fs::read(file)?)?);
+    if let Some(part) = bump {
+        let current = editor
+            .module()
+            .version_info()
+            .and_then(|info| info.file_version)
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "module has no file version")
+            })?;
+        editor.set_file_version(current.bumped(*part as usize))?;
+    }
+    if let Some(version) = file_version {
+        editor.set_file_version(*version)?;
+    }
+    if let Some(version) = product_version {
+        editor.set_product_version(*version)?;
+    }
+    for assignment in string {
+        let Some((key, value)) = assignment.split_once('=') else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("`{}` is not KEY=VALUE", assignment),
+            ));
+        };
+        editor.set_version_string(key, value)?;
+    }
+    for key in remove {
+        editor.edit_version(|root| {
+            root.remove_string(key.as_bytes());
+            Ok(())
+        })?;
+    }
+    let saved = editor.save()?;
+    fs::write(output, &saved.data)?;
+    println!("{}: {}", output.display(), saved);
+    let edited = NeExecutable::read(&mut Cursor::new(&saved.data))?;
+    if let Some(info) = edited.version_info() {
+        for (name, version) in [
+            ("file version", info.file_version),
+            ("product version", info.product_version),
+        ] {
+            if let Some(version) = version {
+                println!("\t{} {}", name, version);
+            }
+        }
+        for (key, value) in &info.strings {
+            println!("\t{} = {:?}", key, value);
+        }
+    }
+    Ok(())
+}
+
+fn repack(file: &Path, output: &Path, options: &RepackOptions) -> io::Result<()> {
+    let repacked = if file.is_dir() {
+        // files of the tree may be edited: it tells no original length
+        Repacked {
+            original_length: None,
+            ..NeExecutable::read_unpacked(file)?.repack(options)?
+        }
+    } else {
+        read_executable(file)?.repack(options)?
+    };
+    fs::write(output, &repacked.data)?;
+    println!("{}: {}", output.display(), repacked);
+    Ok(())
+}
+
+fn unpack(file: &Path, output: &Path, zip: bool) -> io::Result<()> {
+    let parsed = read_executable(file)?;
+    let mut sink = OutputSink::create(output, zip)?;
+    let unpacked = parsed.unpack_to(sink.sink())?;
+    sink.finish()?;
+    println!("{}: {}", output.display(), unpacked);
+    Ok(())
+}
+
+fn res(file: &Path, output: &Path, codepage: Option<Codepage>, language: u16) -> io::Result<()> {
+    let parsed = read_executable(file)?;
+    let export = parsed.to_win32_res(&ResExportOptions {
+        codepage: codepage.unwrap_or(parsed.codepage),
+        language,
+    });
+    fs::write(output, &export.data)?;
+    println!("{}: {:#}", output.display(), export);
+    Ok(())
+}
+
+fn import_res(
+    file: &Path,
+    res: &Path,
+    output: &Path,
+    codepage: Option<Codepage>,
+) -> io::Result<()> {
+    let entries = ne::win32_res::read_res_file(&fs::read(res)?)?;
+    let owned = NeOwned::new(fs::read(file)?)?;
+    let codepage = codepage.unwrap_or(owned.module().codepage);
+    let mut editor = NeEditor::from_owned(owned);
+    let import = editor.import_res(&entries, codepage);
+    let saved = editor.save()?;
+    fs::write(output, &saved.data)?;
+    println!("{}: {}", output.display(), saved);
+    println!("{:#}", import);
+    Ok(())
+}
+
+fn extract(file: &Path, output: &Path, zip: bool) -> io::Result<()> {
+    let parsed = read_resources(file)?;
+    let mut sink = OutputSink::create(output, zip)?;
+    let resources = parsed.extract_resources_to(sink.sink())?;
+    sink.finish()?;
+    for extracted in resources {
+        println!("{}", extracted);
+        if extracted.packing.is_some_and(|p| p.is_supported()) && !extracted.unpacked {
+            println!("\tleft packed: unpacking needs feature `decompress`");
+        }
+    }
+    Ok(())
+}
+
+fn resources(files: &[PathBuf], preview: bool, codepage: Option<Codepage>) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        let parsed = read_resources(file)?;
+        let codepage = codepage.unwrap_or(parsed.codepage);
+        let table = &parsed.resource_table;
+        let shift_count = table.header.alignment_shift_count;
+        println!("{}:", file.display());
+        for resource_type in &table.resource_types {
+            let type_id = resource_type.id();
+            for resource in &resource_type.resources {
+                let packing = resource.data.as_deref().and_then(detect_packing);
+                println!(
+                    "\t{:<12} {:<12} 0x{:X} bytes{}",
+                    type_name(&type_id),
+                    resource.id(),
+                    resource.data_length(shift_count),
+                    packing.map_or(String::new(), |p| format!(", {}", p))
+                );
+                if !preview {
+                    continue;
+                }
+                let (ResourceId::Int(int_type), Some(data)) =
+                    (&type_id, resource.data.as_deref())
+                else {
+                    continue;
+                };
+                match ne::resource_preview::preview(*int_type, &resource.id(), data, codepage) {
+                    Ok(Some(lines)) => {
+                        for line in lines {
+                            println!("\t\t{}", line);
+                        }
+                    }
+                    Ok(None) => (),
+                    Err(e) => println!("\t\tpreview failed: {}", e),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn hashes(
+    files: &[PathBuf],
+    algorithm: HashAlgorithm,
+    find: Option<&ResourceHash>,
+) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        let parsed = read_resources(file)?;
+        let table = &parsed.resource_table;
+        match find {
+            Some(hash) => {
+                if let Some((t, resource)) = table.find_resource_by_hash(hash) {
+                    println!(
+                        "{}: {} {}",
+                        file.display(),
+                        type_name(&t.id()),
+                        resource.id()
+                    );
+                }
+            }
+            None => {
+                println!("{}:", file.display());
+                for (type_id, id, hash) in table.resource_hashes(algorithm) {
+                    println!("\t{:<12} {:<12} {}", type_name(&type_id), id, hash);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn find_resource(paths: &[PathBuf], query: &ResourceQuery) -> io::Result<()> {
+    check_files(paths);
+    let search = find_resources(paths, query)?;
+    println!("{:#}", search);
+    Ok(())
+}
+
+fn depends(file: &Path, paths: &[PathBuf]) -> io::Result<()> {
+    let dependencies = ImportResolver::new(paths.to_vec()).resolve(file)?;
+    println!("{:#}", dependencies);
+    Ok(())
+}
+
+fn footprint(dirs: &[PathBuf]) -> io::Result<()> {
+    check_files(dirs);
+    for dir in dirs {
+        println!("{}: {:#}", dir.display(), InstallFootprint::scan(dir)?);
+    }
+    Ok(())
+}
+
+fn sbom(file: &Path, paths: &[PathBuf]) -> io::Result<()> {
+    let sbom = ImportResolver::new(paths.to_vec()).sbom(file)?;
+    println!("{}", sbom.to_json());
+    Ok(())
+}
+
+fn layout(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        println!("{}:", file.display());
+        for layout in read_executable(file)?.segment_layouts() {
+            println!("{:#}", layout);
+        }
+    }
+    Ok(())
+}
+
+fn fuzz(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        match parse_any_bytes(&fs::read(file)?) {
+            Ok(summary) => println!("{}: {}", file.display(), summary),
+            Err(e) => println!("{}: refused: {}", file.display(), e),
+        }
+    }
+    Ok(())
+}
+
+fn identify(file: &Path, offsets: &[u64]) -> io::Result<()> {
+    let owned = NeOwned::new(fs::read(file)?)?;
+    if offsets.is_empty() {
+        for covered in owned.coverage_map() {
+            println!("{}", covered);
+        }
+    }
+    for &offset in offsets {
+        match owned.identify_offset(offset) {
+            Some(owner) => println!("{:#08X}: {}", offset, owner),
+            None => println!("{:#08X}: not owned by any structure", offset),
+        }
+    }
+    Ok(())
+}
+
+fn slack(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        let owned = NeOwned::new(fs::read(file)?)?;
+        let slack = owned.slack();
+        let bytes: u64 = slack.iter().map(Slack::len).sum();
+        println!("{}: 0x{:X} bytes of slack", file.display(), bytes);
+        for slack in slack {
+            println!("{:#}", slack);
+        }
+    }
+    Ok(())
+}
+
+fn validate(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        let mut f = BufReader::new(File::open(file)?);
+        let diagnostics = ne::validate::validate_layout(&mut f)?;
+        println!("{}: {} issue(s)", file.display(), diagnostics.len());
+        for diagnostic in diagnostics {
+            println!("\t{}", diagnostic);
+        }
+    }
+    Ok(())
+}
+
+fn lint(files: &[PathBuf], filter: &FindingFilter, deny: Option<Severity>) -> io::Result<()> {
+    check_files(files);
+    let mut denied = false;
+    for file in files {
+        let parsed = read_executable(file)?;
+        let lints = parsed.lint();
+        let findings: Vec<&dyn Finding> = parsed
+            .warnings
+            .iter()
+            .map(|w| w as &dyn Finding)
+            .chain(lints.iter().map(|l| l as &dyn Finding))
+            .filter(|finding| filter.allows(*finding))
+            .collect();
+        println!("{}: {} issue(s)", file.display(), findings.len());
+        for finding in findings {
+            println!("\t{}", finding.tagged());
+            denied |= deny.is_some_and(|deny| finding.severity() >= deny);
+        }
+    }
+    if denied {
+        std::process::exit(2);
+    }
+    Ok(())
+}
+
+fn explain(codes: &[String]) -> io::Result<()> {
+    let infos = if codes.is_empty() {
+        ne::findings::FINDINGS.iter().collect()
+    } else {
+        codes
+            .iter()
+            .map(|code| {
+                ne::findings::explain(code).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("unknown finding code `{}`", code),
+                    )
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?
+    };
+    for info in infos {
+        println!("{} {} {}", info.code, info.severity, info.name);
+        println!("\t{}", info.explanation);
+    }
+    Ok(())
+}
+
+fn min_version(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        match read_executable(file)?.estimate_min_version() {
+            Some(estimate) => println!("{}: {:#}", file.display(), estimate),
+            None => println!("{}: not a Windows module", file.display()),
+        }
+    }
+    Ok(())
+}
+
+fn codepage(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        println!("{}: {:#}", file.display(), read_resources(file)?.guess_codepage());
+    }
+    Ok(())
+}
+
+fn gangload(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        match read_executable(file)?.gangload_members() {
+            Some(area) => println!("{}: {:#}", file.display(), area),
+            None => println!("{}: no gangload area", file.display()),
+        }
+    }
+    Ok(())
+}
+
+fn tables(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        println!("{}:", file.display());
+        for range in read_executable(file)?.table_ranges() {
+            println!("\t{}", range);
+        }
+    }
+    Ok(())
+}
 
-    for file in &opts.files {
-        let data = {
-            let mut f = BufReader::new(File::open(file)?);
-            let mut data = Vec::new();
-            f.read_to_end(&mut data)?;
-            data
+fn toolchain(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        println!("{}: {:#}", file.display(), read_executable(file)?.toolchain_guess());
+    }
+    Ok(())
+}
+
+fn driver(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        match read_executable(file)?.driver_conventions() {
+            Some(report) => println!("{}: {:#}", file.display(), report),
+            None => println!("{}: not a driver", file.display()),
+        }
+    }
+    Ok(())
+}
+
+fn functions(files: &[PathBuf], segment: Option<u16>) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        let parsed = read_executable(file)?;
+        let Some(segment) = segment.map(Seg16).or_else(|| parsed.entry_segment()) else {
+            println!("{}: no entry point, give --segment", file.display());
+            continue;
         };
+        println!("{}: {:#}", file.display(), parsed.discover_functions(segment)?);
+    }
+    Ok(())
+}
+
+fn listing(
+    file: &Path,
+    segment: Option<u16>,
+    maps: &[PathBuf],
+    output: Option<&Path>,
+) -> io::Result<()> {
+    let parsed = read_executable(file)?;
+    let symbols = symbol_providers(&parsed, maps)?;
+    let listing = match segment {
+        Some(segment) => parsed.segment_listing(Seg16(segment), &symbols)?,
+        None => parsed.listing(&symbols)?,
+    };
+    match output {
+        Some(output) => fs::write(output, listing),
+        None => {
+            print!("{}", listing);
+            Ok(())
+        }
+    }
+}
+
+fn stack(files: &[PathBuf], maps: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        let parsed = read_executable(file)?;
+        let symbols = symbol_providers(&parsed, maps)?;
+        println!("{}: {:#}", file.display(), parsed.memory_adequacy(&symbols));
+    }
+    Ok(())
+}
 
-        let mut cursor = Cursor::new(data.as_slice());
+fn metrics(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        let mut reader = ChunkedReader::new(File::open(file)?);
+        let (_, metrics) = NeExecutable::read_with_metrics(&mut reader, &ParseOptions::default())?;
+        println!("{}: {:#}", file.display(), metrics);
+        println!("  {:<24} {}", "file", reader.stats());
+    }
+    Ok(())
+}
+
+fn startup(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        match read_executable(file)?.startup()? {
+            Some(startup) => println!("{}: {:#}", file.display(), startup),
+            None => println!("{}: no entry point", file.display()),
+        }
+    }
+    Ok(())
+}
+
+fn vb(files: &[PathBuf]) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        match read_executable(file)?.vb16() {
+            Some(info) => println!("{}: {}", file.display(), info),
+            None => println!("{}: not a Visual Basic module", file.display()),
+        }
+    }
+    Ok(())
+}
+
+fn strings(files: &[PathBuf], options: &StringsOptions) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        let parsed = read_executable(file)?;
+        println!("{}:", file.display());
+        for found in parsed.strings(options) {
+            println!("\t{}\t{}", found.location, found.text.escape_debug());
+        }
+    }
+    Ok(())
+}
+
+fn survey(
+    files: &[PathBuf],
+    format: Option<analysis::Format>,
+    output: Option<&Path>,
+) -> io::Result<()> {
+    check_files(files);
+    let mut corpus = Corpus::new();
+    for file in files {
+        corpus.add_result(&file.display().to_string(), &read_executable(file));
+    }
+    let text = match format {
+        Some(format) => corpus.render(format),
+        None => format!("{:#}\n", corpus),
+    };
+    match output {
+        Some(output) => fs::write(output, text),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
 
-        let parsed = NeExecutable::read(&mut cursor)?;
-        parsed.describe(opts.data, opts.disassemble);
+fn diff(old: &Path, new: &Path) -> io::Result<()> {
+    let report = ne::diff::compare(&read_executable(old)?, &read_executable(new)?);
+    if report.is_empty() {
+        println!("{} and {} are structurally equal", old.display(), new.display());
+    } else {
+        print!("{}", report);
     }
     Ok(())
 }
+
+fn write_atlas(sink: &mut dyn ExtractSink, font: &NeFont) -> io::Result<()> {
+    let raster = match RasterFont::read(font) {
+        Ok(raster) => raster,
+        Err(e) => {
+            eprintln!("\t\t{}: {}", font.file_name(), e);
+            return Ok(());
+        }
+    };
+    let atlas = raster.render_atlas(16);
+    let stem = font.file_name().trim_end_matches(".fnt").to_string();
+    let mut data = Vec::new();
+    #[cfg(feature = "image")]
+    let name = {
+        atlas.to_image().write_png(&mut data)?;
+        stem + ".png"
+    };
+    #[cfg(not(feature = "image"))]
+    let name = {
+        atlas.write_pgm(&mut data)?;
+        stem + ".pgm"
+    };
+    sink.put(&name, &data)
+}
+
+#[cfg(feature = "decompress")]
+fn expand(files: &[PathBuf], output: Option<&Path>) -> io::Result<()> {
+    check_files(files);
+    for file in files {
+        let data = fs::read(file)?;
+        if ne::mscompress::compressed_file(&data).is_none() {
+            println!("{}: not compressed", file.display());
+            continue;
+        }
+        let file_name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let name = ne::mscompress::expanded_name(&file_name, &data)
+            .unwrap_or_else(|| format!("{}.expanded", file_name));
+        let dir = output
+            .or_else(|| file.parent())
+            .unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(dir)?;
+        let expanded = ne::mscompress::expand_compressed(&data)?;
+        let target = dir.join(&name);
+        fs::write(&target, &expanded)?;
+        println!(
+            "{} -> {}: 0x{:X} bytes",
+            file.display(),
+            target.display(),
+            expanded.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+fn images(files: &[PathBuf], extract: &Path, zip: bool) -> io::Result<()> {
+    check_files(files);
+    let mut sink = OutputSink::create(extract, zip)?;
+    for file in files {
+        let parsed = read_resources(file)?;
+        println!("{}:", file.display());
+        for image in parsed.extract_images_to(sink.sink())? {
+            println!(
+                "\t{} {}x{}",
+                image.file_name(),
+                image.image.width,
+                image.image.height
+            );
+        }
+    }
+    sink.finish()
+}
+
+fn main() -> io::Result<()> {
+    env_logger::init();
+
+    let opts = Opts::parse();
+
+    match &opts.command {
+        Some(Command::Fonts {
+            extract,
+            atlas,
+            zip,
+            files,
+        }) => fonts(files, extract.as_deref(), atlas.as_deref(), *zip),
+        Some(Command::Report {
+            format,
+            name,
+            files,
+        }) => report(files, *format, name.as_deref()),
+        Some(Command::Canonical { files }) => canonical(files),
+        Some(Command::Script { kind, map, file }) => script(file, *kind, map),
+        Some(Command::Template { kind, file }) => template(file, *kind),
+        Some(Command::Spec { file }) => spec(file),
+        Some(Command::Shim { output, file }) => shim(file, output),
+        Some(Command::Exports { files }) => exports(files),
+        Some(Command::Apis { map, files }) => apis(files, map),
+        Some(Command::Relocations { files }) => relocations(files),
+        Some(command @ Command::Edit { .. }) => edit(command),
+        Some(command @ Command::SetVersion { .. }) => set_version(command),
+        Some(Command::Repack {
+            segments,
+            output,
+            file,
+        }) => repack(
+            file,
+            output,
+            &RepackOptions {
+                segments: *segments,
+            },
+        ),
+        Some(Command::Unpack { output, zip, file }) => unpack(file, output, *zip),
+        Some(Command::Res {
+            codepage,
+            language,
+            output,
+            file,
+        }) => res(file, output, *codepage, *language),
+        Some(Command::ImportRes {
+            codepage,
+            output,
+            res,
+            file,
+        }) => import_res(file, res, output, *codepage),
+        Some(Command::Extract { output, zip, file }) => extract(file, output, *zip),
+        Some(Command::Resources {
+            preview,
+            codepage,
+            files,
+        }) => resources(files, *preview, *codepage),
+        Some(Command::Codepage { files }) => codepage(files),
+        Some(Command::Hashes {
+            algorithm,
+            find,
+            files,
+        }) => hashes(files, *algorithm, find.as_ref()),
+        Some(Command::FindResource {
+            type_name,
+            id,
+            min_size,
+            hash,
+            contains,
+            paths,
+        }) => find_resource(
+            paths,
+            &ResourceQuery {
+                type_id: type_name.as_deref().map(type_id),
+                id_pattern: id.clone(),
+                min_size: *min_size,
+                hash: hash.clone(),
+                contains: contains.clone(),
+            },
+        ),
+        Some(Command::Depends { paths, file }) => depends(file, paths),
+        Some(Command::Footprint { dirs }) => footprint(dirs),
+        Some(Command::Sbom { paths, file }) => sbom(file, paths),
+        Some(Command::Gangload { files }) => gangload(files),
+        Some(Command::Tables { files }) => tables(files),
+        Some(Command::Layout { files }) => layout(files),
+        Some(Command::Validate { files }) => validate(files),
+        Some(Command::Fuzz { files }) => fuzz(files),
+        Some(Command::Identify { file, offsets }) => identify(file, offsets),
+        Some(Command::Slack { files }) => slack(files),
+        Some(Command::Lint {
+            allow,
+            min_severity,
+            deny,
+            files,
+        }) => {
+            let mut filter = FindingFilter {
+                min_severity: *min_severity,
+                ..Default::default()
+            };
+            for code in allow {
+                filter.suppress(code);
+            }
+            lint(files, &filter, *deny)
+        }
+        Some(Command::Explain { codes }) => explain(codes),
+        Some(Command::MinVersion { files }) => min_version(files),
+        Some(Command::Toolchain { files }) => toolchain(files),
+        Some(Command::Functions { segment, files }) => functions(files, *segment),
+        Some(Command::Listing {
+            segment,
+            map,
+            output,
+            file,
+        }) => listing(file, *segment, map, output.as_deref()),
+        Some(Command::Stack { map, files }) => stack(files, map),
+        Some(Command::Metrics { files }) => metrics(files),
+        Some(Command::Startup { files }) => startup(files),
+        Some(Command::Vb { files }) => vb(files),
+        Some(Command::Driver { files }) => driver(files),
+        Some(Command::Strings {
+            min_length,
+            codepage,
+            files,
+        }) => {
+            let options = StringsOptions {
+                min_length: *min_length,
+                codepage: *codepage,
+                ..Default::default()
+            };
+            strings(files, &options)
+        }
+        Some(Command::Survey {
+            format,
+            output,
+            files,
+        }) => survey(files, *format, output.as_deref()),
+        Some(Command::Loaded {
+            header,
+            segment,
+            file_records,
+            disassemble,
+            dump,
+        }) => {
+            let mut map = LoadedImageMap::new(*header, segment);
+            if *file_records {
+                map.segment_record = FILE_SEGMENT_RECORD;
+            }
+            loaded(dump, &map, *disassemble)
+        }
+        Some(Command::Diff { old, new }) => diff(old, new),
+        #[cfg(feature = "decompress")]
+        Some(Command::Expand { output, files }) => expand(files, output.as_deref()),
+        #[cfg(feature = "image")]
+        Some(Command::Images {
+            extract,
+            zip,
+            files,
+        }) => images(files, extract, *zip),
+        None => describe(&opts.files, opts.data, opts.disassemble, &opts.map),
+    }
+}
@@ -2,6 +2,7 @@ use std::io::{self, Read};
 
 use bytemuck::{Pod, Zeroable};
 
+use crate::ne::error::NeError;
 use crate::util::endian::{Lu16, Lu32};
 
 /// The DOS header.
@@ -55,10 +56,18 @@ impl DosHeader {
         Ok(bytemuck::cast(buf))
     }
 
+    ///
+    /// Checks `MZ` signature; `NeError::BadMagic` tells what was found instead
+    ///
     pub fn check_magic(&self) -> io::Result<()> {
         // 4D 5A == b"MZ"
         if self.magic.value() != 0x5A4D {
-            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid magic"));
+            return Err(NeError::BadMagic {
+                offset: 0,
+                expected: *b"MZ",
+                found: self.magic.value().to_le_bytes(),
+            }
+            .into());
         }
         Ok(())
     }
@@ -0,0 +1,213 @@
+use std::fmt;
+
+use crate::ne::segment_table::NeSegment;
+use crate::ne::NeExecutable;
+
+/// `flags` of segment: data is iterated, offsets on file don't match memory
+const SEG_ITERATED: u16 = 0x0002;
+
+///
+/// Segment number of the module (from 1), as the header,
+/// entry table and relocation records give it. 0 means no segment.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Seg16(pub u16);
+
+impl Seg16 {
+    ///
+    /// Segment of `index` in the segment table (from 0)
+    ///
+    pub fn from_index(index: usize) -> Self {
+        Self(index as u16 + 1)
+    }
+
+    ///
+    /// Index in the segment table (from 0), `None` for segment 0
+    ///
+    pub fn index(self) -> Option<usize> {
+        (self.0 as usize).checked_sub(1)
+    }
+}
+
+impl fmt::Display for Seg16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+///
+/// `segment:offset` address. On file the segment is a segment number,
+/// in memory it is a selector given by loader.
+/// Stored as a dword with segment in the high word (offset comes first on file).
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Far16Ptr {
+    pub segment: u16,
+    pub offset: u16,
+}
+
+impl Far16Ptr {
+    pub const fn new(segment: u16, offset: u16) -> Self {
+        Self { segment, offset }
+    }
+
+    ///
+    /// Reads `offset, segment` words; `None` if `b` is shorter than 4 bytes
+    ///
+    pub fn from_bytes(b: &[u8]) -> Option<Self> {
+        let b = b.get(..4)?;
+        Some(Self::from(u32::from_le_bytes([b[0], b[1], b[2], b[3]])))
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.segment == 0 && self.offset == 0
+    }
+
+    ///
+    /// Segment number, for pointers read from file
+    ///
+    pub fn seg(&self) -> Seg16 {
+        Seg16(self.segment)
+    }
+
+    ///
+    /// Address `delta` bytes further in the same segment, `None` past its end
+    ///
+    pub fn checked_add(self, delta: u16) -> Option<Self> {
+        Some(Self::new(self.segment, self.offset.checked_add(delta)?))
+    }
+}
+
+impl From<u32> for Far16Ptr {
+    fn from(value: u32) -> Self {
+        Self::new((value >> 16) as u16, value as u16)
+    }
+}
+
+impl From<Far16Ptr> for u32 {
+    fn from(ptr: Far16Ptr) -> Self {
+        (ptr.segment as u32) << 16 | ptr.offset as u32
+    }
+}
+
+impl fmt::Display for Far16Ptr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04X}:{:04X}", self.segment, self.offset)
+    }
+}
+
+///
+/// Linear base and size of every segment of the loaded module
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadMap {
+    /// Base and size of segment N at N - 1
+    pub segments: Vec<(u32, u32)>,
+}
+
+impl LoadMap {
+    ///
+    /// Segments one after another from `start`, each of its allocation
+    /// size and beginning at a paragraph (16-byte) boundary
+    ///
+    pub fn sequential(ne: &NeExecutable, start: u32) -> Self {
+        let mut base = start;
+        let segments = ne
+            .segment_entries
+            .iter()
+            .map(|segment| {
+                base = base.next_multiple_of(16);
+                let size = segment.min_alloc().max(segment.data_length()) as u32;
+                let placed = (base, size);
+                base += size;
+                placed
+            })
+            .collect();
+        Self { segments }
+    }
+
+    pub fn base(&self, segment: Seg16) -> Option<u32> {
+        self.segments.get(segment.index()?).map(|&(base, _)| base)
+    }
+
+    ///
+    /// Linear address of `ptr` (its segment is the segment number)
+    ///
+    pub fn linear(&self, ptr: Far16Ptr) -> Option<u32> {
+        let &(base, size) = self.segments.get(ptr.seg().index()?)?;
+        ((ptr.offset as u32) < size).then(|| base + ptr.offset as u32)
+    }
+
+    ///
+    /// `segment:offset` of linear address, if a segment holds it
+    ///
+    pub fn far(&self, linear: u32) -> Option<Far16Ptr> {
+        self.segments
+            .iter()
+            .position(|&(base, size)| linear >= base && linear - base < size)
+            .map(|i| Far16Ptr::new(Seg16::from_index(i).0, (linear - self.segments[i].0) as u16))
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Entry of segment table for segment number
+    ///
+    pub fn segment(&self, segment: Seg16) -> Option<&NeSegment> {
+        self.segment_entries.get(segment.index()?)
+    }
+
+    ///
+    /// File offset of byte at `ptr` (segment number and offset).
+    /// `None` for bytes past data on file and for iterated segments.
+    ///
+    pub fn file_offset(&self, ptr: Far16Ptr) -> Option<u64> {
+        let segment = self.segment(ptr.seg())?;
+        let has_data = segment.header.data_offset_shifted != 0;
+        let is_iterated = segment.header.flags & SEG_ITERATED != 0;
+        (has_data && !is_iterated && (ptr.offset as u64) < segment.data_length())
+            .then(|| segment.data_offset() + ptr.offset as u64)
+    }
+
+    ///
+    /// `segment:offset` of byte at file `offset`, if it is segment data
+    ///
+    pub fn far_at_file_offset(&self, offset: u64) -> Option<Far16Ptr> {
+        self.segment_entries
+            .iter()
+            .enumerate()
+            .find_map(|(i, segment)| {
+                let start = segment.data_offset();
+                let inside = segment.header.data_offset_shifted != 0
+                    && segment.header.flags & SEG_ITERATED == 0
+                    && offset >= start
+                    && offset - start < segment.data_length();
+                inside.then(|| Far16Ptr::new(Seg16::from_index(i).0, (offset - start) as u16))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_addresses() {
+        let ptr = Far16Ptr::from(0x0002_0010);
+        assert_eq!(ptr, Far16Ptr::new(2, 0x10));
+        assert_eq!(u32::from(ptr), 0x0002_0010);
+        assert_eq!(Far16Ptr::from_bytes(&[0x10, 0, 2, 0]), Some(ptr));
+        assert_eq!(ptr.to_string(), "0002:0010");
+        assert_eq!(ptr.seg().index(), Some(1));
+        assert_eq!(Seg16(0).index(), None);
+        assert_eq!(Far16Ptr::new(1, 0xFFFF).checked_add(1), None);
+
+        let map = LoadMap {
+            segments: vec![(0x1000, 0x20), (0x1020, 0x100)],
+        };
+        assert_eq!(map.linear(ptr), Some(0x1030));
+        assert_eq!(map.far(0x1030), Some(ptr));
+        assert_eq!(map.linear(Far16Ptr::new(1, 0x20)), None);
+        assert_eq!(map.far(0x2000), None);
+    }
+}
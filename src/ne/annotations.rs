@@ -0,0 +1,348 @@
+use std::str::FromStr;
+
+use crate::ne::addr::{Far16Ptr, Seg16};
+use crate::ne::symbols::SymbolProvider;
+use crate::ne::NeExecutable;
+
+///
+/// Interactive disassemblers for which annotation scripts are generated
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptKind {
+    /// Python script for Ghidra Script Manager (Jython)
+    Ghidra,
+    /// IDC script for IDA (7.x function names)
+    Idc,
+}
+
+impl FromStr for ScriptKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ghidra" | "py" => Ok(ScriptKind::Ghidra),
+            "ida" | "idc" => Ok(ScriptKind::Idc),
+            _ => Err(format!("unknown script kind `{}`", s)),
+        }
+    }
+}
+
+///
+/// Found by the parser and applied by the script:
+/// everything is addressed by segment number (from 1) and offset.
+///
+enum Annotation {
+    Segment {
+        segment: usize,
+        name: String,
+        is_data: bool,
+    },
+    Function {
+        segment: usize,
+        offset: u16,
+        name: String,
+    },
+    Label {
+        segment: usize,
+        offset: u16,
+        name: String,
+    },
+    Comment {
+        segment: usize,
+        offset: u16,
+        text: String,
+    },
+}
+
+impl NeExecutable {
+    ///
+    /// Generates script declaring segments, entry points, export names,
+    /// imported call targets and floating point fixups in Ghidra or IDA.
+    ///
+    /// Loaders of both tools place NE segments in file order,
+    /// so the script finds the segment N as the N-th memory block (segment)
+    /// of the database. Bases may be fixed by hand in the generated script.
+    /// \param symbols -- names of imported ordinals and unexported code
+    ///
+    pub fn annotation_script(&self, kind: ScriptKind, symbols: &dyn SymbolProvider) -> String {
+        let annotations = self.annotations(symbols);
+        match kind {
+            ScriptKind::Ghidra => ghidra_script(&self.script_title(), &annotations),
+            ScriptKind::Idc => idc_script(&self.script_title(), &annotations),
+        }
+    }
+
+    fn script_title(&self) -> String {
+        self.module_name()
+            .unwrap_or_else(|| "<unnamed>".to_string())
+    }
+
+    fn annotations(&self, symbols: &dyn SymbolProvider) -> Vec<Annotation> {
+        let mut annotations = Vec::new();
+        let is_data = |segment: usize| {
+            self.segment(Seg16(segment as u16))
+                .is_some_and(|s| s.header.flags & 0x0001 != 0)
+        };
+
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let is_data = segment.header.flags & 0x0001 != 0;
+            annotations.push(Annotation::Segment {
+                segment: i + 1,
+                name: format!("{}{}", if is_data { "DATA" } else { "CODE" }, i + 1),
+                is_data,
+            });
+        }
+
+        let entry_point = Far16Ptr::from(self.ne_header.entry_point.value());
+        if entry_point.segment != 0 {
+            annotations.push(Annotation::Function {
+                segment: entry_point.segment as usize,
+                offset: entry_point.offset,
+                name: "start".to_string(),
+            });
+        }
+
+        for export in self.exports() {
+            let segment = export.segment as usize;
+            // constants (segment 0xFE) are not placed in segments
+            if segment == 0 || segment > self.segment_entries.len() {
+                continue;
+            }
+            let name = export
+                .name
+                .as_deref()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("Ordinal_{}", export.ordinal));
+            annotations.push(if is_data(segment) {
+                Annotation::Label {
+                    segment,
+                    offset: export.offset,
+                    name,
+                }
+            } else {
+                Annotation::Function {
+                    segment,
+                    offset: export.offset,
+                    name,
+                }
+            });
+            annotations.push(Annotation::Comment {
+                segment,
+                offset: export.offset,
+                text: format!("exported @{}", export.ordinal),
+            });
+        }
+
+        for import in self.imports() {
+            let text = match import.resolved_name(symbols) {
+                Some(name) => format!("import {} ({})", import, name),
+                None => format!("import {}", import),
+            };
+            for &(segment, offset) in &import.references {
+                annotations.push(Annotation::Comment {
+                    segment,
+                    offset,
+                    text: text.clone(),
+                });
+            }
+        }
+
+        for (segment, offset, fixup) in self.os_fixups() {
+            annotations.push(Annotation::Comment {
+                segment,
+                offset,
+                text: format!("OS fixup {}", fixup),
+            });
+        }
+        annotations
+    }
+}
+
+///
+/// String literal for both Python and IDC
+///
+fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            ' '..='~' => out.push(c),
+            c => out.push_str(&format!("\\x{:02x}", c as u32 & 0xFF)),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn ghidra_script(title: &str, annotations: &[Annotation]) -> String {
+    let mut out = format!(
+        "# Annotations of NE module {} generated by win16ne\n\
+         # @category Win16\n\
+         from ghidra.program.model.symbol import SourceType\n\
+         \n\
+         # N-th initialized memory block is the segment N; fix bases here if not so\n\
+         blocks = [b for b in currentProgram.getMemory().getBlocks() if b.isInitialized()]\n\
+         SEGMENT_BASES = [b.getStart() for b in blocks]\n\
+         \n\
+         def addr(segment, offset):\n\
+         \x20   return SEGMENT_BASES[segment - 1].add(offset)\n\
+         \n\
+         def label(segment, offset, name):\n\
+         \x20   createLabel(addr(segment, offset), name, True, SourceType.IMPORTED)\n\
+         \n\
+         def function(segment, offset, name):\n\
+         \x20   a = addr(segment, offset)\n\
+         \x20   disassemble(a)\n\
+         \x20   if getFunctionAt(a) is None:\n\
+         \x20       createFunction(a, name)\n\
+         \x20   label(segment, offset, name)\n\
+         \n\
+         def comment(segment, offset, text):\n\
+         \x20   a = addr(segment, offset)\n\
+         \x20   old = getEOLComment(a)\n\
+         \x20   setEOLComment(a, text if old is None else old + \"; \" + text)\n\
+         \n",
+        quote(title)
+    );
+    for annotation in annotations {
+        let line = match annotation {
+            Annotation::Segment {
+                segment,
+                name,
+                is_data,
+            } => {
+                let kind = if *is_data { "data" } else { "code" };
+                format!(
+                    "blocks[{}].setComment({})\n",
+                    segment - 1,
+                    quote(&format!("{} segment #{}", kind, segment))
+                ) + &format!("blocks[{}].setName({})\n", segment - 1, quote(name))
+            }
+            Annotation::Function {
+                segment,
+                offset,
+                name,
+            } => format!("function({}, 0x{:04X}, {})\n", segment, offset, quote(name)),
+            Annotation::Label {
+                segment,
+                offset,
+                name,
+            } => format!("label({}, 0x{:04X}, {})\n", segment, offset, quote(name)),
+            Annotation::Comment {
+                segment,
+                offset,
+                text,
+            } => format!("comment({}, 0x{:04X}, {})\n", segment, offset, quote(text)),
+        };
+        out += &line;
+    }
+    out
+}
+
+fn idc_script(title: &str, annotations: &[Annotation]) -> String {
+    let mut out = format!(
+        "// Annotations of NE module {} generated by win16ne\n\
+         #include <idc.idc>\n\
+         \n\
+         // N-th segment of database is the segment N; fix bases here if not so\n\
+         static segment_base(n) {{\n\
+         \x20   auto ea = get_first_seg();\n\
+         \x20   while (n > 1 && ea != BADADDR) {{\n\
+         \x20       ea = get_next_seg(ea);\n\
+         \x20       n = n - 1;\n\
+         \x20   }}\n\
+         \x20   return ea;\n\
+         }}\n\
+         \n\
+         static comment(ea, text) {{\n\
+         \x20   auto old = get_cmt(ea, 0);\n\
+         \x20   if (old != \"\") text = old + \"; \" + text;\n\
+         \x20   set_cmt(ea, text, 0);\n\
+         }}\n\
+         \n\
+         static main() {{\n",
+        quote(title)
+    );
+    for annotation in annotations {
+        let line = match annotation {
+            Annotation::Segment {
+                segment,
+                name,
+                is_data,
+            } => format!(
+                "    set_segm_name(segment_base({}), {});\n    set_segm_class(segment_base({}), {});\n",
+                segment,
+                quote(name),
+                segment,
+                quote(if *is_data { "DATA" } else { "CODE" })
+            ),
+            Annotation::Function {
+                segment,
+                offset,
+                name,
+            } => format!(
+                "    add_func(segment_base({}) + 0x{:04X}, BADADDR);\n    set_name(segment_base({}) + 0x{:04X}, {}, SN_NOCHECK | SN_NOWARN);\n",
+                segment,
+                offset,
+                segment,
+                offset,
+                quote(name)
+            ),
+            Annotation::Label {
+                segment,
+                offset,
+                name,
+            } => format!(
+                "    set_name(segment_base({}) + 0x{:04X}, {}, SN_NOCHECK | SN_NOWARN);\n",
+                segment,
+                offset,
+                quote(name)
+            ),
+            Annotation::Comment {
+                segment,
+                offset,
+                text,
+            } => format!(
+                "    comment(segment_base({}) + 0x{:04X}, {});\n",
+                segment,
+                offset,
+                quote(text)
+            ),
+        };
+        out += &line;
+    }
+    out += "}\n";
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_names() {
+        assert_eq!(quote("?Foo@@YAXXZ"), "\"?Foo@@YAXXZ\"");
+        assert_eq!(quote("a\"b\\c"), "\"a\\\"b\\\\c\"");
+    }
+
+    #[test]
+    fn idc_statements() {
+        let annotations = [
+            Annotation::Function {
+                segment: 1,
+                offset: 0x10,
+                name: "WEP".to_string(),
+            },
+            Annotation::Comment {
+                segment: 1,
+                offset: 0x20,
+                text: "import KERNEL.@91".to_string(),
+            },
+        ];
+        let script = idc_script("SAMPLE", &annotations);
+        assert!(script.contains("    add_func(segment_base(1) + 0x0010, BADADDR);\n"));
+        assert!(script.contains("    comment(segment_base(1) + 0x0020, \"import KERNEL.@91\");\n"));
+        assert!(script.ends_with("}\n"));
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::ne::imports::ImportTarget;
+use crate::ne::symbols::SymbolProvider;
+use crate::ne::NeExecutable;
+
+///
+/// Area of Windows API a module depends on, for what an emulator
+/// or a port has to provide
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ApiArea {
+    /// GDI: drawing, fonts, bitmaps, printing
+    Graphics,
+    /// Files through `OpenFile`, `_lopen` and the like
+    FileIo,
+    /// `DOS3Call`: MS-DOS services, files included
+    DosCalls,
+    /// Serial and parallel ports of `USER`
+    CommPorts,
+    /// `SOUND` and `MMSYSTEM`
+    Sound,
+    /// Dynamic data exchange library
+    Dde,
+    /// Windows, messages, menus and dialogs of `USER`
+    Windowing,
+    /// Memory, tasks, modules and resources of `KERNEL`
+    System,
+    /// Common dialogs, shell, OLE and other system libraries
+    Libraries,
+    /// Sockets and network libraries
+    Network,
+    /// Modules unknown to the report: application DLLs and drivers
+    Other,
+}
+
+impl fmt::Display for ApiArea {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ApiArea::Graphics => "GDI drawing",
+            ApiArea::FileIo => "file I/O",
+            ApiArea::DosCalls => "MS-DOS calls",
+            ApiArea::CommPorts => "comm ports",
+            ApiArea::Sound => "sound",
+            ApiArea::Dde => "DDE",
+            ApiArea::Windowing => "windowing",
+            ApiArea::System => "system",
+            ApiArea::Libraries => "system libraries",
+            ApiArea::Network => "network",
+            ApiArea::Other => "other modules",
+        })
+    }
+}
+
+const FILE_PROCEDURES: &[&str] = &[
+    "OpenFile",
+    "_lopen",
+    "_lcreat",
+    "_lread",
+    "_lwrite",
+    "_llseek",
+    "_lclose",
+    "_hread",
+    "_hwrite",
+    "GetTempFileName",
+    "GetTempDrive",
+];
+
+///
+/// Area of procedure `name` (if known) imported from `module`.
+/// Procedures are compared ignoring case, as linkers uppercase names.
+///
+pub fn classify(module: &str, name: Option<&str>) -> ApiArea {
+    let is = |expected: &str| name.is_some_and(|n| n.eq_ignore_ascii_case(expected));
+    match module.to_ascii_uppercase().as_str() {
+        "GDI" => ApiArea::Graphics,
+        "KERNEL" if FILE_PROCEDURES.iter().any(|&p| is(p)) => ApiArea::FileIo,
+        "KERNEL" if is("DOS3Call") => ApiArea::DosCalls,
+        "KERNEL" => ApiArea::System,
+        "USER" if name.is_some_and(|n| n.to_ascii_uppercase().contains("COMM")) => {
+            ApiArea::CommPorts
+        }
+        "USER" | "KEYBOARD" | "DISPLAY" => ApiArea::Windowing,
+        "SOUND" | "MMSYSTEM" => ApiArea::Sound,
+        "DDEML" => ApiArea::Dde,
+        "WINSOCK" | "NETAPI" | "NETBIOS" => ApiArea::Network,
+        "COMMDLG" | "SHELL" | "OLECLI" | "OLESVR" | "COMPOBJ" | "OLE2" | "VER" | "LZEXPAND"
+        | "TOOLHELP" | "WIN87EM" => ApiArea::Libraries,
+        _ => ApiArea::Other,
+    }
+}
+
+///
+/// Imports of a module grouped by API area: `MODULE.NAME` of every
+/// procedure, or `MODULE.@N` for ordinals no symbols know
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiReport {
+    pub areas: BTreeMap<ApiArea, Vec<String>>,
+    /// Imports by ordinal whose names are unknown: their area is a guess
+    /// by module alone
+    pub unresolved: usize,
+}
+
+impl fmt::Display for ApiReport {
+    ///
+    /// `{}` gives count of imports in every area,
+    /// `{:#}` also the imports of each
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total: usize = self.areas.values().map(Vec::len).sum();
+        write!(f, "{} imports in {} areas", total, self.areas.len())?;
+        if self.unresolved != 0 {
+            write!(f, " ({} by unknown ordinals)", self.unresolved)?;
+        }
+        for (area, imports) in &self.areas {
+            write!(f, "\n\t{:<16} {}", area.to_string(), imports.len())?;
+            if f.alternate() {
+                for import in imports {
+                    write!(f, "\n\t\t{}", import)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Windows API areas used by the module, from its imports.
+    /// Imports by ordinal are named with `symbols`.
+    ///
+    pub fn api_usage(&self, symbols: &dyn SymbolProvider) -> ApiReport {
+        let mut report = ApiReport::default();
+        for import in self.imports() {
+            let name = match &import.target {
+                ImportTarget::Name(name) => Some(name.clone()),
+                ImportTarget::Ordinal(_) => import.resolved_name(symbols),
+            };
+            if name.is_none() {
+                report.unresolved += 1;
+            }
+            let area = classify(&import.module, name.as_deref());
+            let shown = name.unwrap_or_else(|| import.target.to_string());
+            report
+                .areas
+                .entry(area)
+                .or_default()
+                .push(format!("{}.{}", import.module, shown));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_by_module_and_name() {
+        assert_eq!(classify("GDI", None), ApiArea::Graphics);
+        assert_eq!(classify("kernel", Some("_LREAD")), ApiArea::FileIo);
+        assert_eq!(classify("KERNEL", Some("GlobalAlloc")), ApiArea::System);
+        assert_eq!(classify("KERNEL", None), ApiArea::System);
+        assert_eq!(classify("USER", Some("OPENCOMM")), ApiArea::CommPorts);
+        assert_eq!(classify("USER", Some("MessageBox")), ApiArea::Windowing);
+        assert_eq!(classify("MMSYSTEM", Some("sndPlaySound")), ApiArea::Sound);
+        assert_eq!(classify("MYDLL", Some("Init")), ApiArea::Other);
+    }
+}
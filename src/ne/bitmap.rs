@@ -0,0 +1,414 @@
+use std::convert::TryInto;
+use std::io::{self, Write};
+
+use crate::ne::resource_table::{ResourceId, RT_BITMAP, RT_CURSOR, RT_ICON};
+use crate::ne::sink::ExtractSink;
+use crate::ne::NeExecutable;
+use crate::util::png;
+
+///
+/// Decoded picture: 8-bit RGBA pixels, rows from top to bottom.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl RgbaImage {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            rgba: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let pos = (y as usize * self.width as usize + x as usize) * 4;
+        self.rgba[pos..pos + 4].try_into().unwrap()
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, rgba: [u8; 4]) {
+        let pos = (y as usize * self.width as usize + x as usize) * 4;
+        self.rgba[pos..pos + 4].copy_from_slice(&rgba);
+    }
+
+    pub fn write_png<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        png::write_rgba(w, self.width, self.height, &self.rgba)
+    }
+}
+
+///
+/// Header of device independent bitmap. Both `BITMAPINFOHEADER`
+/// and OS/2 `BITMAPCOREHEADER` are accepted.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct DibHeader {
+    pub header_size: u32,
+    pub width: i32,
+    pub height: i32,
+    pub planes: u16,
+    pub bit_count: u16,
+    pub compression: u32,
+    pub colors_used: u32,
+}
+
+impl DibHeader {
+    pub fn read(data: &[u8]) -> io::Result<Self> {
+        let get_u16 = |pos: usize| -> io::Result<u16> {
+            data.get(pos..pos + 2)
+                .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+                .ok_or_else(|| truncated("DIB header"))
+        };
+        let get_u32 = |pos: usize| -> io::Result<u32> {
+            data.get(pos..pos + 4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+                .ok_or_else(|| truncated("DIB header"))
+        };
+
+        let header_size = get_u32(0)?;
+        match header_size {
+            12 => Ok(Self {
+                header_size,
+                width: get_u16(4)? as i32,
+                height: get_u16(6)? as i32,
+                planes: get_u16(8)?,
+                bit_count: get_u16(10)?,
+                compression: 0,
+                colors_used: 0,
+            }),
+            40.. => Ok(Self {
+                header_size,
+                width: get_u32(4)? as i32,
+                height: get_u32(8)? as i32,
+                planes: get_u16(12)?,
+                bit_count: get_u16(14)?,
+                compression: get_u32(16)?,
+                colors_used: get_u32(32)?,
+            }),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown DIB header size: {}", header_size),
+            )),
+        }
+    }
+
+    ///
+    /// Number of palette entries following the header
+    ///
+    pub fn palette_size(&self) -> usize {
+        match (self.colors_used, self.bit_count) {
+            (0, 1 | 4 | 8) => 1 << self.bit_count,
+            (0, _) => 0,
+            (n, _) => n as usize,
+        }
+    }
+
+    fn palette_entry_size(&self) -> usize {
+        if self.header_size == 12 {
+            3
+        } else {
+            4
+        }
+    }
+
+    fn stride(&self) -> usize {
+        (self.width.unsigned_abs() as usize * self.bit_count as usize).div_ceil(32) * 4
+    }
+}
+
+///
+/// Decodes RT_BITMAP resource (DIB without `BITMAPFILEHEADER`).
+///
+pub fn decode_bitmap(data: &[u8]) -> io::Result<RgbaImage> {
+    let header = DibHeader::read(data)?;
+    let height = header.height.unsigned_abs();
+    decode_dib(data, &header, height).map(|(image, _)| image)
+}
+
+///
+/// Decodes RT_ICON resource. Height in header covers both
+/// XOR (color) and AND (transparency) masks.
+///
+pub fn decode_icon(data: &[u8]) -> io::Result<RgbaImage> {
+    let header = DibHeader::read(data)?;
+    let height = header.height.unsigned_abs() / 2;
+    let (mut image, mask_offset) = decode_dib(data, &header, height)?;
+    apply_and_mask(&mut image, &data[mask_offset.min(data.len())..])?;
+    Ok(image)
+}
+
+///
+/// Decodes RT_CURSOR resource: hotspot (2 words) followed by an icon-like DIB.
+/// Returns the picture and hotspot position.
+///
+pub fn decode_cursor(data: &[u8]) -> io::Result<(RgbaImage, (u16, u16))> {
+    if data.len() < 4 {
+        return Err(truncated("cursor hotspot"));
+    }
+    let hotspot = (
+        u16::from_le_bytes([data[0], data[1]]),
+        u16::from_le_bytes([data[2], data[3]]),
+    );
+    Ok((decode_icon(&data[4..])?, hotspot))
+}
+
+///
+/// Decodes `height` rows of DIB color bits.
+/// Returns image and offset right after decoded bits.
+///
+fn decode_dib(data: &[u8], header: &DibHeader, height: u32) -> io::Result<(RgbaImage, usize)> {
+    if header.compression != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "Compressed DIB is not supported: compression={}",
+                header.compression
+            ),
+        ));
+    }
+    if !matches!(header.bit_count, 1 | 4 | 8 | 24) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("Unsupported DIB bit count: {}", header.bit_count),
+        ));
+    }
+
+    let palette_offset = header.header_size as usize;
+    let entry_size = header.palette_entry_size();
+    let palette_end = palette_offset + header.palette_size() * entry_size;
+    let palette = data
+        .get(palette_offset..palette_end)
+        .ok_or_else(|| truncated("DIB palette"))?
+        .chunks(entry_size)
+        .map(|c| [c[2], c[1], c[0], 0xFF])
+        .collect::<Vec<_>>();
+
+    let width = header.width.unsigned_abs();
+    let stride = header.stride();
+    let bits_end = palette_end + stride * height as usize;
+    let bits = data
+        .get(palette_end..bits_end)
+        .ok_or_else(|| truncated("DIB bits"))?;
+
+    let mut image = RgbaImage::new(width, height);
+    let top_down = header.height < 0;
+    for (row_index, row) in bits.chunks(stride.max(1)).enumerate() {
+        let y = if top_down {
+            row_index as u32
+        } else {
+            height - 1 - row_index as u32
+        };
+        for x in 0..width {
+            let color = match header.bit_count {
+                24 => {
+                    let pos = x as usize * 3;
+                    [row[pos + 2], row[pos + 1], row[pos], 0xFF]
+                }
+                bits => {
+                    let bit_pos = x as usize * bits as usize;
+                    let byte = row[bit_pos / 8];
+                    let shift = 8 - bits as usize - bit_pos % 8;
+                    let index = (byte >> shift) as usize & ((1 << bits) - 1);
+                    palette.get(index).copied().unwrap_or([0, 0, 0, 0xFF])
+                }
+            };
+            image.set_pixel(x, y, color);
+        }
+    }
+    Ok((image, bits_end))
+}
+
+///
+/// Makes transparent every pixel which has its AND mask bit set.
+/// (Set bit with non-black XOR color inverts the screen, which can't be
+/// expressed in RGBA: such pixels become transparent too.)
+///
+fn apply_and_mask(image: &mut RgbaImage, mask: &[u8]) -> io::Result<()> {
+    let stride = (image.width as usize).div_ceil(32) * 4;
+    if mask.len() < stride * image.height as usize {
+        return Err(truncated("icon AND mask"));
+    }
+    for (row_index, row) in mask
+        .chunks(stride.max(1))
+        .take(image.height as usize)
+        .enumerate()
+    {
+        let y = image.height - 1 - row_index as u32;
+        for x in 0..image.width {
+            if row[x as usize / 8] & (0x80 >> (x % 8)) != 0 {
+                image.set_pixel(x, y, [0, 0, 0, 0]);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn truncated(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Truncated {}", what))
+}
+
+///
+/// Resource kind the picture was decoded from
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeImageKind {
+    Bitmap,
+    Icon,
+    Cursor { hotspot: (u16, u16) },
+}
+
+#[derive(Debug, Clone)]
+pub struct NeImage {
+    pub kind: NeImageKind,
+    pub resource_id: ResourceId,
+    pub image: RgbaImage,
+}
+
+impl NeImage {
+    ///
+    /// Suggested name of `.png` file: resource kind and ID
+    ///
+    pub fn file_name(&self) -> String {
+        let kind = match self.kind {
+            NeImageKind::Bitmap => "bitmap",
+            NeImageKind::Icon => "icon",
+            NeImageKind::Cursor { .. } => "cursor",
+        };
+        format!("{}_{}.png", kind, self.resource_id)
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Decodes every RT_BITMAP, RT_ICON and RT_CURSOR resource.
+    /// Resources which can't be decoded (compressed DIBs, old
+    /// Windows 1.x/2.x formats) are skipped with a warning.
+    ///
+    pub fn extract_images(&self) -> Vec<NeImage> {
+        let mut images = Vec::new();
+        for type_id in [RT_BITMAP, RT_ICON, RT_CURSOR] {
+            for resource in self.resource_table.resources_of_type(type_id) {
+                let Some(data) = &resource.data else {
+                    continue;
+                };
+                let decoded = match type_id {
+                    RT_BITMAP => decode_bitmap(data).map(|image| (NeImageKind::Bitmap, image)),
+                    RT_ICON => decode_icon(data).map(|image| (NeImageKind::Icon, image)),
+                    _ => decode_cursor(data)
+                        .map(|(image, hotspot)| (NeImageKind::Cursor { hotspot }, image)),
+                };
+                match decoded {
+                    Ok((kind, image)) => images.push(NeImage {
+                        kind,
+                        resource_id: resource.id(),
+                        image,
+                    }),
+                    Err(e) => log::warn!(
+                        "Resource {} of type {} is not decoded: {}",
+                        resource.id(),
+                        type_id,
+                        e
+                    ),
+                }
+            }
+        }
+        images
+    }
+
+    ///
+    /// Puts every image `extract_images` decodes into `sink`
+    /// as `.png` file of its `file_name` and gives the images
+    ///
+    pub fn extract_images_to(&self, sink: &mut dyn ExtractSink) -> io::Result<Vec<NeImage>> {
+        let images = self.extract_images();
+        for image in &images {
+            let mut png = Vec::new();
+            image.image.write_png(&mut png)?;
+            sink.put(&image.file_name(), &png)?;
+        }
+        Ok(images)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::fixture::sample_module;
+    use crate::ne::sink::MemorySink;
+
+    fn info_header(width: i32, height: i32, bit_count: u16) -> Vec<u8> {
+        let mut data = vec![0; 40];
+        data[0..4].copy_from_slice(&40_u32.to_le_bytes());
+        data[4..8].copy_from_slice(&width.to_le_bytes());
+        data[8..12].copy_from_slice(&height.to_le_bytes());
+        data[12..14].copy_from_slice(&1_u16.to_le_bytes());
+        data[14..16].copy_from_slice(&bit_count.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_decode_monochrome_bitmap() {
+        let mut data = info_header(2, 2, 1);
+        data.extend_from_slice(&[0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0]);
+        // bottom row: white, black; top row: black, white
+        data.extend_from_slice(&[0x80, 0, 0, 0, 0x40, 0, 0, 0]);
+        let image = decode_bitmap(&data).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.pixel(0, 1), [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(image.pixel(1, 1), [0, 0, 0, 0xFF]);
+        assert_eq!(image.pixel(0, 0), [0, 0, 0, 0xFF]);
+        assert_eq!(image.pixel(1, 0), [0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn test_decode_icon_mask() {
+        let mut data = info_header(1, 2, 4);
+        let mut palette = vec![0; 16 * 4];
+        palette[4..8].copy_from_slice(&[0x30, 0x20, 0x10, 0]);
+        data.extend_from_slice(&palette);
+        // XOR: one row of color #1, AND: mask bit set (transparent)
+        data.extend_from_slice(&[0x10, 0, 0, 0]);
+        data.extend_from_slice(&[0x80, 0, 0, 0]);
+        let image = decode_icon(&data).unwrap();
+        assert_eq!((image.width, image.height), (1, 1));
+        assert_eq!(image.pixel(0, 0), [0, 0, 0, 0]);
+
+        let len = data.len();
+        data[len - 4] = 0;
+        assert_eq!(
+            decode_icon(&data).unwrap().pixel(0, 0),
+            [0x10, 0x20, 0x30, 0xFF]
+        );
+    }
+
+    #[test]
+    fn test_png_signature() {
+        let mut png = Vec::new();
+        RgbaImage::new(3, 2).write_png(&mut png).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1A\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+    }
+
+    #[test]
+    fn test_images_go_to_sink() {
+        let mut ne = sample_module();
+        let mut sink = MemorySink::default();
+        // icon of the sample isn't a DIB
+        assert!(ne.extract_images_to(&mut sink).unwrap().is_empty());
+        assert!(sink.files.is_empty());
+
+        let mut icon = info_header(1, 2, 4);
+        icon.extend_from_slice(&[0; 16 * 4 + 8]);
+        ne.resource_table.resource_types[0].resources[0].data = Some(icon);
+        let images = ne.extract_images_to(&mut sink).unwrap();
+        assert_eq!(images.len(), 1);
+        let mut png = Vec::new();
+        images[0].image.write_png(&mut png).unwrap();
+        assert_eq!(sink.files.keys().collect::<Vec<_>>(), ["icon_1.png"]);
+        assert_eq!(sink.files["icon_1.png"], png);
+    }
+}
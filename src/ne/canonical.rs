@@ -0,0 +1,415 @@
+use std::fmt::{self, Write};
+
+use crate::ne::entry_table::SegmentEntry;
+use crate::ne::segment_relocations::{RelocationEntry, RelocationTarget};
+use crate::ne::NeExecutable;
+use crate::util::checksum::crc32;
+use crate::util::endian::{Lu16, Lu32};
+
+///
+/// Version of `canonical_dump` format. Any change of the output for
+/// the same module, even of spacing, comes with a new version.
+///
+pub const CANONICAL_DUMP_VERSION: u32 = 1;
+
+///
+/// Fixed text of one field value: numbers are hexadecimal with
+/// the width of their type on file
+///
+trait Canonical {
+    fn canonical(&self) -> String;
+}
+
+impl Canonical for u8 {
+    fn canonical(&self) -> String {
+        format!("0x{:02X}", self)
+    }
+}
+
+impl Canonical for u16 {
+    fn canonical(&self) -> String {
+        format!("0x{:04X}", self)
+    }
+}
+
+impl Canonical for u32 {
+    fn canonical(&self) -> String {
+        format!("0x{:08X}", self)
+    }
+}
+
+impl Canonical for u64 {
+    fn canonical(&self) -> String {
+        format!("0x{:016X}", self)
+    }
+}
+
+impl Canonical for bool {
+    fn canonical(&self) -> String {
+        (*self as u8).to_string()
+    }
+}
+
+impl Canonical for Lu16 {
+    fn canonical(&self) -> String {
+        self.value().canonical()
+    }
+}
+
+impl Canonical for Lu32 {
+    fn canonical(&self) -> String {
+        self.value().canonical()
+    }
+}
+
+impl<T: Canonical, const N: usize> Canonical for [T; N] {
+    fn canonical(&self) -> String {
+        let items: Vec<String> = self.iter().map(Canonical::canonical).collect();
+        format!("[{}]", items.join(" "))
+    }
+}
+
+///
+/// Names and signatures as quoted bytes, non-ASCII ones escaped
+///
+fn quoted(bytes: &[u8]) -> String {
+    format!("\"{}\"", bytes.escape_ascii())
+}
+
+///
+/// Length and CRC-32 of data instead of its bytes
+///
+fn digest(data: &[u8]) -> String {
+    format!("bytes 0x{:08X} crc32 0x{:08X}", data.len(), crc32(data))
+}
+
+struct Dump {
+    text: String,
+}
+
+impl Dump {
+    fn line(&mut self, path: fmt::Arguments, value: &str) {
+        writeln!(self.text, "{} = {}", path, value).expect("String never fails to write");
+    }
+}
+
+macro_rules! fields {
+    ($dump:expr, $prefix:expr, $structure:expr, $($field:ident),+ $(,)?) => {
+        $(
+            $dump.line(
+                format_args!("{}.{}", $prefix, stringify!($field)),
+                &$structure.$field.canonical(),
+            );
+        )+
+    };
+}
+
+fn relocation(entry: &RelocationEntry) -> String {
+    let target = match &entry.target {
+        RelocationTarget::Internal(t) => format!(
+            "internal segment {} movable {} offset {}",
+            t.segment.canonical(),
+            t.is_movable.canonical(),
+            t.offset_or_ordinal.canonical()
+        ),
+        RelocationTarget::ImportByOrdinal(t) => format!(
+            "ordinal module {} ordinal {}",
+            t.module_index.canonical(),
+            t.ordinal.canonical()
+        ),
+        RelocationTarget::ImportByName(t) => format!(
+            "name module {} name_offset {}",
+            t.module_index.canonical(),
+            t.name_offset.canonical()
+        ),
+        RelocationTarget::OsFixup(t) => format!("os_fixup type {}", t.fixup_type.canonical()),
+    };
+    format!(
+        "address_type {} reloc_type {} additive {} offset {} {}",
+        entry.address_type.canonical(),
+        entry.reloc_type.canonical(),
+        entry.is_additive.canonical(),
+        entry.segment_offset.canonical(),
+        target
+    )
+}
+
+impl NeExecutable {
+    ///
+    /// Exhaustive text of every field of every structure read, for snapshot
+    /// tests and byte-for-byte comparison of parses across crate versions.
+    /// One `path = value` line per field, in file order of tables; numbers
+    /// in hexadecimal of their width on file, names as quoted bytes,
+    /// data as length and CRC-32. The first line gives
+    /// `CANONICAL_DUMP_VERSION`. For reading, see the `{:#}` report.
+    ///
+    pub fn canonical_dump(&self) -> String {
+        let mut dump = Dump {
+            text: String::new(),
+        };
+        dump.line(
+            format_args!("canonical_dump_version"),
+            &CANONICAL_DUMP_VERSION.to_string(),
+        );
+
+        let dos = &self.dos_header;
+        fields!(
+            dump, "dos", dos, magic, cblp, cp, crlc, cparhdr, minalloc, maxalloc, ss, sp, csum, ip,
+            cs, lfarlc, ovno, res, oemid, oeminfo, res2, lfanew,
+        );
+        dump.line(format_args!("dos.stub"), &digest(&self.dos_stub));
+
+        let ne = &self.ne_header;
+        dump.line(format_args!("ne.magic"), &quoted(&ne.magic));
+        fields!(
+            dump,
+            "ne",
+            ne,
+            major_linker_version,
+            minor_linker_version,
+            entry_table_offset,
+            entry_table_length,
+            file_load_crc,
+            flags,
+            auto_data_segment_index,
+            init_heap_size,
+            init_stack_size,
+            entry_point,
+            init_stack,
+            segment_count,
+            module_references,
+            non_resident_names_size,
+            segment_table_offset,
+            resource_table_offset,
+            resident_names_table_offset,
+            module_reference_table_offset,
+            import_name_table_offset,
+            non_resident_names_table_offset,
+            movable_entry_point_count,
+            file_alignment_shift_count,
+            resource_table_entries,
+            target_os,
+            os2_exe_flags,
+            return_thunk_offset,
+            segment_reference_thunk_offset,
+            min_code_swap,
+            expected_win_ver,
+        );
+
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let prefix = format!("segment[{}]", i + 1);
+            fields!(
+                dump,
+                prefix,
+                segment.header,
+                data_offset_shifted,
+                data_length,
+                flags,
+                min_alloc,
+            );
+            fields!(dump, prefix, segment, shift_count);
+            let data = segment.data.as_deref().map_or("none".to_string(), digest);
+            dump.line(format_args!("{}.data", prefix), &data);
+            if let Some(table) = self.relocation_tables_per_segment.get(i) {
+                for (j, entry) in table.entries.iter().enumerate() {
+                    dump.line(
+                        format_args!("{}.relocation[{}]", prefix, j),
+                        &relocation(entry),
+                    );
+                }
+            }
+        }
+
+        let resources = &self.resource_table;
+        fields!(dump, "resources", resources.header, alignment_shift_count);
+        for (i, resource_type) in resources.resource_types.iter().enumerate() {
+            let prefix = format!("resource_type[{}]", i);
+            fields!(
+                dump,
+                prefix,
+                resource_type.header,
+                type_id,
+                num_resources,
+                res
+            );
+            if let Some(name) = &resource_type.name {
+                dump.line(format_args!("{}.name", prefix), &quoted(name.as_bytes()));
+            }
+            for (j, resource) in resource_type.resources.iter().enumerate() {
+                let prefix = format!("{}.resource[{}]", prefix, j);
+                fields!(
+                    dump,
+                    prefix,
+                    resource.header,
+                    data_offset_shifted,
+                    data_length,
+                    flags,
+                    resource_id,
+                    res,
+                );
+                if let Some(name) = &resource.name {
+                    dump.line(format_args!("{}.name", prefix), &quoted(name.as_bytes()));
+                }
+                let data = resource.data.as_deref().map_or("none".to_string(), digest);
+                dump.line(format_args!("{}.data", prefix), &data);
+            }
+        }
+
+        for (i, entry) in self.resident_name_table.entries.iter().enumerate() {
+            dump.line(
+                format_args!("resident_name[{}]", i),
+                &format!("{} index {}", quoted(&entry.name), entry.index.canonical()),
+            );
+        }
+        for (i, entry) in self.module_reference_table.entries.iter().enumerate() {
+            dump.line(
+                format_args!("module_reference[{}]", i + 1),
+                &format!(
+                    "offset {} name {}",
+                    entry.header.offset.canonical(),
+                    quoted(&entry.name)
+                ),
+            );
+        }
+        dump.line(
+            format_args!("imported_names"),
+            &quoted(&self.imported_name_table.data),
+        );
+
+        for (i, entry) in self.entry_table.entries.iter().enumerate() {
+            let value = match entry {
+                SegmentEntry::Unused => "unused".to_string(),
+                SegmentEntry::Fixed(e) => format!(
+                    "fixed segment {} flags {} offset {}",
+                    e.segment.canonical(),
+                    e.flags.canonical(),
+                    e.offset.canonical()
+                ),
+                SegmentEntry::Moveable(e) => format!(
+                    "moveable segment {} flags {} offset {} magic {}",
+                    e.segment.canonical(),
+                    e.flags.canonical(),
+                    e.offset.canonical(),
+                    e.magic.canonical()
+                ),
+            };
+            dump.line(format_args!("entry[{}]", i + 1), &value);
+        }
+        for (i, entry) in self.nonresident_name_table.entries.iter().enumerate() {
+            dump.line(
+                format_args!("nonresident_name[{}]", i),
+                &format!("{} index {}", quoted(&entry.name), entry.index.canonical()),
+            );
+        }
+
+        if let Some(overlay) = &self.overlay {
+            dump.line(
+                format_args!("overlay"),
+                &format!(
+                    "offset {} {}",
+                    overlay.offset.canonical(),
+                    digest(&overlay.data)
+                ),
+            );
+        }
+        dump.line(format_args!("codepage"), &self.codepage.to_string());
+        for (i, warning) in self.warnings.iter().enumerate() {
+            dump.line(format_args!("warning[{}]", i), &warning.to_string());
+        }
+        dump.text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::entry_table::{EntryTable, FixedSegmentEntry};
+    use crate::ne::imported_name_table::ImportedNameTable;
+    use crate::ne::module_reference_table::ModuleReferenceTable;
+    use crate::ne::nonresident_name_table::NonresidentNameTable;
+    use crate::ne::resident_name_table::{ResidentNameEntry, ResidentNameTable};
+    use crate::ne::resource_table::NeResourceTable;
+    use crate::ne::segment_relocations::{OsFixup, RelocationTable};
+    use crate::ne::segment_table::{NeSegment, NeSegmentHeader};
+
+    fn module() -> NeExecutable {
+        let mut ne_header: crate::ne::header::NeHeader = bytemuck::Zeroable::zeroed();
+        ne_header.magic = *b"NE";
+        ne_header.segment_count = 1.into();
+        NeExecutable {
+            dos_header: Box::new(bytemuck::Zeroable::zeroed()),
+            dos_stub: b"MZ".to_vec(),
+            ne_header: Box::new(ne_header),
+            segment_entries: vec![NeSegment {
+                header: NeSegmentHeader {
+                    data_offset_shifted: 0x20,
+                    data_length: 4,
+                    flags: 0x0D50,
+                    min_alloc: 4,
+                },
+                shift_count: 4,
+                data: Some(vec![0x90, 0x90, 0xCB, 0x00]),
+            }],
+            resource_table: NeResourceTable::default(),
+            resident_name_table: ResidentNameTable {
+                entries: vec![ResidentNameEntry {
+                    name: b"DEMO".to_vec(),
+                    index: 0,
+                }],
+            },
+            module_reference_table: ModuleReferenceTable {
+                entries: Vec::new(),
+            },
+            imported_name_table: ImportedNameTable::default(),
+            entry_table: EntryTable {
+                entries: vec![SegmentEntry::Fixed(FixedSegmentEntry {
+                    segment: 1,
+                    flags: 3,
+                    offset: 2,
+                })],
+            },
+            nonresident_name_table: NonresidentNameTable {
+                entries: Vec::new(),
+            },
+            relocation_tables_per_segment: vec![RelocationTable {
+                entries: vec![RelocationEntry {
+                    address_type: 3,
+                    reloc_type: 3,
+                    is_additive: true,
+                    segment_offset: 1,
+                    target: RelocationTarget::OsFixup(OsFixup { fixup_type: 1 }),
+                }],
+            }],
+            overlay: None,
+            codepage: Default::default(),
+            warnings: Vec::new(),
+            views: Default::default(),
+        }
+    }
+
+    #[test]
+    fn dumps_every_field_in_fixed_form() {
+        let ne = module();
+        let dump = ne.canonical_dump();
+        assert!(dump.starts_with("canonical_dump_version = 1\ndos.magic = 0x0000\n"));
+        for line in [
+            "dos.res = [0x0000 0x0000 0x0000 0x0000]",
+            "dos.stub = bytes 0x00000002 crc32 0x8FB09B5D",
+            "ne.magic = \"NE\"",
+            "ne.expected_win_ver = [0x00 0x00]",
+            "segment[1].flags = 0x0D50",
+            "segment[1].relocation[0] = address_type 0x03 reloc_type 0x03 additive 1 \
+             offset 0x0001 os_fixup type 0x0001",
+            "resident_name[0] = \"DEMO\" index 0x0000",
+            "imported_names = \"\"",
+            "entry[1] = fixed segment 0x01 flags 0x03 offset 0x0002",
+        ] {
+            assert!(dump.lines().any(|l| l == line), "missing `{}`", line);
+        }
+        assert_eq!(dump, ne.clone().canonical_dump());
+
+        let mut changed = module();
+        changed.segment_entries[0].data.as_mut().unwrap()[0] = 0xCC;
+        assert_ne!(dump, changed.canonical_dump());
+    }
+}
@@ -0,0 +1,303 @@
+use std::fmt;
+
+use crate::ne::resource_table::{RT_STRING, RT_VERSION};
+use crate::ne::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// Pages told apart by letters of their upper halves
+const CANDIDATES: [Codepage; 4] = [
+    Codepage::Windows1251,
+    Codepage::Windows1252,
+    Codepage::Oem866,
+    Codepage::Oem437,
+];
+
+/// Letters giving full weight to the text evidence
+const ENOUGH_LETTERS: u32 = 20;
+
+/// Confidence of code page named by the version resource alone
+const TRANSLATION_CONFIDENCE: u8 = 80;
+
+/// Confidence of code page taken from the language of the version resource
+const LANGUAGE_CONFIDENCE: u8 = 60;
+
+/// Less confident guesses leave the default page to the parser
+const ADOPTED_CONFIDENCE: u8 = 50;
+
+///
+/// Most likely code page of strings of the module, with confidence
+/// from 0 (nothing above 0x7F, just the default) to 100
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodepageGuess {
+    pub codepage: Codepage,
+    pub confidence: u8,
+    pub evidence: Vec<String>,
+}
+
+impl fmt::Display for CodepageGuess {
+    ///
+    /// `{}` gives the page and confidence, `{:#}` also the evidence
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}% confidence)", self.codepage, self.confidence)?;
+        if f.alternate() {
+            for evidence in &self.evidence {
+                write!(f, "\n\t{}", evidence)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_cyrillic(c: char) -> bool {
+    ('\u{0400}'..='\u{04FF}').contains(&c)
+}
+
+fn is_latin(c: char) -> bool {
+    c.is_alphabetic() && ('\u{00C0}'..='\u{024F}').contains(&c)
+}
+
+///
+/// Upper-half bytes which read as letters of the page's script:
+/// Cyrillic words are runs of such bytes, while accented Latin
+/// letters stand among ASCII ones
+///
+fn letter_score(codepage: Codepage, strings: &[Vec<u8>]) -> u32 {
+    let cyrillic = matches!(codepage, Codepage::Windows1251 | Codepage::Oem866);
+    let is_letter = |b: u8| {
+        let c = codepage.decode_byte(b);
+        if cyrillic {
+            is_cyrillic(c)
+        } else {
+            is_latin(c)
+        }
+    };
+    let fits_neighbour = |b: u8| {
+        if cyrillic {
+            b >= 0x80 && is_letter(b)
+        } else {
+            b.is_ascii_alphabetic()
+        }
+    };
+    let mut score = 0;
+    for s in strings {
+        for (i, &b) in s.iter().enumerate() {
+            if b < 0x80 || !is_letter(b) {
+                continue;
+            }
+            let before = i.checked_sub(1).map(|i| s[i]);
+            let after = s.get(i + 1).copied();
+            if before.into_iter().chain(after).any(fits_neighbour) {
+                score += 1;
+            }
+        }
+    }
+    score
+}
+
+fn page_by_number(number: u16) -> Option<Codepage> {
+    match number {
+        1251 => Some(Codepage::Windows1251),
+        1252 => Some(Codepage::Windows1252),
+        866 => Some(Codepage::Oem866),
+        437 => Some(Codepage::Oem437),
+        _ => None,
+    }
+}
+
+///
+/// ANSI page of languages written in Cyrillic: Russian, Ukrainian,
+/// Belarusian, Bulgarian and Macedonian
+///
+fn page_by_language(language: u16) -> Option<Codepage> {
+    match language & 0x03FF {
+        0x19 | 0x22 | 0x23 | 0x02 | 0x2F => Some(Codepage::Windows1251),
+        _ => None,
+    }
+}
+
+///
+/// Language and code page pairs of 16-bit version info: values of
+/// `Translation` and keys of `StringFileInfo` tables (`041904E3`)
+///
+fn translations(node: &[u8], out: &mut Vec<(u16, u16)>) {
+    let word = |at: usize| {
+        node.get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    };
+    let (Some(length), Some(value_length)) = (word(0), word(2)) else {
+        return;
+    };
+    let node = &node[..(length as usize).min(node.len())];
+    let Some(key_length) = node.iter().skip(4).position(|&b| b == 0) else {
+        return;
+    };
+    let key = &node[4..4 + key_length];
+    let value_start = (4 + key_length + 1).next_multiple_of(4);
+    let value_end = (value_start + value_length as usize).min(node.len());
+    let value = node.get(value_start..value_end).unwrap_or_default();
+
+    if key == b"Translation" {
+        for pair in value.chunks_exact(4) {
+            out.push((
+                u16::from_le_bytes([pair[0], pair[1]]),
+                u16::from_le_bytes([pair[2], pair[3]]),
+            ));
+        }
+    } else if key.len() == 8 && key.iter().all(u8::is_ascii_hexdigit) {
+        let hex = std::str::from_utf8(key).unwrap_or_default();
+        if let Ok(pair) = u32::from_str_radix(hex, 16) {
+            out.push(((pair >> 16) as u16, pair as u16));
+        }
+    }
+
+    let mut child = value_end.next_multiple_of(4);
+    while child + 4 <= node.len() {
+        let child_length = word(child).unwrap_or_default() as usize;
+        if child_length < 4 {
+            break;
+        }
+        translations(&node[child..], out);
+        child = (child + child_length).next_multiple_of(4);
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Guesses code page of strings from the translation block of
+    /// the version resource and from upper-half letters of string
+    /// tables and module description. Text overrules the version
+    /// resource when they disagree: translated programs often keep
+    /// the version info of the original.
+    /// `read_with` takes the guess when `ParseOptions::codepage` isn't set.
+    ///
+    pub fn guess_codepage(&self) -> CodepageGuess {
+        let mut evidence = Vec::new();
+
+        let mut pairs = Vec::new();
+        for resource in self.resource_table.resources_of_type(RT_VERSION) {
+            if let Some(data) = &resource.data {
+                translations(data, &mut pairs);
+            }
+        }
+        let by_version = pairs.iter().find_map(|&(language, number)| {
+            let (page, confidence) = match page_by_number(number) {
+                Some(page) => (page, TRANSLATION_CONFIDENCE),
+                None => (page_by_language(language)?, LANGUAGE_CONFIDENCE),
+            };
+            evidence.push(format!(
+                "version resource: language {:#06X}, code page {}",
+                language, number
+            ));
+            Some((page, confidence))
+        });
+
+        let mut strings: Vec<Vec<u8>> = self
+            .nonresident_name_table
+            .entries
+            .first()
+            .map(|e| e.name.clone())
+            .into_iter()
+            .collect();
+        for resource in self.resource_table.resources_of_type(RT_STRING) {
+            let Some(mut data) = resource.data.as_deref() else {
+                continue;
+            };
+            while let Some((&length, rest)) = data.split_first() {
+                let length = (length as usize).min(rest.len());
+                strings.push(rest[..length].to_vec());
+                data = &rest[length..];
+            }
+        }
+        let mut scores: Vec<(Codepage, u32)> = CANDIDATES
+            .iter()
+            .map(|&page| (page, letter_score(page, &strings)))
+            .collect();
+        scores.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        let total: u32 = scores.iter().map(|&(_, score)| score).sum();
+        let by_text = match scores[0] {
+            (_, 0) => None,
+            (page, best) => {
+                evidence.push(format!(
+                    "{} letters of strings read as {}, {} as {}",
+                    best, page, scores[1].1, scores[1].0
+                ));
+                let share = best * 100 / total;
+                Some((
+                    page,
+                    (share * best.min(ENOUGH_LETTERS) / ENOUGH_LETTERS) as u8,
+                ))
+            }
+        };
+
+        let (codepage, confidence) = match (by_version, by_text) {
+            (Some((version, v)), Some((text, t))) if version == text => {
+                // independent evidence: both are wrong less often
+                (
+                    text,
+                    100 - ((100 - v as u32) * (100 - t as u32) / 100) as u8,
+                )
+            }
+            (Some((version, v)), Some((text, t))) => {
+                evidence.push(format!(
+                    "strings disagree with version resource ({})",
+                    version
+                ));
+                if t >= 50 {
+                    (text, t / 2 + 25)
+                } else {
+                    (version, v / 2)
+                }
+            }
+            (Some(guess), None) | (None, Some(guess)) => guess,
+            (None, None) => {
+                evidence.push("no letters above 0x7F".to_string());
+                (Codepage::default(), 0)
+            }
+        };
+        CodepageGuess {
+            codepage,
+            confidence,
+            evidence,
+        }
+    }
+
+    ///
+    /// Page which parser takes when `ParseOptions::codepage` isn't set
+    ///
+    pub(crate) fn guessed_codepage(&self) -> Codepage {
+        let guess = self.guess_codepage();
+        if guess.confidence >= ADOPTED_CONFIDENCE {
+            guess.codepage
+        } else {
+            Codepage::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_cyrillic_from_latin() {
+        // "Привет, мир" in cp1251 and "Größe" in cp1252
+        let russian = vec![b"\xCF\xF0\xE8\xE2\xE5\xF2, \xEC\xE8\xF0".to_vec()];
+        let german = vec![b"Gr\xF6\xDFe f\xFCr".to_vec()];
+        assert_eq!(letter_score(Codepage::Windows1251, &russian), 9);
+        assert_eq!(letter_score(Codepage::Windows1252, &russian), 0);
+        assert!(letter_score(Codepage::Oem866, &russian) < 9);
+        assert_eq!(letter_score(Codepage::Windows1252, &german), 3);
+        assert_eq!(letter_score(Codepage::Windows1251, &german), 2);
+
+        // VS_VERSION_INFO / VarFileInfo / Translation = 0419, 1251
+        let mut translation = vec![0x14, 0, 4, 0];
+        translation.extend_from_slice(b"Translation\0");
+        translation.extend_from_slice(&[0x19, 0x04, 0xE3, 0x04]);
+        let mut pairs = Vec::new();
+        translations(&translation, &mut pairs);
+        assert_eq!(pairs, [(0x0419, 1251)]);
+        assert_eq!(page_by_language(0x0422), Some(Codepage::Windows1251));
+    }
+}
@@ -0,0 +1,490 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::ne::addr::Seg16;
+use crate::ne::fixups::{ADDR_FAR, ADDR_FAR48, ADDR_LOBYTE, ADDR_OFFSET32};
+use crate::ne::owned::NeOwned;
+use crate::ne::raw::NeTable;
+use crate::ne::resource_table::{type_name, ResourceId};
+use crate::ne::NeExecutable;
+
+/// Fields of DOS header: offset, size and name
+const DOS_HEADER_FIELDS: &[(u64, u64, &str)] = &[
+    (0x00, 2, "magic"),
+    (0x02, 2, "cblp"),
+    (0x04, 2, "cp"),
+    (0x06, 2, "crlc"),
+    (0x08, 2, "cparhdr"),
+    (0x0A, 2, "minalloc"),
+    (0x0C, 2, "maxalloc"),
+    (0x0E, 2, "ss"),
+    (0x10, 2, "sp"),
+    (0x12, 2, "csum"),
+    (0x14, 2, "ip"),
+    (0x16, 2, "cs"),
+    (0x18, 2, "lfarlc"),
+    (0x1A, 2, "ovno"),
+    (0x1C, 8, "res"),
+    (0x24, 2, "oemid"),
+    (0x26, 2, "oeminfo"),
+    (0x28, 20, "res2"),
+    (0x3C, 4, "lfanew"),
+];
+
+/// Fields of NE header: offset, size and name
+const NE_HEADER_FIELDS: &[(u64, u64, &str)] = &[
+    (0x00, 2, "magic"),
+    (0x02, 1, "major_linker_version"),
+    (0x03, 1, "minor_linker_version"),
+    (0x04, 2, "entry_table_offset"),
+    (0x06, 2, "entry_table_length"),
+    (0x08, 4, "file_load_crc"),
+    (0x0C, 2, "flags"),
+    (0x0E, 2, "auto_data_segment_index"),
+    (0x10, 2, "init_heap_size"),
+    (0x12, 2, "init_stack_size"),
+    (0x14, 4, "entry_point"),
+    (0x18, 4, "init_stack"),
+    (0x1C, 2, "segment_count"),
+    (0x1E, 2, "module_references"),
+    (0x20, 2, "non_resident_names_size"),
+    (0x22, 2, "segment_table_offset"),
+    (0x24, 2, "resource_table_offset"),
+    (0x26, 2, "resident_names_table_offset"),
+    (0x28, 2, "module_reference_table_offset"),
+    (0x2A, 2, "import_name_table_offset"),
+    (0x2C, 4, "non_resident_names_table_offset"),
+    (0x30, 2, "movable_entry_point_count"),
+    (0x32, 2, "file_alignment_shift_count"),
+    (0x34, 2, "resource_table_entries"),
+    (0x36, 1, "target_os"),
+    (0x37, 1, "os2_exe_flags"),
+    (0x38, 2, "return_thunk_offset"),
+    (0x3A, 2, "segment_reference_thunk_offset"),
+    (0x3C, 2, "min_code_swap"),
+    (0x3E, 2, "expected_win_ver"),
+];
+
+/// Words of segment table records
+const SEGMENT_FIELDS: [&str; 4] = ["data_offset_shifted", "data_length", "flags", "min_alloc"];
+/// Words of resource type records
+const RESOURCE_TYPE_FIELDS: [&str; 4] = ["type_id", "num_resources", "reserved", "reserved"];
+/// Words of resource records
+const RESOURCE_FIELDS: [&str; 6] = [
+    "data_offset_shifted",
+    "data_length",
+    "flags",
+    "resource_id",
+    "reserved",
+    "reserved",
+];
+
+///
+/// Structure owning bytes of file
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Structure {
+    Table(NeTable),
+    /// Real-mode program between DOS and NE headers
+    DosStub,
+    SegmentData(Seg16),
+    /// Bytes after data and relocations of segment up to the next structure
+    SegmentPadding(Seg16),
+    Resource {
+        type_id: ResourceId,
+        id: ResourceId,
+    },
+    Overlay,
+}
+
+impl fmt::Display for Structure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Structure::Table(table) => write!(f, "{}", table),
+            Structure::DosStub => write!(f, "DOS stub"),
+            Structure::SegmentData(segment) => write!(f, "data of segment #{}", segment),
+            Structure::SegmentPadding(segment) => write!(f, "padding of segment #{}", segment),
+            Structure::Resource { type_id, id } => {
+                write!(f, "resource {} of type {}", id, type_name(type_id))
+            }
+            Structure::Overlay => write!(f, "overlay"),
+        }
+    }
+}
+
+///
+/// Bytes of file owned by one structure
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoveredRange {
+    pub structure: Structure,
+    pub range: Range<u64>,
+}
+
+impl fmt::Display for CoveredRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:#08X}-{:#08X} {}",
+            self.range.start, self.range.end, self.structure
+        )
+    }
+}
+
+///
+/// What `identify_offset` tells about one byte of file
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetOwner {
+    pub structure: Structure,
+    /// Bytes of the whole structure
+    pub range: Range<u64>,
+    /// Field or entry of the structure holding the byte,
+    /// e.g. `lfanew` or `segment #2 flags`
+    pub field: Option<String>,
+    /// Offset of the byte from the start of the structure
+    pub relative: u64,
+}
+
+impl fmt::Display for OffsetOwner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} +0x{:X}", self.structure, self.relative)?;
+        if let Some(field) = &self.field {
+            write!(f, ": {}", field)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Name of field of `fields` holding byte `relative`
+///
+fn field_of(fields: &[(u64, u64, &str)], relative: u64) -> Option<String> {
+    fields
+        .iter()
+        .find(|(offset, size, _)| (*offset..offset + size).contains(&relative))
+        .map(|(_, _, name)| name.to_string())
+}
+
+///
+/// Part of name table entry (length byte, name, ordinal) holding byte `relative`
+///
+fn name_entry_of(names: &[(&[u8], u16)], relative: u64) -> Option<String> {
+    let mut start = 0;
+    for (name, index) in names {
+        let end = start + 1 + name.len() as u64 + 2;
+        if relative < end {
+            let part = match relative - start {
+                0 => "length",
+                n if n <= name.len() as u64 => "text",
+                _ => "ordinal",
+            };
+            return Some(format!(
+                "name \"{}\" (@{}) {}",
+                String::from_utf8_lossy(name),
+                index,
+                part
+            ));
+        }
+        start = end;
+    }
+    (relative == start).then(|| "end of table".to_string())
+}
+
+///
+/// Bytes patched by loader for value of `address_type`
+///
+fn fixup_width(address_type: u8) -> u64 {
+    match address_type {
+        ADDR_LOBYTE => 1,
+        ADDR_FAR | ADDR_OFFSET32 => 4,
+        ADDR_FAR48 => 6,
+        _ => 2,
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Every structure of the file by offset: headers and tables,
+    /// DOS stub, data, relocations and padding of segments, resources
+    /// and overlay. Bytes no structure owns are missing from the map.
+    ///
+    pub(crate) fn build_coverage_map(&self) -> Vec<CoveredRange> {
+        let mut map: Vec<CoveredRange> = self
+            .table_ranges()
+            .into_iter()
+            .map(|t| CoveredRange {
+                structure: Structure::Table(t.table),
+                range: t.range(),
+            })
+            .collect();
+
+        let lfanew = self.dos_header.lfanew.value() as u64;
+        let dos_header_size = std::mem::size_of::<crate::mz::DosHeader>() as u64;
+        if !self.dos_stub.is_empty() && lfanew > dos_header_size {
+            map.push(CoveredRange {
+                structure: Structure::DosStub,
+                range: dos_header_size..lfanew,
+            });
+        }
+        for layout in self.segment_layouts() {
+            if let Some(data) = layout.data {
+                map.push(CoveredRange {
+                    structure: Structure::SegmentData(layout.segment),
+                    range: data,
+                });
+            }
+            if !layout.padding.is_empty() {
+                map.push(CoveredRange {
+                    structure: Structure::SegmentPadding(layout.segment),
+                    range: layout.padding,
+                });
+            }
+        }
+        let shift_count = self.resource_table.header.alignment_shift_count;
+        for resource_type in &self.resource_table.resource_types {
+            for resource in &resource_type.resources {
+                if resource.header.data_offset_shifted == 0 {
+                    continue;
+                }
+                let start = resource.data_offset(shift_count);
+                map.push(CoveredRange {
+                    structure: Structure::Resource {
+                        type_id: resource_type.id(),
+                        id: resource.id(),
+                    },
+                    range: start..start + resource.data_length(shift_count),
+                });
+            }
+        }
+        if let Some(overlay) = &self.overlay {
+            map.push(CoveredRange {
+                structure: Structure::Overlay,
+                range: overlay.offset..overlay.offset + overlay.data.len() as u64,
+            });
+        }
+        map.sort_by_key(|c| (c.range.start, c.range.end));
+        map
+    }
+
+    fn resource_table_field(&self, relative: u64) -> Option<String> {
+        if relative < 2 {
+            return Some("alignment_shift_count".to_string());
+        }
+        let mut start = 2;
+        for resource_type in &self.resource_table.resource_types {
+            let type_name = type_name(&resource_type.id());
+            if relative < start + 8 {
+                let word = ((relative - start) / 2) as usize;
+                return Some(format!("type {} {}", type_name, RESOURCE_TYPE_FIELDS[word]));
+            }
+            start += 8;
+            for resource in &resource_type.resources {
+                if relative < start + 12 {
+                    let word = ((relative - start) / 2) as usize;
+                    return Some(format!(
+                        "type {} resource {} {}",
+                        type_name,
+                        resource.id(),
+                        RESOURCE_FIELDS[word]
+                    ));
+                }
+                start += 12;
+            }
+        }
+        if relative < start + 2 {
+            return Some("end of types".to_string());
+        }
+        Some("resource names".to_string())
+    }
+
+    fn segment_data_field(&self, segment: Seg16, relative: u64) -> Option<String> {
+        let places = self.fixup_places(segment.0).ok()?;
+        places
+            .iter()
+            .find(|p| {
+                let start = p.offset as u64;
+                (start..start + fixup_width(p.address_type)).contains(&relative)
+            })
+            .map(|p| format!("fixup at 0x{:04X}", p.offset))
+    }
+
+    ///
+    /// Field or entry of `structure` holding its byte `relative`
+    /// (which must lie inside the structure). Entry table is walked
+    /// by `entry_bytes`, as its bundles are not kept after parsing.
+    ///
+    fn field_at(
+        &self,
+        structure: &Structure,
+        relative: u64,
+        entry_bytes: Option<&[u8]>,
+    ) -> Option<String> {
+        match structure {
+            Structure::Table(NeTable::DosHeader) => field_of(DOS_HEADER_FIELDS, relative),
+            Structure::Table(NeTable::NeHeader) => field_of(NE_HEADER_FIELDS, relative),
+            Structure::Table(NeTable::SegmentTable) => Some(format!(
+                "segment #{} {}",
+                relative / 8 + 1,
+                SEGMENT_FIELDS[(relative % 8 / 2) as usize]
+            )),
+            Structure::Table(NeTable::ResourceTable) => self.resource_table_field(relative),
+            Structure::Table(NeTable::ResidentNames) => {
+                let names: Vec<_> = self
+                    .resident_name_table
+                    .entries
+                    .iter()
+                    .map(|e| (e.name.as_slice(), e.index))
+                    .collect();
+                name_entry_of(&names, relative)
+            }
+            Structure::Table(NeTable::NonresidentNames) => {
+                let names: Vec<_> = self
+                    .nonresident_name_table
+                    .entries
+                    .iter()
+                    .map(|e| (e.name.as_slice(), e.index))
+                    .collect();
+                name_entry_of(&names, relative)
+            }
+            Structure::Table(NeTable::ModuleReferences) => {
+                let index = (relative / 2) as usize;
+                let entry = self.module_reference_table.entries.get(index)?;
+                Some(format!(
+                    "module #{} ({}) name offset",
+                    index + 1,
+                    String::from_utf8_lossy(&entry.name)
+                ))
+            }
+            Structure::Table(NeTable::ImportedNames) => {
+                let data = &self.imported_name_table.data;
+                let mut start = 0;
+                while start < data.len() {
+                    let end = start + 1 + data[start] as usize;
+                    if relative < end as u64 {
+                        let name = data.get(start + 1..end.min(data.len()))?;
+                        return Some(format!(
+                            "name \"{}\" at 0x{:04X}",
+                            String::from_utf8_lossy(name),
+                            start
+                        ));
+                    }
+                    start = end;
+                }
+                None
+            }
+            Structure::Table(NeTable::EntryTable) => entry_table_field(entry_bytes?, relative),
+            Structure::Table(NeTable::Relocations(_)) => Some(match relative {
+                0 | 1 => "record count".to_string(),
+                n => format!("record #{}", (n - 2) / 8),
+            }),
+            Structure::SegmentData(segment) => self.segment_data_field(*segment, relative),
+            _ => None,
+        }
+    }
+}
+
+///
+/// Bundle header or entry (by ordinal) of raw entry table holding byte `relative`
+///
+fn entry_table_field(bytes: &[u8], relative: u64) -> Option<String> {
+    let relative = relative as usize;
+    let mut start = 0;
+    let mut ordinal = 1;
+    while start < bytes.len() {
+        let count = bytes[start] as usize;
+        if count == 0 {
+            return (relative == start).then(|| "end of table".to_string());
+        }
+        if relative < start + 2 {
+            return Some(format!("bundle of {} entries from @{}", count, ordinal));
+        }
+        let size = match *bytes.get(start + 1)? {
+            0 => 0,
+            0xFF => 6,
+            _ => 3,
+        };
+        let end = start + 2 + count * size;
+        if relative < end {
+            return Some(format!(
+                "entry @{}",
+                ordinal + (relative - start - 2) / size
+            ));
+        }
+        ordinal += count;
+        start = end;
+    }
+    None
+}
+
+impl NeOwned {
+    ///
+    /// Structure owning byte at file `offset`, its field or entry and
+    /// offset of the byte inside it. If structures overlap, the smallest
+    /// one wins. `None` for bytes no structure owns.
+    ///
+    pub fn identify_offset(&self, offset: u64) -> Option<OffsetOwner> {
+        let covered = self
+            .coverage_map()
+            .iter()
+            .filter(|c| c.range.contains(&offset))
+            .min_by_key(|c| c.range.end - c.range.start)?;
+        let relative = offset - covered.range.start;
+        let field = self.field_at(
+            &covered.structure,
+            relative,
+            self.raw_bytes(NeTable::EntryTable),
+        );
+        Some(OffsetOwner {
+            structure: covered.structure.clone(),
+            range: covered.range.clone(),
+            field,
+            relative,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_fields_and_entries() {
+        assert_eq!(
+            field_of(DOS_HEADER_FIELDS, 0x3E),
+            Some("lfanew".to_string())
+        );
+        assert_eq!(
+            field_of(NE_HEADER_FIELDS, 0x2D),
+            Some("non_resident_names_table_offset".to_string())
+        );
+        let names: &[(&[u8], u16)] = &[(b"DEMO", 0), (b"WEP", 1)];
+        assert_eq!(
+            name_entry_of(names, 7),
+            Some("name \"WEP\" (@1) length".to_string())
+        );
+        assert_eq!(
+            name_entry_of(names, 12),
+            Some("name \"WEP\" (@1) ordinal".to_string())
+        );
+        assert_eq!(name_entry_of(names, 13), Some("end of table".to_string()));
+
+        // two fixed entries of segment 1, one unused, one moveable
+        let entries = [
+            2, 1, 3, 0, 0, 1, 0x1E, 0, 1, 0, 1, 0xFF, 1, 0xCD, 0x3F, 2, 0, 0, 0,
+        ];
+        assert_eq!(entry_table_field(&entries, 5), Some("entry @2".to_string()));
+        assert_eq!(
+            entry_table_field(&entries, 9),
+            Some("bundle of 1 entries from @3".to_string())
+        );
+        assert_eq!(
+            entry_table_field(&entries, 14),
+            Some("entry @4".to_string())
+        );
+        assert_eq!(
+            entry_table_field(&entries, 18),
+            Some("end of table".to_string())
+        );
+    }
+}
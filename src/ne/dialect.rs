@@ -0,0 +1,189 @@
+use std::fmt;
+
+use crate::ne::header::{pharlap_target, NeHeader};
+use crate::ne::segment_table::aligned;
+use crate::ne::NeExecutable;
+
+/// `os2_exe_flags`: OS/2 module supports long file names
+pub const NE_OS2_LONGNAMES: u8 = 0x01;
+/// `os2_exe_flags`: Windows 2.x application runs in protected mode
+pub const NE_WIN2_PROTMODE: u8 = 0x02;
+/// `os2_exe_flags`: Windows 2.x application supports proportional fonts
+pub const NE_WIN2_PROPFONTS: u8 = 0x04;
+/// `os2_exe_flags`: preloaded segments are kept together in the gangload area
+pub const NE_GANGLOAD: u8 = 0x08;
+
+///
+/// Generation of NE conventions the module was linked for.
+/// Same header fields mean different things in them:
+/// Windows 2.x has no expected version and no gangload area
+/// (its header has offsets of return and segment reference thunks there),
+/// OS/2 keeps resources in segments, not in the Windows resource table.
+/// Multitasking MS-DOS 4.0 (European) runs modules in real mode
+/// and has per-process library initialization.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FormatDialect {
+    Win2x,
+    Win30,
+    Win31,
+    OS2,
+    Dos4,
+}
+
+impl FormatDialect {
+    ///
+    /// OS/2 and MS-DOS 4.0 by target OS; Windows generation by expected
+    /// version, or 2.x if the flags of 2.x applications are set.
+    /// PharLap extended modules follow the API they are linked for;
+    /// the extender runs Windows modules without expected version as 3.0 ones.
+    /// Modules with unknown target OS follow the Windows rules.
+    ///
+    pub fn detect(header: &NeHeader) -> Self {
+        match pharlap_target(header.target_os) {
+            Some(0x01) => return FormatDialect::OS2,
+            Some(_) if header.expected_win_ver == [0, 0] => return FormatDialect::Win30,
+            _ => {}
+        }
+        match header.target_os {
+            0x01 => return FormatDialect::OS2,
+            0x03 => return FormatDialect::Dos4,
+            _ => {}
+        }
+        if header.os2_exe_flags & (NE_WIN2_PROTMODE | NE_WIN2_PROPFONTS) != 0 {
+            return FormatDialect::Win2x;
+        }
+        let [minor, major] = header.expected_win_ver;
+        match (major, minor) {
+            (0..=2, _) => FormatDialect::Win2x,
+            (3, 0..=9) => FormatDialect::Win30,
+            _ => FormatDialect::Win31,
+        }
+    }
+
+    ///
+    /// Whether `expected_win_ver` is filled by linker
+    ///
+    pub fn has_expected_version(self) -> bool {
+        matches!(self, FormatDialect::Win30 | FormatDialect::Win31)
+    }
+
+    ///
+    /// Whether header words at 0x38 and 0x3A give gangload area
+    /// (when `NE_GANGLOAD` is set) rather than thunk offsets
+    ///
+    pub fn has_gangload_area(self) -> bool {
+        matches!(self, FormatDialect::Win30 | FormatDialect::Win31)
+    }
+
+    ///
+    /// Whether resources are described by the Windows resource table
+    ///
+    pub fn has_resource_table(self) -> bool {
+        self != FormatDialect::OS2
+    }
+
+    ///
+    /// Whether upper bits of entry flags count parameter words.
+    /// Real-mode MS-DOS 4.0 has no call gates to copy them for.
+    ///
+    pub fn has_parameter_words(self) -> bool {
+        self != FormatDialect::Dos4
+    }
+}
+
+impl fmt::Display for FormatDialect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            FormatDialect::Win2x => "Windows 2.x",
+            FormatDialect::Win30 => "Windows 3.0",
+            FormatDialect::Win31 => "Windows 3.1",
+            FormatDialect::OS2 => "OS/2",
+            FormatDialect::Dos4 => "multitasking MS-DOS 4.0",
+        })
+    }
+}
+
+///
+/// Names of `os2_exe_flags` bits meaningful for the dialect.
+/// Other bits are given in hex
+///
+pub fn exe_flag_names(flags: u8, dialect: FormatDialect) -> Vec<String> {
+    (0..8)
+        .map(|shift| 1u8 << shift)
+        .filter(|mask| flags & mask != 0)
+        .map(|mask| match (mask, dialect) {
+            (NE_OS2_LONGNAMES, FormatDialect::OS2) => "LONGNAMES".to_string(),
+            (NE_WIN2_PROTMODE, FormatDialect::Win2x) => "WIN2_PROTMODE".to_string(),
+            (NE_WIN2_PROPFONTS, FormatDialect::Win2x) => "WIN2_PROPFONTS".to_string(),
+            (NE_GANGLOAD, FormatDialect::Win30 | FormatDialect::Win31) => "GANGLOAD".to_string(),
+            _ => format!("0x{:02X}", mask),
+        })
+        .collect()
+}
+
+impl NeHeader {
+    pub fn dialect(&self) -> FormatDialect {
+        FormatDialect::detect(self)
+    }
+
+    ///
+    /// File offset and length of the gangload area, if module has one.
+    /// Linkers set `NE_GANGLOAD` with empty area too.
+    ///
+    pub fn gangload_area(&self) -> Option<(u64, u64)> {
+        if !self.dialect().has_gangload_area()
+            || self.os2_exe_flags & NE_GANGLOAD == 0
+            || self.segment_reference_thunk_offset.value() == 0
+        {
+            return None;
+        }
+        let shift = self.file_alignment_shift_count.value();
+        Some((
+            aligned(self.return_thunk_offset.value(), shift),
+            aligned(self.segment_reference_thunk_offset.value(), shift),
+        ))
+    }
+}
+
+impl NeExecutable {
+    pub fn dialect(&self) -> FormatDialect {
+        self.ne_header.dialect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(target_os: u8, os2_exe_flags: u8, expected_win_ver: [u8; 2]) -> NeHeader {
+        let mut header: NeHeader = bytemuck::Zeroable::zeroed();
+        header.target_os = target_os;
+        header.os2_exe_flags = os2_exe_flags;
+        header.expected_win_ver = expected_win_ver;
+        header
+    }
+
+    #[test]
+    fn detects_dialects() {
+        assert_eq!(header(2, 0, [0, 0]).dialect(), FormatDialect::Win2x);
+        assert_eq!(header(2, 0, [3, 2]).dialect(), FormatDialect::Win2x);
+        assert_eq!(header(2, 0, [0, 3]).dialect(), FormatDialect::Win30);
+        assert_eq!(header(2, 0, [10, 3]).dialect(), FormatDialect::Win31);
+        assert_eq!(header(2, 0, [0, 4]).dialect(), FormatDialect::Win31);
+        // flags of 2.x applications mean more than the expected version
+        assert_eq!(
+            header(2, NE_WIN2_PROTMODE, [0, 3]).dialect(),
+            FormatDialect::Win2x
+        );
+        assert_eq!(header(1, 0, [0, 3]).dialect(), FormatDialect::OS2);
+        assert_eq!(header(3, 0, [0, 0]).dialect(), FormatDialect::Dos4);
+        assert_eq!(header(0x81, 0, [0, 0]).dialect(), FormatDialect::OS2);
+        assert_eq!(header(0x82, 0, [0, 0]).dialect(), FormatDialect::Win30);
+        assert_eq!(header(0x82, 0, [10, 3]).dialect(), FormatDialect::Win31);
+        assert_eq!(
+            exe_flag_names(NE_GANGLOAD | NE_OS2_LONGNAMES, FormatDialect::Win31),
+            ["0x01", "GANGLOAD"]
+        );
+    }
+}
@@ -0,0 +1,632 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::ne::exports::Export;
+use crate::ne::header::NeHeader;
+use crate::ne::resource_hash::resource_content;
+use crate::ne::resource_table::ResourceId;
+use crate::ne::NeExecutable;
+use crate::util::checksum::crc32;
+
+///
+/// Structural difference between two modules (usually original and patched one).
+/// Sections are compared by their identity: segments by index,
+/// exports by ordinal, resources by type and ID.
+///
+#[derive(Debug, Clone, Default)]
+pub struct DiffReport {
+    pub header: Vec<FieldChange>,
+    pub segments: Vec<SegmentChange>,
+    pub exports: Vec<ExportChange>,
+    pub resources: Vec<ResourceChange>,
+    pub relocations: Vec<RelocationCountChange>,
+}
+
+/// Header field with different values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: u32,
+    pub new: u32,
+}
+
+/// Segments are numbered from 1 as in relocations and entry table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SegmentChange {
+    Added {
+        segment: usize,
+    },
+    Removed {
+        segment: usize,
+    },
+    Resized {
+        segment: usize,
+        old: u64,
+        new: u64,
+    },
+    FlagsChanged {
+        segment: usize,
+        old: u16,
+        new: u16,
+    },
+    ContentChanged {
+        segment: usize,
+        old_crc: u32,
+        new_crc: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportChange {
+    Added {
+        ordinal: u16,
+        name: Option<String>,
+    },
+    Removed {
+        ordinal: u16,
+        name: Option<String>,
+    },
+    Renamed {
+        ordinal: u16,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    Moved {
+        ordinal: u16,
+        old: (u8, u16),
+        new: (u8, u16),
+    },
+    FlagsChanged {
+        ordinal: u16,
+        old: u8,
+        new: u8,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceChange {
+    Added {
+        type_id: ResourceId,
+        id: ResourceId,
+    },
+    Removed {
+        type_id: ResourceId,
+        id: ResourceId,
+    },
+    ContentChanged {
+        type_id: ResourceId,
+        id: ResourceId,
+        old_crc: u32,
+        new_crc: u32,
+    },
+}
+
+/// Count of relocation records differs for the segment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelocationCountChange {
+    pub segment: usize,
+    pub old: usize,
+    pub new: usize,
+}
+
+impl DiffReport {
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty()
+            && self.segments.is_empty()
+            && self.exports.is_empty()
+            && self.resources.is_empty()
+            && self.relocations.is_empty()
+    }
+}
+
+///
+/// Compares two parsed modules.
+/// \param a -- original module
+/// \param b -- changed module
+///
+pub fn compare(a: &NeExecutable, b: &NeExecutable) -> DiffReport {
+    DiffReport {
+        header: compare_headers(&a.ne_header, &b.ne_header),
+        segments: compare_segments(a, b),
+        exports: compare_exports(a.exports(), b.exports()),
+        resources: compare_resources(a, b),
+        relocations: compare_relocations(a, b),
+    }
+}
+
+fn header_fields(h: &NeHeader) -> [(&'static str, u32); 27] {
+    [
+        ("major_linker_version", h.major_linker_version as u32),
+        ("minor_linker_version", h.minor_linker_version as u32),
+        ("entry_table_offset", h.entry_table_offset.value() as u32),
+        ("entry_table_length", h.entry_table_length.value() as u32),
+        ("file_load_crc", h.file_load_crc.value()),
+        ("flags", h.flags.value() as u32),
+        (
+            "auto_data_segment_index",
+            h.auto_data_segment_index.value() as u32,
+        ),
+        ("init_heap_size", h.init_heap_size.value() as u32),
+        ("init_stack_size", h.init_stack_size.value() as u32),
+        ("entry_point", h.entry_point.value()),
+        ("init_stack", h.init_stack.value()),
+        ("segment_count", h.segment_count.value() as u32),
+        ("module_references", h.module_references.value() as u32),
+        (
+            "non_resident_names_size",
+            h.non_resident_names_size.value() as u32,
+        ),
+        (
+            "segment_table_offset",
+            h.segment_table_offset.value() as u32,
+        ),
+        (
+            "resource_table_offset",
+            h.resource_table_offset.value() as u32,
+        ),
+        (
+            "resident_names_table_offset",
+            h.resident_names_table_offset.value() as u32,
+        ),
+        (
+            "module_reference_table_offset",
+            h.module_reference_table_offset.value() as u32,
+        ),
+        (
+            "import_name_table_offset",
+            h.import_name_table_offset.value() as u32,
+        ),
+        (
+            "non_resident_names_table_offset",
+            h.non_resident_names_table_offset.value(),
+        ),
+        (
+            "movable_entry_point_count",
+            h.movable_entry_point_count.value() as u32,
+        ),
+        (
+            "file_alignment_shift_count",
+            h.file_alignment_shift_count.value() as u32,
+        ),
+        (
+            "resource_table_entries",
+            h.resource_table_entries.value() as u32,
+        ),
+        ("target_os", h.target_os as u32),
+        ("os2_exe_flags", h.os2_exe_flags as u32),
+        ("min_code_swap", h.min_code_swap.value() as u32),
+        (
+            "expected_win_ver",
+            u16::from_be_bytes(h.expected_win_ver) as u32,
+        ),
+    ]
+}
+
+fn compare_headers(a: &NeHeader, b: &NeHeader) -> Vec<FieldChange> {
+    header_fields(a)
+        .iter()
+        .zip(header_fields(b).iter())
+        .filter(|(old, new)| old.1 != new.1)
+        .map(|(old, new)| FieldChange {
+            field: old.0,
+            old: old.1,
+            new: new.1,
+        })
+        .collect()
+}
+
+fn compare_segments(a: &NeExecutable, b: &NeExecutable) -> Vec<SegmentChange> {
+    let mut changes = Vec::new();
+    let count = a.segment_entries.len().max(b.segment_entries.len());
+
+    for i in 0..count {
+        let segment = i + 1;
+        let (old, new) = match (a.segment_entries.get(i), b.segment_entries.get(i)) {
+            (Some(old), Some(new)) => (old, new),
+            (Some(_), None) => {
+                changes.push(SegmentChange::Removed { segment });
+                continue;
+            }
+            (None, Some(_)) => {
+                changes.push(SegmentChange::Added { segment });
+                continue;
+            }
+            (None, None) => unreachable!(),
+        };
+
+        if old.data_length() != new.data_length() {
+            changes.push(SegmentChange::Resized {
+                segment,
+                old: old.data_length(),
+                new: new.data_length(),
+            });
+        }
+        if old.header.flags != new.header.flags {
+            changes.push(SegmentChange::FlagsChanged {
+                segment,
+                old: old.header.flags,
+                new: new.header.flags,
+            });
+        }
+        let old_crc = crc32(old.data.as_deref().unwrap_or_default());
+        let new_crc = crc32(new.data.as_deref().unwrap_or_default());
+        if old_crc != new_crc {
+            changes.push(SegmentChange::ContentChanged {
+                segment,
+                old_crc,
+                new_crc,
+            });
+        }
+    }
+    changes
+}
+
+fn compare_exports(a: &[Export], b: &[Export]) -> Vec<ExportChange> {
+    let old: BTreeMap<u16, &Export> = a.iter().map(|e| (e.ordinal, e)).collect();
+    let new: BTreeMap<u16, &Export> = b.iter().map(|e| (e.ordinal, e)).collect();
+    let mut changes = Vec::new();
+
+    for (&ordinal, old_export) in &old {
+        let new_export = match new.get(&ordinal) {
+            Some(e) => e,
+            None => {
+                changes.push(ExportChange::Removed {
+                    ordinal,
+                    name: old_export.name.as_deref().map(str::to_string),
+                });
+                continue;
+            }
+        };
+        if old_export.name != new_export.name {
+            changes.push(ExportChange::Renamed {
+                ordinal,
+                old: old_export.name.as_deref().map(str::to_string),
+                new: new_export.name.as_deref().map(str::to_string),
+            });
+        }
+        let old_address = (old_export.segment, old_export.offset);
+        let new_address = (new_export.segment, new_export.offset);
+        if old_address != new_address {
+            changes.push(ExportChange::Moved {
+                ordinal,
+                old: old_address,
+                new: new_address,
+            });
+        }
+        if old_export.flags != new_export.flags {
+            changes.push(ExportChange::FlagsChanged {
+                ordinal,
+                old: old_export.flags,
+                new: new_export.flags,
+            });
+        }
+    }
+    for (&ordinal, new_export) in &new {
+        if !old.contains_key(&ordinal) {
+            changes.push(ExportChange::Added {
+                ordinal,
+                name: new_export.name.as_deref().map(str::to_string),
+            });
+        }
+    }
+    changes
+}
+
+///
+/// Data and alignment shift of every resource by (type, ID).
+/// Names are uppercased, because Windows compares them case-insensitively
+///
+fn resource_data(ne: &NeExecutable) -> BTreeMap<(ResourceId, ResourceId), (&[u8], u16)> {
+    let normalize = |id: ResourceId| match id {
+        ResourceId::Name(name) => ResourceId::Name(name.to_ascii_uppercase()),
+        id => id,
+    };
+    let shift_count = ne.resource_table.header.alignment_shift_count;
+    let mut data = BTreeMap::new();
+    for resource_type in &ne.resource_table.resource_types {
+        for resource in &resource_type.resources {
+            let key = (normalize(resource_type.id()), normalize(resource.id()));
+            data.insert(
+                key,
+                (resource.data.as_deref().unwrap_or_default(), shift_count),
+            );
+        }
+    }
+    data
+}
+
+///
+/// Whether resource data are the same but for the padding: the longer one
+/// goes on with fewer zeros than its alignment unit, so repacked
+/// resources stay equal
+///
+fn same_content(a: (&[u8], u16), b: (&[u8], u16)) -> bool {
+    let (short, (long, long_shift)) = if a.0.len() <= b.0.len() {
+        (a.0, b)
+    } else {
+        (b.0, a)
+    };
+    let unit = 1usize.checked_shl(long_shift as u32).unwrap_or(1);
+    let (head, tail) = long.split_at(short.len());
+    head == short && tail.len() < unit && tail.iter().all(|&b| b == 0)
+}
+
+fn compare_resources(a: &NeExecutable, b: &NeExecutable) -> Vec<ResourceChange> {
+    let old = resource_data(a);
+    let new = resource_data(b);
+    let mut changes = Vec::new();
+
+    for ((type_id, id), &old_data) in &old {
+        match new.get(&(type_id.clone(), id.clone())) {
+            None => changes.push(ResourceChange::Removed {
+                type_id: type_id.clone(),
+                id: id.clone(),
+            }),
+            Some(&new_data) if !same_content(old_data, new_data) => {
+                changes.push(ResourceChange::ContentChanged {
+                    type_id: type_id.clone(),
+                    id: id.clone(),
+                    old_crc: crc32(resource_content(old_data.0, old_data.1)),
+                    new_crc: crc32(resource_content(new_data.0, new_data.1)),
+                })
+            }
+            Some(_) => (),
+        }
+    }
+    for (type_id, id) in new.keys() {
+        if !old.contains_key(&(type_id.clone(), id.clone())) {
+            changes.push(ResourceChange::Added {
+                type_id: type_id.clone(),
+                id: id.clone(),
+            });
+        }
+    }
+    changes
+}
+
+fn compare_relocations(a: &NeExecutable, b: &NeExecutable) -> Vec<RelocationCountChange> {
+    let count = a
+        .relocation_tables_per_segment
+        .len()
+        .min(b.relocation_tables_per_segment.len());
+
+    (0..count)
+        .map(|i| RelocationCountChange {
+            segment: i + 1,
+            old: a.relocation_tables_per_segment[i].entries.len(),
+            new: b.relocation_tables_per_segment[i].entries.len(),
+        })
+        .filter(|c| c.old != c.new)
+        .collect()
+}
+
+fn fmt_name(name: &Option<String>) -> &str {
+    name.as_deref().unwrap_or("<unnamed>")
+}
+
+impl fmt::Display for SegmentChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SegmentChange::Added { segment } => write!(f, "segment #{} added", segment),
+            SegmentChange::Removed { segment } => write!(f, "segment #{} removed", segment),
+            SegmentChange::Resized { segment, old, new } => {
+                write!(f, "segment #{} resized: {:#X} -> {:#X}", segment, old, new)
+            }
+            SegmentChange::FlagsChanged { segment, old, new } => {
+                write!(
+                    f,
+                    "segment #{} flags: {:#06X} -> {:#06X}",
+                    segment, old, new
+                )
+            }
+            SegmentChange::ContentChanged {
+                segment,
+                old_crc,
+                new_crc,
+            } => write!(
+                f,
+                "segment #{} content: CRC32 {:08X} -> {:08X}",
+                segment, old_crc, new_crc
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ExportChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportChange::Added { ordinal, name } => {
+                write!(f, "export @{} {} added", ordinal, fmt_name(name))
+            }
+            ExportChange::Removed { ordinal, name } => {
+                write!(f, "export @{} {} removed", ordinal, fmt_name(name))
+            }
+            ExportChange::Renamed { ordinal, old, new } => write!(
+                f,
+                "export @{} renamed: {} -> {}",
+                ordinal,
+                fmt_name(old),
+                fmt_name(new)
+            ),
+            ExportChange::Moved { ordinal, old, new } => write!(
+                f,
+                "export @{} moved: {}:{:04X} -> {}:{:04X}",
+                ordinal, old.0, old.1, new.0, new.1
+            ),
+            ExportChange::FlagsChanged { ordinal, old, new } => {
+                write!(f, "export @{} flags: {:#04X} -> {:#04X}", ordinal, old, new)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ResourceChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResourceChange::Added { type_id, id } => {
+                write!(f, "resource {}/{} added", type_id, id)
+            }
+            ResourceChange::Removed { type_id, id } => {
+                write!(f, "resource {}/{} removed", type_id, id)
+            }
+            ResourceChange::ContentChanged {
+                type_id,
+                id,
+                old_crc,
+                new_crc,
+            } => write!(
+                f,
+                "resource {}/{} content: CRC32 {:08X} -> {:08X}",
+                type_id, id, old_crc, new_crc
+            ),
+        }
+    }
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in &self.header {
+            writeln!(f, "header.{}: {:#X} -> {:#X}", c.field, c.old, c.new)?;
+        }
+        for c in &self.segments {
+            writeln!(f, "{}", c)?;
+        }
+        for c in &self.exports {
+            writeln!(f, "{}", c)?;
+        }
+        for c in &self.resources {
+            writeln!(f, "{}", c)?;
+        }
+        for c in &self.relocations {
+            writeln!(
+                f,
+                "segment #{} relocations: {} -> {}",
+                c.segment, c.old, c.new
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::dialect::FormatDialect;
+    use crate::ne::fixture::sample_module;
+    use crate::ne::resource_table::RT_ICON;
+
+    fn export(ordinal: u16, name: &str, offset: u16) -> Export {
+        Export {
+            ordinal,
+            name: Some(name.into()),
+            is_resident_name: true,
+            segment: 1,
+            offset,
+            is_movable: false,
+            flags: 0x01,
+            dialect: FormatDialect::Win30,
+        }
+    }
+
+    #[test]
+    fn exports_by_ordinal() {
+        let old = [export(1, "WEP", 0x10), export(2, "FOO", 0x20)];
+        let new = [export(1, "WEP", 0x14), export(3, "BAR", 0x30)];
+        let changes = compare_exports(&old, &new);
+        assert_eq!(
+            changes,
+            vec![
+                ExportChange::Moved {
+                    ordinal: 1,
+                    old: (1, 0x10),
+                    new: (1, 0x14)
+                },
+                ExportChange::Removed {
+                    ordinal: 2,
+                    name: Some("FOO".to_string())
+                },
+                ExportChange::Added {
+                    ordinal: 3,
+                    name: Some("BAR".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn header_fields_by_name() {
+        let old = sample_module();
+        let mut new = old.clone();
+        assert!(compare(&old, &new).is_empty());
+        new.ne_header.init_stack_size = 0x2000.into();
+        new.ne_header.expected_win_ver = [10, 3];
+        let diff = compare(&old, &new);
+        assert_eq!(
+            diff.header,
+            [
+                FieldChange {
+                    field: "init_stack_size",
+                    old: 0x1000,
+                    new: 0x2000,
+                },
+                FieldChange {
+                    field: "expected_win_ver",
+                    old: 0x0003,
+                    new: 0x0A03,
+                },
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "header.init_stack_size: 0x1000 -> 0x2000\n\
+             header.expected_win_ver: 0x3 -> 0xA03\n"
+        );
+    }
+
+    fn icon(ne: &mut NeExecutable) -> &mut Vec<u8> {
+        ne.resource_table.resource_types[0].resources[0]
+            .data
+            .as_mut()
+            .unwrap()
+    }
+
+    #[test]
+    fn resources_by_content() {
+        let mut old = sample_module();
+        *icon(&mut old) = vec![7; 0x1C];
+        // zeros up to the 16-byte alignment aren't content
+        let mut new = old.clone();
+        icon(&mut new).extend_from_slice(&[0; 4]);
+        assert!(compare(&old, &new).is_empty());
+
+        icon(&mut new)[0] = 8;
+        let mut added = old.resource_table.resource_types[0].resources[0].clone();
+        added.header.resource_id = 0x8002;
+        new.resource_table.resource_types[0].resources.push(added);
+        let mut changed = vec![7; 0x1C];
+        changed[0] = 8;
+        let icon_type = ResourceId::Int(RT_ICON);
+        assert_eq!(
+            compare(&old, &new).resources,
+            [
+                ResourceChange::ContentChanged {
+                    type_id: icon_type.clone(),
+                    id: ResourceId::Int(1),
+                    old_crc: crc32(&[7; 0x1C]),
+                    new_crc: crc32(&changed),
+                },
+                ResourceChange::Added {
+                    type_id: icon_type.clone(),
+                    id: ResourceId::Int(2),
+                },
+            ]
+        );
+        assert_eq!(
+            compare(&new, &old).resources.last(),
+            Some(&ResourceChange::Removed {
+                type_id: icon_type,
+                id: ResourceId::Int(2),
+            })
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use std::fmt;
+
+use crate::ne::header::NE_LIBRARY;
+use crate::ne::NeExecutable;
+
+///
+/// Exports every kind of Windows driver is expected to have
+/// or which tell its conventions
+///
+pub const STANDARD_DRIVER_EXPORTS: &[&str] = &[
+    "WEP",
+    "DriverProc",
+    "Enable",
+    "Disable",
+    "wodMessage",
+    "widMessage",
+    "modMessage",
+    "midMessage",
+    "auxMessage",
+];
+
+///
+/// Kind of driver known by the procedures Windows calls in it
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriverCategory {
+    pub name: &'static str,
+    /// Every one must be exported
+    pub required: &'static [&'static str],
+    /// At least one must be exported, if any are given
+    pub any_of: &'static [&'static str],
+    /// Usually exported as well
+    pub optional: &'static [&'static str],
+}
+
+const fn category(
+    name: &'static str,
+    required: &'static [&'static str],
+    any_of: &'static [&'static str],
+    optional: &'static [&'static str],
+) -> DriverCategory {
+    DriverCategory {
+        name,
+        required,
+        any_of,
+        optional,
+    }
+}
+
+///
+/// Installable drivers (Windows 3.1 `OpenDriver`), multimedia drivers
+/// of MMSYSTEM and system drivers loaded by USER and GDI
+///
+pub const DRIVER_CATEGORIES: &[DriverCategory] = &[
+    category("installable driver", &["DriverProc"], &[], &[]),
+    category(
+        "wave audio",
+        &["DriverProc"],
+        &["wodMessage", "widMessage"],
+        &[],
+    ),
+    category("MIDI", &["DriverProc"], &["modMessage", "midMessage"], &[]),
+    category("auxiliary audio", &["DriverProc", "auxMessage"], &[], &[]),
+    category(
+        "display",
+        &[
+            "Enable",
+            "Disable",
+            "BitBlt",
+            "ColorInfo",
+            "Control",
+            "Output",
+            "Pixel",
+            "RealizeObject",
+            "StrBlt",
+            "SetCursor",
+            "MoveCursor",
+        ],
+        &[],
+        &[
+            "CheckCursor",
+            "ExtTextOut",
+            "ScanLR",
+            "Inquire",
+            "DeviceBitmap",
+        ],
+    ),
+    category(
+        "printer",
+        &["Enable", "Disable", "Control", "DeviceMode"],
+        &[],
+        &[
+            "ExtDeviceMode",
+            "DeviceCapabilities",
+            "ExtTextOut",
+            "StretchDIBits",
+        ],
+    ),
+    category(
+        "keyboard",
+        &[
+            "Inquire",
+            "Enable",
+            "Disable",
+            "ToAscii",
+            "AnsiToOem",
+            "OemToAnsi",
+        ],
+        &[],
+        &[
+            "SetSpeed",
+            "GetKeyboardType",
+            "MapVirtualKey",
+            "GetKeyNameText",
+        ],
+    ),
+    category(
+        "mouse",
+        &["Inquire", "Enable", "Disable", "MouseGetIntVect"],
+        &[],
+        &[],
+    ),
+    category(
+        "communications",
+        &["INICOM", "SETCOM", "RECCOM", "SNDCOM"],
+        &[],
+        &["SETQUE", "TRMCOM", "STACOM", "CEVT", "GETDCB"],
+    ),
+    category(
+        "system",
+        &["InquireSystem", "CreateSystemTimer", "KillSystemTimer"],
+        &[],
+        &[
+            "EnableSystemTimers",
+            "DisableSystemTimers",
+            "GetSystemMsecCount",
+        ],
+    ),
+    category(
+        "sound (Windows 3.0)",
+        &["OpenSound", "CloseSound"],
+        &[],
+        &["SetVoiceNote", "StartSound", "StopSound"],
+    ),
+];
+
+///
+/// Category whose required exports are present
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CategoryMatch {
+    pub category: &'static DriverCategory,
+    /// Exports of the category found in the module
+    pub found: Vec<&'static str>,
+    /// Optional exports not found
+    pub missing: Vec<&'static str>,
+}
+
+///
+/// Driver conventions of the module
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriverReport {
+    /// Standard driver exports and whether they are present
+    pub standard: Vec<(&'static str, bool)>,
+    /// Matching categories, the most specific (with more exports) first
+    pub categories: Vec<CategoryMatch>,
+}
+
+impl fmt::Display for DriverReport {
+    ///
+    /// `{}` gives kinds of the driver, `{:#}` also exports found and missing
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<_> = self.categories.iter().map(|m| m.category.name).collect();
+        write!(f, "{}", names.join(", "))?;
+        if f.alternate() {
+            for (name, present) in &self.standard {
+                write!(f, "\n\t{:<12}{}", name, if *present { "yes" } else { "no" })?;
+            }
+            for m in &self.categories {
+                write!(f, "\n{}: {}", m.category.name, m.found.join(" "))?;
+                if !m.missing.is_empty() {
+                    write!(f, " (missing {})", m.missing.join(" "))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// Categories of driver having exports `names` (case-insensitive)
+///
+pub fn match_categories(names: &[String]) -> Vec<CategoryMatch> {
+    let has = |export: &&str| names.iter().any(|n| n.eq_ignore_ascii_case(export));
+    let mut matches: Vec<CategoryMatch> = DRIVER_CATEGORIES
+        .iter()
+        .filter(|c| c.required.iter().all(has))
+        .filter(|c| c.any_of.is_empty() || c.any_of.iter().any(has))
+        .map(|category| {
+            let all = category
+                .required
+                .iter()
+                .chain(category.any_of)
+                .chain(category.optional);
+            CategoryMatch {
+                category,
+                found: all.clone().copied().filter(|e| has(e)).collect(),
+                missing: category
+                    .optional
+                    .iter()
+                    .copied()
+                    .filter(|e| !has(e))
+                    .collect(),
+            }
+        })
+        .collect();
+    matches.sort_by_key(|m| std::cmp::Reverse(m.found.len()));
+    matches
+}
+
+impl NeExecutable {
+    ///
+    /// Classifies library as a driver by its export set and reports
+    /// the standard driver exports. `None` for tasks and other libraries.
+    ///
+    pub fn driver_conventions(&self) -> Option<DriverReport> {
+        if self.ne_header.flags.value() & NE_LIBRARY == 0 {
+            return None;
+        }
+        let names: Vec<String> = self
+            .exports()
+            .iter()
+            .filter_map(|e| e.name.as_deref().map(str::to_string))
+            .collect();
+        let categories = match_categories(&names);
+        if categories.is_empty() {
+            return None;
+        }
+        let standard = STANDARD_DRIVER_EXPORTS
+            .iter()
+            .map(|&export| (export, names.iter().any(|n| n.eq_ignore_ascii_case(export))))
+            .collect();
+        Some(DriverReport {
+            standard,
+            categories,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wave_driver_is_also_installable() {
+        let names: Vec<String> = ["WEP", "DRIVERPROC", "WODMESSAGE"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let matches = match_categories(&names);
+        let kinds: Vec<_> = matches.iter().map(|m| m.category.name).collect();
+        assert_eq!(kinds, ["wave audio", "installable driver"]);
+        assert_eq!(matches[0].found, ["DriverProc", "wodMessage"]);
+        assert!(match_categories(&["Enable".to_string()]).is_empty());
+    }
+}
@@ -0,0 +1,644 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::ops::Range;
+
+use crate::ne::addr::Far16Ptr;
+use crate::ne::entry_table::{FixedSegmentEntry, MoveableSegmentEntry, SegmentEntry};
+use crate::ne::owned::NeOwned;
+use crate::ne::resource_table::{
+    NeResource, NeResourceHeader, NeResourceType, NeResourceTypeHeader, ResourceId,
+};
+use crate::ne::segment_relocations::RelocationTarget;
+use crate::ne::segment_table::{SEG_MOVEABLE, SEG_PRELOAD};
+use crate::ne::writer::{encode_entry_table, WriteOptions};
+use crate::ne::NeExecutable;
+
+/// `INT 3Fh` of moveable entry: the loader calls there for not loaded segment
+const MOVEABLE_ENTRY_MAGIC: [u8; 2] = [0xCD, 0x3F];
+
+///
+/// File written by `NeEditor::save`
+///
+#[derive(Debug, Clone)]
+pub struct Saved {
+    pub data: Vec<u8>,
+    /// Places of the original file which were rewritten, in file order.
+    /// `None` if the whole file was rebuilt.
+    pub patched: Option<Vec<Range<u64>>>,
+}
+
+impl fmt::Display for Saved {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.patched {
+            Some(regions) => write!(
+                f,
+                "{} regions patched ({} bytes), {} bytes total",
+                regions.len(),
+                regions.iter().map(|r| r.end - r.start).sum::<u64>(),
+                self.data.len()
+            ),
+            None => write!(f, "rebuilt, {} bytes total", self.data.len()),
+        }
+    }
+}
+
+///
+/// Changes of module layout which keep every reference consistent.
+/// Segments are numbered from 1, as in the file.
+/// Editor made `from_owned` keeps the original file and `save` patches
+/// only places of edited structures, while edits moving tables
+/// (reordered segments, added resources) rebuild the whole file
+/// (see `NeExecutable::to_bytes_with`).
+///
+#[derive(Debug, Clone)]
+pub struct NeEditor {
+    module: NeExecutable,
+    /// File the module was read from
+    original: Option<Vec<u8>>,
+    /// Some edit moves tables, so the file can't be patched
+    relayout: bool,
+    entry_table_edited: bool,
+    /// Resources with replaced data: type and resource indices
+    replaced: BTreeSet<(usize, usize)>,
+}
+
+impl NeEditor {
+    pub fn new(module: NeExecutable) -> Self {
+        Self {
+            module,
+            original: None,
+            relayout: false,
+            entry_table_edited: false,
+            replaced: BTreeSet::new(),
+        }
+    }
+
+    ///
+    /// Editor which saves edits over the bytes the module was read from
+    ///
+    pub fn from_owned(owned: NeOwned) -> Self {
+        let (bytes, module) = owned.into_parts();
+        Self {
+            original: Some(bytes),
+            ..Self::new(module)
+        }
+    }
+
+    pub fn module(&self) -> &NeExecutable {
+        &self.module
+    }
+
+    pub fn into_module(self) -> NeExecutable {
+        self.module
+    }
+
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        Ok(self.save()?.data)
+    }
+
+    ///
+    /// Writes the edited module: the original file with edited places
+    /// rewritten if it is kept and no edit moved tables, else the whole
+    /// file rebuilt. Resources grown past their place on file are moved
+    /// after the last structure of the image (before the overlay).
+    ///
+    pub fn save(&self) -> io::Result<Saved> {
+        if let Some((data, regions)) = self.original.as_deref().and_then(|o| self.patch(o)) {
+            return Ok(Saved {
+                data,
+                patched: Some(regions),
+            });
+        }
+        Ok(Saved {
+            data: self.module.to_bytes()?,
+            patched: None,
+        })
+    }
+
+    ///
+    /// `original` with edited places rewritten, and those places;
+    /// `None` if the file must be rebuilt
+    ///
+    fn patch(&self, original: &[u8]) -> Option<(Vec<u8>, Vec<Range<u64>>)> {
+        if self.relayout {
+            return None;
+        }
+        let module = &self.module;
+        let header = &module.ne_header;
+        let lfanew = module.dos_header.lfanew.value() as usize;
+        let relative = |offset: u16| lfanew + offset as usize;
+
+        let mut places: Vec<(usize, Vec<u8>)> =
+            vec![(lfanew, bytemuck::bytes_of(&**header).to_vec())];
+        let segment_table = module
+            .segment_entries
+            .iter()
+            .flat_map(|s| {
+                let h = s.header;
+                [h.data_offset_shifted, h.data_length, h.flags, h.min_alloc]
+            })
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        places.push((relative(header.segment_table_offset.value()), segment_table));
+        if self.entry_table_edited {
+            let entry_table = encode_entry_table(&module.entry_table);
+            if entry_table.len() != header.entry_table_length.value() as usize {
+                return None;
+            }
+            places.push((relative(header.entry_table_offset.value()), entry_table));
+        }
+
+        let table = &module.resource_table;
+        let shift_count = table.header.alignment_shift_count;
+        let image_end = usize::try_from(module.image_end())
+            .ok()?
+            .min(original.len());
+        let mut appended = Vec::new();
+        for &(type_index, index) in &self.replaced {
+            let resource = &table.resource_types[type_index].resources[index];
+            let data = resource.data.as_ref()?;
+            let units = data.len().div_ceil(1 << shift_count);
+            let capacity = resource.data_length(shift_count) as usize;
+            let mut offset = resource.header.data_offset_shifted;
+            if resource.header.data_offset_shifted != 0 && data.len() <= capacity {
+                let mut place = data.clone();
+                place.resize(capacity, 0);
+                places.push((resource.data_offset(shift_count) as usize, place));
+            } else {
+                // placed after the image when the other places are known
+                appended.push((places.len(), data));
+                offset = 0;
+            }
+            // resource record: offset, length and flags
+            let types_before: usize = table.resource_types[..type_index]
+                .iter()
+                .map(|t| 8 + 12 * t.resources.len())
+                .sum();
+            let record =
+                relative(header.resource_table_offset.value()) + 2 + types_before + 8 + 12 * index;
+            let mut fields = offset.to_le_bytes().to_vec();
+            fields.extend_from_slice(&u16::try_from(units).ok()?.to_le_bytes());
+            fields.extend_from_slice(&resource.header.flags.to_le_bytes());
+            places.push((record, fields));
+        }
+
+        let mut out = original.to_vec();
+        let mut regions = Vec::new();
+        for (at, bytes) in &places {
+            let place = out.get_mut(*at..at + bytes.len())?;
+            if place != &bytes[..] {
+                place.copy_from_slice(bytes);
+                regions.push(*at as u64..(at + bytes.len()) as u64);
+            }
+        }
+        if !appended.is_empty() {
+            let overlay = out.split_off(image_end);
+            for (record, data) in appended {
+                out.resize(out.len().next_multiple_of(1 << shift_count), 0);
+                let offset = u16::try_from(out.len() >> shift_count).ok()?;
+                regions.push(out.len() as u64..(out.len() + data.len()) as u64);
+                out.extend_from_slice(data);
+                let at = places[record].0;
+                out[at..at + 2].copy_from_slice(&offset.to_le_bytes());
+                regions.push(at as u64..at as u64 + 2);
+            }
+            out.resize(out.len().next_multiple_of(1 << shift_count), 0);
+            if !overlay.is_empty() {
+                regions.push(out.len() as u64..(out.len() + overlay.len()) as u64);
+                out.extend_from_slice(&overlay);
+            }
+        }
+        regions.sort_by_key(|r| r.start);
+        // offsets of appended resources are set inside their patched records
+        regions.dedup_by(|next, prev| {
+            let overlaps = next.start <= prev.end;
+            if overlaps {
+                prev.end = prev.end.max(next.end);
+            }
+            overlaps
+        });
+        Some((out, regions))
+    }
+
+    pub fn to_bytes_with(&self, options: &WriteOptions) -> io::Result<Vec<u8>> {
+        Ok(self.module.to_bytes_with(options)?.data)
+    }
+
+    fn check_segment(&self, segment: usize) -> io::Result<()> {
+        if segment == 0 || segment > self.module.segment_entries.len() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "segment #{} doesn't exist (module has {})",
+                    segment,
+                    self.module.segment_entries.len()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    ///
+    /// Puts segments in the given order: `order[i]` is the old number
+    /// of the new segment `i + 1`. Entry table, internal relocations,
+    /// automatic data segment, `CS:IP` and `SS:SP` are renumbered.
+    /// Self-loading modules are refused: their loader knows segments by number.
+    ///
+    pub fn reorder_segments(&mut self, order: &[usize]) -> io::Result<()> {
+        let count = self.module.segment_entries.len();
+        let mut new_number = vec![0_u16; count + 1];
+        for (i, &old) in order.iter().enumerate() {
+            if old == 0 || old > count || new_number[old] != 0 {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{:?} is not an order of segments 1..={}", order, count),
+                ));
+            }
+            new_number[old] = i as u16 + 1;
+        }
+        if order.len() != count {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("order has {} segments, module has {}", order.len(), count),
+            ));
+        }
+        if self.module.is_self_loading() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "segments of self-loading module can't be reordered",
+            ));
+        }
+        let renumber = |segment: u16| match segment as usize {
+            s @ 1.. if s <= count => new_number[s],
+            _ => segment,
+        };
+
+        self.module.invalidate();
+        let module = &mut self.module;
+        let mut segments: Vec<_> = module.segment_entries.drain(..).map(Some).collect();
+        let mut relocations: Vec<_> = module
+            .relocation_tables_per_segment
+            .drain(..)
+            .map(Some)
+            .collect();
+        for &old in order {
+            module.segment_entries.extend(segments[old - 1].take());
+            module
+                .relocation_tables_per_segment
+                .extend(relocations.get_mut(old - 1).and_then(Option::take));
+        }
+
+        for entry in &mut module.entry_table.entries {
+            match entry {
+                SegmentEntry::Unused => {}
+                SegmentEntry::Fixed(e) => e.segment = renumber(e.segment as u16) as u8,
+                SegmentEntry::Moveable(e) => e.segment = renumber(e.segment as u16) as u8,
+            }
+        }
+        for table in &mut module.relocation_tables_per_segment {
+            for entry in &mut table.entries {
+                // 0xFF is a moveable segment referenced through entry ordinal
+                if let RelocationTarget::Internal(f) = &mut entry.target {
+                    if f.segment != 0xFF {
+                        f.segment = renumber(f.segment as u16) as u8;
+                    }
+                }
+            }
+        }
+
+        let header = &mut module.ne_header;
+        header.auto_data_segment_index = renumber(header.auto_data_segment_index.value()).into();
+        for far_ptr in [&mut header.entry_point, &mut header.init_stack] {
+            let mut ptr = Far16Ptr::from(far_ptr.value());
+            ptr.segment = renumber(ptr.segment);
+            *far_ptr = u32::from(ptr).into();
+        }
+        self.relayout = true;
+        Ok(())
+    }
+
+    ///
+    /// Sets or clears `PRELOAD` flag of segment
+    ///
+    pub fn set_preload(&mut self, segment: usize, preload: bool) -> io::Result<()> {
+        self.check_segment(segment)?;
+        self.module.invalidate();
+        let flags = &mut self.module.segment_entries[segment - 1].header.flags;
+        if preload {
+            *flags |= SEG_PRELOAD;
+        } else {
+            *flags &= !SEG_PRELOAD;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Sets or clears `MOVEABLE` flag of segment and turns its entry points
+    /// into moveable (`INT 3Fh` thunk) or fixed ones.
+    /// Internal relocations keep referencing the segment by number.
+    ///
+    pub fn set_moveable(&mut self, segment: usize, moveable: bool) -> io::Result<()> {
+        self.check_segment(segment)?;
+        self.module.invalidate();
+        let module = &mut self.module;
+        let flags = &mut module.segment_entries[segment - 1].header.flags;
+        if moveable {
+            *flags |= SEG_MOVEABLE;
+        } else {
+            *flags &= !SEG_MOVEABLE;
+        }
+
+        for entry in &mut module.entry_table.entries {
+            *entry = match *entry {
+                SegmentEntry::Fixed(e) if moveable && e.segment as usize == segment => {
+                    SegmentEntry::Moveable(MoveableSegmentEntry {
+                        flags: e.flags,
+                        magic: MOVEABLE_ENTRY_MAGIC,
+                        segment: e.segment,
+                        offset: e.offset,
+                    })
+                }
+                SegmentEntry::Moveable(e) if !moveable && e.segment as usize == segment => {
+                    SegmentEntry::Fixed(FixedSegmentEntry {
+                        segment: e.segment,
+                        flags: e.flags,
+                        offset: e.offset,
+                    })
+                }
+                ref other => other.clone(),
+            };
+        }
+        let moveable_entries = module
+            .entry_table
+            .entries
+            .iter()
+            .filter(|e| matches!(e, SegmentEntry::Moveable(_)))
+            .count();
+        module.ne_header.movable_entry_point_count = (moveable_entries as u16).into();
+        self.entry_table_edited = true;
+        Ok(())
+    }
+
+    ///
+    /// Replaces data and memory flags of resource or adds a new one
+    /// (and its type) at the end of the table. Returns whether resource existed.
+    /// Module without resources gets alignment of its segments.
+    ///
+    pub fn set_resource(
+        &mut self,
+        type_id: &ResourceId,
+        name: &ResourceId,
+        flags: u16,
+        data: Vec<u8>,
+    ) -> io::Result<bool> {
+        let (raw_type, type_name) = raw_resource_id(type_id)?;
+        let (raw_name, resource_name) = raw_resource_id(name)?;
+        self.module.invalidate();
+        let table = &mut self.module.resource_table;
+        if table.resource_types.is_empty() {
+            table.header.alignment_shift_count =
+                self.module.ne_header.file_alignment_shift_count.value();
+        }
+
+        let type_index = table
+            .resource_types
+            .iter()
+            .position(|t| t.id().matches(type_id));
+        let resource_type = match type_index {
+            Some(index) => &mut table.resource_types[index],
+            None => {
+                table.resource_types.push(NeResourceType {
+                    header: NeResourceTypeHeader {
+                        type_id: raw_type,
+                        num_resources: 0,
+                        res: [0; 2],
+                    },
+                    name: type_name,
+                    resources: Vec::new(),
+                });
+                table.resource_types.last_mut().unwrap()
+            }
+        };
+        if let Some(index) = resource_type
+            .resources
+            .iter()
+            .position(|r| r.id().matches(name))
+        {
+            let resource = &mut resource_type.resources[index];
+            resource.header.flags = flags;
+            resource.data = Some(data);
+            match type_index {
+                Some(type_index) => {
+                    self.replaced.insert((type_index, index));
+                }
+                None => self.relayout = true,
+            }
+            return Ok(true);
+        }
+        resource_type.resources.push(NeResource {
+            header: NeResourceHeader {
+                data_offset_shifted: 0,
+                data_length: 0,
+                flags,
+                resource_id: raw_name,
+                res: [0; 2],
+            },
+            name: resource_name,
+            data: Some(data),
+        });
+        resource_type.header.num_resources = resource_type.resources.len() as u16;
+        self.relayout = true;
+        Ok(false)
+    }
+}
+
+///
+/// Resource table word of ID (0x8000 flag for integers) and its name.
+/// Names are length-prefixed ANSI strings in the table.
+///
+fn raw_resource_id(id: &ResourceId) -> io::Result<(u16, Option<String>)> {
+    match id {
+        ResourceId::Int(n) if *n <= 0x7FFF => Ok((n | 0x8000, None)),
+        ResourceId::Name(s) if s.is_ascii() && !s.is_empty() && s.len() <= 255 => {
+            Ok((0, Some(s.clone())))
+        }
+        _ => Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("resource ID {} doesn't fit the NE resource table", id),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::entry_table::EntryTable;
+    use crate::ne::imported_name_table::ImportedNameTable;
+    use crate::ne::module_reference_table::ModuleReferenceTable;
+    use crate::ne::nonresident_name_table::NonresidentNameTable;
+    use crate::ne::resident_name_table::ResidentNameTable;
+    use crate::ne::resource_table::NeResourceTable;
+    use crate::ne::segment_relocations::{InternalFixes, RelocationEntry, RelocationTable};
+    use crate::ne::segment_table::{NeSegment, NeSegmentHeader};
+
+    fn segment(flags: u16) -> NeSegment {
+        NeSegment {
+            header: NeSegmentHeader {
+                data_offset_shifted: 1,
+                data_length: 4,
+                flags,
+                min_alloc: 4,
+            },
+            shift_count: 4,
+            data: Some(vec![0; 4]),
+        }
+    }
+
+    fn module() -> NeExecutable {
+        let mut ne_header: crate::ne::header::NeHeader = bytemuck::Zeroable::zeroed();
+        ne_header.auto_data_segment_index = 2.into();
+        ne_header.entry_point = 0x0001_0010.into();
+        ne_header.init_stack = 0x0002_0100.into();
+        let internal = RelocationEntry {
+            address_type: 3,
+            reloc_type: 0,
+            is_additive: false,
+            segment_offset: 0,
+            target: RelocationTarget::Internal(InternalFixes {
+                segment: 2,
+                is_movable: false,
+                offset_or_ordinal: 0,
+            }),
+        };
+        NeExecutable {
+            dos_header: Box::new(bytemuck::Zeroable::zeroed()),
+            dos_stub: Vec::new(),
+            ne_header: Box::new(ne_header),
+            segment_entries: vec![segment(0), segment(1), segment(0)],
+            resource_table: NeResourceTable::default(),
+            resident_name_table: ResidentNameTable {
+                entries: Vec::new(),
+            },
+            module_reference_table: ModuleReferenceTable {
+                entries: Vec::new(),
+            },
+            imported_name_table: ImportedNameTable::default(),
+            entry_table: EntryTable {
+                entries: vec![SegmentEntry::Fixed(FixedSegmentEntry {
+                    segment: 3,
+                    flags: 1,
+                    offset: 0x20,
+                })],
+            },
+            nonresident_name_table: NonresidentNameTable {
+                entries: Vec::new(),
+            },
+            relocation_tables_per_segment: vec![
+                RelocationTable {
+                    entries: vec![internal],
+                },
+                RelocationTable::default(),
+                RelocationTable::default(),
+            ],
+            overlay: None,
+            codepage: Default::default(),
+            warnings: Vec::new(),
+            views: Default::default(),
+        }
+    }
+
+    #[test]
+    fn reorder_renumbers_references() {
+        let mut editor = NeEditor::new(module());
+        assert_eq!(editor.module().exports()[0].segment, 3);
+        editor.reorder_segments(&[3, 1, 2]).unwrap();
+        let ne = editor.module();
+        assert_eq!(ne.exports()[0].segment, 1);
+        assert_eq!(ne.segment_entries[2].header.flags, 1);
+        assert_eq!(ne.ne_header.auto_data_segment_index.value(), 3);
+        assert_eq!(ne.ne_header.entry_point.value(), 0x0002_0010);
+        assert_eq!(ne.ne_header.init_stack.value(), 0x0003_0100);
+        assert!(matches!(
+            ne.entry_table.entries[0],
+            SegmentEntry::Fixed(FixedSegmentEntry { segment: 1, .. })
+        ));
+        // relocations moved with segment 1 and point to the new number of segment 2
+        let RelocationTarget::Internal(f) = &ne.relocation_tables_per_segment[1].entries[0].target
+        else {
+            panic!("internal relocation expected");
+        };
+        assert_eq!(f.segment, 3);
+
+        assert!(editor.reorder_segments(&[1, 1, 2]).is_err());
+        assert!(editor.reorder_segments(&[1, 2]).is_err());
+    }
+
+    #[test]
+    fn moveable_converts_entries() {
+        let mut editor = NeEditor::new(module());
+        editor.set_moveable(3, true).unwrap();
+        assert!(matches!(
+            editor.module().entry_table.entries[0],
+            SegmentEntry::Moveable(MoveableSegmentEntry { segment: 3, .. })
+        ));
+        assert_eq!(
+            editor.module().ne_header.movable_entry_point_count.value(),
+            1
+        );
+        editor.set_moveable(3, false).unwrap();
+        assert!(matches!(
+            editor.module().entry_table.entries[0],
+            SegmentEntry::Fixed(_)
+        ));
+        editor.set_preload(1, true).unwrap();
+        assert_eq!(editor.module().segment_entries[0].header.flags, SEG_PRELOAD);
+        assert!(editor.set_preload(4, true).is_err());
+    }
+
+    #[test]
+    fn save_patches_original() {
+        let mut module = module();
+        module.ne_header.magic = *b"NE";
+        module.ne_header.file_alignment_shift_count = 4.into();
+        let mut editor = NeEditor::new(module);
+        let icon = ResourceId::Int(3);
+        editor
+            .set_resource(&icon, &1.into(), 0x30, vec![1; 20])
+            .unwrap();
+        let original = editor.to_bytes().unwrap();
+
+        let owned = || NeOwned::new(original.clone()).unwrap();
+        let mut editor = NeEditor::from_owned(owned());
+        editor
+            .set_resource(&icon, &1.into(), 0x30, vec![2; 10])
+            .unwrap();
+        let saved = editor.save().unwrap();
+        assert_eq!(saved.data.len(), original.len());
+        assert_eq!(saved.patched.as_ref().map(Vec::len), Some(2));
+        let read = NeOwned::new(saved.data).unwrap();
+        // padded with zeros up to the old length
+        let data = read.resource_bytes(&icon, &1.into()).unwrap();
+        assert_eq!(data.len(), 16);
+        assert_eq!(data[..10], [2; 10]);
+        assert_eq!(data[10..], [0; 6]);
+
+        // too big for its place: moved to the end
+        let mut editor = NeEditor::from_owned(owned());
+        editor
+            .set_resource(&icon, &1.into(), 0x30, vec![3; 40])
+            .unwrap();
+        let saved = editor.save().unwrap();
+        assert!(saved.patched.is_some());
+        let read = NeOwned::new(saved.data).unwrap();
+        assert_eq!(
+            read.resource_bytes(&icon, &1.into()).unwrap()[..40],
+            [3; 40]
+        );
+
+        editor
+            .set_resource(&icon, &2.into(), 0x30, vec![4; 4])
+            .unwrap();
+        assert!(editor.save().unwrap().patched.is_none());
+    }
+}
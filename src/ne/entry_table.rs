@@ -95,18 +95,26 @@ impl EntryTable {
         let mut _ordinal: u16 = 1; // entry index means ordinal in non/resident names tables
 
         while bytes_remaining > 0 {
-            // Read bundle header
-            let mut buffer = [0; 2];
-            r.read_exact(&mut buffer)?;
-            bytes_remaining -= 2;
-
-            let entries_count = buffer[0];
-            let seg_id = buffer[1];
+            // Read bundle header.
+            // Table usually ends by a single zero byte, so count goes first
+            let mut entries_count = 0;
+            r.read_exact(std::slice::from_mut(&mut entries_count))?;
+            bytes_remaining -= 1;
 
             if entries_count == 0 {
                 // End of table marker
                 break;
             }
+            if bytes_remaining == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Bundle header exceeds entry table length",
+                ));
+            }
+
+            let mut seg_id = 0;
+            r.read_exact(std::slice::from_mut(&mut seg_id))?;
+            bytes_remaining -= 1;
 
             if seg_id == 0 {
                 // Unused entries (padding between actual entries)
@@ -148,6 +156,19 @@ impl EntryTable {
     }
 }
 
+/// Entry is exported
+pub const ENTRY_EXPORTED: u8 = 0x01;
+/// Entry uses a global (shared) data segment
+pub const ENTRY_SHARED_DATA: u8 = 0x02;
+
+///
+/// Number of parameter words stored in upper 5 bits of entry flags.
+/// Loader copies these words of stack during ring transitions (OS/2 call gates).
+///
+pub fn parameter_words(flags: u8) -> u8 {
+    flags >> 3
+}
+
 #[derive(Debug, Clone)]
 pub enum SegmentEntry {
     Unused,
@@ -155,6 +176,29 @@ pub enum SegmentEntry {
     Moveable(MoveableSegmentEntry),
 }
 
+impl SegmentEntry {
+    pub fn flags(&self) -> Option<u8> {
+        match self {
+            SegmentEntry::Unused => None,
+            SegmentEntry::Fixed(entry) => Some(entry.flags),
+            SegmentEntry::Moveable(entry) => Some(entry.flags),
+        }
+    }
+
+    pub fn is_exported(&self) -> bool {
+        self.flags().is_some_and(|flags| flags & ENTRY_EXPORTED != 0)
+    }
+
+    pub fn is_shared_data(&self) -> bool {
+        self.flags()
+            .is_some_and(|flags| flags & ENTRY_SHARED_DATA != 0)
+    }
+
+    pub fn parameter_words(&self) -> u8 {
+        self.flags().map_or(0, parameter_words)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct FixedSegmentEntry {
     pub segment: u8,
@@ -194,3 +238,30 @@ impl MoveableSegmentEntry {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_read_sf_single_byte_terminator() {
+        // one unused entry, fixed bundle of one entry, movable bundle of one entry, end
+        let table = b"\
+            \x01\x00\
+            \x01\x01\x13\x34\x12\
+            \x01\xFF\x01\xCD\x3F\x02\x78\x56\
+            \x00";
+        let et = EntryTable::read_sf(&mut Cursor::new(&table[..]), table.len() as u16).unwrap();
+        assert_eq!(et.entries.len(), 3);
+        assert!(matches!(et.entries[0], SegmentEntry::Unused));
+        let fixed = &et.entries[1];
+        assert!(fixed.is_exported() && fixed.is_shared_data());
+        assert_eq!(fixed.parameter_words(), 2);
+        match et.entries[2] {
+            SegmentEntry::Moveable(e) => assert_eq!((e.segment, e.offset), (2, 0x5678)),
+            _ => panic!("movable entry expected"),
+        }
+    }
+}
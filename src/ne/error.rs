@@ -0,0 +1,151 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::ne::source::SourcedError;
+
+///
+/// Signatures of executable formats which may be found instead of the
+/// expected one, with names of the formats
+///
+const KNOWN_MAGICS: &[(&[u8; 2], &str)] = &[
+    (b"MZ", "DOS executable"),
+    (b"ZM", "DOS executable"),
+    (b"NE", "16-bit New Executable"),
+    (b"LE", "Linear Executable (VxD, DOS extender)"),
+    (b"LX", "OS/2 2.x Linear Executable"),
+    (b"PE", "Win32 Portable Executable"),
+    (b"W3", "WIN386.EXE container of VxDs"),
+    (b"W4", "compressed VxD container of Windows 95"),
+];
+
+///
+/// Payload of `io::Error` for files which can't be parsed as NE modules.
+/// Get it back by `NeError::from_io` to branch on the reason.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NeError {
+    ///
+    /// Signature at `offset` of file isn't the `expected` one
+    ///
+    BadMagic {
+        offset: u64,
+        expected: [u8; 2],
+        found: [u8; 2],
+    },
+    ///
+    /// Any other reason, with kind and message of the `io::Error` reporting it
+    ///
+    Malformed {
+        kind: io::ErrorKind,
+        message: String,
+    },
+}
+
+impl NeError {
+    ///
+    /// Payload of `e`, also under `SourcedError` naming the file
+    ///
+    pub fn from_io(e: &io::Error) -> Option<&NeError> {
+        match SourcedError::from_io(e) {
+            Some(sourced) => NeError::from_io(&sourced.error),
+            None => e.get_ref()?.downcast_ref::<NeError>(),
+        }
+    }
+
+    ///
+    /// Name of the format whose signature was found instead of the expected one
+    ///
+    pub fn found_format(&self) -> Option<&'static str> {
+        match self {
+            NeError::BadMagic { found, .. } => KNOWN_MAGICS
+                .iter()
+                .find(|(magic, _)| *magic == found)
+                .map(|&(_, name)| name),
+            NeError::Malformed { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for NeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NeError::BadMagic {
+                offset,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "invalid magic at {:#X}: expected \"{}\", found ",
+                    offset,
+                    String::from_utf8_lossy(expected)
+                )?;
+                match self.found_format() {
+                    Some(name) => write!(f, "\"{}\" ({})", String::from_utf8_lossy(found), name),
+                    None => write!(f, "{:02X} {:02X}", found[0], found[1]),
+                }
+            }
+            NeError::Malformed { message, .. } => f.write_str(message),
+        }
+    }
+}
+
+impl Error for NeError {}
+
+impl From<NeError> for io::Error {
+    fn from(e: NeError) -> Self {
+        let kind = match &e {
+            NeError::BadMagic { .. } => io::ErrorKind::InvalidInput,
+            NeError::Malformed { kind, .. } => *kind,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+impl From<io::Error> for NeError {
+    ///
+    /// Payload of `e` if it has one, or `Malformed` telling what `e` tells
+    ///
+    fn from(e: io::Error) -> Self {
+        match NeError::from_io(&e) {
+            Some(payload) => payload.clone(),
+            None => NeError::Malformed {
+                kind: e.kind(),
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_found_format() {
+        let e: io::Error = NeError::BadMagic {
+            offset: 0x80,
+            expected: *b"NE",
+            found: *b"PE",
+        }
+        .into();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(
+            e.to_string(),
+            "invalid magic at 0x80: expected \"NE\", found \"PE\" (Win32 Portable Executable)"
+        );
+        assert!(matches!(
+            NeError::from_io(&e),
+            Some(NeError::BadMagic { found, .. }) if found == b"PE"
+        ));
+
+        let random = NeError::BadMagic {
+            offset: 0,
+            expected: *b"MZ",
+            found: [0x7F, 0x45],
+        };
+        assert_eq!(random.found_format(), None);
+        assert!(random.to_string().ends_with("found 7F 45"));
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ne::dialect::FormatDialect;
+use crate::ne::entry_table::{parameter_words, SegmentEntry, ENTRY_EXPORTED, ENTRY_SHARED_DATA};
+use crate::ne::NeExecutable;
+use crate::util::demangle::demangle;
+
+///
+/// Entry point of the module joined with its name.
+///
+/// Names come from the resident names table (kept in memory by loader)
+/// or from the nonresident one (loaded only for `GetProcAddress` by name).
+/// Ordinals without names are exported by ordinal only.
+///
+#[derive(Debug, Clone)]
+pub struct Export {
+    pub ordinal: u16,
+    /// Shared with other views of the module (see `NeExecutable::names`)
+    pub name: Option<Arc<str>>,
+    pub is_resident_name: bool,
+    pub segment: u8,
+    pub offset: u16,
+    pub is_movable: bool,
+    pub flags: u8,
+    /// Conventions of the module, which give meaning to `flags`
+    pub dialect: FormatDialect,
+}
+
+impl Export {
+    pub fn is_exported(&self) -> bool {
+        self.flags & ENTRY_EXPORTED != 0
+    }
+
+    pub fn is_shared_data(&self) -> bool {
+        self.flags & ENTRY_SHARED_DATA != 0
+    }
+
+    ///
+    /// Words of stack copied for the call, 0 if dialect doesn't have them
+    ///
+    pub fn parameter_words(&self) -> u8 {
+        if !self.dialect.has_parameter_words() {
+            return 0;
+        }
+        parameter_words(self.flags)
+    }
+
+    ///
+    /// Readable flags of entry point and its name
+    ///
+    pub fn flag_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        if self.is_movable {
+            names.push("MOVEABLE".to_string());
+        }
+        if self.is_exported() {
+            names.push("EXPORTED".to_string());
+        }
+        if self.is_shared_data() {
+            names.push("SHARED_DATA".to_string());
+        }
+        if !self.is_resident_name && self.name.is_some() {
+            names.push("NONRESIDENT".to_string());
+        }
+        if self.parameter_words() != 0 {
+            names.push(format!("params={}", self.parameter_words()));
+        }
+        names
+    }
+
+    ///
+    /// Readable signature of decorated (C or C++) name
+    ///
+    pub fn demangled(&self) -> Option<String> {
+        demangle(self.name.as_deref()?)
+    }
+}
+
+impl fmt::Display for Export {
+    ///
+    /// `{}` gives ordinal, name and address; `{:#}` adds flags and demangled name
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "@{:<5} {:<32} {}:{:04X}",
+            self.ordinal,
+            self.name.as_deref().unwrap_or("<unnamed>"),
+            self.segment,
+            self.offset
+        )?;
+        if f.alternate() {
+            for name in self.flag_names() {
+                write!(f, " {}", name)?;
+            }
+            if let Some(demangled) = self.demangled() {
+                write!(f, " ; {}", demangled)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Builds the exports view: every used entry of entry table in ordinal order
+    /// (ordinals start at 1), with names found in name tables.
+    /// First entries of name tables (module name and description) are skipped.
+    ///
+    pub(crate) fn build_exports(&self) -> Vec<Export> {
+        let names = self.names();
+        let resident = self
+            .resident_name_table
+            .entries
+            .iter()
+            .zip(&names.resident)
+            .skip(1)
+            .map(|(e, name)| (e.index, name, true));
+        let nonresident = self
+            .nonresident_name_table
+            .entries
+            .iter()
+            .zip(&names.nonresident)
+            .skip(1)
+            .map(|(e, name)| (e.index, name, false));
+        // ordinal -> (name, is_resident); the first definition wins
+        let mut names_by_ordinal = BTreeMap::new();
+        for (index, name, is_resident) in resident.chain(nonresident) {
+            names_by_ordinal
+                .entry(index)
+                .or_insert_with(|| (name.clone(), is_resident));
+        }
+
+        let dialect = self.dialect();
+        let mut exports = Vec::new();
+        for (i, entry) in self.entry_table.entries.iter().enumerate() {
+            let ordinal = i as u16 + 1;
+            let (segment, offset, is_movable, flags) = match entry {
+                SegmentEntry::Unused => continue,
+                SegmentEntry::Fixed(e) => (e.segment, e.offset, false, e.flags),
+                SegmentEntry::Moveable(e) => (e.segment, e.offset, true, e.flags),
+            };
+            let (name, is_resident_name) = match names_by_ordinal.remove(&ordinal) {
+                Some((name, is_resident)) => (Some(name), is_resident),
+                None => (None, false),
+            };
+            exports.push(Export {
+                ordinal,
+                name,
+                is_resident_name,
+                segment,
+                offset,
+                is_movable,
+                flags,
+                dialect,
+            });
+        }
+        exports
+    }
+
+    ///
+    /// Entry point of ordinal, `None` for unused or missing entries
+    ///
+    pub fn export_by_ordinal(&self, ordinal: u16) -> Option<Export> {
+        // the view is in ordinal order
+        let exports = self.exports();
+        let i = exports.binary_search_by_key(&ordinal, |e| e.ordinal).ok()?;
+        Some(exports[i].clone())
+    }
+
+    ///
+    /// Entry point found like `GetProcAddress` of Win16 loader does:
+    /// `#123` is an ordinal, other names are upper-cased and compared
+    /// exactly with the resident names and then with the nonresident ones.
+    ///
+    pub fn export_by_name(&self, name: &str) -> Option<Export> {
+        let ordinal = match name.strip_prefix('#') {
+            Some(number) => number.parse().ok()?,
+            None => {
+                let name = name.to_ascii_uppercase();
+                let resident = self.resident_name_table.entries.iter().skip(1);
+                let nonresident = self.nonresident_name_table.entries.iter().skip(1);
+                resident
+                    .map(|e| (&e.name, e.index))
+                    .chain(nonresident.map(|e| (&e.name, e.index)))
+                    .find(|(entry_name, _)| entry_name.as_slice() == name.as_bytes())?
+                    .1
+            }
+        };
+        self.export_by_ordinal(ordinal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::ne::fixture::sample_bytes;
+    use crate::ne::NeExecutable;
+
+    #[test]
+    fn finds_exports_like_get_proc_address() {
+        let ne = NeExecutable::read(&mut Cursor::new(sample_bytes())).unwrap();
+        let found = |name| ne.export_by_name(name).map(|e| (e.ordinal, e.offset));
+        assert_eq!(found("getprocaddress"), Some((1, 0x00)));
+        assert_eq!(found("#5"), Some((5, 0x30)));
+        assert_eq!(found("#x"), None);
+        assert_eq!(found("#0"), None);
+        assert_eq!(found("#6"), None);
+        // named only by the nonresident table
+        assert_eq!(found("HiddenProc"), Some((5, 0x30)));
+        // module name and description aren't exports
+        assert_eq!(found("SAMPLE"), None);
+        assert_eq!(found("Sample application"), None);
+    }
+}
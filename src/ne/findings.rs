@@ -0,0 +1,260 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ne::lint::Lint;
+use crate::ne::warning::Warning;
+
+///
+/// How much a finding matters, from notes to signs of a broken module
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Unusual, harmless for the loader
+    Info,
+    /// Loads, but some callers or loaders misbehave
+    Warn,
+    /// Loader refuses the module or runs into garbage
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+        })
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "warn" | "warning" => Ok(Severity::Warn),
+            "error" => Ok(Severity::Error),
+            _ => Err(format!("unknown severity `{}`", s)),
+        }
+    }
+}
+
+///
+/// Documentation of one finding code
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FindingInfo {
+    /// `NE-Wnnn` of parse warnings, `NE-Lnnn` of lints
+    pub code: &'static str,
+    pub severity: Severity,
+    /// Name of the `Warning` or `Lint` variant
+    pub name: &'static str,
+    pub explanation: &'static str,
+}
+
+///
+/// Every finding code. Codes are never renumbered nor reused:
+/// new findings get the next number, removed ones leave a gap.
+///
+pub const FINDINGS: &[FindingInfo] = &[
+    FindingInfo {
+        code: "NE-W001",
+        severity: Severity::Info,
+        name: "LongName",
+        explanation: "A name of the resident, nonresident or module reference table is \
+                      longer than `ParseOptions::long_name_length`. Linkers truncate names, \
+                      so long ones come from hand-made or damaged tables.",
+    },
+    FindingInfo {
+        code: "NE-W002",
+        severity: Severity::Info,
+        name: "EmptyNameTable",
+        explanation: "The resident or nonresident names table has no entries: the module \
+                      has no name or no description. Names of such modules come from the file.",
+    },
+    FindingInfo {
+        code: "NE-W003",
+        severity: Severity::Warn,
+        name: "ResourceCountMismatch",
+        explanation: "The resource entries field of the NE header isn't the count of resource \
+                      types. Windows linkers leave it zero; other values are left by tools \
+                      which edited resources without fixing the header.",
+    },
+    FindingInfo {
+        code: "NE-L001",
+        severity: Severity::Error,
+        name: "InvalidMovableThunk",
+        explanation: "A movable entry doesn't hold `INT 3Fh`, which the loader patches into \
+                      a far jump when the segment is loaded: calls through it run garbage.",
+    },
+    FindingInfo {
+        code: "NE-L002",
+        severity: Severity::Warn,
+        name: "OrdinalConflict",
+        explanation: "Export names and ordinals don't map one to one, so `GetProcAddress` by \
+                      name and import by ordinal may give different entries.",
+    },
+    FindingInfo {
+        code: "NE-L003",
+        severity: Severity::Error,
+        name: "EntrySegmentMissing",
+        explanation: "An entry references a segment the segment table doesn't have; \
+                      the loader fails importing it.",
+    },
+    FindingInfo {
+        code: "NE-L004",
+        severity: Severity::Error,
+        name: "EntryOutsideSegment",
+        explanation: "An entry offset lies beyond the memory the loader allocates for \
+                      its segment.",
+    },
+    FindingInfo {
+        code: "NE-L005",
+        severity: Severity::Info,
+        name: "OutsideGangload",
+        explanation: "A preloaded structure lies outside of the gangload (fast-load) area \
+                      or crosses its bound: the loader reads it separately, which only \
+                      slows loading down.",
+    },
+    FindingInfo {
+        code: "NE-L006",
+        severity: Severity::Warn,
+        name: "ParameterWordsMismatch",
+        explanation: "`RETF n` of an exported function pops another count of bytes than \
+                      parameter words of its entry declare: pascal callers through call \
+                      gates get their stack corrupted.",
+    },
+    FindingInfo {
+        code: "NE-L007",
+        severity: Severity::Warn,
+        name: "CodeOverrun",
+        explanation: "Code reached from an entry or a relocation target runs past the data \
+                      of its segment. It works only while the loader pads allocations \
+                      with zeros or maps more than the segment.",
+    },
+    FindingInfo {
+        code: "NE-L008",
+        severity: Severity::Error,
+        name: "TargetOutsideSegment",
+        explanation: "A relocation points beyond the memory the loader allocates for its \
+                      target segment.",
+    },
+];
+
+///
+/// Documentation of `code`, case-insensitive
+///
+pub fn explain(code: &str) -> Option<&'static FindingInfo> {
+    FINDINGS
+        .iter()
+        .find(|info| info.code.eq_ignore_ascii_case(code))
+}
+
+fn info(code: &str) -> &'static FindingInfo {
+    explain(code).expect("finding code is in FINDINGS")
+}
+
+///
+/// Warning or lint with its stable code and severity.
+/// `{}` of findings gives the message; `tagged` prefixes it with both.
+///
+pub trait Finding: fmt::Display {
+    fn code(&self) -> &'static str;
+
+    fn severity(&self) -> Severity {
+        info(self.code()).severity
+    }
+
+    fn explanation(&self) -> &'static str {
+        info(self.code()).explanation
+    }
+
+    ///
+    /// `[NE-L003 error] message`
+    ///
+    fn tagged(&self) -> String {
+        format!("[{} {}] {}", self.code(), self.severity(), self)
+    }
+}
+
+impl Finding for Warning {
+    fn code(&self) -> &'static str {
+        match self {
+            Warning::LongName { .. } => "NE-W001",
+            Warning::EmptyNameTable(_) => "NE-W002",
+            Warning::ResourceCountMismatch { .. } => "NE-W003",
+        }
+    }
+}
+
+impl Finding for Lint {
+    fn code(&self) -> &'static str {
+        match self {
+            Lint::InvalidMovableThunk { .. } => "NE-L001",
+            Lint::OrdinalConflict(_) => "NE-L002",
+            Lint::EntrySegmentMissing { .. } => "NE-L003",
+            Lint::EntryOutsideSegment { .. } => "NE-L004",
+            Lint::OutsideGangload(_) => "NE-L005",
+            Lint::ParameterWordsMismatch { .. } => "NE-L006",
+            Lint::CodeOverrun { .. } => "NE-L007",
+            Lint::TargetOutsideSegment { .. } => "NE-L008",
+        }
+    }
+}
+
+///
+/// Which findings to show: suppressed codes and the least severity
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FindingFilter {
+    /// Codes never shown, upper case
+    pub suppressed: BTreeSet<String>,
+    pub min_severity: Option<Severity>,
+}
+
+impl FindingFilter {
+    pub fn suppress(&mut self, code: &str) {
+        self.suppressed.insert(code.to_ascii_uppercase());
+    }
+
+    pub fn allows(&self, finding: &dyn Finding) -> bool {
+        !self.suppressed.contains(finding.code())
+            && self
+                .min_severity
+                .is_none_or(|min| finding.severity() >= min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::raw::NeTable;
+
+    #[test]
+    fn codes_are_unique_and_documented() {
+        let codes: BTreeSet<&str> = FINDINGS.iter().map(|info| info.code).collect();
+        assert_eq!(codes.len(), FINDINGS.len());
+
+        let warning = Warning::EmptyNameTable(NeTable::ResidentNames);
+        assert_eq!(explain("ne-w002").unwrap().name, "EmptyNameTable");
+        assert_eq!(warning.severity(), Severity::Info);
+        assert!(warning.tagged().starts_with("[NE-W002 info] "));
+        let lint = Lint::EntrySegmentMissing {
+            ordinal: 1,
+            segment: 9,
+        };
+        assert_eq!(lint.severity(), Severity::Error);
+
+        let mut filter = FindingFilter {
+            min_severity: Some(Severity::Warn),
+            ..Default::default()
+        };
+        assert!(!filter.allows(&warning));
+        assert!(filter.allows(&lint));
+        filter.suppress("ne-l003");
+        assert!(!filter.allows(&lint));
+        assert_eq!("WARNING".parse(), Ok(Severity::Warn));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::ne::entry_table::{EntryTable, FixedSegmentEntry, SegmentEntry};
+use crate::ne::header::NeHeader;
+use crate::ne::imported_name_table::ImportedNameTable;
+use crate::ne::module_reference_table::{
+    ModuleReferenceEntry, ModuleReferenceEntryHeader, ModuleReferenceTable,
+};
+use crate::ne::nonresident_name_table::{NonresidentNameEntry, NonresidentNameTable};
+use crate::ne::resident_name_table::{ResidentNameEntry, ResidentNameTable};
+use crate::ne::resource_table::{
+    NeResource, NeResourceHeader, NeResourceTable, NeResourceTableHeader, NeResourceType,
+    NeResourceTypeHeader, RT_ICON,
+};
+use crate::ne::segment_relocations::{
+    ImportByOrdinal, RelocationEntry, RelocationTable, RelocationTarget,
+};
+use crate::ne::segment_table::{NeSegment, NeSegmentHeader, SEG_RELOCINFO};
+use crate::ne::NeExecutable;
+
+/// Data segment flag of segment table records
+const SEG_DATA: u16 = 0x0001;
+
+///
+/// Windows 3.0 application `SAMPLE` for tests: code segment 1 of
+/// five exported entries and a fixup of `KERNEL.3`, data segment 2,
+/// icon 1 of 0x20 bytes. Entry 1 is resident `GETPROCADDRESS`,
+/// entry 5 is `HIDDENPROC` of the nonresident names table.
+///
+pub fn sample_module() -> NeExecutable {
+    let mut ne_header: NeHeader = bytemuck::Zeroable::zeroed();
+    ne_header.magic = *b"NE";
+    ne_header.major_linker_version = 5;
+    ne_header.minor_linker_version = 1;
+    ne_header.flags = 0x0302.into();
+    ne_header.auto_data_segment_index = 2.into();
+    ne_header.init_heap_size = 0x400.into();
+    ne_header.init_stack_size = 0x1000.into();
+    ne_header.entry_point = 0x0001_0000.into();
+    ne_header.init_stack = 0x0002_0000.into();
+    ne_header.file_alignment_shift_count = 4.into();
+    ne_header.target_os = 2;
+    ne_header.expected_win_ver = [0, 3];
+
+    let segment = |flags, data: Vec<u8>, min_alloc| NeSegment {
+        header: NeSegmentHeader {
+            data_offset_shifted: 1,
+            data_length: data.len() as u16,
+            flags,
+            min_alloc,
+        },
+        shift_count: 4,
+        data: Some(data),
+    };
+    let fixed = |offset| {
+        SegmentEntry::Fixed(FixedSegmentEntry {
+            segment: 1,
+            flags: 0x01,
+            offset,
+        })
+    };
+    let kernel_3 = RelocationEntry {
+        address_type: 3,
+        reloc_type: 1,
+        is_additive: false,
+        segment_offset: 0x21,
+        target: RelocationTarget::ImportByOrdinal(ImportByOrdinal {
+            module_index: 1,
+            ordinal: 3,
+        }),
+    };
+    let mut code = vec![0xCB; 0x40];
+    code[0x20..0x25].copy_from_slice(b"\x9A\xFF\xFF\0\0");
+
+    NeExecutable {
+        dos_header: Box::new(bytemuck::Zeroable::zeroed()),
+        dos_stub: Vec::new(),
+        ne_header: Box::new(ne_header),
+        segment_entries: vec![
+            segment(SEG_RELOCINFO, code, 0x40),
+            segment(SEG_DATA, vec![0; 0x10], 0x100),
+        ],
+        resource_table: NeResourceTable {
+            header: NeResourceTableHeader {
+                alignment_shift_count: 4,
+            },
+            resource_types: vec![NeResourceType {
+                header: NeResourceTypeHeader {
+                    type_id: 0x8000 | RT_ICON,
+                    num_resources: 1,
+                    res: [0; 2],
+                },
+                name: None,
+                resources: vec![NeResource {
+                    header: NeResourceHeader {
+                        data_offset_shifted: 1,
+                        data_length: 2,
+                        flags: 0x1030,
+                        resource_id: 0x8001,
+                        res: [0; 2],
+                    },
+                    name: None,
+                    data: Some((0..0x20).collect()),
+                }],
+            }],
+        },
+        resident_name_table: ResidentNameTable {
+            entries: vec![
+                ResidentNameEntry {
+                    name: b"SAMPLE".to_vec(),
+                    index: 0,
+                },
+                ResidentNameEntry {
+                    name: b"GETPROCADDRESS".to_vec(),
+                    index: 1,
+                },
+            ],
+        },
+        module_reference_table: ModuleReferenceTable {
+            entries: vec![ModuleReferenceEntry {
+                header: ModuleReferenceEntryHeader { offset: 1 },
+                name: b"KERNEL".to_vec(),
+            }],
+        },
+        imported_name_table: ImportedNameTable {
+            data: b"\x00\x06KERNEL".to_vec(),
+        },
+        entry_table: EntryTable {
+            entries: vec![
+                fixed(0x00),
+                fixed(0x08),
+                fixed(0x10),
+                fixed(0x18),
+                fixed(0x30),
+            ],
+        },
+        nonresident_name_table: NonresidentNameTable {
+            entries: vec![
+                NonresidentNameEntry {
+                    name: b"Sample application".to_vec(),
+                    index: 0,
+                },
+                NonresidentNameEntry {
+                    name: b"HIDDENPROC".to_vec(),
+                    index: 5,
+                },
+            ],
+        },
+        relocation_tables_per_segment: vec![
+            RelocationTable {
+                entries: vec![kernel_3],
+            },
+            RelocationTable::default(),
+        ],
+        overlay: None,
+        codepage: Default::default(),
+        warnings: Vec::new(),
+        views: Default::default(),
+    }
+}
+
+///
+/// File of `sample_module`
+///
+pub fn sample_bytes() -> Vec<u8> {
+    sample_module()
+        .to_bytes()
+        .expect("sample module is written")
+}
@@ -0,0 +1,315 @@
+use std::io;
+
+use crate::ne::addr::{Far16Ptr, Seg16};
+use crate::ne::entry_table::SegmentEntry;
+use crate::ne::imports::ImportTarget;
+use crate::ne::iter_segment_bytes;
+use crate::ne::segment_relocations::{OsFixup, RelocationEntry, RelocationTarget};
+use crate::ne::NeExecutable;
+
+/// Low byte of the offset
+pub const ADDR_LOBYTE: u8 = 0;
+/// 16-bit selector (segment)
+pub const ADDR_SELECTOR: u8 = 2;
+/// 32-bit far pointer: offset, then selector
+pub const ADDR_FAR: u8 = 3;
+/// 16-bit offset
+pub const ADDR_OFFSET: u8 = 5;
+/// 48-bit far pointer: 32-bit offset, then selector
+pub const ADDR_FAR48: u8 = 11;
+/// 32-bit offset
+pub const ADDR_OFFSET32: u8 = 13;
+
+///
+/// Place of segment patched by loader
+///
+#[derive(Debug, Clone)]
+pub struct FixupPlace {
+    pub offset: u16,
+    /// `ADDR_*` kind of the value
+    pub address_type: u8,
+    pub target: FixupTarget,
+}
+
+/// `flags` of segment: data is iterated (`iterations, length, bytes`)
+const SEG_ITERATED: u16 = 0x0002;
+
+///
+/// What relocation record points to, given to resolver of `fixed_segment`
+///
+#[derive(Debug, Clone)]
+pub enum FixupTarget {
+    /// Place in the module: segment number (from 1) and offset.
+    /// Moveable entries are already looked up in the entry table.
+    Internal { segment: u8, offset: u16 },
+    /// Procedure of another module
+    Import {
+        module: String,
+        procedure: ImportTarget,
+    },
+    /// Value added by loader to 8087 emulator calls
+    OsFixup(OsFixup),
+}
+
+impl NeExecutable {
+    ///
+    /// Data of one segment with its relocations applied, without loading
+    /// the rest of the module. Iterated data is expanded first.
+    /// Places of targets unknown to `resolver` keep their bytes from file.
+    /// \param number -- segment number (from 1)
+    /// \param resolver -- `selector:offset` of places in segments and of imports
+    ///
+    pub fn fixed_segment<F>(&self, number: u16, mut resolver: F) -> io::Result<Vec<u8>>
+    where
+        F: FnMut(&FixupTarget) -> Option<Far16Ptr>,
+    {
+        let mut data = self.expanded_segment(number)?;
+        let index = Seg16(number).index().unwrap_or_default();
+        let Some(table) = self.relocation_tables_per_segment.get(index) else {
+            return Ok(data);
+        };
+        for entry in &table.entries {
+            let Some(target) = self.fixup_target(&entry.target) else {
+                continue;
+            };
+            if let Some(address) = resolver(&target) {
+                apply_fixup(&mut data, entry, address)?;
+            }
+        }
+        Ok(data)
+    }
+
+    ///
+    /// Every place of segment patched by loader, by offset,
+    /// with chains of relocation records followed.
+    /// \param number -- segment number (from 1)
+    ///
+    pub fn fixup_places(&self, number: u16) -> io::Result<Vec<FixupPlace>> {
+        let data = self.expanded_segment(number)?;
+        let index = Seg16(number).index().unwrap_or_default();
+        let mut places = Vec::new();
+        for entry in self
+            .relocation_tables_per_segment
+            .get(index)
+            .into_iter()
+            .flat_map(|t| &t.entries)
+        {
+            let Some(target) = self.fixup_target(&entry.target) else {
+                continue;
+            };
+            for offset in chain_places(&data, entry)? {
+                places.push(FixupPlace {
+                    offset,
+                    address_type: entry.address_type,
+                    target: target.clone(),
+                });
+            }
+        }
+        places.sort_by_key(|p| p.offset);
+        Ok(places)
+    }
+
+    ///
+    /// Data of segment as loaded, before relocations: iterated data expanded
+    ///
+    fn expanded_segment(&self, number: u16) -> io::Result<Vec<u8>> {
+        let segment = self.segment(Seg16(number)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no segment #{}", number),
+            )
+        })?;
+        Ok(match &segment.data {
+            Some(data) if segment.header.flags & SEG_ITERATED != 0 => {
+                let length = data.get(2..4).map(|l| u16::from_le_bytes([l[0], l[1]]));
+                if length.is_none_or(|l| data.len() < 4 + l as usize) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("iterated data of segment #{} truncated", number),
+                    ));
+                }
+                iter_segment_bytes(data)
+            }
+            Some(data) => data.clone(),
+            None => Vec::new(),
+        })
+    }
+
+    ///
+    /// Target of relocation record; `None` for moveable entries
+    /// missing in the entry table
+    ///
+    fn fixup_target(&self, target: &RelocationTarget) -> Option<FixupTarget> {
+        let import = |module_index: u16, procedure: ImportTarget| FixupTarget::Import {
+            module: self
+                .referenced_module_name(module_index)
+                .unwrap_or_else(|| format!("<module #{}>", module_index)),
+            procedure,
+        };
+        Some(match target {
+            RelocationTarget::Internal(f) if f.is_movable => {
+                let entry = self
+                    .entry_table
+                    .entries
+                    .get((f.offset_or_ordinal as usize).checked_sub(1)?)?;
+                match entry {
+                    SegmentEntry::Moveable(e) => FixupTarget::Internal {
+                        segment: e.segment,
+                        offset: e.offset,
+                    },
+                    SegmentEntry::Fixed(e) => FixupTarget::Internal {
+                        segment: e.segment,
+                        offset: e.offset,
+                    },
+                    SegmentEntry::Unused => return None,
+                }
+            }
+            RelocationTarget::Internal(f) => FixupTarget::Internal {
+                segment: f.segment,
+                offset: f.offset_or_ordinal,
+            },
+            RelocationTarget::ImportByOrdinal(o) => {
+                import(o.module_index, ImportTarget::Ordinal(o.ordinal))
+            }
+            RelocationTarget::ImportByName(n) => {
+                let name = self
+                    .imported_name_table
+                    .name_at(n.name_offset)
+                    .map(|name| String::from_utf8_lossy(name).into_owned())?;
+                import(n.module_index, ImportTarget::Name(name))
+            }
+            RelocationTarget::OsFixup(o) => FixupTarget::OsFixup(*o),
+        })
+    }
+}
+
+///
+/// Patches places of relocation `entry` in segment `data`.
+/// Additive records add the offset to the value on file;
+/// others replace a chain of places, each holding offset of the next one
+/// till 0xFFFF.
+///
+pub fn apply_fixup(data: &mut [u8], entry: &RelocationEntry, address: Far16Ptr) -> io::Result<()> {
+    for offset in chain_places(data, entry)? {
+        patch(data, offset as usize, entry, address)?;
+    }
+    Ok(())
+}
+
+///
+/// Offsets of places of relocation `entry` in unpatched segment `data`:
+/// the one of additive record, or every link of the chain
+///
+pub fn chain_places(data: &[u8], entry: &RelocationEntry) -> io::Result<Vec<u16>> {
+    let mut places = Vec::new();
+    let mut offset = entry.segment_offset;
+    // a chain can't have more links than words in the segment
+    for _ in 0..=data.len() / 2 {
+        places.push(offset);
+        match read_u16(data, offset as usize) {
+            Some(next) if !entry.is_additive && next != 0xFFFF => offset = next,
+            _ => return Ok(places),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("relocation chain at {:#06X} loops", entry.segment_offset),
+    ))
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    let b = data.get(at..at.checked_add(2)?)?;
+    Some(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn patch(data: &mut [u8], at: usize, entry: &RelocationEntry, address: Far16Ptr) -> io::Result<()> {
+    let size = match entry.address_type {
+        ADDR_LOBYTE => 1,
+        ADDR_SELECTOR | ADDR_OFFSET => 2,
+        ADDR_FAR | ADDR_OFFSET32 => 4,
+        ADDR_FAR48 => 6,
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown address type {} at {:#06X}", other, at),
+            ))
+        }
+    };
+    let place = data.get_mut(at..at + size).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("relocation at {:#06X} is outside of segment", at),
+        )
+    })?;
+    let add = |old: u16| {
+        if entry.is_additive {
+            old.wrapping_add(address.offset)
+        } else {
+            address.offset
+        }
+    };
+    let word = |place: &[u8], at: usize| u16::from_le_bytes([place[at], place[at + 1]]);
+    match entry.address_type {
+        ADDR_LOBYTE => place[0] = add(place[0] as u16) as u8,
+        ADDR_SELECTOR => place.copy_from_slice(&address.segment.to_le_bytes()),
+        ADDR_OFFSET => {
+            let value = add(word(place, 0));
+            place.copy_from_slice(&value.to_le_bytes());
+        }
+        ADDR_FAR => {
+            let value = add(word(place, 0));
+            place[..2].copy_from_slice(&value.to_le_bytes());
+            place[2..].copy_from_slice(&address.segment.to_le_bytes());
+        }
+        ADDR_OFFSET32 | ADDR_FAR48 => {
+            let old = u32::from_le_bytes([place[0], place[1], place[2], place[3]]);
+            let value = if entry.is_additive {
+                old.wrapping_add(address.offset as u32)
+            } else {
+                address.offset as u32
+            };
+            place[..4].copy_from_slice(&value.to_le_bytes());
+            if entry.address_type == ADDR_FAR48 {
+                place[4..].copy_from_slice(&address.segment.to_le_bytes());
+            }
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::segment_relocations::InternalFixes;
+
+    fn entry(address_type: u8, is_additive: bool, segment_offset: u16) -> RelocationEntry {
+        RelocationEntry {
+            address_type,
+            reloc_type: 0,
+            is_additive,
+            segment_offset,
+            target: RelocationTarget::Internal(InternalFixes {
+                segment: 1,
+                is_movable: false,
+                offset_or_ordinal: 0x10,
+            }),
+        }
+    }
+
+    #[test]
+    fn chains_are_followed_and_additive_adds() {
+        let address = Far16Ptr::new(0x1234, 0x10);
+        // far pointers at 0 and 4, chained
+        let mut data = vec![0x04, 0x00, 0, 0, 0xFF, 0xFF, 0, 0];
+        apply_fixup(&mut data, &entry(ADDR_FAR, false, 0), address).unwrap();
+        assert_eq!(data, [0x10, 0x00, 0x34, 0x12, 0x10, 0x00, 0x34, 0x12]);
+
+        let mut data = vec![0x05, 0x00];
+        apply_fixup(&mut data, &entry(ADDR_OFFSET, true, 0), address).unwrap();
+        assert_eq!(data, [0x15, 0x00]);
+
+        let mut data = vec![0x00, 0x00];
+        assert!(apply_fixup(&mut data, &entry(ADDR_OFFSET, false, 0), address).is_err());
+    }
+}
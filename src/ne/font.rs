@@ -0,0 +1,518 @@
+use std::convert::TryInto;
+use std::io;
+
+use crate::ne::resource_table::{ResourceId, RT_FONT};
+use crate::ne::sink::ExtractSink;
+use crate::ne::NeExecutable;
+
+///
+/// Header of the Windows font resource (`.FNT` file contents).
+/// Every RT_FONT resource of `.FON` library is a complete `.FNT` file
+/// which starts with this header.
+///
+/// Versions 0x0100 (Windows 1.x/2.x), 0x0200 (Windows 3.0) and
+/// 0x0300 (Windows 3.x with large fonts support) share these fields.
+///
+#[derive(Debug, Clone)]
+pub struct FontHeader {
+    pub version: u16,
+    pub size: u32,
+    pub copyright: Vec<u8>,
+    pub font_type: u16,
+    pub points: u16,
+    pub vert_res: u16,
+    pub horiz_res: u16,
+    pub ascent: u16,
+    pub internal_leading: u16,
+    pub external_leading: u16,
+    pub italic: u8,
+    pub underline: u8,
+    pub strike_out: u8,
+    pub weight: u16,
+    pub charset: u8,
+    pub pix_width: u16,
+    pub pix_height: u16,
+    pub pitch_and_family: u8,
+    pub avg_width: u16,
+    pub max_width: u16,
+    pub first_char: u8,
+    pub last_char: u8,
+    pub default_char: u8,
+    pub break_char: u8,
+    pub width_bytes: u16,
+    pub device_offset: u32,
+    pub face_offset: u32,
+    pub bits_pointer: u32,
+    pub bits_offset: u32,
+}
+
+impl FontHeader {
+    /// Size of the fields common for all font versions
+    pub const SIZE: usize = 0x75;
+
+    pub fn read(data: &[u8]) -> io::Result<Self> {
+        if data.len() < Self::SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Font resource is too short: length={}", data.len()),
+            ));
+        }
+        let get_u16 = |pos: usize| u16::from_le_bytes(data[pos..pos + 2].try_into().unwrap());
+        let get_u32 = |pos: usize| u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+
+        let copyright = data[0x06..0x42]
+            .iter()
+            .copied()
+            .take_while(|&b| b != 0)
+            .collect();
+
+        Ok(Self {
+            version: get_u16(0x00),
+            size: get_u32(0x02),
+            copyright,
+            font_type: get_u16(0x42),
+            points: get_u16(0x44),
+            vert_res: get_u16(0x46),
+            horiz_res: get_u16(0x48),
+            ascent: get_u16(0x4A),
+            internal_leading: get_u16(0x4C),
+            external_leading: get_u16(0x4E),
+            italic: data[0x50],
+            underline: data[0x51],
+            strike_out: data[0x52],
+            weight: get_u16(0x53),
+            charset: data[0x55],
+            pix_width: get_u16(0x56),
+            pix_height: get_u16(0x58),
+            pitch_and_family: data[0x5A],
+            avg_width: get_u16(0x5B),
+            max_width: get_u16(0x5D),
+            first_char: data[0x5F],
+            last_char: data[0x60],
+            default_char: data[0x61],
+            break_char: data[0x62],
+            width_bytes: get_u16(0x63),
+            device_offset: get_u32(0x65),
+            face_offset: get_u32(0x69),
+            bits_pointer: get_u32(0x6D),
+            bits_offset: get_u32(0x71),
+        })
+    }
+
+    ///
+    /// Vector fonts have the lowest bit of `dfType` set.
+    ///
+    pub fn is_vector(&self) -> bool {
+        self.font_type & 0x0001 != 0
+    }
+}
+
+///
+/// Single font taken from RT_FONT resource.
+/// `data` is a complete `.FNT` file, which can be saved as is.
+///
+#[derive(Debug, Clone)]
+pub struct NeFont {
+    pub resource_id: ResourceId,
+    pub header: FontHeader,
+    pub face_name: String,
+    pub data: Vec<u8>,
+}
+
+impl NeFont {
+    pub fn read(resource_id: ResourceId, data: &[u8]) -> io::Result<Self> {
+        let header = FontHeader::read(data)?;
+        let face_name = data
+            .get(header.face_offset as usize..)
+            .map(|face| {
+                let len = face.iter().position(|&b| b == 0).unwrap_or(face.len());
+                String::from_utf8_lossy(&face[..len]).into_owned()
+            })
+            .unwrap_or_default();
+
+        // resource length is stored in alignment units: cut the padding off
+        let size = (header.size as usize).clamp(FontHeader::SIZE, data.len());
+        Ok(Self {
+            resource_id,
+            header,
+            face_name,
+            data: data[..size].to_vec(),
+        })
+    }
+
+    pub fn point_size(&self) -> u16 {
+        self.header.points
+    }
+
+    pub fn charset(&self) -> u8 {
+        self.header.charset
+    }
+
+    ///
+    /// Suggested name of `.fnt` file: face name, point size
+    /// and resource ID (fonts of one face often share the size).
+    ///
+    pub fn file_name(&self) -> String {
+        let face: String = self
+            .face_name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("{}_{}_{}.fnt", face, self.header.points, self.resource_id)
+    }
+}
+
+///
+/// Position and size of one character in the font bitmap
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlyphMetrics {
+    pub code: u8,
+    pub width: u16,
+    pub height: u16,
+    /// Offset of glyph bits from the beginning of the font (versions 2 and 3)
+    /// or bit offset inside the font strip (version 1)
+    pub offset: u32,
+}
+
+///
+/// Raster font with decoded character table.
+///
+/// Version 1 fonts store all glyphs in one strip of `dfWidthBytes` bytes
+/// per row. Versions 2 and 3 store glyphs one by one, in columns of
+/// 8 pixels wide: column bytes go from top to bottom.
+///
+#[derive(Debug, Clone)]
+pub struct RasterFont<'a> {
+    pub font: &'a NeFont,
+    pub glyphs: Vec<GlyphMetrics>,
+}
+
+impl<'a> RasterFont<'a> {
+    pub fn read(font: &'a NeFont) -> io::Result<Self> {
+        let h = &font.header;
+        if h.is_vector() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Vector fonts can't be rasterized",
+            ));
+        }
+        if h.last_char < h.first_char {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid character range: {}..{}", h.first_char, h.last_char),
+            ));
+        }
+        let count = (h.last_char - h.first_char) as usize + 1;
+        let data = &font.data;
+        let get_u16 = |pos: usize| {
+            data.get(pos..pos + 2)
+                .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+        };
+        let get_u32 = |pos: usize| {
+            data.get(pos..pos + 4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        };
+        let truncated = || io::Error::new(io::ErrorKind::InvalidData, "Truncated character table");
+
+        let mut glyphs = Vec::with_capacity(count);
+        for i in 0..count {
+            let code = h.first_char + i as u8;
+            let (width, offset) = match h.version {
+                0x0100 if h.pix_width != 0 => (h.pix_width, i as u32 * h.pix_width as u32),
+                0x0100 => {
+                    let start = get_u16(0x75 + i * 2).ok_or_else(truncated)?;
+                    let end = get_u16(0x75 + i * 2 + 2).ok_or_else(truncated)?;
+                    (end.saturating_sub(start), start as u32)
+                }
+                0x0200 => {
+                    let pos = 0x76 + i * 4;
+                    let width = get_u16(pos).ok_or_else(truncated)?;
+                    (width, get_u16(pos + 2).ok_or_else(truncated)? as u32)
+                }
+                0x0300 => {
+                    let pos = 0x94 + i * 6;
+                    let width = get_u16(pos).ok_or_else(truncated)?;
+                    (width, get_u32(pos + 2).ok_or_else(truncated)?)
+                }
+                version => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        format!("Unknown font version: 0x{:04X}", version),
+                    ))
+                }
+            };
+            glyphs.push(GlyphMetrics {
+                code,
+                width,
+                height: h.pix_height,
+                offset,
+            });
+        }
+        Ok(Self { font, glyphs })
+    }
+
+    pub fn glyph(&self, code: u8) -> Option<&GlyphMetrics> {
+        self.glyphs.iter().find(|g| g.code == code)
+    }
+
+    ///
+    /// Returns pixels of glyph row by row: 1 for ink, 0 for background.
+    /// Bits lying outside of the font data are treated as background.
+    ///
+    pub fn glyph_pixels(&self, glyph: &GlyphMetrics) -> Vec<u8> {
+        let h = &self.font.header;
+        let data = &self.font.data;
+        let (width, height) = (glyph.width as usize, glyph.height as usize);
+        let mut pixels = vec![0; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let (pos, bit) = if h.version == 0x0100 {
+                    let bit_x = glyph.offset as usize + x;
+                    let row = h.bits_offset as usize + y * h.width_bytes as usize;
+                    (row + bit_x / 8, bit_x % 8)
+                } else {
+                    (glyph.offset as usize + (x / 8) * height + y, x % 8)
+                };
+                if data.get(pos).is_some_and(|&b| b & (0x80 >> bit) != 0) {
+                    pixels[y * width + x] = 1;
+                }
+            }
+        }
+        pixels
+    }
+
+    ///
+    /// Draws all glyphs into a grid of `columns` cells per row.
+    /// Every cell is as wide as the widest glyph.
+    ///
+    pub fn render_atlas(&self, columns: usize) -> GlyphAtlas {
+        let columns = columns.clamp(1, self.glyphs.len().max(1));
+        let rows = self.glyphs.len().div_ceil(columns);
+        let cell_width = self.glyphs.iter().map(|g| g.width).max().unwrap_or(0) as u32;
+        let cell_height = self.font.header.pix_height as u32;
+        let width = cell_width * columns as u32;
+        let height = cell_height * rows as u32;
+
+        let mut pixels = vec![0; width as usize * height as usize];
+        let mut cells = Vec::with_capacity(self.glyphs.len());
+        for (i, glyph) in self.glyphs.iter().enumerate() {
+            let x0 = (i % columns) as u32 * cell_width;
+            let y0 = (i / columns) as u32 * cell_height;
+            let glyph_pixels = self.glyph_pixels(glyph);
+            for y in 0..glyph.height as u32 {
+                for x in 0..glyph.width as u32 {
+                    if glyph_pixels[(y * glyph.width as u32 + x) as usize] != 0 {
+                        pixels[((y0 + y) * width + x0 + x) as usize] = 0xFF;
+                    }
+                }
+            }
+            cells.push(AtlasCell {
+                glyph: *glyph,
+                x: x0,
+                y: y0,
+            });
+        }
+        GlyphAtlas {
+            width,
+            height,
+            cell_width,
+            cell_height,
+            pixels,
+            cells,
+        }
+    }
+}
+
+///
+/// Place of glyph on atlas
+///
+#[derive(Debug, Clone, Copy)]
+pub struct AtlasCell {
+    pub glyph: GlyphMetrics,
+    pub x: u32,
+    pub y: u32,
+}
+
+///
+/// Character grid picture of the font.
+/// `pixels` are 8-bit gray values, 0xFF for ink.
+///
+#[derive(Debug, Clone)]
+pub struct GlyphAtlas {
+    pub width: u32,
+    pub height: u32,
+    pub cell_width: u32,
+    pub cell_height: u32,
+    pub pixels: Vec<u8>,
+    pub cells: Vec<AtlasCell>,
+}
+
+impl GlyphAtlas {
+    ///
+    /// Writes atlas as binary PGM (netpbm graymap)
+    ///
+    pub fn write_pgm<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        write!(w, "P5\n{} {}\n255\n", self.width, self.height)?;
+        w.write_all(&self.pixels)
+    }
+
+    ///
+    /// Converts atlas to black glyphs on transparent background
+    ///
+    #[cfg(feature = "image")]
+    pub fn to_image(&self) -> crate::ne::bitmap::RgbaImage {
+        let rgba = self.pixels.iter().flat_map(|&ink| [0, 0, 0, ink]).collect();
+        crate::ne::bitmap::RgbaImage {
+            width: self.width,
+            height: self.height,
+            rgba,
+        }
+    }
+}
+
+///
+/// Human readable name of `dfCharSet` value
+///
+pub fn charset_name(charset: u8) -> &'static str {
+    match charset {
+        0 => "ANSI",
+        1 => "DEFAULT",
+        2 => "SYMBOL",
+        77 => "MAC",
+        128 => "SHIFTJIS",
+        129 => "HANGEUL",
+        134 => "GB2312",
+        136 => "CHINESEBIG5",
+        161 => "GREEK",
+        162 => "TURKISH",
+        177 => "HEBREW",
+        178 => "ARABIC",
+        186 => "BALTIC",
+        204 => "RUSSIAN",
+        222 => "THAI",
+        238 => "EASTEUROPE",
+        255 => "OEM",
+        _ => "unknown",
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Collects every RT_FONT resource of the module.
+    /// Mostly used for `.FON` font libraries, which are
+    /// resource-only NE modules.
+    ///
+    pub fn extract_fonts(&self) -> io::Result<Vec<NeFont>> {
+        self.resource_table
+            .resources_of_type(RT_FONT)
+            .filter_map(|resource| {
+                resource
+                    .data
+                    .as_ref()
+                    .map(|data| NeFont::read(resource.id(), data))
+            })
+            .collect()
+    }
+
+    ///
+    /// Puts every RT_FONT resource into `sink` as `.fnt` file
+    /// of its `file_name` and gives the fonts
+    ///
+    pub fn extract_fonts_to(&self, sink: &mut dyn ExtractSink) -> io::Result<Vec<NeFont>> {
+        let fonts = self.extract_fonts()?;
+        for font in &fonts {
+            sink.put(&font.file_name(), &font.data)?;
+        }
+        Ok(fonts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::fixture::sample_module;
+    use crate::ne::sink::MemorySink;
+
+    fn font_fixture() -> Vec<u8> {
+        let mut data = vec![0; 0x76];
+        data[0x00..0x02].copy_from_slice(&0x0200_u16.to_le_bytes());
+        data[0x02..0x06].copy_from_slice(&0x0080_u32.to_le_bytes());
+        data[0x06..0x0F].copy_from_slice(b"(c) Font\0");
+        data[0x44..0x46].copy_from_slice(&10_u16.to_le_bytes());
+        data[0x55] = 255;
+        data[0x58..0x5A].copy_from_slice(&13_u16.to_le_bytes());
+        data[0x5F] = 0x20;
+        data[0x60] = 0x7F;
+        data[0x69..0x6D].copy_from_slice(&0x0076_u32.to_le_bytes());
+        data.extend_from_slice(b"Terminal\0");
+        data.resize(0x90, 0);
+        data
+    }
+
+    #[test]
+    fn test_font_header() {
+        let h = FontHeader::read(&font_fixture()).unwrap();
+        assert_eq!(h.version, 0x0200);
+        assert_eq!(h.copyright, b"(c) Font");
+        assert_eq!(h.points, 10);
+        assert_eq!(h.charset, 255);
+        assert_eq!(h.pix_height, 13);
+        assert_eq!((h.first_char, h.last_char), (0x20, 0x7F));
+        assert!(!h.is_vector());
+    }
+
+    #[test]
+    fn test_font_face_and_size() {
+        let font = NeFont::read(ResourceId::Int(3), &font_fixture()).unwrap();
+        assert_eq!(font.face_name, "Terminal");
+        assert_eq!(font.data.len(), 0x80);
+        assert_eq!(font.file_name(), "Terminal_10_3.fnt");
+        assert_eq!(charset_name(font.charset()), "OEM");
+    }
+
+    #[test]
+    fn test_render_v2_glyphs() {
+        let mut data = font_fixture();
+        data[0x58..0x5A].copy_from_slice(&2_u16.to_le_bytes());
+        data[0x5F] = b'A';
+        data[0x60] = b'B';
+        // character table: A is 3 pixels wide, B is 9 pixels wide (two columns)
+        data[0x76..0x7E].copy_from_slice(&[3, 0, 0x80, 0, 9, 0, 0x82, 0]);
+        data.resize(0x88, 0);
+        data[0x80..0x82].copy_from_slice(&[0b1010_0000, 0b0100_0000]);
+        data[0x82..0x86].copy_from_slice(&[0xFF, 0x00, 0x80, 0x00]);
+        data[0x02..0x06].copy_from_slice(&0x0088_u32.to_le_bytes());
+
+        let font = NeFont::read(ResourceId::Int(1), &data).unwrap();
+        let raster = RasterFont::read(&font).unwrap();
+        let a = raster.glyph(b'A').unwrap();
+        assert_eq!((a.width, a.height), (3, 2));
+        assert_eq!(raster.glyph_pixels(a), vec![1, 0, 1, 0, 1, 0]);
+        let b = raster.glyph(b'B').unwrap();
+        assert_eq!(raster.glyph_pixels(b)[..9], [1, 1, 1, 1, 1, 1, 1, 1, 1]);
+
+        let atlas = raster.render_atlas(16);
+        assert_eq!((atlas.width, atlas.height), (18, 2));
+        assert_eq!(atlas.cells[1].x, 9);
+        assert_eq!(&atlas.pixels[..3], &[0xFF, 0, 0xFF]);
+    }
+
+    #[test]
+    fn test_font_too_short() {
+        assert!(NeFont::read(ResourceId::Int(1), &[0; 0x20]).is_err());
+    }
+
+    #[test]
+    fn test_fonts_go_to_sink() {
+        let mut ne = sample_module();
+        let fonts = &mut ne.resource_table.resource_types[0];
+        fonts.header.type_id = 0x8000 | RT_FONT;
+        fonts.resources[0].data = Some(font_fixture());
+        let mut sink = MemorySink::default();
+        let extracted = ne.extract_fonts_to(&mut sink).unwrap();
+        assert_eq!(extracted.len(), 1);
+        assert_eq!(sink.files.keys().collect::<Vec<_>>(), ["Terminal_10_1.fnt"]);
+        assert_eq!(sink.files["Terminal_10_1.fnt"], font_fixture()[..0x80]);
+    }
+}
@@ -0,0 +1,437 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::Path;
+
+use crate::ne::exports::Export;
+use crate::ne::header::NE_LIBRARY;
+use crate::ne::imports::ImportTarget;
+use crate::ne::mscompress::{compressed_file, expand_compressed, expanded_name};
+use crate::ne::packing::PackingScheme;
+use crate::ne::resolver::{provides, UnresolvedImport};
+use crate::ne::resource_search::collect_files;
+use crate::ne::NeExecutable;
+
+///
+/// Modules of Windows 3.1 which setup programs expect in place
+/// and never ship on their disks
+///
+pub const SYSTEM_MODULES: &[&str] = &[
+    "COMMDLG", "COMM", "DDEML", "DISPLAY", "GDI", "KERNEL", "KEYBOARD", "LZEXPAND", "MMSYSTEM",
+    "MOUSE", "OLECLI", "OLESVR", "SHELL", "SOUND", "SYSTEM", "TOOLHELP", "USER", "VER", "WIN87EM",
+];
+
+///
+/// What a file of the disk set holds after expanding
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiskFileKind {
+    /// New Executable module, listed in `InstallFootprint::modules`
+    Ne,
+    /// Linear Executable (`LE`, `LX`, `W3` ...): virtual device drivers
+    Linear { magic: [u8; 2] },
+    /// DOS program, or MZ file with unknown new header
+    Dos,
+    /// Anything else: `SETUP.INF`, help, fonts of other formats, data
+    Other,
+}
+
+impl fmt::Display for DiskFileKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiskFileKind::Ne => write!(f, "NE"),
+            DiskFileKind::Linear { magic } => write!(f, "{}", String::from_utf8_lossy(magic)),
+            DiskFileKind::Dos => write!(f, "DOS"),
+            DiskFileKind::Other => write!(f, "other"),
+        }
+    }
+}
+
+///
+/// File found on the disks
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskFile {
+    /// Path relative to the disk set directory, `/`-separated
+    pub path: String,
+    /// Packer of `COMPRESS.EXE` the file is stored with
+    pub packing: Option<PackingScheme>,
+    /// Name the file gets installed as: expanded name of compressed files
+    pub installed_name: String,
+    pub kind: DiskFileKind,
+    /// Error expanding the file, or parsing its NE module
+    pub error: Option<String>,
+}
+
+///
+/// How an NE module stands in the dependency graph of the disk set
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleRole {
+    /// Other modules of the set import it: installing anything needs it
+    DllRoot,
+    /// Program no module of the set imports: what the user runs
+    LeafApp,
+    /// Library nobody of the set imports: drivers, fonts and
+    /// plug-ins loaded by name at run time
+    Standalone,
+}
+
+impl fmt::Display for ModuleRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ModuleRole::DllRoot => "DLL root",
+            ModuleRole::LeafApp => "leaf app",
+            ModuleRole::Standalone => "standalone library",
+        })
+    }
+}
+
+///
+/// NE module of the disk set
+///
+#[derive(Debug, Clone)]
+pub struct FootprintModule {
+    /// Index of its file in `InstallFootprint::files`
+    pub file: usize,
+    /// Module name, uppercase
+    pub name: String,
+    pub is_library: bool,
+    /// Referenced modules, uppercase, in module reference table order
+    pub depends_on: Vec<String>,
+    /// Modules of the set referencing this one
+    pub importers: Vec<String>,
+    pub role: ModuleRole,
+    exports: Vec<Export>,
+    imports: Vec<(String, ImportTarget)>,
+}
+
+///
+/// NE modules of an install disk set and how they depend on each other
+///
+#[derive(Debug, Clone, Default)]
+pub struct InstallFootprint {
+    /// Every file in path order
+    pub files: Vec<DiskFile>,
+    /// NE modules in path order of their files
+    pub modules: Vec<FootprintModule>,
+    /// Referenced modules of Windows itself (`SYSTEM_MODULES`)
+    pub system: BTreeSet<String>,
+    /// Referenced modules neither on the disks nor of Windows
+    pub missing: BTreeSet<String>,
+    /// Imports modules of the set don't export
+    pub unresolved: Vec<UnresolvedImport>,
+}
+
+///
+/// Kind of expanded file `data` by the signature `e_lfanew` points to
+///
+fn identify(data: &[u8]) -> DiskFileKind {
+    if !data.starts_with(b"MZ") && !data.starts_with(b"ZM") {
+        return DiskFileKind::Other;
+    }
+    let signature = data
+        .get(0x3C..0x40)
+        .map(|lfanew| u32::from_le_bytes([lfanew[0], lfanew[1], lfanew[2], lfanew[3]]) as usize)
+        .and_then(|at| data.get(at..at.checked_add(2)?));
+    match signature {
+        Some(b"NE") => DiskFileKind::Ne,
+        Some(magic @ (b"LE" | b"LX" | b"W3" | b"W4")) => DiskFileKind::Linear {
+            magic: [magic[0], magic[1]],
+        },
+        _ => DiskFileKind::Dos,
+    }
+}
+
+impl FootprintModule {
+    fn new(file: usize, path: &str, ne: &NeExecutable) -> Self {
+        let name = ne
+            .module_name()
+            .unwrap_or_else(|| {
+                let file_name = path.rsplit('/').next().unwrap_or(path);
+                file_name.split('.').next().unwrap_or(file_name).to_string()
+            })
+            .to_uppercase();
+        Self {
+            file,
+            name,
+            is_library: ne.ne_header.flags.value() & NE_LIBRARY != 0,
+            depends_on: (1..=ne.module_reference_table.entries.len() as u16)
+                .filter_map(|i| ne.referenced_module_name(i))
+                .map(|name| name.to_uppercase())
+                .collect(),
+            importers: Vec::new(),
+            role: ModuleRole::LeafApp,
+            exports: ne.exports().to_vec(),
+            imports: ne
+                .imports()
+                .iter()
+                .map(|import| (import.module.to_uppercase(), import.target.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl InstallFootprint {
+    ///
+    /// Walks `dir` with subdirectories of every disk, expanding compressed
+    /// files (with feature `decompress`) and identifying modules in them
+    ///
+    pub fn scan(dir: &Path) -> io::Result<Self> {
+        let mut paths = Vec::new();
+        collect_files(dir, &mut paths)?;
+        let mut files = Vec::new();
+        for path in paths {
+            let relative = path.strip_prefix(dir).unwrap_or(&path);
+            let relative = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((relative, fs::read(&path)?));
+        }
+        Ok(Self::from_files(files))
+    }
+
+    ///
+    /// Footprint of files given by relative path and contents
+    ///
+    pub fn from_files(files: impl IntoIterator<Item = (String, Vec<u8>)>) -> Self {
+        let mut footprint = Self::default();
+        for (path, data) in files {
+            let file_name = path.rsplit('/').next().unwrap_or(&path).to_string();
+            let packing = compressed_file(&data).map(|packing| packing.scheme);
+            let installed_name = expanded_name(&file_name, &data).unwrap_or(file_name);
+            let mut file = DiskFile {
+                path,
+                packing,
+                installed_name,
+                kind: DiskFileKind::Other,
+                error: None,
+            };
+            match expand_compressed(&data) {
+                Ok(expanded) => {
+                    file.kind = identify(&expanded);
+                    if file.kind == DiskFileKind::Ne {
+                        match NeExecutable::read(&mut Cursor::new(&*expanded)) {
+                            Ok(ne) => footprint.modules.push(FootprintModule::new(
+                                footprint.files.len(),
+                                &file.installed_name,
+                                &ne,
+                            )),
+                            Err(e) => file.error = Some(e.to_string()),
+                        }
+                    }
+                }
+                Err(e) => file.error = Some(e.to_string()),
+            }
+            footprint.files.push(file);
+        }
+        footprint.link();
+        footprint
+    }
+
+    ///
+    /// Resolves references between modules: importers, roles,
+    /// modules coming from elsewhere and imports nobody exports
+    ///
+    fn link(&mut self) {
+        let names: Vec<String> = self.modules.iter().map(|m| m.name.clone()).collect();
+        for (index, name) in names.iter().enumerate() {
+            let importers: Vec<String> = self
+                .modules
+                .iter()
+                .filter(|m| m.name != *name && m.depends_on.contains(name))
+                .map(|m| m.name.clone())
+                .collect();
+            let module = &mut self.modules[index];
+            module.role = match (importers.is_empty(), module.is_library) {
+                (false, _) => ModuleRole::DllRoot,
+                (true, true) => ModuleRole::Standalone,
+                (true, false) => ModuleRole::LeafApp,
+            };
+            module.importers = importers;
+        }
+
+        for module in &self.modules {
+            for dependency in &module.depends_on {
+                if names.contains(dependency) {
+                    continue;
+                }
+                if SYSTEM_MODULES.contains(&dependency.as_str()) {
+                    self.system.insert(dependency.clone());
+                } else {
+                    self.missing.insert(dependency.clone());
+                }
+            }
+            for (target_module, target) in &module.imports {
+                // the first file of a module name is the one installed
+                let Some(exporter) = self.modules.iter().find(|m| m.name == *target_module) else {
+                    continue;
+                };
+                if !provides(&exporter.exports, target) {
+                    self.unresolved.push(UnresolvedImport {
+                        importer: module.name.clone(),
+                        module: target_module.clone(),
+                        target: target.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn modules_of_role(&self, role: ModuleRole) -> impl Iterator<Item = &FootprintModule> {
+        self.modules.iter().filter(move |m| m.role == role)
+    }
+}
+
+impl fmt::Display for InstallFootprint {
+    ///
+    /// `{}` gives counts, `{:#}` lists modules by role, other executables
+    /// and references leaving the disk set
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let compressed = self.files.iter().filter(|f| f.packing.is_some()).count();
+        write!(
+            f,
+            "{} files ({} compressed), {} NE modules: {} DLL roots, {} leaf apps, \
+             {} standalone; {} missing modules, {} unresolved imports",
+            self.files.len(),
+            compressed,
+            self.modules.len(),
+            self.modules_of_role(ModuleRole::DllRoot).count(),
+            self.modules_of_role(ModuleRole::LeafApp).count(),
+            self.modules_of_role(ModuleRole::Standalone).count(),
+            self.missing.len(),
+            self.unresolved.len()
+        )?;
+        if !f.alternate() {
+            return Ok(());
+        }
+        for role in [
+            ModuleRole::DllRoot,
+            ModuleRole::LeafApp,
+            ModuleRole::Standalone,
+        ] {
+            if self.modules_of_role(role).next().is_none() {
+                continue;
+            }
+            write!(f, "\n{}s:", role)?;
+            for module in self.modules_of_role(role) {
+                let file = &self.files[module.file];
+                write!(f, "\n\t{:<12} {}", module.name, file.path)?;
+                if file.packing.is_some() {
+                    write!(f, " -> {}", file.installed_name)?;
+                }
+                if !module.importers.is_empty() {
+                    write!(f, " <- {}", module.importers.join(", "))?;
+                }
+            }
+        }
+        let others: Vec<&DiskFile> = self
+            .files
+            .iter()
+            .filter(|file| file.kind != DiskFileKind::Other && file.kind != DiskFileKind::Ne)
+            .collect();
+        if !others.is_empty() {
+            write!(f, "\nOther executables:")?;
+            for file in others {
+                write!(f, "\n\t{:<12} {}", file.kind, file.path)?;
+            }
+        }
+        let failed: Vec<&DiskFile> = self.files.iter().filter(|f| f.error.is_some()).collect();
+        if !failed.is_empty() {
+            write!(f, "\nUnreadable:")?;
+            for file in failed {
+                write!(
+                    f,
+                    "\n\t{}: {}",
+                    file.path,
+                    file.error.as_deref().unwrap_or_default()
+                )?;
+            }
+        }
+        if !self.system.is_empty() {
+            let system: Vec<&str> = self.system.iter().map(String::as_str).collect();
+            write!(f, "\nWindows modules: {}", system.join(", "))?;
+        }
+        if !self.missing.is_empty() {
+            let missing: Vec<&str> = self.missing.iter().map(String::as_str).collect();
+            write!(f, "\nMissing modules: {}", missing.join(", "))?;
+        }
+        if !self.unresolved.is_empty() {
+            write!(f, "\nUnresolved imports:")?;
+            for import in &self.unresolved {
+                write!(f, "\n\t{}", import)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(file: usize, name: &str, is_library: bool, depends_on: &[&str]) -> FootprintModule {
+        FootprintModule {
+            file,
+            name: name.to_string(),
+            is_library,
+            depends_on: depends_on.iter().map(|s| s.to_string()).collect(),
+            importers: Vec::new(),
+            role: ModuleRole::LeafApp,
+            exports: Vec::new(),
+            imports: depends_on
+                .iter()
+                .map(|s| (s.to_string(), ImportTarget::Ordinal(1)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn classifies_roots_and_leaves() {
+        let mut vxd = vec![0; 0x84];
+        vxd[..2].copy_from_slice(b"MZ");
+        vxd[0x3C] = 0x80;
+        vxd[0x80..0x82].copy_from_slice(b"LE");
+        let mut footprint = InstallFootprint::from_files([
+            ("DISK1/SETUP.INF".to_string(), b"[setup]\r\n".to_vec()),
+            ("DISK1/DOSX.EXE".to_string(), b"MZ\x90\0".to_vec()),
+            ("DISK2/VSHARE.386".to_string(), vxd),
+        ]);
+        assert_eq!(footprint.files[0].kind, DiskFileKind::Other);
+        assert_eq!(footprint.files[1].kind, DiskFileKind::Dos);
+        assert_eq!(
+            footprint.files[2].kind,
+            DiskFileKind::Linear { magic: *b"LE" }
+        );
+        assert!(footprint.modules.is_empty());
+
+        footprint.modules = vec![
+            module(0, "APP", false, &["KERNEL", "APPLIB", "VBRUN100"]),
+            module(1, "APPLIB", true, &["KERNEL", "USER"]),
+            module(2, "APPDRV", true, &["KERNEL"]),
+        ];
+        footprint.link();
+        let roles: Vec<ModuleRole> = footprint.modules.iter().map(|m| m.role).collect();
+        assert_eq!(
+            roles,
+            [
+                ModuleRole::LeafApp,
+                ModuleRole::DllRoot,
+                ModuleRole::Standalone
+            ]
+        );
+        assert_eq!(footprint.modules[1].importers, ["APP"]);
+        assert_eq!(
+            footprint.system.iter().collect::<Vec<_>>(),
+            ["KERNEL", "USER"]
+        );
+        assert_eq!(footprint.missing.iter().collect::<Vec<_>>(), ["VBRUN100"]);
+        // APPLIB exports nothing
+        assert_eq!(footprint.unresolved.len(), 1);
+        assert_eq!(footprint.unresolved[0].to_string(), "APP -> APPLIB.@1");
+    }
+}
@@ -0,0 +1,431 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io;
+
+use crate::ne::addr::{Far16Ptr, Seg16};
+use crate::ne::fixups::FixupTarget;
+use crate::ne::NeExecutable;
+use crate::x86::{self, Immediate, Instruction};
+
+///
+/// Why an offset is taken as start of a function
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionSource {
+    /// `CS:IP` of the header
+    EntryPoint,
+    /// Entry of the entry table with the ordinal
+    Export(u16),
+    /// Target of near `CALL` at the offset
+    NearCall(u16),
+    /// Target of far `CALL` at the offset, known by its relocation
+    FarCall(u16),
+}
+
+impl fmt::Display for FunctionSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FunctionSource::EntryPoint => write!(f, "entry point"),
+            FunctionSource::Export(ordinal) => write!(f, "export @{}", ordinal),
+            FunctionSource::NearCall(at) => write!(f, "near call at {:04X}", at),
+            FunctionSource::FarCall(at) => write!(f, "far call at {:04X}", at),
+        }
+    }
+}
+
+///
+/// Functions of one code segment reachable from its entry points
+/// by direct calls and jumps
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionMap {
+    pub segment: Seg16,
+    /// Start offsets with the first reason found for each, by offset
+    pub functions: Vec<(u16, FunctionSource)>,
+    /// Instructions decoded on the way
+    pub instructions: usize,
+}
+
+impl FunctionMap {
+    pub fn offsets(&self) -> impl Iterator<Item = u16> + '_ {
+        self.functions.iter().map(|&(offset, _)| offset)
+    }
+}
+
+impl fmt::Display for FunctionMap {
+    ///
+    /// `{}` gives count of functions, `{:#}` also every start with its reason
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "segment #{}: {} functions, {} instructions",
+            self.segment,
+            self.functions.len(),
+            self.instructions
+        )?;
+        if f.alternate() {
+            for (offset, source) in &self.functions {
+                write!(f, "\n\t{:04X}  {}", offset, source)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// Target of relative `JMP`, `CALL`, `Jcc` or `LOOP`
+///
+pub(crate) fn relative_target(inst: &Instruction) -> Option<u16> {
+    let delta = match inst.immediate {
+        Immediate::Byte(b) => b as i8 as u16,
+        Immediate::Word(w) => w,
+        Immediate::DWord(d) => d as u16,
+        Immediate::None => return None,
+    };
+    Some((inst.end() as u16).wrapping_add(delta))
+}
+
+///
+/// Follows control flow of 16-bit `code` from `roots`. Far pointers of
+/// `CALL` and `JMP` must hold segment numbers, as `fixed_segment` gives them
+/// when internal references are resolved to themselves. Indirect calls
+/// and jumps are not followed.
+/// \param segment -- number of the segment `code` belongs to
+/// \param roots -- known function starts
+///
+pub fn discover(code: &[u8], segment: Seg16, roots: &[(u16, FunctionSource)]) -> FunctionMap {
+    let mut functions = BTreeMap::new();
+    let mut visited = vec![false; code.len()];
+    let mut work = Vec::new();
+    let mut instructions = 0;
+
+    let add_function = |functions: &mut BTreeMap<u16, FunctionSource>, offset, source| {
+        if (offset as usize) < code.len() {
+            functions.entry(offset).or_insert(source);
+        }
+    };
+    for &(offset, source) in roots {
+        add_function(&mut functions, offset, source);
+        work.push(offset);
+    }
+
+    while let Some(start) = work.pop() {
+        let mut pos = start as usize;
+        while pos < code.len() && !visited[pos] {
+            visited[pos] = true;
+            let inst = x86::decode(code, pos, false);
+            if inst.is_invalid {
+                break;
+            }
+            instructions += 1;
+            let at = pos as u16;
+            let far_target = match inst.immediate {
+                Immediate::DWord(ptr) => Some(Far16Ptr::from(ptr)),
+                _ => None,
+            }
+            .filter(|ptr| ptr.seg() == segment);
+
+            let ends_flow = match (inst.opcode, inst.opcode2) {
+                (0x70..=0x7F | 0xE0..=0xE3, None) => {
+                    work.extend(relative_target(&inst));
+                    false
+                }
+                (0xE9 | 0xEB, None) => {
+                    work.extend(relative_target(&inst));
+                    true
+                }
+                (0xE8, None) => {
+                    if let Some(target) = relative_target(&inst) {
+                        add_function(&mut functions, target, FunctionSource::NearCall(at));
+                        work.push(target);
+                    }
+                    false
+                }
+                (0x9A, None) => {
+                    if let Some(ptr) = far_target {
+                        add_function(&mut functions, ptr.offset, FunctionSource::FarCall(at));
+                        work.push(ptr.offset);
+                    }
+                    false
+                }
+                (0xEA, None) => {
+                    work.extend(far_target.map(|ptr| ptr.offset));
+                    true
+                }
+                // RET, RETF, IRET
+                (0xC2 | 0xC3 | 0xCA | 0xCB | 0xCF, None) => true,
+                // indirect near and far JMP
+                (0xFF, None) => matches!(inst.modrm.map(|m| (m >> 3) & 7), Some(4 | 5)),
+                _ => false,
+            };
+            if ends_flow {
+                break;
+            }
+            pos = inst.end();
+        }
+    }
+
+    FunctionMap {
+        segment,
+        functions: functions.into_iter().collect(),
+        instructions,
+    }
+}
+
+///
+/// Bytes of parameters which `RETF` instructions of the function at `start`
+/// pop off the stack: 0 for plain `RETF`, `n` for `RETF n`.
+/// Jumps are followed but calls are not, so only returns
+/// of the function itself are found.
+///
+pub fn far_returns(code: &[u8], start: u16) -> BTreeSet<u16> {
+    let mut returns = BTreeSet::new();
+    let mut visited = vec![false; code.len()];
+    let mut work = vec![start];
+
+    while let Some(start) = work.pop() {
+        let mut pos = start as usize;
+        while pos < code.len() && !visited[pos] {
+            visited[pos] = true;
+            let inst = x86::decode(code, pos, false);
+            if inst.is_invalid {
+                break;
+            }
+            let ends_flow = match (inst.opcode, inst.opcode2) {
+                (0x70..=0x7F | 0xE0..=0xE3, None) => {
+                    work.extend(relative_target(&inst));
+                    false
+                }
+                (0xE9 | 0xEB, None) => {
+                    work.extend(relative_target(&inst));
+                    true
+                }
+                (0xCA, None) => {
+                    if let Immediate::Word(bytes) = inst.immediate {
+                        returns.insert(bytes);
+                    }
+                    true
+                }
+                (0xCB, None) => {
+                    returns.insert(0);
+                    true
+                }
+                (0xC2 | 0xC3 | 0xCF | 0xEA, None) => true,
+                (0xFF, None) => matches!(inst.modrm.map(|m| (m >> 3) & 7), Some(4 | 5)),
+                _ => false,
+            };
+            if ends_flow {
+                break;
+            }
+            pos = inst.end();
+        }
+    }
+    returns
+}
+
+///
+/// Instruction of traced code which runs past the end of its segment data
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overrun {
+    /// Root the instruction was reached from
+    pub root: u16,
+    /// Offset of the instruction
+    pub offset: u32,
+    /// Offset after its last byte; equal to `offset` when the flow
+    /// itself falls off the end of code, or the root lies past it
+    pub end: u32,
+}
+
+///
+/// Follows near control flow of `code` from `roots` and finds instructions
+/// running past the end of `code`, and flow leaving it. As loader fills
+/// the rest of allocation with zeros, bytes after the end of `code` are
+/// taken as zeros. Flow falling off the end right after `CALL` or `INT`
+/// isn't counted, as the callee may not return.
+///
+pub fn overruns(code: &[u8], roots: &[u16]) -> Vec<Overrun> {
+    let mut padded = code.to_vec();
+    padded.resize(code.len() + 16, 0);
+    let mut overruns = Vec::new();
+    let mut visited = vec![false; code.len()];
+    let mut work: Vec<(u16, u16)> = roots.iter().map(|&root| (root, root)).collect();
+
+    while let Some((root, start)) = work.pop() {
+        let mut pos = start as usize;
+        if pos >= code.len() {
+            overruns.push(Overrun {
+                root,
+                offset: pos as u32,
+                end: pos as u32,
+            });
+            continue;
+        }
+        while pos < code.len() && !visited[pos] {
+            visited[pos] = true;
+            let inst = x86::decode(&padded, pos, false);
+            if inst.is_invalid {
+                break;
+            }
+            if inst.end() > code.len() {
+                overruns.push(Overrun {
+                    root,
+                    offset: pos as u32,
+                    end: inst.end() as u32,
+                });
+                break;
+            }
+            let reg = inst.modrm.map(|m| (m >> 3) & 7);
+            let ends_flow = match (inst.opcode, inst.opcode2) {
+                (0x70..=0x7F | 0xE0..=0xE3 | 0xE8, None) => {
+                    work.extend(relative_target(&inst).map(|target| (root, target)));
+                    false
+                }
+                (0xE9 | 0xEB, None) => {
+                    work.extend(relative_target(&inst).map(|target| (root, target)));
+                    true
+                }
+                (0xC2 | 0xC3 | 0xCA | 0xCB | 0xCF | 0xEA, None) => true,
+                (0xFF, None) => matches!(reg, Some(4 | 5)),
+                _ => false,
+            };
+            if ends_flow {
+                break;
+            }
+            let calls = match (inst.opcode, inst.opcode2) {
+                (0x9A | 0xCC | 0xCD | 0xCE | 0xE8, None) => true,
+                (0xFF, None) => matches!(reg, Some(2 | 3)),
+                _ => false,
+            };
+            pos = inst.end();
+            if pos == code.len() && !calls {
+                overruns.push(Overrun {
+                    root,
+                    offset: pos as u32,
+                    end: pos as u32,
+                });
+            }
+        }
+    }
+    overruns.sort_by_key(|o| (o.offset, o.root));
+    overruns.dedup_by_key(|o| o.offset);
+    overruns
+}
+
+impl NeExecutable {
+    ///
+    /// Segment of `CS:IP`; for libraries without it the segment
+    /// of the first entry
+    ///
+    pub fn entry_segment(&self) -> Option<Seg16> {
+        let entry_point = Far16Ptr::from(self.ne_header.entry_point.value());
+        if entry_point.segment != 0 {
+            return Some(entry_point.seg());
+        }
+        self.exports()
+            .iter()
+            .find(|e| e.segment != 0)
+            .map(|e| Seg16(e.segment as u16))
+    }
+
+    ///
+    /// Function starts of code `segment` reachable from `CS:IP` and entries
+    /// of the entry table in it, following direct near calls and jumps and
+    /// far ones with relocations to the same segment
+    ///
+    pub fn discover_functions(&self, segment: Seg16) -> io::Result<FunctionMap> {
+        let is_data = self
+            .segment(segment)
+            .is_some_and(|s| s.header.flags & 0x0001 != 0);
+        if is_data {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("segment #{} is a data segment", segment),
+            ));
+        }
+        // segment numbers stand for selectors, imports get null pointers
+        let code = self.fixed_segment(segment.0, |target| match target {
+            FixupTarget::Internal { segment, offset } => {
+                Some(Far16Ptr::new(*segment as u16, *offset))
+            }
+            FixupTarget::Import { .. } => Some(Far16Ptr::default()),
+            FixupTarget::OsFixup(_) => None,
+        })?;
+
+        let mut roots = Vec::new();
+        let entry_point = Far16Ptr::from(self.ne_header.entry_point.value());
+        if entry_point.seg() == segment {
+            roots.push((entry_point.offset, FunctionSource::EntryPoint));
+        }
+        for export in self.exports() {
+            if export.segment as u16 == segment.0 {
+                roots.push((export.offset, FunctionSource::Export(export.ordinal)));
+            }
+        }
+        Ok(discover(&code, segment, &roots))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_calls_and_jumps() {
+        let code = [
+            0xE8, 0x05, 0x00, // 0000: call 0008
+            0xEB, 0x04, // 0003: jmp 0009
+            0xCC, 0xCC, 0xCC, // 0005: (skipped)
+            0xC3, // 0008: ret
+            0x9A, 0x0F, 0x00, 0x01, 0x00, // 0009: call far 0001:000F
+            0xCB, // 000E: retf
+            0x9A, 0x00, 0x00, 0x02, 0x00, // 000F: call far 0002:0000
+            0xC3, // 0014: ret
+        ];
+        let map = discover(&code, Seg16(1), &[(0, FunctionSource::EntryPoint)]);
+        assert_eq!(
+            map.functions,
+            [
+                (0x00, FunctionSource::EntryPoint),
+                (0x08, FunctionSource::NearCall(0x00)),
+                (0x0F, FunctionSource::FarCall(0x09)),
+            ]
+        );
+        assert_eq!(map.instructions, 7);
+    }
+
+    #[test]
+    fn finds_returns_of_both_branches() {
+        let code = [
+            0x74, 0x04, // 0000: jz 0006
+            0xE8, 0x05, 0x00, // 0002: call 000A
+            0xCB, // 0005: retf
+            0xCA, 0x06, 0x00, // 0006: retf 6
+            0xCC, // 0009: (skipped)
+            0xCA, 0x02, 0x00, // 000A: retf 2 (callee)
+        ];
+        assert_eq!(far_returns(&code, 0), BTreeSet::from([0, 6]));
+        assert_eq!(far_returns(&code, 6), BTreeSet::from([6]));
+    }
+
+    #[test]
+    fn finds_code_running_past_the_end() {
+        let code = [
+            0x74, 0x02, // 0000: jz 0004, past the end
+            0xB8, 0x34, // 0002: mov ax, 0034 with the high byte past the end
+        ];
+        let overrun = |root, offset, end| Overrun { root, offset, end };
+        assert_eq!(
+            overruns(&code, &[0, 0x10]),
+            [
+                overrun(0, 2, 5),
+                overrun(0, 4, 4),
+                overrun(0x10, 0x10, 0x10)
+            ]
+        );
+        // falls off the end, but not after INT 21h
+        assert_eq!(overruns(&[0x40], &[0]), [overrun(0, 1, 1)]);
+        assert_eq!(overruns(&[0xCD, 0x21], &[0]), []);
+    }
+}
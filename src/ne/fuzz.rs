@@ -0,0 +1,178 @@
+use std::fmt;
+use std::io::{self, Cursor};
+
+use crate::ne::addr::Seg16;
+use crate::ne::error::NeError;
+use crate::ne::options::ParseOptions;
+use crate::ne::resource_preview::preview;
+use crate::ne::resource_table::ResourceId;
+use crate::ne::strings::StringsOptions;
+use crate::ne::symbols::OrdinalDatabase;
+use crate::ne::validate::validate_layout;
+use crate::ne::win32_res::ResExportOptions;
+use crate::ne::NeExecutable;
+
+/// Bytes one input may make parsers allocate, so that fuzzers find
+/// crashes instead of headers asking for gigabytes
+pub const FUZZ_MAX_TOTAL_BYTES: u64 = 16 << 20;
+/// Entries of each table one input may declare
+pub const FUZZ_MAX_TABLE_ENTRIES: usize = 0x4000;
+
+///
+/// What `parse_any_bytes` found in its input
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseSummary {
+    pub segments: usize,
+    pub relocations: usize,
+    pub resources: usize,
+    pub exports: usize,
+    pub imports: usize,
+    /// Distinct names of name tables
+    pub names: usize,
+    pub warnings: usize,
+    pub lints: usize,
+    /// Problems of layout found by `validate_layout`
+    pub layout_diagnostics: usize,
+    /// Structures their own parsers refused: resources, fonts,
+    /// code segments, self-loading header and the rewritten image
+    pub rejected: usize,
+}
+
+impl fmt::Display for ParseSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} segments, {} relocations, {} resources, {} exports, {} imports, \
+             {} names, {} warnings, {} lints, {} layout problems, {} rejected",
+            self.segments,
+            self.relocations,
+            self.resources,
+            self.exports,
+            self.imports,
+            self.names,
+            self.warnings,
+            self.lints,
+            self.layout_diagnostics,
+            self.rejected
+        )
+    }
+}
+
+///
+/// Options of `parse_any_bytes`: `ParseOptions` with limits for untrusted input
+///
+pub fn fuzz_options() -> ParseOptions {
+    ParseOptions {
+        max_total_bytes: Some(FUZZ_MAX_TOTAL_BYTES),
+        max_table_entries: Some(FUZZ_MAX_TABLE_ENTRIES),
+        ..ParseOptions::default()
+    }
+}
+
+///
+/// Single entry point for fuzzing harnesses: parses `data` as NE module
+/// within `fuzz_options` and runs every parser of its parts over it —
+/// layout checks, tables, relocations, views, lints, resource previews
+/// and conversions, fonts, code of segments — then writes the module
+/// and parses the result again. Parts failing their own parsers are
+/// counted as `rejected`; only a module which can't be read at all
+/// gives an error. Any panic is a bug.
+///
+pub fn parse_any_bytes(data: &[u8]) -> Result<ParseSummary, NeError> {
+    let layout_diagnostics = validate_layout(&mut Cursor::new(data))?.len();
+    let options = fuzz_options();
+    let ne = NeExecutable::read_with(&mut Cursor::new(data), &options)?;
+
+    let mut summary = ParseSummary {
+        segments: ne.segment_entries.len(),
+        relocations: ne
+            .relocation_tables_per_segment
+            .iter()
+            .map(|t| t.entries.len())
+            .sum(),
+        exports: ne.exports().len(),
+        imports: ne.imports().len(),
+        names: ne.names().distinct,
+        warnings: ne.warnings.len(),
+        lints: ne.lint().len(),
+        layout_diagnostics,
+        ..ParseSummary::default()
+    };
+    let export = ne.to_win32_res(&ResExportOptions {
+        codepage: ne.codepage,
+        ..ResExportOptions::default()
+    });
+    summary.rejected = export.skipped.len();
+    let mut reject = |result: io::Result<()>| {
+        if result.is_err() {
+            summary.rejected += 1;
+        }
+    };
+
+    for resource_type in &ne.resource_table.resource_types {
+        let type_id = match resource_type.id() {
+            ResourceId::Int(id) if !resource_type.is_custom() => Some(id),
+            _ => None,
+        };
+        for resource in &resource_type.resources {
+            summary.resources += 1;
+            if let (Some(type_id), Some(data)) = (type_id, &resource.data) {
+                reject(preview(type_id, &resource.id(), data, ne.codepage).map(drop));
+            }
+        }
+    }
+    reject(ne.extract_fonts().map(drop));
+
+    for number in 1..=ne.segment_entries.len() as u16 {
+        let segment = &ne.segment_entries[number as usize - 1];
+        if segment.header.flags & 0x0001 == 0 {
+            reject(ne.discover_functions(Seg16(number)).map(drop));
+        }
+        reject(ne.fixup_places(number).map(drop));
+    }
+    reject(ne.self_load_header().map(drop));
+    reject(ne.startup().map(drop));
+    reject(ne.listing(&OrdinalDatabase).map(drop));
+    ne.memory_adequacy(&OrdinalDatabase);
+    ne.segment_layouts();
+    ne.table_ranges();
+    ne.relocation_stats();
+    ne.named_ordinals();
+    ne.ordinal_conflicts();
+    ne.estimate_min_version();
+    ne.toolchain_guess();
+    ne.gangload_members();
+    ne.strings(&StringsOptions::default());
+
+    reject(ne.to_bytes().and_then(|written| {
+        NeExecutable::read_with(&mut Cursor::new(written), &options).map(drop)
+    }));
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_garbage_without_panic() {
+        assert!(matches!(
+            parse_any_bytes(b"PE\0\0"),
+            Err(NeError::Malformed { .. })
+        ));
+        let mut image = vec![0u8; 0x80];
+        image[..2].copy_from_slice(b"MZ");
+        image[0x3C] = 0x40;
+        image[0x40..0x42].copy_from_slice(b"LE");
+        assert!(matches!(
+            parse_any_bytes(&image),
+            Err(NeError::BadMagic { found, .. }) if &found == b"LE"
+        ));
+
+        // header claiming tables past the end of file
+        image[0x40..0x42].copy_from_slice(b"NE");
+        image[0x44..0x80].fill(0xFF);
+        assert!(parse_any_bytes(&image).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt;
+
+use crate::ne::addr::Seg16;
+use crate::ne::raw::NeTable;
+use crate::ne::resource_table::{type_name, ResourceId};
+use crate::ne::segment_table::SEG_PRELOAD;
+use crate::ne::NeExecutable;
+
+/// `flags` of resource: loaded at start
+const RES_PRELOAD: u16 = 0x0040;
+
+///
+/// Structure of the module which has a place on file
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AreaItem {
+    Table(NeTable),
+    /// Data of the segment
+    Segment(Seg16),
+    Resource {
+        type_id: ResourceId,
+        id: ResourceId,
+    },
+}
+
+impl fmt::Display for AreaItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AreaItem::Table(table) => write!(f, "{}", table),
+            AreaItem::Segment(segment) => write!(f, "segment #{}", segment),
+            AreaItem::Resource { type_id, id } => {
+                write!(f, "resource {} {}", type_name(type_id), id)
+            }
+        }
+    }
+}
+
+///
+/// Where a structure lies relative to the gangload area
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Inside,
+    Outside,
+    /// Begins on one side of its bound and ends on the other
+    Straddles,
+}
+
+///
+/// Structure of the module with its place on file
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AreaMember {
+    pub item: AreaItem,
+    pub offset: u64,
+    pub length: u64,
+    /// Segment (or its relocations) or resource loaded at start
+    pub preload: bool,
+    pub placement: Placement,
+}
+
+impl AreaMember {
+    ///
+    /// Preloaded data which loader can't take from the area
+    /// read at once, or any structure crossing its bound
+    ///
+    pub fn is_misplaced(&self) -> bool {
+        match self.placement {
+            Placement::Inside => false,
+            Placement::Outside => self.preload,
+            Placement::Straddles => true,
+        }
+    }
+}
+
+impl fmt::Display for AreaMember {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:#08X}-{:#08X} {}{}",
+            self.offset,
+            self.offset + self.length,
+            self.item,
+            if self.preload { " (preload)" } else { "" }
+        )
+    }
+}
+
+///
+/// Gangload (fast-load) area of Windows 3.x modules: part of file which
+/// loader reads at once to take preloaded segments and resources from
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GangloadArea {
+    pub offset: u64,
+    pub length: u64,
+    /// Tables, segments and resources in file order
+    pub members: Vec<AreaMember>,
+}
+
+impl GangloadArea {
+    pub fn with_placement(&self, placement: Placement) -> impl Iterator<Item = &AreaMember> {
+        self.members
+            .iter()
+            .filter(move |m| m.placement == placement)
+    }
+}
+
+impl fmt::Display for GangloadArea {
+    ///
+    /// `{}` gives bounds and counts, `{:#}` also structures inside,
+    /// outside and across the bounds
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let count = |placement| self.with_placement(placement).count();
+        write!(
+            f,
+            "gangload area {:#08X}-{:#08X}: {} inside, {} outside, {} across bounds",
+            self.offset,
+            self.offset + self.length,
+            count(Placement::Inside),
+            count(Placement::Outside),
+            count(Placement::Straddles)
+        )?;
+        if f.alternate() {
+            for (placement, title) in [
+                (Placement::Inside, "inside"),
+                (Placement::Outside, "outside"),
+                (Placement::Straddles, "across bounds"),
+            ] {
+                if count(placement) == 0 {
+                    continue;
+                }
+                write!(f, "\n{}:", title)?;
+                for member in self.with_placement(placement) {
+                    write!(f, "\n\t{}", member)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn placement(area: (u64, u64), offset: u64, length: u64) -> Placement {
+    let (start, end) = (area.0, area.0 + area.1);
+    if offset >= start && offset + length <= end {
+        Placement::Inside
+    } else if offset + length <= start || offset >= end {
+        Placement::Outside
+    } else {
+        Placement::Straddles
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Tables, segments and resources inside and outside of the gangload
+    /// area; `None` if the module has no such area
+    ///
+    pub fn gangload_members(&self) -> Option<GangloadArea> {
+        let area = self.ne_header.gangload_area()?;
+        let is_preloaded = |segment: Seg16| {
+            self.segment(segment)
+                .is_some_and(|s| s.header.flags & SEG_PRELOAD != 0)
+        };
+
+        let mut places = Vec::new();
+        for range in self.table_ranges() {
+            let preload = match range.table {
+                NeTable::Relocations(segment) => is_preloaded(segment),
+                _ => false,
+            };
+            places.push((
+                AreaItem::Table(range.table),
+                range.offset,
+                range.length,
+                preload,
+            ));
+        }
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            if segment.header.data_offset_shifted == 0 {
+                continue;
+            }
+            places.push((
+                AreaItem::Segment(Seg16::from_index(i)),
+                segment.data_offset(),
+                segment.data_length(),
+                segment.header.flags & SEG_PRELOAD != 0,
+            ));
+        }
+        let shift_count = self.resource_table.header.alignment_shift_count;
+        for resource_type in &self.resource_table.resource_types {
+            for resource in &resource_type.resources {
+                if resource.header.data_offset_shifted == 0 {
+                    continue;
+                }
+                places.push((
+                    AreaItem::Resource {
+                        type_id: resource_type.id(),
+                        id: resource.id(),
+                    },
+                    resource.data_offset(shift_count),
+                    resource.data_length(shift_count),
+                    resource.header.flags & RES_PRELOAD != 0,
+                ));
+            }
+        }
+        // stable for structures at the same offset
+        places.sort_by_key(|&(_, offset, _, _)| offset);
+
+        let members = places
+            .into_iter()
+            .map(|(item, offset, length, preload)| AreaMember {
+                item,
+                offset,
+                length,
+                preload,
+                placement: placement(area, offset, length),
+            })
+            .collect();
+        Some(GangloadArea {
+            offset: area.0,
+            length: area.1,
+            members,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn places_by_bounds() {
+        let area = (0x200, 0x100);
+        assert_eq!(placement(area, 0x200, 0x100), Placement::Inside);
+        assert_eq!(placement(area, 0x100, 0x100), Placement::Outside);
+        assert_eq!(placement(area, 0x300, 0x10), Placement::Outside);
+        assert_eq!(placement(area, 0x2F0, 0x20), Placement::Straddles);
+
+        let member = AreaMember {
+            item: AreaItem::Segment(Seg16(2)),
+            offset: 0x400,
+            length: 0x10,
+            preload: true,
+            placement: Placement::Outside,
+        };
+        assert!(member.is_misplaced());
+        assert_eq!(member.to_string(), "0x000400-0x000410 segment #2 (preload)");
+    }
+}
@@ -1,9 +1,20 @@
-use std::io::{self, Read};
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
 
 use bytemuck::{Pod, Zeroable};
 
+use crate::ne::addr::Far16Ptr;
+use crate::ne::dialect::{exe_flag_names, FormatDialect};
+use crate::ne::error::NeError;
 use crate::util::endian::{Lu16, Lu32};
 
+/// Module loads its own segments: procedures are at the start of segment 1
+pub const NE_SELFLOAD: u16 = 0x0800;
+/// Library is initialized for every process using it (MS-DOS 4.0 and OS/2)
+pub const NE_PPLI: u16 = 0x0004;
+/// Module is a library (DLL or driver), not a task
+pub const NE_LIBRARY: u16 = 0x8000;
+
 /// The New Executable header.
 /// Data structure which deserializes from binary
 /// 
@@ -49,93 +60,376 @@ impl NeHeader {
         Ok(bytemuck::cast(buf))
     }
 
-    pub fn check_magic(&self) -> io::Result<()> {
+    ///
+    /// Reads header at `offset` and checks its magic.
+    /// For callers which located the header themselves, not by MZ header.
+    ///
+    pub fn read_at<R: Read + Seek>(r: &mut R, offset: u64) -> io::Result<Self> {
+        r.seek(SeekFrom::Start(offset))?;
+        let header = Self::read(r)?;
+        header.check_magic(offset)?;
+        Ok(header)
+    }
+
+    ///
+    /// Checks `NE` signature of header read at file `offset`;
+    /// `NeError::BadMagic` tells what was found instead (`PE`, `LE`, ...)
+    ///
+    pub fn check_magic(&self, offset: u64) -> io::Result<()> {
         if self.magic != *b"NE" {
-            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid magic"));
+            return Err(NeError::BadMagic {
+                offset,
+                expected: *b"NE",
+                found: self.magic,
+            }
+            .into());
         }
         Ok(())
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::io::Cursor;
+///
+/// Fields of `NeHeader` as native integers, for consumers which don't
+/// need the zero-copy layout. `NeHeader::to_native` gives it;
+/// `NeHeader::from` converts it back.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NativeNeHeader {
+    pub magic: [u8; 2],
+    pub major_linker_version: u8,
+    pub minor_linker_version: u8,
+    pub entry_table_offset: u16,
+    pub entry_table_length: u16,
+    pub file_load_crc: u32,
+    pub flags: u16,
+    pub auto_data_segment_index: u16,
+    pub init_heap_size: u16,
+    pub init_stack_size: u16,
+    pub entry_point: u32,
+    pub init_stack: u32,
+    pub segment_count: u16,
+    pub module_references: u16,
+    pub non_resident_names_size: u16,
+    pub segment_table_offset: u16,
+    pub resource_table_offset: u16,
+    pub resident_names_table_offset: u16,
+    pub module_reference_table_offset: u16,
+    pub import_name_table_offset: u16,
+    pub non_resident_names_table_offset: u32,
+    pub movable_entry_point_count: u16,
+    pub file_alignment_shift_count: u16,
+    pub resource_table_entries: u16,
+    pub target_os: u8,
+    pub os2_exe_flags: u8,
+    pub return_thunk_offset: u16,
+    pub segment_reference_thunk_offset: u16,
+    pub min_code_swap: u16,
+    pub expected_win_ver: [u8; 2],
+}
 
-    use super::*;
+impl From<NeHeader> for NativeNeHeader {
+    fn from(h: NeHeader) -> Self {
+        Self {
+            magic: h.magic,
+            major_linker_version: h.major_linker_version,
+            minor_linker_version: h.minor_linker_version,
+            entry_table_offset: h.entry_table_offset.value(),
+            entry_table_length: h.entry_table_length.value(),
+            file_load_crc: h.file_load_crc.value(),
+            flags: h.flags.value(),
+            auto_data_segment_index: h.auto_data_segment_index.value(),
+            init_heap_size: h.init_heap_size.value(),
+            init_stack_size: h.init_stack_size.value(),
+            entry_point: h.entry_point.value(),
+            init_stack: h.init_stack.value(),
+            segment_count: h.segment_count.value(),
+            module_references: h.module_references.value(),
+            non_resident_names_size: h.non_resident_names_size.value(),
+            segment_table_offset: h.segment_table_offset.value(),
+            resource_table_offset: h.resource_table_offset.value(),
+            resident_names_table_offset: h.resident_names_table_offset.value(),
+            module_reference_table_offset: h.module_reference_table_offset.value(),
+            import_name_table_offset: h.import_name_table_offset.value(),
+            non_resident_names_table_offset: h.non_resident_names_table_offset.value(),
+            movable_entry_point_count: h.movable_entry_point_count.value(),
+            file_alignment_shift_count: h.file_alignment_shift_count.value(),
+            resource_table_entries: h.resource_table_entries.value(),
+            target_os: h.target_os,
+            os2_exe_flags: h.os2_exe_flags,
+            return_thunk_offset: h.return_thunk_offset.value(),
+            segment_reference_thunk_offset: h.segment_reference_thunk_offset.value(),
+            min_code_swap: h.min_code_swap.value(),
+            expected_win_ver: h.expected_win_ver,
+        }
+    }
+}
+
+impl From<NativeNeHeader> for NeHeader {
+    fn from(h: NativeNeHeader) -> Self {
+        Self {
+            magic: h.magic,
+            major_linker_version: h.major_linker_version,
+            minor_linker_version: h.minor_linker_version,
+            entry_table_offset: h.entry_table_offset.into(),
+            entry_table_length: h.entry_table_length.into(),
+            file_load_crc: h.file_load_crc.into(),
+            flags: h.flags.into(),
+            auto_data_segment_index: h.auto_data_segment_index.into(),
+            init_heap_size: h.init_heap_size.into(),
+            init_stack_size: h.init_stack_size.into(),
+            entry_point: h.entry_point.into(),
+            init_stack: h.init_stack.into(),
+            segment_count: h.segment_count.into(),
+            module_references: h.module_references.into(),
+            non_resident_names_size: h.non_resident_names_size.into(),
+            segment_table_offset: h.segment_table_offset.into(),
+            resource_table_offset: h.resource_table_offset.into(),
+            resident_names_table_offset: h.resident_names_table_offset.into(),
+            module_reference_table_offset: h.module_reference_table_offset.into(),
+            import_name_table_offset: h.import_name_table_offset.into(),
+            non_resident_names_table_offset: h.non_resident_names_table_offset.into(),
+            movable_entry_point_count: h.movable_entry_point_count.into(),
+            file_alignment_shift_count: h.file_alignment_shift_count.into(),
+            resource_table_entries: h.resource_table_entries.into(),
+            target_os: h.target_os,
+            os2_exe_flags: h.os2_exe_flags,
+            return_thunk_offset: h.return_thunk_offset.into(),
+            segment_reference_thunk_offset: h.segment_reference_thunk_offset.into(),
+            min_code_swap: h.min_code_swap.into(),
+            expected_win_ver: h.expected_win_ver,
+        }
+    }
+}
+
+impl NeHeader {
+    ///
+    /// Copy of the header with native integers
+    ///
+    pub fn to_native(&self) -> NativeNeHeader {
+        NativeNeHeader::from(*self)
+    }
+}
+
+///
+/// Getters of little-endian fields converted to native integers
+///
+macro_rules! native_getters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        impl NeHeader {
+            $(
+                pub fn $field(&self) -> $ty {
+                    self.$field.value()
+                }
+            )*
+        }
+    };
+}
+
+native_getters!(
+    entry_table_offset: u16,
+    entry_table_length: u16,
+    file_load_crc: u32,
+    flags: u16,
+    auto_data_segment_index: u16,
+    init_heap_size: u16,
+    init_stack_size: u16,
+    entry_point: u32,
+    init_stack: u32,
+    segment_count: u16,
+    module_references: u16,
+    non_resident_names_size: u16,
+    segment_table_offset: u16,
+    resource_table_offset: u16,
+    resident_names_table_offset: u16,
+    module_reference_table_offset: u16,
+    import_name_table_offset: u16,
+    non_resident_names_table_offset: u32,
+    movable_entry_point_count: u16,
+    file_alignment_shift_count: u16,
+    resource_table_entries: u16,
+    return_thunk_offset: u16,
+    segment_reference_thunk_offset: u16,
+    min_code_swap: u16,
+);
+
+///
+/// Names of program flags set in `flags`. Unknown bits are given in hex
+///
+pub fn flag_names(flags: u16, dialect: FormatDialect) -> Vec<String> {
+    (0..16)
+        .map(|shift| 1u16 << shift)
+        .filter(|mask| flags & mask != 0)
+        .map(|mask| match mask {
+            0x0001 => "SINGLEDATA".to_string(),
+            0x0002 => "MULTIPLEDATA".to_string(),
+            NE_PPLI if matches!(dialect, FormatDialect::Dos4 | FormatDialect::OS2) => {
+                "PPLI".to_string()
+            }
+            NE_SELFLOAD => "SELFLOAD".to_string(),
+            0x2000 => "LINK_ERROR".to_string(),
+            NE_LIBRARY => "LIBRARY".to_string(),
+            _ => format!("0x{:04X}", mask),
+        })
+        .collect()
+}
+
+///
+/// High bit of `target_os` set by PharLap 286|DOS-Extender linker:
+/// module runs under DOS through the extender, which provides
+/// the API of the target in low bits (OS/2 or Windows)
+///
+pub const TARGET_PHARLAP: u8 = 0x80;
+
+///
+/// Target with the API the PharLap extender provides,
+/// or `None` if module is not linked for the extender
+///
+pub fn pharlap_target(target_os: u8) -> Option<u8> {
+    match target_os {
+        0x81 | 0x82 => Some(target_os & !TARGET_PHARLAP),
+        _ => None,
+    }
+}
+
+pub fn target_os_name(target_os: u8) -> &'static str {
+    match target_os {
+        0x0 => "Not specified",
+        0x1 => "OS/2",
+        0x2 => "Windows/286",
+        0x3 => "MS-DOS 4.0 (multitasking)",
+        0x4 => "Windows/386",
+        0x5 => "Borland OSS",
+        0x81 => "PharLap 286|DOS-Extender, OS/2 API",
+        0x82 => "PharLap 286|DOS-Extender, Windows API",
+        _ => "Unknown",
+    }
+}
+
+impl NeHeader {
     ///
-    /// Common view of data without CPU specific
-    /// (data not depends on endianness)
-    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-    pub struct NeHeader2 {
-        pub magic: [u8; 2],
-        pub major_linker_version: u8,
-        pub minor_linker_version: u8,
-        pub entry_table_offset: u16,
-        pub entry_table_length: u16,
-        pub file_load_crc: u32,
-        pub flags: u16,
-        pub auto_data_segment_index: u16,
-        pub init_heap_size: u16,
-        pub init_stack_size: u16,
-        pub entry_point: u32,
-        pub init_stack: u32,
-        pub segment_count: u16,
-        pub module_references: u16,
-        pub non_resident_names_size: u16,
-        pub segment_table_offset: u16,
-        pub resource_table_offset: u16,
-        pub resident_names_table_offset: u16,
-        pub module_reference_table_offset: u16,
-        pub import_name_table_offset: u16,
-        pub non_resident_names_table_offset: u32,
-        pub movable_entry_point_count: u16,
-        pub file_alignment_shift_count: u16,
-        pub resource_table_entries: u16,
-        pub target_os: u8,
-        pub os2_exe_flags: u8,
-        pub return_thunk_offset: u16,
-        pub segment_reference_thunk_offset: u16,
-        pub min_code_swap: u16,
-        pub expected_win_ver: [u8; 2],
+    /// Readable (name, value) pairs of the most important fields
+    ///
+    pub fn summary_rows(&self) -> Vec<(&'static str, String)> {
+        let flags = flag_names(self.flags.value(), self.dialect()).join(" | ");
+        let entry_point = self.entry_point.value();
+        let init_stack = self.init_stack.value();
+        let mut rows = vec![
+            (
+                "Linker version",
+                format!(
+                    "{}.{}",
+                    self.major_linker_version, self.minor_linker_version
+                ),
+            ),
+            ("Flags", format!("0x{:04X} {}", self.flags.value(), flags)),
+            (
+                "Auto-data segment",
+                self.auto_data_segment_index.value().to_string(),
+            ),
+            (
+                "Initial heap size",
+                format!("0x{:04X}", self.init_heap_size.value()),
+            ),
+            (
+                "Initial stack size",
+                format!("0x{:04X}", self.init_stack_size.value()),
+            ),
+            (
+                "Entry point (CS:IP)",
+                Far16Ptr::from(entry_point).to_string(),
+            ),
+            (
+                "Initial stack (SS:SP)",
+                Far16Ptr::from(init_stack).to_string(),
+            ),
+            ("Segments", self.segment_count.value().to_string()),
+            ("Referenced modules", self.module_references.value().to_string()),
+            (
+                "Movable entry points",
+                self.movable_entry_point_count.value().to_string(),
+            ),
+            (
+                "File alignment shift",
+                self.file_alignment_shift_count.value().to_string(),
+            ),
+            (
+                "Target OS",
+                format!(
+                    "{} (0x{:02X})",
+                    target_os_name(self.target_os),
+                    self.target_os
+                ),
+            ),
+        ];
+        let dialect = self.dialect();
+        rows.push(("Format dialect", dialect.to_string()));
+        if self.os2_exe_flags != 0 {
+            rows.push((
+                "Other flags",
+                format!(
+                    "0x{:02X} {}",
+                    self.os2_exe_flags,
+                    exe_flag_names(self.os2_exe_flags, dialect).join(" | ")
+                ),
+            ));
+        }
+        if let Some((offset, length)) = self.gangload_area() {
+            rows.push((
+                "Gangload area",
+                format!("offset 0x{:08X} length 0x{:05X}", offset, length),
+            ));
+        } else if dialect == FormatDialect::Win2x && self.return_thunk_offset.value() != 0 {
+            rows.push((
+                "Return thunks",
+                format!("0x{:04X}", self.return_thunk_offset.value()),
+            ));
+            rows.push((
+                "Segment reference thunks",
+                format!("0x{:04X}", self.segment_reference_thunk_offset.value()),
+            ));
+        }
+        if dialect.has_expected_version() && self.expected_win_ver[1] != 0 {
+            rows.push((
+                "Expected Windows version",
+                format!("{}.{}", self.expected_win_ver[1], self.expected_win_ver[0]),
+            ));
+        }
+        rows
     }
+}
 
-    impl From<NeHeader> for NeHeader2 {
-        fn from(h: NeHeader) -> Self {
-            Self {
-                magic: h.magic,
-                major_linker_version: h.major_linker_version,
-                minor_linker_version: h.minor_linker_version,
-                entry_table_offset: h.entry_table_offset.value(),
-                entry_table_length: h.entry_table_length.value(),
-                file_load_crc: h.file_load_crc.value(),
-                flags: h.flags.value(),
-                auto_data_segment_index: h.auto_data_segment_index.value(),
-                init_heap_size: h.init_heap_size.value(),
-                init_stack_size: h.init_stack_size.value(),
-                entry_point: h.entry_point.value(),
-                init_stack: h.init_stack.value(),
-                segment_count: h.segment_count.value(),
-                module_references: h.module_references.value(),
-                non_resident_names_size: h.non_resident_names_size.value(),
-                segment_table_offset: h.segment_table_offset.value(),
-                resource_table_offset: h.resource_table_offset.value(),
-                resident_names_table_offset: h.resident_names_table_offset.value(),
-                module_reference_table_offset: h.module_reference_table_offset.value(),
-                import_name_table_offset: h.import_name_table_offset.value(),
-                non_resident_names_table_offset: h.non_resident_names_table_offset.value(),
-                movable_entry_point_count: h.movable_entry_point_count.value(),
-                file_alignment_shift_count: h.file_alignment_shift_count.value(),
-                resource_table_entries: h.resource_table_entries.value(),
-                target_os: h.target_os,
-                os2_exe_flags: h.os2_exe_flags,
-                return_thunk_offset: h.return_thunk_offset.value(),
-                segment_reference_thunk_offset: h.segment_reference_thunk_offset.value(),
-                min_code_swap: h.min_code_swap.value(),
-                expected_win_ver: h.expected_win_ver,
+impl fmt::Display for NeHeader {
+    ///
+    /// `{}` gives one line summary, `{:#}` gives aligned list of fields
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = flag_names(self.flags.value(), self.dialect()).join(" | ");
+        if !f.alternate() {
+            return write!(
+                f,
+                "LINK.EXE {}.{}, {}, {} segment(s), flags: {}",
+                self.major_linker_version,
+                self.minor_linker_version,
+                target_os_name(self.target_os),
+                self.segment_count.value(),
+                flags
+            );
+        }
+        for (i, (name, value)) in self.summary_rows().iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
             }
+            write!(f, "{:<28}{}", name, value)?;
         }
+        Ok(())
     }
+}
 
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
     #[test]
     fn test_ne_header_size() {
         assert_eq!(std::mem::size_of::<NeHeader>(), 0x40);
@@ -150,9 +444,12 @@ mod tests {
             \x00\x00\x08\x00\xFF\xFF\x02\x08\x00\x00\x00\x00\x00\x00\x00\x03\
         ";
         let h = NeHeader::read(&mut Cursor::new(buf)).unwrap();
+        assert_eq!(h.entry_table_offset(), 0x016C);
+        assert_eq!(h.non_resident_names_table_offset(), 0x076E);
+        assert_eq!(NeHeader::from(h.to_native()), h);
         assert_eq!(
-            NeHeader2::from(h),
-            NeHeader2 {
+            h.to_native(),
+            NativeNeHeader {
                 magic: *b"NE",
                 major_linker_version: 5,
                 minor_linker_version: 10,
@@ -186,4 +483,15 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_ne_header_read_at() {
+        let mut buf = vec![0; 0x50];
+        buf[0x10..0x12].copy_from_slice(b"NE");
+        buf[0x14] = 0x6C;
+        let h = NeHeader::read_at(&mut Cursor::new(&buf), 0x10).unwrap();
+        assert_eq!(h.entry_table_offset.value(), 0x6C);
+        assert!(NeHeader::read_at(&mut Cursor::new(&buf), 0).is_err());
+        assert!(NeHeader::read_at(&mut Cursor::new(&buf), 0x20).is_err());
+    }
 }
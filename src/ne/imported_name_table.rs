@@ -0,0 +1,53 @@
+use std::io::{self, Read};
+
+///
+/// The imported names table contains the names of modules and procedures
+/// that are imported by the executable file. Each entry is composed of
+/// a 1-byte field that contains the length of the string, followed by
+/// any number of characters. The strings are not null-terminated and are
+/// case sensitive.
+///
+/// Entries are referenced by offsets from the module reference table
+/// and from "import by name" relocation records, so the table is kept raw.
+///
+#[derive(Debug, Clone, Default)]
+pub struct ImportedNameTable {
+    pub data: Vec<u8>,
+}
+
+impl ImportedNameTable {
+    ///
+    /// \param size -- table ends where the entry table begins
+    ///
+    pub fn read<R: Read>(r: &mut R, size: u16) -> io::Result<Self> {
+        let mut data = vec![0; size as usize];
+        r.read_exact(&mut data)?;
+        Ok(Self { data })
+    }
+
+    ///
+    /// Length-prefixed string at the offset from the beginning of the table
+    ///
+    pub fn name_at(&self, offset: u16) -> Option<&[u8]> {
+        let offset = offset as usize;
+        let len = *self.data.get(offset)? as usize;
+        self.data.get(offset + 1..offset + 1 + len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_by_offset() {
+        let table = ImportedNameTable {
+            data: b"\x00\x06KERNEL\x04USER\x0BMESSAGE".to_vec(),
+        };
+        assert_eq!(table.name_at(1), Some(&b"KERNEL"[..]));
+        assert_eq!(table.name_at(8), Some(&b"USER"[..]));
+        // truncated name
+        assert_eq!(table.name_at(13), None);
+        assert_eq!(table.name_at(100), None);
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ne::segment_relocations::{OsFixup, RelocationTarget};
+use crate::ne::symbols::SymbolProvider;
+use crate::ne::NeExecutable;
+use crate::util::demangle::demangle;
+
+///
+/// Procedure of another module: imported by @ordinal or by name
+///
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ImportTarget {
+    Ordinal(u16),
+    Name(String),
+}
+
+impl ImportTarget {
+    ///
+    /// Readable signature of procedure imported by decorated name
+    ///
+    pub fn demangled(&self) -> Option<String> {
+        match self {
+            ImportTarget::Ordinal(_) => None,
+            ImportTarget::Name(name) => demangle(name),
+        }
+    }
+}
+
+impl fmt::Display for ImportTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportTarget::Ordinal(ordinal) => write!(f, "@{}", ordinal),
+            ImportTarget::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+///
+/// Imported procedure with every place of segments patched by loader for it.
+/// Win16 modules have no import table: imports are known only from relocations.
+///
+#[derive(Debug, Clone)]
+pub struct Import {
+    /// Index in module reference table, starting at 1
+    pub module_index: u16,
+    /// Shared with other views of the module (see `NeExecutable::names`)
+    pub module: Arc<str>,
+    pub target: ImportTarget,
+    /// Segment number (from 1) and offset of every fixup
+    pub references: Vec<(usize, u16)>,
+}
+
+impl Import {
+    ///
+    /// Name of procedure imported by ordinal, if `symbols` know it
+    ///
+    pub fn resolved_name(&self, symbols: &dyn SymbolProvider) -> Option<String> {
+        match self.target {
+            ImportTarget::Ordinal(ordinal) => symbols.name_by_ordinal(&self.module, ordinal),
+            ImportTarget::Name(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Import {
+    ///
+    /// `{}` gives `MODULE.target`, `{:#}` also lists fixup locations
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.module, self.target)?;
+        if f.alternate() {
+            for (segment, offset) in &self.references {
+                write!(f, "\n\t{}:{:04X}", segment, offset)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Name of referenced module by its index (from 1)
+    ///
+    pub fn referenced_module_name(&self, module_index: u16) -> Option<String> {
+        let entry = self
+            .module_reference_table
+            .entries
+            .get((module_index as usize).checked_sub(1)?)?;
+        Some(String::from_utf8_lossy(&entry.name).into_owned())
+    }
+
+    ///
+    /// Collects imported procedures from relocation records of all segments,
+    /// sorted by module and procedure.
+    ///
+    pub(crate) fn build_imports(&self) -> Vec<Import> {
+        let mut imports: Vec<Import> = Vec::new();
+        for (i, table) in self.relocation_tables_per_segment.iter().enumerate() {
+            for entry in &table.entries {
+                let (module_index, target) = match &entry.target {
+                    RelocationTarget::ImportByOrdinal(o) => {
+                        (o.module_index, ImportTarget::Ordinal(o.ordinal))
+                    }
+                    RelocationTarget::ImportByName(n) => {
+                        let name = self
+                            .imported_name_table
+                            .name_at(n.name_offset)
+                            .map(|name| String::from_utf8_lossy(name).into_owned())
+                            .unwrap_or_else(|| format!("<bad name offset {:#X}>", n.name_offset));
+                        (n.module_index, ImportTarget::Name(name))
+                    }
+                    _ => continue,
+                };
+                let reference = (i + 1, entry.segment_offset);
+                match imports
+                    .iter_mut()
+                    .find(|import| import.module_index == module_index && import.target == target)
+                {
+                    Some(import) => import.references.push(reference),
+                    None => imports.push(Import {
+                        module_index,
+                        module: module_index
+                            .checked_sub(1)
+                            .and_then(|i| self.names().modules.get(i as usize))
+                            .cloned()
+                            .unwrap_or_else(|| format!("<module #{}>", module_index).into()),
+                        target,
+                        references: vec![reference],
+                    }),
+                }
+            }
+        }
+        imports.sort_by(|a, b| (&a.module, &a.target).cmp(&(&b.module, &b.target)));
+        imports
+    }
+
+    ///
+    /// Floating point emulator fixups: segment number (from 1), offset and fixup,
+    /// in file order of segments and of their relocation records.
+    /// Loader patches them itself, they are not imports of any module.
+    ///
+    pub fn os_fixups(&self) -> Vec<(usize, u16, OsFixup)> {
+        let mut fixups = Vec::new();
+        for (i, table) in self.relocation_tables_per_segment.iter().enumerate() {
+            for entry in &table.entries {
+                if let RelocationTarget::OsFixup(fixup) = entry.target {
+                    fixups.push((i + 1, entry.segment_offset, fixup));
+                }
+            }
+        }
+        fixups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::ne::fixture::sample_module;
+    use crate::ne::segment_relocations::{OsFixupKind, RelocationTable};
+
+    #[test]
+    fn os_fixups_by_segment_and_offset() {
+        let mut ne = sample_module();
+        // FIWRQQ at 2:0004, FIDRQQ at 2:000A
+        let records = b"\x02\0\x02\x03\x04\0\x06\0\0\0\x02\x07\x0A\0\x05\0\0\0";
+        ne.relocation_tables_per_segment[1] =
+            RelocationTable::read(&mut Cursor::new(&records[..])).unwrap();
+
+        let fixups = ne.os_fixups();
+        let found: Vec<_> = fixups
+            .iter()
+            .map(|(segment, offset, fixup)| (*segment, *offset, fixup.kind()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (2, 0x0004, Some(OsFixupKind::Fiwrqq)),
+                (2, 0x000A, Some(OsFixupKind::Fidrqq)),
+            ]
+        );
+        // the import of segment 1 isn't an OS fixup
+        assert!(sample_module().os_fixups().is_empty());
+    }
+}
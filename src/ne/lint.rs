@@ -0,0 +1,494 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::ne::addr::{Far16Ptr, Seg16};
+use crate::ne::entry_table::SegmentEntry;
+use crate::ne::fixups::{FixupTarget, ADDR_FAR, ADDR_SELECTOR};
+use crate::ne::functions::{far_returns, overruns, Overrun};
+use crate::ne::gangload::{AreaMember, Placement};
+use crate::ne::ordinals::OrdinalConflict;
+use crate::ne::segment_table::NeSegment;
+use crate::ne::spec::CONSTANT_SEGMENT;
+use crate::ne::NeExecutable;
+
+///
+/// Suspicious but parseable condition found in the module.
+/// Lints don't stop parsing: loader may accept such files,
+/// but they are a sign of corruption, tampering or a broken linker.
+/// Every kind has a stable code and severity of `Finding`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lint {
+    ///
+    /// Movable entry must contain `INT 3Fh` (0xCD 0x3F) instruction,
+    /// which loader patches into a far jump when segment gets loaded.
+    ///
+    InvalidMovableThunk { ordinal: u16, found: [u8; 2] },
+    ///
+    /// Names and ordinals don't map one to one:
+    /// `GetProcAddress` gets another entry than one of definitions means
+    ///
+    OrdinalConflict(OrdinalConflict),
+    ///
+    /// Entry references a segment which the segment table doesn't have.
+    /// Fixed entries of segment 0xFE are constants and reference nothing.
+    ///
+    EntrySegmentMissing { ordinal: u16, segment: u8 },
+    ///
+    /// Entry offset lies beyond the memory the loader allocates for its segment
+    ///
+    EntryOutsideSegment {
+        ordinal: u16,
+        segment: u8,
+        offset: u16,
+        size: u64,
+    },
+    ///
+    /// Preloaded structure lies outside of the gangload area, or a structure
+    /// crosses its bound: loader reads it separately, which slows loading down
+    ///
+    OutsideGangload(AreaMember),
+    ///
+    /// `RETF n` of exported function pops other count of bytes than
+    /// parameter words of its entry declare: pascal callers get their
+    /// stack corrupted. Entries without parameter words are not checked.
+    ///
+    ParameterWordsMismatch {
+        ordinal: u16,
+        words: u8,
+        /// Distinct byte counts of `RETF` found in the function
+        popped: Vec<u16>,
+    },
+    ///
+    /// Code reached from an entry or a far relocation target runs past
+    /// the data of its segment: into zeros the loader fills the rest
+    /// of allocation with, past the allocation, or across the 64K limit
+    /// of the selector. Such modules load only by lenience of the loader
+    /// and break once it maps segments exactly.
+    ///
+    CodeOverrun {
+        segment: u16,
+        overrun: Overrun,
+        /// Bytes of segment data
+        length: u64,
+        /// Bytes the loader allocates for the segment
+        size: u64,
+    },
+    ///
+    /// Relocation of `segment` at `place` points beyond the memory
+    /// the loader allocates for its target segment
+    ///
+    TargetOutsideSegment {
+        segment: u16,
+        place: u16,
+        target: u8,
+        offset: u16,
+        size: u64,
+    },
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Lint::InvalidMovableThunk { ordinal, found } => write!(
+                f,
+                "movable entry @{} has {:02X} {:02X} instead of INT 3Fh (CD 3F)",
+                ordinal, found[0], found[1]
+            ),
+            Lint::OrdinalConflict(conflict) => write!(f, "{}", conflict),
+            Lint::EntrySegmentMissing { ordinal, segment } => write!(
+                f,
+                "entry @{} references segment {} which doesn't exist",
+                ordinal, segment
+            ),
+            Lint::EntryOutsideSegment {
+                ordinal,
+                segment,
+                offset,
+                size,
+            } => write!(
+                f,
+                "entry @{} at {}:{:04X} lies outside the segment of 0x{:X} bytes",
+                ordinal, segment, offset, size
+            ),
+            Lint::OutsideGangload(member) => match member.placement {
+                Placement::Straddles => {
+                    write!(f, "{} crosses bound of the gangload area", member)
+                }
+                _ => write!(f, "gangload area doesn't hold {}", member),
+            },
+            Lint::ParameterWordsMismatch {
+                ordinal,
+                words,
+                popped,
+            } => {
+                let popped: Vec<String> = popped
+                    .iter()
+                    .map(|bytes| format!("RETF {}", bytes))
+                    .collect();
+                write!(
+                    f,
+                    "entry @{} declares {} parameter words but returns with {}",
+                    ordinal,
+                    words,
+                    popped.join(", ")
+                )
+            }
+            Lint::CodeOverrun {
+                segment,
+                overrun,
+                length,
+                size,
+            } => {
+                write!(f, "code reached from {}:{:04X} ", segment, overrun.root)?;
+                if overrun.end > 0x10000 {
+                    write!(f, "crosses the 64K selector limit")?;
+                } else if overrun.end as u64 > *size {
+                    write!(f, "runs past 0x{:X} bytes allocated for the segment", size)?;
+                } else {
+                    write!(
+                        f,
+                        "runs into zeros past 0x{:X} bytes of segment data",
+                        length
+                    )?;
+                }
+                write!(f, " at {}:{:04X}", segment, overrun.offset)
+            }
+            Lint::TargetOutsideSegment {
+                segment,
+                place,
+                target,
+                offset,
+                size,
+            } => write!(
+                f,
+                "relocation at {}:{:04X} points to {}:{:04X} outside the segment of 0x{:X} bytes",
+                segment, place, target, offset, size
+            ),
+        }
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Runs every check over the parsed module
+    ///
+    pub fn lint(&self) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        lints.extend(validate_movable_entries(&self.entry_table.entries));
+        lints.extend(validate_entry_segments(
+            &self.entry_table.entries,
+            &self.segment_entries,
+        ));
+        lints.extend(
+            self.ordinal_conflicts()
+                .into_iter()
+                .map(Lint::OrdinalConflict),
+        );
+        lints.extend(self.validate_parameter_words());
+        lints.extend(self.validate_code_reach());
+        if let Some(area) = self.gangload_members() {
+            lints.extend(
+                area.members
+                    .into_iter()
+                    .filter(AreaMember::is_misplaced)
+                    .map(Lint::OutsideGangload),
+            );
+        }
+        lints
+    }
+
+    ///
+    /// Checks that exported functions take off the stack as many words
+    /// as their entries declare. Segments with broken relocations
+    /// or iterated data which doesn't expand are skipped.
+    ///
+    pub fn validate_parameter_words(&self) -> Vec<Lint> {
+        let mut code = BTreeMap::new();
+        let mut lints = Vec::new();
+        for export in self.exports() {
+            let words = export.parameter_words();
+            let segment = Seg16(export.segment as u16);
+            let is_code = self
+                .segment(segment)
+                .is_some_and(|s| s.header.flags & 0x0001 == 0);
+            if words == 0 || !is_code {
+                continue;
+            }
+            let code = code
+                .entry(segment)
+                .or_insert_with(|| self.fixed_segment(segment.0, |_| None).ok());
+            let Some(code) = code else {
+                continue;
+            };
+            let returns = far_returns(code, export.offset);
+            if returns.is_empty() || returns.iter().all(|&n| n == words as u16 * 2) {
+                continue;
+            }
+            lints.push(Lint::ParameterWordsMismatch {
+                ordinal: export.ordinal,
+                words,
+                popped: returns.into_iter().collect(),
+            });
+        }
+        lints
+    }
+
+    ///
+    /// Checks that code reachable from `CS:IP`, entries and targets
+    /// of far relocations stays inside data of its segment, and that
+    /// relocations point inside allocations of their target segments.
+    /// Segments with broken relocations are skipped.
+    ///
+    pub fn validate_code_reach(&self) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        let mut roots: BTreeMap<Seg16, BTreeSet<u16>> = BTreeMap::new();
+        let entry_point = Far16Ptr::from(self.ne_header.entry_point.value());
+        roots
+            .entry(entry_point.seg())
+            .or_default()
+            .insert(entry_point.offset);
+        for export in self.exports() {
+            roots
+                .entry(Seg16(export.segment as u16))
+                .or_default()
+                .insert(export.offset);
+        }
+
+        for number in 1..=self.segment_entries.len() as u16 {
+            let Ok(places) = self.fixup_places(number) else {
+                continue;
+            };
+            for place in places {
+                let FixupTarget::Internal { segment, offset } = place.target else {
+                    continue;
+                };
+                let Some(target) = self.segment(Seg16(segment as u16)) else {
+                    continue;
+                };
+                let size = allocated_size(target);
+                if place.address_type != ADDR_SELECTOR && offset as u64 >= size {
+                    lints.push(Lint::TargetOutsideSegment {
+                        segment: number,
+                        place: place.offset,
+                        target: segment,
+                        offset,
+                        size,
+                    });
+                } else if place.address_type == ADDR_FAR {
+                    roots
+                        .entry(Seg16(segment as u16))
+                        .or_default()
+                        .insert(offset);
+                }
+            }
+        }
+
+        for (segment, offsets) in roots {
+            let Some(target) = self.segment(segment) else {
+                continue;
+            };
+            if target.header.flags & 0x0001 != 0 {
+                continue;
+            }
+            let Ok(code) = self.fixed_segment(segment.0, |_| None) else {
+                continue;
+            };
+            let size = allocated_size(target);
+            let offsets: Vec<u16> = offsets.into_iter().collect();
+            lints.extend(
+                overruns(&code, &offsets)
+                    .into_iter()
+                    // offsets outside the allocation are entry and relocation lints
+                    .filter(|overrun| (overrun.offset as u64) < size)
+                    .map(|overrun| Lint::CodeOverrun {
+                        segment: segment.0,
+                        overrun,
+                        length: code.len() as u64,
+                        size,
+                    }),
+            );
+        }
+        lints
+    }
+}
+
+///
+/// Memory the loader allocates for `segment`: the larger of its data
+/// and its minimum allocation
+///
+pub(crate) fn allocated_size(segment: &NeSegment) -> u64 {
+    let data_length = if segment.header.data_offset_shifted == 0 {
+        0
+    } else {
+        segment.data_length()
+    };
+    segment.min_alloc().max(data_length)
+}
+
+///
+/// Checks that movable entries reference `INT 3Fh` thunks
+///
+pub fn validate_movable_entries(entries: &[SegmentEntry]) -> Vec<Lint> {
+    entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| match entry {
+            SegmentEntry::Moveable(e) if e.magic != *b"\xCD\x3F" => {
+                Some(Lint::InvalidMovableThunk {
+                    ordinal: i as u16 + 1,
+                    found: e.magic,
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+///
+/// Checks that entries reference existing segments at offsets inside them.
+/// Segment occupies the larger of its data and its allocation in memory.
+///
+pub fn validate_entry_segments(entries: &[SegmentEntry], segments: &[NeSegment]) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let ordinal = i as u16 + 1;
+        let (segment, offset) = match entry {
+            SegmentEntry::Unused => continue,
+            SegmentEntry::Fixed(e) if e.segment == CONSTANT_SEGMENT => continue,
+            SegmentEntry::Fixed(e) => (e.segment, e.offset),
+            SegmentEntry::Moveable(e) => (e.segment, e.offset),
+        };
+        let Some(target) = Seg16(segment as u16).index().and_then(|i| segments.get(i)) else {
+            lints.push(Lint::EntrySegmentMissing { ordinal, segment });
+            continue;
+        };
+        let size = allocated_size(target);
+        if offset as u64 >= size {
+            lints.push(Lint::EntryOutsideSegment {
+                ordinal,
+                segment,
+                offset,
+                size,
+            });
+        }
+    }
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::entry_table::{FixedSegmentEntry, MoveableSegmentEntry};
+    use crate::ne::segment_table::NeSegmentHeader;
+
+    fn segment(data_offset_shifted: u16, data_length: u16, min_alloc: u16) -> NeSegment {
+        NeSegment {
+            header: NeSegmentHeader {
+                data_offset_shifted,
+                data_length,
+                flags: 0,
+                min_alloc,
+            },
+            shift_count: 4,
+            data: None,
+        }
+    }
+
+    #[test]
+    fn entries_lie_inside_allocations() {
+        let segments = [
+            // data is larger than the allocation
+            segment(1, 0x40, 0x20),
+            // allocation is larger than the data
+            segment(8, 0x10, 0x100),
+            // no data in the file: only the allocation counts
+            segment(0, 0x80, 0x30),
+        ];
+        let fixed = |segment, offset| {
+            SegmentEntry::Fixed(FixedSegmentEntry {
+                segment,
+                flags: 0x01,
+                offset,
+            })
+        };
+        let moveable = |segment, offset| {
+            SegmentEntry::Moveable(MoveableSegmentEntry {
+                flags: 0x01,
+                magic: *b"\xCD\x3F",
+                segment,
+                offset,
+            })
+        };
+        let missing = |segment| {
+            Some(Lint::EntrySegmentMissing {
+                ordinal: 1,
+                segment,
+            })
+        };
+        let outside = |segment, offset, size| {
+            Some(Lint::EntryOutsideSegment {
+                ordinal: 1,
+                segment,
+                offset,
+                size,
+            })
+        };
+        let cases = [
+            (SegmentEntry::Unused, None),
+            (fixed(CONSTANT_SEGMENT, 0xFFFF), None),
+            (fixed(0, 0), missing(0)),
+            (fixed(4, 0), missing(4)),
+            (moveable(4, 0), missing(4)),
+            (fixed(1, 0x3F), None),
+            (fixed(1, 0x40), outside(1, 0x40, 0x40)),
+            (moveable(2, 0xFF), None),
+            (moveable(2, 0x100), outside(2, 0x100, 0x100)),
+            (fixed(3, 0x2F), None),
+            (fixed(3, 0x30), outside(3, 0x30, 0x30)),
+            (fixed(3, 0x7F), outside(3, 0x7F, 0x30)),
+        ];
+        for (entry, expected) in cases {
+            assert_eq!(
+                validate_entry_segments(std::slice::from_ref(&entry), &segments),
+                Vec::from_iter(expected),
+                "{:?}",
+                entry
+            );
+        }
+    }
+
+    #[test]
+    fn moveable_entries_call_int_3f() {
+        let moveable = |magic| {
+            SegmentEntry::Moveable(MoveableSegmentEntry {
+                flags: 0x01,
+                magic,
+                segment: 1,
+                offset: 0,
+            })
+        };
+        let entries = [
+            moveable(*b"\xCD\x3F"),
+            SegmentEntry::Unused,
+            moveable(*b"\xCD\x21"),
+            SegmentEntry::Fixed(FixedSegmentEntry {
+                segment: 1,
+                flags: 0x01,
+                offset: 0,
+            }),
+        ];
+        let lints = validate_movable_entries(&entries);
+        assert_eq!(
+            lints,
+            [Lint::InvalidMovableThunk {
+                ordinal: 3,
+                found: [0xCD, 0x21],
+            }]
+        );
+        assert_eq!(
+            lints[0].to_string(),
+            "movable entry @3 has CD 21 instead of INT 3Fh (CD 3F)"
+        );
+    }
+}
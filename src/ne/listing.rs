@@ -0,0 +1,334 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io;
+
+use crate::ne::addr::Seg16;
+use crate::ne::fixups::{
+    FixupTarget, ADDR_FAR, ADDR_FAR48, ADDR_LOBYTE, ADDR_OFFSET, ADDR_OFFSET32, ADDR_SELECTOR,
+};
+use crate::ne::functions::relative_target;
+use crate::ne::symbols::SymbolProvider;
+use crate::ne::thunks::import_name;
+use crate::ne::NeExecutable;
+use crate::x86;
+
+/// Instruction bytes on one line of listing; longer instructions continue below
+const LISTING_BYTES_PER_LINE: usize = 6;
+
+const CONDITIONS: [&str; 16] = [
+    "o", "no", "b", "nb", "z", "nz", "be", "nbe", "s", "ns", "p", "np", "l", "nl", "le", "nle",
+];
+
+///
+/// Relocated place of a segment as listing shows it
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedRelocation {
+    /// `ADDR_*` kind of the value
+    pub address_type: u8,
+    /// Symbol of the target, `MODULE.NAME` of imports
+    pub target: String,
+}
+
+fn address_type_name(address_type: u8) -> &'static str {
+    match address_type {
+        ADDR_LOBYTE => "lobyte",
+        ADDR_SELECTOR => "selector",
+        ADDR_FAR => "far",
+        ADDR_OFFSET => "offset",
+        ADDR_FAR48 => "far48",
+        ADDR_OFFSET32 => "offset32",
+        _ => "addr?",
+    }
+}
+
+const REGISTERS: [&str; 8] = ["ax", "cx", "dx", "bx", "sp", "bp", "si", "di"];
+
+///
+/// Instructions of one opcode which `x86::Instruction` doesn't print:
+/// register pushes, returns, `MOV reg, imm16`, `INT`
+///
+fn simple_instruction(inst: &x86::Instruction) -> Option<String> {
+    let register = REGISTERS[inst.opcode as usize & 7];
+    let immediate = match inst.immediate {
+        x86::Immediate::Byte(b) => b as u16,
+        x86::Immediate::Word(w) => w,
+        _ => 0,
+    };
+    let text = match (inst.opcode, inst.opcode2) {
+        (0x40..=0x47, None) => format!("inc %{}", register),
+        (0x48..=0x4F, None) => format!("dec %{}", register),
+        (0x50..=0x57, None) => format!("push %{}", register),
+        (0x58..=0x5F, None) => format!("pop %{}", register),
+        (0x06 | 0x0E | 0x16 | 0x1E, None) => {
+            format!(
+                "push %{}",
+                ["es", "cs", "ss", "ds"][inst.opcode as usize >> 3]
+            )
+        }
+        (0x07 | 0x17 | 0x1F, None) => {
+            format!(
+                "pop %{}",
+                ["es", "cs", "ss", "ds"][inst.opcode as usize >> 3]
+            )
+        }
+        (0xB8..=0xBF, None) => format!("mov ${:#X}, %{}", immediate, register),
+        (0xC2, None) => format!("ret ${:#X}", immediate),
+        (0xC3, None) => "ret".to_string(),
+        (0xCA, None) => format!("retf ${:#X}", immediate),
+        (0xCB, None) => "retf".to_string(),
+        (0xCD, None) => format!("int ${:#X}", immediate),
+        (0x90, None) => "nop".to_string(),
+        _ => return None,
+    };
+    Some(text)
+}
+
+///
+/// Mnemonic of relative `CALL`, `JMP`, `Jcc`, `LOOP` or `JCXZ`
+///
+fn branch_mnemonic(inst: &x86::Instruction) -> Option<String> {
+    let name = match (inst.opcode, inst.opcode2) {
+        (0xE8, None) => "call",
+        (0xE9, None) => "jmp",
+        (0xEB, None) => "jmp short",
+        (0xE0, None) => "loopnz",
+        (0xE1, None) => "loopz",
+        (0xE2, None) => "loop",
+        (0xE3, None) => "jcxz",
+        (opcode @ 0x70..=0x7F, None) | (0x0F, Some(opcode @ 0x80..=0x8F)) => {
+            return Some(format!("j{}", CONDITIONS[opcode as usize & 0xF]))
+        }
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+///
+/// Assembler-style listing of 16-bit `code` of `segment`, decoded by
+/// linear sweep: offset, bytes, instruction and `;` notes of relocated
+/// places in it. Targets of branches are named by `labels`, or get
+/// `loc_XXXX` labels; far calls and jumps with relocations name their target.
+/// \param labels -- names of offsets: symbols and function starts
+/// \param relocations -- relocated places by offset
+///
+pub fn list_code(
+    code: &[u8],
+    segment: Seg16,
+    labels: &BTreeMap<u16, String>,
+    relocations: &BTreeMap<u16, ListedRelocation>,
+) -> String {
+    let mut instructions = Vec::new();
+    let mut labels = labels.clone();
+    let mut pos = 0;
+    while pos < code.len() {
+        let inst = x86::decode(code, pos, false);
+        pos = inst.end();
+        if let (Some(_), Some(target)) = (branch_mnemonic(&inst), relative_target(&inst)) {
+            if (target as usize) < code.len() {
+                labels
+                    .entry(target)
+                    .or_insert_with(|| format!("loc_{:04X}", target));
+            }
+        }
+        instructions.push(inst);
+    }
+
+    let mut out = String::new();
+    let name = format!("_SEG{}", segment);
+    let _ = writeln!(out, "{}\tSEGMENT", name);
+    for inst in &instructions {
+        let at = inst.pos as u16;
+        let end = inst.end().min(code.len());
+        if let Some(label) = labels.get(&at) {
+            let _ = writeln!(out, "{}:", label);
+        }
+
+        let text = match (branch_mnemonic(inst), relative_target(inst)) {
+            (Some(mnemonic), Some(target)) => match labels.get(&target) {
+                Some(label) => format!("{} {}", mnemonic, label),
+                None => format!("{} {:04X}", mnemonic, target),
+            },
+            _ if matches!((inst.opcode, inst.opcode2), (0x9A | 0xEA, None)) => {
+                let mnemonic = if inst.opcode == 0x9A {
+                    "call far"
+                } else {
+                    "jmp far"
+                };
+                match (
+                    relocations.get(&at.wrapping_add(1)),
+                    code.get(inst.pos + 1..end),
+                ) {
+                    (Some(relocation), _) if relocation.address_type == ADDR_FAR => {
+                        format!("{} {}", mnemonic, relocation.target)
+                    }
+                    (_, Some(&[o0, o1, s0, s1])) => format!(
+                        "{} {:04X}:{:04X}",
+                        mnemonic,
+                        u16::from_le_bytes([s0, s1]),
+                        u16::from_le_bytes([o0, o1])
+                    ),
+                    _ => inst.to_string(),
+                }
+            }
+            _ if inst.is_invalid => inst.to_string(),
+            _ => simple_instruction(inst).unwrap_or_else(|| inst.to_string()),
+        };
+        let notes: Vec<String> = relocations
+            .range(at..)
+            .take_while(|(&offset, _)| (offset as usize) < end)
+            .map(|(offset, relocation)| {
+                format!(
+                    "+{} {} {}",
+                    offset - at,
+                    address_type_name(relocation.address_type),
+                    relocation.target
+                )
+            })
+            .collect();
+
+        let bytes = &code[inst.pos..end];
+        for (line, chunk) in bytes.chunks(LISTING_BYTES_PER_LINE).enumerate() {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+            let offset = inst.pos + line * LISTING_BYTES_PER_LINE;
+            if line > 0 {
+                let _ = writeln!(out, " {:04X}  {}", offset, hex.join(" "));
+                continue;
+            }
+            let _ = write!(
+                out,
+                " {:04X}  {:<w$}  {}",
+                offset,
+                hex.join(" "),
+                text,
+                w = LISTING_BYTES_PER_LINE * 3 - 1
+            );
+            if !notes.is_empty() {
+                let _ = write!(out, "\t; {}", notes.join("; "));
+            }
+            out.push('\n');
+        }
+    }
+    let _ = writeln!(out, "{}\tENDS", name);
+    out
+}
+
+impl NeExecutable {
+    ///
+    /// Listing of code `segment` as `list_code` gives it, with symbols
+    /// of `symbols` and function starts of `discover_functions` as labels.
+    /// Bytes are as on file, iterated data expanded.
+    ///
+    pub fn segment_listing(
+        &self,
+        segment: Seg16,
+        symbols: &dyn SymbolProvider,
+    ) -> io::Result<String> {
+        let code = self.fixed_segment(segment.0, |_| None)?;
+        let mut labels = BTreeMap::new();
+        if let Ok(functions) = self.discover_functions(segment) {
+            for offset in functions.offsets() {
+                labels.insert(offset, format!("sub_{:04X}", offset));
+            }
+        }
+        for offset in 0..code.len().min(u16::MAX as usize + 1) {
+            if let Some(symbol) = symbols
+                .symbol_at(segment.0, offset as u16)
+                .filter(|s| s.offset as usize == offset)
+            {
+                labels.insert(symbol.offset, symbol.name);
+            }
+        }
+        let relocations = self
+            .fixup_places(segment.0)?
+            .into_iter()
+            .map(|place| {
+                let target = match &place.target {
+                    FixupTarget::Internal { segment, offset } => {
+                        symbols.symbolicate(*segment as u16, *offset)
+                    }
+                    FixupTarget::Import { .. } => {
+                        import_name(&place.target, symbols).unwrap_or_default()
+                    }
+                    FixupTarget::OsFixup(fixup) => fixup.to_string(),
+                };
+                let relocation = ListedRelocation {
+                    address_type: place.address_type,
+                    target,
+                };
+                (place.offset, relocation)
+            })
+            .collect();
+        Ok(list_code(&code, segment, &labels, &relocations))
+    }
+
+    ///
+    /// Listing of the whole module, approximating `.COD` files of compilers:
+    /// every code segment by `segment_listing`, data segments and
+    /// segments without data on file as comments
+    ///
+    pub fn listing(&self, symbols: &dyn SymbolProvider) -> io::Result<String> {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "; Listing of {} {}",
+            self.module_name().unwrap_or_default(),
+            self.description().unwrap_or_default()
+        );
+        for (index, entry) in self.segment_entries.iter().enumerate() {
+            let segment = Seg16(index as u16 + 1);
+            let is_data = entry.header.flags & 0x0001 != 0;
+            let _ = write!(
+                out,
+                "\n; Segment {}: {}, 0x{:X} bytes",
+                segment,
+                if is_data { "DATA" } else { "CODE" },
+                entry.data.as_ref().map_or(0, Vec::len)
+            );
+            if entry.data.is_none() {
+                out.push_str(", no data on file\n");
+            } else if is_data {
+                out.push_str(", not listed\n");
+            } else {
+                out.push('\n');
+                out.push_str(&self.segment_listing(segment, symbols)?);
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_branches_and_relocations() {
+        let code = [
+            0x9A, 0xFF, 0xFF, 0x00, 0x00, // 0000: call far KERNEL.INITTASK
+            0x74, 0x03, // 0005: jz 000A
+            0xE8, 0x00, 0x00, // 0007: call 000A
+            0xC3, // 000A: ret
+        ];
+        let labels = BTreeMap::from([(0x07, "WinMain".to_string())]);
+        let relocations = BTreeMap::from([(
+            0x01,
+            ListedRelocation {
+                address_type: ADDR_FAR,
+                target: "KERNEL.INITTASK".to_string(),
+            },
+        )]);
+        let listing = list_code(&code, Seg16(1), &labels, &relocations);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines[0], "_SEG1\tSEGMENT");
+        assert_eq!(
+            lines[1],
+            " 0000  9A FF FF 00 00     call far KERNEL.INITTASK\t; +1 far KERNEL.INITTASK"
+        );
+        assert!(lines[2].ends_with("jz loc_000A"));
+        assert_eq!(lines[3], "WinMain:");
+        assert!(lines[4].ends_with("call loc_000A"));
+        assert_eq!(lines[5], "loc_000A:");
+        assert_eq!(*lines.last().unwrap(), "_SEG1\tENDS");
+    }
+}
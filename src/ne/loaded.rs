@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+use std::io::{self, Cursor, Seek, SeekFrom};
+use std::str::FromStr;
+
+use crate::mz::DosHeader;
+use crate::ne::addr::Seg16;
+use crate::ne::entry_table::EntryTable;
+use crate::ne::header::NeHeader;
+use crate::ne::imported_name_table::ImportedNameTable;
+use crate::ne::module_reference_table::ModuleReferenceTable;
+use crate::ne::nonresident_name_table::NonresidentNameTable;
+use crate::ne::options::{Budget, ParseOptions};
+use crate::ne::read_resource_table;
+use crate::ne::resident_name_table::ResidentNameTable;
+use crate::ne::segment_relocations::RelocationTable;
+use crate::ne::segment_table::{NeSegment, SEG_RELOCINFO};
+use crate::ne::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// Records of segment table on file
+pub const FILE_SEGMENT_RECORD: u64 = 8;
+/// Records of segment table in module database: file record and handle
+pub const MODULE_DATABASE_SEGMENT_RECORD: u64 = 10;
+
+///
+/// Offset of decimal or `0x`-prefixed hexadecimal `s`
+///
+pub fn parse_offset(s: &str) -> Result<u64, String> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|_| format!("invalid offset `{}`", s))
+}
+
+///
+/// Where the loader put one segment: `NUMBER=OFFSET` on command line
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentBase {
+    pub segment: Seg16,
+    /// Offset of the first byte of segment in the dump
+    pub offset: u64,
+}
+
+impl FromStr for SegmentBase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (segment, offset) = s
+            .split_once('=')
+            .ok_or_else(|| format!("`{}` is not NUMBER=OFFSET", s))?;
+        let segment = segment
+            .parse()
+            .ok()
+            .filter(|&n| n != 0)
+            .ok_or_else(|| format!("invalid segment number `{}`", segment))?;
+        Ok(Self {
+            segment: Seg16(segment),
+            offset: parse_offset(offset)?,
+        })
+    }
+}
+
+///
+/// Places of an NE module in a memory dump
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedImageMap {
+    /// Offset of the NE header in the dump; tables it points to
+    /// are taken from offsets relative to it, as on file
+    pub header: u64,
+    /// Bytes taken by each record of the segment table:
+    /// `MODULE_DATABASE_SEGMENT_RECORD` when the header is the module
+    /// database made by the loader, `FILE_SEGMENT_RECORD` for a copy of file
+    pub segment_record: u64,
+    /// Offsets of loaded segments in the dump, by segment number.
+    /// Other segments are taken as not loaded (load on call, discarded).
+    pub segments: BTreeMap<Seg16, u64>,
+}
+
+impl LoadedImageMap {
+    pub fn new(header: u64, segments: &[SegmentBase]) -> Self {
+        Self {
+            header,
+            segment_record: MODULE_DATABASE_SEGMENT_RECORD,
+            segments: segments.iter().map(|b| (b.segment, b.offset)).collect(),
+        }
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Reconstructs module from a dump of memory where the loader put it:
+    /// header and resident tables at `map.header`, segments at their bases.
+    /// Segments get data of file length from the dump with relocations
+    /// already applied, so they have no relocation records, and their
+    /// `RELOCINFO` flags are cleared. Loader doesn't keep nonresident names
+    /// and loads resources on demand, so both are left empty.
+    ///
+    pub fn parse_loaded_image(dump: &[u8], map: &LoadedImageMap) -> io::Result<Self> {
+        let options = ParseOptions::default();
+        let mut budget = Budget::new(&options);
+        let mut file = Cursor::new(dump);
+        let lfanew = map.header;
+        let ne_header = NeHeader::read_at(&mut file, lfanew)?;
+
+        let segment_table = lfanew + ne_header.segment_table_offset.value() as u64;
+        let mut segment_entries = Vec::new();
+        for i in 0..ne_header.segment_count.value() as u64 {
+            file.seek(SeekFrom::Start(segment_table + i * map.segment_record))?;
+            let mut segment =
+                NeSegment::read(&mut file, ne_header.file_alignment_shift_count.value())?;
+            segment.header.flags &= !SEG_RELOCINFO;
+            segment_entries.push(segment);
+        }
+        for (&number, &base) in &map.segments {
+            let segment = number
+                .index()
+                .and_then(|i| segment_entries.get_mut(i))
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("segment table has no segment #{}", number),
+                    )
+                })?;
+            if segment.header.data_offset_shifted == 0 {
+                continue;
+            }
+            let length = segment.data_length();
+            let data = base
+                .checked_add(length)
+                .and_then(|end| dump.get(usize::try_from(base).ok()?..usize::try_from(end).ok()?))
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "segment #{} of 0x{:X} bytes at 0x{:X} runs past end of dump",
+                            number, length, base
+                        ),
+                    )
+                })?;
+            budget.allocate(&format!("segment #{}", number), length)?;
+            segment.data = Some(data.to_vec());
+        }
+
+        let resource_table = read_resource_table(&mut file, lfanew, &ne_header, &mut budget)?;
+
+        file.seek(SeekFrom::Start(
+            lfanew + ne_header.resident_names_table_offset.value() as u64,
+        ))?;
+        let resident_name_table = ResidentNameTable::read(&mut file)?;
+
+        file.seek(SeekFrom::Start(
+            lfanew + ne_header.module_reference_table_offset.value() as u64,
+        ))?;
+        let mut module_reference_table =
+            ModuleReferenceTable::read(&mut file, ne_header.module_references.value())?;
+        let int_offset = lfanew + ne_header.import_name_table_offset.value() as u64;
+        module_reference_table.read_names(&mut file, int_offset)?;
+
+        file.seek(SeekFrom::Start(int_offset))?;
+        let int_size = ne_header
+            .entry_table_offset
+            .value()
+            .saturating_sub(ne_header.import_name_table_offset.value());
+        let imported_name_table = ImportedNameTable::read(&mut file, int_size)?;
+
+        file.seek(SeekFrom::Start(
+            lfanew + ne_header.entry_table_offset.value() as u64,
+        ))?;
+        let entry_table = EntryTable::read_sf(&mut file, ne_header.entry_table_length.value())?;
+
+        let mut dos_header: DosHeader = bytemuck::Zeroable::zeroed();
+        dos_header.lfanew = (lfanew as u32).into();
+        let relocation_tables_per_segment = vec![RelocationTable::default(); segment_entries.len()];
+        let mut ne = Self {
+            dos_header: Box::new(dos_header),
+            dos_stub: Vec::new(),
+            ne_header: Box::new(ne_header),
+            segment_entries,
+            resource_table,
+            resident_name_table,
+            module_reference_table,
+            imported_name_table,
+            entry_table,
+            nonresident_name_table: NonresidentNameTable {
+                entries: Vec::new(),
+            },
+            relocation_tables_per_segment,
+            overlay: None,
+            codepage: Codepage::default(),
+            warnings: Vec::new(),
+            views: Default::default(),
+        };
+        ne.codepage = ne.guessed_codepage();
+        ne.warnings = ne.parse_warnings(&options);
+        Ok(ne)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::fixture::sample_bytes;
+    use crate::ne::writer::encode_entry_table;
+
+    #[test]
+    fn parses_segment_bases() {
+        assert_eq!(
+            "2=0x1A30".parse::<SegmentBase>(),
+            Ok(SegmentBase {
+                segment: Seg16(2),
+                offset: 0x1A30,
+            })
+        );
+        assert_eq!(parse_offset("4096"), Ok(4096));
+        assert!("0=0x100".parse::<SegmentBase>().is_err());
+        assert!("1:0x100".parse::<SegmentBase>().is_err());
+    }
+
+    /// Dump of the sample module as the loader keeps it: header and
+    /// tables at 0, module database records of segments after them,
+    /// segment #2 at 0x300, segment #1 with its fixup applied at 0x400
+    fn dump() -> (NeExecutable, Vec<u8>) {
+        let file = sample_bytes();
+        let ne = NeExecutable::read(&mut Cursor::new(&file)).unwrap();
+        let lfanew = ne.dos_header.lfanew.value() as usize;
+        let tables_end = ne.segment_entries[0].data_offset() as usize;
+        let mut dump = file[lfanew..tables_end].to_vec();
+
+        let mut header = *ne.ne_header;
+        header.segment_table_offset = (dump.len() as u16).into();
+        dump[..std::mem::size_of::<NeHeader>()].copy_from_slice(bytemuck::bytes_of(&header));
+        for (i, segment) in ne.segment_entries.iter().enumerate() {
+            let h = segment.header;
+            for field in [h.data_offset_shifted, h.data_length, h.flags, h.min_alloc] {
+                dump.extend_from_slice(&field.to_le_bytes());
+            }
+            // handle of the segment in global heap
+            dump.extend_from_slice(&(0x1000 + 0x10 * i as u16).to_le_bytes());
+        }
+
+        dump.resize(0x500, 0xEE);
+        let mut code = ne.segment_entries[0].data.clone().unwrap();
+        code[0x21..0x25].copy_from_slice(&[0x34, 0x12, 0x17, 0x01]);
+        dump[0x400..0x440].copy_from_slice(&code);
+        dump[0x300..0x310].fill(0x55);
+        (ne, dump)
+    }
+
+    #[test]
+    fn reconstructs_module_from_dump() {
+        let (ne, dump) = dump();
+        let map = LoadedImageMap::new(0, &["1=0x400".parse().unwrap(), "2=0x300".parse().unwrap()]);
+        let loaded = NeExecutable::parse_loaded_image(&dump, &map).unwrap();
+
+        assert_eq!(loaded.segment_entries.len(), 2);
+        assert_eq!(
+            loaded.segment_entries[0].data.as_deref(),
+            Some(&dump[0x400..0x440])
+        );
+        assert_eq!(
+            loaded.segment_entries[1].data.as_deref(),
+            Some(&[0x55; 0x10][..])
+        );
+        for (segment, original) in loaded.segment_entries.iter().zip(&ne.segment_entries) {
+            assert_eq!(segment.header.flags, original.header.flags & !SEG_RELOCINFO);
+            assert_eq!(segment.header.data_length, original.header.data_length);
+        }
+        assert!(ne.segment_entries[0].header.flags & SEG_RELOCINFO != 0);
+        assert!(loaded
+            .relocation_tables_per_segment
+            .iter()
+            .all(|t| t.entries.is_empty()));
+
+        let names = |ne: &NeExecutable| -> Vec<(Vec<u8>, u16)> {
+            ne.resident_name_table
+                .entries
+                .iter()
+                .map(|e| (e.name.clone(), e.index))
+                .collect()
+        };
+        assert_eq!(names(&loaded), names(&ne));
+        assert_eq!(loaded.module_name().as_deref(), Some("SAMPLE"));
+        assert_eq!(
+            encode_entry_table(&loaded.entry_table),
+            encode_entry_table(&ne.entry_table)
+        );
+        assert!(loaded.nonresident_name_table.entries.is_empty());
+        assert_eq!(loaded.imported_name_table.data, ne.imported_name_table.data);
+
+        // segments not in the map aren't loaded
+        let map = LoadedImageMap::new(0, &["2=0x300".parse().unwrap()]);
+        let loaded = NeExecutable::parse_loaded_image(&dump, &map).unwrap();
+        assert!(loaded.segment_entries[0].data.is_none());
+    }
+
+    #[test]
+    fn segment_past_end_of_dump() {
+        let (_, dump) = dump();
+        for base in ["0x4F0", "0xFFFFFFFFFFFFFFF0"] {
+            let map = LoadedImageMap::new(0, &[format!("1={}", base).parse().unwrap()]);
+            let error = NeExecutable::parse_loaded_image(&dump, &map).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(error.to_string().contains("runs past end of dump"));
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::fmt;
+use std::io::{self, Read, Seek};
+use std::time::{Duration, Instant};
+
+use crate::mz::DosHeader;
+use crate::ne::options::ParseOptions;
+use crate::ne::NeExecutable;
+#[cfg(feature = "alloc-metrics")]
+use crate::util::alloc_count::AllocationStats;
+use crate::util::chunked::{CountingReader, IoStats};
+
+///
+/// Cost of reading one module by `read_with_metrics`
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// Calls made by the parser to the reader and bytes it gave
+    /// (`ChunkedReader::stats` tell what reached the file)
+    pub io: IoStats,
+    /// Time of every parsing stage in the order of reading
+    pub stages: Vec<(&'static str, Duration)>,
+    /// Time of the whole read, DOS header included
+    pub total: Duration,
+    /// Bytes taken from `ParseOptions` limits for tables and data
+    pub budget_bytes: u64,
+    /// Allocations of the reading thread (`alloc-metrics` feature)
+    #[cfg(feature = "alloc-metrics")]
+    pub allocations: AllocationStats,
+}
+
+impl Metrics {
+    ///
+    /// Time of `stage`, zero if it never ran
+    ///
+    pub fn stage(&self, stage: &str) -> Duration {
+        self.stages
+            .iter()
+            .filter(|(name, _)| *name == stage)
+            .map(|(_, time)| *time)
+            .sum()
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} total, {}, {} bytes budgeted",
+            self.total, self.io, self.budget_bytes
+        )?;
+        #[cfg(feature = "alloc-metrics")]
+        write!(
+            f,
+            ", {} allocations of {} bytes",
+            self.allocations.count, self.allocations.bytes
+        )?;
+        if f.alternate() {
+            for (stage, time) in &self.stages {
+                write!(f, "\n  {:<24} {:?}", stage, time)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// Stopwatch of parsing stages threaded through `read_image`;
+/// readers without metrics pass one which is `off` and measures nothing
+///
+#[derive(Debug, Default)]
+pub(crate) struct StageClock {
+    last: Option<Instant>,
+    stages: Vec<(&'static str, Duration)>,
+    /// `Budget::allocated` at the end of reading
+    pub budget_bytes: u64,
+}
+
+impl StageClock {
+    pub fn off() -> Self {
+        Self::default()
+    }
+
+    pub fn on() -> Self {
+        Self {
+            last: Some(Instant::now()),
+            ..Self::default()
+        }
+    }
+
+    ///
+    /// Ends `stage` started by the previous lap or by `on`
+    ///
+    pub fn lap(&mut self, stage: &'static str) {
+        if let Some(last) = self.last {
+            let now = Instant::now();
+            self.stages.push((stage, now - last));
+            self.last = Some(now);
+        }
+    }
+
+    pub fn into_stages(self) -> Vec<(&'static str, Duration)> {
+        self.stages
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// `read_with` measuring time of every table and data read,
+    /// calls made to `file` and, with `alloc-metrics` feature,
+    /// allocations made while reading
+    ///
+    pub fn read_with_metrics<R: Read + Seek>(
+        file: &mut R,
+        options: &ParseOptions,
+    ) -> io::Result<(Self, Metrics)> {
+        #[cfg(feature = "alloc-metrics")]
+        let allocations = AllocationStats::current();
+        let start = Instant::now();
+        let mut clock = StageClock::on();
+        let mut file = CountingReader::new(&mut *file);
+        let dos_header = DosHeader::read(&mut file)?;
+        dos_header.check_magic()?;
+        clock.lap("DOS header");
+
+        let ne = Self::read_image(&mut file, dos_header, options, &mut clock)?;
+        let metrics = Metrics {
+            io: file.stats(),
+            total: start.elapsed(),
+            budget_bytes: clock.budget_bytes,
+            stages: clock.into_stages(),
+            #[cfg(feature = "alloc-metrics")]
+            allocations: AllocationStats::current().since(allocations),
+        };
+        Ok((ne, metrics))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::ne::fixture::sample_bytes;
+    use crate::util::chunked::ChunkedReader;
+
+    #[test]
+    fn clock_off_measures_nothing() {
+        let mut clock = StageClock::off();
+        clock.lap("segment table");
+        assert!(clock.into_stages().is_empty());
+
+        let mut clock = StageClock::on();
+        clock.lap("segment table");
+        clock.lap("entry table");
+        clock.lap("segment table");
+        let metrics = Metrics {
+            stages: clock.into_stages(),
+            ..Default::default()
+        };
+        let names: Vec<&str> = metrics.stages.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["segment table", "entry table", "segment table"]);
+        assert_eq!(
+            metrics.stage("segment table"),
+            metrics.stages[0].1 + metrics.stages[2].1
+        );
+        assert_eq!(metrics.stage("overlay"), Duration::ZERO);
+
+        let text = format!("{:#}", metrics);
+        assert!(text.starts_with("0ns total, 0 reads, 0 seeks, 0 bytes, 0 bytes budgeted"));
+        assert_eq!(text.lines().count(), 4);
+        assert!(!metrics.to_string().contains('\n'));
+    }
+
+    #[test]
+    fn chunks_cut_calls_of_full_parse() {
+        let data = sample_bytes();
+        let options = ParseOptions::default();
+        let (_, direct) =
+            NeExecutable::read_with_metrics(&mut Cursor::new(&data), &options).unwrap();
+        let mut chunked = ChunkedReader::new(Cursor::new(&data));
+        let (ne, metrics) = NeExecutable::read_with_metrics(&mut chunked, &options).unwrap();
+        assert_eq!(ne.exports().len(), 5);
+        // the parser makes the same calls, the file gets them by chunks
+        assert_eq!(metrics.io, direct.io);
+        // one chunk read to its end, one seek for the file length
+        assert_eq!(
+            chunked.stats(),
+            IoStats {
+                reads: 2,
+                seeks: 1,
+                bytes: data.len() as u64,
+            }
+        );
+    }
+
+    #[test]
+    fn header_tables_are_read_at_once() {
+        let data = sample_bytes();
+        let (_, direct) =
+            NeExecutable::read_with_metrics(&mut Cursor::new(&data), &ParseOptions::default())
+                .unwrap();
+        // DOS header, file length, DOS stub, NE header, then all tables of
+        // the header by one read; segment #1 with the relocation count
+        // checked before its records, segment #2 and the icon right after
+        // it, the file length for the overlay
+        assert_eq!(
+            direct.io,
+            IoStats {
+                reads: 10,
+                seeks: 8,
+                bytes: 64 + 128 + 64 + 131 + 0x40 + 2 + 2 + 8 + 0x10 + 0x20,
+            }
+        );
+    }
+}
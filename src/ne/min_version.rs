@@ -0,0 +1,161 @@
+use std::fmt;
+
+use crate::ne::dialect::FormatDialect;
+use crate::ne::imports::ImportTarget;
+use crate::ne::ordinal_db::{lookup_module, lookup_name, lookup_ordinal, WinVersion};
+use crate::ne::resource_table::{type_name, ResourceId, RT_BITMAP, RT_CURSOR, RT_FONT, RT_ICON};
+use crate::ne::NeExecutable;
+
+/// `flags`: application runs only in protected mode (Windows 3.0)
+const NE_PROTMODE_ONLY: u16 = 0x0008;
+
+///
+/// Fact of the module requiring at least `version`
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionEvidence {
+    pub version: WinVersion,
+    pub reason: String,
+}
+
+impl fmt::Display for VersionEvidence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.version, self.reason)
+    }
+}
+
+///
+/// Minimal Windows version the module needs by its content,
+/// compared with `expected_win_ver` of the header
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionEstimate {
+    /// Version from the header, if the dialect has it
+    pub declared: Option<WinVersion>,
+    pub minimum: WinVersion,
+    /// Every fact found, newest version first
+    pub evidence: Vec<VersionEvidence>,
+}
+
+impl VersionEstimate {
+    ///
+    /// Whether the header claims an older version than content needs
+    ///
+    pub fn is_understated(&self) -> bool {
+        self.declared
+            .is_some_and(|declared| declared < self.minimum)
+    }
+}
+
+impl fmt::Display for VersionEstimate {
+    ///
+    /// `{}` gives the conclusion, `{:#}` also the evidence
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "needs Windows {}", self.minimum)?;
+        match self.declared {
+            Some(declared) if self.is_understated() => {
+                write!(f, " (header says {}, too old)", declared)?
+            }
+            Some(declared) => write!(f, " (header says {})", declared)?,
+            None => write!(f, " (header gives no version)")?,
+        }
+        if f.alternate() {
+            for evidence in &self.evidence {
+                write!(f, "\n\t{}", evidence)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Estimates minimal Windows version from imported procedures
+    /// and modules (by the built-in ordinal database), formats of
+    /// resources and header flags. `None` for OS/2 and MS-DOS 4.0 modules.
+    ///
+    pub fn estimate_min_version(&self) -> Option<VersionEstimate> {
+        let dialect = self.dialect();
+        if matches!(dialect, FormatDialect::OS2 | FormatDialect::Dos4) {
+            return None;
+        }
+        let mut evidence = Vec::new();
+        let mut found = |version: WinVersion, reason: String| {
+            evidence.push(VersionEvidence { version, reason })
+        };
+
+        let flags = self.ne_header.flags.value();
+        if flags & NE_PROTMODE_ONLY != 0 && dialect != FormatDialect::Win2x {
+            found(WinVersion::WIN30, "runs only in protected mode".to_string());
+        }
+
+        let mut modules = Vec::new();
+        for import in self.imports() {
+            if !modules.contains(&import.module) {
+                if let Some((since, note)) = lookup_module(&import.module) {
+                    let note = note.map_or(String::new(), |n| format!(", {}", n));
+                    found(since, format!("imports module {}{}", import.module, note));
+                }
+                modules.push(import.module.clone());
+            }
+            let known = match &import.target {
+                ImportTarget::Ordinal(ordinal) => lookup_ordinal(&import.module, *ordinal),
+                ImportTarget::Name(name) => lookup_name(&import.module, name),
+            };
+            if let Some(known) = known.filter(|k| k.since > WinVersion::WIN20) {
+                found(
+                    known.since,
+                    format!(
+                        "imports {}.{} ({})",
+                        known.module, known.name, import.target
+                    ),
+                );
+            }
+        }
+
+        let table = &self.resource_table;
+        for type_id in [RT_BITMAP, RT_ICON, RT_CURSOR] {
+            let is_dib = |data: &[u8]| {
+                // cursors start with the hot spot
+                let start = if type_id == RT_CURSOR { 4 } else { 0 };
+                data.get(start..start + 4)
+                    .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .is_some_and(|size| size == 12 || size == 40)
+            };
+            if let Some(resource) = table
+                .resources_of_type(type_id)
+                .find(|r| r.data.as_deref().is_some_and(is_dib))
+            {
+                found(
+                    WinVersion::WIN30,
+                    format!(
+                        "{} {} is a device-independent bitmap",
+                        type_name(&ResourceId::Int(type_id)),
+                        resource.id()
+                    ),
+                );
+            }
+        }
+        if let Some(resource) = table.resources_of_type(RT_FONT).find(|r| {
+            r.data
+                .as_deref()
+                .and_then(|d| d.get(..2))
+                .is_some_and(|v| u16::from_le_bytes([v[0], v[1]]) >= 0x0300)
+        }) {
+            found(
+                WinVersion::WIN30,
+                format!("font {} has version 3.00 format", resource.id()),
+            );
+        }
+
+        evidence.sort_by_key(|e| std::cmp::Reverse(e.version));
+        let [minor, major] = self.ne_header.expected_win_ver;
+        Some(VersionEstimate {
+            declared: (dialect.has_expected_version() && major != 0)
+                .then(|| WinVersion::new(major, minor)),
+            minimum: evidence.first().map_or(WinVersion::WIN20, |e| e.version),
+            evidence,
+        })
+    }
+}
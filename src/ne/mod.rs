@@ -1,37 +1,137 @@
 use log::{debug, error};
 use std::io::{self, Read, Seek, SeekFrom};
 
+use self::addr::{Far16Ptr, Seg16};
+use self::dialect::FormatDialect;
 use self::entry_table::EntryTable;
 use self::header::NeHeader;
+use self::imported_name_table::ImportedNameTable;
+use self::metrics::StageClock;
 use self::module_reference_table::ModuleReferenceTable;
 use self::nonresident_name_table::NonresidentNameTable;
+use self::options::{Budget, ParseOptions};
+use self::overlay::Overlay;
+use self::raw::header_table_ranges;
 use self::resident_name_table::ResidentNameTable;
 use self::resource_table::NeResourceTable;
-use self::segment_table::NeSegment;
+use self::segment_table::{NeSegment, SEG_RELOCINFO};
+use self::symbols::SymbolProvider;
+use self::thunks::ImportCallKind;
+use self::validate::{
+    check_ne_header_layout, check_relocations_layout, check_resources_layout,
+    check_segments_layout, check_tables_layout, LayoutErrors,
+};
+use self::views::ViewCache;
+use self::warning::Warning;
 use crate::mz::DosHeader;
+use crate::util::chunked::PrefetchedReader;
+use crate::util::codepage::Codepage;
+use crate::util::trace::{trace_event, trace_span, trace_warn};
 use crate::ne::segment_relocations::{RelocationTable, RelocationTarget};
 
+pub mod addr;
+pub mod annotations;
+pub mod apis;
+#[cfg(feature = "image")]
+pub mod bitmap;
+pub mod canonical;
+pub mod charset;
+pub mod coverage;
+pub mod dialect;
+pub mod diff;
+pub mod driver;
+pub mod editor;
 pub mod entry_table;
+pub mod error;
+pub mod exports;
+pub mod findings;
+#[cfg(test)]
+pub(crate) mod fixture;
+pub mod fixups;
+pub mod font;
+pub mod footprint;
+pub mod functions;
+pub mod fuzz;
+pub mod gangload;
 pub mod header;
+pub mod imported_name_table;
+pub mod imports;
+pub mod lint;
+pub mod listing;
+pub mod loaded;
+pub mod metrics;
+pub mod min_version;
 pub mod module_reference_table;
+pub mod mscompress;
 pub mod nonresident_name_table;
+pub mod open;
+pub mod options;
+pub mod ordinal_db;
+pub mod ordinals;
+pub mod overlay;
+pub mod owned;
+pub mod packing;
+pub mod raw;
+pub mod relocation_stats;
+pub mod repack;
+pub mod report;
 pub mod resident_name_table;
+pub mod resolver;
+pub mod resource_convert;
+pub mod resource_hash;
+pub mod resource_preview;
+pub mod resource_search;
 pub mod resource_table;
+pub mod sbom;
+pub mod segment_layout;
 pub mod segment_table;
+pub mod self_loader;
 pub mod segment_relocations;
+pub mod shim;
+pub mod sink;
+pub mod slack;
+pub mod source;
+pub mod spec;
+pub mod stack_usage;
+pub mod startup;
+pub mod strings;
+pub mod symbols;
+pub mod template;
+pub mod thunks;
+pub mod toolchain;
+pub mod unpack;
+pub mod validate;
+pub mod vb16;
+pub mod version_edit;
+pub mod version_info;
+pub mod views;
+pub mod warning;
+pub mod win32_res;
+pub mod writer;
 
 /// The parsed New Executable binary.
 #[derive(Debug, Clone)]
 pub struct NeExecutable {
     pub dos_header: Box<DosHeader>,
+    /// Bytes before NE header: DOS header and real-mode stub program.
+    /// Empty for images read by `read_at`.
+    pub dos_stub: Vec<u8>,
     pub ne_header: Box<NeHeader>,
     pub segment_entries: Vec<NeSegment>,
     pub resource_table: NeResourceTable,
     pub resident_name_table: ResidentNameTable,
     pub module_reference_table: ModuleReferenceTable,
+    pub imported_name_table: ImportedNameTable,
     pub entry_table: EntryTable,
     pub nonresident_name_table: NonresidentNameTable,
-    pub relocation_tables_per_segment: Vec<RelocationTable>
+    pub relocation_tables_per_segment: Vec<RelocationTable>,
+    pub overlay: Option<Overlay>,
+    /// Code page of names and strings (`ParseOptions::codepage`)
+    pub codepage: Codepage,
+    /// Weird but parseable conditions found while reading
+    pub warnings: Vec<Warning>,
+    /// Derived views computed on first query (see `invalidate`)
+    pub(crate) views: ViewCache,
 }
 
 impl NeExecutable {
@@ -39,6 +139,19 @@ impl NeExecutable {
     /// Just reads NE image structures
     /// 
     pub fn read<R: Read + Seek>(file: &mut R) -> io::Result<Self> {
+        Self::read_with(file, &ParseOptions::default())
+    }
+    ///
+    /// Reads NE image structures keeping within limits of `options`.
+    /// Exceeded limit gives an error with `LimitExceeded` payload.
+    /// Header tables are fetched in file order, adjacent ones by one read
+    /// (see `header_table_ranges`), then segments with their relocations
+    /// and resource data, as linkers lay them out. Reads of segment and
+    /// resource data are passed to `file` as they are: files of many
+    /// small records are best given through `ChunkedReader`.
+    ///
+    pub fn read_with<R: Read + Seek>(file: &mut R, options: &ParseOptions) -> io::Result<Self> {
+        trace_span!("ne::read", "options={:?}", options);
         let dos_header = DosHeader::read(file)?;
         debug!("dos_header = {:?}", dos_header);
         
@@ -49,79 +162,309 @@ impl NeExecutable {
             }
         };
 
+        Self::read_image(file, dos_header, options, &mut StageClock::off())
+    }
+    ///
+    /// Reads NE image whose header is already located at `offset`,
+    /// without looking for MZ header (carved images, memory dumps, other containers).
+    /// Other offsets of the image are still taken from the beginning of `file`.
+    /// `dos_header` of the result is zeroed except `lfanew`.
+    ///
+    pub fn read_at<R: Read + Seek>(file: &mut R, offset: u64) -> io::Result<Self> {
+        Self::read_at_with(file, offset, &ParseOptions::default())
+    }
+    ///
+    /// `read_at` keeping within limits of `options`
+    ///
+    pub fn read_at_with<R: Read + Seek>(
+        file: &mut R,
+        offset: u64,
+        options: &ParseOptions,
+    ) -> io::Result<Self> {
+        trace_span!("ne::read_at", "offset={:#X} options={:?}", offset, options);
+        let lfanew = u32::try_from(offset).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("NE header offset {:#X} doesn't fit in 32 bits", offset),
+            )
+        })?;
+        let mut dos_header: DosHeader = bytemuck::Zeroable::zeroed();
+        dos_header.lfanew = lfanew.into();
+        Self::read_image(file, dos_header, options, &mut StageClock::off())
+    }
+
+    ///
+    /// Reads only NE header and resource table with resource data,
+    /// for font packs and icon libraries whose code is never looked at.
+    /// Segment table, name tables, entry table, relocations and overlay
+    /// are left empty, so only resource views of the result are meaningful.
+    ///
+    pub fn read_resources_only<R: Read + Seek>(file: &mut R) -> io::Result<Self> {
+        Self::read_resources_only_with(file, &ParseOptions::default())
+    }
+    ///
+    /// `read_resources_only` keeping within limits of `options`
+    ///
+    pub fn read_resources_only_with<R: Read + Seek>(
+        file: &mut R,
+        options: &ParseOptions,
+    ) -> io::Result<Self> {
+        trace_span!("ne::read_resources_only", "options={:?}", options);
+        let dos_header = DosHeader::read(file)?;
+        dos_header.check_magic()?;
+        let mut budget = Budget::new(options);
         let lfanew = dos_header.lfanew.value() as u64;
+        let file_size = file.seek(SeekFrom::End(0))?;
+        LayoutErrors::check(check_ne_header_layout(file_size, lfanew))?;
+        let ne_header = NeHeader::read_at(file, lfanew)?;
 
-        file.seek(SeekFrom::Start(lfanew))?;
+        let mut resource_table = read_resource_table(file, lfanew, &ne_header, &mut budget)?;
+        LayoutErrors::check(check_resources_layout(file_size, &resource_table))?;
+        resource_table.read_data(file, &mut budget)?;
 
-        let ne_header = NeHeader::read(file)?;
-        ne_header.check_magic()?;
+        let mut ne = Self {
+            dos_header: Box::new(dos_header),
+            dos_stub: Vec::new(),
+            ne_header: Box::new(ne_header),
+            segment_entries: Vec::new(),
+            resource_table,
+            resident_name_table: ResidentNameTable {
+                entries: Vec::new(),
+            },
+            module_reference_table: ModuleReferenceTable {
+                entries: Vec::new(),
+            },
+            imported_name_table: ImportedNameTable::default(),
+            entry_table: EntryTable {
+                entries: Vec::new(),
+            },
+            nonresident_name_table: NonresidentNameTable {
+                entries: Vec::new(),
+            },
+            relocation_tables_per_segment: Vec::new(),
+            overlay: None,
+            codepage: Codepage::default(),
+            warnings: Vec::new(),
+            views: Default::default(),
+        };
+        ne.codepage = options.codepage.unwrap_or_else(|| ne.guessed_codepage());
+        Ok(ne)
+    }
+
+    fn read_image<R: Read + Seek>(
+        file: &mut R,
+        dos_header: DosHeader,
+        options: &ParseOptions,
+        clock: &mut StageClock,
+    ) -> io::Result<Self> {
+        let mut budget = Budget::new(options);
+        let lfanew = dos_header.lfanew.value() as u64;
+        let file_size = file.seek(SeekFrom::End(0))?;
+        LayoutErrors::check(check_ne_header_layout(file_size, lfanew))?;
+
+        // images located by the caller have no MZ header and no stub to keep
+        let mut dos_stub = Vec::new();
+        if dos_header.check_magic().is_ok() {
+            budget.allocate("DOS stub", lfanew)?;
+            dos_stub.resize(lfanew as usize, 0);
+            file.seek(SeekFrom::Start(0))?;
+            file.read_exact(&mut dos_stub)?;
+        }
+        clock.lap("DOS stub");
+
+        trace_event!("NE header at {:#X}", lfanew);
+        let ne_header = NeHeader::read_at(file, lfanew)?;
+        LayoutErrors::check(check_tables_layout(file_size, lfanew, &ne_header))?;
+        let dialect = ne_header.dialect();
+        trace_event!("format dialect: {}", dialect);
+        if ne_header.major_linker_version < 3 && dialect != FormatDialect::Win2x {
+            trace_warn!("LINK.EXE {}.{} structures are unsupported", ne_header.major_linker_version, ne_header.minor_linker_version);
+        }
+        clock.lap("NE header");
+
+        // header tables are fetched in file order, adjacent ones by one read,
+        // and parsed from memory
+        let ranges: Vec<(u64, u64)> = header_table_ranges(lfanew, &ne_header)
+            .iter()
+            .map(|t| (t.offset, t.length.min(file_size.saturating_sub(t.offset))))
+            .collect();
+        let file = &mut PrefetchedReader::new(file, &ranges)?;
+        clock.lap("header tables");
 
         file.seek(SeekFrom::Start(
             lfanew + ne_header.segment_table_offset.value() as u64,
         ))?;
 
+        budget.check_entries("segment table", ne_header.segment_count.value() as usize)?;
         let mut segment_entries = (0..ne_header.segment_count.value())
             .map(|_| NeSegment::read(file, ne_header.file_alignment_shift_count.value()))
             .collect::<Result<Vec<_>, _>>()?;
+        LayoutErrors::check(check_segments_layout(file_size, &segment_entries))?;
+        trace_event!(
+            "segment table at {:#X}: {} entries",
+            lfanew + ne_header.segment_table_offset.value() as u64,
+            segment_entries.len()
+        );
+        clock.lap("segment table");
         
-        let rt_offset = lfanew + ne_header.resource_table_offset.value() as u64;
+        let mut resource_table = read_resource_table(file, lfanew, &ne_header, &mut budget)?;
+
+        clock.lap("resource table");
 
-        file.seek(SeekFrom::Start(rt_offset))?;
-        let resource_table = if ne_header.resource_table_entries.value() == 0xFFFF {
-            NeResourceTable::read_variadic(file)?
-        } else {
-            NeResourceTable::read(file, ne_header.resource_table_entries.value())?
-        };
-        
         let rnt_offset = lfanew + ne_header.resident_names_table_offset.value() as u64;
         file.seek(SeekFrom::Start(rnt_offset))?;
         let resident_name_table = ResidentNameTable::read(file)?;
+        budget.check_entries("resident names table", resident_name_table.entries.len())?;
+        trace_event!(
+            "resident names table at {:#X}: {} entries",
+            rnt_offset,
+            resident_name_table.entries.len()
+        );
+        clock.lap("resident names table");
         
         let mrt_offset = lfanew + ne_header.module_reference_table_offset.value() as u64;
         file.seek(SeekFrom::Start(mrt_offset))?;
+        budget.check_entries(
+            "module reference table",
+            ne_header.module_references.value() as usize,
+        )?;
         let mut module_reference_table =
             ModuleReferenceTable::read(file, ne_header.module_references.value())?;
         
         let int_offset = lfanew + ne_header.import_name_table_offset.value() as u64;
         module_reference_table.read_names(file, int_offset)?;
+        trace_event!(
+            "module reference table at {:#X}: {} entries",
+            mrt_offset,
+            module_reference_table.entries.len()
+        );
+        clock.lap("module reference table");
+
+        file.seek(SeekFrom::Start(int_offset))?;
+        let int_size = ne_header
+            .entry_table_offset
+            .value()
+            .saturating_sub(ne_header.import_name_table_offset.value());
+        budget.allocate("imported names table", int_size as u64)?;
+        let imported_name_table = ImportedNameTable::read(file, int_size)?;
+        trace_event!("imported names table at {:#X}: {} bytes", int_offset, int_size);
+        clock.lap("imported names table");
 
         let et_offset = lfanew + ne_header.entry_table_offset.value() as u64;
         file.seek(SeekFrom::Start(et_offset))?;
 
         // replaced: read(x) -> read_sf(x)
         let entry_table = EntryTable::read_sf(file, ne_header.entry_table_length.value())?;
+        budget.check_entries("entry table", entry_table.entries.len())?;
+        trace_event!(
+            "entry table at {:#X}: {} entries",
+            et_offset,
+            entry_table.entries.len()
+        );
+        clock.lap("entry table");
         
         let nnt_offset = ne_header.non_resident_names_table_offset.value() as u64;
         file.seek(SeekFrom::Start(nnt_offset))?;
         let nonresident_name_table = NonresidentNameTable::read(file)?;
-        
+        budget.check_entries("nonresident names table", nonresident_name_table.entries.len())?;
+        trace_event!(
+            "nonresident names table at {:#X}: {} entries",
+            nnt_offset,
+            nonresident_name_table.entries.len()
+        );
+        clock.lap("nonresident names table");
 
         let mut relocs_per_segment = Vec::<RelocationTable>::new();
         
-        for segment in &mut segment_entries {
-            segment.read_data(file)?;
-            if segment.header.flags & 0x0008 != 0 {  // must not be SEG_WITHIN_RELOCS
-                let relocations = RelocationTable::read(file)?;
-                relocs_per_segment.push(relocations);
+        for (i, segment) in segment_entries.iter_mut().enumerate() {
+            trace_span!(
+                "segment",
+                "#{} offset={:#X} length={:#X}",
+                i + 1,
+                segment.data_offset(),
+                segment.data_length()
+            );
+            if segment.header.data_offset_shifted != 0 {
+                budget.allocate(&format!("segment #{}", i + 1), segment.data_length())?;
             }
+            segment.read_data(file)?;
+            LayoutErrors::check(check_relocations_layout(file, file_size, i + 1, segment)?)?;
+            // relocation records follow segment data on file.
+            // Segments without them get an empty table: tables go in segments order
+            let relocations = if segment.data.is_some() && segment.header.flags & SEG_RELOCINFO != 0 {
+                RelocationTable::read(file)?
+            } else {
+                RelocationTable::default()
+            };
+            budget.check_entries("relocation table", relocations.entries.len())?;
+            budget.allocate(
+                &format!("relocations of segment #{}", i + 1),
+                8 * relocations.entries.len() as u64,
+            )?;
+            trace_event!("{} relocations", relocations.entries.len());
+            relocs_per_segment.push(relocations);
+        }
+        clock.lap("segments and relocations");
+
+        // linkers put resources after segments: data is read in file order
+        {
+            trace_span!("resource data");
+            LayoutErrors::check(check_resources_layout(file_size, &resource_table))?;
+            resource_table.read_data(file, &mut budget)?;
         }
+        clock.lap("resource data");
 
-        Ok(Self {
+        let mut ne = Self {
             dos_header: Box::new(dos_header),
+            dos_stub,
             ne_header: Box::new(ne_header),
             segment_entries,
             resource_table,
             resident_name_table,
             module_reference_table,
+            imported_name_table,
             entry_table,
             nonresident_name_table,
-            relocation_tables_per_segment: relocs_per_segment
-        })
+            relocation_tables_per_segment: relocs_per_segment,
+            overlay: None,
+            codepage: Codepage::default(),
+            warnings: Vec::new(),
+            views: Default::default(),
+        };
+        ne.codepage = options.codepage.unwrap_or_else(|| ne.guessed_codepage());
+        ne.overlay = Overlay::read(file, ne.image_end(), &mut budget)?;
+        clock.lap("overlay");
+        ne.warnings = ne.parse_warnings(options);
+        clock.lap("warnings");
+        trace_event!("{} warnings", ne.warnings.len());
+        trace_event!("{} bytes allocated", budget.allocated());
+        clock.budget_bytes = budget.allocated();
+        Ok(ne)
+    }
+    ///
+    /// Module name: the first entry of resident names table,
+    /// decoded with the code page of the module
+    ///
+    pub fn module_name(&self) -> Option<String> {
+        self.resident_name_table
+            .entries
+            .first()
+            .map(|e| self.codepage.decode(&e.name))
+    }
+    ///
+    /// Module description: the first entry of nonresident names table,
+    /// decoded with the code page of the module
+    ///
+    pub fn description(&self) -> Option<String> {
+        self.nonresident_name_table
+            .entries
+            .first()
+            .map(|e| self.codepage.decode(&e.name))
     }
     ///
     /// Writes read information of NE image in terminal.
     /// 
-    pub(crate) fn describe(&self, show_data: bool, disassemble: bool) {
+    pub(crate) fn describe(&self, show_data: bool, disassemble: bool, symbols: &dyn SymbolProvider) {
         let Self {
             ne_header,
             segment_entries,
@@ -184,14 +527,12 @@ impl NeExecutable {
             ne_header.init_stack_size.value()
         );
         println!(
-            "\tEntry point (CS:IP): {:04X}:{:04X}",
-            ne_header.entry_point.value() >> 16,
-            ne_header.entry_point.value() & 0xFFFF
+            "\tEntry point (CS:IP): {}",
+            Far16Ptr::from(ne_header.entry_point.value())
         );
         println!(
-            "\tInitial stack (SS:SP): {:04X}:{:04X}",
-            ne_header.init_stack.value() >> 16,
-            ne_header.init_stack.value() & 0xFFFF
+            "\tInitial stack (SS:SP): {}",
+            Far16Ptr::from(ne_header.init_stack.value())
         );
         println!(
             "\tNumber of segments: {}",
@@ -219,14 +560,17 @@ impl NeExecutable {
             0x0 => print!("Not specified"),
             0x1 => print!("OS/2"),
             0x2 => print!("Windows/286"),
-            0x3 => print!("DOS 4.x"),
+            0x3 => print!("MS-DOS 4.0 (multitasking)"),
             0x4 => print!("Windows/386"),
             0x5 => print!("Borland OSS"),
+            0x81 | 0x82 => print!("{}", header::target_os_name(ne_header.target_os)),
             _ => print!("Unknown {:X}", ne_header.target_os)
         }
 
         println!();
+        println!("\tFormat dialect: {}", ne_header.dialect());
         match ne_header.expected_win_ver[1] {
+            _ if !ne_header.dialect().has_expected_version() => (),
             0 => (),    
             _ => {
                 println!(
@@ -245,7 +589,7 @@ impl NeExecutable {
             println!("\tAllocation: 0x{:04X}", segment.min_alloc());
 
             // SEGMENT RELOCATIONS info
-            if self.relocation_tables_per_segment.len() == 0 {
+            if self.relocation_tables_per_segment[i].entries.is_empty() {
                 println!("\tSEG_WITHIN_RELOCS");
                 continue;
             }
@@ -265,10 +609,19 @@ impl NeExecutable {
                         println!("\t\t#Segment {}", f.segment);
                         println!("\t\tOffset {:X}", f.offset_or_ordinal);
                         println!("\t\t.MOVEABLE? {}", f.is_movable);
+                        if !f.is_movable {
+                            println!("\t\tSymbol {}", symbols.symbolicate(f.segment as u16, f.offset_or_ordinal));
+                        }
                     },
                     RelocationTarget::ImportByOrdinal(o) => {
                         println!("SEG_RELOC_IMPORT_BY_ORDINAL");
-                        println!("Procedure: @{}", o.ordinal);
+                        let name = self
+                            .referenced_module_name(o.module_index)
+                            .and_then(|module| symbols.name_by_ordinal(&module, o.ordinal));
+                        match name {
+                            Some(name) => println!("Procedure: @{} {}", o.ordinal, name),
+                            None => println!("Procedure: @{}", o.ordinal),
+                        }
                         println!("Module# {}", o.module_index);
                     },
                     RelocationTarget::ImportByName(n) => {
@@ -276,6 +629,10 @@ impl NeExecutable {
                         println!("Procedure name offset: {}", n.name_offset);
                         println!("Module# {}", n.module_index)
                     }
+                    RelocationTarget::OsFixup(o) => {
+                        println!("SEG_RELOC_OS_FIXUP");
+                        println!("Fixup type: {} {}", o.fixup_type, o);
+                    }
                 }
                 
                 println!();
@@ -301,7 +658,7 @@ impl NeExecutable {
         
         if !self.resident_name_table.entries.len() > 1 {
             println!("Resident names:");
-            for entry in &self.resident_name_table.entries[1..] {
+            for entry in self.resident_name_table.entries.iter().skip(1) {
                 println!(
                     "\t{:3} {}",
                     entry.index,
@@ -311,7 +668,7 @@ impl NeExecutable {
         }
         if !self.nonresident_name_table.entries.len() > 1 {
             println!("Nonresident names:");
-            for entry in &self.nonresident_name_table.entries[1..] {
+            for entry in self.nonresident_name_table.entries.iter().skip(1) {
                 println!(
                     "\t{:3} {}",
                     entry.index,
@@ -377,7 +734,28 @@ impl NeExecutable {
 
                         println!("Segment #{} {} [{}]", segment_index + 1, segment_type, segment_compressed);
                         
-                        define_disassemble(data, segment_type, is_data, is_iterated);
+                        let segment = segment_index as u16 + 1;
+                        // segments with broken relocation chains are shown without imports
+                        let calls = match is_data {
+                            true => Vec::new(),
+                            false => self.import_calls(Seg16(segment), symbols).unwrap_or_default(),
+                        };
+                        let labels = |offset: usize| {
+                            let symbol = symbols.symbol_at(segment, offset as u16)
+                                .filter(|s| s.offset as usize == offset);
+                            match symbol {
+                                Some(symbol) => Some(symbol.name),
+                                None => calls.iter()
+                                    .find(|c| c.offset as usize == offset && c.kind == ImportCallKind::JumpFar)
+                                    .map(|c| format!("thunk_{}", c.import)),
+                            }
+                        };
+                        let comments = |offset: usize| {
+                            calls.iter()
+                                .find(|c| c.offset as usize == offset)
+                                .map(|c| c.import.clone())
+                        };
+                        define_disassemble(data, segment_type, is_data, is_iterated, &labels, &comments);
                     }
                     None => (),
                 }
@@ -422,17 +800,68 @@ impl NeExecutable {
         }
     }
 }
+
+///
+/// Reads resource table with resource names, without resource data
+///
+fn read_resource_table<R: Read + Seek>(
+    file: &mut R,
+    lfanew: u64,
+    ne_header: &NeHeader,
+    budget: &mut Budget,
+) -> io::Result<NeResourceTable> {
+    let rt_offset = lfanew + ne_header.resource_table_offset.value() as u64;
+
+    file.seek(SeekFrom::Start(rt_offset))?;
+    // Windows linkers don't fill `resource_table_entries` (it holds 0 or 0xFFFF),
+    // so table is read until the terminating zero type ID.
+    // No resource table at all, if it ends where resident names begin.
+    // OS/2 table only names resources kept in the last segments.
+    let resource_table = {
+        trace_span!("resource table", "offset={:#X}", rt_offset);
+        let mut resource_table =
+            if ne_header.resource_table_offset == ne_header.resident_names_table_offset
+                || !ne_header.dialect().has_resource_table()
+            {
+                NeResourceTable::default()
+            } else {
+                NeResourceTable::read_variadic(file)?
+            };
+        budget.check_entries("resource table", resource_table.resource_types.len())?;
+        for resource_type in &resource_table.resource_types {
+            budget.check_entries("resource table", resource_type.resources.len())?;
+            trace_event!(
+                "resource type {:#06X}: {} resources",
+                resource_type.header.type_id,
+                resource_type.resources.len()
+            );
+        }
+        resource_table.read_names(file, rt_offset)?;
+        resource_table
+    };
+    Ok(resource_table)
+}
 ///
 /// Defines segment's storage type by flags in segment's header
 /// and call disassemble procedure
 /// 
-fn define_disassemble(data: &Vec<u8>, segment_type: &'static str, is_data: bool, is_iterated: bool) {
+/// \param labels -- names of offsets in the segment
+/// \param comments -- notes of instructions, such as imports they call
+///
+fn define_disassemble(
+    data: &Vec<u8>,
+    segment_type: &'static str,
+    is_data: bool,
+    is_iterated: bool,
+    labels: &dyn Fn(usize) -> Option<String>,
+    comments: &dyn Fn(usize) -> Option<String>,
+) {
     match is_data {
         true => println!("\tSkipped!"),
         false => {
             match !is_iterated {
-                true => crate::x86::disassemble(data, false, segment_type),
-                false => crate::x86::disassemble(&iter_segment_bytes(data), false, segment_type)
+                true => crate::x86::disassemble_annotated(data, false, segment_type, labels, comments),
+                false => crate::x86::disassemble_annotated(&iter_segment_bytes(data), false, segment_type, labels, comments)
             }
         }
     }
@@ -446,10 +875,81 @@ fn define_disassemble(data: &Vec<u8>, segment_type: &'static str, is_data: bool,
 /// 
 /// \param data -- compressed bytes slice
 ///
-fn iter_segment_bytes(data: &[u8]) -> Vec<u8> {
+pub(crate) fn iter_segment_bytes(data: &[u8]) -> Vec<u8> {
     let iterations = u16::from_le_bytes([data[0], data[1]]);
     let data_size = u16::from_le_bytes([data[2], data[3]]);
     let raw_data = &data[4..4 + data_size as usize];
     
     raw_data.repeat(iterations as usize)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::ops::Range;
+
+    use super::*;
+    use crate::ne::coverage::Structure;
+    use crate::ne::fixture::sample_bytes;
+    use crate::ne::raw::NeTable;
+
+    ///
+    /// Reader failing on reads of bytes outside of `allowed` ranges
+    ///
+    struct GuardedReader<'a> {
+        data: Cursor<&'a [u8]>,
+        allowed: Vec<Range<u64>>,
+    }
+
+    impl Read for GuardedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let start = self.data.position();
+            let end = (start + buf.len() as u64).min(self.data.get_ref().len() as u64);
+            let inside = |range: &Range<u64>| range.start <= start && end <= range.end;
+            if start < end && !self.allowed.iter().any(inside) {
+                return Err(io::Error::other(format!(
+                    "read of {:#X}..{:#X} outside of resources",
+                    start, end
+                )));
+            }
+            self.data.read(buf)
+        }
+    }
+
+    impl Seek for GuardedReader<'_> {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.data.seek(pos)
+        }
+    }
+
+    #[test]
+    fn resources_only_reads_just_resources() {
+        let data = sample_bytes();
+        let ne = NeExecutable::read(&mut Cursor::new(&data[..])).unwrap();
+        let allowed = ne
+            .coverage_map()
+            .iter()
+            .filter(|covered| {
+                matches!(
+                    covered.structure,
+                    Structure::Table(NeTable::DosHeader | NeTable::NeHeader | NeTable::ResourceTable)
+                        | Structure::Resource { .. }
+                )
+            })
+            .map(|covered| covered.range.clone())
+            .collect();
+        let mut guarded = GuardedReader {
+            data: Cursor::new(&data[..]),
+            allowed,
+        };
+        assert!(NeExecutable::read(&mut guarded).is_err());
+
+        guarded.data.set_position(0);
+        let resources = NeExecutable::read_resources_only(&mut guarded).unwrap();
+        assert_eq!(
+            format!("{:?}", resources.resource_table),
+            format!("{:?}", ne.resource_table)
+        );
+        assert!(resources.segment_entries.is_empty());
+    }
+}
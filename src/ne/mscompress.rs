@@ -0,0 +1,197 @@
+use std::borrow::Cow;
+use std::io::{self, ErrorKind};
+
+#[cfg(feature = "decompress")]
+use crate::ne::packing::decompress;
+use crate::ne::packing::{detect_packing, Packing, PackingScheme};
+
+/// KWAJ header flags: optional fields following the fixed header, in this order
+const KWAJ_HAS_LENGTH: u16 = 0x01;
+const KWAJ_HAS_UNKNOWN: u16 = 0x02;
+const KWAJ_HAS_DATA: u16 = 0x04;
+const KWAJ_HAS_NAME: u16 = 0x08;
+const KWAJ_HAS_EXTENSION: u16 = 0x10;
+
+///
+/// Packing of `data` if it's a whole file packed by `COMPRESS.EXE`
+/// (`SETUP.EX_`, `GDI.DL_` of distribution disks): SZDD, SZ or KWAJ.
+/// Archives (zip, cabinets) aren't such files.
+///
+pub fn compressed_file(data: &[u8]) -> Option<Packing> {
+    detect_packing(data).filter(|packing| {
+        matches!(
+            packing.scheme,
+            PackingScheme::Szdd | PackingScheme::Sz | PackingScheme::Kwaj { .. }
+        )
+    })
+}
+
+///
+/// Name kept in the optional fields of KWAJ header, `NAME.EXT`
+///
+pub fn kwaj_file_name(data: &[u8]) -> Option<String> {
+    if !matches!(compressed_file(data)?.scheme, PackingScheme::Kwaj { .. }) {
+        return None;
+    }
+    let flags = u16::from_le_bytes(data.get(12..14)?.try_into().ok()?);
+    let mut at = 14;
+    if flags & KWAJ_HAS_LENGTH != 0 {
+        at += 4;
+    }
+    if flags & KWAJ_HAS_UNKNOWN != 0 {
+        at += 2;
+    }
+    if flags & KWAJ_HAS_DATA != 0 {
+        at += 2 + u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?) as usize;
+    }
+    let mut text = |present: bool, max: usize| -> Option<String> {
+        if !present {
+            return Some(String::new());
+        }
+        let field = data.get(at..)?;
+        let length = field.iter().take(max).position(|&b| b == 0)?;
+        at += length + 1;
+        Some(String::from_utf8_lossy(&field[..length]).into_owned())
+    };
+    let name = text(flags & KWAJ_HAS_NAME != 0, 9)?;
+    let extension = text(flags & KWAJ_HAS_EXTENSION != 0, 4)?;
+    match (name.is_empty(), extension.is_empty()) {
+        (true, _) => None,
+        (false, true) => Some(name),
+        (false, false) => Some(format!("{}.{}", name, extension)),
+    }
+}
+
+///
+/// Name of the expanded file as `EXPAND.EXE -r` gives it: the name kept by
+/// KWAJ, or `compressed_name` with the trailing `_` replaced by the character
+/// SZDD keeps (lowercase after a lowercase name). `None` if the file tells nothing
+/// or names something other than a file of the same directory: the name comes
+/// from the compressed file and mustn't lead anywhere else (`..\AUTOEXEC.BAT`).
+///
+pub fn expanded_name(compressed_name: &str, data: &[u8]) -> Option<String> {
+    let name = match compressed_file(data)?.scheme {
+        PackingScheme::Kwaj { .. } => kwaj_file_name(data),
+        PackingScheme::Szdd => {
+            let stem = compressed_name.strip_suffix('_')?;
+            let last = match *data.get(9)? {
+                0 => return None,
+                c if stem.ends_with(|s: char| s.is_ascii_lowercase()) => c.to_ascii_lowercase(),
+                c => c,
+            };
+            Some(format!("{}{}", stem, last as char))
+        }
+        _ => None,
+    }?;
+    is_plain_file_name(&name).then_some(name)
+}
+
+///
+/// Whether `name` is a file name alone: no directories, drives or
+/// parent references of DOS or Unix paths
+///
+fn is_plain_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && !name.contains("..")
+        && !name.contains(['/', '\\', ':'])
+        && !std::path::Path::new(name).is_absolute()
+}
+
+///
+/// `data` expanded if it's a compressed file, as is otherwise.
+/// Without feature `decompress`, or for KWAJ methods it doesn't
+/// unpack, compressed files give an `Unsupported` error.
+///
+pub fn expand_compressed(data: &[u8]) -> io::Result<Cow<'_, [u8]>> {
+    let Some(packing) = compressed_file(data) else {
+        return Ok(Cow::Borrowed(data));
+    };
+    #[cfg(feature = "decompress")]
+    if packing.is_supported() {
+        let expanded = decompress(data)?;
+        return match packing.unpacked_length {
+            Some(length) if expanded.len() != length as usize => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{} file is truncated: expands to 0x{:X} of 0x{:X} bytes",
+                    packing.scheme,
+                    expanded.len(),
+                    length
+                ),
+            )),
+            _ => Ok(Cow::Owned(expanded)),
+        };
+    }
+    let reason = if cfg!(feature = "decompress") {
+        "method isn't supported, run EXPAND.EXE"
+    } else {
+        "expanding needs feature `decompress`"
+    };
+    Err(io::Error::new(
+        ErrorKind::Unsupported,
+        format!("file is {}: {}", packing.scheme, reason),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// "ABCABCABC!" of `APP.EXE` packed by SZDD
+    const SZDD_FILE: &[u8] = b"SZDD\x88\xF0\x27\x33AE\x0A\0\0\0\x17ABC\xF0\xF3!";
+
+    #[test]
+    fn restores_names_and_data() {
+        assert_eq!(
+            expanded_name("APP.EX_", SZDD_FILE).as_deref(),
+            Some("APP.EXE")
+        );
+        assert_eq!(
+            expanded_name("app.ex_", SZDD_FILE).as_deref(),
+            Some("app.exe")
+        );
+        assert_eq!(expanded_name("APP.EXE", SZDD_FILE), None);
+
+        let mut kwaj = b"KWAJ\x88\xF0\x27\xD1\0\0\0\0\x1D\0\x0A\0\0\0".to_vec();
+        kwaj.extend_from_slice(b"\x02\0\xAA\xBBSETUP\0EXE\0");
+        let start = kwaj.len() as u16;
+        kwaj[10..12].copy_from_slice(&start.to_le_bytes());
+        kwaj.extend_from_slice(b"ABCABCABC!");
+        assert_eq!(kwaj_file_name(&kwaj).as_deref(), Some("SETUP.EXE"));
+        assert_eq!(expanded_name("X._", &kwaj).as_deref(), Some("SETUP.EXE"));
+        for stored in [&b"../pwned\0"[..], b"/tmp/x\0sh\0", b"..\\a\0", b"C:X\0"] {
+            let mut traversing = kwaj.clone();
+            traversing.truncate(22);
+            traversing.extend_from_slice(stored);
+            if !stored[..stored.len() - 1].contains(&0) {
+                traversing[12] = 0x0D;
+            }
+            let start = traversing.len() as u16;
+            traversing[10..12].copy_from_slice(&start.to_le_bytes());
+            traversing.extend_from_slice(b"ABCABCABC!");
+            assert!(kwaj_file_name(&traversing).is_some());
+            assert_eq!(expanded_name("X._", &traversing), None);
+        }
+
+        assert!(matches!(
+            expand_compressed(b"MZ\x90\0"),
+            Ok(Cow::Borrowed(b"MZ\x90\0"))
+        ));
+        #[cfg(feature = "decompress")]
+        {
+            assert_eq!(expand_compressed(SZDD_FILE).unwrap(), &b"ABCABCABC!"[..]);
+            assert_eq!(expand_compressed(&kwaj).unwrap(), &b"ABCABCABC!"[..]);
+            let truncated = &SZDD_FILE[..SZDD_FILE.len() - 3];
+            assert_eq!(
+                expand_compressed(truncated).unwrap_err().kind(),
+                ErrorKind::InvalidData
+            );
+        }
+        #[cfg(not(feature = "decompress"))]
+        assert_eq!(
+            expand_compressed(SZDD_FILE).unwrap_err().kind(),
+            ErrorKind::Unsupported
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use std::fs;
+use std::io::{self, Cursor, ErrorKind, Read, Seek};
+use std::path::Path;
+
+use crate::ne::findings::Finding;
+use crate::ne::fuzz::fuzz_options;
+use crate::ne::mscompress::expand_compressed;
+use crate::ne::options::ParseOptions;
+use crate::ne::source::{with_source_name, NamedModule, SourceName};
+use crate::ne::NeExecutable;
+use crate::util::codepage::Codepage;
+
+///
+/// Entry point of the crate:
+/// `Win16Ne::options().codepage(Codepage::Windows1251).strict(true).open(path)?`
+/// or just `Win16Ne::open(path)?` with defaults
+///
+#[derive(Debug, Clone, Copy)]
+pub struct Win16Ne;
+
+impl Win16Ne {
+    pub fn options() -> OpenOptions {
+        OpenOptions::default()
+    }
+
+    ///
+    /// Reads module at `path` with default `OpenOptions`
+    ///
+    pub fn open(path: impl AsRef<Path>) -> io::Result<NeExecutable> {
+        Self::options().open(path)
+    }
+}
+
+///
+/// Choices of reading a module in one place: `ParseOptions` with their
+/// code page and limits, what to read and how lenient to be.
+/// By default the whole module is read without limits, compressed
+/// files are expanded and parse warnings are kept in `warnings`.
+///
+#[derive(Debug, Clone)]
+pub struct OpenOptions {
+    parse: ParseOptions,
+    strict: bool,
+    resources_only: bool,
+    expand: bool,
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        Self {
+            parse: ParseOptions::default(),
+            strict: false,
+            resources_only: false,
+            expand: true,
+        }
+    }
+}
+
+impl OpenOptions {
+    ///
+    /// Code page of names and strings instead of the guessed one
+    ///
+    pub fn codepage(mut self, codepage: Codepage) -> Self {
+        self.parse.codepage = Some(codepage);
+        self
+    }
+
+    ///
+    /// Strict reading fails on the first parse warning
+    /// instead of keeping it in `warnings`
+    ///
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn max_total_bytes(mut self, bytes: u64) -> Self {
+        self.parse.max_total_bytes = Some(bytes);
+        self
+    }
+
+    pub fn max_table_entries(mut self, entries: usize) -> Self {
+        self.parse.max_table_entries = Some(entries);
+        self
+    }
+
+    ///
+    /// Limits of `parse_any_bytes` for files of unknown origin
+    ///
+    pub fn untrusted(mut self) -> Self {
+        let limits = fuzz_options();
+        self.parse.max_total_bytes = limits.max_total_bytes;
+        self.parse.max_table_entries = limits.max_table_entries;
+        self
+    }
+
+    ///
+    /// Leaves data of bigger resources unread, for `ResourceHandle` to load on demand
+    ///
+    pub fn lazy_resources(mut self, max_eager_size: u64) -> Self {
+        self.parse.max_eager_resource_size = Some(max_eager_size);
+        self
+    }
+
+    ///
+    /// Reads only NE header and resources (see `read_resources_only`)
+    ///
+    pub fn resources_only(mut self, resources_only: bool) -> Self {
+        self.resources_only = resources_only;
+        self
+    }
+
+    ///
+    /// Whether files packed by `COMPRESS.EXE` are expanded before reading
+    /// (see `expand_compressed`); on by default
+    ///
+    pub fn expand_compressed(mut self, expand: bool) -> Self {
+        self.expand = expand;
+        self
+    }
+
+    pub fn parse_options(&self) -> &ParseOptions {
+        &self.parse
+    }
+
+    ///
+    /// Reads module at `path`
+    ///
+    pub fn open(&self, path: impl AsRef<Path>) -> io::Result<NeExecutable> {
+        self.read_bytes(&fs::read(path)?)
+    }
+
+    ///
+    /// Reads module of `data`, expanded first if compressed
+    ///
+    pub fn read_bytes(&self, data: &[u8]) -> io::Result<NeExecutable> {
+        if self.expand {
+            self.read(&mut Cursor::new(&*expand_compressed(data)?))
+        } else {
+            self.read(&mut Cursor::new(data))
+        }
+    }
+
+    ///
+    /// Reads module of `file` as is: seekable readers aren't expanded
+    ///
+    pub fn read<R: Read + Seek>(&self, file: &mut R) -> io::Result<NeExecutable> {
+        let ne = if self.resources_only {
+            NeExecutable::read_resources_only_with(file, &self.parse)?
+        } else {
+            NeExecutable::read_with(file, &self.parse)?
+        };
+        match ne.warnings.first() {
+            Some(warning) if self.strict => {
+                Err(io::Error::new(ErrorKind::InvalidData, warning.tagged()))
+            }
+            _ => Ok(ne),
+        }
+    }
+
+    ///
+    /// Reads module from `reader` of source `name`, with errors naming it.
+    /// `NeExecutable::open_from_reader_with_name` reads through it too.
+    ///
+    pub fn read_named<R: Read>(&self, reader: &mut R, name: SourceName) -> io::Result<NamedModule> {
+        let mut data = Vec::new();
+        reader
+            .read_to_end(&mut data)
+            .map_err(|e| with_source_name(&name, e))?;
+        self.read_bytes_named(&data, name)
+    }
+
+    ///
+    /// Reads module of `data` extracted from source `name` as `read_bytes` does,
+    /// with errors naming it
+    ///
+    pub fn read_bytes_named(&self, data: &[u8], name: SourceName) -> io::Result<NamedModule> {
+        match self.read_bytes(data) {
+            Ok(module) => Ok(NamedModule {
+                source_name: name,
+                module,
+            }),
+            Err(e) => Err(with_source_name(&name, e)),
+        }
+    }
+}
+
+///
+/// Default choices of reading with `parse` options
+///
+impl From<ParseOptions> for OpenOptions {
+    fn from(parse: ParseOptions) -> Self {
+        Self {
+            parse,
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::fixture::sample_bytes;
+    use crate::ne::source::SourcedError;
+
+    #[test]
+    fn options_wire_parse_options() {
+        let options = Win16Ne::options()
+            .codepage(Codepage::Windows1251)
+            .untrusted()
+            .lazy_resources(0x1000)
+            .strict(true);
+        let parse = options.parse_options();
+        assert_eq!(parse.codepage, Some(Codepage::Windows1251));
+        assert_eq!(parse.max_total_bytes, fuzz_options().max_total_bytes);
+        assert_eq!(parse.max_eager_resource_size, Some(0x1000));
+        assert!(options.strict);
+
+        let e = options
+            .read_named(&mut &b"ZZ"[..], SourceName::member("A.ZIP", "B.EXE"))
+            .unwrap_err();
+        assert_eq!(
+            SourcedError::from_io(&e).unwrap().source_name.to_string(),
+            "A.ZIP!B.EXE"
+        );
+    }
+
+    #[test]
+    fn named_reads_expand_alike() {
+        // sample module packed by SZDD as literals only
+        let module = sample_bytes();
+        let mut szdd = b"SZDD\x88\xF0\x27\x33AE".to_vec();
+        szdd.extend_from_slice(&(module.len() as u32).to_le_bytes());
+        for literals in module.chunks(8) {
+            szdd.push(0xFF);
+            szdd.extend_from_slice(literals);
+        }
+        let name = SourceName::file("APP.EX_");
+        let options = ParseOptions::default();
+        let reads = [
+            OpenOptions::from(options.clone()).read_named(&mut &szdd[..], name.clone()),
+            NeExecutable::open_from_reader_with_name(&mut &szdd[..], name.clone(), &options),
+            NeExecutable::open_bytes_with_name(&szdd, name, &options),
+        ];
+        for read in reads {
+            #[cfg(feature = "decompress")]
+            assert_eq!(
+                read.unwrap().module.module_name().as_deref(),
+                Some("SAMPLE")
+            );
+            #[cfg(not(feature = "decompress"))]
+            assert_eq!(read.unwrap_err().kind(), ErrorKind::Unsupported);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::util::codepage::Codepage;
+
+///
+/// How a file is read, and limits of resources spent on it.
+/// Services parsing untrusted files set them to get `LimitExceeded`
+/// error instead of allocating whatever the headers ask for.
+/// No limits by default.
+///
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Ceiling of bytes allocated for segments, resources,
+    /// relocations, imported names and overlay together
+    pub max_total_bytes: Option<u64>,
+    /// Ceiling of entries in each table (segments, modules, resources, names, relocations)
+    pub max_table_entries: Option<usize>,
+    /// Bigger resources are not read while parsing;
+    /// their data stays `None` and is available through `ResourceHandle`
+    pub max_eager_resource_size: Option<u64>,
+    /// Code page of module name, description and other names;
+    /// guessed from resources if not set (see `guess_codepage`)
+    pub codepage: Option<Codepage>,
+    /// Longer names give `Warning::LongName`; by default only names
+    /// of 255 bytes do, which fill the length byte
+    pub long_name_length: Option<usize>,
+}
+
+///
+/// Payload of `io::Error` returned when file needs more than `ParseOptions` allow.
+/// Get it back by `LimitExceeded::from_io`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitExceeded {
+    /// Name of exceeded limit (field of `ParseOptions`)
+    pub limit: &'static str,
+    /// Structure which needed these resources
+    pub what: String,
+    pub requested: u64,
+    pub allowed: u64,
+}
+
+impl LimitExceeded {
+    pub fn from_io(e: &io::Error) -> Option<&LimitExceeded> {
+        e.get_ref()?.downcast_ref::<LimitExceeded>()
+    }
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} needs {}, but {} is {}",
+            self.what, self.requested, self.limit, self.allowed
+        )
+    }
+}
+
+impl Error for LimitExceeded {}
+
+///
+/// Tracks spending of one parse against `ParseOptions`
+///
+#[derive(Debug, Clone)]
+pub struct Budget {
+    options: ParseOptions,
+    allocated: u64,
+}
+
+impl Budget {
+    pub fn new(options: &ParseOptions) -> Self {
+        Self {
+            options: options.clone(),
+            allocated: 0,
+        }
+    }
+
+    /// Bytes allocated so far
+    pub fn allocated(&self) -> u64 {
+        self.allocated
+    }
+
+    ///
+    /// Takes `bytes` from the budget before allocating them
+    /// \param what -- structure for the error message
+    ///
+    pub fn allocate(&mut self, what: &str, bytes: u64) -> io::Result<()> {
+        let requested = self.allocated.saturating_add(bytes);
+        if let Some(allowed) = self.options.max_total_bytes {
+            if requested > allowed {
+                return Err(limit_exceeded("max_total_bytes", what, requested, allowed));
+            }
+        }
+        self.allocated = requested;
+        Ok(())
+    }
+
+    pub fn check_entries(&self, what: &str, count: usize) -> io::Result<()> {
+        match self.options.max_table_entries {
+            Some(allowed) if count > allowed => Err(limit_exceeded(
+                "max_table_entries",
+                what,
+                count as u64,
+                allowed as u64,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    ///
+    /// Whether resource of this size is read while parsing
+    ///
+    pub fn is_eager_resource(&self, size: u64) -> bool {
+        self.options
+            .max_eager_resource_size
+            .is_none_or(|max| size <= max)
+    }
+}
+
+fn limit_exceeded(limit: &'static str, what: &str, requested: u64, allowed: u64) -> io::Error {
+    io::Error::other(LimitExceeded {
+        limit,
+        what: what.to_string(),
+        requested,
+        allowed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget_ceilings() {
+        let options = ParseOptions {
+            max_total_bytes: Some(100),
+            max_table_entries: Some(3),
+            max_eager_resource_size: Some(10),
+            ..ParseOptions::default()
+        };
+        let mut budget = Budget::new(&options);
+        budget.allocate("segment #1", 60).unwrap();
+        let e = budget.allocate("segment #2", 50).unwrap_err();
+        let limit = LimitExceeded::from_io(&e).unwrap();
+        assert_eq!(limit.limit, "max_total_bytes");
+        assert_eq!((limit.requested, limit.allowed), (110, 100));
+        assert_eq!(budget.allocated(), 60);
+
+        assert!(budget.check_entries("segment table", 3).is_ok());
+        assert!(budget.check_entries("segment table", 4).is_err());
+        assert!(budget.is_eager_resource(10));
+        assert!(!budget.is_eager_resource(11));
+
+        let unlimited = Budget::new(&ParseOptions::default());
+        assert!(unlimited.check_entries("segment table", usize::MAX).is_ok());
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+
+///
+/// Windows version as `expected_win_ver` holds it: 3.10 is `{ major: 3, minor: 10 }`
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WinVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl WinVersion {
+    pub const WIN20: WinVersion = WinVersion::new(2, 0);
+    pub const WIN30: WinVersion = WinVersion::new(3, 0);
+    pub const WIN31: WinVersion = WinVersion::new(3, 10);
+
+    pub const fn new(major: u8, minor: u8) -> Self {
+        Self { major, minor }
+    }
+}
+
+impl fmt::Display for WinVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+///
+/// Procedure of a system module known by its ordinal
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownExport {
+    pub module: &'static str,
+    pub ordinal: u16,
+    pub name: &'static str,
+    /// First Windows version exporting the procedure
+    pub since: WinVersion,
+}
+
+const fn known(
+    module: &'static str,
+    ordinal: u16,
+    name: &'static str,
+    since: WinVersion,
+) -> KnownExport {
+    KnownExport {
+        module,
+        ordinal,
+        name,
+        since,
+    }
+}
+
+///
+/// Built-in ordinal database: exports of system modules whose ordinals
+/// are fixed since they appeared (numbering of Windows SDK import libraries).
+/// Procedures older than 3.0 are given as 2.0.
+///
+pub const KNOWN_EXPORTS: &[KnownExport] = &[
+    known("KERNEL", 1, "FatalExit", WinVersion::WIN20),
+    known("KERNEL", 3, "GetVersion", WinVersion::WIN20),
+    known("KERNEL", 5, "LocalAlloc", WinVersion::WIN20),
+    known("KERNEL", 7, "LocalFree", WinVersion::WIN20),
+    known("KERNEL", 15, "GlobalAlloc", WinVersion::WIN20),
+    known("KERNEL", 17, "GlobalFree", WinVersion::WIN20),
+    known("KERNEL", 18, "GlobalLock", WinVersion::WIN20),
+    known("KERNEL", 19, "GlobalUnlock", WinVersion::WIN20),
+    known("KERNEL", 30, "WaitEvent", WinVersion::WIN20),
+    known("KERNEL", 74, "OpenFile", WinVersion::WIN20),
+    known("KERNEL", 81, "_lclose", WinVersion::WIN20),
+    known("KERNEL", 82, "_lread", WinVersion::WIN20),
+    known("KERNEL", 83, "_lcreat", WinVersion::WIN20),
+    known("KERNEL", 84, "_llseek", WinVersion::WIN20),
+    known("KERNEL", 85, "_lopen", WinVersion::WIN20),
+    known("KERNEL", 86, "_lwrite", WinVersion::WIN20),
+    known("KERNEL", 91, "InitTask", WinVersion::WIN20),
+    known("KERNEL", 102, "DOS3Call", WinVersion::WIN20),
+    known("KERNEL", 320, "IsTask", WinVersion::WIN31),
+    known("KERNEL", 334, "IsBadReadPtr", WinVersion::WIN31),
+    known("KERNEL", 335, "IsBadWritePtr", WinVersion::WIN31),
+    known("KERNEL", 336, "IsBadCodePtr", WinVersion::WIN31),
+    known("KERNEL", 337, "IsBadStringPtr", WinVersion::WIN31),
+    known("KERNEL", 346, "IsBadHugeReadPtr", WinVersion::WIN31),
+    known("KERNEL", 347, "IsBadHugeWritePtr", WinVersion::WIN31),
+    known("KERNEL", 348, "hmemcpy", WinVersion::WIN31),
+    known("KERNEL", 349, "_hread", WinVersion::WIN31),
+    known("KERNEL", 350, "_hwrite", WinVersion::WIN31),
+    known("KERNEL", 353, "lstrcpyn", WinVersion::WIN31),
+    known("KERNEL", 354, "GetAppCompatFlags", WinVersion::WIN31),
+    known("USER", 1, "MessageBox", WinVersion::WIN20),
+    known("USER", 5, "InitApp", WinVersion::WIN20),
+    known("USER", 200, "OpenComm", WinVersion::WIN20),
+    known("USER", 204, "ReadComm", WinVersion::WIN20),
+    known("USER", 205, "WriteComm", WinVersion::WIN20),
+    known("USER", 207, "CloseComm", WinVersion::WIN20),
+    known("USER", 213, "BuildCommDCB", WinVersion::WIN20),
+    known("USER", 291, "SetWindowsHookEx", WinVersion::WIN31),
+    known("USER", 292, "UnhookWindowsHookEx", WinVersion::WIN31),
+    known("USER", 293, "CallNextHookEx", WinVersion::WIN31),
+    known("USER", 370, "GetWindowPlacement", WinVersion::WIN31),
+    known("USER", 371, "SetWindowPlacement", WinVersion::WIN31),
+    known("USER", 373, "SubtractRect", WinVersion::WIN31),
+    known("GDI", 330, "EnumFontFamilies", WinVersion::WIN31),
+];
+
+///
+/// System modules which appeared with a Windows version.
+/// Some of them were also shipped with applications for the older one.
+///
+pub const KNOWN_MODULES: &[(&str, WinVersion, Option<&str>)] = &[
+    (
+        "COMMDLG",
+        WinVersion::WIN31,
+        Some("redistributable for 3.0"),
+    ),
+    ("DDEML", WinVersion::WIN31, Some("redistributable for 3.0")),
+    (
+        "LZEXPAND",
+        WinVersion::WIN31,
+        Some("redistributable for 3.0"),
+    ),
+    (
+        "MMSYSTEM",
+        WinVersion::WIN31,
+        Some("Multimedia Extensions for 3.0"),
+    ),
+    ("OLECLI", WinVersion::WIN31, Some("redistributable for 3.0")),
+    ("OLESVR", WinVersion::WIN31, Some("redistributable for 3.0")),
+    ("SHELL", WinVersion::WIN31, None),
+    (
+        "TOOLHELP",
+        WinVersion::WIN31,
+        Some("redistributable for 3.0"),
+    ),
+    ("VER", WinVersion::WIN31, Some("redistributable for 3.0")),
+];
+
+///
+/// Known procedure of `module` (case-insensitive) by ordinal
+///
+pub fn lookup_ordinal(module: &str, ordinal: u16) -> Option<&'static KnownExport> {
+    KNOWN_EXPORTS
+        .iter()
+        .find(|e| e.ordinal == ordinal && e.module.eq_ignore_ascii_case(module))
+}
+
+///
+/// Known procedure of `module` by name; both are case-insensitive,
+/// as names of Win16 exports are uppercased by linkers
+///
+pub fn lookup_name(module: &str, name: &str) -> Option<&'static KnownExport> {
+    KNOWN_EXPORTS
+        .iter()
+        .find(|e| e.name.eq_ignore_ascii_case(name) && e.module.eq_ignore_ascii_case(module))
+}
+
+///
+/// Version and note of the system `module`, if it is newer than Windows 3.0
+///
+pub fn lookup_module(module: &str) -> Option<(WinVersion, Option<&'static str>)> {
+    KNOWN_MODULES
+        .iter()
+        .find(|(name, ..)| name.eq_ignore_ascii_case(module))
+        .map(|&(_, since, note)| (since, note))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookups_ignore_case() {
+        assert_eq!(
+            lookup_ordinal("user", 371).unwrap().name,
+            "SetWindowPlacement"
+        );
+        assert_eq!(
+            lookup_name("KERNEL", "ISBADREADPTR").unwrap().since,
+            WinVersion::WIN31
+        );
+        assert!(lookup_ordinal("GDI", 1).is_none());
+        assert_eq!(lookup_module("toolhelp").unwrap().0, WinVersion::WIN31);
+        assert!(WinVersion::WIN30 < WinVersion::WIN31);
+    }
+}
@@ -0,0 +1,263 @@
+use std::fmt;
+
+use crate::ne::entry_table::SegmentEntry;
+use crate::ne::NeExecutable;
+
+///
+/// Name table holding a name of entry point
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameTable {
+    Resident,
+    Nonresident,
+}
+
+impl fmt::Display for NameTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            NameTable::Resident => "resident",
+            NameTable::Nonresident => "nonresident",
+        })
+    }
+}
+
+///
+/// Name of entry point in one of name tables
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedOrdinal {
+    pub name: String,
+    pub ordinal: u16,
+    pub table: NameTable,
+}
+
+impl fmt::Display for NamedOrdinal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{} ({})", self.name, self.ordinal, self.table)
+    }
+}
+
+///
+/// Names and ordinals which don't map one to one.
+/// Loader searches the resident names table and then the nonresident one,
+/// each from the beginning, so the first definition wins.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrdinalConflict {
+    /// Ordinal named more than once; `definitions[0]` is the name reported for it
+    DuplicateOrdinal {
+        ordinal: u16,
+        definitions: Vec<NamedOrdinal>,
+    },
+    /// Name given to different ordinals; `GetProcAddress` finds `definitions[0]`
+    DuplicateName {
+        name: String,
+        definitions: Vec<NamedOrdinal>,
+    },
+    /// Name of ordinal which the entry table doesn't define; `GetProcAddress` fails
+    UndefinedOrdinal(NamedOrdinal),
+}
+
+impl fmt::Display for OrdinalConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |definitions: &[NamedOrdinal]| {
+            definitions
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            OrdinalConflict::DuplicateOrdinal {
+                ordinal,
+                definitions,
+            } => write!(
+                f,
+                "ordinal @{} is named {} times: {}; {} wins",
+                ordinal,
+                definitions.len(),
+                list(definitions),
+                definitions[0]
+            ),
+            OrdinalConflict::DuplicateName { name, definitions } => write!(
+                f,
+                "name {} is given to {} ordinals: {}; {} wins",
+                name,
+                definitions.len(),
+                list(definitions),
+                definitions[0]
+            ),
+            OrdinalConflict::UndefinedOrdinal(named) => {
+                write!(f, "{} names no entry point", named)
+            }
+        }
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Names of both tables in loader search order,
+    /// without module name and description (ordinal 0)
+    ///
+    pub fn named_ordinals(&self) -> Vec<NamedOrdinal> {
+        let resident = self
+            .resident_name_table
+            .entries
+            .iter()
+            .skip(1)
+            .map(|e| (&e.name, e.index, NameTable::Resident));
+        let nonresident = self
+            .nonresident_name_table
+            .entries
+            .iter()
+            .skip(1)
+            .map(|e| (&e.name, e.index, NameTable::Nonresident));
+        resident
+            .chain(nonresident)
+            .map(|(name, ordinal, table)| NamedOrdinal {
+                name: String::from_utf8_lossy(name).into_owned(),
+                ordinal,
+                table,
+            })
+            .collect()
+    }
+
+    ///
+    /// Ordinals named more than once, names given to several ordinals
+    /// and names of ordinals without entry points
+    ///
+    pub fn ordinal_conflicts(&self) -> Vec<OrdinalConflict> {
+        let named = self.named_ordinals();
+        let mut conflicts = Vec::new();
+
+        let mut seen_ordinals = Vec::new();
+        let mut seen_names = Vec::new();
+        for (i, definition) in named.iter().enumerate() {
+            let rest = &named[i + 1..];
+            if !seen_ordinals.contains(&definition.ordinal) {
+                seen_ordinals.push(definition.ordinal);
+                let mut definitions = vec![definition.clone()];
+                definitions.extend(
+                    rest.iter()
+                        .filter(|d| d.ordinal == definition.ordinal)
+                        .cloned(),
+                );
+                if definitions.len() > 1 {
+                    conflicts.push(OrdinalConflict::DuplicateOrdinal {
+                        ordinal: definition.ordinal,
+                        definitions,
+                    });
+                }
+            }
+            if !seen_names.contains(&&definition.name) {
+                seen_names.push(&definition.name);
+                let mut definitions = vec![definition.clone()];
+                definitions.extend(
+                    rest.iter()
+                        .filter(|d| d.name == definition.name && d.ordinal != definition.ordinal)
+                        .cloned(),
+                );
+                if definitions.len() > 1 {
+                    conflicts.push(OrdinalConflict::DuplicateName {
+                        name: definition.name.clone(),
+                        definitions,
+                    });
+                }
+            }
+        }
+
+        for definition in &named {
+            let entry = (definition.ordinal as usize)
+                .checked_sub(1)
+                .and_then(|i| self.entry_table.entries.get(i));
+            if matches!(entry, None | Some(SegmentEntry::Unused)) {
+                conflicts.push(OrdinalConflict::UndefinedOrdinal(definition.clone()));
+            }
+        }
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::fixture::sample_module;
+    use crate::ne::nonresident_name_table::NonresidentNameEntry;
+    use crate::ne::resident_name_table::ResidentNameEntry;
+    use NameTable::{Nonresident, Resident};
+
+    fn named(name: &str, ordinal: u16, table: NameTable) -> NamedOrdinal {
+        NamedOrdinal {
+            name: name.to_string(),
+            ordinal,
+            table,
+        }
+    }
+
+    #[test]
+    fn first_definition_wins() {
+        let mut ne = sample_module();
+        ne.resident_name_table.entries.push(ResidentNameEntry {
+            name: b"DUPNAME".to_vec(),
+            index: 2,
+        });
+        for (name, index) in [("ALIAS", 1), ("DUPNAME", 3), ("GHOST", 9), ("GONE", 4)] {
+            ne.nonresident_name_table
+                .entries
+                .push(NonresidentNameEntry {
+                    name: name.as_bytes().to_vec(),
+                    index,
+                });
+        }
+        ne.entry_table.entries[3] = SegmentEntry::Unused;
+        assert_eq!(
+            ne.named_ordinals(),
+            [
+                named("GETPROCADDRESS", 1, Resident),
+                named("DUPNAME", 2, Resident),
+                named("HIDDENPROC", 5, Nonresident),
+                named("ALIAS", 1, Nonresident),
+                named("DUPNAME", 3, Nonresident),
+                named("GHOST", 9, Nonresident),
+                named("GONE", 4, Nonresident),
+            ]
+        );
+
+        let conflicts = ne.ordinal_conflicts();
+        assert_eq!(
+            conflicts,
+            [
+                OrdinalConflict::DuplicateOrdinal {
+                    ordinal: 1,
+                    definitions: vec![
+                        named("GETPROCADDRESS", 1, Resident),
+                        named("ALIAS", 1, Nonresident),
+                    ],
+                },
+                OrdinalConflict::DuplicateName {
+                    name: "DUPNAME".to_string(),
+                    definitions: vec![
+                        named("DUPNAME", 2, Resident),
+                        named("DUPNAME", 3, Nonresident),
+                    ],
+                },
+                OrdinalConflict::UndefinedOrdinal(named("GHOST", 9, Nonresident)),
+                OrdinalConflict::UndefinedOrdinal(named("GONE", 4, Nonresident)),
+            ]
+        );
+        assert_eq!(
+            conflicts[1].to_string(),
+            "name DUPNAME is given to 2 ordinals: DUPNAME@2 (resident), \
+             DUPNAME@3 (nonresident); DUPNAME@2 (resident) wins"
+        );
+        // lookups agree with the winners
+        assert_eq!(ne.export_by_name("DUPNAME").map(|e| e.ordinal), Some(2));
+        assert_eq!(
+            ne.export_by_ordinal(1).and_then(|e| e.name).as_deref(),
+            Some("GETPROCADDRESS")
+        );
+        assert!(ne.export_by_name("GHOST").is_none());
+
+        assert!(sample_module().ordinal_conflicts().is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::ne::options::Budget;
+use crate::ne::segment_table::SEG_RELOCINFO;
+use crate::ne::NeExecutable;
+use crate::util::trace::trace_warn;
+
+///
+/// Data appended after the last structure of NE image.
+/// Loader never reads it, so installers, self-extracting archives
+/// and debuggers (CodeView, Turbo Debugger symbols) keep their data there.
+///
+#[derive(Debug, Clone)]
+pub struct Overlay {
+    /// File offset of the overlay
+    pub offset: u64,
+    pub data: Vec<u8>,
+}
+
+impl Overlay {
+    ///
+    /// Reads everything from `image_end` till the end of file
+    /// \param image_end -- first byte after NE image structures
+    ///
+    pub fn read<R: Read + Seek>(
+        r: &mut R,
+        image_end: u64,
+        budget: &mut Budget,
+    ) -> io::Result<Option<Self>> {
+        let file_end = r.seek(SeekFrom::End(0))?;
+        if file_end <= image_end {
+            return Ok(None);
+        }
+        budget.allocate("overlay", file_end - image_end)?;
+        trace_warn!(
+            "overlay of {} bytes at {:#X}",
+            file_end - image_end,
+            image_end
+        );
+        r.seek(SeekFrom::Start(image_end))?;
+        let mut data = Vec::with_capacity((file_end - image_end) as usize);
+        r.read_to_end(&mut data)?;
+        Ok(Some(Self {
+            offset: image_end,
+            data,
+        }))
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Finds the end of NE image: the farthest end of tables,
+    /// segments (with their relocation records) and resources.
+    ///
+    pub fn image_end(&self) -> u64 {
+        let lfanew = self.dos_header.lfanew.value() as u64;
+        let header = &self.ne_header;
+
+        let entry_table_end = lfanew
+            + header.entry_table_offset.value() as u64
+            + header.entry_table_length.value() as u64;
+        let nonresident_names_end = header.non_resident_names_table_offset.value() as u64
+            + header.non_resident_names_size.value() as u64;
+
+        let segments_end = self
+            .segment_entries
+            .iter()
+            .zip(&self.relocation_tables_per_segment)
+            .filter(|(segment, _)| segment.data.is_some())
+            .map(|(segment, relocations)| {
+                let relocations_size = if segment.header.flags & SEG_RELOCINFO != 0 {
+                    2 + 8 * relocations.entries.len() as u64
+                } else {
+                    0
+                };
+                segment.data_offset() + segment.data_length() + relocations_size
+            })
+            .max()
+            .unwrap_or(0);
+
+        let shift_count = self.resource_table.header.alignment_shift_count;
+        let resources_end = self
+            .resource_table
+            .resource_types
+            .iter()
+            .flat_map(|t| &t.resources)
+            .map(|r| r.data_offset(shift_count) + r.data_length(shift_count))
+            .max()
+            .unwrap_or(0);
+
+        entry_table_end
+            .max(nonresident_names_end)
+            .max(segments_end)
+            .max(resources_end)
+    }
+
+    ///
+    /// Length of the file the module was read from: end of the overlay,
+    /// of the image if there is none
+    ///
+    pub fn file_length(&self) -> u64 {
+        match &self.overlay {
+            Some(overlay) => overlay.offset + overlay.data.len() as u64,
+            None => self.image_end(),
+        }
+    }
+}
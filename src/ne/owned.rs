@@ -0,0 +1,119 @@
+use std::io::{self, Cursor};
+use std::ops::Deref;
+
+use crate::ne::addr::Seg16;
+use crate::ne::options::ParseOptions;
+use crate::ne::resource_table::{ResourceHandle, ResourceId};
+use crate::ne::NeExecutable;
+
+///
+/// Parsed module together with the bytes it was read from.
+/// Views of segments, resources and overlay are slices of these bytes
+/// located by offsets of parsed structures, so the handle has no
+/// lifetime and can be moved between threads as a whole.
+/// Dereferences to the parsed `NeExecutable`.
+///
+#[derive(Debug, Clone)]
+pub struct NeOwned {
+    bytes: Vec<u8>,
+    module: NeExecutable,
+}
+
+impl NeOwned {
+    pub fn new(bytes: Vec<u8>) -> io::Result<Self> {
+        Self::with_options(bytes, &ParseOptions::default())
+    }
+
+    ///
+    /// Parses `bytes` keeping within limits of `options`
+    ///
+    pub fn with_options(bytes: Vec<u8>, options: &ParseOptions) -> io::Result<Self> {
+        let module = NeExecutable::read_with(&mut Cursor::new(&bytes), options)?;
+        Ok(Self { bytes, module })
+    }
+
+    pub fn module(&self) -> &NeExecutable {
+        &self.module
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_parts(self) -> (Vec<u8>, NeExecutable) {
+        (self.bytes, self.module)
+    }
+
+    ///
+    /// `length` bytes at file `offset`, if the file has all of them
+    ///
+    pub fn slice(&self, offset: u64, length: u64) -> Option<&[u8]> {
+        let start = usize::try_from(offset).ok()?;
+        let end = start.checked_add(usize::try_from(length).ok()?)?;
+        self.bytes.get(start..end)
+    }
+
+    ///
+    /// Data of segment on file, without relocation records.
+    /// \param number -- segment number (from 1)
+    ///
+    pub fn segment_bytes(&self, number: u16) -> Option<&[u8]> {
+        let segment = self.module.segment(Seg16(number))?;
+        if segment.header.data_offset_shifted == 0 {
+            return None;
+        }
+        self.slice(segment.data_offset(), segment.data_length())
+    }
+
+    ///
+    /// Data of resource on file, with padding up to resource alignment
+    ///
+    pub fn resource_bytes(&self, type_id: &ResourceId, id: &ResourceId) -> Option<&[u8]> {
+        let table = &self.module.resource_table;
+        let resource = table.find_resource(type_id, id)?;
+        if resource.header.data_offset_shifted == 0 {
+            return None;
+        }
+        let shift_count = table.header.alignment_shift_count;
+        self.slice(
+            resource.data_offset(shift_count),
+            resource.data_length(shift_count),
+        )
+    }
+
+    ///
+    /// Data located by `handle` (e.g. of `custom_resources`)
+    ///
+    pub fn handle_bytes(&self, handle: &ResourceHandle) -> Option<&[u8]> {
+        self.slice(handle.offset, handle.length)
+    }
+
+    ///
+    /// Everything after the last structure of NE image
+    ///
+    pub fn overlay_bytes(&self) -> Option<&[u8]> {
+        let overlay = self.module.overlay.as_ref()?;
+        self.bytes.get(overlay.offset as usize..)
+    }
+}
+
+impl Deref for NeOwned {
+    type Target = NeExecutable;
+
+    fn deref(&self) -> &NeExecutable {
+        &self.module
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    #[test]
+    fn moves_between_threads() {
+        assert_send_sync::<NeOwned>();
+        assert!(NeOwned::new(b"MZ".to_vec()).is_err());
+    }
+}
@@ -0,0 +1,418 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use crate::ne::resource_table::ResourceId;
+use crate::ne::sink::{DirSink, ExtractSink};
+use crate::ne::unpack::resource_paths;
+use crate::ne::NeExecutable;
+
+/// Signature of files packed by `COMPRESS.EXE` of MS-DOS 5 / Windows 3.1
+const SZDD_MAGIC: &[u8; 8] = b"SZDD\x88\xF0\x27\x33";
+/// Signature of files packed by `COMPRESS.EXE -Z` of MS-DOS 6
+const KWAJ_MAGIC: &[u8; 8] = b"KWAJ\x88\xF0\x27\xD1";
+/// Signature of files packed by the QuickBASIC variant of `COMPRESS.EXE`
+const SZ_MAGIC: &[u8; 8] = b"SZ \x88\xF0\x27\x33\xD1";
+
+/// KWAJ data is stored as is
+pub const KWAJ_STORED: u16 = 0;
+/// KWAJ data is XORed with 0xFF
+pub const KWAJ_XOR: u16 = 1;
+/// KWAJ data is packed as SZDD data
+pub const KWAJ_LZSS: u16 = 2;
+/// KWAJ data is packed by LZ with Huffman codes
+pub const KWAJ_LZH: u16 = 3;
+/// KWAJ data is packed by MSZIP (deflate)
+pub const KWAJ_MSZIP: u16 = 4;
+
+///
+/// Packer whose signature starts resource data
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackingScheme {
+    /// `SZDD`: LZSS of `COMPRESS.EXE`, unpacked by `EXPAND.EXE` and `LZEXPAND.DLL`
+    Szdd,
+    /// `SZ` of QuickBASIC and early `COMPRESS.EXE`: the same LZSS
+    Sz,
+    /// `KWAJ` of `COMPRESS.EXE -Z` with one of `KWAJ_*` methods
+    Kwaj {
+        method: u16,
+    },
+    /// PKZIP archive (`PK\3\4`)
+    Pkzip,
+    /// Cabinet of setup programs (`MSCF`)
+    Cabinet,
+    Gzip,
+}
+
+impl fmt::Display for PackingScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PackingScheme::Szdd => write!(f, "SZDD"),
+            PackingScheme::Sz => write!(f, "SZ"),
+            PackingScheme::Kwaj { method } => {
+                let name = match *method {
+                    KWAJ_STORED => "stored",
+                    KWAJ_XOR => "XOR",
+                    KWAJ_LZSS => "LZSS",
+                    KWAJ_LZH => "LZH",
+                    KWAJ_MSZIP => "MSZIP",
+                    _ => return write!(f, "KWAJ (method {})", method),
+                };
+                write!(f, "KWAJ ({})", name)
+            }
+            PackingScheme::Pkzip => write!(f, "PKZIP"),
+            PackingScheme::Cabinet => write!(f, "cabinet"),
+            PackingScheme::Gzip => write!(f, "gzip"),
+        }
+    }
+}
+
+///
+/// Packed data found by `detect_packing`
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packing {
+    pub scheme: PackingScheme,
+    /// Length of unpacked data, if the header tells it
+    pub unpacked_length: Option<u32>,
+}
+
+impl Packing {
+    ///
+    /// Whether `decompress` unpacks this scheme
+    ///
+    pub fn is_supported(&self) -> bool {
+        matches!(
+            self.scheme,
+            PackingScheme::Szdd
+                | PackingScheme::Sz
+                | PackingScheme::Kwaj {
+                    method: KWAJ_STORED | KWAJ_XOR | KWAJ_LZSS
+                }
+        )
+    }
+}
+
+impl fmt::Display for Packing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} packed", self.scheme)?;
+        if let Some(length) = self.unpacked_length {
+            write!(f, ", 0x{:X} bytes unpacked", length)?;
+        }
+        Ok(())
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+///
+/// Packer of `data` told by its signature, `None` for data of no known packer
+///
+pub fn detect_packing(data: &[u8]) -> Option<Packing> {
+    let (scheme, unpacked_length) = if data.starts_with(SZDD_MAGIC) {
+        (PackingScheme::Szdd, u32_at(data, 10))
+    } else if data.starts_with(SZ_MAGIC) {
+        (PackingScheme::Sz, u32_at(data, 8))
+    } else if data.starts_with(KWAJ_MAGIC) {
+        let method = u16_at(data, 8)?;
+        // the first optional field of the header is the unpacked length
+        let flags = u16_at(data, 12)?;
+        let length = if flags & 1 != 0 {
+            u32_at(data, 14)
+        } else {
+            None
+        };
+        (PackingScheme::Kwaj { method }, length)
+    } else if data.starts_with(b"PK\x03\x04") {
+        (PackingScheme::Pkzip, u32_at(data, 22))
+    } else if data.starts_with(b"MSCF\0\0\0\0") {
+        (PackingScheme::Cabinet, None)
+    } else if data.starts_with(b"\x1F\x8B\x08") {
+        let length = data.len().checked_sub(4).and_then(|end| u32_at(data, end));
+        (PackingScheme::Gzip, length)
+    } else {
+        return None;
+    };
+    Some(Packing {
+        scheme,
+        unpacked_length,
+    })
+}
+
+#[cfg(feature = "decompress")]
+fn truncated(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{} header is truncated", what),
+    )
+}
+
+/// Window of LZSS of `COMPRESS.EXE`
+#[cfg(feature = "decompress")]
+const LZSS_WINDOW: usize = 0x1000;
+/// First write position in the window of SZDD and KWAJ LZSS data
+#[cfg(feature = "decompress")]
+const SZDD_WINDOW_START: usize = LZSS_WINDOW - 16;
+/// First write position in the window of QuickBASIC SZ data
+#[cfg(feature = "decompress")]
+const SZ_WINDOW_START: usize = LZSS_WINDOW - 18;
+
+///
+/// LZSS of `COMPRESS.EXE`: flag byte for every 8 items, set bits are literal
+/// bytes, clear ones 12-bit position and 4-bit length in a window of 4 KiB
+/// filled with spaces, written from `start` on. Stops after `limit` bytes if given.
+///
+#[cfg(feature = "decompress")]
+fn expand_lzss(packed: &[u8], start: usize, limit: Option<usize>) -> Vec<u8> {
+    const WINDOW: usize = LZSS_WINDOW;
+    let mut window = [b' '; WINDOW];
+    let mut position = start;
+    let mut out = Vec::new();
+    let mut input = packed.iter().copied();
+    let full = |out: &Vec<u8>| limit.is_some_and(|limit| out.len() >= limit);
+    'items: while let Some(flags) = input.next() {
+        for bit in 0..8 {
+            if full(&out) {
+                break 'items;
+            }
+            if flags & (1 << bit) != 0 {
+                let Some(byte) = input.next() else {
+                    break 'items;
+                };
+                out.push(byte);
+                window[position] = byte;
+                position = (position + 1) % WINDOW;
+            } else {
+                let (Some(low), Some(high)) = (input.next(), input.next()) else {
+                    break 'items;
+                };
+                let mut from = low as usize | ((high as usize & 0xF0) << 4);
+                for _ in 0..(high & 0x0F) as usize + 3 {
+                    let byte = window[from];
+                    out.push(byte);
+                    window[position] = byte;
+                    position = (position + 1) % WINDOW;
+                    from = (from + 1) % WINDOW;
+                }
+            }
+        }
+    }
+    if let Some(limit) = limit {
+        out.truncate(limit);
+    }
+    out
+}
+
+///
+/// Unpacks `data` of SZDD, SZ or KWAJ (stored, XOR or LZSS method) packer.
+/// Other schemes (see `Packing::is_supported`) give `Unsupported` error.
+///
+#[cfg(feature = "decompress")]
+pub fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let packing = detect_packing(data).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "data has no known packer signature",
+        )
+    })?;
+    let limit = packing.unpacked_length.map(|length| length as usize);
+    match packing.scheme {
+        PackingScheme::Szdd => Ok(expand_lzss(
+            data.get(14..).ok_or_else(|| truncated("SZDD"))?,
+            SZDD_WINDOW_START,
+            limit,
+        )),
+        PackingScheme::Sz => Ok(expand_lzss(
+            data.get(12..).ok_or_else(|| truncated("SZ"))?,
+            SZ_WINDOW_START,
+            limit,
+        )),
+        PackingScheme::Kwaj { method } => {
+            let start = u16_at(data, 10).ok_or_else(|| truncated("KWAJ"))? as usize;
+            let packed = data.get(start..).ok_or_else(|| truncated("KWAJ"))?;
+            match method {
+                KWAJ_STORED => Ok(packed.to_vec()),
+                KWAJ_XOR => Ok(packed.iter().map(|b| b ^ 0xFF).collect()),
+                KWAJ_LZSS => Ok(expand_lzss(packed, SZDD_WINDOW_START, limit)),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("{} data can't be unpacked", packing.scheme),
+                )),
+            }
+        }
+        scheme => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} data can't be unpacked", scheme),
+        )),
+    }
+}
+
+///
+/// Resource whose data starts with a packer signature
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedResource {
+    pub type_id: ResourceId,
+    pub id: ResourceId,
+    pub packing: Packing,
+}
+
+///
+/// Resource written by `extract_resources`
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractedResource {
+    /// Path of the file relative to the directory
+    pub path: String,
+    pub length: usize,
+    /// Packer of resource data, if detected
+    pub packing: Option<Packing>,
+    /// Whether the file holds unpacked data
+    pub unpacked: bool,
+}
+
+impl fmt::Display for ExtractedResource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: 0x{:X} bytes", self.path, self.length)?;
+        match (&self.packing, self.unpacked) {
+            (Some(packing), true) => write!(f, ", unpacked from {}", packing.scheme),
+            (Some(packing), false) => write!(f, ", {}", packing),
+            (None, _) => Ok(()),
+        }
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Resources packed by known packers, mostly payloads of installers
+    /// in `RCDATA` and custom types
+    ///
+    pub fn packed_resources(&self) -> Vec<PackedResource> {
+        let mut packed = Vec::new();
+        for resource_type in &self.resource_table.resource_types {
+            for resource in &resource_type.resources {
+                let Some(packing) = resource.data.as_deref().and_then(detect_packing) else {
+                    continue;
+                };
+                packed.push(PackedResource {
+                    type_id: resource_type.id(),
+                    id: resource.id(),
+                    packing,
+                });
+            }
+        }
+        packed
+    }
+
+    ///
+    /// Writes data of every read resource into `dir` as
+    /// `resources/<type>/<id>.bin`, the paths of `unpack`.
+    /// With feature `decompress`, data of supported packers is unpacked
+    /// (see `decompress`); data failing to unpack is written as is.
+    ///
+    pub fn extract_resources(&self, dir: &Path) -> io::Result<Vec<ExtractedResource>> {
+        self.extract_resources_to(&mut DirSink::new(dir))
+    }
+
+    ///
+    /// Puts data of every read resource into `sink`, as `extract_resources` does
+    ///
+    pub fn extract_resources_to(
+        &self,
+        sink: &mut dyn ExtractSink,
+    ) -> io::Result<Vec<ExtractedResource>> {
+        let mut extracted = Vec::new();
+        let resources = self
+            .resource_table
+            .resource_types
+            .iter()
+            .flat_map(|t| &t.resources);
+        for (resource, path) in resources.zip(resource_paths(&self.resource_table)) {
+            let Some(data) = &resource.data else {
+                continue;
+            };
+            let packing = detect_packing(data);
+            #[cfg(feature = "decompress")]
+            let unpacked = packing
+                .filter(Packing::is_supported)
+                .and_then(|_| decompress(data).ok());
+            #[cfg(not(feature = "decompress"))]
+            let unpacked: Option<Vec<u8>> = None;
+
+            let written = unpacked.as_deref().unwrap_or(data);
+            sink.put(&path, written)?;
+            extracted.push(ExtractedResource {
+                path,
+                length: written.len(),
+                packing,
+                unpacked: unpacked.is_some(),
+            });
+        }
+        Ok(extracted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// "ABCABCABC!" packed by SZDD: three literals, a copy of 6, a literal
+    const SZDD_SAMPLE: &[u8] = b"SZDD\x88\xF0\x27\x33A\0\x0A\0\0\0\x17ABC\xF0\xF3!";
+
+    #[test]
+    fn detects_packer_signatures() {
+        assert_eq!(
+            detect_packing(SZDD_SAMPLE),
+            Some(Packing {
+                scheme: PackingScheme::Szdd,
+                unpacked_length: Some(10),
+            })
+        );
+        let kwaj = b"KWAJ\x88\xF0\x27\xD1\x02\0\x12\0\x01\0\x0A\0\0\0";
+        assert_eq!(
+            detect_packing(kwaj).map(|p| (p.scheme, p.unpacked_length)),
+            Some((PackingScheme::Kwaj { method: KWAJ_LZSS }, Some(10)))
+        );
+        assert_eq!(
+            detect_packing(b"PK\x03\x04").map(|p| p.scheme),
+            Some(PackingScheme::Pkzip)
+        );
+        assert_eq!(detect_packing(b"\x28\0\0\0BITMAP"), None);
+    }
+
+    #[cfg(feature = "decompress")]
+    #[test]
+    fn expands_szdd_and_kwaj() {
+        assert_eq!(decompress(SZDD_SAMPLE).unwrap(), b"ABCABCABC!");
+        // the same text: SZ writes its window from 0xFEE, the copy reads from there
+        let sz = b"SZ \x88\xF0\x27\x33\xD1\x0A\0\0\0\x17ABC\xEE\xF3!";
+        assert_eq!(
+            detect_packing(sz).map(|p| p.scheme),
+            Some(PackingScheme::Sz)
+        );
+        assert_eq!(decompress(sz).unwrap(), b"ABCABCABC!");
+        // back-reference into the initial spaces of the window
+        let spaces = b"SZDD\x88\xF0\x27\x33A\0\x05\0\0\0\x01X\x00\xF1";
+        assert_eq!(decompress(spaces).unwrap(), b"X    ");
+        let mut kwaj = b"KWAJ\x88\xF0\x27\xD1\x02\0\x12\0\x01\0\x0A\0\0\0".to_vec();
+        kwaj.extend_from_slice(&SZDD_SAMPLE[14..]);
+        assert_eq!(decompress(&kwaj).unwrap(), b"ABCABCABC!");
+        let mut lzh = kwaj.clone();
+        lzh[8] = KWAJ_LZH as u8;
+        assert_eq!(
+            decompress(&lzh).unwrap_err().kind(),
+            io::ErrorKind::Unsupported
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::mz::DosHeader;
+use crate::ne::addr::Seg16;
+use crate::ne::header::NeHeader;
+use crate::ne::owned::NeOwned;
+use crate::ne::segment_table::SEG_RELOCINFO;
+use crate::ne::NeExecutable;
+
+///
+/// Table of the module kept on file as it was encoded
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NeTable {
+    DosHeader,
+    NeHeader,
+    SegmentTable,
+    ResourceTable,
+    ResidentNames,
+    ModuleReferences,
+    ImportedNames,
+    EntryTable,
+    NonresidentNames,
+    /// Relocation records following data of the segment, with their count
+    Relocations(Seg16),
+}
+
+impl fmt::Display for NeTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NeTable::DosHeader => write!(f, "DOS header"),
+            NeTable::NeHeader => write!(f, "NE header"),
+            NeTable::SegmentTable => write!(f, "segment table"),
+            NeTable::ResourceTable => write!(f, "resource table"),
+            NeTable::ResidentNames => write!(f, "resident names table"),
+            NeTable::ModuleReferences => write!(f, "module reference table"),
+            NeTable::ImportedNames => write!(f, "imported names table"),
+            NeTable::EntryTable => write!(f, "entry table"),
+            NeTable::NonresidentNames => write!(f, "nonresident names table"),
+            NeTable::Relocations(segment) => write!(f, "relocations of segment #{}", segment),
+        }
+    }
+}
+
+///
+/// Place of a table on file
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableRange {
+    pub table: NeTable,
+    pub offset: u64,
+    pub length: u64,
+}
+
+impl TableRange {
+    pub fn end(&self) -> u64 {
+        self.offset + self.length
+    }
+
+    pub fn range(&self) -> Range<u64> {
+        self.offset..self.end()
+    }
+}
+
+impl fmt::Display for TableRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#08X}-{:#08X} {}", self.offset, self.end(), self.table)
+    }
+}
+
+///
+/// Size of name table with entries of `lengths`: length byte, name
+/// and ordinal of each, then the terminating zero
+///
+fn names_length(lengths: impl Iterator<Item = usize>) -> u64 {
+    lengths.map(|len| 1 + len as u64 + 2).sum::<u64>() + 1
+}
+
+///
+/// Places of header tables as `header` at `lfanew` gives them, before
+/// they are read: name and resource tables end where the next table
+/// begins, as linkers lay them out one after another. Relocations and
+/// tables absent in the header aren't included.
+///
+pub fn header_table_ranges(lfanew: u64, header: &NeHeader) -> Vec<TableRange> {
+    let relative = |offset: u16| lfanew + offset as u64;
+    let between = |start: u16, end: u16| end.saturating_sub(start) as u64;
+    let resources = if header.dialect().has_resource_table() {
+        between(
+            header.resource_table_offset.value(),
+            header.resident_names_table_offset.value(),
+        )
+    } else {
+        0
+    };
+    [
+        (
+            NeTable::SegmentTable,
+            relative(header.segment_table_offset.value()),
+            8 * header.segment_count.value() as u64,
+        ),
+        (
+            NeTable::ResourceTable,
+            relative(header.resource_table_offset.value()),
+            resources,
+        ),
+        (
+            NeTable::ResidentNames,
+            relative(header.resident_names_table_offset.value()),
+            between(
+                header.resident_names_table_offset.value(),
+                header.module_reference_table_offset.value(),
+            ),
+        ),
+        (
+            NeTable::ModuleReferences,
+            relative(header.module_reference_table_offset.value()),
+            2 * header.module_references.value() as u64,
+        ),
+        (
+            NeTable::ImportedNames,
+            relative(header.import_name_table_offset.value()),
+            between(
+                header.import_name_table_offset.value(),
+                header.entry_table_offset.value(),
+            ),
+        ),
+        (
+            NeTable::EntryTable,
+            relative(header.entry_table_offset.value()),
+            header.entry_table_length.value() as u64,
+        ),
+        (
+            NeTable::NonresidentNames,
+            header.non_resident_names_table_offset.value() as u64,
+            header.non_resident_names_size.value() as u64,
+        ),
+    ]
+    .into_iter()
+    .filter(|&(_, offset, length)| length != 0 && offset != 0)
+    .map(|(table, offset, length)| TableRange {
+        table,
+        offset,
+        length,
+    })
+    .collect()
+}
+
+impl NeExecutable {
+    ///
+    /// Place of `table` on file as parsed. Sizes of name tables are counted
+    /// from their entries with the terminating zero; the resource table
+    /// ends where resident names begin, as Windows takes it.
+    /// `None` for tables absent in the module.
+    ///
+    pub fn table_range(&self, table: NeTable) -> Option<TableRange> {
+        let lfanew = self.dos_header.lfanew.value() as u64;
+        let header = &self.ne_header;
+        let relative = |offset: u16| lfanew + offset as u64;
+
+        let (offset, length) = match table {
+            // images read by `read_at` have no DOS header on file
+            NeTable::DosHeader if self.dos_stub.is_empty() => return None,
+            NeTable::DosHeader => (0, std::mem::size_of::<DosHeader>() as u64),
+            NeTable::NeHeader => (lfanew, std::mem::size_of::<NeHeader>() as u64),
+            NeTable::SegmentTable => (
+                relative(header.segment_table_offset.value()),
+                8 * self.segment_entries.len() as u64,
+            ),
+            NeTable::ResourceTable => {
+                let start = header.resource_table_offset.value();
+                let end = header.resident_names_table_offset.value();
+                if start >= end || !header.dialect().has_resource_table() {
+                    return None;
+                }
+                (relative(start), (end - start) as u64)
+            }
+            NeTable::ResidentNames => (
+                relative(header.resident_names_table_offset.value()),
+                names_length(
+                    self.resident_name_table
+                        .entries
+                        .iter()
+                        .map(|e| e.name.len()),
+                ),
+            ),
+            NeTable::ModuleReferences => (
+                relative(header.module_reference_table_offset.value()),
+                2 * self.module_reference_table.entries.len() as u64,
+            ),
+            NeTable::ImportedNames => (
+                relative(header.import_name_table_offset.value()),
+                self.imported_name_table.data.len() as u64,
+            ),
+            NeTable::EntryTable => (
+                relative(header.entry_table_offset.value()),
+                header.entry_table_length.value() as u64,
+            ),
+            NeTable::NonresidentNames => {
+                if header.non_resident_names_table_offset.value() == 0 {
+                    return None;
+                }
+                (
+                    header.non_resident_names_table_offset.value() as u64,
+                    names_length(
+                        self.nonresident_name_table
+                            .entries
+                            .iter()
+                            .map(|e| e.name.len()),
+                    ),
+                )
+            }
+            NeTable::Relocations(number) => {
+                let segment = self.segment(number)?;
+                let relocations = self.relocation_tables_per_segment.get(number.index()?)?;
+                if segment.data.is_none() || segment.header.flags & SEG_RELOCINFO == 0 {
+                    return None;
+                }
+                (
+                    segment.data_offset() + segment.data_length(),
+                    2 + 8 * relocations.entries.len() as u64,
+                )
+            }
+        };
+        Some(TableRange {
+            table,
+            offset,
+            length,
+        })
+    }
+
+    ///
+    /// Places of every table present in the module: headers and tables
+    /// in the order of the header, then relocations by segment number
+    ///
+    pub fn table_ranges(&self) -> Vec<TableRange> {
+        let tables = [
+            NeTable::DosHeader,
+            NeTable::NeHeader,
+            NeTable::SegmentTable,
+            NeTable::ResourceTable,
+            NeTable::ResidentNames,
+            NeTable::ModuleReferences,
+            NeTable::ImportedNames,
+            NeTable::EntryTable,
+            NeTable::NonresidentNames,
+        ];
+        let relocations =
+            (0..self.segment_entries.len()).map(|i| NeTable::Relocations(Seg16::from_index(i)));
+        tables
+            .into_iter()
+            .chain(relocations)
+            .filter_map(|table| self.table_range(table))
+            .collect()
+    }
+}
+
+impl NeOwned {
+    ///
+    /// Bytes of `table` as encoded on file, to hash, show or patch them
+    /// without writing the parsed form back.
+    /// `None` for absent tables and for tables past the end of file.
+    ///
+    pub fn raw_bytes(&self, table: NeTable) -> Option<&[u8]> {
+        let range = self.table_range(table)?;
+        self.slice(range.offset, range.length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_tables_end_with_zero() {
+        // "SAMPLE" @0, "WEP" @1
+        assert_eq!(names_length([6, 3].into_iter()), 9 + 6 + 1);
+        assert_eq!(names_length(std::iter::empty()), 1);
+        let range = TableRange {
+            table: NeTable::Relocations(Seg16(2)),
+            offset: 0x100,
+            length: 10,
+        };
+        assert_eq!(range.range(), 0x100..0x10A);
+        assert_eq!(
+            range.to_string(),
+            "0x000100-0x00010A relocations of segment #2"
+        );
+    }
+}
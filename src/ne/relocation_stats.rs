@@ -0,0 +1,202 @@
+use std::fmt;
+
+use crate::ne::segment_relocations::{RelocationTable, RelocationTarget};
+use crate::ne::NeExecutable;
+
+///
+/// Relocations of one imported module
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleHits {
+    /// Index in module reference table, starting at 1
+    pub module_index: u16,
+    pub module: String,
+    /// Relocation records
+    pub records: usize,
+    /// Places patched by loader: records and links of their chains
+    pub locations: usize,
+}
+
+///
+/// Aggregate relocation info of a segment or of the whole module
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelocationStats {
+    pub internal: usize,
+    pub import_by_ordinal: usize,
+    pub import_by_name: usize,
+    pub os_fixup: usize,
+    /// Records adding target to the value on file
+    pub additive: usize,
+    /// Records replacing a chain of places linked through words on file
+    pub chained: usize,
+    /// Places patched by loader (following chains)
+    pub locations: usize,
+    /// Imported modules, the most hit first
+    pub modules: Vec<ModuleHits>,
+}
+
+impl RelocationStats {
+    pub fn records(&self) -> usize {
+        self.internal + self.import_by_ordinal + self.import_by_name + self.os_fixup
+    }
+
+    ///
+    /// Adds relocation table of segment.
+    /// \param data -- segment data for the chains; without it every record counts as one place
+    ///
+    pub fn add(&mut self, ne: &NeExecutable, table: &RelocationTable, data: Option<&[u8]>) {
+        for entry in &table.entries {
+            let module_index = match &entry.target {
+                RelocationTarget::Internal(_) => {
+                    self.internal += 1;
+                    None
+                }
+                RelocationTarget::ImportByOrdinal(o) => {
+                    self.import_by_ordinal += 1;
+                    Some(o.module_index)
+                }
+                RelocationTarget::ImportByName(n) => {
+                    self.import_by_name += 1;
+                    Some(n.module_index)
+                }
+                RelocationTarget::OsFixup(_) => {
+                    self.os_fixup += 1;
+                    None
+                }
+            };
+            let locations = if entry.is_additive {
+                self.additive += 1;
+                1
+            } else {
+                self.chained += 1;
+                data.map_or(1, |data| chain_length(data, entry.segment_offset))
+            };
+            self.locations += locations;
+
+            let Some(module_index) = module_index else {
+                continue;
+            };
+            match self
+                .modules
+                .iter_mut()
+                .find(|m| m.module_index == module_index)
+            {
+                Some(hits) => {
+                    hits.records += 1;
+                    hits.locations += locations;
+                }
+                None => self.modules.push(ModuleHits {
+                    module_index,
+                    module: ne
+                        .referenced_module_name(module_index)
+                        .unwrap_or_else(|| format!("<module #{}>", module_index)),
+                    records: 1,
+                    locations,
+                }),
+            }
+        }
+        self.modules
+            .sort_by(|a, b| (b.locations, &a.module).cmp(&(a.locations, &b.module)));
+    }
+}
+
+///
+/// Number of places in chain of non-additive relocation starting at `offset`.
+/// Each place holds offset of the next one, 0xFFFF ends the chain.
+/// Chains leaving segment or longer than possible are cut.
+///
+pub fn chain_length(data: &[u8], offset: u16) -> usize {
+    let mut length = 0;
+    let mut offset = offset as usize;
+    while offset + 1 < data.len() && length <= data.len() / 2 {
+        length += 1;
+        let next = u16::from_le_bytes([data[offset], data[offset + 1]]);
+        if next == 0xFFFF {
+            break;
+        }
+        offset = next as usize;
+    }
+    length.max(1)
+}
+
+impl fmt::Display for RelocationStats {
+    ///
+    /// `{}` gives one line of counts, `{:#}` also lists imported modules
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} records ({} internal, {} by ordinal, {} by name, {} OS fixups), \
+            {} additive, {} chained, {} locations",
+            self.records(),
+            self.internal,
+            self.import_by_ordinal,
+            self.import_by_name,
+            self.os_fixup,
+            self.additive,
+            self.chained,
+            self.locations
+        )?;
+        if f.alternate() {
+            for hits in &self.modules {
+                write!(
+                    f,
+                    "\n\t{:<12} {:>6} records {:>6} locations",
+                    hits.module, hits.records, hits.locations
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Relocation statistics of every segment, in segment order
+    ///
+    pub fn segment_relocation_stats(&self) -> Vec<RelocationStats> {
+        self.segment_entries
+            .iter()
+            .zip(&self.relocation_tables_per_segment)
+            .map(|(segment, table)| {
+                let mut stats = RelocationStats::default();
+                stats.add(self, table, segment.data.as_deref());
+                stats
+            })
+            .collect()
+    }
+
+    ///
+    /// Relocation statistics of the whole module
+    ///
+    pub(crate) fn build_relocation_stats(&self) -> RelocationStats {
+        let mut stats = RelocationStats::default();
+        for (segment, table) in self
+            .segment_entries
+            .iter()
+            .zip(&self.relocation_tables_per_segment)
+        {
+            stats.add(self, table, segment.data.as_deref());
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_chains() {
+        // chain 0 -> 4 -> 2 -> end
+        let data = [0x04, 0x00, 0xFF, 0xFF, 0x02, 0x00];
+        assert_eq!(chain_length(&data, 0), 3);
+        assert_eq!(chain_length(&data, 2), 1);
+        // looped chain is cut
+        let data = [0x02, 0x00, 0x00, 0x00];
+        assert_eq!(chain_length(&data, 0), 3);
+        // offset out of segment still is one record
+        assert_eq!(chain_length(&data, 0x100), 1);
+    }
+}
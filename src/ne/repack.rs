@@ -0,0 +1,302 @@
+use std::fmt;
+use std::io::{self, ErrorKind};
+
+use crate::ne::resource_convert::Bytes;
+use crate::ne::resource_table::{RT_BITMAP, RT_CURSOR, RT_ICON, RT_STRING};
+use crate::ne::writer::{WriteOptions, WrittenImage};
+use crate::ne::NeExecutable;
+
+///
+/// What `repack` may change besides the resource alignment
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RepackOptions {
+    /// Also shrink sectors of segment data (`file_alignment_shift_count`)
+    pub segments: bool,
+}
+
+///
+/// Rebuilt file with the chosen alignments and the bytes saved
+/// against the file the module was read from
+///
+#[derive(Debug, Clone)]
+pub struct Repacked {
+    pub data: Vec<u8>,
+    /// (old, new) resource alignment shift
+    pub resource_shift: (u16, u16),
+    /// (old, new) segment sector shift
+    pub segment_shift: (u16, u16),
+    /// Length of the original file (see `file_length`). `None` for modules
+    /// not read from a file (`read_unpacked` trees), left out of the report
+    pub original_length: Option<u64>,
+    /// Zero bytes still inserted to align segments and resources
+    pub padding: u64,
+}
+
+impl Repacked {
+    ///
+    /// Bytes the rebuilt file is shorter than the original one.
+    /// Zero if it isn't: writer drops no data, but rebuilds tables
+    /// without the gaps and sharing tools may have left.
+    /// `None` if the original length isn't known.
+    ///
+    pub fn bytes_saved(&self) -> Option<u64> {
+        self.original_length
+            .map(|length| length.saturating_sub(self.data.len() as u64))
+    }
+}
+
+impl fmt::Display for Repacked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "resource shift {} -> {}, segment shift {} -> {}, ",
+            self.resource_shift.0,
+            self.resource_shift.1,
+            self.segment_shift.0,
+            self.segment_shift.1,
+        )?;
+        match (self.original_length, self.bytes_saved()) {
+            (Some(length), Some(saved)) => write!(
+                f,
+                "{} -> {} bytes ({} saved), ",
+                length,
+                self.data.len(),
+                saved
+            )?,
+            _ => write!(f, "{} bytes, ", self.data.len())?,
+        }
+        write!(f, "{} bytes of padding", self.padding)
+    }
+}
+
+///
+/// Some loaders read zero segment shift as 9 (512-byte sectors),
+/// so shifts are not lowered below 1.
+///
+const MIN_SHIFT: u16 = 1;
+
+impl NeExecutable {
+    ///
+    /// Rebuilds the module with the smallest alignment shifts
+    /// at which every offset and length still fits in its 16-bit field.
+    /// Data read from file includes the padding of resources up to the old
+    /// alignment. Zeros are real data as often (masks, empty strings), so only
+    /// resources whose format tells their length (see `format_length`)
+    /// lose the zeros past it, others are kept as read.
+    ///
+    pub fn repack(&self, options: &RepackOptions) -> io::Result<Repacked> {
+        let old_segment_shift = self.ne_header.file_alignment_shift_count.value();
+        let old_resource_shift = self.resource_table.header.alignment_shift_count;
+        let mut trimmed = self.clone();
+        for resource_type in &mut trimmed.resource_table.resource_types {
+            let type_id = resource_type.header.type_id;
+            for resource in &mut resource_type.resources {
+                let Some(data) = &mut resource.data else {
+                    continue;
+                };
+                match format_length(type_id, data) {
+                    Some(length) if data[length..].iter().all(|&b| b == 0) => data.truncate(length),
+                    _ => {}
+                }
+            }
+        }
+
+        let segment_shift = if options.segments {
+            trimmed
+                .smallest_shift(|shift| WriteOptions {
+                    segment_shift: Some(shift),
+                    resource_shift: None,
+                })?
+                .0
+        } else {
+            old_segment_shift
+        };
+        let (resource_shift, image) = trimmed.smallest_shift(|shift| WriteOptions {
+            segment_shift: Some(segment_shift),
+            resource_shift: Some(shift),
+        })?;
+
+        Ok(Repacked {
+            data: image.data,
+            resource_shift: (old_resource_shift, resource_shift),
+            segment_shift: (old_segment_shift, segment_shift),
+            original_length: Some(self.file_length()),
+            padding: image.padding,
+        })
+    }
+
+    ///
+    /// Tries shifts from `MIN_SHIFT` up to 15
+    ///
+    fn smallest_shift(
+        &self,
+        options: impl Fn(u16) -> WriteOptions,
+    ) -> io::Result<(u16, WrittenImage)> {
+        let mut last_error = None;
+        for shift in MIN_SHIFT..=15 {
+            match self.to_bytes_with(&options(shift)) {
+                Ok(image) => return Ok((shift, image)),
+                Err(e) if e.kind() == ErrorKind::InvalidData => last_error = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| io::Error::new(ErrorKind::InvalidData, "no alignment fits")))
+    }
+}
+
+///
+/// Length the resource format gives to `data` of resource type `type_id`:
+/// uncompressed DIB of icons, cursors and bitmaps, 16 strings of string
+/// tables. `None` for other types or data not fitting in `data`.
+///
+fn format_length(type_id: u16, data: &[u8]) -> Option<usize> {
+    let length = match type_id ^ 0x8000 {
+        RT_BITMAP => dib_length(data, false)?,
+        RT_ICON => dib_length(data, true)?,
+        RT_CURSOR => 4 + dib_length(data.get(4..)?, true)?,
+        RT_STRING => (0..16).try_fold(0, |at, _| Some(at + 1 + *data.get(at)? as usize))?,
+        _ => return None,
+    };
+    (length <= data.len()).then_some(length)
+}
+
+///
+/// Length of uncompressed DIB: header, palette and color bits, then the
+/// AND mask if `has_mask` (icons and cursors, whose height covers both)
+///
+fn dib_length(data: &[u8], has_mask: bool) -> Option<usize> {
+    let mut r = Bytes::new(data);
+    let (header_size, width, height, bit_count, colors_used, entry_size) = match r.u32().ok()? {
+        12 => {
+            let (width, height) = (r.u16().ok()? as usize, r.u16().ok()? as usize);
+            r.u16().ok()?;
+            (12, width, height, r.u16().ok()?, 0, 3)
+        }
+        40 => {
+            let width = (r.u32().ok()? as i32).unsigned_abs() as usize;
+            let height = (r.u32().ok()? as i32).unsigned_abs() as usize;
+            r.u16().ok()?;
+            let bit_count = r.u16().ok()?;
+            if r.u32().ok()? != 0 {
+                return None;
+            }
+            r.take(12).ok()?;
+            (40, width, height, bit_count, r.u32().ok()? as usize, 4)
+        }
+        _ => return None,
+    };
+    let palette = match (colors_used, bit_count) {
+        (0, 1 | 4 | 8) => 1 << bit_count,
+        (0, _) => 0,
+        (n, _) => n,
+    };
+    let stride = (width * bit_count as usize).div_ceil(32) * 4;
+    let bits = if has_mask {
+        (stride + width.div_ceil(32) * 4) * (height / 2)
+    } else {
+        stride * height
+    };
+    Some(header_size + palette * entry_size + bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::ne::diff::{compare, FieldChange};
+    use crate::ne::fixture::sample_module;
+
+    fn with_icon(data: Vec<u8>) -> (Vec<u8>, NeExecutable) {
+        let mut module = sample_module();
+        module.resource_table.resource_types[0].resources[0].data = Some(data);
+        let file = module.to_bytes().unwrap();
+        let ne = NeExecutable::read(&mut Cursor::new(&file)).unwrap();
+        (file, ne)
+    }
+
+    fn icon(ne: &NeExecutable) -> &[u8] {
+        ne.resource_table.resource_types[0].resources[0]
+            .data
+            .as_deref()
+            .unwrap()
+    }
+
+    /// 32x32 icon of 16 colors: its AND mask of zeros ends the data
+    fn icon_of_zero_mask() -> Vec<u8> {
+        let mut dib = vec![0; 744];
+        dib[0] = 40;
+        dib[4] = 32;
+        dib[8] = 64;
+        dib[12] = 1;
+        dib[14] = 4;
+        dib[40..104].fill(0x80);
+        dib[104..616].fill(0x12);
+        dib
+    }
+
+    #[test]
+    fn round_trip_trims_resource_padding() {
+        let (file, ne) = with_icon(icon_of_zero_mask());
+        // read with the zeros up to 16-byte alignment
+        assert_eq!(icon(&ne).len(), 752);
+
+        let repacked = ne.repack(&RepackOptions::default()).unwrap();
+        assert_eq!(repacked.resource_shift, (4, 1));
+        assert_eq!(repacked.segment_shift, (4, 4));
+        assert_eq!(repacked.original_length, Some(file.len() as u64));
+        let saved = (file.len() - repacked.data.len()) as u64;
+        assert_eq!(repacked.bytes_saved(), Some(saved));
+        assert!(saved >= 8);
+        assert!(repacked.to_string().contains(&format!(
+            "{} -> {} bytes ({} saved)",
+            file.len(),
+            repacked.data.len(),
+            saved
+        )));
+
+        let read = NeExecutable::read(&mut Cursor::new(&repacked.data)).unwrap();
+        assert_eq!(icon(&read), icon_of_zero_mask());
+        let diff = compare(&ne, &read);
+        assert!(diff.is_empty(), "{}", diff);
+    }
+
+    #[test]
+    fn keeps_zeros_of_unknown_length() {
+        let mut data = vec![0xAA; 0x1C];
+        data.extend_from_slice(&[0; 4]);
+        let (_, ne) = with_icon(data.clone());
+        let repacked = ne.repack(&RepackOptions::default()).unwrap();
+        let read = NeExecutable::read(&mut Cursor::new(&repacked.data)).unwrap();
+        assert_eq!(icon(&read), data);
+
+        assert_eq!(format_length(0x8000 | RT_STRING, &[0; 16]), Some(16));
+        assert_eq!(format_length(0x8000 | RT_STRING, b"\x02AB\0"), None);
+        assert_eq!(format_length(RT_ICON, &icon_of_zero_mask()), None);
+    }
+
+    #[test]
+    fn picks_smallest_fitting_shift() {
+        // 0x12000 units of 2 bytes don't fit the 16-bit length of the record
+        let (_, ne) = with_icon(vec![1; 0x24000]);
+        let repacked = ne.repack(&RepackOptions { segments: true }).unwrap();
+        assert_eq!(repacked.resource_shift, (4, 2));
+        assert_eq!(repacked.segment_shift, (4, MIN_SHIFT));
+
+        let read = NeExecutable::read(&mut Cursor::new(&repacked.data)).unwrap();
+        assert_eq!(read.resource_table.header.alignment_shift_count, 2);
+        assert_eq!(icon(&read).len(), 0x24000);
+        let diff = compare(&ne, &read);
+        assert_eq!(
+            diff.header,
+            [FieldChange {
+                field: "file_alignment_shift_count",
+                old: 4,
+                new: 1,
+            }]
+        );
+        assert!(diff.resources.is_empty() && diff.segments.is_empty());
+    }
+}
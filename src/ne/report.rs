@@ -0,0 +1,525 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ne::resource_table::type_name;
+use crate::ne::segment_table::segment_flag_names;
+use crate::ne::source::NamedModule;
+use crate::ne::symbols::OrdinalDatabase;
+use crate::ne::NeExecutable;
+
+impl fmt::Display for NeExecutable {
+    ///
+    /// `{}` gives one line summary of the module.
+    /// `{:#}` gives the whole report: header, segments, exports,
+    /// imports and resources with aligned columns.
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self
+            .module_name()
+            .unwrap_or_else(|| "<unnamed>".to_string());
+        let exports = self.exports();
+        let imports = self.imports();
+        if !f.alternate() {
+            return write!(
+                f,
+                "{}: {}, {} export(s), {} import(s), {}",
+                name,
+                self.ne_header,
+                exports.len(),
+                imports.len(),
+                self.resource_table
+            );
+        }
+
+        writeln!(f, "Module: {}", name)?;
+        if let Some(description) = self.description() {
+            writeln!(f, "Description: {}", description)?;
+        }
+
+        writeln!(f, "\nHeader:")?;
+        for line in format!("{:#}", self.ne_header).lines() {
+            writeln!(f, "\t{}", line)?;
+        }
+
+        match self.self_load_header() {
+            Ok(Some(header)) => {
+                writeln!(f, "\nSelf-loading procedures:")?;
+                for (name, ptr) in header.overridden_procedures() {
+                    writeln!(f, "\t{:<16}{}", name, ptr)?;
+                }
+            }
+            Ok(None) => (),
+            Err(e) => writeln!(f, "\nSelf-loading header: {}", e)?,
+        }
+
+        writeln!(f, "\nSegments:")?;
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let relocations = self
+                .relocation_tables_per_segment
+                .get(i)
+                .map_or(0, |t| t.entries.len());
+            writeln!(
+                f,
+                "\t#{:<3} {:#} relocations {}",
+                i + 1,
+                segment,
+                relocations
+            )?;
+        }
+
+        writeln!(f, "\nExports:")?;
+        for export in exports {
+            writeln!(f, "\t{:#}", export)?;
+        }
+
+        writeln!(f, "\nImports:")?;
+        for import in imports {
+            write!(
+                f,
+                "\t{:<40} {} reference(s)",
+                import.to_string(),
+                import.references.len()
+            )?;
+            let note = import
+                .target
+                .demangled()
+                .or_else(|| import.resolved_name(&OrdinalDatabase));
+            match note {
+                Some(note) => writeln!(f, " ; {}", note)?,
+                None => writeln!(f)?,
+            }
+        }
+
+        let os_fixups = self.os_fixups();
+        if !os_fixups.is_empty() {
+            writeln!(f, "\nOS fixups:")?;
+            for (segment, offset, fixup) in &os_fixups {
+                writeln!(f, "\t{}:{:04X} {}", segment, offset, fixup)?;
+            }
+        }
+
+        writeln!(f, "\nResources:")?;
+        for line in format!("{:#}", self.resource_table).lines() {
+            writeln!(f, "\t{}", line)?;
+        }
+
+        if let Some(overlay) = &self.overlay {
+            writeln!(
+                f,
+                "\nOverlay: offset 0x{:08X} length 0x{:X}",
+                overlay.offset,
+                overlay.data.len()
+            )?;
+        }
+
+        if !self.warnings.is_empty() {
+            writeln!(f, "\nWarnings:")?;
+            for warning in &self.warnings {
+                writeln!(f, "\t{}", warning)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// Document formats of `render_report`
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Ok(Format::Markdown),
+            "html" | "htm" => Ok(Format::Html),
+            _ => Err(format!("unknown report format `{}`", s)),
+        }
+    }
+}
+
+///
+/// Format independent parts of the report
+///
+enum Block {
+    Heading(String),
+    Text(String),
+    Table {
+        columns: &'static [&'static str],
+        rows: Vec<Vec<String>>,
+    },
+    #[cfg(feature = "image")]
+    Image {
+        caption: String,
+        png: Vec<u8>,
+    },
+}
+
+impl NeExecutable {
+    ///
+    /// Renders the complete analysis document: header summary,
+    /// tables of segments, exports, imports and resources, lints,
+    /// and icon previews embedded as `data:` URLs (with `image` feature).
+    /// Output depends only on the file: segments and resources come in
+    /// file order, exports in ordinal order, imports sorted by module
+    /// and procedure, so reports of the same file are identical byte for byte.
+    ///
+    pub fn render_report(&self, format: Format) -> String {
+        render(format, &self.report_title(), &self.report_blocks())
+    }
+
+    fn report_title(&self) -> String {
+        self.module_name()
+            .unwrap_or_else(|| "<unnamed>".to_string())
+    }
+
+    fn report_blocks(&self) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        if let Some(description) = self.description() {
+            blocks.push(Block::Text(description));
+        }
+
+        blocks.push(Block::Heading("Header".to_string()));
+        blocks.push(Block::Table {
+            columns: &["Field", "Value"],
+            rows: self
+                .ne_header
+                .summary_rows()
+                .into_iter()
+                .map(|(name, value)| vec![name.to_string(), value])
+                .collect(),
+        });
+
+        match self.self_load_header() {
+            Ok(Some(header)) => {
+                blocks.push(Block::Heading("Self-loading procedures".to_string()));
+                blocks.push(Block::Table {
+                    columns: &["Procedure", "Address"],
+                    rows: header
+                        .overridden_procedures()
+                        .into_iter()
+                        .map(|(name, ptr)| vec![name.to_string(), ptr.to_string()])
+                        .collect(),
+                });
+            }
+            Ok(None) => (),
+            Err(e) => blocks.push(Block::Text(format!("Self-loading header: {}", e))),
+        }
+
+        blocks.push(Block::Heading("Segments".to_string()));
+        blocks.push(Block::Table {
+            columns: &[
+                "#",
+                "Type",
+                "Offset",
+                "Length",
+                "Allocation",
+                "Flags",
+                "Relocations",
+            ],
+            rows: self
+                .segment_entries
+                .iter()
+                .enumerate()
+                .map(|(i, segment)| {
+                    let kind = if segment.header.flags & 0x0001 != 0 {
+                        "DATA"
+                    } else {
+                        "CODE"
+                    };
+                    let relocations = self
+                        .relocation_tables_per_segment
+                        .get(i)
+                        .map_or(0, |t| t.entries.len());
+                    vec![
+                        (i + 1).to_string(),
+                        kind.to_string(),
+                        format!("0x{:08X}", segment.data_offset()),
+                        format!("0x{:05X}", segment.data_length()),
+                        format!("0x{:05X}", segment.min_alloc()),
+                        segment_flag_names(segment.header.flags).join(" "),
+                        relocations.to_string(),
+                    ]
+                })
+                .collect(),
+        });
+
+        blocks.push(Block::Heading("Exports".to_string()));
+        blocks.push(Block::Table {
+            columns: &["Ordinal", "Name", "Address", "Flags"],
+            rows: self
+                .exports()
+                .iter()
+                .map(|e| {
+                    vec![
+                        format!("@{}", e.ordinal),
+                        e.name.as_deref().unwrap_or_default().to_string(),
+                        format!("{}:{:04X}", e.segment, e.offset),
+                        e.flag_names().join(" "),
+                    ]
+                })
+                .collect(),
+        });
+
+        blocks.push(Block::Heading("Imports".to_string()));
+        blocks.push(Block::Table {
+            columns: &["Module", "Procedure", "References"],
+            rows: self
+                .imports()
+                .iter()
+                .map(|i| {
+                    let references = i
+                        .references
+                        .iter()
+                        .map(|(segment, offset)| format!("{}:{:04X}", segment, offset))
+                        .collect::<Vec<_>>();
+                    vec![
+                        i.module.to_string(),
+                        i.target.to_string(),
+                        references.join(" "),
+                    ]
+                })
+                .collect(),
+        });
+
+        let shift_count = self.resource_table.header.alignment_shift_count;
+        blocks.push(Block::Heading("Resources".to_string()));
+        blocks.push(Block::Table {
+            columns: &["Type", "ID", "Offset", "Length"],
+            rows: self
+                .resource_table
+                .resource_types
+                .iter()
+                .flat_map(|t| t.resources.iter().map(move |r| (t, r)))
+                .map(|(t, r)| {
+                    vec![
+                        type_name(&t.id()),
+                        r.id().to_string(),
+                        format!("0x{:08X}", r.data_offset(shift_count)),
+                        format!("0x{:X}", r.data_length(shift_count)),
+                    ]
+                })
+                .collect(),
+        });
+
+        #[cfg(feature = "image")]
+        {
+            let images = self.extract_images();
+            if !images.is_empty() {
+                blocks.push(Block::Heading("Images".to_string()));
+            }
+            for image in images {
+                let mut png = Vec::new();
+                if image.image.write_png(&mut png).is_ok() {
+                    blocks.push(Block::Image {
+                        caption: image.file_name(),
+                        png,
+                    });
+                }
+            }
+        }
+
+        blocks.push(Block::Heading("Lints".to_string()));
+        let lints = self.lint();
+        if lints.is_empty() {
+            blocks.push(Block::Text("No issues found.".to_string()));
+        }
+        for lint in lints {
+            blocks.push(Block::Text(lint.to_string()));
+        }
+        blocks
+    }
+}
+
+fn render(format: Format, title: &str, blocks: &[Block]) -> String {
+    match format {
+        Format::Markdown => render_markdown(title, blocks),
+        Format::Html => render_html(title, blocks),
+    }
+}
+
+fn render_markdown(title: &str, blocks: &[Block]) -> String {
+    let escape = |s: &str| s.replace('|', "\\|");
+    let mut out = format!("# {}\n", title);
+    for block in blocks {
+        out.push('\n');
+        match block {
+            Block::Heading(text) => out += &format!("## {}\n", text),
+            Block::Text(text) => out += &format!("{}\n", text),
+            Block::Table { columns, rows } => {
+                out += &format!("| {} |\n", columns.join(" | "));
+                out += &format!("|{}\n", "---|".repeat(columns.len()));
+                for row in rows {
+                    let cells = row.iter().map(|c| escape(c)).collect::<Vec<_>>();
+                    out += &format!("| {} |\n", cells.join(" | "));
+                }
+            }
+            #[cfg(feature = "image")]
+            Block::Image { caption, png } => {
+                out += &format!(
+                    "![{}](data:image/png;base64,{})\n",
+                    caption,
+                    crate::util::base64::encode(png)
+                );
+            }
+        }
+    }
+    out
+}
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn render_html(title: &str, blocks: &[Block]) -> String {
+    let title = escape_html(title);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>table {{ border-collapse: collapse; }} td, th {{ border: 1px solid #999; \
+         padding: 2px 6px; font-family: monospace; }}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        title, title
+    );
+    for block in blocks {
+        match block {
+            Block::Heading(text) => out += &format!("<h2>{}</h2>\n", escape_html(text)),
+            Block::Text(text) => out += &format!("<p>{}</p>\n", escape_html(text)),
+            Block::Table { columns, rows } => {
+                out += "<table>\n<tr>";
+                for column in columns.iter() {
+                    out += &format!("<th>{}</th>", escape_html(column));
+                }
+                out += "</tr>\n";
+                for row in rows {
+                    out += "<tr>";
+                    for cell in row {
+                        out += &format!("<td>{}</td>", escape_html(cell));
+                    }
+                    out += "</tr>\n";
+                }
+                out += "</table>\n";
+            }
+            #[cfg(feature = "image")]
+            Block::Image { caption, png } => {
+                out += &format!(
+                    "<figure><img src=\"data:image/png;base64,{}\" alt=\"{}\">\
+                     <figcaption>{}</figcaption></figure>\n",
+                    crate::util::base64::encode(png),
+                    escape_html(caption),
+                    escape_html(caption)
+                );
+            }
+        }
+    }
+    out += "</body>\n</html>\n";
+    out
+}
+
+impl NamedModule {
+    ///
+    /// `render_report` of the module telling its source under the title
+    ///
+    pub fn render_report(&self, format: Format) -> String {
+        let mut blocks = self.module.report_blocks();
+        blocks.insert(0, Block::Text(format!("Source: {}", self.source_name)));
+        render(format, &self.module.report_title(), &blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::ne::fixture::sample_bytes;
+
+    fn blocks() -> Vec<Block> {
+        vec![
+            Block::Heading("Exports".to_string()),
+            Block::Table {
+                columns: &["Ordinal", "Name"],
+                rows: vec![vec!["@1".to_string(), "A|B<C>".to_string()]],
+            },
+        ]
+    }
+
+    #[test]
+    fn markdown_tables() {
+        let md = render_markdown("MOD", &blocks());
+        assert_eq!(
+            md,
+            "# MOD\n\n## Exports\n\n| Ordinal | Name |\n|---|---|\n| @1 | A\\|B<C> |\n"
+        );
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        let html = render_html("MOD", &blocks());
+        assert!(html.contains("<tr><td>@1</td><td>A|B&lt;C&gt;</td></tr>"));
+        assert!(html.contains("<h2>Exports</h2>"));
+    }
+
+    #[test]
+    fn summary_and_report_of_module() {
+        let ne = NeExecutable::read(&mut Cursor::new(sample_bytes())).unwrap();
+        assert_eq!(
+            ne.to_string(),
+            "SAMPLE: LINK.EXE 5.1, Windows/286, 2 segment(s), \
+             flags: MULTIPLEDATA | 0x0100 | 0x0200, \
+             5 export(s), 1 import(s), 1 type(s), 1 resource(s)"
+        );
+        let expected = "\
+Module: SAMPLE
+Description: Sample application
+
+Header:
+\tLinker version              5.1
+\tFlags                       0x0302 MULTIPLEDATA | 0x0100 | 0x0200
+\tAuto-data segment           2
+\tInitial heap size           0x0400
+\tInitial stack size          0x1000
+\tEntry point (CS:IP)         0001:0000
+\tInitial stack (SS:SP)       0002:0000
+\tSegments                    2
+\tReferenced modules          1
+\tMovable entry points        0
+\tFile alignment shift        4
+\tTarget OS                   Windows/286 (0x02)
+\tFormat dialect              Windows 3.0
+\tExpected Windows version    3.0
+
+Segments:
+\t#1   CODE offset 0x00000150 length 0x00040 alloc 0x00040 flags 0x0100 RELOCINFO relocations 1
+\t#2   DATA offset 0x000001A0 length 0x00010 alloc 0x00100 flags 0x0001 relocations 0
+
+Exports:
+\t@1     GETPROCADDRESS                   1:0000 EXPORTED
+\t@2     <unnamed>                        1:0008 EXPORTED
+\t@3     <unnamed>                        1:0010 EXPORTED
+\t@4     <unnamed>                        1:0018 EXPORTED
+\t@5     HIDDENPROC                       1:0030 EXPORTED NONRESIDENT
+
+Imports:
+\tKERNEL.@3                                1 reference(s) ; GetVersion
+
+Resources:
+\tICON
+\t\t1                offset 0x000001B0 length 0x000020 MOVEABLE PURE
+";
+        assert_eq!(format!("{:#}", ne), expected);
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::ne::exports::Export;
+use crate::ne::imports::ImportTarget;
+use crate::ne::NeExecutable;
+use crate::util::chunked::ChunkedReader;
+
+///
+/// Finds files of referenced modules like Windows 3.x loader does:
+/// in the given directories, then in the directory of the first module.
+/// Module name is tried with `.DLL`, `.EXE` and `.DRV` extensions
+/// (case-insensitive), `KERNEL` also as `KRNL386.EXE`/`KRNL286.EXE`.
+///
+#[derive(Debug, Clone, Default)]
+pub struct ImportResolver {
+    /// Directories in search order (current, Windows, System, PATH ...)
+    pub search_path: Vec<PathBuf>,
+}
+
+///
+/// Module of the dependency tree
+///
+#[derive(Debug, Clone)]
+pub struct ModuleNode {
+    /// Name used by importers (module reference table), uppercase
+    pub name: String,
+    /// File found for the module, `None` if not found
+    pub path: Option<PathBuf>,
+    /// Error of reading the found file
+    pub error: Option<String>,
+    /// Referenced modules, in module reference table order
+    pub depends_on: Vec<String>,
+}
+
+///
+/// Import which the module exporting it doesn't provide
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedImport {
+    pub importer: String,
+    pub module: String,
+    pub target: ImportTarget,
+}
+
+impl fmt::Display for UnresolvedImport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -> {}.{}", self.importer, self.module, self.target)
+    }
+}
+
+///
+/// Result of recursive resolution: every reached module (the first one is the root)
+/// and imports missing in found modules. Imports from modules not found are not listed.
+///
+#[derive(Debug, Clone)]
+pub struct Dependencies {
+    pub modules: Vec<ModuleNode>,
+    pub unresolved: Vec<UnresolvedImport>,
+}
+
+impl Dependencies {
+    pub fn missing_modules(&self) -> impl Iterator<Item = &ModuleNode> {
+        self.modules.iter().filter(|m| m.path.is_none())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.unresolved.is_empty()
+            && self
+                .modules
+                .iter()
+                .all(|m| m.path.is_some() && m.error.is_none())
+    }
+}
+
+impl fmt::Display for Dependencies {
+    ///
+    /// `{}` gives counts, `{:#}` lists modules and unresolved imports
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} modules, {} not found, {} unresolved imports",
+            self.modules.len(),
+            self.missing_modules().count(),
+            self.unresolved.len()
+        )?;
+        if !f.alternate() {
+            return Ok(());
+        }
+        for module in &self.modules {
+            write!(f, "\n\t{:<12} ", module.name)?;
+            match (&module.path, &module.error) {
+                (None, _) => write!(f, "not found")?,
+                (Some(path), None) => write!(f, "{}", path.display())?,
+                (Some(path), Some(error)) => write!(f, "{} ({})", path.display(), error)?,
+            }
+            if !module.depends_on.is_empty() {
+                write!(f, " -> {}", module.depends_on.join(", "))?;
+            }
+        }
+        if !self.unresolved.is_empty() {
+            write!(f, "\nUnresolved imports:")?;
+            for import in &self.unresolved {
+                write!(f, "\n\t{}", import)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ImportResolver {
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        Self { search_path }
+    }
+
+    ///
+    /// Looks for file of module `name` in `directories`, in their order.
+    /// Files differing only by case (on case-sensitive file systems)
+    /// are tried in byte order of names, not in order of the directory listing.
+    ///
+    pub fn find_module(name: &str, directories: &[PathBuf]) -> Option<PathBuf> {
+        let name = name.to_uppercase();
+        let mut candidates: Vec<String> = ["DLL", "EXE", "DRV"]
+            .iter()
+            .map(|ext| format!("{}.{}", name, ext))
+            .collect();
+        if name == "KERNEL" {
+            candidates.extend(["KRNL386.EXE".to_string(), "KRNL286.EXE".to_string()]);
+        }
+        for directory in directories {
+            let Ok(dir) = fs::read_dir(directory) else {
+                continue;
+            };
+            let mut files: Vec<_> = dir
+                .filter_map(Result::ok)
+                .map(|e| (e.file_name().to_string_lossy().to_uppercase(), e.path()))
+                .collect();
+            files.sort_by(|a, b| a.1.cmp(&b.1));
+            for candidate in &candidates {
+                if let Some((_, path)) = files.iter().find(|(file, _)| file == candidate) {
+                    return Some(path.clone());
+                }
+            }
+        }
+        None
+    }
+
+    ///
+    /// Walks the dependency tree of module `path`
+    ///
+    pub fn resolve(&self, path: &Path) -> io::Result<Dependencies> {
+        let root = read(path)?;
+        let mut directories = self.search_path.clone();
+        if let Some(parent) = path.parent() {
+            directories.push(parent.to_path_buf());
+        }
+
+        let root_name = root
+            .module_name()
+            .unwrap_or_else(|| {
+                path.file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .to_uppercase();
+        let mut seen = HashSet::from([root_name.clone()]);
+        let mut queue = vec![(root_name, path.to_path_buf(), root)];
+        let mut modules = Vec::new();
+        let mut unresolved = Vec::new();
+        let mut loaded = Vec::new();
+
+        while !queue.is_empty() {
+            let (name, path, ne) = queue.remove(0);
+            let depends_on: Vec<String> = (1..=ne.module_reference_table.entries.len() as u16)
+                .filter_map(|i| ne.referenced_module_name(i))
+                .map(|name| name.to_uppercase())
+                .collect();
+
+            for dependency in &depends_on {
+                if !seen.insert(dependency.clone()) {
+                    continue;
+                }
+                let mut node = ModuleNode {
+                    name: dependency.clone(),
+                    path: Self::find_module(dependency, &directories),
+                    error: None,
+                    depends_on: Vec::new(),
+                };
+                if let Some(path) = &node.path {
+                    match read(path) {
+                        Ok(ne) => queue.push((dependency.clone(), path.clone(), ne)),
+                        Err(e) => node.error = Some(e.to_string()),
+                    }
+                }
+                if node.error.is_some() || node.path.is_none() {
+                    modules.push(node);
+                }
+            }
+
+            modules.push(ModuleNode {
+                name: name.clone(),
+                path: Some(path),
+                error: None,
+                depends_on,
+            });
+            loaded.push((name, ne));
+        }
+
+        for (importer, ne) in &loaded {
+            for import in ne.imports() {
+                let module = import.module.to_uppercase();
+                let Some((_, exporter)) = loaded.iter().find(|(name, _)| *name == module) else {
+                    continue;
+                };
+                if !provides(exporter.exports(), &import.target) {
+                    unresolved.push(UnresolvedImport {
+                        importer: importer.clone(),
+                        module,
+                        target: import.target.clone(),
+                    });
+                }
+            }
+        }
+
+        // root first, then modules in order of reaching them
+        let order: Vec<String> = loaded.iter().map(|(name, _)| name.clone()).collect();
+        modules.sort_by_key(|m| {
+            order
+                .iter()
+                .position(|name| *name == m.name)
+                .unwrap_or(order.len())
+        });
+        Ok(Dependencies {
+            modules,
+            unresolved,
+        })
+    }
+}
+
+fn read(path: &Path) -> io::Result<NeExecutable> {
+    NeExecutable::read(&mut ChunkedReader::new(File::open(path)?))
+}
+
+///
+/// Whether exports contain the imported procedure.
+/// Names are compared case-insensitively: resident names are uppercase on file.
+///
+pub fn provides(exports: &[Export], target: &ImportTarget) -> bool {
+    match target {
+        ImportTarget::Ordinal(ordinal) => exports.iter().any(|e| e.ordinal == *ordinal),
+        ImportTarget::Name(name) => exports.iter().any(|e| {
+            e.name
+                .as_deref()
+                .is_some_and(|export| export.eq_ignore_ascii_case(name))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::dialect::FormatDialect;
+
+    #[test]
+    fn provides_by_ordinal_and_name() {
+        let exports = [Export {
+            ordinal: 5,
+            name: Some("INITAPP".into()),
+            is_resident_name: true,
+            segment: 1,
+            offset: 0x10,
+            is_movable: true,
+            flags: 0x01,
+            dialect: FormatDialect::Win30,
+        }];
+        assert!(provides(&exports, &ImportTarget::Ordinal(5)));
+        assert!(!provides(&exports, &ImportTarget::Ordinal(6)));
+        assert!(provides(
+            &exports,
+            &ImportTarget::Name("InitApp".to_string())
+        ));
+        assert!(!provides(
+            &exports,
+            &ImportTarget::Name("INITTASK".to_string())
+        ));
+    }
+}
@@ -0,0 +1,647 @@
+use std::io::{self, ErrorKind};
+
+use crate::ne::resource_table::{
+    RT_ACCELERATOR, RT_BITMAP, RT_CURSOR, RT_DIALOG, RT_FONT, RT_FONTDIR, RT_GROUP_CURSOR,
+    RT_GROUP_ICON, RT_ICON, RT_MENU, RT_NAMETABLE, RT_RCDATA, RT_STRING, RT_VERSION,
+};
+use crate::util::codepage::Codepage;
+
+/// `DS_SETFONT`: dialog template has point size and face name of font
+pub(crate) const DS_SETFONT: u32 = 0x0040;
+/// Popup item of menu template: no ID, sub-items follow
+pub(crate) const MF_POPUP: u16 = 0x0010;
+/// Last item of menu level
+pub(crate) const MF_END: u16 = 0x0080;
+/// Last entry of accelerator table
+const ACCEL_END: u8 = 0x80;
+
+///
+/// Sequential little-endian reader of resource data
+///
+pub(crate) struct Bytes<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Bytes<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    pub(crate) fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos + n).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("resource ends unexpectedly at {:#x}", self.data.len()),
+            )
+        })?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> io::Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> io::Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    /// Zero-terminated string, without the terminator
+    pub(crate) fn sz(&mut self) -> io::Result<&'a [u8]> {
+        let rest = &self.data[self.pos.min(self.data.len())..];
+        let length = rest.iter().position(|&b| b == 0).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                "string of resource is not terminated",
+            )
+        })?;
+        self.pos += length + 1;
+        Ok(&rest[..length])
+    }
+
+    /// Zero-terminated UTF-16 string, without the terminator
+    pub(crate) fn wsz(&mut self) -> io::Result<String> {
+        let mut units = Vec::new();
+        loop {
+            match self.u16()? {
+                0 => return Ok(String::from_utf16_lossy(&units)),
+                unit => units.push(unit),
+            }
+        }
+    }
+
+    pub(crate) fn align(&mut self, alignment: usize) {
+        self.pos = self.pos.next_multiple_of(alignment);
+    }
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn align(out: &mut Vec<u8>, alignment: usize) {
+    out.resize(out.len().next_multiple_of(alignment), 0);
+}
+
+///
+/// ANSI string as zero-terminated UTF-16 (`WCHAR[]`)
+///
+fn put_wsz(out: &mut Vec<u8>, ansi: &[u8], codepage: Codepage) {
+    for unit in codepage.decode(ansi).encode_utf16() {
+        put_u16(out, unit);
+    }
+    put_u16(out, 0);
+}
+
+///
+/// String as zero-terminated ANSI one
+///
+fn put_sz(out: &mut Vec<u8>, s: &str, codepage: Codepage) -> io::Result<()> {
+    out.extend(encode(s, codepage)?);
+    out.push(0);
+    Ok(())
+}
+
+fn encode(s: &str, codepage: Codepage) -> io::Result<Vec<u8>> {
+    codepage.encode(s).map_err(|c| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!("{:?} has no character {:?} in {}", s, c, codepage),
+        )
+    })
+}
+
+///
+/// Win16 `0xFF, WORD` ordinal or string as Win32 `0xFFFF, WORD` or `WCHAR[]`
+///
+fn put_sz_or_ordinal(out: &mut Vec<u8>, r: &mut Bytes, codepage: Codepage) -> io::Result<()> {
+    if r.peek() == Some(0xFF) {
+        r.u8()?;
+        put_u16(out, 0xFFFF);
+        put_u16(out, r.u16()?);
+    } else {
+        put_wsz(out, r.sz()?, codepage);
+    }
+    Ok(())
+}
+
+///
+/// Resource data converted between Win16 and Win32 forms
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Converted {
+    /// Same format in both: data is copied
+    Copied(Vec<u8>),
+    /// Structure re-encoded (strings in UTF-16, wider fields)
+    Reencoded(Vec<u8>),
+    /// Has no form in the other format
+    Skipped(String),
+}
+
+///
+/// Re-encodes Win16 resource data of predefined type into the Win32 form.
+/// String tables, menus, accelerators, dialogs and version info change
+/// their layout; DIB bitmaps, icons, cursors, their directories, fonts
+/// and raw or custom data are the same in both.
+/// \param codepage -- code page of ANSI strings in the resource
+///
+pub fn to_win32(type_id: u16, data: &[u8], codepage: Codepage) -> io::Result<Converted> {
+    let reencoded = match type_id {
+        RT_STRING => string_table(data, codepage)?,
+        RT_MENU => menu(data, codepage)?,
+        RT_ACCELERATOR => accelerators(data)?,
+        RT_DIALOG => dialog(data, codepage)?,
+        RT_VERSION => version_info(data, codepage)?,
+        RT_NAMETABLE => {
+            return Ok(Converted::Skipped(
+                "name table exists only in Windows 3.0 modules".to_string(),
+            ))
+        }
+        RT_BITMAP | RT_ICON | RT_CURSOR => {
+            // cursor starts with its hot spot
+            let header = if type_id == RT_CURSOR { 4 } else { 0 };
+            let size = data
+                .get(header..header + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+            if !matches!(size, Some(12 | 40)) {
+                return Ok(Converted::Skipped(
+                    "not a device-independent bitmap (Windows 2.x format)".to_string(),
+                ));
+            }
+            return Ok(Converted::Copied(data.to_vec()));
+        }
+        _ => return Ok(Converted::Copied(data.to_vec())),
+    };
+    Ok(Converted::Reencoded(reencoded))
+}
+
+///
+/// 16 strings of block: byte length and ANSI characters
+/// become word length and UTF-16 ones. Missing strings are empty.
+///
+fn string_table(data: &[u8], codepage: Codepage) -> io::Result<Vec<u8>> {
+    let mut r = Bytes::new(data);
+    let mut out = Vec::new();
+    for _ in 0..16 {
+        let string = if r.is_empty() {
+            &[][..]
+        } else {
+            let length = r.u8()? as usize;
+            r.take(length)?
+        };
+        let units: Vec<u16> = codepage.decode(string).encode_utf16().collect();
+        put_u16(&mut out, units.len() as u16);
+        for unit in units {
+            put_u16(&mut out, unit);
+        }
+    }
+    Ok(out)
+}
+
+///
+/// Header (version and offset words) is the same,
+/// item texts become UTF-16
+///
+fn menu(data: &[u8], codepage: Codepage) -> io::Result<Vec<u8>> {
+    let mut r = Bytes::new(data);
+    let mut out = Vec::new();
+    let version = r.u16()?;
+    let offset = r.u16()?;
+    if version != 0 {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("unknown menu template version {}", version),
+        ));
+    }
+    put_u16(&mut out, version);
+    put_u16(&mut out, offset);
+    r.take(offset as usize)?;
+    menu_items(&mut r, &mut out, codepage)?;
+    Ok(out)
+}
+
+fn menu_items(r: &mut Bytes, out: &mut Vec<u8>, codepage: Codepage) -> io::Result<()> {
+    loop {
+        let option = r.u16()?;
+        put_u16(out, option);
+        if option & MF_POPUP == 0 {
+            put_u16(out, r.u16()?);
+        }
+        put_wsz(out, r.sz()?, codepage);
+        if option & MF_POPUP != 0 {
+            menu_items(r, out, codepage)?;
+        }
+        if option & MF_END != 0 {
+            return Ok(());
+        }
+    }
+}
+
+///
+/// 5-byte `(flags, key, command)` entries become 8-byte ones with padding
+///
+fn accelerators(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut r = Bytes::new(data);
+    let mut out = Vec::new();
+    loop {
+        let flags = r.u8()?;
+        put_u16(&mut out, flags as u16);
+        put_u16(&mut out, r.u16()?);
+        put_u16(&mut out, r.u16()?);
+        put_u16(&mut out, 0);
+        if flags & ACCEL_END != 0 {
+            return Ok(out);
+        }
+    }
+}
+
+///
+/// Win16 `DLGTEMPLATE` and its items into the Win32 `DLGTEMPLATE`:
+/// extended styles (none) are added, item count becomes a word,
+/// strings become UTF-16 and predefined classes become atoms.
+///
+fn dialog(data: &[u8], codepage: Codepage) -> io::Result<Vec<u8>> {
+    let mut r = Bytes::new(data);
+    let mut out = Vec::new();
+    let style = r.u32()?;
+    let count = r.u8()?;
+    put_u32(&mut out, style);
+    put_u32(&mut out, 0);
+    put_u16(&mut out, count as u16);
+    for _ in 0..4 {
+        put_u16(&mut out, r.u16()?);
+    }
+    // menu, class, caption
+    put_sz_or_ordinal(&mut out, &mut r, codepage)?;
+    put_wsz(&mut out, r.sz()?, codepage);
+    put_wsz(&mut out, r.sz()?, codepage);
+    if style & DS_SETFONT != 0 {
+        put_u16(&mut out, r.u16()?);
+        put_wsz(&mut out, r.sz()?, codepage);
+    }
+
+    for _ in 0..count {
+        align(&mut out, 4);
+        let position: Vec<u16> = (0..4).map(|_| r.u16()).collect::<io::Result<_>>()?;
+        let id = r.u16()?;
+        put_u32(&mut out, r.u32()?);
+        put_u32(&mut out, 0);
+        for value in position {
+            put_u16(&mut out, value);
+        }
+        put_u16(&mut out, id);
+        match r.peek() {
+            Some(class @ 0x80..) => {
+                r.u8()?;
+                put_u16(&mut out, 0xFFFF);
+                put_u16(&mut out, class as u16);
+            }
+            _ => put_wsz(&mut out, r.sz()?, codepage),
+        }
+        put_sz_or_ordinal(&mut out, &mut r, codepage)?;
+        // Win32 size of creation data includes the size word itself
+        let extra = r.u8()? as usize;
+        if extra == 0 {
+            put_u16(&mut out, 0);
+        } else {
+            put_u16(&mut out, extra as u16 + 2);
+            out.extend_from_slice(r.take(extra)?);
+        }
+    }
+    Ok(out)
+}
+
+///
+/// Node of version info: `wLength`, `wValueLength`, key, value, children,
+/// each part aligned to 4 bytes from the beginning of the resource.
+/// Win32 nodes also have `wType` and UTF-16 keys; text values
+/// (strings of `StringFileInfo` tables) become UTF-16 and their length
+/// is given in characters.
+///
+fn version_info(data: &[u8], codepage: Codepage) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    version_node(data, 0, 0, false, &mut out, codepage)?;
+    Ok(out)
+}
+
+fn version_node(
+    data: &[u8],
+    start: usize,
+    depth: usize,
+    in_string_file_info: bool,
+    out: &mut Vec<u8>,
+    codepage: Codepage,
+) -> io::Result<usize> {
+    let mut r = Bytes::new(data);
+    r.pos = start;
+    let length = r.u16()? as usize;
+    let value_length = r.u16()? as usize;
+    let end = start + length;
+    if length < 4 || end > data.len() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "version info node at {:#x} has bad length {}",
+                start, length
+            ),
+        ));
+    }
+    let key = r.sz()?;
+    r.align(4);
+    let value = r.take(value_length.min(end.saturating_sub(r.pos)))?;
+    r.align(4);
+
+    // strings are children of tables which are children of StringFileInfo
+    let is_text = in_string_file_info && depth == 3;
+    let node_start = out.len();
+    put_u16(out, 0);
+    if is_text {
+        let text = value.split(|&b| b == 0).next().unwrap_or_default();
+        let characters = codepage.decode(text).encode_utf16().count();
+        put_u16(
+            out,
+            if value.is_empty() {
+                0
+            } else {
+                characters as u16 + 1
+            },
+        );
+        put_u16(out, 1);
+        put_wsz(out, key, codepage);
+        align(out, 4);
+        if !value.is_empty() {
+            put_wsz(out, text, codepage);
+        }
+    } else {
+        put_u16(out, value.len() as u16);
+        put_u16(out, 0);
+        put_wsz(out, key, codepage);
+        align(out, 4);
+        out.extend_from_slice(value);
+    }
+
+    let in_string_file_info = in_string_file_info || depth == 1 && key == b"StringFileInfo";
+    let mut child = r.pos;
+    while child + 4 <= end {
+        align(out, 4);
+        child = version_node(data, child, depth + 1, in_string_file_info, out, codepage)?
+            .next_multiple_of(4);
+    }
+
+    let node_length = out.len() - node_start;
+    out[node_start..node_start + 2].copy_from_slice(&(node_length as u16).to_le_bytes());
+    Ok(end)
+}
+
+///
+/// Converts Win32 resource data of predefined type down to the Win16 form.
+/// String tables and version info are re-encoded into ANSI strings;
+/// DIB bitmaps, icons and cursors of formats Windows 3.x shows,
+/// their directories, fonts and raw data are copied.
+/// Menus, dialogs, accelerators and Win32-only types are skipped.
+/// \param codepage -- code page of ANSI strings to produce
+///
+pub fn from_win32(type_id: u16, data: &[u8], codepage: Codepage) -> io::Result<Converted> {
+    let reencoded = match type_id {
+        RT_STRING => string_table_16(data, codepage)?,
+        RT_VERSION => version_info_16(data, codepage)?,
+        RT_BITMAP | RT_ICON | RT_CURSOR => {
+            let header = if type_id == RT_CURSOR { 4 } else { 0 };
+            return Ok(match dib_problem(data.get(header..).unwrap_or_default()) {
+                Some(problem) => Converted::Skipped(problem.to_string()),
+                None => Converted::Copied(data.to_vec()),
+            });
+        }
+        RT_GROUP_CURSOR | RT_GROUP_ICON | RT_FONTDIR | RT_FONT | RT_RCDATA => {
+            return Ok(Converted::Copied(data.to_vec()))
+        }
+        RT_MENU | RT_DIALOG | RT_ACCELERATOR => {
+            return Ok(Converted::Skipped(
+                "conversion into Win16 form is not supported".to_string(),
+            ))
+        }
+        _ if type_id > 0xFF => return Ok(Converted::Copied(data.to_vec())),
+        _ => {
+            return Ok(Converted::Skipped(
+                "type exists only in Win32 modules".to_string(),
+            ))
+        }
+    };
+    Ok(Converted::Reencoded(reencoded))
+}
+
+///
+/// Why Windows 3.x can't show the DIB: size of header, bit count
+/// and compression of `BITMAPINFOHEADER` are checked
+///
+fn dib_problem(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG") {
+        return Some("PNG image");
+    }
+    let mut r = Bytes::new(data);
+    let size = r.u32().ok()?;
+    match size {
+        12 => return None,
+        40 => {}
+        _ => return Some("unknown bitmap header"),
+    }
+    r.take(10).ok()?;
+    let bit_count = r.u16().ok()?;
+    let compression = r.u32().ok()?;
+    if !matches!(bit_count, 1 | 4 | 8 | 24) {
+        return Some("bit count is not 1, 4, 8 or 24");
+    }
+    if compression > 2 {
+        return Some("compression is not RGB or RLE");
+    }
+    None
+}
+
+///
+/// 16 strings of word length and UTF-16 characters
+/// become byte length and ANSI ones
+///
+fn string_table_16(data: &[u8], codepage: Codepage) -> io::Result<Vec<u8>> {
+    let mut r = Bytes::new(data);
+    let mut out = Vec::new();
+    for _ in 0..16 {
+        let length = if r.is_empty() { 0 } else { r.u16()? as usize };
+        let units: Vec<u16> = (0..length).map(|_| r.u16()).collect::<io::Result<_>>()?;
+        let string = encode(&String::from_utf16_lossy(&units), codepage)?;
+        let length = u8::try_from(string.len()).map_err(|_| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("string of {} bytes doesn't fit Win16 table", string.len()),
+            )
+        })?;
+        out.push(length);
+        out.extend_from_slice(&string);
+    }
+    Ok(out)
+}
+
+///
+/// Win32 version info nodes without `wType`: keys and text values
+/// become ANSI, length of text values is given in bytes
+///
+fn version_info_16(data: &[u8], codepage: Codepage) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    version_node_16(data, 0, &mut out, codepage)?;
+    Ok(out)
+}
+
+fn version_node_16(
+    data: &[u8],
+    start: usize,
+    out: &mut Vec<u8>,
+    codepage: Codepage,
+) -> io::Result<usize> {
+    let mut r = Bytes::new(data);
+    r.pos = start;
+    let length = r.u16()? as usize;
+    let value_length = r.u16()? as usize;
+    let is_text = r.u16()? == 1;
+    let end = start + length;
+    if length < 6 || end > data.len() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "version info node at {:#x} has bad length {}",
+                start, length
+            ),
+        ));
+    }
+    let key = r.wsz()?;
+    r.align(4);
+
+    let node_start = out.len();
+    out.extend_from_slice(&[0; 4]);
+    put_sz(out, &key, codepage)?;
+    align(out, 4);
+    let value_start = out.len();
+    if is_text {
+        if value_length > 0 {
+            let value = r.take((value_length * 2).min(end.saturating_sub(r.pos)))?;
+            let units: Vec<u16> = value
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .take_while(|&unit| unit != 0)
+                .collect();
+            put_sz(out, &String::from_utf16_lossy(&units), codepage)?;
+        }
+    } else {
+        out.extend_from_slice(r.take(value_length.min(end.saturating_sub(r.pos)))?);
+    }
+    let value_length = out.len() - value_start;
+    r.align(4);
+
+    let mut child = r.pos;
+    while child + 6 <= end {
+        align(out, 4);
+        child = version_node_16(data, child, out, codepage)?.next_multiple_of(4);
+    }
+
+    let node_length = out.len() - node_start;
+    out[node_start..node_start + 2].copy_from_slice(&(node_length as u16).to_le_bytes());
+    out[node_start + 2..node_start + 4].copy_from_slice(&(value_length as u16).to_le_bytes());
+    Ok(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_become_utf16() {
+        let mut block = vec![2, b'H', b'i', 1, 0xE9];
+        block.resize(block.len() + 14, 0);
+        let Converted::Reencoded(out) = to_win32(RT_STRING, &block, Codepage::Windows1252).unwrap()
+        else {
+            panic!("string table is re-encoded");
+        };
+        assert_eq!(&out[..10], &[2, 0, b'H', 0, b'i', 0, 1, 0, 0xE9, 0]);
+        assert_eq!(out.len(), 10 + 14 * 2);
+
+        let Converted::Reencoded(back) =
+            from_win32(RT_STRING, &out, Codepage::Windows1252).unwrap()
+        else {
+            panic!("string table is re-encoded");
+        };
+        assert_eq!(back, block);
+    }
+
+    #[test]
+    fn accelerators_are_widened() {
+        let data = [0x01, 0x41, 0x00, 0x64, 0x00, 0x81, 0x42, 0x00, 0x65, 0x00];
+        let Converted::Reencoded(out) =
+            to_win32(RT_ACCELERATOR, &data, Codepage::default()).unwrap()
+        else {
+            panic!("accelerators are re-encoded");
+        };
+        assert_eq!(
+            out,
+            [1, 0, 0x41, 0, 0x64, 0, 0, 0, 0x81, 0, 0x42, 0, 0x65, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn version_strings_are_text() {
+        // VS_VERSION_INFO / StringFileInfo / 040904E4 / "A" = "b"
+        let mut data = Vec::new();
+        let string = [10, 0, 2, 0, b'A', 0, 0, 0, b'b', 0];
+        let mut table = vec![0, 0, 0, 0];
+        table.extend_from_slice(b"040904E4\0");
+        table.resize(16, 0);
+        table.extend_from_slice(&string);
+        let table_length = table.len() as u16;
+        table[..2].copy_from_slice(&table_length.to_le_bytes());
+        let mut file_info = vec![0, 0, 0, 0];
+        file_info.extend_from_slice(b"StringFileInfo\0");
+        file_info.resize(20, 0);
+        file_info.extend_from_slice(&table);
+        let file_info_length = file_info.len() as u16;
+        file_info[..2].copy_from_slice(&file_info_length.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(b"VS_VERSION_INFO\0");
+        data.extend_from_slice(&file_info);
+        let length = data.len() as u16;
+        data[..2].copy_from_slice(&length.to_le_bytes());
+
+        let Converted::Reencoded(out) = to_win32(RT_VERSION, &data, Codepage::default()).unwrap()
+        else {
+            panic!("version info is re-encoded");
+        };
+        assert_eq!(u16::from_le_bytes([out[0], out[1]]) as usize, out.len());
+        let key: Vec<u8> = "A\0".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let at = out.windows(key.len()).rposition(|w| w == key).unwrap();
+        // wValueLength = 2 characters, wType = text
+        assert_eq!(&out[at - 4..at], &[2, 0, 1, 0]);
+        // value is aligned to 4 bytes
+        assert_eq!(&out[at + 6..at + 10], &[b'b', 0, 0, 0]);
+
+        let Converted::Reencoded(back) = from_win32(RT_VERSION, &out, Codepage::default()).unwrap()
+        else {
+            panic!("version info is re-encoded");
+        };
+        assert_eq!(back, data);
+    }
+}
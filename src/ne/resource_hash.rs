@@ -0,0 +1,180 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ne::resource_table::{NeResource, NeResourceTable, NeResourceType, ResourceId};
+use crate::util::checksum::{crc32, fnv1a64, sha256};
+
+///
+/// Hash functions of resource content
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HashAlgorithm {
+    Crc32,
+    Fnv1a64,
+    #[default]
+    Sha256,
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "crc32" | "crc" => Ok(HashAlgorithm::Crc32),
+            "fnv" | "fnv1a" | "fnv1a64" => Ok(HashAlgorithm::Fnv1a64),
+            "sha256" | "sha-256" => Ok(HashAlgorithm::Sha256),
+            _ => Err(format!("unknown hash algorithm `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            HashAlgorithm::Crc32 => "crc32",
+            HashAlgorithm::Fnv1a64 => "fnv1a64",
+            HashAlgorithm::Sha256 => "sha256",
+        })
+    }
+}
+
+impl HashAlgorithm {
+    pub fn hash(self, data: &[u8]) -> ResourceHash {
+        let digest = match self {
+            HashAlgorithm::Crc32 => crc32(data).to_be_bytes().to_vec(),
+            HashAlgorithm::Fnv1a64 => fnv1a64(data).to_be_bytes().to_vec(),
+            HashAlgorithm::Sha256 => sha256(data).to_vec(),
+        };
+        ResourceHash {
+            algorithm: self,
+            digest,
+        }
+    }
+}
+
+///
+/// Digest of resource content; `{}` gives `algorithm:hex`
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResourceHash {
+    pub algorithm: HashAlgorithm,
+    pub digest: Vec<u8>,
+}
+
+impl fmt::Display for ResourceHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:", self.algorithm)?;
+        for byte in &self.digest {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ResourceHash {
+    type Err = String;
+
+    ///
+    /// Parses `algorithm:hex` given by `Display`
+    ///
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (algorithm, hex) = s
+            .split_once(':')
+            .ok_or_else(|| format!("hash `{}` is not `algorithm:hex`", s))?;
+        if hex.len() % 2 != 0 || !hex.is_ascii() {
+            return Err(format!("digest `{}` is not hexadecimal", hex));
+        }
+        let digest = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("digest `{}` is not hexadecimal", hex))?;
+        Ok(Self {
+            algorithm: algorithm.parse()?,
+            digest,
+        })
+    }
+}
+
+///
+/// Resource data without the zero padding of its last alignment unit.
+/// Lengths on file are rounded up to alignment of the module,
+/// so the same icon in two files differs only by these zeros.
+///
+pub fn resource_content(data: &[u8], shift_count: u16) -> &[u8] {
+    let unit = 1usize.checked_shl(shift_count as u32).unwrap_or(1);
+    let min_length = data.len().saturating_sub(unit - 1);
+    let mut length = data.len();
+    while length > min_length && data[length - 1] == 0 {
+        length -= 1;
+    }
+    &data[..length]
+}
+
+impl NeResource {
+    ///
+    /// Hash of `resource_content`, if the data was read
+    ///
+    pub fn content_hash(&self, shift_count: u16, algorithm: HashAlgorithm) -> Option<ResourceHash> {
+        let data = self.data.as_deref()?;
+        Some(algorithm.hash(resource_content(data, shift_count)))
+    }
+}
+
+impl NeResourceTable {
+    ///
+    /// Type, ID and content hash of every resource whose data was read,
+    /// in file order of the resource table
+    ///
+    pub fn resource_hashes(
+        &self,
+        algorithm: HashAlgorithm,
+    ) -> Vec<(ResourceId, ResourceId, ResourceHash)> {
+        let shift_count = self.header.alignment_shift_count;
+        self.resource_types
+            .iter()
+            .flat_map(|t| {
+                t.resources.iter().filter_map(move |resource| {
+                    let hash = resource.content_hash(shift_count, algorithm)?;
+                    Some((t.id(), resource.id(), hash))
+                })
+            })
+            .collect()
+    }
+
+    ///
+    /// First resource whose content has `hash` (by the algorithm of `hash`)
+    ///
+    pub fn find_resource_by_hash(
+        &self,
+        hash: &ResourceHash,
+    ) -> Option<(&NeResourceType, &NeResource)> {
+        let shift_count = self.header.alignment_shift_count;
+        self.resource_types.iter().find_map(|t| {
+            t.resources
+                .iter()
+                .find(|resource| {
+                    resource.content_hash(shift_count, hash.algorithm).as_ref() == Some(hash)
+                })
+                .map(|resource| (t, resource))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_ignores_alignment_padding() {
+        assert_eq!(
+            resource_content(&[1, 0, 2, 0, 0, 0, 0, 0], 2),
+            [1, 0, 2, 0, 0]
+        );
+        assert_eq!(resource_content(&[1, 0, 0, 0], 0), [1, 0, 0, 0]);
+
+        let hash = HashAlgorithm::Crc32.hash(b"123456789");
+        assert_eq!(hash.to_string(), "crc32:cbf43926");
+        assert_eq!("crc32:cbf43926".parse::<ResourceHash>(), Ok(hash));
+    }
+}
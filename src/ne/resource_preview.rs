@@ -0,0 +1,232 @@
+use std::io;
+
+use crate::ne::resource_convert::{Bytes, DS_SETFONT, MF_END, MF_POPUP};
+use crate::ne::resource_table::{
+    ResourceId, RT_BITMAP, RT_CURSOR, RT_DIALOG, RT_ICON, RT_MENU, RT_STRING,
+};
+use crate::util::codepage::Codepage;
+
+/// Menu item without text and ID draws a separator line
+const MF_SEPARATOR_ID: u16 = 0;
+
+///
+/// Readable lines showing resource content without extracting it:
+/// strings with their IDs, menu item tree, dialog caption and controls,
+/// dimensions and bit depth of bitmaps, icons and cursors.
+/// `None` for types without preview.
+/// \param resource_id -- ID of resource (string block number for `RT_STRING`)
+/// \param codepage -- code page of ANSI strings in the resource
+///
+pub fn preview(
+    type_id: u16,
+    resource_id: &ResourceId,
+    data: &[u8],
+    codepage: Codepage,
+) -> io::Result<Option<Vec<String>>> {
+    let lines = match type_id {
+        RT_STRING => strings(resource_id, data, codepage)?,
+        RT_MENU => menu(data, codepage)?,
+        RT_DIALOG => dialog(data, codepage)?,
+        RT_BITMAP => vec![dib(data, false)],
+        RT_ICON => vec![dib(data, true)],
+        RT_CURSOR => vec![dib(data.get(4..).unwrap_or_default(), true)],
+        _ => return Ok(None),
+    };
+    Ok(Some(lines))
+}
+
+///
+/// Block N holds strings with IDs from (N - 1) * 16; empty strings are missing
+///
+fn strings(resource_id: &ResourceId, data: &[u8], codepage: Codepage) -> io::Result<Vec<String>> {
+    let base = match resource_id {
+        ResourceId::Int(block) => (*block as u32).saturating_sub(1) * 16,
+        ResourceId::Name(_) => 0,
+    };
+    let mut r = Bytes::new(data);
+    let mut lines = Vec::new();
+    for i in 0..16 {
+        if r.is_empty() {
+            break;
+        }
+        let length = r.u8()? as usize;
+        let text = r.take(length)?;
+        if !text.is_empty() {
+            lines.push(format!("{}: {:?}", base + i, codepage.decode(text)));
+        }
+    }
+    Ok(lines)
+}
+
+fn menu(data: &[u8], codepage: Codepage) -> io::Result<Vec<String>> {
+    let mut r = Bytes::new(data);
+    let _version = r.u16()?;
+    let offset = r.u16()?;
+    r.take(offset as usize)?;
+    let mut lines = Vec::new();
+    menu_items(&mut r, 0, &mut lines, codepage)?;
+    Ok(lines)
+}
+
+fn menu_items(
+    r: &mut Bytes,
+    depth: usize,
+    lines: &mut Vec<String>,
+    codepage: Codepage,
+) -> io::Result<()> {
+    let indent = "  ".repeat(depth);
+    loop {
+        let option = r.u16()?;
+        if option & MF_POPUP != 0 {
+            let text = codepage.decode(r.sz()?);
+            lines.push(format!("{}{:?}", indent, text));
+            menu_items(r, depth + 1, lines, codepage)?;
+        } else {
+            let id = r.u16()?;
+            let text = r.sz()?;
+            if id == MF_SEPARATOR_ID && text.is_empty() {
+                lines.push(format!("{}---", indent));
+            } else {
+                lines.push(format!("{}{:?} = {}", indent, codepage.decode(text), id));
+            }
+        }
+        if option & MF_END != 0 {
+            return Ok(());
+        }
+    }
+}
+
+///
+/// Name of predefined control class given by atom
+///
+fn class_name(atom: u8) -> String {
+    match atom {
+        0x80 => "BUTTON".to_string(),
+        0x81 => "EDIT".to_string(),
+        0x82 => "STATIC".to_string(),
+        0x83 => "LISTBOX".to_string(),
+        0x84 => "SCROLLBAR".to_string(),
+        0x85 => "COMBOBOX".to_string(),
+        _ => format!("0x{:02X}", atom),
+    }
+}
+
+///
+/// `0xFF, WORD` ordinal or string
+///
+fn sz_or_ordinal(r: &mut Bytes, codepage: Codepage) -> io::Result<String> {
+    if r.peek() == Some(0xFF) {
+        r.u8()?;
+        Ok(format!("#{}", r.u16()?))
+    } else {
+        Ok(format!("{:?}", codepage.decode(r.sz()?)))
+    }
+}
+
+fn dialog(data: &[u8], codepage: Codepage) -> io::Result<Vec<String>> {
+    let mut r = Bytes::new(data);
+    let style = r.u32()?;
+    let count = r.u8()?;
+    let [x, y, cx, cy] = [r.u16()?, r.u16()?, r.u16()?, r.u16()?];
+    let menu = if r.peek() == Some(0) {
+        r.u8()?;
+        None
+    } else {
+        Some(sz_or_ordinal(&mut r, codepage)?)
+    };
+    let class = codepage.decode(r.sz()?);
+    let caption = codepage.decode(r.sz()?);
+
+    let mut header = format!("{:?} at ({}, {}) size {}x{}", caption, x, y, cx, cy);
+    if style & DS_SETFONT != 0 {
+        let points = r.u16()?;
+        header += &format!(", font {:?} {}pt", codepage.decode(r.sz()?), points);
+    }
+    if let Some(menu) = menu {
+        header += &format!(", menu {}", menu);
+    }
+    if !class.is_empty() {
+        header += &format!(", class {:?}", class);
+    }
+
+    let mut lines = vec![header];
+    for _ in 0..count {
+        let [x, y, cx, cy] = [r.u16()?, r.u16()?, r.u16()?, r.u16()?];
+        let id = r.u16()?;
+        let _style = r.u32()?;
+        let class = match r.peek() {
+            Some(atom @ 0x80..) => {
+                r.u8()?;
+                class_name(atom)
+            }
+            _ => codepage.decode(r.sz()?),
+        };
+        let text = sz_or_ordinal(&mut r, codepage)?;
+        let extra = r.u8()? as usize;
+        r.take(extra)?;
+        lines.push(format!(
+            "  {} {} {} at ({}, {}) size {}x{}",
+            class, id, text, x, y, cx, cy
+        ));
+    }
+    Ok(lines)
+}
+
+///
+/// Dimensions of DIB; icons and cursors keep XOR and AND masks
+/// one above another, so their header has double height
+///
+fn dib(data: &[u8], has_mask: bool) -> String {
+    let mut r = Bytes::new(data);
+    let dimensions = (|| -> io::Result<_> {
+        match r.u32()? {
+            12 => Ok((r.u16()? as i64, r.u16()? as i64, r.u16()?, r.u16()?)),
+            40 => Ok((
+                r.u32()? as i32 as i64,
+                r.u32()? as i32 as i64,
+                r.u16()?,
+                r.u16()?,
+            )),
+            size => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("header of {} bytes", size),
+            )),
+        }
+    })();
+    match dimensions {
+        Ok((width, height, _planes, bit_count)) => {
+            let height = if has_mask {
+                height.abs() / 2
+            } else {
+                height.abs()
+            };
+            format!("{}x{}, {} bpp", width, height, bit_count)
+        }
+        Err(_) => "not a device-independent bitmap (Windows 2.x format)".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previews_strings_and_menus() {
+        let block = b"\x02Hi\x00\x03Bye";
+        assert_eq!(
+            preview(RT_STRING, &ResourceId::Int(2), block, Codepage::default()).unwrap(),
+            Some(vec!["16: \"Hi\"".to_string(), "18: \"Bye\"".to_string()])
+        );
+
+        let menu =
+            b"\x00\x00\x00\x00\x90\x00&File\x00\x00\x00\x64\x00&Open\x00\x80\x00\x00\x00\x00";
+        assert_eq!(
+            preview(RT_MENU, &ResourceId::Int(1), menu, Codepage::default()).unwrap(),
+            Some(vec![
+                "\"&File\"".to_string(),
+                "  \"&Open\" = 100".to_string(),
+                "  ---".to_string(),
+            ])
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+use crate::ne::resource_hash::{resource_content, ResourceHash};
+use crate::ne::resource_table::{type_name, ResourceId};
+use crate::ne::NeExecutable;
+
+///
+/// Which resources `find_resources` reports. Unset conditions match
+/// every resource; resources whose data wasn't read match only
+/// queries without conditions on content.
+///
+#[derive(Debug, Clone, Default)]
+pub struct ResourceQuery {
+    /// Type of resource, compared by `ResourceId::matches`
+    pub type_id: Option<ResourceId>,
+    /// Pattern of resource ID as `{}` prints it, case-insensitive:
+    /// `*` stands for any run of characters, `?` for one character
+    pub id_pattern: Option<String>,
+    /// Shorter resources don't match
+    pub min_size: Option<u64>,
+    /// Hash of `resource_content`
+    pub hash: Option<ResourceHash>,
+    /// Text which data decoded with the code page of the module
+    /// must contain, case-insensitive
+    pub contains: Option<String>,
+}
+
+///
+/// Resource of a module found by `find_resources`
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceMatch {
+    pub path: PathBuf,
+    pub type_id: ResourceId,
+    pub id: ResourceId,
+    /// Bytes of resource data on file
+    pub length: u64,
+}
+
+impl fmt::Display for ResourceMatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} {} 0x{:X} bytes",
+            self.path.display(),
+            type_name(&self.type_id),
+            self.id,
+            self.length
+        )
+    }
+}
+
+///
+/// Result of `find_resources`
+///
+#[derive(Debug, Clone, Default)]
+pub struct ResourceSearch {
+    pub matches: Vec<ResourceMatch>,
+    /// Modules whose resources were searched
+    pub modules: usize,
+    /// Files which aren't NE modules or whose resources can't be read
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+impl ResourceSearch {
+    ///
+    /// Count of modules containing at least one match
+    ///
+    pub fn matching_modules(&self) -> usize {
+        let mut paths: Vec<&Path> = self.matches.iter().map(|m| m.path.as_path()).collect();
+        paths.dedup();
+        paths.len()
+    }
+}
+
+impl fmt::Display for ResourceSearch {
+    ///
+    /// `{}` gives counts, `{:#}` lists matches
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            for found in &self.matches {
+                writeln!(f, "{}", found)?;
+            }
+        }
+        write!(
+            f,
+            "{} resources in {} of {} modules, {} files skipped",
+            self.matches.len(),
+            self.matching_modules(),
+            self.modules,
+            self.skipped.len()
+        )
+    }
+}
+
+///
+/// Whether `text` matches `pattern` of `*` and `?` wildcards
+///
+fn wildcard(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| wildcard(rest, &text[skip..])),
+        Some((&c, rest)) => text.split_first().is_some_and(|(&t, text)| {
+            (c == '?' || c.eq_ignore_ascii_case(&t)) && wildcard(rest, text)
+        }),
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Type, ID and data length of resources of the module matching `query`,
+    /// in file order of the resource table
+    ///
+    pub fn query_resources(&self, query: &ResourceQuery) -> Vec<(ResourceId, ResourceId, u64)> {
+        let shift_count = self.resource_table.header.alignment_shift_count;
+        let pattern: Option<Vec<char>> = query.id_pattern.as_ref().map(|p| p.chars().collect());
+        let needle = query.contains.as_ref().map(|s| s.to_lowercase());
+        let mut found = Vec::new();
+
+        for resource_type in &self.resource_table.resource_types {
+            let type_id = resource_type.id();
+            if query.type_id.as_ref().is_some_and(|t| !t.matches(&type_id)) {
+                continue;
+            }
+            for resource in &resource_type.resources {
+                let id = resource.id();
+                let length = resource.data_length(shift_count);
+                if pattern.as_ref().is_some_and(|pattern| {
+                    !wildcard(pattern, &id.to_string().chars().collect::<Vec<_>>())
+                }) || query.min_size.is_some_and(|min| length < min)
+                {
+                    continue;
+                }
+                if query.hash.is_some() || needle.is_some() {
+                    let Some(data) = &resource.data else {
+                        continue;
+                    };
+                    if query.hash.as_ref().is_some_and(|hash| {
+                        hash.algorithm.hash(resource_content(data, shift_count)) != *hash
+                    }) || needle.as_ref().is_some_and(|needle| {
+                        !self.codepage.decode(data).to_lowercase().contains(needle)
+                    }) {
+                        continue;
+                    }
+                }
+                found.push((type_id.clone(), id, length));
+            }
+        }
+        found
+    }
+}
+
+///
+/// Searches resources of every NE module in `roots` for ones matching
+/// `query`. Directories are walked recursively in byte order of names;
+/// files which aren't NE modules are skipped.
+///
+pub fn find_resources(roots: &[PathBuf], query: &ResourceQuery) -> io::Result<ResourceSearch> {
+    let mut search = ResourceSearch::default();
+    let mut files = Vec::new();
+    for root in roots {
+        collect_files(root, &mut files)?;
+    }
+    for path in files {
+        let module = fs::read(&path)
+            .and_then(|data| NeExecutable::read_resources_only(&mut Cursor::new(data)));
+        let ne = match module {
+            Ok(ne) => ne,
+            Err(e) => {
+                search.skipped.push((path, e.to_string()));
+                continue;
+            }
+        };
+        search.modules += 1;
+        for (type_id, id, length) in ne.query_resources(query) {
+            search.matches.push(ResourceMatch {
+                path: path.clone(),
+                type_id,
+                id,
+                length,
+            });
+        }
+    }
+    Ok(search)
+}
+
+pub(crate) fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<_>>()?;
+    entries.sort();
+    for entry in entries {
+        collect_files(&entry, files)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards() {
+        let matches = |pattern: &str, text: &str| {
+            wildcard(
+                &pattern.chars().collect::<Vec<_>>(),
+                &text.chars().collect::<Vec<_>>(),
+            )
+        };
+        assert!(matches("*", ""));
+        assert!(matches("sb*", "SB16"));
+        assert!(matches("?b*6", "sB16"));
+        assert!(matches("*16", "SB16"));
+        assert!(!matches("sb?", "SB16"));
+        assert!(!matches("", "1"));
+    }
+}
@@ -1,5 +1,126 @@
 use std::convert::TryInto;
-use std::io::{self, Read};
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::ne::options::Budget;
+use crate::ne::segment_table::aligned;
+use crate::util::trace::trace_warn;
+
+/// Cursor resource type (`RT_CURSOR`)
+pub const RT_CURSOR: u16 = 1;
+/// Bitmap resource type (`RT_BITMAP`)
+pub const RT_BITMAP: u16 = 2;
+/// Icon resource type (`RT_ICON`)
+pub const RT_ICON: u16 = 3;
+/// Menu template resource type (`RT_MENU`)
+pub const RT_MENU: u16 = 4;
+/// Dialog template resource type (`RT_DIALOG`)
+pub const RT_DIALOG: u16 = 5;
+/// String table resource type (`RT_STRING`)
+pub const RT_STRING: u16 = 6;
+/// Font directory resource type (`RT_FONTDIR`)
+pub const RT_FONTDIR: u16 = 7;
+/// Font resource type (`RT_FONT`)
+pub const RT_FONT: u16 = 8;
+/// Accelerator table resource type (`RT_ACCELERATOR`)
+pub const RT_ACCELERATOR: u16 = 9;
+/// Raw data resource type (`RT_RCDATA`)
+pub const RT_RCDATA: u16 = 10;
+/// Cursor directory resource type (`RT_GROUP_CURSOR`)
+pub const RT_GROUP_CURSOR: u16 = 12;
+/// Icon directory resource type (`RT_GROUP_ICON`)
+pub const RT_GROUP_ICON: u16 = 14;
+/// Name table of Windows 3.0 modules (`RT_NAMETABLE`)
+pub const RT_NAMETABLE: u16 = 15;
+/// Version information resource type (`RT_VERSION`)
+pub const RT_VERSION: u16 = 16;
+
+///
+/// Identifier of resource type or resource itself.
+/// Integer IDs have the 0x8000 flag set on file; otherwise the value
+/// is an offset of length-prefixed name from the beginning of the resource table.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ResourceId {
+    Int(u16),
+    Name(String),
+}
+
+impl ResourceId {
+    ///
+    /// Compares IDs like Windows does: names are case insensitive
+    ///
+    pub fn matches(&self, other: &ResourceId) -> bool {
+        match (self, other) {
+            (ResourceId::Int(a), ResourceId::Int(b)) => a == b,
+            (ResourceId::Name(a), ResourceId::Name(b)) => a.eq_ignore_ascii_case(b),
+            _ => false,
+        }
+    }
+}
+
+impl From<u16> for ResourceId {
+    fn from(id: u16) -> Self {
+        ResourceId::Int(id)
+    }
+}
+
+impl From<&str> for ResourceId {
+    fn from(name: &str) -> Self {
+        ResourceId::Name(name.to_string())
+    }
+}
+
+impl fmt::Display for ResourceId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResourceId::Int(id) => write!(f, "{}", id),
+            ResourceId::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+///
+/// Name of predefined resource type (`RT_*` without prefix),
+/// string name or `#number` for other types
+///
+pub fn type_name(type_id: &ResourceId) -> String {
+    let name = match type_id {
+        ResourceId::Name(name) => return name.clone(),
+        ResourceId::Int(RT_CURSOR) => "CURSOR",
+        ResourceId::Int(RT_BITMAP) => "BITMAP",
+        ResourceId::Int(RT_ICON) => "ICON",
+        ResourceId::Int(RT_MENU) => "MENU",
+        ResourceId::Int(RT_DIALOG) => "DIALOG",
+        ResourceId::Int(RT_STRING) => "STRING",
+        ResourceId::Int(RT_FONTDIR) => "FONTDIR",
+        ResourceId::Int(RT_FONT) => "FONT",
+        ResourceId::Int(RT_ACCELERATOR) => "ACCELERATOR",
+        ResourceId::Int(RT_RCDATA) => "RCDATA",
+        ResourceId::Int(RT_GROUP_CURSOR) => "GROUP_CURSOR",
+        ResourceId::Int(RT_GROUP_ICON) => "GROUP_ICON",
+        ResourceId::Int(RT_NAMETABLE) => "NAMETABLE",
+        ResourceId::Int(RT_VERSION) => "VERSION",
+        ResourceId::Int(id) => return format!("#{}", id),
+    };
+    name.to_string()
+}
+
+///
+/// Type ID of a name given by `type_name`: predefined name (case-insensitive),
+/// `#number` or a plain number, otherwise name of custom type
+///
+pub fn type_id(name: &str) -> ResourceId {
+    let number = name.strip_prefix('#').unwrap_or(name);
+    if let Ok(id) = number.parse::<u16>() {
+        return ResourceId::Int(id);
+    }
+    (1..=RT_VERSION)
+        .map(ResourceId::Int)
+        .find(|id| type_name(id).eq_ignore_ascii_case(name))
+        .unwrap_or_else(|| ResourceId::Name(name.to_string()))
+}
+
 ///
 /// Resources are segments that contain data but 
 /// are not included in a program's normal data segments. 
@@ -15,7 +136,7 @@ use std::io::{self, Read};
 /// adjusting other values in the table. (Offsets are from the beginning
 /// of the table.)
 ///
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct NeResourceTable {
     pub header: NeResourceTableHeader,
     pub resource_types: Vec<NeResourceType>,
@@ -47,9 +168,99 @@ impl NeResourceTable {
             resource_types,
         })
     }
+
+    ///
+    /// Reads names of string-identified types and resources.
+    ///
+    /// \param offset -- file offset of resource table (names are relative to it)
+    pub fn read_names<R: Read + Seek>(&mut self, r: &mut R, offset: u64) -> io::Result<()> {
+        for resource_type in &mut self.resource_types {
+            resource_type.name = read_name(r, offset, resource_type.header.type_id)?;
+            for resource in &mut resource_type.resources {
+                resource.name = read_name(r, offset, resource.header.resource_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Finds resource type by its integer ID or name
+    ///
+    pub fn find_type(&self, type_id: &ResourceId) -> Option<&NeResourceType> {
+        self.resource_types.iter().find(|t| t.id().matches(type_id))
+    }
+
+    ///
+    /// Finds resource by type and resource IDs, integer or named:
+    /// `find_resource(&RT_DIALOG.into(), &"ABOUTBOX".into())`
+    ///
+    pub fn find_resource(&self, type_id: &ResourceId, id: &ResourceId) -> Option<&NeResource> {
+        self.find_type(type_id)?
+            .resources
+            .iter()
+            .find(|resource| resource.id().matches(id))
+    }
+
+    ///
+    /// Reads data of every resource in the table.
+    /// Offsets and lengths of resources are shifted by the
+    /// table's own alignment shift count (not the segment one).
+    /// Resources bigger than `max_eager_resource_size` are left unread.
+    ///
+    pub fn read_data<R: Read + Seek>(&mut self, r: &mut R, budget: &mut Budget) -> io::Result<()> {
+        let shift_count = self.header.alignment_shift_count;
+        for resource_type in &mut self.resource_types {
+            for resource in &mut resource_type.resources {
+                let length = resource.data_length(shift_count);
+                if !budget.is_eager_resource(length) {
+                    trace_warn!(
+                        "resource {} of {} bytes is left unread",
+                        resource.id(),
+                        length
+                    );
+                    continue;
+                }
+                budget.allocate(&format!("resource {}", resource.id()), length)?;
+                resource.read_data(r, shift_count)?;
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Lazily walks resources of RT_RCDATA type and of custom types:
+    /// named ones and integer ones above 255 (lower numbers are reserved
+    /// for predefined types). Data of these resources is opaque and may be
+    /// big (installers put whole archives there), so it is meant to be
+    /// streamed through the returned handles. Handles only locate data on
+    /// file: parsing still reads every resource into memory unless
+    /// `ParseOptions::max_eager_resource_size` is set below its length.
+    ///
+    pub fn custom_resources(&self) -> impl Iterator<Item = ResourceHandle> + '_ {
+        let shift_count = self.header.alignment_shift_count;
+        self.resource_types
+            .iter()
+            .filter(|t| t.is_custom())
+            .flat_map(move |t| {
+                t.resources
+                    .iter()
+                    .map(move |resource| resource.handle(t.id(), shift_count))
+            })
+    }
+
+    ///
+    /// Returns all resources of the integer type `type_id`
+    /// (without the 0x8000 flag; e.g. [`RT_FONT`]) in file order.
+    ///
+    pub fn resources_of_type(&self, type_id: u16) -> impl Iterator<Item = &NeResource> {
+        self.resource_types
+            .iter()
+            .filter(move |t| t.header.type_id == type_id | 0x8000)
+            .flat_map(|t| t.resources.iter())
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct NeResourceTableHeader {
     pub alignment_shift_count: u16,
 }
@@ -69,6 +280,7 @@ impl NeResourceTableHeader {
 #[derive(Debug, Clone)]
 pub struct NeResourceType {
     pub header: NeResourceTypeHeader,
+    pub name: Option<String>,
     pub resources: Vec<NeResource>,
 }
 impl NeResourceType {
@@ -77,18 +289,41 @@ impl NeResourceType {
         let resources = (0..header.num_resources)
             .map(|_| NeResource::read(r))
             .collect::<Result<Vec<_>, _>>()?;
-        Ok(Self { header, resources })
+        Ok(Self {
+            header,
+            name: None,
+            resources,
+        })
+    }
+
+    pub fn id(&self) -> ResourceId {
+        make_id(self.header.type_id, &self.name)
+    }
+
+    ///
+    /// RT_RCDATA or user-defined (named or numbered above 255) type
+    ///
+    pub fn is_custom(&self) -> bool {
+        let type_id = self.header.type_id;
+        type_id & 0x8000 == 0 || type_id == RT_RCDATA | 0x8000 || type_id & 0x7FFF > 0xFF
     }
 
     pub fn read_opt<R: Read>(r: &mut R) -> io::Result<Option<Self>> {
-        let header = NeResourceTypeHeader::read(r)?;
-        if header.type_id == 0 {
+        // the table ends with a zero type ID alone, not with a whole header
+        let mut type_id = [0; 2];
+        r.read_exact(&mut type_id)?;
+        if type_id == [0, 0] {
             return Ok(None);
         }
+        let header = NeResourceTypeHeader::read(&mut type_id.chain(&mut *r))?;
         let resources = (0..header.num_resources)
             .map(|_| NeResource::read(r))
             .collect::<Result<Vec<_>, _>>()?;
-        Ok(Some(Self { header, resources }))
+        Ok(Some(Self {
+            header,
+            name: None,
+            resources,
+        }))
     }
 }
 
@@ -112,16 +347,106 @@ impl NeResourceTypeHeader {
     }
 }
 
+impl fmt::Display for NeResourceTable {
+    ///
+    /// `{}` gives counts of types and resources, `{:#}` lists every resource
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let count: usize = self.resource_types.iter().map(|t| t.resources.len()).sum();
+        if !f.alternate() {
+            return write!(
+                f,
+                "{} type(s), {} resource(s)",
+                self.resource_types.len(),
+                count
+            );
+        }
+        let shift_count = self.header.alignment_shift_count;
+        for (i, resource_type) in self.resource_types.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", type_name(&resource_type.id()))?;
+            for resource in &resource_type.resources {
+                write!(
+                    f,
+                    "\n\t{:<16} offset 0x{:08X} length 0x{:06X}",
+                    resource.id().to_string(),
+                    resource.data_offset(shift_count),
+                    resource.data_length(shift_count)
+                )?;
+                for (mask, name) in [(0x0010, "MOVEABLE"), (0x0020, "PURE"), (0x0040, "PRELOAD")] {
+                    if resource.header.flags & mask != 0 {
+                        write!(f, " {}", name)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NeResource {
     pub header: NeResourceHeader,
+    pub name: Option<String>,
+    pub data: Option<Vec<u8>>,
 }
 impl NeResource {
     pub fn read<R: Read>(r: &mut R) -> io::Result<Self> {
         Ok(Self {
             header: NeResourceHeader::read(r)?,
+            name: None,
+            data: None,
         })
     }
+
+    pub fn id(&self) -> ResourceId {
+        make_id(self.header.resource_id, &self.name)
+    }
+
+    pub fn read_data<R: Read + Seek>(&mut self, r: &mut R, shift_count: u16) -> io::Result<()> {
+        if self.header.data_offset_shifted == 0 {
+            return Ok(());
+        }
+        r.seek(SeekFrom::Start(self.data_offset(shift_count)))?;
+        let mut data = vec![0; self.data_length(shift_count) as usize];
+        r.read_exact(&mut data)?;
+        self.data = Some(data);
+        Ok(())
+    }
+
+    pub fn data_offset(&self, shift_count: u16) -> u64 {
+        aligned(self.header.data_offset_shifted, shift_count)
+    }
+
+    ///
+    /// Length of resource on file. It is stored in alignment units too,
+    /// so it usually contains some padding after the real content.
+    ///
+    pub fn data_length(&self, shift_count: u16) -> u64 {
+        aligned(self.header.data_length, shift_count)
+    }
+
+    pub fn handle(&self, type_id: ResourceId, shift_count: u16) -> ResourceHandle {
+        ResourceHandle {
+            type_id,
+            resource_id: self.id(),
+            offset: self.data_offset(shift_count),
+            length: self.data_length(shift_count),
+        }
+    }
+
+    ///
+    /// Integer resource ID without the 0x8000 flag,
+    /// or `None` if resource is named by a string.
+    ///
+    pub fn int_id(&self) -> Option<u16> {
+        match self.header.resource_id & 0x8000 {
+            0 => None,
+            _ => Some(self.header.resource_id & 0x7FFF),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -147,3 +472,162 @@ impl NeResourceHeader {
         })
     }
 }
+
+fn make_id(raw: u16, name: &Option<String>) -> ResourceId {
+    match raw & 0x8000 {
+        0 => ResourceId::Name(name.clone().unwrap_or_default()),
+        _ => ResourceId::Int(raw & 0x7FFF),
+    }
+}
+
+///
+/// Reads length-prefixed name, if `raw` ID is not an integer one
+///
+fn read_name<R: Read + Seek>(r: &mut R, table_offset: u64, raw: u16) -> io::Result<Option<String>> {
+    if raw & 0x8000 != 0 {
+        return Ok(None);
+    }
+    r.seek(SeekFrom::Start(table_offset + raw as u64))?;
+    let len = {
+        let mut len = 0;
+        r.read_exact(std::slice::from_mut(&mut len))?;
+        len
+    };
+    let mut name = vec![0; len as usize];
+    r.read_exact(&mut name)?;
+    Ok(Some(String::from_utf8_lossy(&name).into_owned()))
+}
+
+///
+/// Location of resource data on file.
+/// Data is read only on demand, from the same file the table was read from.
+/// Handles don't keep parsing from reading the data too: set
+/// `ParseOptions::max_eager_resource_size` to leave big resources on file.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceHandle {
+    pub type_id: ResourceId,
+    pub resource_id: ResourceId,
+    pub offset: u64,
+    pub length: u64,
+}
+
+impl ResourceHandle {
+    ///
+    /// Positions the file at resource data and returns
+    /// a reader limited by resource length.
+    ///
+    pub fn reader<'r, R: Read + Seek>(&self, r: &'r mut R) -> io::Result<io::Take<&'r mut R>> {
+        r.seek(SeekFrom::Start(self.offset))?;
+        Ok(r.take(self.length))
+    }
+
+    ///
+    /// Streams resource data in pieces of at most `chunk_size` bytes.
+    ///
+    pub fn chunks<'r, R: Read + Seek>(
+        &self,
+        r: &'r mut R,
+        chunk_size: usize,
+    ) -> io::Result<ResourceChunks<'r, R>> {
+        Ok(ResourceChunks {
+            reader: self.reader(r)?,
+            chunk_size: chunk_size.max(1),
+        })
+    }
+}
+
+///
+/// Iterator over resource data pieces. Fails with `UnexpectedEof`
+/// if file ends before the resource does.
+///
+pub struct ResourceChunks<'r, R> {
+    reader: io::Take<&'r mut R>,
+    chunk_size: usize,
+}
+
+impl<R: Read> Iterator for ResourceChunks<'_, R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = self.reader.limit() as usize;
+        if remaining == 0 {
+            return None;
+        }
+        let mut chunk = vec![0; remaining.min(self.chunk_size)];
+        match self.reader.read_exact(&mut chunk) {
+            Ok(()) => Some(Ok(chunk)),
+            Err(e) => {
+                self.reader.set_limit(0);
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_resource_chunks() {
+        let file = (0..64).collect::<Vec<u8>>();
+        let handle = ResourceHandle {
+            type_id: ResourceId::Int(RT_RCDATA),
+            resource_id: ResourceId::Name("PAYLOAD".to_string()),
+            offset: 16,
+            length: 20,
+        };
+        let mut r = Cursor::new(file.as_slice());
+        let chunks = handle
+            .chunks(&mut r, 8)
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0], (16..24).collect::<Vec<u8>>());
+        assert_eq!(chunks[2], (32..36).collect::<Vec<u8>>());
+
+        let truncated = ResourceHandle {
+            offset: 60,
+            ..handle.clone()
+        };
+        let mut chunks = truncated.chunks(&mut r, 8).unwrap();
+        assert!(chunks.next().unwrap().is_err());
+        assert!(chunks.next().is_none());
+    }
+
+    #[test]
+    fn test_resource_names() {
+        // shift, one named type with one named resource, terminator, names
+        let mut table = vec![4, 0];
+        table.extend_from_slice(&[0x18, 0, 1, 0, 0, 0, 0, 0]);
+        table.extend_from_slice(&[0x01, 0, 0x01, 0, 0x30, 0, 0x1F, 0, 0, 0, 0, 0]);
+        table.extend_from_slice(&[0, 0]);
+        table.extend_from_slice(b"\x06MYDATA\x07Payload\x00");
+        let mut r = Cursor::new(table.as_slice());
+        let mut rt = NeResourceTable::read_variadic(&mut r).unwrap();
+        rt.read_names(&mut r, 0).unwrap();
+
+        assert_eq!(rt.resource_types[0].id(), ResourceId::from("MYDATA"));
+        let resource = rt
+            .find_resource(&"mydata".into(), &"PAYLOAD".into())
+            .unwrap();
+        assert_eq!(resource.id(), ResourceId::Name("Payload".to_string()));
+        assert!(rt
+            .find_resource(&RT_RCDATA.into(), &"PAYLOAD".into())
+            .is_none());
+    }
+
+    #[test]
+    fn test_type_id() {
+        for id in [RT_CURSOR, RT_GROUP_ICON, RT_VERSION, 0x20] {
+            assert_eq!(type_id(&type_name(&ResourceId::Int(id))), ResourceId::Int(id));
+        }
+        assert_eq!(type_id("string"), ResourceId::Int(RT_STRING));
+        assert_eq!(type_id("10"), ResourceId::Int(RT_RCDATA));
+        assert_eq!(type_id("MYDATA"), ResourceId::from("MYDATA"));
+    }
+}
@@ -0,0 +1,226 @@
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+use crate::ne::header::NE_LIBRARY;
+use crate::ne::resolver::ImportResolver;
+use crate::ne::NeExecutable;
+use crate::util::checksum::sha256;
+use crate::util::json::json_string;
+
+/// `specVersion` of CycloneDX which `Sbom::to_json` follows
+pub const SBOM_SPEC_VERSION: &str = "1.5";
+
+///
+/// Module as a component of software inventory
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Component {
+    /// Module name, uppercase; also the `bom-ref` of the component
+    pub name: String,
+    /// `FileVersion` of the version resource
+    pub version: Option<String>,
+    /// `FileDescription` of the version resource, or module description
+    pub description: Option<String>,
+    /// `CompanyName` of the version resource
+    pub publisher: Option<String>,
+    /// Library (DLL, driver) rather than application
+    pub is_library: bool,
+    /// File of the module; `None` for modules not found
+    pub path: Option<PathBuf>,
+    /// SHA-256 of the file
+    pub sha256: Option<[u8; 32]>,
+    /// Names of referenced modules, in module reference table order
+    pub depends_on: Vec<String>,
+}
+
+///
+/// Components of a module and of modules it depends on.
+/// The first component is the module itself.
+///
+#[derive(Debug, Clone, Default)]
+pub struct Sbom {
+    pub components: Vec<Component>,
+}
+
+impl Component {
+    fn to_json(&self) -> String {
+        let mut fields = vec![
+            format!(
+                "\"type\":\"{}\"",
+                if self.is_library {
+                    "library"
+                } else {
+                    "application"
+                }
+            ),
+            format!("\"bom-ref\":{}", json_string(&self.name)),
+            format!("\"name\":{}", json_string(&self.name)),
+        ];
+        if let Some(version) = &self.version {
+            fields.push(format!("\"version\":{}", json_string(version)));
+        }
+        if let Some(description) = &self.description {
+            fields.push(format!("\"description\":{}", json_string(description)));
+        }
+        if let Some(publisher) = &self.publisher {
+            fields.push(format!("\"publisher\":{}", json_string(publisher)));
+        }
+        if let Some(digest) = &self.sha256 {
+            let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+            fields.push(format!(
+                "\"hashes\":[{{\"alg\":\"SHA-256\",\"content\":\"{}\"}}]",
+                hex
+            ));
+        }
+        let mut properties = vec![format!(
+            "{{\"name\":\"win16:found\",\"value\":\"{}\"}}",
+            self.path.is_some()
+        )];
+        if let Some(path) = &self.path {
+            properties.push(format!(
+                "{{\"name\":\"win16:path\",\"value\":{}}}",
+                json_string(&path.display().to_string())
+            ));
+        }
+        fields.push(format!("\"properties\":[{}]", properties.join(",")));
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+impl Sbom {
+    ///
+    /// CycloneDX-like JSON: the module as `metadata.component`,
+    /// modules it depends on as `components`, and references
+    /// between them as `dependencies`
+    ///
+    pub fn to_json(&self) -> String {
+        let Some((root, rest)) = self.components.split_first() else {
+            return format!(
+                "{{\"bomFormat\":\"CycloneDX\",\"specVersion\":\"{}\",\"version\":1}}",
+                SBOM_SPEC_VERSION
+            );
+        };
+        let components: Vec<String> = rest.iter().map(Component::to_json).collect();
+        let dependencies: Vec<String> = self
+            .components
+            .iter()
+            .map(|c| {
+                let depends_on: Vec<String> =
+                    c.depends_on.iter().map(|name| json_string(name)).collect();
+                format!(
+                    "{{\"ref\":{},\"dependsOn\":[{}]}}",
+                    json_string(&c.name),
+                    depends_on.join(",")
+                )
+            })
+            .collect();
+        format!(
+            "{{\"bomFormat\":\"CycloneDX\",\"specVersion\":\"{}\",\"version\":1,\
+             \"metadata\":{{\"component\":{}}},\"components\":[{}],\"dependencies\":[{}]}}",
+            SBOM_SPEC_VERSION,
+            root.to_json(),
+            components.join(","),
+            dependencies.join(",")
+        )
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// The module as a component: name and kind from the headers,
+    /// version, description and publisher from the version resource.
+    /// Module description is taken when the resource has none.
+    /// File, hash and dependencies are left for the caller.
+    ///
+    pub fn component(&self) -> Component {
+        let info = self.version_info();
+        let string = |key| {
+            info.as_ref()
+                .and_then(|info| info.string(key))
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+        };
+        Component {
+            name: self.module_name().unwrap_or_default().to_uppercase(),
+            version: info.as_ref().and_then(|info| info.version()),
+            description: string("FileDescription")
+                .or_else(|| self.description().filter(|s| !s.is_empty())),
+            publisher: string("CompanyName"),
+            is_library: self.ne_header.flags.value() & NE_LIBRARY != 0,
+            ..Component::default()
+        }
+    }
+}
+
+impl ImportResolver {
+    ///
+    /// Components of module `path` and of every module it depends on,
+    /// found like `resolve` finds them. Modules not found or not read
+    /// are listed as libraries with names only.
+    ///
+    pub fn sbom(&self, path: &Path) -> io::Result<Sbom> {
+        let dependencies = self.resolve(path)?;
+        let mut components = Vec::new();
+        for module in dependencies.modules {
+            let mut component = match (&module.path, &module.error) {
+                (Some(path), None) => {
+                    let data = fs::read(path)?;
+                    let ne = NeExecutable::read(&mut Cursor::new(&data))?;
+                    Component {
+                        sha256: Some(sha256(&data)),
+                        ..ne.component()
+                    }
+                }
+                // only libraries are referenced
+                _ => Component {
+                    is_library: true,
+                    ..Component::default()
+                },
+            };
+            component.name = module.name;
+            component.path = module.path;
+            component.depends_on = module.depends_on;
+            components.push(component);
+        }
+        Ok(Sbom { components })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_root_as_metadata() {
+        let sbom = Sbom {
+            components: vec![
+                Component {
+                    name: "APP".to_string(),
+                    version: Some("1.0".to_string()),
+                    path: Some(PathBuf::from("APP.EXE")),
+                    depends_on: vec!["KERNEL".to_string()],
+                    ..Component::default()
+                },
+                Component {
+                    name: "KERNEL".to_string(),
+                    is_library: true,
+                    ..Component::default()
+                },
+            ],
+        };
+        assert_eq!(
+            sbom.to_json(),
+            "{\"bomFormat\":\"CycloneDX\",\"specVersion\":\"1.5\",\"version\":1,\
+             \"metadata\":{\"component\":{\"type\":\"application\",\"bom-ref\":\"APP\",\
+             \"name\":\"APP\",\"version\":\"1.0\",\"properties\":[\
+             {\"name\":\"win16:found\",\"value\":\"true\"},\
+             {\"name\":\"win16:path\",\"value\":\"APP.EXE\"}]}},\
+             \"components\":[{\"type\":\"library\",\"bom-ref\":\"KERNEL\",\"name\":\"KERNEL\",\
+             \"properties\":[{\"name\":\"win16:found\",\"value\":\"false\"}]}],\
+             \"dependencies\":[{\"ref\":\"APP\",\"dependsOn\":[\"KERNEL\"]},\
+             {\"ref\":\"KERNEL\",\"dependsOn\":[]}]}"
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::ne::addr::Seg16;
+use crate::ne::raw::NeTable;
+use crate::ne::NeExecutable;
+
+///
+/// Places of file occupied by one segment: its data, relocation records
+/// following them, and padding up to the next structure on file.
+/// Linkers fill padding with zeros up to the next sector, some leave
+/// debug information there; it may be reused when the segment grows.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentLayout {
+    pub segment: Seg16,
+    /// `None` for segments without data on file (allocated only)
+    pub data: Option<Range<u64>>,
+    /// `None` for segments without `RELOCINFO` or without parsed records
+    pub relocations: Option<Range<u64>>,
+    /// Bytes after data and relocations not taken by any other structure;
+    /// empty if the next one follows right away or overlaps
+    pub padding: Range<u64>,
+}
+
+impl SegmentLayout {
+    ///
+    /// Whole place of the segment on file, padding included
+    ///
+    pub fn range(&self) -> Option<Range<u64>> {
+        let data = self.data.as_ref()?;
+        Some(data.start..self.padding.end)
+    }
+
+    ///
+    /// Bytes the segment with its relocations may grow by in place
+    ///
+    pub fn room(&self) -> u64 {
+        self.padding.end - self.padding.start
+    }
+}
+
+impl fmt::Display for SegmentLayout {
+    ///
+    /// `{}` gives place of data and room after them,
+    /// `{:#}` also every part on its own line
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(range) = self.range() else {
+            return write!(f, "segment #{}: no data on file", self.segment);
+        };
+        write!(
+            f,
+            "segment #{}: {:#08X}-{:#08X}, 0x{:X} bytes of room",
+            self.segment,
+            range.start,
+            range.end,
+            self.room()
+        )?;
+        if f.alternate() {
+            let parts = [
+                ("data", self.data.clone()),
+                ("relocations", self.relocations.clone()),
+                (
+                    "padding",
+                    Some(self.padding.clone()).filter(|p| !p.is_empty()),
+                ),
+            ];
+            for (name, part) in parts {
+                if let Some(part) = part {
+                    write!(f, "\n\t{:#08X}-{:#08X} {}", part.start, part.end, name)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// First of `starts` at `end` or after it, or `limit` if it's nearer
+///
+fn next_start(end: u64, starts: &[u64], limit: u64) -> u64 {
+    starts
+        .iter()
+        .copied()
+        .filter(|&start| start >= end)
+        .fold(limit.max(end), u64::min)
+}
+
+impl NeExecutable {
+    ///
+    /// Starts of structures on file other than parts of `except`:
+    /// tables, data and relocations of other segments, and resources
+    ///
+    fn structure_starts(&self, except: Seg16) -> Vec<u64> {
+        let mut starts: Vec<u64> = self
+            .table_ranges()
+            .into_iter()
+            .filter(|range| range.table != NeTable::Relocations(except))
+            .map(|range| range.offset)
+            .collect();
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            if Seg16::from_index(i) != except && segment.header.data_offset_shifted != 0 {
+                starts.push(segment.data_offset());
+            }
+        }
+        let shift_count = self.resource_table.header.alignment_shift_count;
+        starts.extend(
+            self.resource_table
+                .resource_types
+                .iter()
+                .flat_map(|t| &t.resources)
+                .map(|r| r.data_offset(shift_count)),
+        );
+        starts
+    }
+
+    ///
+    /// Places of data, relocation records and padding of `segment` on file.
+    /// Padding of the last structure of the image ends where the image does.
+    /// `None` if the segment table doesn't have the segment.
+    ///
+    pub fn segment_layout(&self, segment: Seg16) -> Option<SegmentLayout> {
+        let entry = self.segment(segment)?;
+        if entry.header.data_offset_shifted == 0 {
+            return Some(SegmentLayout {
+                segment,
+                data: None,
+                relocations: None,
+                padding: 0..0,
+            });
+        }
+        let data_start = entry.data_offset();
+        let data = data_start..data_start + entry.data_length();
+        let relocations = self
+            .table_range(NeTable::Relocations(segment))
+            .map(|range| range.range());
+        let end = relocations.as_ref().map_or(data.end, |r| r.end);
+        let padding_end = next_start(end, &self.structure_starts(segment), self.image_end());
+        Some(SegmentLayout {
+            segment,
+            data: Some(data),
+            relocations,
+            padding: end..padding_end,
+        })
+    }
+
+    ///
+    /// Layouts of every segment, by segment number
+    ///
+    pub fn segment_layouts(&self) -> Vec<SegmentLayout> {
+        (0..self.segment_entries.len())
+            .filter_map(|i| self.segment_layout(Seg16::from_index(i)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padding_ends_at_next_structure() {
+        assert_eq!(next_start(0x250, &[0x40, 0x300, 0x280], 0x400), 0x280);
+        assert_eq!(next_start(0x250, &[0x40, 0x250], 0x400), 0x250);
+        // last structure of the image
+        assert_eq!(next_start(0x3F0, &[0x40], 0x400), 0x400);
+        let layout = SegmentLayout {
+            segment: Seg16(1),
+            data: Some(0x240..0x26A),
+            relocations: Some(0x26A..0x29C),
+            padding: 0x29C..0x2A0,
+        };
+        assert_eq!(layout.room(), 4);
+        assert_eq!(
+            layout.to_string(),
+            "segment #1: 0x000240-0x0002A0, 0x4 bytes of room"
+        );
+    }
+}
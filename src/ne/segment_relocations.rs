@@ -4,13 +4,15 @@
 /// Mostly expected for importing procedures adresses
 /// and importing procedure @ordinals
 /// 
+use std::fmt;
 use std::io::{self, Read};
 
 #[derive(Debug, Clone)]
 pub enum RelocationTarget {
     Internal(InternalFixes),
     ImportByOrdinal(ImportByOrdinal), 
-    ImportByName(ImportByName)
+    ImportByName(ImportByName),
+    OsFixup(OsFixup),
 }
 
 #[derive(Debug, Clone)]
@@ -22,7 +24,7 @@ pub struct RelocationEntry {
     pub target: RelocationTarget,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct RelocationTable {
     pub entries: Vec<RelocationEntry>,
 }
@@ -42,6 +44,97 @@ pub struct ImportByName {
     pub module_index: u16,
     pub name_offset: u16,
 }
+///
+/// Operating system fixup: loader patches floating point
+/// instructions (emulator calls) depending on presence of 8087.
+///
+#[derive(Debug, Copy, Clone)]
+pub struct OsFixup {
+    pub fixup_type: u16,
+}
+
+///
+/// Patches of 8087 emulator calls named by the symbols of
+/// Microsoft floating point libraries. Compilers emit `INT 34h..3Dh`
+/// instead of coprocessor instructions; with 8087 present loader turns
+/// them back into `ESC` (and prefix) opcodes by adding the symbol value.
+/// Prefixed instructions are emulated by `INT 3Ch`.
+/// `FJxRQQ` is the second byte of two-byte patches.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OsFixupKind {
+    /// `DS:` prefixed instruction
+    Fiarqq,
+    /// `SS:` prefixed instruction
+    Fisrqq,
+    /// `CS:` prefixed instruction
+    Ficrqq,
+    /// `ES:` prefixed instruction
+    Fierqq,
+    /// `ESC` (D8h..DFh) without prefix, `INT 34h..3Bh`
+    Fidrqq,
+    /// `FWAIT`, `INT 3Dh`
+    Fiwrqq,
+}
+
+impl OsFixupKind {
+    pub fn from_type(fixup_type: u16) -> Option<Self> {
+        match fixup_type {
+            1 => Some(Self::Fiarqq),
+            2 => Some(Self::Fisrqq),
+            3 => Some(Self::Ficrqq),
+            4 => Some(Self::Fierqq),
+            5 => Some(Self::Fidrqq),
+            6 => Some(Self::Fiwrqq),
+            _ => None,
+        }
+    }
+
+    pub fn symbols(&self) -> &'static str {
+        match self {
+            Self::Fiarqq => "FIARQQ/FJARQQ",
+            Self::Fisrqq => "FISRQQ/FJSRQQ",
+            Self::Ficrqq => "FICRQQ/FJCRQQ",
+            Self::Fierqq => "FIERQQ",
+            Self::Fidrqq => "FIDRQQ",
+            Self::Fiwrqq => "FIWRQQ",
+        }
+    }
+
+    ///
+    /// Instruction restored by the patch
+    ///
+    pub fn patched_instruction(&self) -> &'static str {
+        match self {
+            Self::Fiarqq => "DS: ESC",
+            Self::Fisrqq => "SS: ESC",
+            Self::Ficrqq => "CS: ESC",
+            Self::Fierqq => "ES: ESC",
+            Self::Fidrqq => "ESC",
+            Self::Fiwrqq => "FWAIT",
+        }
+    }
+}
+
+impl OsFixup {
+    pub fn kind(&self) -> Option<OsFixupKind> {
+        OsFixupKind::from_type(self.fixup_type)
+    }
+}
+
+impl fmt::Display for OsFixup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind() {
+            Some(kind) => write!(
+                f,
+                "{} (8087 emulator, {})",
+                kind.symbols(),
+                kind.patched_instruction()
+            ),
+            None => write!(f, "unknown OS fixup {}", self.fixup_type),
+        }
+    }
+}
 
 impl RelocationTable {
     /// Reads relocation table for a segment
@@ -107,6 +200,11 @@ impl RelocationTable {
 
                     RelocationTarget::ImportByName(import_by_name) 
                 }
+                // Operating system fixup
+                0x03 => {
+                    let fixup_type = u16::from_le_bytes([entry_buf[4], entry_buf[5]]);
+                    RelocationTarget::OsFixup(OsFixup { fixup_type })
+                }
                 _ => {
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidData,
@@ -126,4 +224,31 @@ impl RelocationTable {
         
         Ok(Self { entries })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn os_fixup_types() {
+        let cases = [
+            (1, Some(OsFixupKind::Fiarqq)),
+            (2, Some(OsFixupKind::Fisrqq)),
+            (3, Some(OsFixupKind::Ficrqq)),
+            (4, Some(OsFixupKind::Fierqq)),
+            (5, Some(OsFixupKind::Fidrqq)),
+            (6, Some(OsFixupKind::Fiwrqq)),
+            (0, None),
+            (7, None),
+        ];
+        for (fixup_type, kind) in cases {
+            assert_eq!(OsFixupKind::from_type(fixup_type), kind, "{}", fixup_type);
+        }
+        assert_eq!(
+            OsFixup { fixup_type: 1 }.to_string(),
+            "FIARQQ/FJARQQ (8087 emulator, DS: ESC)"
+        );
+        assert_eq!(OsFixup { fixup_type: 7 }.to_string(), "unknown OS fixup 7");
+    }
+}
@@ -1,5 +1,13 @@
 use std::convert::TryInto;
+use std::fmt;
 use std::io::{self, Read, Seek, SeekFrom};
+
+/// Segment may be moved by memory manager; its entries go through `INT 3Fh` thunks
+pub const SEG_MOVEABLE: u16 = 0x0010;
+/// Segment is loaded at start instead of on first call
+pub const SEG_PRELOAD: u16 = 0x0040;
+/// Relocation records follow segment data on file
+pub const SEG_RELOCINFO: u16 = 0x0100;
 ///
 /// This table contains one 8-byte record for every code and data segment
 /// in the program or library module. 
@@ -43,7 +51,7 @@ impl NeSegment {
     }
 
     pub fn data_offset(&self) -> u64 {
-        (self.header.data_offset_shifted as u64) << self.shift_count
+        aligned(self.header.data_offset_shifted, self.shift_count)
     }
 
     pub fn data_length(&self) -> u64 {
@@ -63,6 +71,65 @@ impl NeSegment {
     }
 }
 
+///
+/// Bytes in `units` of alignment given by `shift_count`. Counts past 32
+/// are taken as 32: offsets of 4 GiB and more lie past any file anyway.
+///
+pub fn aligned(units: u16, shift_count: u16) -> u64 {
+    (units as u64) << shift_count.min(32)
+}
+
+///
+/// Names of segment flags set in `flags`
+///
+pub fn segment_flag_names(flags: u16) -> Vec<&'static str> {
+    [
+        (SEG_MOVEABLE, "MOVEABLE"),
+        (0x0020, "SHAREABLE"),
+        (SEG_PRELOAD, "PRELOAD"),
+        (0x0080, "READONLY"),
+        (SEG_RELOCINFO, "RELOCINFO"),
+        (0x1000, "DISCARDABLE"),
+    ]
+    .iter()
+    .filter(|(mask, _)| flags & mask != 0)
+    .map(|&(_, name)| name)
+    .collect()
+}
+
+impl fmt::Display for NeSegment {
+    ///
+    /// `{}` gives kind, place on file and flags;
+    /// `{:#}` also gives allocation size and raw flags
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = if self.header.flags & 0x0001 != 0 {
+            "DATA"
+        } else {
+            "CODE"
+        };
+        write!(
+            f,
+            "{:<4} offset 0x{:08X} length 0x{:05X}",
+            kind,
+            self.data_offset(),
+            self.data_length()
+        )?;
+        if f.alternate() {
+            write!(
+                f,
+                " alloc 0x{:05X} flags 0x{:04X}",
+                self.min_alloc(),
+                self.header.flags
+            )?;
+        }
+        for name in segment_flag_names(self.header.flags) {
+            write!(f, " {}", name)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct NeSegmentHeader {
     pub data_offset_shifted: u16,
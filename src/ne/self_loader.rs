@@ -0,0 +1,136 @@
+use std::io::{self, ErrorKind};
+
+use crate::ne::addr::Far16Ptr;
+use crate::ne::header::NE_SELFLOAD;
+use crate::ne::NeExecutable;
+
+///
+/// Header at the start of segment 1 of self-loading module (`NE_SELFLOAD` flag).
+/// Windows calls `BootApp` instead of loading segments itself,
+/// and the module loads them by `LoadAppSeg` (usually unpacking or decrypting them).
+/// `MyAlloc`, `EntryAddrProc` and `SetOwner` are filled in by KERNEL at load time.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfLoadHeader {
+    /// "A0"
+    pub signature: [u8; 2],
+    pub reserved: u16,
+    pub boot_app: Far16Ptr,
+    pub load_app_seg: Far16Ptr,
+    pub reserved_proc: Far16Ptr,
+    pub my_alloc: Far16Ptr,
+    pub entry_addr_proc: Far16Ptr,
+    pub exit_proc: Far16Ptr,
+    pub reserved_words: [u16; 4],
+    pub set_owner: Far16Ptr,
+}
+
+impl SelfLoadHeader {
+    pub const SIZE: usize = 0x28;
+
+    pub fn read(data: &[u8]) -> io::Result<Self> {
+        if data.len() < Self::SIZE {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "self-load header needs {} bytes, segment has {}",
+                    Self::SIZE,
+                    data.len()
+                ),
+            ));
+        }
+        let get_u16 = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]);
+        let get_far = |pos: usize| Far16Ptr::from_bytes(&data[pos..]).unwrap();
+        let header = Self {
+            signature: [data[0], data[1]],
+            reserved: get_u16(2),
+            boot_app: get_far(4),
+            load_app_seg: get_far(8),
+            reserved_proc: get_far(12),
+            my_alloc: get_far(16),
+            entry_addr_proc: get_far(20),
+            exit_proc: get_far(24),
+            reserved_words: [get_u16(28), get_u16(30), get_u16(32), get_u16(34)],
+            set_owner: get_far(36),
+        };
+        if header.signature != *b"A0" {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "self-load header signature is {:02X} {:02X} instead of \"A0\"",
+                    header.signature[0], header.signature[1]
+                ),
+            ));
+        }
+        Ok(header)
+    }
+
+    ///
+    /// Loader procedures provided by the module (not null on file)
+    ///
+    pub fn overridden_procedures(&self) -> Vec<(&'static str, Far16Ptr)> {
+        [
+            ("BootApp", self.boot_app),
+            ("LoadAppSeg", self.load_app_seg),
+            ("Reserved", self.reserved_proc),
+            ("MyAlloc", self.my_alloc),
+            ("EntryAddrProc", self.entry_addr_proc),
+            ("ExitProc", self.exit_proc),
+            ("SetOwner", self.set_owner),
+        ]
+        .into_iter()
+        .filter(|(_, ptr)| !ptr.is_null())
+        .collect()
+    }
+}
+
+impl NeExecutable {
+    pub fn is_self_loading(&self) -> bool {
+        self.ne_header.flags.value() & NE_SELFLOAD != 0
+    }
+
+    ///
+    /// Reads self-load header from segment 1, if module has `NE_SELFLOAD` flag
+    ///
+    pub fn self_load_header(&self) -> io::Result<Option<SelfLoadHeader>> {
+        if !self.is_self_loading() {
+            return Ok(None);
+        }
+        let data = self
+            .segment_entries
+            .first()
+            .and_then(|s| s.data.as_deref())
+            .ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    "self-loading module has no data in segment 1",
+                )
+            })?;
+        SelfLoadHeader::read(data).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_overridden_procedures() {
+        let mut data = vec![0; SelfLoadHeader::SIZE];
+        data[0..2].copy_from_slice(b"A0");
+        data[4..8].copy_from_slice(&[0x10, 0x00, 0x01, 0x00]); // BootApp 1:0010
+        data[8..12].copy_from_slice(&[0x80, 0x01, 0x01, 0x00]); // LoadAppSeg 1:0180
+        let header = SelfLoadHeader::read(&data).unwrap();
+        assert_eq!(
+            header.overridden_procedures(),
+            vec![
+                ("BootApp", Far16Ptr::new(1, 0x10)),
+                ("LoadAppSeg", Far16Ptr::new(1, 0x180)),
+            ]
+        );
+
+        data[0] = b'B';
+        assert!(SelfLoadHeader::read(&data).is_err());
+        assert!(SelfLoadHeader::read(&data[..8]).is_err());
+    }
+}
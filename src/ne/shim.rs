@@ -0,0 +1,154 @@
+use std::fmt;
+
+use crate::ne::exports::Export;
+use crate::ne::spec::CONSTANT_SEGMENT;
+use crate::ne::NeExecutable;
+
+///
+/// Sources of a 32-bit DLL exporting the same names and ordinals
+/// as the 16-bit module: module definition file for the linker
+/// and NASM thunks (`nasm -f win32`) to be replaced by real code
+/// while callers are moved off the Win16 DLL.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Win32Shim {
+    pub def: String,
+    pub asm: String,
+}
+
+impl fmt::Display for Win32Shim {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\n{}", self.def, self.asm)
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Generates `Win32Shim` of the module exports.
+    ///
+    /// Every procedure gets a `stdcall` thunk returning 0. Parameter
+    /// words of `FAR PASCAL` entries widen to doublewords in Win32, so
+    /// thunk pops 4 bytes per word; entries without parameter words pop
+    /// nothing and are marked for review. Exported data becomes a
+    /// `DATA` export of one reserved word, constants are listed in comments
+    /// (Win32 has no absolute exports). Unnamed entries stay `NONAME`.
+    ///
+    pub fn win32_shim(&self) -> Win32Shim {
+        let module = self.module_name().unwrap_or_else(|| "MODULE".to_string());
+        let mut def = format!(
+            "; Generated by win16ne from {}\nLIBRARY {}\nEXPORTS\n",
+            module, module
+        );
+        let mut code = String::new();
+        let mut data = String::new();
+        for export in self.exports() {
+            let is_data = self.is_data_segment(export.segment);
+            def += &def_line(&module, export, is_data);
+            if export.segment == CONSTANT_SEGMENT {
+                continue;
+            }
+            if is_data {
+                data += &data_thunk(export);
+            } else {
+                code += &code_thunk(export);
+            }
+        }
+        let asm = format!(
+            "; Generated by win16ne from {}\nbits 32\n\nsection .text\n{}\nsection .data\n{}",
+            module, code, data
+        );
+        Win32Shim { def, asm }
+    }
+}
+
+fn symbol(export: &Export) -> String {
+    format!("shim_{}", export.ordinal)
+}
+
+fn def_line(module: &str, export: &Export, is_data: bool) -> String {
+    let name = export
+        .name
+        .as_deref()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{}_{}", module, export.ordinal));
+    if export.segment == CONSTANT_SEGMENT {
+        return format!(
+            "\t; {} @{} is constant 0x{:04X}\n",
+            name, export.ordinal, export.offset
+        );
+    }
+    let noname = if export.name.is_none() { " NONAME" } else { "" };
+    let kind = if is_data { " DATA" } else { "" };
+    format!(
+        "\t{}={} @{}{}{}\n",
+        name,
+        symbol(export),
+        export.ordinal,
+        noname,
+        kind
+    )
+}
+
+fn code_thunk(export: &Export) -> String {
+    let words = export.parameter_words();
+    let note = if words == 0 {
+        "parameters unknown".to_string()
+    } else {
+        format!("{} parameter word(s)", words)
+    };
+    format!(
+        "\nglobal _{0}\n_{0}: ; @{1} at {2}:{3:04X}, {4}\n\txor eax, eax\n\tret {5}\n",
+        symbol(export),
+        export.ordinal,
+        export.segment,
+        export.offset,
+        note,
+        words as u32 * 4
+    )
+}
+
+fn data_thunk(export: &Export) -> String {
+    format!(
+        "\nglobal _{0}\n_{0}: ; @{1} data at {2}:{3:04X}\n\tdd 0\n",
+        symbol(export),
+        export.ordinal,
+        export.segment,
+        export.offset
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::dialect::FormatDialect;
+
+    fn export(name: Option<&str>, segment: u8, flags: u8) -> Export {
+        Export {
+            ordinal: 7,
+            name: name.map(Into::into),
+            is_resident_name: true,
+            segment,
+            offset: 0x42,
+            is_movable: false,
+            flags,
+            dialect: FormatDialect::Win30,
+        }
+    }
+
+    #[test]
+    fn exports_keep_names_and_ordinals() {
+        assert_eq!(
+            def_line("SAMPLE", &export(Some("FOO"), 1, 0x01), false),
+            "\tFOO=shim_7 @7\n"
+        );
+        assert_eq!(
+            def_line("SAMPLE", &export(None, 2, 0x01), true),
+            "\tSAMPLE_7=shim_7 @7 NONAME DATA\n"
+        );
+        assert_eq!(
+            def_line("SAMPLE", &export(Some("MAXVAL"), 0xFE, 0x01), false),
+            "\t; MAXVAL @7 is constant 0x0042\n"
+        );
+        assert!(code_thunk(&export(Some("FOO"), 1, 0x01 | 2 << 3)).ends_with("\tret 8\n"));
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::util::checksum::crc32;
+
+///
+/// Destination of extracted files: `unpack_to`, `extract_resources_to`,
+/// `extract_fonts_to` and `extract_images_to` put every file through it
+/// by `/`-separated relative path
+///
+pub trait ExtractSink {
+    fn put(&mut self, path: &str, data: &[u8]) -> io::Result<()>;
+}
+
+///
+/// Writes files under a directory, creating subdirectories as needed
+///
+#[derive(Debug, Clone)]
+pub struct DirSink {
+    pub dir: PathBuf,
+}
+
+impl DirSink {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+}
+
+impl ExtractSink for DirSink {
+    fn put(&mut self, path: &str, data: &[u8]) -> io::Result<()> {
+        let full = self.dir.join(path);
+        if let Some(parent) = full.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(full, data)
+    }
+}
+
+///
+/// Keeps files in memory by path; a file put twice keeps the last data
+///
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    pub files: BTreeMap<String, Vec<u8>>,
+}
+
+impl ExtractSink for MemorySink {
+    fn put(&mut self, path: &str, data: &[u8]) -> io::Result<()> {
+        self.files.insert(path.to_string(), data.to_vec());
+        Ok(())
+    }
+}
+
+const ZIP_LOCAL_HEADER: u32 = 0x04034B50;
+const ZIP_CENTRAL_HEADER: u32 = 0x02014B50;
+const ZIP_END_OF_DIRECTORY: u32 = 0x06054B50;
+/// Version 1.0 needed to extract stored files
+const ZIP_VERSION: u16 = 10;
+/// MS-DOS date of 1980-01-01, the earliest one zip can hold
+const ZIP_DATE: u16 = 0x21;
+
+///
+/// Central directory record of a file already written
+///
+struct ZipEntry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+///
+/// Writes files into a zip archive, stored without compression.
+/// Nothing is readable before `finish` writes the central directory.
+/// Archives are limited to 4 GiB and 65535 files (no ZIP64).
+///
+pub struct ZipSink<W: Write> {
+    out: W,
+    written: u64,
+    entries: Vec<ZipEntry>,
+}
+
+fn too_large(what: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidInput,
+        format!("{} doesn't fit into zip without ZIP64", what),
+    )
+}
+
+impl<W: Write> ZipSink<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            written: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    fn emit(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn offset(&self) -> io::Result<u32> {
+        u32::try_from(self.written).map_err(|_| too_large("archive"))
+    }
+
+    ///
+    /// Writes the central directory and gives the writer back
+    ///
+    pub fn finish(mut self) -> io::Result<W> {
+        let start = self.offset()?;
+        let count = u16::try_from(self.entries.len()).map_err(|_| too_large("file count"))?;
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend_from_slice(&ZIP_CENTRAL_HEADER.to_le_bytes());
+            // made by version 2.0 on MS-DOS
+            directory.extend_from_slice(&20u16.to_le_bytes());
+            for word in [ZIP_VERSION, 0, 0, 0, ZIP_DATE] {
+                directory.extend_from_slice(&word.to_le_bytes());
+            }
+            for dword in [entry.crc, entry.size, entry.size] {
+                directory.extend_from_slice(&dword.to_le_bytes());
+            }
+            // name, extra field, comment, disk, internal attributes
+            for word in [entry.name.len() as u16, 0, 0, 0, 0] {
+                directory.extend_from_slice(&word.to_le_bytes());
+            }
+            directory.extend_from_slice(&0u32.to_le_bytes());
+            directory.extend_from_slice(&entry.offset.to_le_bytes());
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+        let size = u32::try_from(directory.len()).map_err(|_| too_large("central directory"))?;
+        directory.extend_from_slice(&ZIP_END_OF_DIRECTORY.to_le_bytes());
+        for word in [0, 0, count, count] {
+            directory.extend_from_slice(&word.to_le_bytes());
+        }
+        directory.extend_from_slice(&size.to_le_bytes());
+        directory.extend_from_slice(&start.to_le_bytes());
+        directory.extend_from_slice(&0u16.to_le_bytes());
+        self.emit(&directory)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+impl<W: Write> ExtractSink for ZipSink<W> {
+    fn put(&mut self, path: &str, data: &[u8]) -> io::Result<()> {
+        let name = path.trim_start_matches('/');
+        if name.len() > u16::MAX as usize {
+            return Err(too_large("file name"));
+        }
+        let entry = ZipEntry {
+            name: name.to_string(),
+            crc: crc32(data),
+            size: u32::try_from(data.len()).map_err(|_| too_large(name))?,
+            offset: self.offset()?,
+        };
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&ZIP_LOCAL_HEADER.to_le_bytes());
+        // version, flags, method "stored", time, date
+        for word in [ZIP_VERSION, 0, 0, 0, ZIP_DATE] {
+            header.extend_from_slice(&word.to_le_bytes());
+        }
+        for dword in [entry.crc, entry.size, entry.size] {
+            header.extend_from_slice(&dword.to_le_bytes());
+        }
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+        self.emit(&header)?;
+        self.emit(data)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_at(data: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn writes_stored_zip() {
+        let mut zip = ZipSink::new(Vec::new());
+        zip.put("a/b.bin", b"hello").unwrap();
+        zip.put("c", b"").unwrap();
+        let data = zip.finish().unwrap();
+
+        assert_eq!(u32_at(&data, 0), ZIP_LOCAL_HEADER);
+        assert_eq!(u32_at(&data, 14), crc32(b"hello"));
+        assert_eq!(&data[30..37], b"a/b.bin");
+        assert_eq!(&data[37..42], b"hello");
+        assert_eq!(u32_at(&data, 42), ZIP_LOCAL_HEADER);
+
+        let end = data.len() - 22;
+        assert_eq!(u32_at(&data, end), ZIP_END_OF_DIRECTORY);
+        assert_eq!(&data[end + 8..end + 12], [2, 0, 2, 0]);
+        let directory = u32_at(&data, end + 16) as usize;
+        assert_eq!(directory, 42 + 30 + 1);
+        assert_eq!(u32_at(&data, directory), ZIP_CENTRAL_HEADER);
+        assert_eq!(u32_at(&data, end + 12) as usize, end - directory);
+
+        let mut memory = MemorySink::default();
+        memory.put("x", b"1").unwrap();
+        memory.put("x", b"2").unwrap();
+        assert_eq!(memory.files["x"], b"2");
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::ne::coverage::Structure;
+use crate::ne::owned::NeOwned;
+use crate::ne::strings::{scan, StringsOptions};
+
+/// Vendors of compilers and linkers whose names in slack tell the toolchain
+const TOOLCHAIN_MARKERS: &[&str] = &[
+    "Microsoft",
+    "Borland",
+    "Watcom",
+    "Symantec",
+    "Zortech",
+    "TopSpeed",
+    "MetaWare",
+];
+
+///
+/// What bytes of slack are
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlackFill {
+    Zeros,
+    /// The same byte repeated: `0xFF` of erased media, spaces, `INT 3`
+    Repeated(u8),
+    /// Anything else: leftover buffers of the linker or hidden data
+    Mixed,
+}
+
+impl fmt::Display for SlackFill {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SlackFill::Zeros => write!(f, "zeros"),
+            SlackFill::Repeated(byte) => write!(f, "filled with 0x{:02X}", byte),
+            SlackFill::Mixed => write!(f, "non-zero data"),
+        }
+    }
+}
+
+///
+/// Bytes between structures which no structure owns:
+/// alignment padding of segments and resources, slack between tables
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slack {
+    pub range: Range<u64>,
+    /// Structure ending right before the slack, `None` at start of file
+    pub after: Option<Structure>,
+    /// Structure starting right after the slack, `None` at end of file
+    pub before: Option<Structure>,
+    pub fill: SlackFill,
+    /// Runs of 4 and more printable ASCII characters: file offset and text
+    pub strings: Vec<(u64, String)>,
+    /// Names of `TOOLCHAIN_MARKERS` found in the strings
+    pub markers: Vec<&'static str>,
+}
+
+impl fmt::Display for Slack {
+    ///
+    /// `{}` gives one line, `{:#}` also lists strings
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:#08X}-{:#08X} 0x{:X} bytes",
+            self.range.start,
+            self.range.end,
+            self.range.end - self.range.start
+        )?;
+        match &self.after {
+            Some(structure) => write!(f, " after {}", structure)?,
+            None => write!(f, " at start of file")?,
+        }
+        match &self.before {
+            Some(structure) => write!(f, ", before {}", structure)?,
+            None => write!(f, ", at end of file")?,
+        }
+        write!(f, ": {}", self.fill)?;
+        if !self.strings.is_empty() {
+            write!(f, ", {} string(s)", self.strings.len())?;
+        }
+        if !self.markers.is_empty() {
+            write!(f, ", mentions {}", self.markers.join(", "))?;
+        }
+        if f.alternate() {
+            for (offset, text) in &self.strings {
+                write!(f, "\n\t{:#08X} {:?}", offset, text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Slack {
+    fn new(range: Range<u64>, data: &[u8]) -> Self {
+        let fill = match data.first() {
+            Some(&first) if data.iter().all(|&b| b == first) => match first {
+                0 => SlackFill::Zeros,
+                byte => SlackFill::Repeated(byte),
+            },
+            _ => SlackFill::Mixed,
+        };
+        let options = StringsOptions {
+            codepage: None,
+            ..StringsOptions::default()
+        };
+        let strings: Vec<(u64, String)> = scan(data, &options)
+            .into_iter()
+            .map(|(start, _, text)| (range.start + start as u64, text))
+            .collect();
+        let markers = TOOLCHAIN_MARKERS
+            .iter()
+            .copied()
+            .filter(|marker| strings.iter().any(|(_, text)| text.contains(marker)))
+            .collect();
+        Self {
+            range,
+            after: None,
+            before: None,
+            fill,
+            strings,
+            markers,
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.range.end - self.range.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+}
+
+impl NeOwned {
+    ///
+    /// Every run of bytes between structures of `coverage_map`, from the
+    /// start to the end of file. Padding of segments counts as slack
+    /// after data or relocations of the segment.
+    ///
+    pub fn slack(&self) -> Vec<Slack> {
+        let bytes = self.bytes();
+        let file_size = bytes.len() as u64;
+        let mut slack = Vec::new();
+        let mut end = 0;
+        let mut last: Option<Structure> = None;
+
+        let mut push = |range: Range<u64>, after: &Option<Structure>, before| {
+            let data = &bytes[range.start as usize..range.end as usize];
+            slack.push(Slack {
+                after: after.clone(),
+                before,
+                ..Slack::new(range, data)
+            });
+        };
+        for covered in self.module().coverage_map() {
+            if matches!(covered.structure, Structure::SegmentPadding(_)) {
+                continue;
+            }
+            let start = covered.range.start.min(file_size);
+            if start > end {
+                push(end..start, &last, Some(covered.structure.clone()));
+            }
+            let covered_end = covered.range.end.min(file_size);
+            if covered_end >= end {
+                end = covered_end;
+                last = Some(covered.structure.clone());
+            }
+        }
+        if end < file_size {
+            push(end..file_size, &last, None);
+        }
+        slack
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_fill_and_strings() {
+        assert_eq!(Slack::new(0..4, &[0; 4]).fill, SlackFill::Zeros);
+        assert_eq!(Slack::new(0..2, &[0xCC; 2]).fill, SlackFill::Repeated(0xCC));
+
+        let slack = Slack::new(0x100..0x11A, b"\0\0Borland C++ 1991\0\x01\x02\0abc\0");
+        assert_eq!(slack.fill, SlackFill::Mixed);
+        assert_eq!(slack.strings, [(0x102, "Borland C++ 1991".to_string())]);
+        assert_eq!(slack.markers, ["Borland"]);
+        assert_eq!(slack.len(), 0x1A);
+    }
+}
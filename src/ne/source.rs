@@ -0,0 +1,197 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+
+use crate::ne::open::OpenOptions;
+use crate::ne::options::ParseOptions;
+use crate::ne::NeExecutable;
+
+///
+/// Logical name of module bytes for reports and errors: path of the file,
+/// or path of the archive with name of the member as the archive stores it
+/// (`/` of zip, `\` of cabinets are kept). `{}` gives `DISK1.ZIP!BIN/APP.EXE`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceName {
+    /// Archive holding the module; `None` for plain files
+    pub archive: Option<String>,
+    /// Path of a plain file, member name in an archive
+    pub name: String,
+}
+
+impl SourceName {
+    pub fn file(path: &str) -> Self {
+        Self {
+            archive: None,
+            name: path.to_string(),
+        }
+    }
+
+    pub fn member(archive: &str, member: &str) -> Self {
+        Self {
+            archive: Some(archive.to_string()),
+            name: member.to_string(),
+        }
+    }
+
+    ///
+    /// Reads names as `Display` gives them: archive and member split at the first `!`
+    ///
+    pub fn parse(text: &str) -> Self {
+        match text.split_once('!') {
+            Some((archive, member)) => Self::member(archive, member),
+            None => Self::file(text),
+        }
+    }
+}
+
+impl fmt::Display for SourceName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.archive {
+            Some(archive) => write!(f, "{}!{}", archive, self.name),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+///
+/// Payload of `io::Error` telling which source failed to parse.
+/// `NeError::from_io` looks through it to the reason.
+///
+#[derive(Debug)]
+pub struct SourcedError {
+    pub source_name: SourceName,
+    pub error: io::Error,
+}
+
+impl SourcedError {
+    pub fn from_io(e: &io::Error) -> Option<&SourcedError> {
+        e.get_ref()?.downcast_ref::<SourcedError>()
+    }
+}
+
+impl fmt::Display for SourcedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.source_name, self.error)
+    }
+}
+
+impl Error for SourcedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+///
+/// `e` with `name` of its source: same kind, message prefixed by the name.
+/// Errors already naming a source are kept as they are.
+///
+pub fn with_source_name(name: &SourceName, e: io::Error) -> io::Error {
+    if SourcedError::from_io(&e).is_some() {
+        return e;
+    }
+    io::Error::new(
+        e.kind(),
+        SourcedError {
+            source_name: name.clone(),
+            error: e,
+        },
+    )
+}
+
+///
+/// Module parsed from bytes of a named source
+///
+#[derive(Debug, Clone)]
+pub struct NamedModule {
+    pub source_name: SourceName,
+    pub module: NeExecutable,
+}
+
+impl NeExecutable {
+    ///
+    /// Reads the module from `reader` which can't seek (archive member
+    /// being decompressed, pipe) by taking all its bytes into memory.
+    /// Errors, of reading too, are `SourcedError`s naming `name`.
+    /// Same as `OpenOptions::read_named` of `options`: compressed
+    /// modules are expanded.
+    ///
+    pub fn open_from_reader_with_name<R: Read>(
+        reader: &mut R,
+        name: SourceName,
+        options: &ParseOptions,
+    ) -> io::Result<NamedModule> {
+        OpenOptions::from(options.clone()).read_named(reader, name)
+    }
+
+    ///
+    /// Reads the module from bytes already extracted from `name`
+    /// (see `OpenOptions::read_bytes_named`)
+    ///
+    pub fn open_bytes_with_name(
+        data: &[u8],
+        name: SourceName,
+        options: &ParseOptions,
+    ) -> io::Result<NamedModule> {
+        OpenOptions::from(options.clone()).read_bytes_named(data, name)
+    }
+
+    ///
+    /// Reads every member of `archive` given as name and bytes,
+    /// in the order of `members`; one failed member doesn't stop others
+    ///
+    pub fn open_archive_members<'a>(
+        archive: &str,
+        members: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+        options: &ParseOptions,
+    ) -> Vec<io::Result<NamedModule>> {
+        let options = OpenOptions::from(options.clone());
+        members
+            .into_iter()
+            .map(|(member, data)| {
+                options.read_bytes_named(data, SourceName::member(archive, member))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::error::NeError;
+
+    #[test]
+    fn errors_name_archive_members() {
+        let name = SourceName::parse("DISK1.ZIP!BIN/APP.EXE");
+        assert_eq!(name, SourceName::member("DISK1.ZIP", "BIN/APP.EXE"));
+        assert_eq!(name.to_string(), "DISK1.ZIP!BIN/APP.EXE");
+        assert_eq!(SourceName::parse("C:\\APP.EXE").archive, None);
+
+        let mut data = [0u8; 0x40];
+        data[..2].copy_from_slice(b"PE");
+        let results = NeExecutable::open_archive_members(
+            "DISK1.ZIP",
+            [("BIN/APP.EXE", &data[..]), ("EMPTY.DLL", &[][..])],
+            &ParseOptions::default(),
+        );
+        assert_eq!(results.len(), 2);
+        let e = results[0].as_ref().unwrap_err();
+        assert!(e.to_string().starts_with("DISK1.ZIP!BIN/APP.EXE: "));
+        assert_eq!(SourcedError::from_io(e).unwrap().source_name, name);
+        assert!(matches!(
+            NeError::from_io(e),
+            Some(NeError::BadMagic { found, .. }) if found == b"PE"
+        ));
+        assert!(results[1]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .starts_with("DISK1.ZIP!EMPTY.DLL: "));
+
+        let twice = with_source_name(
+            &SourceName::file("OTHER"),
+            results.into_iter().next().unwrap().unwrap_err(),
+        );
+        assert!(twice.to_string().starts_with("DISK1.ZIP!"));
+    }
+}
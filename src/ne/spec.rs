@@ -0,0 +1,101 @@
+use crate::ne::addr::Seg16;
+use crate::ne::exports::Export;
+use crate::ne::NeExecutable;
+
+/// Segment number of exported constants in the entry table
+pub(crate) const CONSTANT_SEGMENT: u8 = 0xFE;
+
+impl NeExecutable {
+    ///
+    /// Generates Wine `.spec` file of the 16-bit module from its exports:
+    /// `ordinal type name(args) handler`.
+    ///
+    /// Win16 exports are `FAR PASCAL`, so functions are declared `pascal`
+    /// with one `word` argument per parameter word of the entry.
+    /// Entries without parameter words get a `stub`: word count is unknown,
+    /// because linkers fill it only for OS/2 call gates.
+    /// Exported data becomes a `stub` too and constants become `equate`.
+    ///
+    pub fn wine_spec(&self) -> String {
+        let module = self.module_name().unwrap_or_else(|| "MODULE".to_string());
+        let mut out = format!("# Generated by win16ne from {}\n\n", module);
+        for export in self.exports() {
+            out += &spec_line(&module, export, self.is_data_segment(export.segment));
+        }
+        out
+    }
+
+    pub(crate) fn is_data_segment(&self, segment: u8) -> bool {
+        self.segment(Seg16(segment as u16))
+            .is_some_and(|s| s.header.flags & 0x0001 != 0)
+    }
+}
+
+fn spec_line(module: &str, export: &Export, is_data: bool) -> String {
+    let name = export
+        .name
+        .as_deref()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{}_{}", module, export.ordinal));
+
+    if export.segment == CONSTANT_SEGMENT {
+        return format!(
+            "{} equate {} 0x{:04X}\n",
+            export.ordinal, name, export.offset
+        );
+    }
+    if is_data {
+        return format!(
+            "# data in segment {} at 0x{:04X}\n{} stub {}\n",
+            export.segment, export.offset, export.ordinal, name
+        );
+    }
+    match export.parameter_words() {
+        0 => format!(
+            "# parameters unknown, code at {}:{:04X}\n{} stub {}\n",
+            export.segment, export.offset, export.ordinal, name
+        ),
+        words => format!(
+            "{} pascal {}({}) {}\n",
+            export.ordinal,
+            name,
+            vec!["word"; words as usize].join(" "),
+            name
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::dialect::FormatDialect;
+
+    fn export(name: Option<&str>, segment: u8, flags: u8) -> Export {
+        Export {
+            ordinal: 7,
+            name: name.map(Into::into),
+            is_resident_name: true,
+            segment,
+            offset: 0x42,
+            is_movable: false,
+            flags,
+            dialect: FormatDialect::Win30,
+        }
+    }
+
+    #[test]
+    fn lines_by_entry_kind() {
+        assert_eq!(
+            spec_line("SAMPLE", &export(Some("FOO"), 1, 0x01 | 2 << 3), false),
+            "7 pascal FOO(word word) FOO\n"
+        );
+        assert_eq!(
+            spec_line("SAMPLE", &export(None, 1, 0x01), false),
+            "# parameters unknown, code at 1:0042\n7 stub SAMPLE_7\n"
+        );
+        assert_eq!(
+            spec_line("SAMPLE", &export(Some("MAXVAL"), 0xFE, 0x01), false),
+            "7 equate MAXVAL 0x0042\n"
+        );
+    }
+}
@@ -0,0 +1,654 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::ne::addr::{Far16Ptr, Seg16};
+use crate::ne::fixups::FixupTarget;
+use crate::ne::functions::relative_target;
+use crate::ne::header::NE_LIBRARY;
+use crate::ne::lint::allocated_size;
+use crate::ne::symbols::SymbolProvider;
+use crate::ne::thunks::import_name;
+use crate::ne::NeExecutable;
+use crate::x86::{self, Immediate};
+
+/// Task stack recommended at the least for Windows applications:
+/// system code called by the task runs on it too
+pub const MIN_WINDOWS_STACK: u32 = 5 * 1024;
+/// Instructions followed in one function
+const FRAME_MAX_INSTRUCTIONS: usize = 0x4000;
+/// Calls followed in depth; deeper chains count as leaves
+const MAX_CALL_DEPTH: usize = 256;
+
+///
+/// Stack use of one function, found by following its near flow
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    /// Most bytes pushed or reserved below the return address
+    pub local: u32,
+    /// Internal calls: bytes used at the call, size of return address, target
+    pub calls: Vec<(u32, u32, Far16Ptr)>,
+    /// Imported procedures called: offset, `MODULE.NAME`, the last constant pushed
+    pub imports: Vec<(u16, String, Option<u16>)>,
+    /// `CALL` through registers or memory, not followed
+    pub indirect_calls: usize,
+}
+
+fn immediate(inst: &x86::Instruction) -> Option<u16> {
+    match inst.immediate {
+        Immediate::Byte(b) => Some(b as i8 as u16),
+        Immediate::Word(w) => Some(w),
+        _ => None,
+    }
+}
+
+///
+/// State of one path through a function
+///
+#[derive(Debug, Clone, Copy, Default)]
+struct Path {
+    pos: usize,
+    /// Bytes pushed or reserved
+    depth: u32,
+    /// `depth` at `MOV BP, SP`
+    base: Option<u32>,
+    /// `depth` before arguments of the next call were pushed
+    arguments: u32,
+    /// Registers holding constants
+    registers: [Option<u16>; 8],
+}
+
+impl Path {
+    ///
+    /// Called procedure returned: Pascal procedures of Windows pop their
+    /// arguments, C ones leave them to `ADD SP` of the caller
+    ///
+    fn returned(&mut self) {
+        self.depth = self.arguments;
+        self.registers = [None; 8];
+    }
+}
+
+///
+/// Follows function of `code` at `start` through jumps and branches,
+/// counting pushes, pops, `SUB SP`, `ADD SP`, `ENTER` and `LEAVE`.
+/// Arguments pushed for a call are taken as removed when it returns.
+/// Paths joining an instruction already seen keep the first depth.
+/// \param places -- targets of relocations by offset of the patched place
+///
+pub fn trace_frame(
+    code: &[u8],
+    segment: Seg16,
+    places: &BTreeMap<u16, FixupTarget>,
+    start: u16,
+    symbols: &dyn SymbolProvider,
+) -> Frame {
+    let mut frame = Frame::default();
+    let mut visited = BTreeSet::new();
+    let mut work = vec![Path {
+        pos: start as usize,
+        ..Path::default()
+    }];
+    let mut instructions = 0;
+
+    while let Some(mut path) = work.pop() {
+        let mut pushed: Option<u16> = None;
+        let mut after_call = false;
+        loop {
+            let pos = path.pos;
+            if pos >= code.len() || !visited.insert(pos) || instructions >= FRAME_MAX_INSTRUCTIONS {
+                break;
+            }
+            instructions += 1;
+            let inst = x86::decode(code, pos, false);
+            if inst.is_invalid {
+                break;
+            }
+            let at = pos as u16;
+            path.pos = inst.end();
+            frame.local = frame.local.max(path.depth);
+            let reg = inst.opcode as usize & 7;
+            let returned = std::mem::take(&mut after_call);
+
+            match (inst.opcode, inst.opcode2, inst.modrm) {
+                (0x50..=0x57, None, _) => {
+                    path.depth += 2;
+                    pushed = path.registers[reg];
+                }
+                (0x68 | 0x6A, None, _) => {
+                    path.depth += 2;
+                    pushed = immediate(&inst);
+                }
+                (0x06 | 0x0E | 0x16 | 0x1E | 0x9C, None, _)
+                | (0xFF, None, Some(0x30..=0x37 | 0x70..=0x77 | 0xB0..=0xB7 | 0xF0..=0xF7)) => {
+                    path.depth += 2;
+                    pushed = None;
+                }
+                (0x60, None, _) => path.depth += 16,
+                (0x58..=0x5F | 0x07 | 0x17 | 0x1F | 0x9D, None, _) | (0x8F, None, _) => {
+                    path.depth = path.depth.saturating_sub(2);
+                    path.arguments = path.depth;
+                    if (0x58..=0x5F).contains(&inst.opcode) {
+                        path.registers[reg] = None;
+                    }
+                }
+                (0x61, None, _) => {
+                    path.depth = path.depth.saturating_sub(16);
+                    path.arguments = path.depth;
+                    path.registers = [None; 8];
+                }
+                (0xB8..=0xBF, None, _) => path.registers[reg] = immediate(&inst),
+                // SUB SP, imm
+                (0x81 | 0x83, None, Some(0xEC)) => {
+                    let size = immediate(&inst).unwrap_or_default() as i16 as i32;
+                    path.depth = (path.depth as i32 + size).max(0) as u32;
+                    path.arguments = path.depth;
+                }
+                // ADD SP, imm right after a call removes its arguments, already dropped
+                (0x81 | 0x83, None, Some(0xC4)) => {
+                    if !returned {
+                        let size = immediate(&inst).unwrap_or_default() as i16 as i32;
+                        path.depth = (path.depth as i32 - size).max(0) as u32;
+                    }
+                    path.arguments = path.depth;
+                }
+                // MOV BP, SP and MOV SP, BP
+                (0x8B, None, Some(0xEC)) | (0x89, None, Some(0xE5)) => {
+                    path.base = Some(path.depth);
+                    path.arguments = path.depth;
+                }
+                (0x8B, None, Some(0xE5)) | (0x89, None, Some(0xEC)) => {
+                    path.depth = path.base.unwrap_or(path.depth);
+                    path.arguments = path.depth;
+                }
+                (0xC8, None, _) => {
+                    let size = code
+                        .get(pos + 1..pos + 3)
+                        .map_or(0, |b| u16::from_le_bytes([b[0], b[1]]) as u32);
+                    path.depth += 2;
+                    path.base = Some(path.depth);
+                    path.depth += size;
+                    path.arguments = path.depth;
+                }
+                (0xC9, None, _) => {
+                    path.depth = path.base.unwrap_or(path.depth).saturating_sub(2);
+                    path.arguments = path.depth;
+                }
+                (0xE8, None, _) => {
+                    if let Some(target) = relative_target(&inst) {
+                        frame
+                            .calls
+                            .push((path.depth, 2, Far16Ptr::new(segment.0, target)));
+                    }
+                    path.returned();
+                    after_call = true;
+                }
+                (0x9A | 0xEA, None, _) => {
+                    let return_size = if inst.opcode == 0x9A { 4 } else { 0 };
+                    match places.get(&at.wrapping_add(1)) {
+                        Some(target @ FixupTarget::Import { .. }) => {
+                            if let Some(name) = import_name(target, symbols) {
+                                frame.imports.push((at, name, pushed));
+                            }
+                        }
+                        Some(FixupTarget::Internal { segment, offset }) => {
+                            let target = Far16Ptr::new(*segment as u16, *offset);
+                            frame.calls.push((path.depth, return_size, target));
+                        }
+                        _ => {}
+                    }
+                    if inst.opcode == 0xEA {
+                        break;
+                    }
+                    path.returned();
+                    after_call = true;
+                }
+                (0xFF, None, Some(modrm)) if matches!((modrm >> 3) & 7, 2 | 3) => {
+                    frame.indirect_calls += 1;
+                    path.returned();
+                    after_call = true;
+                }
+                (0xFF, None, Some(modrm)) if matches!((modrm >> 3) & 7, 4 | 5) => break,
+                (0xC2 | 0xC3 | 0xCA | 0xCB | 0xCF, None, _) => break,
+                (0xE9 | 0xEB, None, _) => match relative_target(&inst) {
+                    Some(target) => path.pos = target as usize,
+                    None => break,
+                },
+                (0x70..=0x7F | 0xE0..=0xE3, None, _) | (0x0F, Some(0x80..=0x8F), _) => {
+                    if let Some(target) = relative_target(&inst) {
+                        work.push(Path {
+                            pos: target as usize,
+                            ..path
+                        });
+                    }
+                }
+                _ => path.registers = [None; 8],
+            }
+        }
+    }
+    frame
+}
+
+///
+/// Stack the module needs by its call graph
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StackEstimate {
+    /// Bytes of the deepest chain of internal calls from `CS:IP`, plus the
+    /// deepest exported function called back far on top of it
+    pub depth: u32,
+    /// Functions of the deepest chain from `CS:IP`
+    pub chain: Vec<Far16Ptr>,
+    /// Deepest exported function, as window procedures run on the task stack
+    pub callback: Option<Far16Ptr>,
+    /// Functions calling themselves directly or through others;
+    /// a recursive call counts once
+    pub recursive: Vec<Far16Ptr>,
+    /// Indirect calls found, not followed
+    pub indirect_calls: usize,
+}
+
+///
+/// Call of `LocalAlloc` or `LocalReAlloc` with its size if it was
+/// pushed as a constant
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalAllocCall {
+    pub call: Far16Ptr,
+    pub import: String,
+    pub size: Option<u16>,
+}
+
+///
+/// Size of stack or heap which is likely to fail at run time
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryIssue {
+    /// Declared stack is smaller than the call graph needs
+    StackBelowEstimate { declared: u32, estimated: u32 },
+    /// Stack is smaller than `MIN_WINDOWS_STACK`
+    StackBelowMinimum { declared: u32 },
+    /// Automatic data, local heap and stack don't fit into one 64K segment
+    DgroupOverflow { data: u64, heap: u16, stack: u16 },
+    /// Local heap is allocated from, but the module has none and doesn't call `LocalInit`
+    LocalAllocWithoutHeap { calls: usize },
+    /// Constant sizes of local allocations add up to more than the initial heap
+    AllocationsExceedHeap { total: u32, heap: u16 },
+    /// Recursion makes the depth unbounded
+    Recursion { function: Far16Ptr },
+}
+
+impl fmt::Display for MemoryIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MemoryIssue::StackBelowEstimate {
+                declared,
+                estimated,
+            } => write!(
+                f,
+                "stack of 0x{:X} bytes is smaller than 0x{:X} bytes the calls need",
+                declared, estimated
+            ),
+            MemoryIssue::StackBelowMinimum { declared } => write!(
+                f,
+                "stack of 0x{:X} bytes is below the 0x{:X} bytes Windows applications need",
+                declared, MIN_WINDOWS_STACK
+            ),
+            MemoryIssue::DgroupOverflow { data, heap, stack } => write!(
+                f,
+                "data 0x{:X} + heap 0x{:X} + stack 0x{:X} bytes exceed 64K of the automatic data segment",
+                data, heap, stack
+            ),
+            MemoryIssue::LocalAllocWithoutHeap { calls } => write!(
+                f,
+                "{} local allocation(s) without local heap or LocalInit",
+                calls
+            ),
+            MemoryIssue::AllocationsExceedHeap { total, heap } => write!(
+                f,
+                "constant local allocations of 0x{:X} bytes exceed heap of 0x{:X} bytes",
+                total, heap
+            ),
+            MemoryIssue::Recursion { function } => {
+                write!(f, "recursion through {}, depth is unbounded", function)
+            }
+        }
+    }
+}
+
+///
+/// Declared stack and heap of the module against what its code does
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryAdequacy {
+    /// `init_stack_size`, or `SP` of `SS:SP` if it is zero
+    pub stack_size: u32,
+    pub heap_size: u16,
+    /// `None` for libraries and modules without `CS:IP`
+    pub stack: Option<StackEstimate>,
+    pub local_allocs: Vec<LocalAllocCall>,
+    pub issues: Vec<MemoryIssue>,
+}
+
+impl fmt::Display for MemoryAdequacy {
+    ///
+    /// `{}` gives sizes and count of issues, `{:#}` lists issues
+    /// and the deepest chain of calls
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "stack 0x{:X}, heap 0x{:X}",
+            self.stack_size, self.heap_size
+        )?;
+        if let Some(stack) = &self.stack {
+            write!(f, ", estimated stack 0x{:X}", stack.depth)?;
+        }
+        write!(
+            f,
+            ", {} local allocation(s), {} issue(s)",
+            self.local_allocs.len(),
+            self.issues.len()
+        )?;
+        if !f.alternate() {
+            return Ok(());
+        }
+        for issue in &self.issues {
+            write!(f, "\n\t{}", issue)?;
+        }
+        if let Some(stack) = &self.stack {
+            let chain: Vec<String> = stack.chain.iter().map(|p| p.to_string()).collect();
+            write!(f, "\n\tdeepest chain: {}", chain.join(" -> "))?;
+            if let Some(callback) = stack.callback {
+                write!(f, "\n\tdeepest callback: {}", callback)?;
+            }
+            if stack.indirect_calls > 0 {
+                write!(
+                    f,
+                    "\n\t{} indirect call(s) not followed",
+                    stack.indirect_calls
+                )?;
+            }
+        }
+        for alloc in &self.local_allocs {
+            match alloc.size {
+                Some(size) => write!(f, "\n\t{} {} 0x{:X} bytes", alloc.call, alloc.import, size)?,
+                None => write!(f, "\n\t{} {}", alloc.call, alloc.import)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+type SegmentCode = (Vec<u8>, BTreeMap<u16, FixupTarget>);
+
+///
+/// Frames of functions by address and depths computed from them
+///
+struct CallGraph<'a> {
+    ne: &'a NeExecutable,
+    symbols: &'a dyn SymbolProvider,
+    /// Code of segments with relocation targets by place; `None` for data segments
+    segments: BTreeMap<u16, Option<SegmentCode>>,
+    frames: BTreeMap<Far16Ptr, Frame>,
+    /// Depth of function and the callee of its deepest call
+    depths: BTreeMap<Far16Ptr, (u32, Option<Far16Ptr>)>,
+    active: BTreeSet<Far16Ptr>,
+    recursive: BTreeSet<Far16Ptr>,
+}
+
+impl CallGraph<'_> {
+    fn frame(&mut self, function: Far16Ptr) -> Frame {
+        if let Some(frame) = self.frames.get(&function) {
+            return frame.clone();
+        }
+        let ne = self.ne;
+        let segment = self.segments.entry(function.segment).or_insert_with(|| {
+            let is_code = ne
+                .segment(function.seg())
+                .is_some_and(|s| s.header.flags & 0x0001 == 0);
+            if !is_code {
+                return None;
+            }
+            let code = ne.fixed_segment(function.segment, |_| None).ok()?;
+            let places = ne
+                .fixup_places(function.segment)
+                .ok()?
+                .into_iter()
+                .map(|place| (place.offset, place.target))
+                .collect();
+            Some((code, places))
+        });
+        let frame = match segment {
+            Some((code, places)) => {
+                trace_frame(code, function.seg(), places, function.offset, self.symbols)
+            }
+            None => Frame::default(),
+        };
+        self.frames.insert(function, frame.clone());
+        frame
+    }
+
+    fn depth(&mut self, function: Far16Ptr, level: usize) -> u32 {
+        if let Some(&(depth, _)) = self.depths.get(&function) {
+            return depth;
+        }
+        if !self.active.insert(function) {
+            self.recursive.insert(function);
+            return 0;
+        }
+        let frame = self.frame(function);
+        let mut depth = (frame.local, None);
+        if level < MAX_CALL_DEPTH {
+            for &(used, return_size, callee) in &frame.calls {
+                let total = used + return_size + self.depth(callee, level + 1);
+                if total > depth.0 {
+                    depth = (total, Some(callee));
+                }
+            }
+        }
+        self.active.remove(&function);
+        self.depths.insert(function, depth);
+        depth.0
+    }
+
+    fn chain(&self, from: Far16Ptr) -> Vec<Far16Ptr> {
+        let mut chain = vec![from];
+        let mut next = self.depths.get(&from).and_then(|&(_, callee)| callee);
+        while let Some(function) = next {
+            if chain.contains(&function) {
+                break;
+            }
+            chain.push(function);
+            next = self.depths.get(&function).and_then(|&(_, callee)| callee);
+        }
+        chain
+    }
+}
+
+fn is_local_alloc(import: &str) -> bool {
+    let name = import.rsplit('.').next().unwrap_or_default();
+    name.eq_ignore_ascii_case("LocalAlloc") || name.eq_ignore_ascii_case("LocalReAlloc")
+}
+
+impl NeExecutable {
+    ///
+    /// Estimates stack depth by the call graph from `CS:IP` and exported
+    /// functions, finds local allocations, and checks `init_stack_size`
+    /// and `init_heap_size` against them and against 64K of the automatic
+    /// data segment. Indirect calls and calls of imported procedures
+    /// are not counted, so the estimate is the least the module needs.
+    ///
+    pub fn memory_adequacy(&self, symbols: &dyn SymbolProvider) -> MemoryAdequacy {
+        let header = &self.ne_header;
+        let stack_pointer = Far16Ptr::from(header.init_stack.value());
+        let stack_size = match header.init_stack_size.value() {
+            0 => stack_pointer.offset as u32,
+            size => size as u32,
+        };
+        let mut adequacy = MemoryAdequacy {
+            stack_size,
+            heap_size: header.init_heap_size.value(),
+            ..MemoryAdequacy::default()
+        };
+        let mut graph = CallGraph {
+            ne: self,
+            symbols,
+            segments: BTreeMap::new(),
+            frames: BTreeMap::new(),
+            depths: BTreeMap::new(),
+            active: BTreeSet::new(),
+            recursive: BTreeSet::new(),
+        };
+
+        let entry_point = Far16Ptr::from(header.entry_point.value());
+        let is_library = header.flags.value() & NE_LIBRARY != 0;
+        let mut roots = Vec::new();
+        if entry_point.segment != 0 {
+            roots.push(entry_point);
+        }
+        let exports: Vec<Far16Ptr> = self
+            .exports()
+            .iter()
+            .filter(|e| e.segment != 0 && e.segment != 0xFE)
+            .map(|e| Far16Ptr::new(e.segment as u16, e.offset))
+            .collect();
+        roots.extend(&exports);
+
+        let mut callback: Option<(u32, Far16Ptr)> = None;
+        for &export in &exports {
+            let depth = 4 + graph.depth(export, 0);
+            if callback.is_none_or(|(deepest, _)| depth > deepest) {
+                callback = Some((depth, export));
+            }
+        }
+        if entry_point.segment != 0 && !is_library {
+            let depth = graph.depth(entry_point, 0);
+            let stack = StackEstimate {
+                depth: depth + callback.map_or(0, |(depth, _)| depth),
+                chain: graph.chain(entry_point),
+                callback: callback.map(|(_, export)| export),
+                ..StackEstimate::default()
+            };
+            adequacy.stack = Some(stack);
+        }
+
+        // every reachable function was traced by computing the depths
+        let mut local_init = false;
+        for (function, frame) in &graph.frames {
+            for (offset, import, size) in &frame.imports {
+                let name = import.rsplit('.').next().unwrap_or_default();
+                local_init |= name.eq_ignore_ascii_case("LocalInit");
+                if is_local_alloc(import) {
+                    adequacy.local_allocs.push(LocalAllocCall {
+                        call: Far16Ptr::new(function.segment, *offset),
+                        import: import.clone(),
+                        size: *size,
+                    });
+                }
+            }
+        }
+        adequacy.local_allocs.sort_by_key(|a| a.call);
+        adequacy.local_allocs.dedup_by_key(|a| a.call);
+        let indirect_calls = graph.frames.values().map(|f| f.indirect_calls).sum();
+        if let Some(stack) = &mut adequacy.stack {
+            stack.recursive = graph.recursive.iter().copied().collect();
+            stack.indirect_calls = indirect_calls;
+        }
+
+        if let Some(stack) = &adequacy.stack {
+            if stack_size < stack.depth {
+                adequacy.issues.push(MemoryIssue::StackBelowEstimate {
+                    declared: stack_size,
+                    estimated: stack.depth,
+                });
+            }
+            if stack_size < MIN_WINDOWS_STACK {
+                adequacy.issues.push(MemoryIssue::StackBelowMinimum {
+                    declared: stack_size,
+                });
+            }
+            for &function in &stack.recursive {
+                adequacy.issues.push(MemoryIssue::Recursion { function });
+            }
+        }
+        let data_segment = Seg16(header.auto_data_segment_index.value());
+        if let Some(data) = self.segment(data_segment) {
+            let data = allocated_size(data);
+            let stack = header.init_stack_size.value();
+            let heap = header.init_heap_size.value();
+            if data + heap as u64 + stack as u64 > 0x10000 {
+                adequacy
+                    .issues
+                    .push(MemoryIssue::DgroupOverflow { data, heap, stack });
+            }
+        }
+        if !adequacy.local_allocs.is_empty() {
+            if adequacy.heap_size == 0 && !local_init {
+                adequacy.issues.push(MemoryIssue::LocalAllocWithoutHeap {
+                    calls: adequacy.local_allocs.len(),
+                });
+            }
+            let total: u32 = adequacy
+                .local_allocs
+                .iter()
+                .filter_map(|a| a.size)
+                .map(u32::from)
+                .sum();
+            if adequacy.heap_size != 0 && total > adequacy.heap_size as u32 {
+                adequacy.issues.push(MemoryIssue::AllocationsExceedHeap {
+                    total,
+                    heap: adequacy.heap_size,
+                });
+            }
+        }
+        adequacy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::imports::ImportTarget;
+    use crate::ne::symbols::OrdinalDatabase;
+
+    #[test]
+    fn counts_frame_and_calls() {
+        let code = [
+            0x55, // 0000: push bp
+            0x8B, 0xEC, // 0001: mov bp, sp
+            0x83, 0xEC, 0x20, // 0003: sub sp, 20h
+            0x6A, 0x40, // 0006: push 40h
+            0xB8, 0x00, 0x01, // 0008: mov ax, 100h
+            0x50, // 000B: push ax
+            0x9A, 0xFF, 0xFF, 0x00, 0x00, // 000C: call far KERNEL.LocalAlloc
+            0x74, 0x03, // 0011: jz 0016
+            0xE8, 0x05, 0x00, // 0013: call 001B
+            0x8B, 0xE5, // 0016: mov sp, bp
+            0x5D, // 0018: pop bp
+            0xCB, // 0019: retf
+            0x90, // 001A
+            0x50, // 001B: push ax
+            0x58, // 001C: pop ax
+            0xC3, // 001D: ret
+        ];
+        let places = BTreeMap::from([(
+            0x0D,
+            FixupTarget::Import {
+                module: "KERNEL".to_string(),
+                procedure: ImportTarget::Ordinal(5),
+            },
+        )]);
+        let frame = trace_frame(&code, Seg16(1), &places, 0, &OrdinalDatabase);
+        assert_eq!(frame.local, 0x26);
+        assert_eq!(frame.calls, [(0x22, 2, Far16Ptr::new(1, 0x1B))]);
+        assert_eq!(
+            frame.imports,
+            [(0x0C, "KERNEL.LocalAlloc".to_string(), Some(0x100))]
+        );
+        assert!(is_local_alloc(&frame.imports[0].1));
+
+        let callee = trace_frame(&code, Seg16(1), &places, 0x1B, &OrdinalDatabase);
+        assert_eq!(callee.local, 2);
+    }
+}
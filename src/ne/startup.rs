@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+
+use crate::ne::addr::{Far16Ptr, Seg16};
+use crate::ne::fixups::FixupTarget;
+use crate::ne::functions::relative_target;
+use crate::ne::imports::ImportTarget;
+use crate::ne::ordinal_db::lookup_ordinal;
+use crate::ne::NeExecutable;
+use crate::x86;
+
+/// Instructions of startup code followed from `CS:IP` before giving up
+pub const STARTUP_MAX_INSTRUCTIONS: usize = 512;
+
+///
+/// Call of `WinMain` found in startup code
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WinMainCall {
+    /// Offset of `CALL` in the segment of `CS:IP`
+    pub call: u16,
+    /// Segment number and offset of `WinMain`
+    pub target: Far16Ptr,
+    /// Five words were pushed right before the call, as startup code
+    /// passes `hInstance`, `hPrevInstance`, `lpCmdLine` and `nCmdShow`.
+    /// Otherwise this is just the first call after `INITAPP`.
+    pub arguments_pushed: bool,
+}
+
+///
+/// Startup code of a Win16 task: `CALL`s of `INITTASK`, `WAITEVENT`
+/// and `INITAPP` by offset in the segment of `CS:IP`, and of `WinMain`
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Startup {
+    pub segment: Seg16,
+    pub init_task: Option<u16>,
+    pub wait_event: Option<u16>,
+    pub init_app: Option<u16>,
+    pub win_main: Option<WinMainCall>,
+}
+
+impl Startup {
+    ///
+    /// Whole `INITTASK`, `WAITEVENT`, `INITAPP`, `WinMain` sequence was found
+    ///
+    pub fn is_canonical(&self) -> bool {
+        self.init_task.is_some()
+            && self.wait_event.is_some()
+            && self.init_app.is_some()
+            && self.win_main.is_some()
+    }
+}
+
+impl fmt::Display for Startup {
+    ///
+    /// `{}` gives `WinMain` address, `{:#}` lists every call
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.init_task, self.win_main) {
+            (None, _) => write!(f, "startup code doesn't call INITTASK")?,
+            (Some(_), None) => write!(f, "WinMain not found")?,
+            (Some(_), Some(win_main)) => write!(
+                f,
+                "WinMain at {}:{:04X}{}",
+                win_main.target.segment,
+                win_main.target.offset,
+                if self.is_canonical() {
+                    ""
+                } else {
+                    " (startup sequence incomplete)"
+                }
+            )?,
+        }
+        if !f.alternate() {
+            return Ok(());
+        }
+        for (name, call) in [
+            ("INITTASK", self.init_task),
+            ("WAITEVENT", self.wait_event),
+            ("INITAPP", self.init_app),
+        ] {
+            match call {
+                Some(offset) => write!(f, "\n\t{}:{:04X} call {}", self.segment, offset, name)?,
+                None => write!(f, "\n\t{} not called", name)?,
+            }
+        }
+        if let Some(win_main) = self.win_main {
+            write!(
+                f,
+                "\n\t{}:{:04X} call WinMain {}:{:04X}, {}",
+                self.segment,
+                win_main.call,
+                win_main.target.segment,
+                win_main.target.offset,
+                if win_main.arguments_pushed {
+                    "arguments pushed"
+                } else {
+                    "first call after INITAPP"
+                }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Name of imported procedure as `KNOWN_EXPORTS` give it
+///
+fn import_name<'a>(module: &str, procedure: &'a ImportTarget) -> Option<&'a str> {
+    match procedure {
+        ImportTarget::Ordinal(ordinal) => lookup_ordinal(module, *ordinal).map(|e| e.name),
+        ImportTarget::Name(name) => Some(name),
+    }
+}
+
+///
+/// Follows startup code of `code` from `start` straight on: unconditional
+/// jumps are taken, conditional ones fall through, as they lead to error exits.
+/// `WinMain` is the first internal call after `INITAPP` with five words pushed
+/// before it, or without such a call the first internal call after `INITAPP`.
+/// \param places -- targets of relocations by offset of the patched place
+///
+pub fn trace_startup(
+    code: &[u8],
+    segment: Seg16,
+    places: &BTreeMap<u16, FixupTarget>,
+    start: u16,
+) -> Startup {
+    let mut startup = Startup {
+        segment,
+        ..Startup::default()
+    };
+    let mut visited = vec![false; code.len()];
+    let mut pos = start as usize;
+    let mut pushes = 0;
+
+    for _ in 0..STARTUP_MAX_INSTRUCTIONS {
+        if pos >= code.len() || visited[pos] {
+            break;
+        }
+        visited[pos] = true;
+        let inst = x86::decode(code, pos, false);
+        if inst.is_invalid {
+            break;
+        }
+        let at = pos as u16;
+        pos = inst.end();
+
+        let target = match (inst.opcode, inst.opcode2) {
+            (0x06 | 0x0E | 0x16 | 0x1E | 0x50..=0x57 | 0x68 | 0x6A, None) => {
+                pushes += 1;
+                continue;
+            }
+            (0xFF, None) if inst.modrm.map(|m| (m >> 3) & 7) == Some(6) => {
+                pushes += 1;
+                continue;
+            }
+            (0xE9 | 0xEB, None) => {
+                match relative_target(&inst) {
+                    Some(target) => pos = target as usize,
+                    None => break,
+                }
+                continue;
+            }
+            (0xC2 | 0xC3 | 0xCA | 0xCB | 0xCF | 0xEA, None) => break,
+            (0xE8, None) => relative_target(&inst).map(|offset| Far16Ptr::new(segment.0, offset)),
+            (0x9A, None) => match places.get(&at.wrapping_add(1)) {
+                Some(FixupTarget::Import { module, procedure }) => {
+                    let slot = match import_name(module, procedure) {
+                        Some(name) if name.eq_ignore_ascii_case("InitTask") => {
+                            &mut startup.init_task
+                        }
+                        Some(name) if name.eq_ignore_ascii_case("WaitEvent") => {
+                            &mut startup.wait_event
+                        }
+                        Some(name) if name.eq_ignore_ascii_case("InitApp") => &mut startup.init_app,
+                        _ => {
+                            pushes = 0;
+                            continue;
+                        }
+                    };
+                    slot.get_or_insert(at);
+                    None
+                }
+                Some(FixupTarget::Internal { segment, offset }) => {
+                    Some(Far16Ptr::new(*segment as u16, *offset))
+                }
+                _ => None,
+            },
+            _ => continue,
+        };
+
+        if let (Some(target), Some(_)) = (target, startup.init_app) {
+            let call = WinMainCall {
+                call: at,
+                target,
+                arguments_pushed: pushes >= 5,
+            };
+            if call.arguments_pushed {
+                startup.win_main = Some(call);
+                break;
+            }
+            startup.win_main.get_or_insert(call);
+        }
+        pushes = 0;
+    }
+    startup
+}
+
+impl NeExecutable {
+    ///
+    /// Startup code at `CS:IP`: calls of `INITTASK`, `WAITEVENT`,
+    /// `INITAPP` and the `WinMain` they lead to.
+    /// `None` for modules without `CS:IP` (most libraries).
+    ///
+    pub fn startup(&self) -> io::Result<Option<Startup>> {
+        let entry_point = Far16Ptr::from(self.ne_header.entry_point.value());
+        if entry_point.segment == 0 {
+            return Ok(None);
+        }
+        let code = self.fixed_segment(entry_point.segment, |_| None)?;
+        let places = self
+            .fixup_places(entry_point.segment)?
+            .into_iter()
+            .map(|place| (place.offset, place.target))
+            .collect();
+        Ok(Some(trace_startup(
+            &code,
+            entry_point.seg(),
+            &places,
+            entry_point.offset,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_win_main_after_init_app() {
+        let code = [
+            0x33, 0xED, // 0000: xor bp, bp
+            0x55, // 0002: push bp
+            0x9A, 0xFF, 0xFF, 0x00, 0x00, // 0003: call far KERNEL.91
+            0x0B, 0xC0, // 0008: or ax, ax
+            0x74, 0x20, // 000A: jz error
+            0x9A, 0xFF, 0xFF, 0x00, 0x00, // 000C: call far KERNEL.30
+            0x57, // 0011: push di
+            0x9A, 0xFF, 0xFF, 0x00, 0x00, // 0012: call far USER.INITAPP
+            0xE8, 0x10, 0x00, // 0017: call 002A, _cinit
+            0x57, 0x56, 0x06, 0x53, 0x52, // 001A: push di, si, es, bx, dx
+            0x9A, 0x00, 0x00, 0x00, 0x00, // 001F: call far 0002:0040
+            0x50, // 0024: push ax
+        ];
+        let import = |module: &str, procedure| FixupTarget::Import {
+            module: module.to_string(),
+            procedure,
+        };
+        let places = BTreeMap::from([
+            (0x04, import("KERNEL", ImportTarget::Ordinal(91))),
+            (0x0D, import("KERNEL", ImportTarget::Ordinal(30))),
+            (
+                0x13,
+                import("USER", ImportTarget::Name("INITAPP".to_string())),
+            ),
+            (
+                0x20,
+                FixupTarget::Internal {
+                    segment: 2,
+                    offset: 0x40,
+                },
+            ),
+        ]);
+        let startup = trace_startup(&code, Seg16(1), &places, 0);
+        assert_eq!(
+            (startup.init_task, startup.wait_event, startup.init_app),
+            (Some(0x03), Some(0x0C), Some(0x12))
+        );
+        assert_eq!(
+            startup.win_main,
+            Some(WinMainCall {
+                call: 0x1F,
+                target: Far16Ptr::new(2, 0x40),
+                arguments_pushed: true,
+            })
+        );
+        assert!(startup.is_canonical());
+
+        // without pushed arguments the first call after INITAPP is taken
+        let startup = trace_startup(&code[..0x1A], Seg16(1), &places, 0);
+        assert_eq!(startup.win_main.unwrap().target, Far16Ptr::new(1, 0x2A));
+    }
+}
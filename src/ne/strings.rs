@@ -0,0 +1,164 @@
+use std::fmt;
+
+use crate::ne::NeExecutable;
+use crate::util::codepage::Codepage;
+
+///
+/// Where the string was found
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringLocation {
+    /// Segments are numbered from 1
+    Segment { segment: usize, offset: u16 },
+    /// File offset inside the overlay
+    Overlay { offset: u64 },
+}
+
+impl fmt::Display for StringLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StringLocation::Segment { segment, offset } => write!(f, "{}:{:04X}", segment, offset),
+            StringLocation::Overlay { offset } => write!(f, "overlay:{:08X}", offset),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundString {
+    pub location: StringLocation,
+    pub text: String,
+    /// Count of bytes occupied on file
+    pub length: usize,
+    /// String has no characters decoded from upper half of code page
+    pub is_ascii: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StringsOptions {
+    /// Shorter runs of printable characters are ignored
+    pub min_length: usize,
+    /// Code page to decode bytes 0x80..0xFF with; only ASCII is taken without it
+    pub codepage: Option<Codepage>,
+    pub scan_overlay: bool,
+}
+
+impl Default for StringsOptions {
+    fn default() -> Self {
+        Self {
+            min_length: 4,
+            codepage: None,
+            scan_overlay: true,
+        }
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Scans segments and overlay for runs of printable characters
+    /// like `strings` does, but remembers segment:offset of every run.
+    ///
+    pub fn strings(&self, options: &StringsOptions) -> Vec<FoundString> {
+        let mut found = Vec::new();
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            if let Some(data) = &segment.data {
+                for (start, length, text) in scan(data, options) {
+                    found.push(FoundString {
+                        location: StringLocation::Segment {
+                            segment: i + 1,
+                            offset: start as u16,
+                        },
+                        is_ascii: text.is_ascii(),
+                        text,
+                        length,
+                    });
+                }
+            }
+        }
+        if let (Some(overlay), true) = (&self.overlay, options.scan_overlay) {
+            for (start, length, text) in scan(&overlay.data, options) {
+                found.push(FoundString {
+                    location: StringLocation::Overlay {
+                        offset: overlay.offset + start as u64,
+                    },
+                    is_ascii: text.is_ascii(),
+                    text,
+                    length,
+                });
+            }
+        }
+        found
+    }
+}
+
+fn printable(b: u8, codepage: Option<Codepage>) -> Option<char> {
+    match b {
+        b'\t' | 0x20..=0x7E => Some(b as char),
+        0x80..=0xFF => {
+            let c = codepage?.decode_byte(b);
+            // box drawing, letters and punctuation; not undefined or control ones
+            if c == '\u{FFFD}' || c.is_control() {
+                None
+            } else {
+                Some(c)
+            }
+        }
+        _ => None,
+    }
+}
+
+///
+/// Finds runs of printable characters: (offset, length in bytes, decoded text)
+///
+pub(crate) fn scan(data: &[u8], options: &StringsOptions) -> Vec<(usize, usize, String)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut text = String::new();
+
+    for (i, &b) in data.iter().enumerate() {
+        match printable(b, options.codepage) {
+            Some(c) => {
+                if text.is_empty() {
+                    start = i;
+                }
+                text.push(c);
+            }
+            None => {
+                if i - start >= options.min_length && !text.is_empty() {
+                    runs.push((start, i - start, std::mem::take(&mut text)));
+                }
+                text.clear();
+            }
+        }
+    }
+    if data.len() - start >= options.min_length && !text.is_empty() {
+        runs.push((start, data.len() - start, text));
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_runs_with_offsets() {
+        let data = b"\x00\x01Hello\x00ab\x00World!";
+        let runs = scan(data, &StringsOptions::default());
+        assert_eq!(
+            runs,
+            vec![(2, 5, "Hello".to_string()), (11, 6, "World!".to_string())]
+        );
+    }
+
+    #[test]
+    fn decodes_upper_half_with_codepage() {
+        let data = b"\x00\xCF\xF0\xE8\xE2\xE5\xF2\x00";
+        assert!(scan(data, &StringsOptions::default()).is_empty());
+
+        let options = StringsOptions {
+            codepage: Some(Codepage::Windows1251),
+            ..Default::default()
+        };
+        assert_eq!(scan(data, &options), vec![(1, 6, "Привет".to_string())]);
+    }
+}
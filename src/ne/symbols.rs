@@ -0,0 +1,561 @@
+use std::io;
+
+use crate::ne::addr::Far16Ptr;
+use crate::ne::ordinal_db::{lookup_name, lookup_ordinal};
+use crate::ne::NeExecutable;
+
+/// Segment of entry table constants: they are not placed in memory
+const CONSTANT_SEGMENT: u8 = 0xFE;
+
+/// CodeView 3.x publics: `offset, segment, type, name`
+const SST_PUBLIC: u16 = 0x0102;
+/// CodeView 4.x publics given as symbol records
+const SST_PUBLIC_SYM: u16 = 0x011A;
+/// Public symbol record of 16-bit CodeView 4.x
+const S_PUB16: u16 = 0x0103;
+
+///
+/// Name placed at `segment:offset` of the analyzed module.
+/// Segments are numbers from 1, as in the segment table.
+///
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Symbol {
+    pub segment: u16,
+    pub offset: u16,
+    pub name: String,
+}
+
+///
+/// Source of names for the disassembler, imports and symbolication.
+/// Every lookup is optional, so a provider answers only what it knows.
+///
+pub trait SymbolProvider {
+    ///
+    /// Name of procedure exported by `module` with `ordinal`
+    ///
+    fn name_by_ordinal(&self, _module: &str, _ordinal: u16) -> Option<String> {
+        None
+    }
+
+    ///
+    /// Ordinal of procedure exported by `module` with `name`
+    ///
+    fn ordinal_by_name(&self, _module: &str, _name: &str) -> Option<u16> {
+        None
+    }
+
+    ///
+    /// Nearest symbol of analyzed module at or before `segment:offset`
+    ///
+    fn symbol_at(&self, _segment: u16, _offset: u16) -> Option<Symbol> {
+        None
+    }
+
+    ///
+    /// `NAME` or `NAME+0xN` of `segment:offset`, `SSSS:OOOO` without symbol
+    ///
+    fn symbolicate(&self, segment: u16, offset: u16) -> String {
+        match self.symbol_at(segment, offset) {
+            Some(s) if s.offset == offset => s.name,
+            Some(s) => format!("{}+{:#X}", s.name, offset - s.offset),
+            None => Far16Ptr::new(segment, offset).to_string(),
+        }
+    }
+}
+
+///
+/// Symbol of sorted `symbols` nearest at or before `segment:offset`
+///
+fn nearest(symbols: &[Symbol], segment: u16, offset: u16) -> Option<Symbol> {
+    let end = symbols.partition_point(|s| (s.segment, s.offset) <= (segment, offset));
+    symbols[..end]
+        .last()
+        .filter(|s| s.segment == segment)
+        .cloned()
+}
+
+///
+/// Procedures of system modules of the built-in ordinal database
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OrdinalDatabase;
+
+impl SymbolProvider for OrdinalDatabase {
+    fn name_by_ordinal(&self, module: &str, ordinal: u16) -> Option<String> {
+        lookup_ordinal(module, ordinal).map(|e| e.name.to_string())
+    }
+
+    fn ordinal_by_name(&self, module: &str, name: &str) -> Option<u16> {
+        lookup_name(module, name).map(|e| e.ordinal)
+    }
+}
+
+///
+/// Named entry points of the analyzed module itself
+///
+#[derive(Debug, Clone, Default)]
+pub struct ExportedNames {
+    pub module: String,
+    symbols: Vec<Symbol>,
+    ordinals: Vec<(u16, String)>,
+}
+
+impl ExportedNames {
+    pub fn new(ne: &NeExecutable) -> Self {
+        let mut names = Self {
+            module: ne.module_name().unwrap_or_default(),
+            ..Self::default()
+        };
+        for export in ne.exports() {
+            let Some(name) = &export.name else {
+                continue;
+            };
+            if export.segment != CONSTANT_SEGMENT {
+                names.symbols.push(Symbol {
+                    segment: export.segment as u16,
+                    offset: export.offset,
+                    name: name.to_string(),
+                });
+            }
+            names.ordinals.push((export.ordinal, name.to_string()));
+        }
+        names.symbols.sort();
+        names
+    }
+}
+
+impl SymbolProvider for ExportedNames {
+    fn name_by_ordinal(&self, module: &str, ordinal: u16) -> Option<String> {
+        if !self.module.eq_ignore_ascii_case(module) {
+            return None;
+        }
+        self.ordinals
+            .iter()
+            .find(|(o, _)| *o == ordinal)
+            .map(|(_, name)| name.clone())
+    }
+
+    fn ordinal_by_name(&self, module: &str, name: &str) -> Option<u16> {
+        if !self.module.eq_ignore_ascii_case(module) {
+            return None;
+        }
+        self.ordinals
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(name))
+            .map(|(o, _)| *o)
+    }
+
+    fn symbol_at(&self, segment: u16, offset: u16) -> Option<Symbol> {
+        nearest(&self.symbols, segment, offset)
+    }
+}
+
+///
+/// Publics of `.MAP` file written by Microsoft LINK:
+///
+/// ```text
+///   Address         Publics by Value
+///
+///  0001:0010       _WinMain
+///  0000:0000  Imp  MESSAGEBOX           (USER.1)
+/// ```
+///
+/// `Imp` lines give names of imported ordinals,
+/// `Abs` and `Unr` (absolute and unresolved) lines are skipped.
+///
+#[derive(Debug, Clone, Default)]
+pub struct MapFile {
+    pub symbols: Vec<Symbol>,
+    /// Module, ordinal and name of every import by ordinal
+    pub imports: Vec<(String, u16, String)>,
+}
+
+impl MapFile {
+    pub fn parse(text: &str) -> Self {
+        let mut map = Self::default();
+        let mut in_publics = false;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with("Address") {
+                in_publics = line.contains("Publics by");
+                continue;
+            }
+            if line.starts_with("Program entry point") {
+                in_publics = false;
+            }
+            if !in_publics {
+                continue;
+            }
+            let mut tokens = line.split_whitespace();
+            let Some((segment, offset)) = tokens.next().and_then(parse_address) else {
+                continue;
+            };
+            let (name, import) = match tokens.next() {
+                Some("Abs" | "Unr") | None => continue,
+                Some("Imp") => (tokens.next(), tokens.next()),
+                Some(name) => (Some(name), None),
+            };
+            let Some(name) = name else {
+                continue;
+            };
+            match import.and_then(parse_import) {
+                Some((module, ordinal)) => {
+                    map.imports
+                        .push((module.to_string(), ordinal, name.to_string()))
+                }
+                None if import.is_none() => map.symbols.push(Symbol {
+                    segment,
+                    offset,
+                    name: name.to_string(),
+                }),
+                None => (),
+            }
+        }
+        // both "by Name" and "by Value" lists are usually present
+        map.symbols.sort();
+        map.symbols.dedup();
+        map.imports.sort();
+        map.imports.dedup();
+        map
+    }
+}
+
+///
+/// `SSSS:OOOO` of hexadecimal numbers
+///
+fn parse_address(s: &str) -> Option<(u16, u16)> {
+    let (segment, offset) = s.split_once(':')?;
+    Some((
+        u16::from_str_radix(segment, 16).ok()?,
+        u16::from_str_radix(offset, 16).ok()?,
+    ))
+}
+
+///
+/// `(MODULE.ordinal)`; imports by name have no ordinal
+///
+fn parse_import(s: &str) -> Option<(&str, u16)> {
+    let (module, ordinal) = s.strip_prefix('(')?.strip_suffix(')')?.rsplit_once('.')?;
+    Some((module, ordinal.parse().ok()?))
+}
+
+impl SymbolProvider for MapFile {
+    fn name_by_ordinal(&self, module: &str, ordinal: u16) -> Option<String> {
+        self.imports
+            .iter()
+            .find(|(m, o, _)| *o == ordinal && m.eq_ignore_ascii_case(module))
+            .map(|(.., name)| name.clone())
+    }
+
+    fn ordinal_by_name(&self, module: &str, name: &str) -> Option<u16> {
+        self.imports
+            .iter()
+            .find(|(m, _, n)| n.eq_ignore_ascii_case(name) && m.eq_ignore_ascii_case(module))
+            .map(|&(_, o, _)| o)
+    }
+
+    fn symbol_at(&self, segment: u16, offset: u16) -> Option<Symbol> {
+        nearest(&self.symbols, segment, offset)
+    }
+}
+
+///
+/// Publics of CodeView debug information appended to the file.
+///
+/// The last 8 bytes are `NBxx` signature and distance from the end
+/// of file back to the debug information header (the same signature
+/// and offset of subsection directory). NB00-NB04 (CodeView 3.x)
+/// keep publics in `sstPublic` subsections, NB05 and later
+/// in `sstPublicSym` of `S_PUB16` records.
+///
+#[derive(Debug, Clone, Default)]
+pub struct CodeViewSymbols {
+    /// `NB00`, `NB05`, `NB09`, ...
+    pub signature: String,
+    pub symbols: Vec<Symbol>,
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    let b = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    let b = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("CodeView: {}", message))
+}
+
+impl CodeViewSymbols {
+    ///
+    /// Reads publics from data ending with CodeView trailer.
+    /// `None` if there is no trailer.
+    /// \param data -- file or its overlay (debug information is its tail)
+    ///
+    pub fn read(data: &[u8]) -> io::Result<Option<Self>> {
+        let Some(trailer) = data.len().checked_sub(8) else {
+            return Ok(None);
+        };
+        let signature = &data[trailer..trailer + 4];
+        if &signature[..2] != b"NB" || !signature[2..].iter().all(u8::is_ascii_digit) {
+            return Ok(None);
+        }
+        let version = (signature[2] - b'0') * 10 + (signature[3] - b'0');
+        let distance = u32_at(data, trailer + 4).unwrap() as usize;
+        let base = data
+            .len()
+            .checked_sub(distance)
+            .filter(|&base| data.get(base..base + 4) == Some(signature))
+            .ok_or_else(|| invalid("trailer points outside of data"))?;
+        let directory = base + u32_at(data, base + 4).ok_or_else(|| invalid("no header"))? as usize;
+
+        // type, position (from `base`) and size of every subsection
+        let mut subsections = Vec::new();
+        if version < 5 {
+            let count = u16_at(data, directory).ok_or_else(|| invalid("no directory"))?;
+            for i in 0..count as usize {
+                let entry = directory + 2 + i * 10;
+                let kind = u16_at(data, entry).ok_or_else(|| invalid("directory truncated"))?;
+                let lfo = u32_at(data, entry + 4).ok_or_else(|| invalid("directory truncated"))?;
+                let size = u16_at(data, entry + 8).ok_or_else(|| invalid("directory truncated"))?;
+                subsections.push((kind, lfo as usize, size as usize));
+            }
+        } else {
+            let header_size = u16_at(data, directory).ok_or_else(|| invalid("no directory"))?;
+            let entry_size = u16_at(data, directory + 2).ok_or_else(|| invalid("no directory"))?;
+            let count = u32_at(data, directory + 4).ok_or_else(|| invalid("no directory"))?;
+            for i in 0..count as usize {
+                let entry = directory + header_size as usize + i * entry_size as usize;
+                let kind = u16_at(data, entry).ok_or_else(|| invalid("directory truncated"))?;
+                let lfo = u32_at(data, entry + 4).ok_or_else(|| invalid("directory truncated"))?;
+                let size = u32_at(data, entry + 8).ok_or_else(|| invalid("directory truncated"))?;
+                subsections.push((kind, lfo as usize, size as usize));
+            }
+        }
+
+        let mut symbols = Vec::new();
+        for (kind, lfo, size) in subsections {
+            let start = base + lfo;
+            let content = data
+                .get(start..start + size)
+                .ok_or_else(|| invalid("subsection outside of data"))?;
+            match kind {
+                SST_PUBLIC if version < 5 => read_publics(content, &mut symbols),
+                SST_PUBLIC_SYM => read_public_records(content, &mut symbols),
+                _ => (),
+            }
+        }
+        symbols.sort();
+        symbols.dedup();
+        Ok(Some(Self {
+            signature: String::from_utf8_lossy(signature).into_owned(),
+            symbols,
+        }))
+    }
+}
+
+///
+/// Length-prefixed name at `offset`
+///
+fn pascal_name(data: &[u8], offset: usize) -> Option<String> {
+    let length = *data.get(offset)? as usize;
+    let name = data.get(offset + 1..offset + 1 + length)?;
+    Some(String::from_utf8_lossy(name).into_owned())
+}
+
+///
+/// `sstPublic` of CodeView 3.x: `offset, segment, type index, name`
+///
+fn read_publics(content: &[u8], symbols: &mut Vec<Symbol>) {
+    let mut pos = 0;
+    while let (Some(offset), Some(segment), Some(name)) = (
+        u16_at(content, pos),
+        u16_at(content, pos + 2),
+        pascal_name(content, pos + 6),
+    ) {
+        pos += 7 + name.len();
+        symbols.push(Symbol {
+            segment,
+            offset,
+            name,
+        });
+    }
+}
+
+///
+/// `sstPublicSym` of CodeView 4.x: records of `length, type, ...`;
+/// only `S_PUB16` ones are taken, as NE modules are 16-bit
+///
+fn read_public_records(content: &[u8], symbols: &mut Vec<Symbol>) {
+    // NB09 tables begin with the signature of symbol format
+    let mut pos = match u32_at(content, 0) {
+        Some(1..=3) => 4,
+        _ => 0,
+    };
+    while let (Some(length), Some(kind)) = (u16_at(content, pos), u16_at(content, pos + 2)) {
+        if kind == S_PUB16 {
+            if let (Some(offset), Some(segment), Some(name)) = (
+                u16_at(content, pos + 4),
+                u16_at(content, pos + 6),
+                pascal_name(content, pos + 10),
+            ) {
+                symbols.push(Symbol {
+                    segment,
+                    offset,
+                    name,
+                });
+            }
+        }
+        if length == 0 {
+            break;
+        }
+        pos += 2 + length as usize;
+    }
+}
+
+impl SymbolProvider for CodeViewSymbols {
+    fn symbol_at(&self, segment: u16, offset: u16) -> Option<Symbol> {
+        nearest(&self.symbols, segment, offset)
+    }
+}
+
+///
+/// Providers consulted in order: names come from the first one
+/// which knows them, symbols are the nearest found by any of them
+///
+#[derive(Default)]
+pub struct SymbolProviders {
+    providers: Vec<Box<dyn SymbolProvider>>,
+}
+
+impl SymbolProviders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Built-in providers of the module: CodeView publics of its overlay,
+    /// its own export names and the ordinal database
+    ///
+    pub fn for_module(ne: &NeExecutable) -> io::Result<Self> {
+        let mut providers = Self::new();
+        if let Some(overlay) = &ne.overlay {
+            if let Some(codeview) = CodeViewSymbols::read(&overlay.data)? {
+                providers.push(codeview);
+            }
+        }
+        providers.push(ExportedNames::new(ne));
+        providers.push(OrdinalDatabase);
+        Ok(providers)
+    }
+
+    pub fn push(&mut self, provider: impl SymbolProvider + 'static) {
+        self.providers.push(Box::new(provider));
+    }
+
+    ///
+    /// Consults `provider` before the ones already added
+    ///
+    pub fn push_front(&mut self, provider: impl SymbolProvider + 'static) {
+        self.providers.insert(0, Box::new(provider));
+    }
+}
+
+impl SymbolProvider for SymbolProviders {
+    fn name_by_ordinal(&self, module: &str, ordinal: u16) -> Option<String> {
+        self.providers
+            .iter()
+            .find_map(|p| p.name_by_ordinal(module, ordinal))
+    }
+
+    fn ordinal_by_name(&self, module: &str, name: &str) -> Option<u16> {
+        self.providers
+            .iter()
+            .find_map(|p| p.ordinal_by_name(module, name))
+    }
+
+    fn symbol_at(&self, segment: u16, offset: u16) -> Option<Symbol> {
+        // `max_by_key` keeps the last of equal ones, so reverse for the first
+        self.providers
+            .iter()
+            .rev()
+            .filter_map(|p| p.symbol_at(segment, offset))
+            .max_by_key(|s| s.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = "
+ Start     Length     Name                   Class
+ 0001:0000 00120H     _TEXT                  CODE
+
+  Address         Publics by Value
+
+ 0000:0000  Unr  __acrtused
+ 0001:0010       _WinMain
+ 0001:0080       _WndProc
+ 0001:0000  Imp  MESSAGEBOX           (USER.1)
+ 0001:0000  Imp  LSTRLEN              (KERNEL.LSTRLEN)
+
+Program entry point at 0001:0000
+";
+
+    #[test]
+    fn map_file_gives_symbols_and_imports() {
+        let map = MapFile::parse(MAP);
+        assert_eq!(map.symbols.len(), 2);
+        assert_eq!(
+            map.name_by_ordinal("user", 1).as_deref(),
+            Some("MESSAGEBOX")
+        );
+        assert_eq!(map.ordinal_by_name("USER", "MessageBox"), Some(1));
+        assert_eq!(map.symbolicate(1, 0x10), "_WinMain");
+        assert_eq!(map.symbolicate(1, 0x90), "_WndProc+0x10");
+        assert_eq!(map.symbolicate(1, 0x08), "0001:0008");
+
+        let mut providers = SymbolProviders::new();
+        providers.push(OrdinalDatabase);
+        providers.push_front(map);
+        assert_eq!(
+            providers.name_by_ordinal("USER", 1).as_deref(),
+            Some("MESSAGEBOX")
+        );
+        assert_eq!(
+            providers.name_by_ordinal("USER", 5).as_deref(),
+            Some("InitApp")
+        );
+    }
+
+    #[test]
+    fn codeview_publics_are_read_from_trailer() {
+        let mut publics = Vec::new();
+        publics.extend_from_slice(&[0x34, 0x12, 0x02, 0x00, 0x00, 0x00, 4]);
+        publics.extend_from_slice(b"MAIN");
+
+        let mut data = b"junk".to_vec();
+        let base = data.len();
+        data.extend_from_slice(b"NB02");
+        data.extend_from_slice(&8u32.to_le_bytes());
+        // directory of one subsection right after it
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&SST_PUBLIC.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&20u32.to_le_bytes());
+        data.extend_from_slice(&(publics.len() as u16).to_le_bytes());
+        data.extend_from_slice(&publics);
+        let distance = (data.len() + 8 - base) as u32;
+        data.extend_from_slice(b"NB02");
+        data.extend_from_slice(&distance.to_le_bytes());
+
+        let codeview = CodeViewSymbols::read(&data).unwrap().unwrap();
+        assert_eq!(codeview.signature, "NB02");
+        assert_eq!(codeview.symbolicate(2, 0x1236), "MAIN+0x2");
+        assert!(CodeViewSymbols::read(b"no debug info").unwrap().is_none());
+    }
+}
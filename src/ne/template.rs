@@ -0,0 +1,467 @@
+use std::str::FromStr;
+
+use crate::ne::resource_table::type_name;
+use crate::ne::segment_table::SEG_RELOCINFO;
+use crate::ne::NeExecutable;
+
+///
+/// Binary template languages of hex editors and parser generators
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateKind {
+    /// Kaitai Struct `.ksy` (YAML)
+    Kaitai,
+    /// 010 Editor binary template `.bt`
+    Bt,
+}
+
+impl FromStr for TemplateKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "kaitai" | "ksy" => Ok(TemplateKind::Kaitai),
+            "010" | "bt" => Ok(TemplateKind::Bt),
+            _ => Err(format!("unknown template kind `{}`", s)),
+        }
+    }
+}
+
+/// Field of a structure: name, size of one item (1, 2 or 4) and number of items
+type Field = (&'static str, u8, usize);
+
+const DOS_HEADER: &[Field] = &[
+    ("magic", 2, 1),
+    ("cblp", 2, 1),
+    ("cp", 2, 1),
+    ("crlc", 2, 1),
+    ("cparhdr", 2, 1),
+    ("minalloc", 2, 1),
+    ("maxalloc", 2, 1),
+    ("ss", 2, 1),
+    ("sp", 2, 1),
+    ("csum", 2, 1),
+    ("ip", 2, 1),
+    ("cs", 2, 1),
+    ("lfarlc", 2, 1),
+    ("ovno", 2, 1),
+    ("res", 2, 4),
+    ("oemid", 2, 1),
+    ("oeminfo", 2, 1),
+    ("res2", 2, 10),
+    ("lfanew", 4, 1),
+];
+
+const NE_HEADER: &[Field] = &[
+    ("magic", 1, 2),
+    ("major_linker_version", 1, 1),
+    ("minor_linker_version", 1, 1),
+    ("entry_table_offset", 2, 1),
+    ("entry_table_length", 2, 1),
+    ("file_load_crc", 4, 1),
+    ("flags", 2, 1),
+    ("auto_data_segment_index", 2, 1),
+    ("init_heap_size", 2, 1),
+    ("init_stack_size", 2, 1),
+    ("entry_point", 4, 1),
+    ("init_stack", 4, 1),
+    ("segment_count", 2, 1),
+    ("module_references", 2, 1),
+    ("non_resident_names_size", 2, 1),
+    ("segment_table_offset", 2, 1),
+    ("resource_table_offset", 2, 1),
+    ("resident_names_table_offset", 2, 1),
+    ("module_reference_table_offset", 2, 1),
+    ("import_name_table_offset", 2, 1),
+    ("non_resident_names_table_offset", 4, 1),
+    ("movable_entry_point_count", 2, 1),
+    ("file_alignment_shift_count", 2, 1),
+    ("resource_table_entries", 2, 1),
+    ("target_os", 1, 1),
+    ("os2_exe_flags", 1, 1),
+    ("return_thunk_offset", 2, 1),
+    ("segment_reference_thunk_offset", 2, 1),
+    ("min_code_swap", 2, 1),
+    ("expected_win_ver", 1, 2),
+];
+
+const SEGMENT_ENTRY: &[Field] = &[
+    ("data_offset_shifted", 2, 1),
+    ("data_length", 2, 1),
+    ("flags", 2, 1),
+    ("min_alloc", 2, 1),
+];
+
+const RELOCATION_ENTRY: &[Field] = &[
+    ("address_type", 1, 1),
+    ("reloc_type", 1, 1),
+    ("segment_offset", 2, 1),
+    ("target1", 2, 1),
+    ("target2", 2, 1),
+];
+
+const STRUCTS: &[(&str, &[Field])] = &[
+    ("dos_header", DOS_HEADER),
+    ("ne_header", NE_HEADER),
+    ("segment_entry", SEGMENT_ENTRY),
+    ("relocation_entry", RELOCATION_ENTRY),
+];
+
+///
+/// Part of the file placed by the template
+///
+enum Region {
+    /// `count` structures of `STRUCTS`
+    Struct {
+        name: String,
+        offset: u64,
+        type_name: &'static str,
+        count: usize,
+    },
+    /// Relocation records of segment: count word and its entries
+    Relocations { name: String, offset: u64 },
+    Bytes {
+        name: String,
+        offset: u64,
+        length: u64,
+    },
+}
+
+impl Region {
+    fn name(&self) -> &str {
+        match self {
+            Region::Struct { name, .. }
+            | Region::Relocations { name, .. }
+            | Region::Bytes { name, .. } => name,
+        }
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Generates template placing every structure of this file at
+    /// its computed offset: headers, tables, segment data with
+    /// relocation records, resources and overlay.
+    /// Tables of names have no length in the header, so each one
+    /// covers bytes up to the next table.
+    ///
+    pub fn layout_template(&self, kind: TemplateKind) -> String {
+        let regions = self.template_regions();
+        let title = self.module_name().unwrap_or_else(|| "module".to_string());
+        match kind {
+            TemplateKind::Kaitai => kaitai_template(&title, &regions),
+            TemplateKind::Bt => bt_template(&title, &regions),
+        }
+    }
+
+    fn template_regions(&self) -> Vec<Region> {
+        let h = &self.ne_header;
+        let lfanew = self.dos_header.lfanew.value() as u64;
+        let relative = |offset: u16| lfanew + offset as u64;
+        let span = |from: u16, to: u16| to.saturating_sub(from) as u64;
+
+        let mut regions = vec![Region::Struct {
+            name: "dos_header".to_string(),
+            offset: 0,
+            type_name: "dos_header",
+            count: 1,
+        }];
+        if lfanew != 0 {
+            regions.push(Region::Struct {
+                name: "ne_header".to_string(),
+                offset: lfanew,
+                type_name: "ne_header",
+                count: 1,
+            });
+        }
+        regions.push(Region::Struct {
+            name: "segment_table".to_string(),
+            offset: relative(h.segment_table_offset.value()),
+            type_name: "segment_entry",
+            count: h.segment_count.value() as usize,
+        });
+        let tables = [
+            (
+                "resource_table",
+                h.resource_table_offset.value(),
+                span(
+                    h.resource_table_offset.value(),
+                    h.resident_names_table_offset.value(),
+                ),
+            ),
+            (
+                "resident_names",
+                h.resident_names_table_offset.value(),
+                span(
+                    h.resident_names_table_offset.value(),
+                    h.module_reference_table_offset.value(),
+                ),
+            ),
+            (
+                "module_references",
+                h.module_reference_table_offset.value(),
+                2 * h.module_references.value() as u64,
+            ),
+            (
+                "imported_names",
+                h.import_name_table_offset.value(),
+                span(
+                    h.import_name_table_offset.value(),
+                    h.entry_table_offset.value(),
+                ),
+            ),
+            (
+                "entry_table",
+                h.entry_table_offset.value(),
+                h.entry_table_length.value() as u64,
+            ),
+        ];
+        for (name, offset, length) in tables {
+            if length != 0 {
+                regions.push(Region::Bytes {
+                    name: name.to_string(),
+                    offset: relative(offset),
+                    length,
+                });
+            }
+        }
+        if h.non_resident_names_size.value() != 0 {
+            regions.push(Region::Bytes {
+                name: "nonresident_names".to_string(),
+                offset: h.non_resident_names_table_offset.value() as u64,
+                length: h.non_resident_names_size.value() as u64,
+            });
+        }
+
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            if segment.header.data_offset_shifted == 0 {
+                continue;
+            }
+            regions.push(Region::Bytes {
+                name: format!("segment_{}", i + 1),
+                offset: segment.data_offset(),
+                length: segment.data_length(),
+            });
+            if segment.header.flags & SEG_RELOCINFO != 0 {
+                regions.push(Region::Relocations {
+                    name: format!("relocations_{}", i + 1),
+                    offset: segment.data_offset() + segment.data_length(),
+                });
+            }
+        }
+
+        let shift_count = self.resource_table.header.alignment_shift_count;
+        for resource_type in &self.resource_table.resource_types {
+            let type_id = sanitize(&type_name(&resource_type.id()));
+            for resource in &resource_type.resources {
+                if resource.header.data_offset_shifted == 0 {
+                    continue;
+                }
+                regions.push(Region::Bytes {
+                    name: format!(
+                        "resource_{}_{}",
+                        type_id,
+                        sanitize(&resource.id().to_string())
+                    ),
+                    offset: resource.data_offset(shift_count),
+                    length: resource.data_length(shift_count),
+                });
+            }
+        }
+
+        if let Some(overlay) = &self.overlay {
+            regions.push(Region::Bytes {
+                name: "overlay".to_string(),
+                offset: overlay.offset,
+                length: overlay.data.len() as u64,
+            });
+        }
+        deduplicate_names(&mut regions);
+        regions
+    }
+}
+
+///
+/// Lowercase letters, digits and underscores of `s`
+///
+fn sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+///
+/// Identifier accepted by both languages
+///
+fn identifier(s: &str) -> String {
+    let id = sanitize(s);
+    if id.starts_with(|c: char| c.is_ascii_lowercase()) {
+        id
+    } else {
+        format!("m_{}", id)
+    }
+}
+
+fn deduplicate_names(regions: &mut [Region]) {
+    let mut seen: Vec<String> = Vec::new();
+    for region in regions.iter_mut() {
+        let base = region.name().to_string();
+        let mut name = base.clone();
+        let mut n = 2;
+        while seen.contains(&name) {
+            name = format!("{}_{}", base, n);
+            n += 1;
+        }
+        match region {
+            Region::Struct { name: n, .. }
+            | Region::Relocations { name: n, .. }
+            | Region::Bytes { name: n, .. } => *n = name.clone(),
+        }
+        seen.push(name);
+    }
+}
+
+fn kaitai_template(title: &str, regions: &[Region]) -> String {
+    let mut out = format!(
+        "# Generated by win16ne from {}\nmeta:\n  id: {}\n  endian: le\ntypes:\n",
+        title,
+        identifier(title)
+    );
+    for (name, fields) in STRUCTS {
+        out += &format!("  {}:\n    seq:\n", name);
+        for &(field, size, count) in *fields {
+            out += &format!("      - id: {}\n        type: u{}\n", field, size);
+            if count > 1 {
+                out += &format!("        repeat: expr\n        repeat-expr: {}\n", count);
+            }
+        }
+    }
+    out += "  relocation_table:\n    seq:\n      - id: count\n        type: u2\n      \
+            - id: entries\n        type: relocation_entry\n        repeat: expr\n        \
+            repeat-expr: count\n";
+
+    out += "instances:\n";
+    for region in regions {
+        match region {
+            Region::Struct {
+                name,
+                offset,
+                type_name,
+                count,
+            } => {
+                out += &format!(
+                    "  {}:\n    pos: 0x{:X}\n    type: {}\n",
+                    name, offset, type_name
+                );
+                if *count != 1 {
+                    out += &format!("    repeat: expr\n    repeat-expr: {}\n", count);
+                }
+            }
+            Region::Relocations { name, offset } => {
+                out += &format!(
+                    "  {}:\n    pos: 0x{:X}\n    type: relocation_table\n",
+                    name, offset
+                );
+            }
+            Region::Bytes {
+                name,
+                offset,
+                length,
+            } => {
+                out += &format!(
+                    "  {}:\n    pos: 0x{:X}\n    size: 0x{:X}\n",
+                    name, offset, length
+                );
+            }
+        }
+    }
+    out
+}
+
+fn bt_template(title: &str, regions: &[Region]) -> String {
+    let mut out = format!(
+        "// Generated by win16ne from {}\nLittleEndian();\n\n",
+        title
+    );
+    for (name, fields) in STRUCTS {
+        out += "typedef struct {\n";
+        for &(field, size, count) in *fields {
+            let ty = match size {
+                1 => "UCHAR",
+                2 => "WORD",
+                _ => "DWORD",
+            };
+            if count > 1 {
+                out += &format!("    {} {}[{}];\n", ty, field, count);
+            } else {
+                out += &format!("    {} {};\n", ty, field);
+            }
+        }
+        out += &format!("}} {};\n\n", name.to_ascii_uppercase());
+    }
+    out += "typedef struct {\n    WORD count;\n    \
+            if (count) RELOCATION_ENTRY entries[count];\n} RELOCATION_TABLE;\n\n";
+
+    for region in regions {
+        match region {
+            Region::Struct {
+                name,
+                offset,
+                type_name,
+                count,
+            } => {
+                let ty = type_name.to_ascii_uppercase();
+                out += &format!("FSeek(0x{:X});\n", offset);
+                if *count == 1 {
+                    out += &format!("{} {};\n", ty, name);
+                } else if *count > 1 {
+                    out += &format!("{} {}[{}];\n", ty, name, count);
+                }
+            }
+            Region::Relocations { name, offset } => {
+                out += &format!("FSeek(0x{:X});\nRELOCATION_TABLE {};\n", offset, name);
+            }
+            Region::Bytes {
+                name,
+                offset,
+                length,
+            } => {
+                out += &format!("FSeek(0x{:X});\nUCHAR {}[0x{:X}];\n", offset, name, length);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_identifiers() {
+        assert_eq!(identifier("GROUP_ICON"), "group_icon");
+        assert_eq!(identifier("1"), "m_1");
+        assert_eq!(identifier("My Data"), "my_data");
+
+        let mut regions = vec![
+            Region::Bytes {
+                name: "a".to_string(),
+                offset: 0,
+                length: 1,
+            },
+            Region::Bytes {
+                name: "a".to_string(),
+                offset: 1,
+                length: 1,
+            },
+        ];
+        deduplicate_names(&mut regions);
+        assert_eq!(regions[1].name(), "a_2");
+    }
+}
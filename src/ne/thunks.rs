@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+
+use crate::ne::addr::Seg16;
+use crate::ne::fixups::{FixupTarget, ADDR_FAR};
+use crate::ne::imports::ImportTarget;
+use crate::ne::symbols::SymbolProvider;
+use crate::ne::NeExecutable;
+use crate::x86::{self, Immediate};
+
+///
+/// How code reaches an imported procedure
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportCallKind {
+    /// `CALL FAR ptr16:16` patched by loader
+    CallFar,
+    /// `JMP FAR ptr16:16` patched by loader: a thunk of the import
+    JumpFar,
+    /// `CALL FAR [pointer]`: far pointer of the data segment patched by loader
+    CallThroughPointer { pointer: u16 },
+    /// `CALL` or `JMP` of a `JMP FAR` thunk of the same segment
+    CallThunk { thunk: u16 },
+}
+
+///
+/// Instruction of code segment reaching imported procedure
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportCall {
+    /// Offset of the instruction
+    pub offset: u16,
+    pub kind: ImportCallKind,
+    /// `MODULE.NAME`, or `MODULE.@N` if no symbols know the ordinal
+    pub import: String,
+}
+
+impl fmt::Display for ImportCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ImportCallKind::CallFar => write!(f, "call far {}", self.import),
+            ImportCallKind::JumpFar => write!(f, "jmp far {}", self.import),
+            ImportCallKind::CallThroughPointer { pointer } => {
+                write!(f, "call far [{:04X}] {}", pointer, self.import)
+            }
+            ImportCallKind::CallThunk { thunk } => {
+                write!(f, "{} (thunk at {:04X})", self.import, thunk)
+            }
+        }
+    }
+}
+
+pub(crate) fn import_name(target: &FixupTarget, symbols: &dyn SymbolProvider) -> Option<String> {
+    let FixupTarget::Import { module, procedure } = target else {
+        return None;
+    };
+    let procedure = match procedure {
+        ImportTarget::Ordinal(ordinal) => symbols
+            .name_by_ordinal(module, *ordinal)
+            .unwrap_or_else(|| procedure.to_string()),
+        ImportTarget::Name(name) => name.clone(),
+    };
+    Some(format!("{}.{}", module, procedure))
+}
+
+impl NeExecutable {
+    ///
+    /// Far pointers to imports in segment, by offset
+    ///
+    fn import_pointers(
+        &self,
+        segment: Seg16,
+        symbols: &dyn SymbolProvider,
+    ) -> io::Result<BTreeMap<u16, String>> {
+        Ok(self
+            .fixup_places(segment.0)?
+            .into_iter()
+            .filter(|p| p.address_type == ADDR_FAR)
+            .filter_map(|p| Some((p.offset, import_name(&p.target, symbols)?)))
+            .collect())
+    }
+
+    ///
+    /// Instructions of code `segment` reaching imported procedures, by offset:
+    /// far calls and jumps patched by loader, calls through far pointers
+    /// of the automatic data segment, and near calls and jumps
+    /// of `JMP FAR` thunks. Code is decoded by linear sweep.
+    ///
+    pub fn import_calls(
+        &self,
+        segment: Seg16,
+        symbols: &dyn SymbolProvider,
+    ) -> io::Result<Vec<ImportCall>> {
+        let code = self.fixed_segment(segment.0, |_| None)?;
+        let patched = self.import_pointers(segment, symbols)?;
+        let data_segment = Seg16(self.ne_header.auto_data_segment_index.value());
+        let pointers = match self.segment(data_segment) {
+            Some(_) if data_segment != segment => self.import_pointers(data_segment, symbols)?,
+            _ => BTreeMap::new(),
+        };
+
+        let mut calls = Vec::new();
+        let mut near = Vec::new();
+        let mut pos = 0;
+        while pos < code.len() {
+            let inst = x86::decode(&code, pos, false);
+            let offset = pos as u16;
+            let kind = match (inst.opcode, inst.opcode2, inst.modrm) {
+                (0x9A, None, _) => Some(ImportCallKind::CallFar),
+                (0xEA, None, _) => Some(ImportCallKind::JumpFar),
+                // CALL FAR [disp16]
+                (0xFF, None, Some(0x1E)) => match inst.displacement {
+                    Immediate::Word(pointer) => {
+                        Some(ImportCallKind::CallThroughPointer { pointer })
+                    }
+                    _ => None,
+                },
+                (0xE8 | 0xE9, None, _) => {
+                    if let Immediate::Word(delta) = inst.immediate {
+                        near.push((offset, (inst.end() as u16).wrapping_add(delta)));
+                    }
+                    None
+                }
+                _ => None,
+            };
+            let import = match kind {
+                Some(ImportCallKind::CallThroughPointer { pointer }) => pointers.get(&pointer),
+                Some(_) => patched.get(&offset.wrapping_add(1)),
+                None => None,
+            };
+            if let (Some(kind), Some(import)) = (kind, import) {
+                calls.push(ImportCall {
+                    offset,
+                    kind,
+                    import: import.clone(),
+                });
+            }
+            pos = inst.end();
+        }
+
+        let thunks: BTreeMap<u16, String> = calls
+            .iter()
+            .filter(|c| c.kind == ImportCallKind::JumpFar)
+            .map(|c| (c.offset, c.import.clone()))
+            .collect();
+        for (offset, thunk) in near {
+            if let Some(import) = thunks.get(&thunk) {
+                calls.push(ImportCall {
+                    offset,
+                    kind: ImportCallKind::CallThunk { thunk },
+                    import: import.clone(),
+                });
+            }
+        }
+        calls.sort_by_key(|c| c.offset);
+        Ok(calls)
+    }
+
+    ///
+    /// `JMP FAR` thunks of imports in every code segment:
+    /// segment, offset and `MODULE.NAME` of the import
+    ///
+    pub fn import_thunks(&self, symbols: &dyn SymbolProvider) -> Vec<(Seg16, u16, String)> {
+        let mut thunks = Vec::new();
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            if segment.header.flags & 0x0001 != 0 {
+                continue;
+            }
+            let segment = Seg16::from_index(i);
+            // segments with broken relocation chains have no thunks to show
+            let Ok(calls) = self.import_calls(segment, symbols) else {
+                continue;
+            };
+            thunks.extend(
+                calls
+                    .into_iter()
+                    .filter(|c| c.kind == ImportCallKind::JumpFar)
+                    .map(|c| (segment, c.offset, c.import)),
+            );
+        }
+        thunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::symbols::OrdinalDatabase;
+
+    #[test]
+    fn names_imports_by_ordinal_database() {
+        let target = FixupTarget::Import {
+            module: "KERNEL".to_string(),
+            procedure: ImportTarget::Ordinal(91),
+        };
+        assert_eq!(
+            import_name(&target, &OrdinalDatabase).as_deref(),
+            Some("KERNEL.InitTask")
+        );
+        let unknown = FixupTarget::Import {
+            module: "MYDLL".to_string(),
+            procedure: ImportTarget::Ordinal(7),
+        };
+        assert_eq!(
+            import_name(&unknown, &OrdinalDatabase).as_deref(),
+            Some("MYDLL.@7")
+        );
+        let internal = FixupTarget::Internal {
+            segment: 1,
+            offset: 0,
+        };
+        assert_eq!(import_name(&internal, &OrdinalDatabase), None);
+
+        let call = ImportCall {
+            offset: 0x20,
+            kind: ImportCallKind::CallThunk { thunk: 0x1F0 },
+            import: "USER.MESSAGEBOX".to_string(),
+        };
+        assert_eq!(call.to_string(), "USER.MESSAGEBOX (thunk at 01F0)");
+    }
+}
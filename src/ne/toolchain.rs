@@ -0,0 +1,253 @@
+use std::fmt;
+
+use crate::ne::addr::Far16Ptr;
+use crate::ne::vb16::runtime_version;
+use crate::ne::NeExecutable;
+
+///
+/// Compilers and linkers recognized by `toolchain_guess`
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Toolchain {
+    /// Microsoft C 5.1 with LINK 5.0x
+    MsC5,
+    /// Microsoft C 6.0 with LINK 5.1x-5.2x
+    MsC6,
+    /// Microsoft C/C++ 7.0 and Visual C++ 1.x with LINK 5.3 and later
+    MsC7,
+    /// Borland C++ or Pascal with TLINK
+    Borland,
+    /// Watcom C/C++ with WLINK
+    Watcom,
+    /// Visual Basic program: stub calling the VBRUN runtime
+    VisualBasic,
+}
+
+impl fmt::Display for Toolchain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Toolchain::MsC5 => "Microsoft C 5.1",
+            Toolchain::MsC6 => "Microsoft C 6.0",
+            Toolchain::MsC7 => "Microsoft C/C++ 7.0 or Visual C++ 1.x",
+            Toolchain::Borland => "Borland C++/TLINK",
+            Toolchain::Watcom => "Watcom C/C++",
+            Toolchain::VisualBasic => "Visual Basic",
+        })
+    }
+}
+
+const MICROSOFT: [Toolchain; 3] = [Toolchain::MsC5, Toolchain::MsC6, Toolchain::MsC7];
+
+///
+/// Texts of runtime libraries found in segment data
+///
+const RUNTIME_SIGNATURES: &[(&str, &[Toolchain], u32)] = &[
+    ("MS Run-Time Library", &MICROSOFT, 3),
+    ("Borland C++", &[Toolchain::Borland], 4),
+    ("Borland Intl", &[Toolchain::Borland], 4),
+    ("Turbo Pascal", &[Toolchain::Borland], 4),
+    ("WATCOM", &[Toolchain::Watcom], 4),
+];
+
+///
+/// Messages of default real-mode stubs: WINSTUB of Microsoft LINK and the TLINK one
+///
+const STUB_SIGNATURES: &[(&str, &[Toolchain], u32)] = &[
+    ("This program requires Microsoft Windows", &MICROSOFT, 1),
+    (
+        "This program must be run under Microsoft Windows",
+        &[Toolchain::Borland],
+        1,
+    ),
+];
+
+/// `XOR BP, BP; PUSH BP; CALL FAR` -- start of C runtime startup calling `InitTask`
+const C_STARTUP: [u8; 4] = [0x33, 0xED, 0x55, 0x9A];
+
+///
+/// Toolchain with score of the evidence found for it
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub toolchain: Toolchain,
+    pub score: u32,
+    pub evidence: Vec<String>,
+}
+
+///
+/// Toolchains which might have built the module, the most likely first.
+/// Nothing is certain: linkers of one vendor were used with compilers
+/// of others, and packers replace startup code.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolchainGuess {
+    pub candidates: Vec<Candidate>,
+    /// Facts making every guess less reliable
+    pub caveats: Vec<String>,
+}
+
+impl ToolchainGuess {
+    pub fn best(&self) -> Option<&Candidate> {
+        self.candidates.first()
+    }
+
+    fn add(&mut self, toolchains: &[Toolchain], score: u32, evidence: String) {
+        for &toolchain in toolchains {
+            match self
+                .candidates
+                .iter_mut()
+                .find(|c| c.toolchain == toolchain)
+            {
+                Some(candidate) => {
+                    candidate.score += score;
+                    candidate.evidence.push(evidence.clone());
+                }
+                None => self.candidates.push(Candidate {
+                    toolchain,
+                    score,
+                    evidence: vec![evidence.clone()],
+                }),
+            }
+        }
+    }
+}
+
+impl fmt::Display for ToolchainGuess {
+    ///
+    /// `{}` gives the best guess, `{:#}` every candidate with its evidence
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(best) = self.best() else {
+            return write!(f, "unknown toolchain");
+        };
+        if !f.alternate() {
+            return write!(f, "{} (score {})", best.toolchain, best.score);
+        }
+        for (i, candidate) in self.candidates.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{} (score {})", candidate.toolchain, candidate.score)?;
+            for evidence in &candidate.evidence {
+                write!(f, "\n\t{}", evidence)?;
+            }
+        }
+        for caveat in &self.caveats {
+            write!(f, "\nnote: {}", caveat)?;
+        }
+        Ok(())
+    }
+}
+
+fn contains(data: &[u8], text: &str) -> bool {
+    data.windows(text.len()).any(|w| w == text.as_bytes())
+}
+
+impl NeExecutable {
+    ///
+    /// Guesses compiler and linker of the module from linker version,
+    /// runtime library texts in segments, startup code at the entry point,
+    /// real-mode stub message, imports of runtime modules and self-loading flag
+    ///
+    pub fn toolchain_guess(&self) -> ToolchainGuess {
+        let mut guess = ToolchainGuess::default();
+
+        let header = &self.ne_header;
+        let version = (header.major_linker_version, header.minor_linker_version);
+        let linked_by = match version {
+            (5, 0..=9) => Some(Toolchain::MsC5),
+            (5, 10..=29) => Some(Toolchain::MsC6),
+            (5, 30..) | (6.., _) => Some(Toolchain::MsC7),
+            _ => None,
+        };
+        if let Some(toolchain) = linked_by {
+            guess.add(
+                &[toolchain],
+                2,
+                format!("linker version {}.{:02}", version.0, version.1),
+            );
+        }
+
+        for (signature, toolchains, score) in RUNTIME_SIGNATURES {
+            let found = self
+                .segment_entries
+                .iter()
+                .filter_map(|s| s.data.as_deref())
+                .any(|data| contains(data, signature));
+            if !found {
+                continue;
+            }
+            // version of the Microsoft runtime is told by the linker
+            let toolchains = match linked_by {
+                Some(linked_by) if toolchains.contains(&linked_by) => &[linked_by][..],
+                _ => toolchains,
+            };
+            guess.add(
+                toolchains,
+                *score,
+                format!("runtime library text {:?}", signature),
+            );
+        }
+
+        for (signature, toolchains, score) in STUB_SIGNATURES {
+            if contains(&self.dos_stub, signature) {
+                guess.add(toolchains, *score, format!("stub message {:?}", signature));
+            }
+        }
+
+        let entry_point = Far16Ptr::from(header.entry_point.value());
+        let startup = self
+            .segment(entry_point.seg())
+            .and_then(|s| s.data.as_deref())
+            .and_then(|data| data.get(entry_point.offset as usize..));
+        if startup.is_some_and(|code| code.starts_with(&C_STARTUP)) {
+            let toolchains = [&MICROSOFT[..], &[Toolchain::Borland]].concat();
+            guess.add(
+                &toolchains,
+                1,
+                format!("C runtime startup at {}", entry_point),
+            );
+        }
+
+        for index in 1..=self.module_reference_table.entries.len() as u16 {
+            let Some(module) = self.referenced_module_name(index) else {
+                continue;
+            };
+            if runtime_version(&module).is_some() {
+                guess.add(
+                    &[Toolchain::VisualBasic],
+                    6,
+                    format!("imports Visual Basic runtime {}", module),
+                );
+            }
+        }
+
+        if self.is_self_loading() {
+            guess
+                .caveats
+                .push("self-loading module: startup code may belong to a packer".to_string());
+        }
+
+        guess.candidates.sort_by_key(|c| std::cmp::Reverse(c.score));
+        guess
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_add_up_per_toolchain() {
+        let mut guess = ToolchainGuess::default();
+        guess.add(&MICROSOFT, 1, "startup".to_string());
+        guess.add(&[Toolchain::MsC6], 2, "linker".to_string());
+        guess.candidates.sort_by_key(|c| std::cmp::Reverse(c.score));
+
+        let best = guess.best().unwrap();
+        assert_eq!(best.toolchain, Toolchain::MsC6);
+        assert_eq!(best.score, 3);
+        assert_eq!(guess.to_string(), "Microsoft C 6.0 (score 3)");
+        assert_eq!(guess.candidates.len(), 3);
+    }
+}
@@ -0,0 +1,596 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor, ErrorKind};
+use std::path::Path;
+
+use crate::mz::DosHeader;
+use crate::ne::entry_table::EntryTable;
+use crate::ne::header::NeHeader;
+use crate::ne::imported_name_table::ImportedNameTable;
+use crate::ne::module_reference_table::ModuleReferenceTable;
+use crate::ne::nonresident_name_table::NonresidentNameTable;
+use crate::ne::overlay::Overlay;
+use crate::ne::raw::NeTable;
+use crate::ne::resident_name_table::ResidentNameTable;
+use crate::ne::resource_hash::HashAlgorithm;
+use crate::ne::resource_table::{type_name, NeResourceTable};
+use crate::ne::segment_relocations::RelocationTable;
+use crate::ne::segment_table::NeSegment;
+use crate::ne::sink::{DirSink, ExtractSink};
+use crate::ne::writer::{encode_entry_table, encode_relocation, encode_resource_table, push_name};
+use crate::ne::NeExecutable;
+use crate::util::codepage::Codepage;
+use crate::util::json::json_string;
+
+const MANIFEST: &str = "manifest.json";
+const DOS_STUB: &str = "headers/dos_stub.bin";
+const NE_HEADER: &str = "headers/ne_header.bin";
+const SEGMENT_TABLE: &str = "tables/segment_table.bin";
+const RESOURCE_TABLE: &str = "tables/resource_table.bin";
+const ENTRY_TABLE: &str = "tables/entry_table.bin";
+const RESIDENT_NAMES: &str = "names/resident.bin";
+const NONRESIDENT_NAMES: &str = "names/nonresident.bin";
+const MODULE_REFERENCES: &str = "names/module_references.bin";
+const IMPORTED_NAMES: &str = "names/imported.bin";
+const OVERLAY: &str = "debug/overlay.bin";
+
+///
+/// Directory tree written by `unpack`: number of files and their bytes
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unpacked {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl fmt::Display for Unpacked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} files, {} bytes", self.files, self.bytes)
+    }
+}
+
+///
+/// File name from resource type or ID: other characters than
+/// letters, digits, `#`, `_` and `-` are replaced by `_`
+///
+fn path_component(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "#_-".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+///
+/// Paths of resource data in table order. IDs which give the same
+/// file name are told apart by their place in the type, next numbers
+/// if another ID gives that name too.
+///
+pub(crate) fn resource_paths(table: &NeResourceTable) -> Vec<String> {
+    let mut taken = BTreeSet::new();
+    let mut paths = Vec::new();
+    for resource_type in &table.resource_types {
+        let dir = path_component(&type_name(&resource_type.id()));
+        for (i, resource) in resource_type.resources.iter().enumerate() {
+            let name = path_component(&resource.id().to_string());
+            let mut path = format!("resources/{}/{}.bin", dir, name);
+            let mut number = i;
+            while !taken.insert(path.to_ascii_lowercase()) {
+                path = format!("resources/{}/{}_{}.bin", dir, name, number);
+                number += 1;
+            }
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+fn segment_path(number: usize) -> String {
+    format!("segments/{}.bin", number)
+}
+
+fn relocations_path(number: usize) -> String {
+    format!("segments/{}.relocs.bin", number)
+}
+
+fn encode_names<'a>(entries: impl Iterator<Item = (&'a [u8], u16)>) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    for (name, index) in entries {
+        push_name(&mut out, name)?;
+        out.extend_from_slice(&index.to_le_bytes());
+    }
+    out.push(0);
+    Ok(out)
+}
+
+///
+/// Data of tree file by `/`-separated path, `None` if there is no such file
+///
+type TreeFiles<'a> = &'a dyn Fn(&str) -> io::Result<Option<Vec<u8>>>;
+
+///
+/// Reads `path` of the tree; error tells which file is missing
+///
+fn read_file(files: TreeFiles, path: &str) -> io::Result<Vec<u8>> {
+    read_optional(files, path)?
+        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, format!("{}: no such file", path)))
+}
+
+fn read_optional(files: TreeFiles, path: &str) -> io::Result<Option<Vec<u8>>> {
+    files(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))
+}
+
+///
+/// Collects files of the tree and their manifest records
+///
+struct TreeWriter<'s> {
+    sink: &'s mut dyn ExtractSink,
+    unpacked: Unpacked,
+}
+
+impl TreeWriter<'_> {
+    ///
+    /// Writes `data` into `path` and gives `"path":...,"size":...,"hash":...`
+    /// fields of its manifest record
+    ///
+    fn write(&mut self, path: &str, data: &[u8]) -> io::Result<String> {
+        self.sink.put(path, data)?;
+        self.unpacked.files += 1;
+        self.unpacked.bytes += data.len() as u64;
+        Ok(format!(
+            "\"path\":{},\"size\":{},\"hash\":\"{}\"",
+            json_string(path),
+            data.len(),
+            HashAlgorithm::Sha256.hash(data)
+        ))
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Writes structures of the module into a directory tree:
+    /// `headers/` and `tables/` as encoded by the writer, `segments/`
+    /// with relocation records, `resources/<type>/`, `names/` tables
+    /// and `debug/` data appended after the image (CodeView, TD symbols).
+    /// `manifest.json` lists every file with its original offset on file
+    /// and hash. `read_unpacked` builds the module back from the tree.
+    /// Data of every resource must be read (not skipped by `ParseOptions`).
+    ///
+    pub fn unpack(&self, dir: &Path) -> io::Result<Unpacked> {
+        self.unpack_to(&mut DirSink::new(dir))
+    }
+
+    ///
+    /// Puts files of the `unpack` tree into `sink`
+    ///
+    pub fn unpack_to(&self, sink: &mut dyn ExtractSink) -> io::Result<Unpacked> {
+        let mut tree = TreeWriter {
+            sink,
+            unpacked: Unpacked::default(),
+        };
+        let offset = |table: NeTable| {
+            self.table_range(table)
+                .map_or("null".to_string(), |r| r.offset.to_string())
+        };
+
+        let mut records = Vec::new();
+        let mut table_record = |tree: &mut TreeWriter, table: NeTable, path: &str, data: &[u8]| {
+            let fields = tree.write(path, data)?;
+            records.push(format!(
+                "{{\"table\":{},\"offset\":{},{}}}",
+                json_string(&table.to_string()),
+                offset(table),
+                fields
+            ));
+            io::Result::Ok(())
+        };
+        if !self.dos_stub.is_empty() {
+            table_record(&mut tree, NeTable::DosHeader, DOS_STUB, &self.dos_stub)?;
+        }
+        table_record(
+            &mut tree,
+            NeTable::NeHeader,
+            NE_HEADER,
+            bytemuck::bytes_of(&*self.ne_header),
+        )?;
+        let segment_table: Vec<u8> = self
+            .segment_entries
+            .iter()
+            .flat_map(|s| {
+                let h = s.header;
+                [h.data_offset_shifted, h.data_length, h.flags, h.min_alloc]
+            })
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        table_record(
+            &mut tree,
+            NeTable::SegmentTable,
+            SEGMENT_TABLE,
+            &segment_table,
+        )?;
+        if !self.resource_table.resource_types.is_empty() {
+            let mut resource_table = Vec::new();
+            let records = encode_resource_table(&self.resource_table, &mut resource_table);
+            // places of data as on file: zero ones tell `read_unpacked` of no data
+            let resources = self
+                .resource_table
+                .resource_types
+                .iter()
+                .flat_map(|t| &t.resources);
+            for (resource, at) in resources.zip(records) {
+                let header = resource.header;
+                resource_table[at..at + 2]
+                    .copy_from_slice(&header.data_offset_shifted.to_le_bytes());
+                resource_table[at + 2..at + 4].copy_from_slice(&header.data_length.to_le_bytes());
+            }
+            table_record(
+                &mut tree,
+                NeTable::ResourceTable,
+                RESOURCE_TABLE,
+                &resource_table,
+            )?;
+        }
+        let entry_table = encode_entry_table(&self.entry_table);
+        table_record(&mut tree, NeTable::EntryTable, ENTRY_TABLE, &entry_table)?;
+        let resident = encode_names(
+            self.resident_name_table
+                .entries
+                .iter()
+                .map(|e| (&e.name[..], e.index)),
+        )?;
+        table_record(&mut tree, NeTable::ResidentNames, RESIDENT_NAMES, &resident)?;
+        let nonresident = encode_names(
+            self.nonresident_name_table
+                .entries
+                .iter()
+                .map(|e| (&e.name[..], e.index)),
+        )?;
+        table_record(
+            &mut tree,
+            NeTable::NonresidentNames,
+            NONRESIDENT_NAMES,
+            &nonresident,
+        )?;
+        let module_references: Vec<u8> = self
+            .module_reference_table
+            .entries
+            .iter()
+            .flat_map(|e| e.header.offset.to_le_bytes())
+            .collect();
+        table_record(
+            &mut tree,
+            NeTable::ModuleReferences,
+            MODULE_REFERENCES,
+            &module_references,
+        )?;
+        table_record(
+            &mut tree,
+            NeTable::ImportedNames,
+            IMPORTED_NAMES,
+            &self.imported_name_table.data,
+        )?;
+
+        let mut segments = Vec::new();
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let Some(data) = &segment.data else {
+                continue;
+            };
+            let number = i + 1;
+            let mut record = format!(
+                "{{\"segment\":{},\"offset\":{},\"flags\":{},{}",
+                number,
+                segment.data_offset(),
+                segment.header.flags,
+                tree.write(&segment_path(number), data)?
+            );
+            if let Some(relocations) = self.relocation_tables_per_segment.get(i) {
+                if !relocations.entries.is_empty() {
+                    let mut encoded = (relocations.entries.len() as u16).to_le_bytes().to_vec();
+                    for relocation in &relocations.entries {
+                        encoded.extend_from_slice(&encode_relocation(relocation));
+                    }
+                    let fields = tree.write(&relocations_path(number), &encoded)?;
+                    record.push_str(&format!(",\"relocations\":{{{}}}", fields));
+                }
+            }
+            record.push('}');
+            segments.push(record);
+        }
+
+        let shift_count = self.resource_table.header.alignment_shift_count;
+        let mut resources = Vec::new();
+        let all_resources = self
+            .resource_table
+            .resource_types
+            .iter()
+            .flat_map(|t| t.resources.iter().map(move |r| (t, r)));
+        for ((resource_type, resource), path) in
+            all_resources.zip(resource_paths(&self.resource_table))
+        {
+            let Some(data) = &resource.data else {
+                if resource.header.data_offset_shifted == 0 {
+                    continue;
+                }
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("data of resource {} was not read", resource.id()),
+                ));
+            };
+            resources.push(format!(
+                "{{\"type\":{},\"id\":{},\"offset\":{},\"flags\":{},{}}}",
+                json_string(&type_name(&resource_type.id())),
+                json_string(&resource.id().to_string()),
+                resource.data_offset(shift_count),
+                resource.header.flags,
+                tree.write(&path, data)?
+            ));
+        }
+
+        let debug = match &self.overlay {
+            Some(overlay) => format!(
+                "{{\"offset\":{},{}}}",
+                overlay.offset,
+                tree.write(OVERLAY, &overlay.data)?
+            ),
+            None => "null".to_string(),
+        };
+
+        let list = |items: &[String]| format!("[\n    {}\n  ]", items.join(",\n    "));
+        let manifest = format!(
+            "{{\n  \"module\":{},\n  \"tables\":{},\n  \"segments\":{},\n  \
+             \"resources\":{},\n  \"debug\":{}\n}}\n",
+            self.module_name()
+                .map_or("null".to_string(), |name| json_string(&name)),
+            list(&records),
+            list(&segments),
+            list(&resources),
+            debug
+        );
+        tree.write(MANIFEST, manifest.as_bytes())?;
+        Ok(tree.unpacked)
+    }
+
+    ///
+    /// Builds the module back from the tree written by `unpack`.
+    /// Files may be edited in between: segment and resource lengths
+    /// come from their files, offsets are recomputed when the module
+    /// is written. `manifest.json` isn't read.
+    ///
+    pub fn read_unpacked(dir: &Path) -> io::Result<Self> {
+        Self::read_unpacked_from(&|path| match fs::read(dir.join(path)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        })
+    }
+
+    ///
+    /// `read_unpacked` of the tree whose files `files` gives
+    /// (`MemorySink::files` of `unpack_to`, an archive)
+    ///
+    pub fn read_unpacked_from(files: TreeFiles) -> io::Result<Self> {
+        let dos_stub = read_optional(files, DOS_STUB)?.unwrap_or_default();
+        let dos_header = match dos_stub.len() {
+            0 => bytemuck::Zeroable::zeroed(),
+            _ => DosHeader::read(&mut Cursor::new(&dos_stub))?,
+        };
+        let ne_header = NeHeader::read(&mut Cursor::new(read_file(files, NE_HEADER)?))?;
+        ne_header.check_magic(dos_header.lfanew.value() as u64)?;
+
+        let segment_table = read_file(files, SEGMENT_TABLE)?;
+        let shift_count = ne_header.file_alignment_shift_count.value();
+        let mut segment_entries = Vec::new();
+        let mut relocation_tables_per_segment = Vec::new();
+        let mut r = Cursor::new(&segment_table);
+        for i in 0..segment_table.len() / 8 {
+            let number = i + 1;
+            let mut segment = NeSegment::read(&mut r, shift_count)?;
+            if segment.header.data_offset_shifted != 0 {
+                let data = read_file(files, &segment_path(number))?;
+                segment.header.data_length = match data.len() {
+                    0x10000 => 0,
+                    len => u16::try_from(len).map_err(|_| {
+                        io::Error::new(
+                            ErrorKind::InvalidData,
+                            format!("{} is longer than 64K", segment_path(number)),
+                        )
+                    })?,
+                };
+                segment.data = Some(data);
+            }
+            let relocations = match read_optional(files, &relocations_path(number))? {
+                Some(data) => RelocationTable::read(&mut Cursor::new(data))?,
+                None => RelocationTable {
+                    entries: Vec::new(),
+                },
+            };
+            segment_entries.push(segment);
+            relocation_tables_per_segment.push(relocations);
+        }
+
+        let mut resource_table = match read_optional(files, RESOURCE_TABLE)? {
+            Some(data) => {
+                let mut r = Cursor::new(data);
+                let mut table = NeResourceTable::read_variadic(&mut r)?;
+                table.read_names(&mut r, 0)?;
+                table
+            }
+            None => NeResourceTable::default(),
+        };
+        let paths = resource_paths(&resource_table);
+        let resources = resource_table
+            .resource_types
+            .iter_mut()
+            .flat_map(|t| t.resources.iter_mut());
+        for (resource, path) in resources.zip(paths) {
+            if resource.header.data_offset_shifted != 0 {
+                resource.data = Some(read_file(files, &path)?);
+            }
+        }
+
+        let entry_table = read_file(files, ENTRY_TABLE)?;
+        let entry_table =
+            EntryTable::read(&mut Cursor::new(&entry_table), entry_table.len() as u16)?;
+        let resident_name_table =
+            ResidentNameTable::read(&mut Cursor::new(read_file(files, RESIDENT_NAMES)?))?;
+        let nonresident_name_table =
+            NonresidentNameTable::read(&mut Cursor::new(read_file(files, NONRESIDENT_NAMES)?))?;
+        let imported_names = read_file(files, IMPORTED_NAMES)?;
+        let module_references = read_file(files, MODULE_REFERENCES)?;
+        let mut module_reference_table = ModuleReferenceTable::read(
+            &mut Cursor::new(&module_references),
+            (module_references.len() / 2) as u16,
+        )?;
+        module_reference_table.read_names(&mut Cursor::new(&imported_names), 0)?;
+        let imported_name_table = ImportedNameTable {
+            data: imported_names,
+        };
+        let overlay = read_optional(files, OVERLAY)?.map(|data| Overlay { offset: 0, data });
+
+        let mut module = Self {
+            dos_header: Box::new(dos_header),
+            dos_stub,
+            ne_header: Box::new(ne_header),
+            segment_entries,
+            resource_table,
+            resident_name_table,
+            module_reference_table,
+            imported_name_table,
+            entry_table,
+            nonresident_name_table,
+            relocation_tables_per_segment,
+            overlay,
+            codepage: Codepage::default(),
+            warnings: Vec::new(),
+            views: Default::default(),
+        };
+        module.warnings = module.parse_warnings(&Default::default());
+        Ok(module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::diff::compare;
+    use crate::ne::fixture::sample_bytes;
+    use crate::ne::resource_table::{
+        NeResource, NeResourceHeader, NeResourceTableHeader, NeResourceType, NeResourceTypeHeader,
+    };
+    use crate::ne::sink::MemorySink;
+
+    #[test]
+    fn resource_paths_are_unique() {
+        let resource = |resource_id, name: Option<&str>| NeResource {
+            header: NeResourceHeader {
+                data_offset_shifted: 1,
+                data_length: 1,
+                flags: 0,
+                resource_id,
+                res: [0; 2],
+            },
+            name: name.map(str::to_string),
+            data: None,
+        };
+        let table = NeResourceTable {
+            header: NeResourceTableHeader {
+                alignment_shift_count: 4,
+            },
+            resource_types: vec![NeResourceType {
+                header: NeResourceTypeHeader {
+                    type_id: 0x8003,
+                    num_resources: 6,
+                    res: [0; 2],
+                },
+                name: None,
+                resources: vec![
+                    resource(0x8001, None),
+                    resource(0x0010, Some("MY ICON")),
+                    resource(0x0020, Some("MY/ICON")),
+                    resource(0x0030, Some("MY_ICON_2")),
+                    resource(0x0040, Some("MY_ICON_5")),
+                    resource(0x0050, Some("my icon")),
+                ],
+            }],
+        };
+        assert_eq!(
+            resource_paths(&table),
+            [
+                "resources/ICON/1.bin",
+                "resources/ICON/MY_ICON.bin",
+                "resources/ICON/MY_ICON_2.bin",
+                // names of other IDs aren't taken again
+                "resources/ICON/MY_ICON_2_3.bin",
+                "resources/ICON/MY_ICON_5.bin",
+                "resources/ICON/my_icon_6.bin",
+            ]
+        );
+    }
+
+    #[test]
+    fn tree_builds_the_module_back() {
+        let file = sample_bytes();
+        let ne = NeExecutable::read(&mut Cursor::new(&file)).unwrap();
+        let mut sink = MemorySink::default();
+        let unpacked = ne.unpack_to(&mut sink).unwrap();
+        assert_eq!(unpacked.files, sink.files.len());
+        assert!(sink.files.contains_key("resources/ICON/1.bin"));
+        assert!(sink.files.contains_key("segments/1.relocs.bin"));
+        assert!(!sink.files.contains_key("segments/2.relocs.bin"));
+
+        let read =
+            NeExecutable::read_unpacked_from(&|path| Ok(sink.files.get(path).cloned())).unwrap();
+        assert_eq!(
+            read.ne_header.segment_table_offset,
+            ne.ne_header.segment_table_offset
+        );
+        assert_eq!(
+            format!("{:?}", read.resident_name_table),
+            format!("{:?}", ne.resident_name_table)
+        );
+        assert_eq!(
+            format!("{:?}", read.nonresident_name_table),
+            format!("{:?}", ne.nonresident_name_table)
+        );
+        assert_eq!(
+            format!("{:?}", read.module_reference_table),
+            format!("{:?}", ne.module_reference_table)
+        );
+        assert_eq!(read.imported_name_table.data, ne.imported_name_table.data);
+        assert_eq!(
+            encode_entry_table(&read.entry_table),
+            encode_entry_table(&ne.entry_table)
+        );
+        for (a, b) in read.segment_entries.iter().zip(&ne.segment_entries) {
+            assert_eq!(format!("{:?}", a.header), format!("{:?}", b.header));
+            assert_eq!(a.data, b.data);
+        }
+        let relocations = |ne: &NeExecutable| -> Vec<Vec<[u8; 8]>> {
+            ne.relocation_tables_per_segment
+                .iter()
+                .map(|t| t.entries.iter().map(encode_relocation).collect())
+                .collect()
+        };
+        assert_eq!(relocations(&read), relocations(&ne));
+        assert_eq!(relocations(&read)[0].len(), 1);
+        assert_eq!(
+            format!("{:?}", read.resource_table),
+            format!("{:?}", ne.resource_table)
+        );
+
+        assert!(compare(&ne, &read).is_empty());
+        assert_eq!(read.to_bytes().unwrap(), file);
+    }
+
+    #[test]
+    fn missing_file_is_named() {
+        let error = NeExecutable::read_unpacked_from(&|_| Ok(None)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        assert_eq!(error.to_string(), "headers/ne_header.bin: no such file");
+    }
+}
@@ -0,0 +1,326 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+
+use crate::mz::DosHeader;
+use crate::ne::header::NeHeader;
+use crate::ne::resource_table::{type_name, NeResourceTable};
+use crate::ne::segment_table::{NeSegment, SEG_RELOCINFO};
+
+///
+/// Structure which lies (partly) beyond the end of file
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutDiagnostic {
+    /// Header field (or table) the position comes from
+    pub field: String,
+    pub start: u64,
+    pub end: u64,
+    pub file_size: u64,
+}
+
+impl fmt::Display for LayoutDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} spans 0x{:X}..0x{:X}, but file ends at 0x{:X}",
+            self.field, self.start, self.end, self.file_size
+        )
+    }
+}
+
+///
+/// Payload of `InvalidData` error returned by `NeExecutable::read`
+/// when headers point outside of the file
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutErrors(pub Vec<LayoutDiagnostic>);
+
+impl fmt::Display for LayoutErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "structures outside of file: ")?;
+        for (i, diagnostic) in self.0.iter().enumerate() {
+            if i != 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for LayoutErrors {}
+
+impl LayoutErrors {
+    ///
+    /// Error for `read` if there are any diagnostics
+    ///
+    pub fn check(diagnostics: Vec<LayoutDiagnostic>) -> io::Result<()> {
+        if diagnostics.is_empty() {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            ErrorKind::InvalidData,
+            LayoutErrors(diagnostics),
+        ))
+    }
+}
+
+fn check(
+    diagnostics: &mut Vec<LayoutDiagnostic>,
+    field: impl Into<String>,
+    start: u64,
+    length: u64,
+    file_size: u64,
+) {
+    let end = start.saturating_add(length);
+    if end > file_size {
+        diagnostics.push(LayoutDiagnostic {
+            field: field.into(),
+            start,
+            end,
+            file_size,
+        });
+    }
+}
+
+///
+/// Checks that the NE header itself fits into the file
+///
+pub fn check_ne_header_layout(file_size: u64, lfanew: u64) -> Vec<LayoutDiagnostic> {
+    let mut diagnostics = Vec::new();
+    check(
+        &mut diagnostics,
+        "e_lfanew (NE header)",
+        lfanew,
+        0x40,
+        file_size,
+    );
+    diagnostics
+}
+
+///
+/// Checks every table position given by the header.
+/// Tables of unknown length (resource and name tables) are only checked to start in the file.
+///
+pub fn check_tables_layout(file_size: u64, lfanew: u64, h: &NeHeader) -> Vec<LayoutDiagnostic> {
+    let mut diagnostics = Vec::new();
+    let relative = |offset: u16| lfanew + offset as u64;
+
+    check(
+        &mut diagnostics,
+        "segment_table_offset",
+        relative(h.segment_table_offset.value()),
+        8 * h.segment_count.value() as u64,
+        file_size,
+    );
+    if h.resource_table_offset != h.resident_names_table_offset {
+        check(
+            &mut diagnostics,
+            "resource_table_offset",
+            relative(h.resource_table_offset.value()),
+            2,
+            file_size,
+        );
+    }
+    check(
+        &mut diagnostics,
+        "resident_names_table_offset",
+        relative(h.resident_names_table_offset.value()),
+        1,
+        file_size,
+    );
+    check(
+        &mut diagnostics,
+        "module_reference_table_offset",
+        relative(h.module_reference_table_offset.value()),
+        2 * h.module_references.value() as u64,
+        file_size,
+    );
+    check(
+        &mut diagnostics,
+        "import_name_table_offset",
+        relative(h.import_name_table_offset.value()),
+        h.entry_table_offset
+            .value()
+            .saturating_sub(h.import_name_table_offset.value()) as u64,
+        file_size,
+    );
+    check(
+        &mut diagnostics,
+        "entry_table_offset",
+        relative(h.entry_table_offset.value()),
+        h.entry_table_length.value() as u64,
+        file_size,
+    );
+    // the only offset from the beginning of file
+    check(
+        &mut diagnostics,
+        "non_resident_names_table_offset",
+        h.non_resident_names_table_offset.value() as u64,
+        h.non_resident_names_size.value() as u64,
+        file_size,
+    );
+    diagnostics
+}
+
+///
+/// Checks data of every segment and the count word of its relocations
+///
+pub fn check_segments_layout(file_size: u64, segments: &[NeSegment]) -> Vec<LayoutDiagnostic> {
+    let mut diagnostics = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        if segment.header.data_offset_shifted == 0 {
+            continue;
+        }
+        let relocations = if segment.header.flags & SEG_RELOCINFO != 0 {
+            2
+        } else {
+            0
+        };
+        check(
+            &mut diagnostics,
+            format!("segment #{} data", i + 1),
+            segment.data_offset(),
+            segment.data_length() + relocations,
+            file_size,
+        );
+    }
+    diagnostics
+}
+
+///
+/// Checks relocation records of segment. Their count is stored on file
+/// right after segment data, so the reader is left at the count word.
+/// \param number -- segment number (from 1)
+///
+pub fn check_relocations_layout<R: Read + Seek>(
+    r: &mut R,
+    file_size: u64,
+    number: usize,
+    segment: &NeSegment,
+) -> io::Result<Vec<LayoutDiagnostic>> {
+    let mut diagnostics = Vec::new();
+    if segment.header.data_offset_shifted == 0 || segment.header.flags & SEG_RELOCINFO == 0 {
+        return Ok(diagnostics);
+    }
+    let start = segment.data_offset() + segment.data_length();
+    r.seek(SeekFrom::Start(start))?;
+    let mut count = [0; 2];
+    r.read_exact(&mut count)?;
+    r.seek(SeekFrom::Start(start))?;
+    check(
+        &mut diagnostics,
+        format!("relocations of segment #{}", number),
+        start,
+        2 + 8 * u16::from_le_bytes(count) as u64,
+        file_size,
+    );
+    Ok(diagnostics)
+}
+
+///
+/// Checks data of every resource
+///
+pub fn check_resources_layout(file_size: u64, table: &NeResourceTable) -> Vec<LayoutDiagnostic> {
+    let mut diagnostics = Vec::new();
+    let shift_count = table.header.alignment_shift_count;
+    for resource_type in &table.resource_types {
+        for resource in &resource_type.resources {
+            if resource.header.data_offset_shifted == 0 {
+                continue;
+            }
+            check(
+                &mut diagnostics,
+                format!(
+                    "resource {} of type {}",
+                    resource.id(),
+                    type_name(&resource_type.id())
+                ),
+                resource.data_offset(shift_count),
+                resource.data_length(shift_count),
+                file_size,
+            );
+        }
+    }
+    diagnostics
+}
+
+///
+/// Validation pass alone: reads only headers, segment and resource tables
+/// and reports every structure outside of the file.
+/// Later stages are checked only if earlier ones fit into the file.
+///
+pub fn validate_layout<R: Read + Seek>(r: &mut R) -> io::Result<Vec<LayoutDiagnostic>> {
+    let file_size = r.seek(SeekFrom::End(0))?;
+    let mut diagnostics = Vec::new();
+    check(&mut diagnostics, "MZ header", 0, 0x40, file_size);
+    if !diagnostics.is_empty() {
+        return Ok(diagnostics);
+    }
+
+    r.seek(SeekFrom::Start(0))?;
+    let lfanew = DosHeader::read(r)?.lfanew.value() as u64;
+    diagnostics.extend(check_ne_header_layout(file_size, lfanew));
+    if !diagnostics.is_empty() {
+        return Ok(diagnostics);
+    }
+
+    r.seek(SeekFrom::Start(lfanew))?;
+    let header = NeHeader::read(r)?;
+    let tables = check_tables_layout(file_size, lfanew, &header);
+    let segment_table_fits = !tables.iter().any(|d| d.field == "segment_table_offset");
+    diagnostics.extend(tables);
+    if segment_table_fits {
+        r.seek(SeekFrom::Start(
+            lfanew + header.segment_table_offset.value() as u64,
+        ))?;
+        let segments = (0..header.segment_count.value())
+            .map(|_| NeSegment::read(r, header.file_alignment_shift_count.value()))
+            .collect::<Result<Vec<_>, _>>()?;
+        diagnostics.extend(check_segments_layout(file_size, &segments));
+        if diagnostics.is_empty() {
+            for (i, segment) in segments.iter().enumerate() {
+                diagnostics.extend(check_relocations_layout(r, file_size, i + 1, segment)?);
+            }
+        }
+    }
+    if diagnostics.is_empty() && header.resource_table_offset != header.resident_names_table_offset
+    {
+        let offset = lfanew + header.resource_table_offset.value() as u64;
+        r.seek(SeekFrom::Start(offset))?;
+        let mut resources = NeResourceTable::read_variadic(r)?;
+        resources.read_names(r, offset)?;
+        diagnostics.extend(check_resources_layout(file_size, &resources));
+    }
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_fields_beyond_end() {
+        let mut header: NeHeader = bytemuck::Zeroable::zeroed();
+        header.segment_table_offset = 0x40.into();
+        header.segment_count = 0x10.into();
+        header.resource_table_offset = 0x60.into();
+        header.resident_names_table_offset = 0x60.into();
+        header.module_reference_table_offset = 0x60.into();
+        header.import_name_table_offset = 0x60.into();
+        header.entry_table_offset = 0x60.into();
+        header.entry_table_length = 0x100.into();
+        header.non_resident_names_table_offset = 0x90.into();
+        header.non_resident_names_size = 0x10.into();
+
+        let diagnostics = check_tables_layout(0x100, 0x80, &header);
+        let fields: Vec<_> = diagnostics.iter().map(|d| d.field.as_str()).collect();
+        assert_eq!(fields, ["segment_table_offset", "entry_table_offset"]);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "segment_table_offset spans 0xC0..0x140, but file ends at 0x100"
+        );
+        assert!(check_tables_layout(0x1000, 0x80, &header).is_empty());
+    }
+}
@@ -0,0 +1,286 @@
+use std::fmt;
+
+use crate::ne::addr::Seg16;
+use crate::ne::NeExecutable;
+
+/// Export of custom control library called by Visual Basic on loading it
+const VBX_INIT: &str = "VBINITCC";
+
+/// `usVersion` of `MODEL` structures of VB 1.0-3.0 Control Development Kit
+const MODEL_VERSIONS: [u16; 3] = [0x0100, 0x0200, 0x0300];
+/// `MODEL` up to `nValueProp`; VB 2.0 and later add `usCtlVersion`
+const MODEL_SIZE: usize = 33;
+/// Standard properties and events are given by indices from the top of the word
+const STD_INFO_FIRST: u16 = 0xFF00;
+
+///
+/// Kind of Visual Basic module
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VbKind {
+    /// Program running on the `runtime` library (`VBRUN300`, `VB40016`, ...)
+    Program { runtime: String, version: u8 },
+    /// Custom control library (`.VBX`)
+    CustomControl,
+}
+
+///
+/// Control registered by `.VBX` through `VBRegisterModel`:
+/// `MODEL` structure found in the automatic data segment
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlModel {
+    /// Offset of the structure in the data segment
+    pub offset: u16,
+    /// Version of CDK headers the control was built with (`0x0300` for 3.0)
+    pub vb_version: u16,
+    pub default_name: String,
+    pub class_name: String,
+    pub parent_class: Option<String>,
+    /// Names of own properties, `#N` for standard ones
+    pub properties: Vec<String>,
+    /// Names of own events, `#N` for standard ones
+    pub events: Vec<String>,
+}
+
+impl fmt::Display for ControlModel {
+    ///
+    /// `{}` gives class and default name, `{:#}` also properties and events
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}), VB {}.{}",
+            self.class_name,
+            self.default_name,
+            self.vb_version >> 8,
+            self.vb_version & 0xFF
+        )?;
+        if let Some(parent) = &self.parent_class {
+            write!(f, ", subclasses {}", parent)?;
+        }
+        if f.alternate() {
+            write!(f, "\n\tproperties: {}", self.properties.join(" "))?;
+            write!(f, "\n\tevents: {}", self.events.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Visual Basic specific content of the module
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vb16Info {
+    pub kind: VbKind,
+    /// Controls of custom control library
+    pub controls: Vec<ControlModel>,
+    /// `.FRM` names of forms mentioned in segment data of programs
+    pub forms: Vec<String>,
+    /// `.VBX` libraries mentioned in segment data
+    pub custom_controls: Vec<String>,
+}
+
+impl fmt::Display for Vb16Info {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            VbKind::Program { runtime, version } => {
+                write!(f, "Visual Basic {} program ({})", version, runtime)?
+            }
+            VbKind::CustomControl => write!(f, "Visual Basic custom control library")?,
+        }
+        for control in &self.controls {
+            write!(f, "\ncontrol {:#}", control)?;
+        }
+        for form in &self.forms {
+            write!(f, "\nform {}", form)?;
+        }
+        for vbx in &self.custom_controls {
+            write!(f, "\nuses {}", vbx)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Version of Visual Basic by name of its runtime library
+///
+pub(crate) fn runtime_version(module: &str) -> Option<u8> {
+    let module = module.to_ascii_uppercase();
+    match module.as_str() {
+        "VBRUN100" => Some(1),
+        "VBRUN200" => Some(2),
+        "VBRUN300" => Some(3),
+        "VB40016" => Some(4),
+        _ => None,
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    let b = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes([b[0], b[1]]))
+}
+
+///
+/// Zero-terminated identifier at near pointer `offset` in data segment
+///
+fn identifier_at(data: &[u8], offset: u16) -> Option<String> {
+    let tail = data.get(offset as usize..)?;
+    let end = tail.iter().take(64).position(|&b| b == 0)?;
+    let name = &tail[..end];
+    let is_identifier =
+        !name.is_empty() && name.iter().all(|&b| b.is_ascii_alphanumeric() || b == b'_');
+    is_identifier.then(|| String::from_utf8_lossy(name).into_owned())
+}
+
+///
+/// Names of `PROPINFO` or `EVENTINFO` list at `offset`: near pointers
+/// ending with 0, each structure starting with pointer to its name
+///
+fn info_names(data: &[u8], offset: u16) -> Option<Vec<String>> {
+    let mut names = Vec::new();
+    if offset == 0 {
+        return Some(names);
+    }
+    for i in 0..256 {
+        let entry = u16_at(data, offset as usize + i * 2)?;
+        match entry {
+            0 => return Some(names),
+            STD_INFO_FIRST.. => names.push(format!("#{}", !entry)),
+            _ => names.push(identifier_at(data, u16_at(data, entry as usize)?)?),
+        }
+    }
+    None
+}
+
+///
+/// `MODEL` structure at `offset`, if its pointers lead to valid names
+///
+fn model_at(data: &[u8], offset: usize) -> Option<ControlModel> {
+    let vb_version = u16_at(data, offset)?;
+    if !MODEL_VERSIONS.contains(&vb_version) || data.len() < offset + MODEL_SIZE {
+        return None;
+    }
+    let word = |at: usize| u16_at(data, offset + at);
+    let parent_class = match word(24)? {
+        0 => None,
+        pointer => Some(identifier_at(data, pointer)?),
+    };
+    Some(ControlModel {
+        offset: offset as u16,
+        vb_version,
+        default_name: identifier_at(data, word(20)?)?,
+        class_name: identifier_at(data, word(22)?)?,
+        parent_class,
+        properties: info_names(data, word(26)?)?,
+        events: info_names(data, word(28)?)?,
+    })
+}
+
+///
+/// Zero-terminated names ending with `extension` (with dot, uppercase)
+///
+fn file_names(data: &[u8], extension: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    for text in data.split(|&b| b == 0) {
+        let start = text
+            .iter()
+            .rposition(|&b| !b.is_ascii_graphic())
+            .map_or(0, |p| p + 1);
+        let name = String::from_utf8_lossy(&text[start..]).into_owned();
+        let file_name = name.rsplit(['\\', '/', ':']).next().unwrap_or_default();
+        if file_name.len() > extension.len() && file_name.to_ascii_uppercase().ends_with(extension)
+        {
+            names.push(file_name.to_ascii_uppercase());
+        }
+    }
+    names
+}
+
+impl NeExecutable {
+    ///
+    /// Visual Basic 1.0-4.0 program (by import of its runtime) or custom control
+    /// library (by `VBINITCC` export) with its structures:
+    /// `MODEL` tables of controls in the automatic data segment of `.VBX`,
+    /// names of forms and custom controls in segments of programs.
+    /// `None` for other modules.
+    ///
+    pub fn vb16(&self) -> Option<Vb16Info> {
+        let is_vbx = self.exports().iter().any(|e| {
+            e.name
+                .as_deref()
+                .is_some_and(|n| n.eq_ignore_ascii_case(VBX_INIT))
+        });
+        let kind = if is_vbx {
+            VbKind::CustomControl
+        } else {
+            (1..=self.module_reference_table.entries.len() as u16)
+                .filter_map(|i| self.referenced_module_name(i))
+                .find_map(|runtime| {
+                    let version = runtime_version(&runtime)?;
+                    Some(VbKind::Program { runtime, version })
+                })?
+        };
+
+        let mut info = Vb16Info {
+            kind,
+            controls: Vec::new(),
+            forms: Vec::new(),
+            custom_controls: Vec::new(),
+        };
+        if is_vbx {
+            let data_segment = Seg16(self.ne_header.auto_data_segment_index.value());
+            if let Some(data) = self.segment(data_segment).and_then(|s| s.data.as_deref()) {
+                info.controls = (0..data.len())
+                    .step_by(2)
+                    .filter_map(|offset| model_at(data, offset))
+                    .collect();
+            }
+        }
+        for data in self
+            .segment_entries
+            .iter()
+            .filter_map(|s| s.data.as_deref())
+        {
+            if !is_vbx {
+                info.forms.extend(file_names(data, ".FRM"));
+            }
+            info.custom_controls.extend(file_names(data, ".VBX"));
+        }
+        for names in [&mut info.forms, &mut info.custom_controls] {
+            names.sort();
+            names.dedup();
+        }
+        Some(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_control_model() {
+        let mut data = vec![0u8; 0x40];
+        data[0..2].copy_from_slice(&0x0300u16.to_le_bytes());
+        data[20..22].copy_from_slice(&0x30u16.to_le_bytes()); // default name
+        data[22..24].copy_from_slice(&0x36u16.to_le_bytes()); // class name
+        data[26..28].copy_from_slice(&0x22u16.to_le_bytes()); // property list
+                                                              // property list: standard property 0 (0xFFFF), end
+        data[0x22..0x24].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        data[0x30..0x35].copy_from_slice(b"Gauge");
+        data[0x36..0x3B].copy_from_slice(b"GAUGE");
+
+        let model = model_at(&data, 0).unwrap();
+        assert_eq!(model.default_name, "Gauge");
+        assert_eq!(model.properties, ["#0"]);
+        assert!(model.events.is_empty());
+        assert_eq!(model.to_string(), "GAUGE (Gauge), VB 3.0");
+        assert!(model_at(&data, 2).is_none());
+
+        assert_eq!(
+            file_names(b"\0C:\\VB\\MAIN.FRM\0x\x01gauge.vbx\0", ".VBX"),
+            ["GAUGE.VBX"]
+        );
+    }
+}
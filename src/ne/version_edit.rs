@@ -0,0 +1,356 @@
+use std::io::{self, ErrorKind};
+
+use crate::ne::editor::NeEditor;
+use crate::ne::resource_table::{ResourceId, RT_VERSION};
+use crate::ne::version_info::{children, node, FileVersion, Node, VS_FFI_SIGNATURE};
+
+/// Translation of the `StringFileInfo` table added to version info without
+/// one: U.S. English, Windows ANSI
+pub const DEFAULT_TRANSLATION: &[u8] = b"040904E4";
+
+///
+/// Node of 16-bit version info owning its key, value and children.
+/// `encode` writes the nodes back, recalculating `wLength` of every
+/// node and `wValueLength` from the value.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VersionNode {
+    pub key: Vec<u8>,
+    /// Value as on file; text values keep their terminating zero
+    pub value: Vec<u8>,
+    pub children: Vec<VersionNode>,
+}
+
+impl VersionNode {
+    pub fn new(key: &[u8], value: &[u8]) -> Self {
+        Self {
+            key: key.to_vec(),
+            value: value.to_vec(),
+            children: Vec::new(),
+        }
+    }
+
+    ///
+    /// Reads the tree of version info resource `data`
+    ///
+    pub fn parse(data: &[u8]) -> io::Result<Self> {
+        Self::from_node(data, &node(data, 0)?)
+    }
+
+    fn from_node(data: &[u8], parsed: &Node) -> io::Result<Self> {
+        Ok(Self {
+            key: parsed.key.to_vec(),
+            value: parsed.value.to_vec(),
+            children: children(data, parsed)?
+                .iter()
+                .map(|child| Self::from_node(data, child))
+                .collect::<io::Result<_>>()?,
+        })
+    }
+
+    ///
+    /// Writes the tree as `VersionInfo::parse` reads it: key, value and
+    /// every child aligned to 4 bytes; `wLength` doesn't count padding
+    /// after the last part of a node.
+    ///
+    pub fn encode(&self) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        self.encode_into(&mut out)?;
+        Ok(out)
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) -> io::Result<()> {
+        let too_long = |what: &str| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} of version info node \"{}\" is longer than 0xFFFF bytes",
+                    what,
+                    self.key.escape_ascii()
+                ),
+            )
+        };
+        if self.key.contains(&0) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "key \"{}\" of version info node contains zero",
+                    self.key.escape_ascii()
+                ),
+            ));
+        }
+        let value_length = u16::try_from(self.value.len()).map_err(|_| too_long("value"))?;
+        let start = out.len();
+        out.extend_from_slice(&[0, 0]);
+        out.extend_from_slice(&value_length.to_le_bytes());
+        out.extend_from_slice(&self.key);
+        out.push(0);
+        out.resize(out.len().next_multiple_of(4), 0);
+        out.extend_from_slice(&self.value);
+        for child in &self.children {
+            out.resize(out.len().next_multiple_of(4), 0);
+            child.encode_into(out)?;
+        }
+        let length = u16::try_from(out.len() - start).map_err(|_| too_long("node"))?;
+        out[start..start + 2].copy_from_slice(&length.to_le_bytes());
+        Ok(())
+    }
+
+    ///
+    /// The first child with `key` (case-insensitive)
+    ///
+    pub fn child_mut(&mut self, key: &[u8]) -> Option<&mut VersionNode> {
+        self.children
+            .iter_mut()
+            .find(|child| child.key.eq_ignore_ascii_case(key))
+    }
+
+    fn child_or_insert(&mut self, key: &[u8], index: usize) -> &mut VersionNode {
+        let position = self
+            .children
+            .iter()
+            .position(|child| child.key.eq_ignore_ascii_case(key));
+        let position = position.unwrap_or_else(|| {
+            let index = index.min(self.children.len());
+            self.children.insert(index, VersionNode::new(key, b""));
+            index
+        });
+        &mut self.children[position]
+    }
+
+    fn fixed_info(&mut self) -> io::Result<&mut [u8]> {
+        match self.value.get_mut(..24) {
+            Some(fixed) if fixed[..4] == VS_FFI_SIGNATURE.to_le_bytes() => Ok(fixed),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "version info has no VS_FIXEDFILEINFO",
+            )),
+        }
+    }
+
+    ///
+    /// Sets `dwFileVersionMS` and `dwFileVersionLS` of fixed info
+    ///
+    pub fn set_file_version(&mut self, version: FileVersion) -> io::Result<()> {
+        let (ms, ls) = version.to_dwords();
+        let fixed = self.fixed_info()?;
+        fixed[8..12].copy_from_slice(&ms.to_le_bytes());
+        fixed[12..16].copy_from_slice(&ls.to_le_bytes());
+        Ok(())
+    }
+
+    ///
+    /// Sets `dwProductVersionMS` and `dwProductVersionLS` of fixed info
+    ///
+    pub fn set_product_version(&mut self, version: FileVersion) -> io::Result<()> {
+        let (ms, ls) = version.to_dwords();
+        let fixed = self.fixed_info()?;
+        fixed[16..20].copy_from_slice(&ms.to_le_bytes());
+        fixed[20..24].copy_from_slice(&ls.to_le_bytes());
+        Ok(())
+    }
+
+    ///
+    /// Value of string `key` in the first `StringFileInfo` table
+    ///
+    pub fn string(&self, key: &[u8]) -> Option<&[u8]> {
+        let table = self
+            .children
+            .iter()
+            .find(|child| child.key.eq_ignore_ascii_case(b"StringFileInfo"))?
+            .children
+            .first()?;
+        let string = table
+            .children
+            .iter()
+            .find(|string| string.key.eq_ignore_ascii_case(key))?;
+        string.value.split(|&b| b == 0).next()
+    }
+
+    ///
+    /// Sets string `key` of the first `StringFileInfo` table, adding the string
+    /// at the end of the table if missing. Version info without strings
+    /// gets `StringFileInfo` before other blocks, with a `DEFAULT_TRANSLATION` table.
+    ///
+    pub fn set_string(&mut self, key: &[u8], value: &[u8]) {
+        let string_file_info = self.child_or_insert(b"StringFileInfo", 0);
+        if string_file_info.children.is_empty() {
+            string_file_info
+                .children
+                .push(VersionNode::new(DEFAULT_TRANSLATION, b""));
+        }
+        let table = &mut string_file_info.children[0];
+        let string = table.child_or_insert(key, usize::MAX);
+        string.value = value.to_vec();
+        string.value.push(0);
+    }
+
+    ///
+    /// Removes string `key` of the first `StringFileInfo` table;
+    /// returns whether it was there
+    ///
+    pub fn remove_string(&mut self, key: &[u8]) -> bool {
+        let Some(table) = self
+            .child_mut(b"StringFileInfo")
+            .and_then(|block| block.children.first_mut())
+        else {
+            return false;
+        };
+        let count = table.children.len();
+        table
+            .children
+            .retain(|string| !string.key.eq_ignore_ascii_case(key));
+        table.children.len() != count
+    }
+}
+
+impl NeEditor {
+    ///
+    /// Applies `edit` to the first version resource which parses and writes
+    /// it back with the same ID and flags
+    ///
+    pub fn edit_version(
+        &mut self,
+        edit: impl FnOnce(&mut VersionNode) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let found = self
+            .module()
+            .resource_table
+            .resources_of_type(RT_VERSION)
+            .find_map(|resource| {
+                let root = VersionNode::parse(resource.data.as_deref()?).ok()?;
+                (root.key == b"VS_VERSION_INFO")
+                    .then(|| (resource.id(), resource.header.flags, root))
+            });
+        let Some((id, flags, mut root)) = found else {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "module has no version resource",
+            ));
+        };
+        edit(&mut root)?;
+        self.set_resource(&ResourceId::Int(RT_VERSION), &id, flags, root.encode()?)?;
+        Ok(())
+    }
+
+    ///
+    /// Sets file version of fixed info and the `FileVersion` string if present
+    ///
+    pub fn set_file_version(&mut self, version: FileVersion) -> io::Result<()> {
+        self.edit_version(|root| {
+            root.set_file_version(version)?;
+            if root.string(b"FileVersion").is_some() {
+                root.set_string(b"FileVersion", version.to_string().as_bytes());
+            }
+            Ok(())
+        })
+    }
+
+    ///
+    /// Sets product version of fixed info and the `ProductVersion` string if present
+    ///
+    pub fn set_product_version(&mut self, version: FileVersion) -> io::Result<()> {
+        self.edit_version(|root| {
+            root.set_product_version(version)?;
+            if root.string(b"ProductVersion").is_some() {
+                root.set_string(b"ProductVersion", version.to_string().as_bytes());
+            }
+            Ok(())
+        })
+    }
+
+    ///
+    /// Sets string `key` of version info (`CompanyName`, `FileDescription` ...),
+    /// encoded with the code page of the module
+    ///
+    pub fn set_version_string(&mut self, key: &str, value: &str) -> io::Result<()> {
+        let codepage = self.module().codepage;
+        let encode = |text: &str| {
+            codepage.encode(text).map_err(|c| {
+                io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{:?} has no character in code page {}", c, codepage),
+                )
+            })
+        };
+        let (key, value) = (encode(key)?, encode(value)?);
+        self.edit_version(|root| {
+            root.set_string(&key, &value);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ne::version_info::VersionInfo;
+    use crate::util::codepage::Codepage;
+
+    fn fixed_info() -> Vec<u8> {
+        let mut fixed = Vec::new();
+        for dword in [
+            VS_FFI_SIGNATURE,
+            0x10000,
+            0x10002,
+            0x30004,
+            0x10002,
+            0x30004,
+        ] {
+            fixed.extend_from_slice(&dword.to_le_bytes());
+        }
+        fixed.resize(52, 0);
+        fixed
+    }
+
+    #[test]
+    fn encodes_edited_tree() {
+        let mut table = VersionNode::new(b"040904E4", b"");
+        table.children = vec![
+            VersionNode::new(b"CompanyName", b"Acme\0"),
+            VersionNode::new(b"FileVersion", b"1.2.3.4\0"),
+        ];
+        let mut root = VersionNode::new(b"VS_VERSION_INFO", &fixed_info());
+        root.children = vec![
+            VersionNode {
+                children: vec![table],
+                ..VersionNode::new(b"StringFileInfo", b"")
+            },
+            VersionNode::new(b"VarFileInfo", b""),
+        ];
+        let data = root.encode().unwrap();
+        assert_eq!(VersionNode::parse(&data).unwrap(), root);
+
+        root.set_file_version("1.2.3.5".parse().unwrap()).unwrap();
+        root.set_string(b"companyname", b"Acme Software Corporation");
+        root.set_string(b"FileDescription", b"Sample");
+        assert!(root.remove_string(b"FileVersion"));
+        let data = root.encode().unwrap();
+        assert_eq!(u16::from_le_bytes([data[0], data[1]]) as usize, data.len());
+
+        let info = VersionInfo::parse(&data, Codepage::Windows1252).unwrap();
+        assert_eq!(info.file_version, Some(FileVersion([1, 2, 3, 5])));
+        assert_eq!(info.product_version, Some(FileVersion([1, 2, 3, 4])));
+        assert_eq!(
+            info.strings,
+            [
+                (
+                    "CompanyName".to_string(),
+                    "Acme Software Corporation".to_string()
+                ),
+                ("FileDescription".to_string(), "Sample".to_string()),
+            ]
+        );
+        assert_eq!(VersionNode::parse(&data).unwrap(), root);
+
+        assert_eq!(
+            FileVersion([1, 2, 3, 4]).bumped(1),
+            FileVersion([1, 3, 0, 0])
+        );
+        let mut bare = VersionNode::new(b"VS_VERSION_INFO", b"");
+        assert!(bare.set_file_version(FileVersion::default()).is_err());
+        bare.set_string(b"CompanyName", b"Acme");
+        assert_eq!(bare.string(b"COMPANYNAME"), Some(&b"Acme"[..]));
+        assert_eq!(bare.children[0].children[0].key, DEFAULT_TRANSLATION);
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::str::FromStr;
+
+use crate::ne::resource_table::RT_VERSION;
+use crate::ne::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// `dwSignature` of `VS_FIXEDFILEINFO`
+pub const VS_FFI_SIGNATURE: u32 = 0xFEEF04BD;
+
+///
+/// Version number of `VS_FIXEDFILEINFO`: major, minor, release and build;
+/// `{}` gives `1.2.3.4`
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FileVersion(pub [u16; 4]);
+
+impl FileVersion {
+    ///
+    /// Version of `dwFileVersionMS` and `dwFileVersionLS` pair
+    ///
+    pub fn from_dwords(ms: u32, ls: u32) -> Self {
+        Self([(ms >> 16) as u16, ms as u16, (ls >> 16) as u16, ls as u16])
+    }
+
+    ///
+    /// `dwFileVersionMS` and `dwFileVersionLS` of the version
+    ///
+    pub fn to_dwords(self) -> (u32, u32) {
+        let [major, minor, release, build] = self.0.map(u32::from);
+        (major << 16 | minor, release << 16 | build)
+    }
+
+    ///
+    /// Version with `part` (0 for major ... 3 for build) incremented
+    /// and the parts after it zeroed
+    ///
+    pub fn bumped(self, part: usize) -> Self {
+        let mut parts = self.0;
+        parts[part] = parts[part].wrapping_add(1);
+        parts[part + 1..].fill(0);
+        Self(parts)
+    }
+}
+
+impl FromStr for FileVersion {
+    type Err = String;
+
+    /// Accepts one to four numbers: `3.10` is `3.10.0.0`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = [0; 4];
+        let numbers: Vec<&str> = s.split('.').collect();
+        if numbers.len() > 4 {
+            return Err(format!("version `{}` has more than 4 parts", s));
+        }
+        for (part, number) in parts.iter_mut().zip(numbers) {
+            *part = number
+                .trim()
+                .parse()
+                .map_err(|_| format!("bad part `{}` of version `{}`", number, s))?;
+        }
+        Ok(Self(parts))
+    }
+}
+
+impl fmt::Display for FileVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [major, minor, release, build] = self.0;
+        write!(f, "{}.{}.{}.{}", major, minor, release, build)
+    }
+}
+
+///
+/// Contents of 16-bit version resource
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VersionInfo {
+    pub file_version: Option<FileVersion>,
+    pub product_version: Option<FileVersion>,
+    /// Values of the first `StringFileInfo` table in order on file:
+    /// `CompanyName`, `FileDescription`, `FileVersion` ...
+    pub strings: Vec<(String, String)>,
+}
+
+///
+/// Node of version info: `wLength`, `wValueLength`, key, value and children,
+/// each part aligned to 4 bytes from the beginning of the resource.
+/// Offsets are from the beginning of the resource.
+///
+pub(crate) struct Node<'a> {
+    pub key: &'a [u8],
+    pub value: &'a [u8],
+    pub children: usize,
+    pub end: usize,
+}
+
+pub(crate) fn node(data: &[u8], start: usize) -> io::Result<Node<'_>> {
+    let word = |at: usize| {
+        data.get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+    };
+    let bad_length = |length| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "version info node at {:#x} has bad length {}",
+                start, length
+            ),
+        )
+    };
+    let (Some(length), Some(value_length)) = (word(start), word(start + 2)) else {
+        return Err(bad_length(0));
+    };
+    let end = start + length;
+    if length < 4 || end > data.len() {
+        return Err(bad_length(length));
+    }
+    let key_length = data[start + 4..end]
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("key of version info node at {:#x} is not terminated", start),
+            )
+        })?;
+    let value_start = (start + 4 + key_length + 1).next_multiple_of(4).min(end);
+    let value_end = (value_start + value_length).min(end);
+    Ok(Node {
+        key: &data[start + 4..start + 4 + key_length],
+        value: &data[value_start..value_end],
+        children: value_end.next_multiple_of(4),
+        end,
+    })
+}
+
+pub(crate) fn children<'a>(data: &'a [u8], parent: &Node) -> io::Result<Vec<Node<'a>>> {
+    let mut nodes = Vec::new();
+    let mut child = parent.children;
+    while child + 4 <= parent.end {
+        let node = node(&data[..parent.end], child)?;
+        child = node.end.next_multiple_of(4);
+        nodes.push(node);
+    }
+    Ok(nodes)
+}
+
+impl VersionInfo {
+    ///
+    /// Parses 16-bit `VS_VERSION_INFO` (nodes without `wType`, ANSI keys
+    /// and values). Fixed info with a bad signature is left out.
+    /// \param codepage -- code page of string values
+    ///
+    pub fn parse(data: &[u8], codepage: Codepage) -> io::Result<Self> {
+        let root = node(data, 0)?;
+        if root.key != b"VS_VERSION_INFO" {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "version info starts with \"{}\" instead of VS_VERSION_INFO",
+                    root.key.escape_ascii()
+                ),
+            ));
+        }
+        let mut info = VersionInfo::default();
+        let dword = |at: usize| {
+            root.value
+                .get(at..at + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+        if dword(0) == Some(VS_FFI_SIGNATURE) {
+            if let (Some(ms), Some(ls)) = (dword(8), dword(12)) {
+                info.file_version = Some(FileVersion::from_dwords(ms, ls));
+            }
+            if let (Some(ms), Some(ls)) = (dword(16), dword(20)) {
+                info.product_version = Some(FileVersion::from_dwords(ms, ls));
+            }
+        }
+
+        for block in children(data, &root)? {
+            if block.key != b"StringFileInfo" {
+                continue;
+            }
+            let Some(table) = children(data, &block)?.into_iter().next() else {
+                continue;
+            };
+            for string in children(data, &table)? {
+                let value = string.value.split(|&b| b == 0).next().unwrap_or_default();
+                info.strings
+                    .push((codepage.decode(string.key), codepage.decode(value)));
+            }
+            break;
+        }
+        Ok(info)
+    }
+
+    ///
+    /// Value of string `key` (case-insensitive)
+    ///
+    pub fn string(&self, key: &str) -> Option<&str> {
+        self.strings
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    ///
+    /// `FileVersion` string, or file version of fixed info without it
+    ///
+    pub fn version(&self) -> Option<String> {
+        self.string("FileVersion")
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .or_else(|| self.file_version.map(|v| v.to_string()))
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// The first version resource which parses, decoded with the code page
+    /// of the module. Resources with data not read are skipped.
+    ///
+    pub fn version_info(&self) -> Option<VersionInfo> {
+        self.resource_table
+            .resources_of_type(RT_VERSION)
+            .filter_map(|resource| resource.data.as_ref())
+            .find_map(|data| VersionInfo::parse(data, self.codepage).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(key: &[u8], value: &[u8], children: &[Vec<u8>]) -> Vec<u8> {
+        let mut node = vec![0, 0];
+        node.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        node.extend_from_slice(key);
+        node.push(0);
+        node.resize(node.len().next_multiple_of(4), 0);
+        node.extend_from_slice(value);
+        for child in children {
+            node.resize(node.len().next_multiple_of(4), 0);
+            node.extend_from_slice(child);
+        }
+        let length = node.len() as u16;
+        node[..2].copy_from_slice(&length.to_le_bytes());
+        node
+    }
+
+    #[test]
+    fn reads_fixed_info_and_strings() {
+        let mut fixed = Vec::new();
+        for dword in [VS_FFI_SIGNATURE, 0x10000, 0x3000A, 0x20001, 0x3000A, 0] {
+            fixed.extend_from_slice(&dword.to_le_bytes());
+        }
+        fixed.resize(52, 0);
+        let table = node(
+            b"040904E4",
+            b"",
+            &[
+                node(b"CompanyName", b"Acme\0", &[]),
+                node(b"FileVersion", b" 3.10.x \0", &[]),
+            ],
+        );
+        let data = node(
+            b"VS_VERSION_INFO",
+            &fixed,
+            &[node(b"StringFileInfo", b"", &[table])],
+        );
+
+        let info = VersionInfo::parse(&data, Codepage::Windows1252).unwrap();
+        assert_eq!(info.file_version, Some(FileVersion([3, 10, 2, 1])));
+        assert_eq!(info.product_version.unwrap().to_string(), "3.10.0.0");
+        assert_eq!(info.string("companyname"), Some("Acme"));
+        assert_eq!(info.version().as_deref(), Some("3.10.x"));
+
+        let bare = node(b"VS_VERSION_INFO", &fixed, &[]);
+        let info = VersionInfo::parse(&bare, Codepage::Windows1252).unwrap();
+        assert_eq!(info.version().as_deref(), Some("3.10.2.1"));
+        assert!(VersionInfo::parse(b"\x04\0\0\0", Codepage::Windows1252).is_err());
+    }
+}
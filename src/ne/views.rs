@@ -0,0 +1,130 @@
+use std::sync::{Arc, OnceLock};
+
+use crate::ne::coverage::CoveredRange;
+use crate::ne::exports::Export;
+use crate::ne::imports::Import;
+use crate::ne::relocation_stats::RelocationStats;
+use crate::ne::NeExecutable;
+use crate::util::intern::Interner;
+
+///
+/// Names of name tables decoded once: equal names of the tables
+/// share one string
+///
+#[derive(Debug, Clone, Default)]
+pub struct NameTable {
+    /// Resident names in table order, module name first
+    pub resident: Vec<Arc<str>>,
+    /// Nonresident names in table order, description first
+    pub nonresident: Vec<Arc<str>>,
+    /// Names of referenced modules, in order of module reference table
+    pub modules: Vec<Arc<str>>,
+    /// Distinct names of all tables
+    pub distinct: usize,
+}
+
+///
+/// Derived views of a module computed on first query and kept until
+/// `NeExecutable::invalidate`. Views walk entry and relocation tables,
+/// which interactive tools would otherwise repeat for every query.
+///
+#[derive(Debug, Clone, Default)]
+pub struct ViewCache {
+    names: OnceLock<NameTable>,
+    exports: OnceLock<Vec<Export>>,
+    imports: OnceLock<Vec<Import>>,
+    relocation_stats: OnceLock<RelocationStats>,
+    coverage_map: OnceLock<Vec<CoveredRange>>,
+}
+
+impl NeExecutable {
+    ///
+    /// Forgets memoized views. Must be called after changing tables
+    /// of the module in place; `NeEditor` calls it on every edit.
+    ///
+    pub fn invalidate(&mut self) {
+        self.views = ViewCache::default();
+    }
+
+    ///
+    /// Decoded names of resident, nonresident and module reference tables
+    ///
+    pub fn names(&self) -> &NameTable {
+        self.views.names.get_or_init(|| {
+            let mut interner = Interner::new();
+            let mut intern = |names: &mut dyn Iterator<Item = &Vec<u8>>| -> Vec<Arc<str>> {
+                names.map(|name| interner.intern(name)).collect()
+            };
+            let resident = intern(&mut self.resident_name_table.entries.iter().map(|e| &e.name));
+            let nonresident =
+                intern(&mut self.nonresident_name_table.entries.iter().map(|e| &e.name));
+            let modules = intern(&mut self.module_reference_table.entries.iter().map(|e| &e.name));
+            NameTable {
+                resident,
+                nonresident,
+                modules,
+                distinct: interner.len(),
+            }
+        })
+    }
+
+    ///
+    /// Exports view (see `build_exports`), computed once
+    ///
+    pub fn exports(&self) -> &[Export] {
+        self.views.exports.get_or_init(|| self.build_exports())
+    }
+
+    ///
+    /// Imports view (see `build_imports`), computed once
+    ///
+    pub fn imports(&self) -> &[Import] {
+        self.views.imports.get_or_init(|| self.build_imports())
+    }
+
+    ///
+    /// Relocation statistics of the whole module, computed once
+    ///
+    pub fn relocation_stats(&self) -> &RelocationStats {
+        self.views
+            .relocation_stats
+            .get_or_init(|| self.build_relocation_stats())
+    }
+    ///
+    /// Coverage map (see `build_coverage_map`), computed once
+    ///
+    pub fn coverage_map(&self) -> &[CoveredRange] {
+        self.views
+            .coverage_map
+            .get_or_init(|| self.build_coverage_map())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ne::editor::NeEditor;
+    use crate::ne::entry_table::SegmentEntry;
+    use crate::ne::fixture::sample_module;
+
+    #[test]
+    fn invalidate_serves_fresh_views() {
+        let mut ne = sample_module();
+        assert_eq!(ne.exports().len(), 5);
+        assert!(std::ptr::eq(ne.exports(), ne.exports()));
+        assert!(std::ptr::eq(ne.coverage_map(), ne.coverage_map()));
+
+        // views are kept until invalidated
+        ne.entry_table.entries[4] = SegmentEntry::Unused;
+        assert_eq!(ne.exports().len(), 5);
+        ne.invalidate();
+        assert_eq!(ne.exports().len(), 4);
+
+        let mut editor = NeEditor::new(ne);
+        assert_eq!(editor.module().exports()[0].segment, 1);
+        assert_eq!(editor.module().imports()[0].references, [(1, 0x21)]);
+        editor.reorder_segments(&[2, 1]).unwrap();
+        let ne = editor.module();
+        assert!(ne.exports().iter().all(|export| export.segment == 2));
+        assert_eq!(ne.imports()[0].references, [(2, 0x21)]);
+    }
+}
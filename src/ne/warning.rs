@@ -0,0 +1,207 @@
+use std::fmt;
+
+use crate::ne::dialect::FormatDialect;
+use crate::ne::options::ParseOptions;
+use crate::ne::raw::NeTable;
+use crate::ne::NeExecutable;
+
+/// Default of `ParseOptions::long_name_length`: the most the length byte holds, less one
+const LONG_NAME_LENGTH: usize = 254;
+
+///
+/// Weird but parseable condition found while reading the module.
+/// Unlike errors they don't stop parsing, and unlike lints they are
+/// kept in `NeExecutable::warnings` for the viewers to show.
+/// Every kind has a stable code and severity of `Finding`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    ///
+    /// Name of `table` entry at `index` (from 0) is longer
+    /// than `ParseOptions::long_name_length`
+    ///
+    LongName {
+        table: NeTable,
+        index: usize,
+        length: usize,
+    },
+    ///
+    /// Name table without entries: module without name or description
+    ///
+    EmptyNameTable(NeTable),
+    ///
+    /// `resource_table_entries` of the header doesn't count resource types.
+    /// Windows linkers don't fill it; 0 is taken as unset, 0xFFFF is reported.
+    ///
+    ResourceCountMismatch { field: u16, found: usize },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::LongName {
+                table,
+                index,
+                length,
+            } => write!(f, "name #{} of {} is {} bytes long", index, table, length),
+            Warning::EmptyNameTable(table) => write!(f, "{} is empty", table),
+            Warning::ResourceCountMismatch { field, found } => write!(
+                f,
+                "header gives {:#06X} resource types, table has {}",
+                field, found
+            ),
+        }
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Checks parsed tables for conditions reported as `Warning`.
+    /// `read_with` keeps them in `warnings`;
+    /// modules built or edited in memory may be checked again.
+    ///
+    pub fn parse_warnings(&self, options: &ParseOptions) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        let long_name = options.long_name_length.unwrap_or(LONG_NAME_LENGTH);
+
+        let names = [
+            (
+                NeTable::ResidentNames,
+                self.resident_name_table
+                    .entries
+                    .iter()
+                    .map(|e| e.name.len())
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                NeTable::NonresidentNames,
+                self.nonresident_name_table
+                    .entries
+                    .iter()
+                    .map(|e| e.name.len())
+                    .collect(),
+            ),
+            (
+                NeTable::ModuleReferences,
+                self.module_reference_table
+                    .entries
+                    .iter()
+                    .map(|e| e.name.len())
+                    .collect(),
+            ),
+        ];
+        for (table, lengths) in names {
+            let is_name_table = table != NeTable::ModuleReferences;
+            if is_name_table && lengths.is_empty() {
+                warnings.push(Warning::EmptyNameTable(table));
+            }
+            for (index, &length) in lengths.iter().enumerate() {
+                if length > long_name {
+                    warnings.push(Warning::LongName {
+                        table,
+                        index,
+                        length,
+                    });
+                }
+            }
+        }
+
+        // OS/2 counts resource segments there
+        let field = self.ne_header.resource_table_entries.value();
+        let found = self.resource_table.resource_types.len();
+        if self.dialect() != FormatDialect::OS2 && field != 0 && field as usize != found {
+            warnings.push(Warning::ResourceCountMismatch { field, found });
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::ne::fixture::sample_module;
+    use crate::ne::resident_name_table::ResidentNameEntry;
+
+    fn long(table: NeTable, index: usize, length: usize) -> Warning {
+        Warning::LongName {
+            table,
+            index,
+            length,
+        }
+    }
+
+    #[test]
+    fn long_names_against_option() {
+        let mut ne = sample_module();
+        let defaults = ParseOptions::default();
+        assert_eq!(ne.parse_warnings(&defaults), []);
+
+        ne.resident_name_table.entries.push(ResidentNameEntry {
+            name: vec![b'A'; 255],
+            index: 2,
+        });
+        // as long as the default allows
+        ne.nonresident_name_table.entries[1].name = vec![b'B'; 254];
+        assert_eq!(
+            ne.parse_warnings(&defaults),
+            [long(NeTable::ResidentNames, 2, 255)]
+        );
+        let read = NeExecutable::read(&mut Cursor::new(ne.to_bytes().unwrap())).unwrap();
+        assert_eq!(read.warnings, [long(NeTable::ResidentNames, 2, 255)]);
+
+        let options = |length| ParseOptions {
+            long_name_length: Some(length),
+            ..Default::default()
+        };
+        assert_eq!(ne.parse_warnings(&options(255)), []);
+        // `GETPROCADDRESS` and the description are longer than 10 bytes, `KERNEL` isn't
+        assert_eq!(
+            ne.parse_warnings(&options(10)),
+            [
+                long(NeTable::ResidentNames, 1, 14),
+                long(NeTable::ResidentNames, 2, 255),
+                long(NeTable::NonresidentNames, 0, 18),
+                long(NeTable::NonresidentNames, 1, 254),
+            ]
+        );
+        assert_eq!(
+            ne.parse_warnings(&options(5)).last(),
+            Some(&long(NeTable::ModuleReferences, 0, 6))
+        );
+    }
+
+    #[test]
+    fn empty_tables_and_resource_count() {
+        let mut ne = sample_module();
+        let options = ParseOptions::default();
+        ne.resident_name_table.entries.clear();
+        ne.nonresident_name_table.entries.clear();
+        // modules without imports are usual
+        ne.module_reference_table.entries.clear();
+        assert_eq!(
+            ne.parse_warnings(&options),
+            [
+                Warning::EmptyNameTable(NeTable::ResidentNames),
+                Warning::EmptyNameTable(NeTable::NonresidentNames),
+            ]
+        );
+
+        let mut ne = sample_module();
+        let mismatch = |field| Warning::ResourceCountMismatch { field, found: 1 };
+        for (field, expected) in [(0, None), (1, None), (2, Some(2)), (0xFFFF, Some(0xFFFF))] {
+            ne.ne_header.resource_table_entries = field.into();
+            assert_eq!(
+                ne.parse_warnings(&options),
+                Vec::from_iter(expected.map(mismatch)),
+                "{:#X}",
+                field
+            );
+        }
+        // OS/2 counts resource segments there
+        ne.ne_header.target_os = 1;
+        assert_eq!(ne.dialect(), FormatDialect::OS2);
+        assert_eq!(ne.parse_warnings(&options), []);
+    }
+}
@@ -0,0 +1,397 @@
+use std::fmt;
+use std::io::{self, ErrorKind};
+
+use crate::ne::editor::NeEditor;
+use crate::ne::resource_convert::{self, Converted};
+use crate::ne::resource_table::{type_name, ResourceId};
+use crate::ne::NeExecutable;
+use crate::util::codepage::Codepage;
+
+/// `MOVEABLE`, `PURE`, `PRELOAD` and `DISCARDABLE` memory flags,
+/// the same in NE resource table and `.res` headers
+const MEMORY_FLAGS: u16 = 0x1070;
+/// `MAKELANGID(LANG_ENGLISH, SUBLANG_ENGLISH_US)`
+pub const LANG_EN_US: u16 = 0x0409;
+
+///
+/// Resource entry of Win32 `.res` file
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResEntry {
+    pub type_id: ResourceId,
+    pub name: ResourceId,
+    pub memory_flags: u16,
+    pub language: u16,
+    pub data: Vec<u8>,
+}
+
+impl ResEntry {
+    ///
+    /// Header: `DataSize`, `HeaderSize`, type, name (each `0xFFFF, WORD`
+    /// or UTF-16 string), `DataVersion`, `MemoryFlags`, `LanguageId`,
+    /// `Version` and `Characteristics`, aligned to 4 bytes
+    ///
+    fn write(&self, out: &mut Vec<u8>) {
+        let mut names = Vec::new();
+        for id in [&self.type_id, &self.name] {
+            match id {
+                ResourceId::Int(id) => {
+                    names.extend_from_slice(&0xFFFF_u16.to_le_bytes());
+                    names.extend_from_slice(&id.to_le_bytes());
+                }
+                ResourceId::Name(name) => {
+                    for unit in name.encode_utf16().chain([0]) {
+                        names.extend_from_slice(&unit.to_le_bytes());
+                    }
+                }
+            }
+        }
+        names.resize(names.len().next_multiple_of(4), 0);
+
+        let header_size = 8 + names.len() + 16;
+        out.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(header_size as u32).to_le_bytes());
+        out.extend_from_slice(&names);
+        out.extend_from_slice(&0_u32.to_le_bytes());
+        out.extend_from_slice(&self.memory_flags.to_le_bytes());
+        out.extend_from_slice(&self.language.to_le_bytes());
+        out.extend_from_slice(&0_u32.to_le_bytes());
+        out.extend_from_slice(&0_u32.to_le_bytes());
+        out.extend_from_slice(&self.data);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+}
+
+///
+/// Contents of `.res` file: the empty entry which marks 32-bit format
+/// and the entries given
+///
+pub fn write_res_file(entries: &[ResEntry]) -> Vec<u8> {
+    let mut out = Vec::new();
+    ResEntry {
+        type_id: ResourceId::Int(0),
+        name: ResourceId::Int(0),
+        memory_flags: 0,
+        language: 0,
+        data: Vec::new(),
+    }
+    .write(&mut out);
+    for entry in entries {
+        entry.write(&mut out);
+    }
+    out
+}
+
+///
+/// Entries of `.res` file, without the empty one.
+/// 16-bit `.res` files (without the empty entry) are refused.
+///
+pub fn read_res_file(data: &[u8]) -> io::Result<Vec<ResEntry>> {
+    let invalid = |message: String| io::Error::new(ErrorKind::InvalidData, message);
+    let u16_at = |pos: usize| -> io::Result<u16> {
+        data.get(pos..pos + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .ok_or_else(|| invalid(format!(".res file ends unexpectedly at {:#x}", data.len())))
+    };
+    let u32_at = |pos: usize| -> io::Result<u32> {
+        Ok(u16_at(pos)? as u32 | (u16_at(pos + 2)? as u32) << 16)
+    };
+    let id_at = |pos: &mut usize| -> io::Result<ResourceId> {
+        if u16_at(*pos)? == 0xFFFF {
+            *pos += 4;
+            return Ok(ResourceId::Int(u16_at(*pos - 2)?));
+        }
+        let mut units = Vec::new();
+        loop {
+            let unit = u16_at(*pos)?;
+            *pos += 2;
+            if unit == 0 {
+                return Ok(ResourceId::Name(String::from_utf16_lossy(&units)));
+            }
+            units.push(unit);
+        }
+    };
+
+    if data.get(..8) != Some(&[0, 0, 0, 0, 0x20, 0, 0, 0]) {
+        return Err(invalid("not a 32-bit .res file".to_string()));
+    }
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let data_size = u32_at(pos)? as usize;
+        let header_size = u32_at(pos + 4)? as usize;
+        let mut names = pos + 8;
+        let type_id = id_at(&mut names)?;
+        let name = id_at(&mut names)?;
+        let fields = names.next_multiple_of(4);
+        let memory_flags = u16_at(fields + 4)?;
+        let language = u16_at(fields + 6)?;
+        let start = pos + header_size;
+        let entry_data = data
+            .get(start..start + data_size)
+            .ok_or_else(|| invalid(format!("data of {} {} is out of file", type_id, name)))?;
+        if type_id != ResourceId::Int(0) {
+            entries.push(ResEntry {
+                type_id,
+                name,
+                memory_flags,
+                language,
+                data: entry_data.to_vec(),
+            });
+        }
+        pos = (start + data_size).next_multiple_of(4);
+    }
+    Ok(entries)
+}
+
+///
+/// How NE resources are exported into `.res` file
+///
+#[derive(Debug, Clone, Copy)]
+pub struct ResExportOptions {
+    /// Code page of strings of resources (NE module doesn't record it)
+    pub codepage: Codepage,
+    /// Language of every resource (NE module has no languages)
+    pub language: u16,
+}
+
+impl Default for ResExportOptions {
+    fn default() -> Self {
+        Self {
+            codepage: Codepage::default(),
+            language: LANG_EN_US,
+        }
+    }
+}
+
+///
+/// Resource which has no Win32 form or whose data can't be converted
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedResource {
+    pub type_id: ResourceId,
+    pub name: ResourceId,
+    pub reason: String,
+}
+
+impl fmt::Display for SkippedResource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} skipped: {}",
+            type_name(&self.type_id),
+            self.name,
+            self.reason
+        )
+    }
+}
+
+///
+/// Exported `.res` file and what didn't get into it
+///
+#[derive(Debug, Clone)]
+pub struct ResExport {
+    pub data: Vec<u8>,
+    pub copied: usize,
+    pub reencoded: usize,
+    pub skipped: Vec<SkippedResource>,
+}
+
+impl fmt::Display for ResExport {
+    ///
+    /// `{}` gives counts, `{:#}` also lists skipped resources
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} resources ({} copied, {} re-encoded), {} skipped, {} bytes",
+            self.copied + self.reencoded,
+            self.copied,
+            self.reencoded,
+            self.skipped.len(),
+            self.data.len()
+        )?;
+        if f.alternate() {
+            for skipped in &self.skipped {
+                write!(f, "\n\t{}", skipped)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NeExecutable {
+    ///
+    /// Resources of module as Win32 `.res` file for linking into 32-bit ports.
+    /// Predefined types are re-encoded (see `resource_convert::to_win32`),
+    /// named and custom types are copied. Order of resource table is kept.
+    ///
+    pub fn to_win32_res(&self, options: &ResExportOptions) -> ResExport {
+        let mut entries = Vec::new();
+        let mut export = ResExport {
+            data: Vec::new(),
+            copied: 0,
+            reencoded: 0,
+            skipped: Vec::new(),
+        };
+        for resource_type in &self.resource_table.resource_types {
+            let type_id = resource_type.id();
+            for resource in &resource_type.resources {
+                let name = resource.id();
+                let converted = match (&resource.data, &type_id) {
+                    (None, _) => Converted::Skipped("data is not read".to_string()),
+                    (Some(data), ResourceId::Int(id)) if !resource_type.is_custom() => {
+                        resource_convert::to_win32(*id, data, options.codepage)
+                            .unwrap_or_else(|e| Converted::Skipped(e.to_string()))
+                    }
+                    (Some(data), _) => Converted::Copied(data.clone()),
+                };
+                let data = match converted {
+                    Converted::Copied(data) => {
+                        export.copied += 1;
+                        data
+                    }
+                    Converted::Reencoded(data) => {
+                        export.reencoded += 1;
+                        data
+                    }
+                    Converted::Skipped(reason) => {
+                        export.skipped.push(SkippedResource {
+                            type_id: type_id.clone(),
+                            name,
+                            reason,
+                        });
+                        continue;
+                    }
+                };
+                entries.push(ResEntry {
+                    type_id: type_id.clone(),
+                    name,
+                    memory_flags: resource.header.flags & MEMORY_FLAGS,
+                    language: options.language,
+                    data,
+                });
+            }
+        }
+        export.data = write_res_file(&entries);
+        export
+    }
+}
+
+///
+/// What `import_res` did with entries of `.res` file
+///
+#[derive(Debug, Clone, Default)]
+pub struct ResImport {
+    pub added: usize,
+    pub replaced: usize,
+    pub skipped: Vec<SkippedResource>,
+}
+
+impl fmt::Display for ResImport {
+    ///
+    /// `{}` gives counts, `{:#}` also lists skipped resources
+    ///
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} added, {} replaced, {} skipped",
+            self.added,
+            self.replaced,
+            self.skipped.len()
+        )?;
+        if f.alternate() {
+            for skipped in &self.skipped {
+                write!(f, "\n\t{}", skipped)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NeEditor {
+    ///
+    /// Adds resources of Win32 `.res` file to the module or replaces
+    /// ones with the same type and name. Resources are converted down
+    /// (see `resource_convert::from_win32`); those which can't be,
+    /// and other languages of the same resource, are skipped.
+    /// \param codepage -- code page of ANSI strings of the module
+    ///
+    pub fn import_res(&mut self, entries: &[ResEntry], codepage: Codepage) -> ResImport {
+        let mut import = ResImport::default();
+        let mut imported: Vec<(&ResourceId, &ResourceId)> = Vec::new();
+        for entry in entries {
+            let skip = |reason: &str| SkippedResource {
+                type_id: entry.type_id.clone(),
+                name: entry.name.clone(),
+                reason: reason.to_string(),
+            };
+            if imported
+                .iter()
+                .any(|(t, n)| t.matches(&entry.type_id) && n.matches(&entry.name))
+            {
+                import
+                    .skipped
+                    .push(skip(&format!("another language ({:#06x})", entry.language)));
+                continue;
+            }
+            let converted = match &entry.type_id {
+                ResourceId::Int(id) => resource_convert::from_win32(*id, &entry.data, codepage)
+                    .unwrap_or_else(|e| Converted::Skipped(e.to_string())),
+                ResourceId::Name(_) => Converted::Copied(entry.data.clone()),
+            };
+            let data = match converted {
+                Converted::Copied(data) | Converted::Reencoded(data) => data,
+                Converted::Skipped(reason) => {
+                    import.skipped.push(skip(&reason));
+                    continue;
+                }
+            };
+            match self.set_resource(
+                &entry.type_id,
+                &entry.name,
+                entry.memory_flags & MEMORY_FLAGS,
+                data,
+            ) {
+                Ok(true) => import.replaced += 1,
+                Ok(false) => import.added += 1,
+                Err(e) => {
+                    import.skipped.push(skip(&e.to_string()));
+                    continue;
+                }
+            }
+            imported.push((&entry.type_id, &entry.name));
+        }
+        import
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn res_file_round_trip() {
+        let entries = vec![
+            ResEntry {
+                type_id: ResourceId::Int(10),
+                name: ResourceId::from("AB"),
+                memory_flags: 0x0030,
+                language: LANG_EN_US,
+                data: vec![1, 2, 3],
+            },
+            ResEntry {
+                type_id: ResourceId::from("MYTYPE"),
+                name: ResourceId::Int(7),
+                memory_flags: 0x1030,
+                language: 0x0419,
+                data: vec![4; 9],
+            },
+        ];
+        let file = write_res_file(&entries);
+        // empty entry is 32 bytes, name "AB\0" makes the next header 36 bytes
+        assert_eq!(&file[32..40], &[3, 0, 0, 0, 36, 0, 0, 0]);
+        assert_eq!(read_res_file(&file).unwrap(), entries);
+        assert!(read_res_file(&file[32..]).is_err());
+    }
+}
@@ -0,0 +1,431 @@
+use std::io::{self, ErrorKind, Write};
+
+use crate::ne::dialect::NE_GANGLOAD;
+use crate::ne::entry_table::{EntryTable, SegmentEntry};
+use crate::ne::resource_table::NeResourceTable;
+use crate::ne::segment_relocations::{RelocationEntry, RelocationTarget};
+use crate::ne::segment_table::SEG_RELOCINFO;
+use crate::ne::NeExecutable;
+
+///
+/// Alignments of rebuilt file. `None` keeps alignment of the module.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteOptions {
+    /// Sector shift of segment data (`file_alignment_shift_count`)
+    pub segment_shift: Option<u16>,
+    /// Alignment shift of resource data (first word of resource table)
+    pub resource_shift: Option<u16>,
+}
+
+///
+/// Rebuilt file with the bytes spent on alignment
+///
+#[derive(Debug, Clone)]
+pub struct WrittenImage {
+    pub data: Vec<u8>,
+    /// Zero bytes inserted to align segments and resources
+    pub padding: u64,
+}
+
+///
+/// Stub for images without one (read by `read_at`):
+/// prints the usual message and exits from DOS.
+///
+const DEFAULT_STUB_CODE: &[u8] = b"\x0E\x1F\xBA\x0E\x00\xB4\x09\xCD\x21\xB8\x01\x4C\xCD\x21\
+This program requires Microsoft Windows.\r\n$";
+
+impl NeExecutable {
+    ///
+    /// Serializes the module: DOS stub, NE header and tables,
+    /// segments with their relocations, resources and overlay.
+    /// Tables follow the header in the usual linker order;
+    /// all offsets of the header and tables are recomputed.
+    /// Data of every resource must be read (not skipped by `ParseOptions`).
+    ///
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        Ok(self.to_bytes_with(&WriteOptions::default())?.data)
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.to_bytes()?)
+    }
+
+    pub fn to_bytes_with(&self, options: &WriteOptions) -> io::Result<WrittenImage> {
+        let segment_shift = options
+            .segment_shift
+            .unwrap_or(self.ne_header.file_alignment_shift_count.value());
+        let resource_shift = options
+            .resource_shift
+            .unwrap_or(self.resource_table.header.alignment_shift_count);
+        if segment_shift > 15 || resource_shift > 15 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "alignment shift is bigger than 15",
+            ));
+        }
+
+        let mut out = if self.dos_stub.len() >= 0x40 {
+            self.dos_stub.clone()
+        } else {
+            default_stub()
+        };
+        let lfanew = out.len();
+        out[0x3C..0x40].copy_from_slice(&(lfanew as u32).to_le_bytes());
+
+        let mut header = *self.ne_header;
+        // preloaded segments are not kept together, so the gangload area is dropped
+        if header.gangload_area().is_some() {
+            header.os2_exe_flags &= !NE_GANGLOAD;
+            header.return_thunk_offset = 0.into();
+            header.segment_reference_thunk_offset = 0.into();
+        }
+        out.extend_from_slice(bytemuck::bytes_of(&header));
+
+        // segment table, filled when segment places are known
+        let segment_table = out.len();
+        out.resize(segment_table + 8 * self.segment_entries.len(), 0);
+
+        let resource_table = out.len();
+        let has_resources = !self.resource_table.resource_types.is_empty();
+        let resource_records = if has_resources {
+            encode_resource_table(&self.resource_table, &mut out)
+        } else {
+            Vec::new()
+        };
+
+        let resident_names = out.len();
+        for entry in &self.resident_name_table.entries {
+            push_name(&mut out, &entry.name)?;
+            out.extend_from_slice(&entry.index.to_le_bytes());
+        }
+        out.push(0);
+
+        let module_references = out.len();
+        for entry in &self.module_reference_table.entries {
+            out.extend_from_slice(&entry.header.offset.to_le_bytes());
+        }
+
+        let imported_names = out.len();
+        out.extend_from_slice(&self.imported_name_table.data);
+
+        let entry_table = out.len();
+        out.extend_from_slice(&encode_entry_table(&self.entry_table));
+        let entry_table_length = out.len() - entry_table;
+
+        let nonresident_names = out.len();
+        for entry in &self.nonresident_name_table.entries {
+            push_name(&mut out, &entry.name)?;
+            out.extend_from_slice(&entry.index.to_le_bytes());
+        }
+        out.push(0);
+        let nonresident_names_size = out.len() - nonresident_names;
+
+        let relative =
+            |offset: usize, field: &str| u16::try_from(offset - lfanew).map_err(|_| too_big(field));
+        header.segment_table_offset = relative(segment_table, "segment table")?.into();
+        header.resource_table_offset = if has_resources {
+            relative(resource_table, "resource table")?
+        } else {
+            relative(resident_names, "resident names table")?
+        }
+        .into();
+        header.resident_names_table_offset =
+            relative(resident_names, "resident names table")?.into();
+        header.module_reference_table_offset =
+            relative(module_references, "module reference table")?.into();
+        header.import_name_table_offset = relative(imported_names, "imported names table")?.into();
+        header.entry_table_offset = relative(entry_table, "entry table")?.into();
+        header.entry_table_length = u16::try_from(entry_table_length)
+            .map_err(|_| too_big("entry table"))?
+            .into();
+        header.non_resident_names_table_offset = (nonresident_names as u32).into();
+        header.non_resident_names_size = u16::try_from(nonresident_names_size)
+            .map_err(|_| too_big("nonresident names table"))?
+            .into();
+        header.segment_count = (self.segment_entries.len() as u16).into();
+        header.module_references = (self.module_reference_table.entries.len() as u16).into();
+        header.file_alignment_shift_count = segment_shift.into();
+
+        let mut padding = 0;
+        for (i, segment) in self.segment_entries.iter().enumerate() {
+            let mut record = segment.header;
+            match &segment.data {
+                Some(data) if segment.header.data_offset_shifted != 0 => {
+                    padding += align(&mut out, segment_shift);
+                    record.data_offset_shifted = u16::try_from(out.len() >> segment_shift)
+                        .map_err(|_| too_big(&format!("offset of segment #{}", i + 1)))?;
+                    out.extend_from_slice(data);
+                    if segment.header.flags & SEG_RELOCINFO != 0 {
+                        let relocations = self
+                            .relocation_tables_per_segment
+                            .get(i)
+                            .map_or(&[][..], |t| &t.entries[..]);
+                        out.extend_from_slice(&(relocations.len() as u16).to_le_bytes());
+                        for relocation in relocations {
+                            out.extend_from_slice(&encode_relocation(relocation));
+                        }
+                    }
+                }
+                _ => record.data_offset_shifted = 0,
+            }
+            let at = segment_table + 8 * i;
+            out[at..at + 2].copy_from_slice(&record.data_offset_shifted.to_le_bytes());
+            out[at + 2..at + 4].copy_from_slice(&record.data_length.to_le_bytes());
+            out[at + 4..at + 6].copy_from_slice(&record.flags.to_le_bytes());
+            out[at + 6..at + 8].copy_from_slice(&record.min_alloc.to_le_bytes());
+        }
+
+        if has_resources {
+            out[resource_table..resource_table + 2].copy_from_slice(&resource_shift.to_le_bytes());
+            let resources = self
+                .resource_table
+                .resource_types
+                .iter()
+                .flat_map(|t| &t.resources);
+            for (resource, record) in resources.zip(resource_records) {
+                let Some(data) = &resource.data else {
+                    if resource.header.data_offset_shifted == 0 {
+                        continue;
+                    }
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("data of resource {} was not read", resource.id()),
+                    ));
+                };
+                padding += align(&mut out, resource_shift);
+                let what = format!("resource {}", resource.id());
+                let offset =
+                    u16::try_from(out.len() >> resource_shift).map_err(|_| too_big(&what))?;
+                out.extend_from_slice(data);
+                padding += align(&mut out, resource_shift);
+                let units = ((data.len() as u64).div_ceil(1 << resource_shift))
+                    .try_into()
+                    .map_err(|_| too_big(&what))?;
+                out[record..record + 2].copy_from_slice(&offset.to_le_bytes());
+                out[record + 2..record + 4].copy_from_slice(&u16::to_le_bytes(units));
+            }
+        }
+
+        if let Some(overlay) = &self.overlay {
+            out.extend_from_slice(&overlay.data);
+        }
+
+        out[lfanew..lfanew + 0x40].copy_from_slice(bytemuck::bytes_of(&header));
+        Ok(WrittenImage { data: out, padding })
+    }
+}
+
+fn too_big(what: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("{} doesn't fit in its field with these alignments", what),
+    )
+}
+
+///
+/// Pads `out` with zeros up to `1 << shift` boundary, returns number of added bytes
+///
+fn align(out: &mut Vec<u8>, shift: u16) -> u64 {
+    let unit = 1_usize << shift;
+    let padding = out.len().next_multiple_of(unit) - out.len();
+    out.resize(out.len() + padding, 0);
+    padding as u64
+}
+
+pub(crate) fn push_name(out: &mut Vec<u8>, name: &[u8]) -> io::Result<()> {
+    let len = u8::try_from(name.len()).map_err(|_| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "name `{}` is longer than 255 bytes",
+                String::from_utf8_lossy(name)
+            ),
+        )
+    })?;
+    out.push(len);
+    out.extend_from_slice(name);
+    Ok(())
+}
+
+fn default_stub() -> Vec<u8> {
+    let mut stub = vec![0; 0x40];
+    stub[0..2].copy_from_slice(b"MZ");
+    let size = 0x40 + DEFAULT_STUB_CODE.len();
+    stub[2..4].copy_from_slice(&((size % 512) as u16).to_le_bytes());
+    stub[4..6].copy_from_slice(&(size.div_ceil(512) as u16).to_le_bytes());
+    stub[8..10].copy_from_slice(&4_u16.to_le_bytes()); // header paragraphs
+    stub[0x0C..0x0E].copy_from_slice(&0xFFFF_u16.to_le_bytes()); // maxalloc
+    stub[0x10..0x12].copy_from_slice(&0x00B8_u16.to_le_bytes()); // sp
+    stub[0x18..0x1A].copy_from_slice(&0x0040_u16.to_le_bytes()); // lfarlc
+    stub.extend_from_slice(DEFAULT_STUB_CODE);
+    stub.resize(stub.len().next_multiple_of(16), 0);
+    stub
+}
+
+///
+/// Writes resource table with zero data places and returns positions
+/// of resource records in `out` (in table order) to fill them later.
+/// Names of types and resources follow the table, each written once.
+///
+pub(crate) fn encode_resource_table(table: &NeResourceTable, out: &mut Vec<u8>) -> Vec<usize> {
+    let start = out.len();
+    let records_size: usize = table
+        .resource_types
+        .iter()
+        .map(|t| 8 + 12 * t.resources.len())
+        .sum();
+    let names_start = 2 + records_size + 2;
+
+    let mut names: Vec<(String, u16)> = Vec::new();
+    let mut names_data = Vec::new();
+    let mut name_offset = |name: &Option<String>, raw: u16| -> u16 {
+        if raw & 0x8000 != 0 {
+            return raw;
+        }
+        let name = name.clone().unwrap_or_default();
+        if let Some((_, offset)) = names.iter().find(|(n, _)| *n == name) {
+            return *offset;
+        }
+        let offset = (names_start + names_data.len()) as u16;
+        let bytes = &name.as_bytes()[..name.len().min(255)];
+        names_data.push(bytes.len() as u8);
+        names_data.extend_from_slice(bytes);
+        names.push((name, offset));
+        offset
+    };
+
+    let mut records = Vec::new();
+    out.extend_from_slice(&table.header.alignment_shift_count.to_le_bytes());
+    for resource_type in &table.resource_types {
+        let type_id = name_offset(&resource_type.name, resource_type.header.type_id);
+        out.extend_from_slice(&type_id.to_le_bytes());
+        out.extend_from_slice(&(resource_type.resources.len() as u16).to_le_bytes());
+        for word in resource_type.header.res {
+            out.extend_from_slice(&word.to_le_bytes());
+        }
+        for resource in &resource_type.resources {
+            records.push(out.len());
+            let resource_id = name_offset(&resource.name, resource.header.resource_id);
+            out.extend_from_slice(&[0; 4]);
+            out.extend_from_slice(&resource.header.flags.to_le_bytes());
+            out.extend_from_slice(&resource_id.to_le_bytes());
+            for word in resource.header.res {
+                out.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+    }
+    out.extend_from_slice(&[0; 2]);
+    out.extend_from_slice(&names_data);
+    out.push(0);
+    debug_assert_eq!(start + names_start + names_data.len() + 1, out.len());
+    records
+}
+
+///
+/// Bundles consecutive entries of the same kind: unused ones,
+/// fixed ones of the same segment and moveable ones (at most 255 a bundle).
+///
+pub fn encode_entry_table(table: &EntryTable) -> Vec<u8> {
+    let bundle_key = |entry: &SegmentEntry| match entry {
+        SegmentEntry::Unused => 0,
+        SegmentEntry::Fixed(e) => e.segment,
+        SegmentEntry::Moveable(_) => 0xFF,
+    };
+    let mut out = Vec::new();
+    let mut rest = &table.entries[..];
+    while let Some(first) = rest.first() {
+        let key = bundle_key(first);
+        let count = rest
+            .iter()
+            .take(255)
+            .take_while(|e| bundle_key(e) == key)
+            .count();
+        out.push(count as u8);
+        out.push(key);
+        for entry in &rest[..count] {
+            match entry {
+                SegmentEntry::Unused => {}
+                SegmentEntry::Fixed(e) => {
+                    out.push(e.flags);
+                    out.extend_from_slice(&e.offset.to_le_bytes());
+                }
+                SegmentEntry::Moveable(e) => {
+                    out.push(e.flags);
+                    out.extend_from_slice(&e.magic);
+                    out.push(e.segment);
+                    out.extend_from_slice(&e.offset.to_le_bytes());
+                }
+            }
+        }
+        rest = &rest[count..];
+    }
+    out.push(0);
+    out
+}
+
+pub(crate) fn encode_relocation(entry: &RelocationEntry) -> [u8; 8] {
+    let mut record = [0; 8];
+    record[0] = entry.address_type;
+    record[1] = (entry.reloc_type & 0x03) | if entry.is_additive { 0x04 } else { 0 };
+    record[2..4].copy_from_slice(&entry.segment_offset.to_le_bytes());
+    let (first, second) = match &entry.target {
+        RelocationTarget::Internal(f) => {
+            record[4] = f.segment;
+            (None, f.offset_or_ordinal)
+        }
+        RelocationTarget::ImportByOrdinal(o) => (Some(o.module_index), o.ordinal),
+        RelocationTarget::ImportByName(n) => (Some(n.module_index), n.name_offset),
+        RelocationTarget::OsFixup(o) => (Some(o.fixup_type), 0),
+    };
+    if let Some(first) = first {
+        record[4..6].copy_from_slice(&first.to_le_bytes());
+    }
+    record[6..8].copy_from_slice(&second.to_le_bytes());
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::ne::entry_table::{FixedSegmentEntry, MoveableSegmentEntry};
+
+    #[test]
+    fn entry_table_round_trip() {
+        let fixed = |offset| {
+            SegmentEntry::Fixed(FixedSegmentEntry {
+                segment: 1,
+                flags: 0x01,
+                offset,
+            })
+        };
+        let table = EntryTable {
+            entries: vec![
+                fixed(0x10),
+                fixed(0x20),
+                SegmentEntry::Unused,
+                SegmentEntry::Moveable(MoveableSegmentEntry {
+                    flags: 0x03,
+                    magic: [0xCD, 0x3F],
+                    segment: 2,
+                    offset: 0x30,
+                }),
+            ],
+        };
+        let data = encode_entry_table(&table);
+        assert_eq!(data.len(), (2 + 2 * 3) + 2 + (2 + 6) + 1);
+        let read = EntryTable::read(&mut Cursor::new(&data), data.len() as u16).unwrap();
+        assert_eq!(read.entries.len(), 4);
+        assert!(matches!(read.entries[2], SegmentEntry::Unused));
+        assert!(matches!(
+            read.entries[3],
+            SegmentEntry::Moveable(MoveableSegmentEntry {
+                segment: 2,
+                offset: 0x30,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,18 @@
+///
+/// Types most programs reading modules need, under one `use`:
+/// `use win16ne::prelude::*;`. Items are added here, not removed
+/// or renamed, so glob imports keep compiling across versions.
+///
+pub use crate::ne::editor::NeEditor;
+pub use crate::ne::error::NeError;
+pub use crate::ne::findings::{Finding, FindingFilter, Severity};
+pub use crate::ne::lint::Lint;
+pub use crate::ne::open::{OpenOptions, Win16Ne};
+pub use crate::ne::options::{LimitExceeded, ParseOptions};
+pub use crate::ne::owned::NeOwned;
+pub use crate::ne::resource_table::{ResourceHandle, ResourceId};
+pub use crate::ne::source::{NamedModule, SourceName, SourcedError};
+pub use crate::ne::symbols::{MapFile, OrdinalDatabase, SymbolProvider, SymbolProviders};
+pub use crate::ne::warning::Warning;
+pub use crate::ne::NeExecutable;
+pub use crate::util::codepage::Codepage;
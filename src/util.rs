@@ -1 +1,14 @@
+#[cfg(feature = "alloc-metrics")]
+pub mod alloc_count;
+#[cfg(feature = "image")]
+pub mod base64;
+pub mod checksum;
+pub mod chunked;
+pub mod codepage;
+pub mod demangle;
 pub mod endian;
+pub mod intern;
+pub mod json;
+#[cfg(feature = "image")]
+pub mod png;
+pub mod trace;
@@ -0,0 +1,77 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+    static ALLOCATED_BYTES: Cell<u64> = const { Cell::new(0) };
+}
+
+///
+/// Allocations made by one thread
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllocationStats {
+    /// Calls of `alloc` and `realloc`
+    pub count: u64,
+    /// Bytes requested by them
+    pub bytes: u64,
+}
+
+impl AllocationStats {
+    ///
+    /// Allocations of the calling thread so far
+    ///
+    pub fn current() -> Self {
+        Self {
+            count: ALLOCATIONS.try_with(Cell::get).unwrap_or_default(),
+            bytes: ALLOCATED_BYTES.try_with(Cell::get).unwrap_or_default(),
+        }
+    }
+
+    ///
+    /// Allocations made after `earlier`
+    ///
+    pub fn since(self, earlier: Self) -> Self {
+        Self {
+            count: self.count.saturating_sub(earlier.count),
+            bytes: self.bytes.saturating_sub(earlier.bytes),
+        }
+    }
+}
+
+fn count(bytes: usize) {
+    // counters of exiting threads are gone
+    let _ = ALLOCATIONS.try_with(|c| c.set(c.get() + 1));
+    let _ = ALLOCATED_BYTES.try_with(|c| c.set(c.get() + bytes as u64));
+}
+
+///
+/// Allocation counting (`alloc-metrics` feature).
+///
+/// Global allocator passing every call to the system allocator and counting
+/// allocations of the calling thread, so parsers running on other threads
+/// don't mix into `Metrics` of one parse:
+/// `#[global_allocator] static A: CountingAllocator = CountingAllocator;`
+///
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
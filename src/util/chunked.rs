@@ -0,0 +1,414 @@
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Chunks of 64 KiB hold the headers and tables of most modules at once
+pub const DEFAULT_CHUNK_SIZE: u64 = 0x10000;
+/// Chunks kept at once: the one of headers and tables and a few of data
+pub const DEFAULT_MAX_CHUNKS: usize = 4;
+
+///
+/// Calls made to a reader and bytes it gave
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoStats {
+    pub reads: u64,
+    pub seeks: u64,
+    pub bytes: u64,
+}
+
+impl fmt::Display for IoStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} reads, {} seeks, {} bytes",
+            self.reads, self.seeks, self.bytes
+        )
+    }
+}
+
+///
+/// Reader counting every call passed to the inner one,
+/// to compare ways of reading a file
+///
+#[derive(Debug)]
+pub struct CountingReader<R> {
+    inner: R,
+    stats: IoStats,
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            stats: IoStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> IoStats {
+        self.stats
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.stats.reads += 1;
+        self.stats.bytes += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.stats.seeks += 1;
+        self.inner.seek(pos)
+    }
+}
+
+///
+/// Reader fetching the inner one by whole chunks and serving reads and
+/// seeks of parsers from the chunks it keeps. Parsers jump between tables
+/// close to each other, so most of their reads cost no call at all,
+/// and adjacent chunks are fetched without seeking.
+/// Unlike `BufReader` it keeps its buffers across seeks.
+///
+#[derive(Debug)]
+pub struct ChunkedReader<R> {
+    inner: R,
+    chunk_size: u64,
+    max_chunks: usize,
+    /// Index and data of chunks, the last used last
+    chunks: Vec<(u64, Vec<u8>)>,
+    position: u64,
+    /// Position of the inner reader, if known
+    inner_position: Option<u64>,
+    length: Option<u64>,
+    stats: IoStats,
+}
+
+impl<R: Read + Seek> ChunkedReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_chunks(inner, DEFAULT_CHUNK_SIZE, DEFAULT_MAX_CHUNKS)
+    }
+
+    ///
+    /// \param chunk_size -- bytes fetched by one read, at least 1
+    /// \param max_chunks -- chunks kept at once, at least 1
+    ///
+    pub fn with_chunks(inner: R, chunk_size: u64, max_chunks: usize) -> Self {
+        Self {
+            inner,
+            chunk_size: chunk_size.max(1),
+            max_chunks: max_chunks.max(1),
+            chunks: Vec::new(),
+            position: 0,
+            inner_position: Some(0),
+            length: None,
+            stats: IoStats::default(),
+        }
+    }
+
+    ///
+    /// Calls made to the inner reader so far
+    ///
+    pub fn stats(&self) -> IoStats {
+        self.stats
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn inner_seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.stats.seeks += 1;
+        let position = self.inner.seek(pos)?;
+        self.inner_position = Some(position);
+        Ok(position)
+    }
+
+    ///
+    /// Data of chunk `index`, fetched if it isn't kept
+    ///
+    fn chunk(&mut self, index: u64) -> io::Result<&[u8]> {
+        if let Some(i) = self.chunks.iter().position(|(kept, _)| *kept == index) {
+            let chunk = self.chunks.remove(i);
+            self.chunks.push(chunk);
+        } else {
+            let start = index * self.chunk_size;
+            if self.inner_position != Some(start) {
+                self.inner_seek(SeekFrom::Start(start))?;
+            }
+            let mut data = vec![0; self.chunk_size as usize];
+            let mut filled = 0;
+            while filled < data.len() {
+                let n = match self.inner.read(&mut data[filled..]) {
+                    Ok(n) => n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => {
+                        self.inner_position = None;
+                        return Err(e);
+                    }
+                };
+                self.stats.reads += 1;
+                self.stats.bytes += n as u64;
+                if n == 0 {
+                    break;
+                }
+                filled += n;
+            }
+            data.truncate(filled);
+            self.inner_position = Some(start + filled as u64);
+            if self.chunks.len() == self.max_chunks {
+                self.chunks.remove(0);
+            }
+            self.chunks.push((index, data));
+        }
+        Ok(&self.chunks.last().expect("chunk was just kept").1)
+    }
+}
+
+impl<R: Read + Seek> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let index = self.position / self.chunk_size;
+        let offset = (self.position % self.chunk_size) as usize;
+        let chunk = self.chunk(index)?;
+        let n = chunk.len().saturating_sub(offset).min(buf.len());
+        buf[..n].copy_from_slice(&chunk[offset..offset + n]);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for ChunkedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+            SeekFrom::End(delta) => {
+                let length = match self.length {
+                    Some(length) => length,
+                    None => {
+                        let length = self.inner_seek(SeekFrom::End(0))?;
+                        self.length = Some(length);
+                        length
+                    }
+                };
+                length.checked_add_signed(delta)
+            }
+        };
+        self.position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
+
+///
+/// Sorts `(offset, length)` ranges by offset and merges adjacent
+/// and overlapping ones. Empty ranges are dropped.
+/// Returns `(start, end)` of merged ranges.
+///
+pub fn merge_ranges(ranges: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut sorted: Vec<(u64, u64)> = ranges
+        .iter()
+        .filter(|(_, length)| *length != 0)
+        .map(|&(offset, length)| (offset, offset.saturating_add(length)))
+        .collect();
+    sorted.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(sorted.len());
+    for (start, end) in sorted {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+///
+/// Reader fetching the given ranges in advance, in offset order and
+/// adjacent ones by one read, and serving reads within them from memory.
+/// Other reads are passed to the inner reader, which is seeked
+/// only when it isn't at the position already.
+///
+#[derive(Debug)]
+pub struct PrefetchedReader<'a, R> {
+    inner: &'a mut R,
+    /// Start and data of merged ranges, by offset
+    spans: Vec<(u64, Vec<u8>)>,
+    position: u64,
+    /// Position of the inner reader, if known
+    inner_position: Option<u64>,
+}
+
+impl<'a, R: Read + Seek> PrefetchedReader<'a, R> {
+    ///
+    /// \param ranges -- `(offset, length)` of the ranges to fetch.
+    ///     Parts past the end of `inner` are left out.
+    ///
+    pub fn new(inner: &'a mut R, ranges: &[(u64, u64)]) -> io::Result<Self> {
+        let mut spans = Vec::new();
+        let mut inner_position = None;
+        for (start, end) in merge_ranges(ranges) {
+            if inner_position != Some(start) {
+                inner.seek(SeekFrom::Start(start))?;
+            }
+            let mut data = vec![0; (end - start) as usize];
+            let mut filled = 0;
+            while filled < data.len() {
+                match inner.read(&mut data[filled..]) {
+                    Ok(0) => break,
+                    Ok(n) => filled += n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+            data.truncate(filled);
+            inner_position = Some(start + filled as u64);
+            spans.push((start, data));
+        }
+        Ok(Self {
+            inner,
+            spans,
+            position: 0,
+            inner_position,
+        })
+    }
+}
+
+impl<R: Read + Seek> Read for PrefetchedReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let after = self
+            .spans
+            .partition_point(|(start, _)| *start <= self.position);
+        if let Some((start, data)) = after.checked_sub(1).map(|i| &self.spans[i]) {
+            let offset = (self.position - start) as usize;
+            if offset < data.len() {
+                let n = (data.len() - offset).min(buf.len());
+                buf[..n].copy_from_slice(&data[offset..offset + n]);
+                self.position += n as u64;
+                return Ok(n);
+            }
+        }
+        if self.inner_position != Some(self.position) {
+            self.inner_position = None;
+            self.inner.seek(SeekFrom::Start(self.position))?;
+        }
+        let n = self
+            .inner
+            .read(buf)
+            .inspect_err(|_| self.inner_position = None)?;
+        self.position += n as u64;
+        self.inner_position = Some(self.position);
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for PrefetchedReader<'_, R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+            SeekFrom::End(_) => {
+                self.inner_position = None;
+                let position = self.inner.seek(pos)?;
+                self.inner_position = Some(position);
+                Some(position)
+            }
+        };
+        self.position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn serves_near_reads_from_kept_chunks() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut reader = ChunkedReader::with_chunks(Cursor::new(data.clone()), 256, 2);
+        let mut buf = [0; 4];
+        for offset in [0x40, 0x10, 0x80, 0x2F0, 0x44] {
+            reader.seek(SeekFrom::Start(offset)).unwrap();
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, data[offset as usize..offset as usize + 4]);
+        }
+        // chunk 0, then chunk 2 after a seek
+        assert_eq!(
+            reader.stats(),
+            IoStats {
+                reads: 2,
+                seeks: 1,
+                bytes: 512,
+            }
+        );
+
+        // reads across chunks and past the end
+        assert_eq!(reader.seek(SeekFrom::End(-10)).unwrap(), 990);
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, data[990..]);
+        reader.seek(SeekFrom::Start(250)).unwrap();
+        let mut across = [0; 10];
+        reader.read_exact(&mut across).unwrap();
+        assert_eq!(across, data[250..260]);
+    }
+
+    #[test]
+    fn prefetches_merged_ranges_in_offset_order() {
+        assert_eq!(
+            merge_ranges(&[(0x80, 0x10), (0x40, 0x40), (0x10, 0), (0x200, 8), (0x84, 4)]),
+            [(0x40, 0x90), (0x200, 0x208)]
+        );
+
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut counting = CountingReader::new(Cursor::new(data.clone()));
+        let mut reader =
+            PrefetchedReader::new(&mut counting, &[(0x200, 8), (0x80, 0x10), (0x40, 0x40)])
+                .unwrap();
+        let mut buf = [0; 4];
+        for offset in [0x80, 0x200, 0x40, 0x8C] {
+            reader.seek(SeekFrom::Start(offset)).unwrap();
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, data[offset as usize..offset as usize + 4]);
+        }
+        // reads past the ranges go to the file: the one right after
+        // the last range needs no seek
+        reader.seek(SeekFrom::Start(0x208)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        reader.seek(SeekFrom::Start(0x8E)).unwrap();
+        let mut across = [0; 4];
+        reader.read_exact(&mut across).unwrap();
+        assert_eq!(across, data[0x8E..0x92]);
+        assert_eq!(
+            counting.stats(),
+            IoStats {
+                reads: 4,
+                seeks: 3,
+                bytes: 0x50 + 8 + 4 + 2,
+            }
+        );
+    }
+}
@@ -0,0 +1,338 @@
+///
+/// Readable form of decorated names found in 16-bit modules.
+/// Supported are C names (`_foo` of cdecl, `_FOO@8` of stdcall)
+/// and Microsoft C++ names (`?Func@Class@@RECXH@Z`) of 16-bit compilers,
+/// where near and far variants alternate in function and pointer codes.
+/// Pascal names (upper-cased, not decorated) have nothing to demangle.
+///
+pub fn demangle(name: &str) -> Option<String> {
+    if let Some(rest) = name.strip_prefix('?') {
+        return Parser::new(rest).function_or_data();
+    }
+    let rest = name.strip_prefix('_')?;
+    if rest.is_empty() || rest.starts_with('_') {
+        return None;
+    }
+    if let Some((name, bytes)) = rest.rsplit_once('@') {
+        if !name.is_empty() && !bytes.is_empty() && bytes.bytes().all(|b| b.is_ascii_digit()) {
+            return Some(format!("{} (stdcall, {} bytes of arguments)", name, bytes));
+        }
+        return None;
+    }
+    Some(format!("{} (cdecl)", rest))
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    pos: usize,
+    /// Back references to names (`0`..`9`)
+    names: Vec<String>,
+    /// Back references to argument types (`0`..`9`)
+    types: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            s: s.as_bytes(),
+            pos: 0,
+            names: Vec::new(),
+            types: Vec::new(),
+        }
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = *self.s.get(self.pos)?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).copied()
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        if let Some(c @ b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+            return self.names.get((c - b'0') as usize).cloned();
+        }
+        let start = self.pos;
+        while self.next()? != b'@' {}
+        let name = String::from_utf8_lossy(&self.s[start..self.pos - 1]).into_owned();
+        if name.is_empty() {
+            return None;
+        }
+        if self.names.len() < 10 {
+            self.names.push(name.clone());
+        }
+        Some(name)
+    }
+
+    ///
+    /// `name@scope@...@@` as `scope::...::name`
+    ///
+    fn qualified_name(&mut self, first: String) -> Option<String> {
+        let mut parts = vec![first];
+        while self.peek()? != b'@' {
+            parts.push(self.identifier()?);
+        }
+        self.pos += 1;
+        parts.reverse();
+        Some(parts.join("::"))
+    }
+
+    fn special_name(&mut self) -> Option<String> {
+        let operator = match self.next()? {
+            b'0' => "{ctor}",
+            b'1' => "{dtor}",
+            b'2' => "operator new",
+            b'3' => "operator delete",
+            b'4' => "operator=",
+            b'5' => "operator>>",
+            b'6' => "operator<<",
+            b'7' => "operator!",
+            b'8' => "operator==",
+            b'9' => "operator!=",
+            b'A' => "operator[]",
+            b'C' => "operator->",
+            b'D' => "operator*",
+            b'E' => "operator++",
+            b'F' => "operator--",
+            b'G' => "operator-",
+            b'H' => "operator+",
+            b'I' => "operator&",
+            b'K' => "operator/",
+            b'M' => "operator<",
+            b'N' => "operator<=",
+            b'O' => "operator>",
+            b'P' => "operator>=",
+            b'R' => "operator()",
+            b'Y' => "operator+=",
+            b'Z' => "operator-=",
+            _ => return None,
+        };
+        Some(operator.to_string())
+    }
+
+    fn function_or_data(&mut self) -> Option<String> {
+        let (name, special) = if self.peek()? == b'?' {
+            self.pos += 1;
+            (self.special_name()?, true)
+        } else {
+            (self.identifier()?, false)
+        };
+        let mut name = self.qualified_name(name)?;
+        if special {
+            // constructors and destructors are named after the class
+            let class = name.rsplit_once("::").map(|(scope, _)| scope.to_string());
+            if let Some(class) = class {
+                let short = class.rsplit("::").next().unwrap_or(&class).to_string();
+                name = name
+                    .replace("{ctor}", &short)
+                    .replace("{dtor}", &format!("~{}", short));
+            }
+        }
+
+        let code = self.next()?;
+        match code {
+            b'0'..=b'4' => {
+                let ty = self.data_type()?;
+                self.next()?;
+                Some(format!("{} {}", ty, name))
+            }
+            b'A'..=b'X' => {
+                let index = code - b'A';
+                let access = ["private: ", "protected: ", "public: "][(index / 8) as usize];
+                let kind = match index % 8 / 2 {
+                    1 => "static ",
+                    2 => "virtual ",
+                    _ => "",
+                };
+                let this_const = if index % 8 / 2 != 1 {
+                    let modifier = self.next()?;
+                    matches!((modifier.wrapping_sub(b'A')) % 4, 1 | 3)
+                } else {
+                    false
+                };
+                let signature = self.signature(&name, index % 2 == 1)?;
+                Some(format!(
+                    "{}{}{}{}",
+                    access,
+                    kind,
+                    signature,
+                    if this_const { " const" } else { "" }
+                ))
+            }
+            b'Y' | b'Z' => self.signature(&name, code == b'Z'),
+            _ => None,
+        }
+    }
+
+    fn calling_convention(&mut self) -> Option<&'static str> {
+        Some(match self.next()? {
+            b'A' | b'B' => "__cdecl",
+            b'C' | b'D' => "__pascal",
+            b'E' | b'F' => "__thiscall",
+            b'G' | b'H' => "__stdcall",
+            b'I' | b'J' => "__fastcall",
+            _ => return None,
+        })
+    }
+
+    fn signature(&mut self, name: &str, is_far: bool) -> Option<String> {
+        let convention = self.calling_convention()?;
+        let return_type = match self.peek()? {
+            b'@' => {
+                self.pos += 1;
+                None
+            }
+            _ => Some(self.data_type()?),
+        };
+        let arguments = self.arguments()?;
+        let mut out = String::new();
+        if let Some(return_type) = return_type {
+            out.push_str(&return_type);
+            out.push(' ');
+        }
+        if is_far {
+            out.push_str("__far ");
+        }
+        out.push_str(&format!("{} {}({})", convention, name, arguments));
+        Some(out)
+    }
+
+    fn arguments(&mut self) -> Option<String> {
+        if self.peek()? == b'X' {
+            self.pos += 1;
+            return Some("void".to_string());
+        }
+        let mut arguments = Vec::new();
+        loop {
+            match self.peek()? {
+                b'@' => {
+                    self.pos += 1;
+                    break;
+                }
+                b'Z' => {
+                    self.pos += 1;
+                    arguments.push("...".to_string());
+                    break;
+                }
+                c @ b'0'..=b'9' => {
+                    self.pos += 1;
+                    arguments.push(self.types.get((c - b'0') as usize)?.clone());
+                }
+                _ => {
+                    let start = self.pos;
+                    let ty = self.data_type()?;
+                    if self.pos - start > 1 && self.types.len() < 10 {
+                        self.types.push(ty.clone());
+                    }
+                    arguments.push(ty);
+                }
+            }
+        }
+        Some(arguments.join(", "))
+    }
+
+    fn data_type(&mut self) -> Option<String> {
+        let c = self.next()?;
+        let simple = match c {
+            b'C' => "signed char",
+            b'D' => "char",
+            b'E' => "unsigned char",
+            b'F' => "short",
+            b'G' => "unsigned short",
+            b'H' => "int",
+            b'I' => "unsigned int",
+            b'J' => "long",
+            b'K' => "unsigned long",
+            b'M' => "float",
+            b'N' => "double",
+            b'O' => "long double",
+            b'X' => "void",
+            b'_' => match self.next()? {
+                b'J' => "__int64",
+                b'K' => "unsigned __int64",
+                b'N' => "bool",
+                b'W' => "wchar_t",
+                _ => return None,
+            },
+            b'P' | b'Q' | b'A' | b'B' => return self.indirection(c),
+            b'V' | b'U' | b'T' => {
+                let first = self.identifier()?;
+                return self.qualified_name(first);
+            }
+            b'W' => {
+                self.next()?;
+                let first = self.identifier()?;
+                return Some(format!("enum {}", self.qualified_name(first)?));
+            }
+            b'?' => {
+                // cv qualifiers of class returned by value
+                self.next()?;
+                return self.data_type();
+            }
+            _ => return None,
+        };
+        Some(simple.to_string())
+    }
+
+    ///
+    /// Pointers (`P`, const `Q`) and references (`A`, volatile `B`).
+    /// Modifier holds const/volatile in its low bits and near/far/huge in high ones.
+    ///
+    fn indirection(&mut self, c: u8) -> Option<String> {
+        let modifier = self.next()?.checked_sub(b'A')?;
+        if modifier > 11 {
+            return None;
+        }
+        let pointee = self.data_type()?;
+        let cv = match modifier % 4 {
+            1 => "const ",
+            2 => "volatile ",
+            3 => "const volatile ",
+            _ => "",
+        };
+        let distance = ["", " __far", " __huge"][(modifier / 4) as usize];
+        let sigil = if matches!(c, b'P' | b'Q') { "*" } else { "&" };
+        let constant = if c == b'Q' { " const" } else { "" };
+        Some(format!(
+            "{}{}{} {}{}",
+            cv, pointee, distance, sigil, constant
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn c_names() {
+        assert_eq!(demangle("_main").as_deref(), Some("main (cdecl)"));
+        assert_eq!(
+            demangle("_FOO@8").as_deref(),
+            Some("FOO (stdcall, 8 bytes of arguments)")
+        );
+        assert_eq!(demangle("GETPROCADDRESS"), None);
+        assert_eq!(demangle("__acrtused"), None);
+    }
+
+    #[test]
+    fn cpp_names() {
+        assert_eq!(
+            demangle("?Foo@@ZCXH@Z").as_deref(),
+            Some("void __far __pascal Foo(int)")
+        );
+        assert_eq!(
+            demangle("?Draw@CWnd@@RECHPEDI@Z").as_deref(),
+            Some("public: int __far __pascal CWnd::Draw(char __far *, unsigned int)")
+        );
+        assert_eq!(
+            demangle("??0CWnd@@QEA@XZ").as_deref(),
+            Some("public: __cdecl CWnd::CWnd(void)")
+        );
+        assert_eq!(demangle("?broken"), None);
+    }
+}
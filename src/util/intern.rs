@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+///
+/// Table of decoded names handing out one shared string for every
+/// distinct name. Views of modules with thousands of names clone
+/// the handles instead of allocating a `String` per name per view.
+///
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    names: HashMap<Vec<u8>, Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Shared string of `name` decoded as UTF-8 with replacement characters,
+    /// as views of name tables decode them
+    ///
+    pub fn intern(&mut self, name: &[u8]) -> Arc<str> {
+        if let Some(interned) = self.names.get(name) {
+            return interned.clone();
+        }
+        let interned: Arc<str> = String::from_utf8_lossy(name).into();
+        self.names.insert(name.to_vec(), interned.clone());
+        interned
+    }
+
+    /// Distinct names interned so far
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_equal_names() {
+        let mut interner = Interner::new();
+        let a = interner.intern(b"KERNEL");
+        let b = interner.intern(b"KERNEL");
+        let c = interner.intern(b"USER");
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(&*c, "USER");
+        assert_eq!(interner.len(), 2);
+    }
+}
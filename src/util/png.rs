@@ -0,0 +1,67 @@
+use std::io::{self, Write};
+
+use crate::util::checksum::{adler32, crc32_update};
+
+///
+/// Writes 8-bit RGBA image as PNG file.
+/// Image data is not compressed (zlib stored blocks are used):
+/// resource images are tiny and this keeps the crate dependency-free.
+///
+/// \param rgba -- `width * height * 4` bytes, rows from top to bottom
+///
+pub fn write_rgba<W: Write>(w: &mut W, width: u32, height: u32, rgba: &[u8]) -> io::Result<()> {
+    let stride = width as usize * 4;
+    if rgba.len() != stride * height as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Image buffer size mismatch: {}x{} for {} bytes",
+                width,
+                height,
+                rgba.len()
+            ),
+        ));
+    }
+
+    w.write_all(b"\x89PNG\r\n\x1A\n")?;
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // bit depth 8, color type 6 (RGBA), deflate, adaptive filtering, no interlace
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+    write_chunk(w, b"IHDR", &ihdr)?;
+
+    // every scanline is prefixed by filter type 0 (None)
+    let mut raw = Vec::with_capacity((stride + 1) * height as usize);
+    for row in rgba.chunks(stride.max(1)).take(height as usize) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[0x01, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        zlib.push(is_final as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+    write_chunk(w, b"IDAT", &zlib)?;
+
+    write_chunk(w, b"IEND", &[])
+}
+
+fn write_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    let crc = crc32_update(crc32_update(0, kind), data);
+    w.write_all(&crc.to_be_bytes())
+}
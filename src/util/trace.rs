@@ -0,0 +1,89 @@
+///
+/// Parsing stages instrumentation (`tracing` feature).
+///
+/// Spans and events are emitted as `log` records with `win16ne::trace` target,
+/// so they reach any `log` backend, or `tracing` subscribers through `tracing-log`.
+/// Without the feature macros expand to nothing and arguments are not evaluated.
+///
+#[cfg(feature = "tracing")]
+pub struct Span {
+    name: &'static str,
+    fields: String,
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl Span {
+    pub fn enter(name: &'static str, fields: String) -> Self {
+        log::debug!(target: "win16ne::trace", "enter {} {}", name, fields);
+        Self {
+            name,
+            fields,
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        log::debug!(
+            target: "win16ne::trace",
+            "exit {} {} elapsed={:?}",
+            self.name,
+            self.fields,
+            self.start.elapsed()
+        );
+    }
+}
+
+///
+/// Opens span lasting till the end of enclosing block:
+/// `trace_span!("segment table", "offset={:#X}", offset);`
+///
+#[cfg(feature = "tracing")]
+macro_rules! trace_span {
+    ($name:expr) => {
+        let _span = $crate::util::trace::Span::enter($name, String::new());
+    };
+    ($name:expr, $($arg:tt)+) => {
+        let _span = $crate::util::trace::Span::enter($name, format!($($arg)+));
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_span {
+    ($($arg:tt)*) => {};
+}
+
+///
+/// Debug event inside current span: `trace_event!("entries={}", count);`
+///
+#[cfg(feature = "tracing")]
+macro_rules! trace_event {
+    ($($arg:tt)+) => {
+        log::debug!(target: "win16ne::trace", $($arg)+)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_event {
+    ($($arg:tt)*) => {};
+}
+
+///
+/// Suspicious but parseable input: `trace_warn!("overlay of {} bytes", len);`
+///
+#[cfg(feature = "tracing")]
+macro_rules! trace_warn {
+    ($($arg:tt)+) => {
+        log::warn!(target: "win16ne::trace", $($arg)+)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_warn {
+    ($($arg:tt)*) => {};
+}
+
+pub(crate) use {trace_event, trace_span, trace_warn};
@@ -1,11 +1,37 @@
 use std::fmt;
 
 pub fn disassemble(code: &[u8], is_32: bool, segment_type: &str) {
+    disassemble_with_labels(code, is_32, segment_type, &|_| None)
+}
+
+///
+/// Same as `disassemble`, printing `label:` lines before instructions
+/// at offsets which `labels` knows
+///
+pub fn disassemble_with_labels(
+    code: &[u8],
+    is_32: bool,
+    segment_type: &str,
+    labels: &dyn Fn(usize) -> Option<String>,
+) {
+    disassemble_annotated(code, is_32, segment_type, labels, &|_| None)
+}
+
+///
+/// Same as `disassemble_with_labels`, printing `; comment` after
+/// instructions at offsets which `comments` knows
+///
+pub fn disassemble_annotated(
+    code: &[u8],
+    is_32: bool,
+    segment_type: &str,
+    labels: &dyn Fn(usize) -> Option<String>,
+    comments: &dyn Fn(usize) -> Option<String>,
+) {
     let mut insts = Vec::new();
     let mut pos = 0;
     while pos < code.len() {
-        let mut inst = eat(&code[pos..], is_32).unwrap_or_else(|_| gen_invalid(code[pos]));
-        inst.pos = pos;
+        let inst = decode(code, pos, is_32);
         pos += inst.len();
         insts.push(inst);
     }
@@ -14,6 +40,9 @@ pub fn disassemble(code: &[u8], is_32: bool, segment_type: &str) {
     for inst in &insts {
         let pos = inst.pos;
         let len = inst.len();
+        if let Some(label) = labels(pos) {
+            println!("{}:", label);
+        }
         for skip in 0..((len + 6) / 7) {
             print!("{:4X}:   ", pos + skip);
             for i in 0..7 {
@@ -24,12 +53,25 @@ pub fn disassemble(code: &[u8], is_32: bool, segment_type: &str) {
                 }
             }
             if skip == 0 {
-                println!("   {}", inst);
+                match comments(pos) {
+                    Some(comment) => println!("   {} ; {}", inst, comment),
+                    None => println!("   {}", inst),
+                }
             }
         }
     }
 }
 
+///
+/// Instruction at `pos` of `code`, or one invalid byte if it can't be decoded.
+/// \param pos -- offset of the instruction, less than `code.len()`
+///
+pub fn decode(code: &[u8], pos: usize, is_32: bool) -> Instruction {
+    let mut inst = eat(&code[pos..], is_32).unwrap_or_else(|_| gen_invalid(code[pos]));
+    inst.pos = pos;
+    inst
+}
+
 fn eat(code: &[u8], is_32c: bool) -> Result<Instruction, EatError> {
     let mut eater = SimpleEater::new(code);
     let inst_prefix = eater.next_if(|b| b == 0xF0 || b == 0xF2 || b == 0xF3);
@@ -156,7 +198,7 @@ fn eat(code: &[u8], is_32c: bool) -> Result<Instruction, EatError> {
         0b00110000_00110000_00110000_00110000,
         0b00000000_00000000_00000000_00000000,
         0b11111111_11111111_00001111_00000000,
-        0b00000000_00000000_00000000_00001011,
+        0b00000100_00000000_00000000_00001011,
         0b11111111_11111111_00000011_00000000,
         0b00000000_00000000_00100101_11000111,
         0b00000000_11000000_00001111_11111111,
@@ -194,8 +236,11 @@ fn eat(code: &[u8], is_32c: bool) -> Result<Instruction, EatError> {
         }
     } else if opcode == 0xC2 || opcode == 0xCA {
         ImmediateSize::Word
+    } else if (opcode == 0x9A || opcode == 0xEA) && !is_32d {
+        // far pointer: offset, then segment
+        ImmediateSize::DWord
     } else {
-        // TODO: EA, F6, F7
+        // TODO: 48-bit far pointers of 9A and EA, F6, F7
         ImmediateSize::None
     };
     let imm = match immediate_size {
@@ -263,6 +308,13 @@ pub struct Instruction {
 }
 
 impl Instruction {
+    ///
+    /// Offset of the next instruction
+    ///
+    pub fn end(&self) -> usize {
+        self.pos + self.len()
+    }
+
     fn len(&self) -> usize {
         self.inst_prefix.is_some() as usize
             + self.addr_prefix.is_some() as usize